@@ -0,0 +1,129 @@
+//! Regression guardrails for the paths that slow down first on large
+//! libraries: top lists, heatmap and novelty reports over a synthetic
+//! database, and batch insert throughput.
+//!
+//! The database is generated once and kept under `target/bench-data`, so
+//! later runs skip the generation. `FOOTPRINTS_BENCH_SCROBBLES` sets its size
+//! (1,000,000 by default).
+//!
+//! Run with `cargo bench --bench critical_paths`
+
+use chrono::{DateTime, Duration, Utc};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use footprints::db::{self, DbPool, ScrobbleFilter};
+use footprints::demo;
+use footprints::models::Scrobble;
+use footprints::reports::period::Granularity;
+use std::path::PathBuf;
+
+const DEFAULT_SCROBBLES: usize = 1_000_000;
+
+// Bump when the generated data changes, so stale databases aren't reused
+const GENERATOR_VERSION: u32 = 2;
+
+fn history_end() -> DateTime<Utc> {
+    "2025-01-01T00:00:00Z".parse().unwrap()
+}
+
+/// The synthetic database, generated on first use
+fn bench_database() -> DbPool {
+    let scrobbles = std::env::var("FOOTPRINTS_BENCH_SCROBBLES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_SCROBBLES);
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/bench-data");
+    let path = dir.join(format!(
+        "scrobbles-v{}-{}.sqlite",
+        GENERATOR_VERSION, scrobbles
+    ));
+
+    if !path.exists() {
+        std::fs::create_dir_all(&dir).unwrap();
+        // Build under another name, so an interrupted run isn't reused
+        let partial = path.with_extension("partial");
+        let _ = std::fs::remove_file(&partial);
+
+        let pool = db::create_pool(partial.to_str().unwrap()).unwrap();
+        db::init_database(&pool).unwrap();
+        eprintln!("Generating {} scrobbles into {}", scrobbles, path.display());
+        demo::populate_synthetic(&pool, scrobbles, history_end(), 42).unwrap();
+        db::analyze_database(&pool).unwrap();
+        drop(pool);
+        std::fs::rename(&partial, &path).unwrap();
+    }
+
+    let pool = db::create_pool(path.to_str().unwrap()).unwrap();
+    db::init_database(&pool).unwrap();
+    pool
+}
+
+fn critical_paths(c: &mut Criterion) {
+    let pool = bench_database();
+    let end = history_end();
+    let last_year = (Some(end - Duration::days(365)), Some(end));
+
+    let mut top_lists = c.benchmark_group("top_lists");
+    top_lists.sample_size(10);
+    top_lists.bench_function("top_artists_all_time", |b| {
+        b.iter(|| db::get_top_artists(&pool, 50, None, None).unwrap())
+    });
+    top_lists.bench_function("top_artists_last_year", |b| {
+        b.iter(|| db::get_top_artists(&pool, 50, last_year.0, last_year.1).unwrap())
+    });
+    top_lists.bench_function("top_tracks_last_year", |b| {
+        b.iter(|| db::get_top_tracks(&pool, 50, last_year.0, last_year.1).unwrap())
+    });
+    top_lists.bench_function("top_albums_last_year", |b| {
+        b.iter(|| db::get_top_albums(&pool, 50, last_year.0, last_year.1).unwrap())
+    });
+    top_lists.finish();
+
+    let mut reports = c.benchmark_group("reports");
+    reports.sample_size(10);
+    reports.bench_function("heatmap_last_year", |b| {
+        b.iter(|| {
+            footprints::reports::heatmap::generate_heatmap(
+                &pool,
+                last_year.0,
+                last_year.1,
+                chrono_tz::UTC,
+                None,
+                &ScrobbleFilter::default(),
+                footprints::reports::locale::Locale::En,
+            )
+            .unwrap()
+        })
+    });
+    reports.bench_function("novelty_last_year", |b| {
+        b.iter(|| {
+            footprints::reports::novelty::generate_novelty_report(
+                &pool,
+                last_year.0,
+                last_year.1,
+                Granularity::Month,
+            )
+            .unwrap()
+        })
+    });
+    reports.finish();
+
+    let batch: Vec<Scrobble> = demo::synthetic_scrobbles(1_000, Utc::now(), 7).collect();
+    let mut insert = c.benchmark_group("insert");
+    insert.throughput(Throughput::Elements(batch.len() as u64));
+    insert.bench_function("batch_insert_1000", |b| {
+        b.iter_batched(
+            || {
+                let temp_file = tempfile::NamedTempFile::new().unwrap();
+                let pool = db::create_pool(temp_file.path().to_str().unwrap()).unwrap();
+                db::init_database(&pool).unwrap();
+                (temp_file, pool)
+            },
+            |(_temp_file, pool)| db::insert_scrobbles_batch(&pool, &batch).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+    insert.finish();
+}
+
+criterion_group!(benches, critical_paths);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+//! Throughput of the queries behind the dashboard, with pooled connections
+//! keeping their prepared statements and with every call preparing afresh.
+//!
+//! Run with `cargo bench --bench hot_queries`
+
+use chrono::{DateTime, Duration, Utc};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use footprints::db::{self, DbPool};
+use footprints::models::Scrobble;
+use r2d2_sqlite::SqliteConnectionManager;
+
+const SCROBBLES: i64 = 20_000;
+
+fn seed(pool: &DbPool, now: DateTime<Utc>) {
+    let scrobbles: Vec<Scrobble> = (0..SCROBBLES)
+        .map(|i| {
+            Scrobble::new(
+                format!("Artist {}", i % 200),
+                format!("Track {}", i % 1500),
+                now - Duration::minutes(i * 25),
+                "lastfm".to_string(),
+            )
+            .with_album(format!("Album {}", i % 400))
+        })
+        .collect();
+    db::insert_scrobbles_batch(pool, &scrobbles).unwrap();
+}
+
+/// A pool over the same file whose connections re-prepare every statement
+fn uncached_pool(path: &str) -> DbPool {
+    let manager = SqliteConnectionManager::file(path).with_init(|conn| {
+        conn.set_prepared_statement_cache_capacity(0);
+        Ok(())
+    });
+    r2d2::Pool::new(manager).unwrap()
+}
+
+fn hot_queries(c: &mut Criterion) {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let path = temp_file.path().to_str().unwrap();
+    let cached = db::create_pool(path).unwrap();
+    db::init_database(&cached).unwrap();
+
+    let now = Utc::now();
+    seed(&cached, now);
+    let uncached = uncached_pool(path);
+    let week = (Some(now - Duration::days(7)), Some(now));
+
+    let mut group = c.benchmark_group("hot_queries");
+    for (name, pool) in [("cached", &cached), ("uncached", &uncached)] {
+        group.bench_with_input(
+            BenchmarkId::new("top_artists_week", name),
+            pool,
+            |b, pool| b.iter(|| db::get_top_artists(pool, 15, week.0, week.1).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("recent_scrobbles", name),
+            pool,
+            |b, pool| b.iter(|| db::get_scrobbles(pool, Some(50), Some(0)).unwrap()),
+        );
+        group.bench_with_input(BenchmarkId::new("overview_week", name), pool, |b, pool| {
+            b.iter(|| {
+                footprints::overview::build(pool, now, chrono_tz::UTC, week.0, week.1).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hot_queries);
+criterion_main!(benches);
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Utc};
+
+use crate::db::DbPool;
+use crate::live::{LiveEvent, LiveHub};
+use crate::models::Achievement;
+use crate::reports::locale::Locale;
+use crate::reports::sessions::DEFAULT_SESSION_GAP_MINUTES;
+
+/// Store the milestones the year of `now` awards that weren't earned yet,
+/// and return them
+pub fn record_earned(pool: &DbPool, now: DateTime<Utc>) -> Result<Vec<Achievement>> {
+    let year = now.year();
+    let report = crate::reports::yearly::generate_yearly_report(
+        pool,
+        year,
+        DEFAULT_SESSION_GAP_MINUTES,
+        Locale::default(),
+    )?;
+
+    let mut earned = Vec::new();
+    for milestone in report.milestones {
+        let mut achievement = Achievement::earned(year, milestone, now);
+        if let Some(id) = crate::db::insert_achievement(pool, &achievement)? {
+            achievement.id = Some(id);
+            earned.push(achievement);
+        }
+    }
+    Ok(earned)
+}
+
+/// Records achievements after syncs bring in new scrobbles and announces
+/// each new one to the log, live subscribers and an optional webhook
+#[derive(Clone)]
+pub struct AchievementNotifier {
+    pool: DbPool,
+    live_hub: LiveHub,
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl AchievementNotifier {
+    pub fn new(pool: DbPool, live_hub: LiveHub) -> Self {
+        Self {
+            pool,
+            live_hub,
+            webhook_url: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Also POST each new achievement as JSON to this URL
+    pub fn with_webhook_url(mut self, webhook_url: Option<String>) -> Self {
+        self.webhook_url = webhook_url;
+        self
+    }
+
+    /// Record what the archive earned as of now; failures are only logged
+    /// so they never fail a sync
+    pub async fn check(&self) {
+        let pool = self.pool.clone();
+        let earned = tokio::task::spawn_blocking(move || record_earned(&pool, Utc::now())).await;
+        match earned {
+            Ok(Ok(achievements)) => {
+                for achievement in achievements {
+                    self.deliver(achievement).await;
+                }
+            }
+            Ok(Err(e)) => tracing::error!("Failed to record achievements: {}", e),
+            Err(e) => tracing::error!("Achievement check panicked: {}", e),
+        }
+    }
+
+    async fn deliver(&self, achievement: Achievement) {
+        tracing::info!(
+            year = achievement.year,
+            key = %achievement.key,
+            "Achievement earned: {}",
+            achievement.title
+        );
+
+        if let Some(url) = &self.webhook_url
+            && let Err(e) = self
+                .client
+                .post(url)
+                .json(&achievement)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+        {
+            tracing::error!("Failed to deliver achievement to webhook: {}", e);
+        }
+
+        self.live_hub
+            .publish(LiveEvent::Achievement { achievement });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MilestoneMetric, MilestoneRule, Scrobble};
+    use crate::reports::yearly::MilestoneIcon;
+    use crate::test_utils::setup_pool;
+
+    fn plays(pool: &DbPool, from: &str, times: i64) {
+        let start: DateTime<Utc> = from.parse().unwrap();
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                "Low".to_string(),
+                format!("Track {}", i),
+                start + chrono::Duration::minutes(i * 4),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_achievements_are_earned_once() {
+        let (pool, _temp_file) = setup_pool();
+        let rule = MilestoneRule::new(
+            "Devoted".to_string(),
+            "{value} plays of {artist}".to_string(),
+            MilestoneMetric::TopArtistPlays,
+            20.0,
+            MilestoneIcon::Trophy,
+        );
+        let rule_id = crate::db::insert_milestone_rule(&pool, &rule).unwrap();
+
+        plays(&pool, "2024-03-09T20:00:00Z", 10);
+        let first = record_earned(&pool, "2024-03-09T21:00:00Z".parse().unwrap()).unwrap();
+        assert!(!first.is_empty());
+        assert!(first.iter().all(|a| a.key != format!("rule:{}", rule_id)));
+
+        // Only the newly reached rule the second time around
+        plays(&pool, "2024-03-10T20:00:00Z", 10);
+        let now: DateTime<Utc> = "2024-03-10T21:00:00Z".parse().unwrap();
+        let second = record_earned(&pool, now).unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].key, format!("rule:{}", rule_id));
+        assert_eq!(second[0].description, "20 plays of Low");
+        assert_eq!(second[0].earned_at, now);
+
+        assert!(record_earned(&pool, now).unwrap().is_empty());
+        let stored = crate::db::get_achievements(&pool, Some(2024)).unwrap();
+        assert_eq!(stored.len(), first.len() + 1);
+        assert_eq!(stored[0].title, "Devoted");
+        assert!(
+            crate::db::get_achievements(&pool, Some(2023))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
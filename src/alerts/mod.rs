@@ -0,0 +1,251 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::db::DbPool;
+use crate::live::{LiveEvent, LiveHub};
+use crate::models::{AlertKind, AlertRule};
+
+// How often alert rules are evaluated
+const ALERT_CHECK_INTERVAL_SECS: u64 = 300;
+
+/// Notification sent when an alert rule fires
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule_id: i64,
+    pub kind: AlertKind,
+    pub message: String,
+    pub triggered_at: DateTime<Utc>,
+}
+
+/// Evaluate every enabled rule at `now`. Rules whose condition newly holds
+/// fire and become active; active rules whose condition cleared are reset.
+pub fn evaluate(pool: &DbPool, now: DateTime<Utc>) -> Result<Vec<Alert>> {
+    let mut alerts = Vec::new();
+
+    for rule in crate::db::get_alert_rules(pool)? {
+        let Some(rule_id) = rule.id.filter(|_| rule.enabled) else {
+            continue;
+        };
+
+        match (condition_message(pool, &rule, now)?, rule.active) {
+            (Some(message), false) => {
+                crate::db::set_alert_rule_active(pool, rule_id, true, Some(now))?;
+                alerts.push(Alert {
+                    rule_id,
+                    kind: rule.kind,
+                    message,
+                    triggered_at: now,
+                });
+            }
+            (None, true) => crate::db::set_alert_rule_active(pool, rule_id, false, None)?,
+            _ => {}
+        }
+    }
+
+    Ok(alerts)
+}
+
+/// Description of the problem when the rule's condition holds
+fn condition_message(
+    pool: &DbPool,
+    rule: &AlertRule,
+    now: DateTime<Utc>,
+) -> Result<Option<String>> {
+    let cutoff = now - Duration::hours(rule.threshold_hours);
+
+    match rule.kind {
+        AlertKind::NoScrobbles => {
+            // An empty archive has nothing to fall behind on
+            let latest = crate::db::get_last_scrobble_timestamp(pool)?;
+            Ok(latest.filter(|ts| *ts < cutoff).map(|ts| {
+                format!(
+                    "No scrobbles in the last {}h (latest at {})",
+                    rule.threshold_hours,
+                    ts.format("%Y-%m-%d %H:%M UTC")
+                )
+            }))
+        }
+        AlertKind::SyncFailing => {
+            let failures = crate::db::get_sync_failures(pool, cutoff)?;
+            if failures.is_empty() {
+                return Ok(None);
+            }
+
+            let details: Vec<String> = failures
+                .iter()
+                .map(|f| {
+                    format!(
+                        "{} user {} since {}{}",
+                        f.source,
+                        f.username,
+                        f.failing_since.format("%Y-%m-%d %H:%M UTC"),
+                        f.last_error
+                            .as_deref()
+                            .map(|e| format!(" ({})", e))
+                            .unwrap_or_default()
+                    )
+                })
+                .collect();
+            Ok(Some(format!(
+                "Sync failing for over {}h: {}",
+                rule.threshold_hours,
+                details.join("; ")
+            )))
+        }
+    }
+}
+
+/// Evaluates alert rules in the background and delivers fired alerts to the
+/// log, live subscribers and an optional webhook
+#[derive(Clone)]
+pub struct AlertMonitor {
+    pool: DbPool,
+    live_hub: LiveHub,
+    webhook_url: Option<String>,
+    client: reqwest::Client,
+}
+
+impl AlertMonitor {
+    pub fn new(pool: DbPool, live_hub: LiveHub) -> Self {
+        Self {
+            pool,
+            live_hub,
+            webhook_url: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Also POST each alert as JSON to this URL
+    pub fn with_webhook_url(mut self, webhook_url: Option<String>) -> Self {
+        self.webhook_url = webhook_url;
+        self
+    }
+
+    pub fn start(&self) {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs(ALERT_CHECK_INTERVAL_SECS);
+            loop {
+                match evaluate(&monitor.pool, Utc::now()) {
+                    Ok(alerts) => {
+                        for alert in alerts {
+                            monitor.deliver(alert).await;
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to evaluate alert rules: {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    async fn deliver(&self, alert: Alert) {
+        tracing::warn!(rule_id = alert.rule_id, "Alert: {}", alert.message);
+
+        if let Some(url) = &self.webhook_url
+            && let Err(e) = self
+                .client
+                .post(url)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+        {
+            tracing::error!("Failed to deliver alert to webhook: {}", e);
+        }
+
+        self.live_hub.publish(LiveEvent::Alert { alert });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Scrobble, SyncConfig};
+    use crate::test_utils::setup_pool;
+
+    fn at(ts: &str) -> DateTime<Utc> {
+        ts.parse().unwrap()
+    }
+
+    #[test]
+    fn test_no_scrobbles_alert_fires_once_per_incident() {
+        let (pool, _temp_file) = setup_pool();
+        crate::db::insert_alert_rule(&pool, &AlertRule::new(AlertKind::NoScrobbles, 48)).unwrap();
+
+        // Nothing to watch yet
+        assert!(
+            evaluate(&pool, at("2024-01-10T00:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+
+        let scrobble = |ts: &str| Scrobble::new("A".into(), "T".into(), at(ts), "test".into());
+        crate::db::insert_scrobble(&pool, &scrobble("2024-01-01T00:00:00Z")).unwrap();
+
+        assert!(
+            evaluate(&pool, at("2024-01-02T00:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+        let fired = evaluate(&pool, at("2024-01-04T00:00:00Z")).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].kind, AlertKind::NoScrobbles);
+
+        // Still silent: no repeat until the condition clears
+        assert!(
+            evaluate(&pool, at("2024-01-05T00:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+
+        crate::db::insert_scrobble(&pool, &scrobble("2024-01-05T12:00:00Z")).unwrap();
+        assert!(
+            evaluate(&pool, at("2024-01-06T00:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(!crate::db::get_alert_rules(&pool).unwrap()[0].active);
+
+        assert_eq!(
+            evaluate(&pool, at("2024-01-08T00:00:00Z")).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_sync_failing_alert() {
+        let (pool, _temp_file) = setup_pool();
+        crate::db::insert_alert_rule(&pool, &AlertRule::new(AlertKind::SyncFailing, 72)).unwrap();
+        let config_id = crate::db::insert_sync_config(
+            &pool,
+            &SyncConfig::new("listenbrainz".into(), "me".into(), 60),
+        )
+        .unwrap();
+
+        crate::db::record_sync_failure(&pool, config_id, "timeout", at("2024-01-01T00:00:00Z"))
+            .unwrap();
+        // Later failures don't move the streak start
+        crate::db::record_sync_failure(&pool, config_id, "HTTP 503", at("2024-01-03T00:00:00Z"))
+            .unwrap();
+
+        assert!(
+            evaluate(&pool, at("2024-01-03T12:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+        let fired = evaluate(&pool, at("2024-01-04T12:00:00Z")).unwrap();
+        assert_eq!(fired.len(), 1);
+        assert!(fired[0].message.contains("listenbrainz user me"));
+        assert!(fired[0].message.contains("HTTP 503"));
+
+        crate::db::update_sync_timestamp(&pool, config_id, at("2024-01-05T00:00:00Z")).unwrap();
+        assert!(
+            evaluate(&pool, at("2024-01-05T01:00:00Z"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(!crate::db::get_alert_rules(&pool).unwrap()[0].active);
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::db::DbPool;
+use crate::models::Scrobble;
+
+/// More scrobbles than this in a clock hour means tracks under two minutes
+/// back to back, which one listener can't keep up
+pub const MAX_SCROBBLES_PER_HOUR: i64 = 30;
+
+/// Consecutive overfull hours needed before a burst is reported
+pub const MIN_DENSE_HOURS: i64 = 2;
+
+// Allowance for clocks running a little ahead of the server's
+const FUTURE_TOLERANCE_MINUTES: i64 = 5;
+
+/// A request to make against the API
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub method: &'static str,
+    pub href: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrobbleLinks {
+    pub inspect: Link,
+    pub delete: Link,
+}
+
+/// A suspicious scrobble with the requests to look at or remove it
+#[derive(Debug, Clone, Serialize)]
+pub struct FlaggedScrobble {
+    #[serde(flatten)]
+    pub scrobble: Scrobble,
+    pub links: ScrobbleLinks,
+}
+
+/// Several scrobbles stamped with the same second
+#[derive(Debug, Clone, Serialize)]
+pub struct Collision {
+    pub timestamp: DateTime<Utc>,
+    pub scrobbles: Vec<FlaggedScrobble>,
+}
+
+/// Consecutive hours each holding more than `MAX_SCROBBLES_PER_HOUR`
+#[derive(Debug, Clone, Serialize)]
+pub struct DenseSpan {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub hours: i64,
+    pub scrobbles: i64,
+    pub per_hour: f64,
+    /// Lists the span's scrobbles, each of which can then be deleted
+    pub inspect: Link,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnomalyReport {
+    pub collisions: Vec<Collision>,
+    pub dense_spans: Vec<DenseSpan>,
+    pub future: Vec<FlaggedScrobble>,
+}
+
+/// Data that can't be a real listening history, at most `limit` of each kind,
+/// most recent first
+pub fn find_anomalies(pool: &DbPool, now: DateTime<Utc>, limit: usize) -> Result<AnomalyReport> {
+    let collisions = crate::db::get_timestamp_collisions(pool, limit as i64)?
+        .into_iter()
+        .map(|group| Collision {
+            timestamp: group[0].timestamp,
+            scrobbles: group.into_iter().map(flag).collect(),
+        })
+        .collect();
+
+    let hours = crate::db::get_hours_over(pool, MAX_SCROBBLES_PER_HOUR)?;
+    let mut dense_spans = dense_spans(&hours);
+    dense_spans.reverse();
+    dense_spans.truncate(limit);
+
+    let future = crate::db::get_scrobbles_after(
+        pool,
+        now + Duration::minutes(FUTURE_TOLERANCE_MINUTES),
+        limit as i64,
+    )?
+    .into_iter()
+    .map(flag)
+    .collect();
+
+    Ok(AnomalyReport {
+        collisions,
+        dense_spans,
+        future,
+    })
+}
+
+/// Group overfull hours, oldest first, into runs of at least `MIN_DENSE_HOURS`
+fn dense_spans(hours: &[(DateTime<Utc>, i64)]) -> Vec<DenseSpan> {
+    let mut runs: Vec<Vec<(DateTime<Utc>, i64)>> = Vec::new();
+    for &(hour, count) in hours {
+        match runs.last_mut() {
+            Some(run) if run[run.len() - 1].0 + Duration::hours(1) == hour => {
+                run.push((hour, count))
+            }
+            _ => runs.push(vec![(hour, count)]),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|run| run.len() as i64 >= MIN_DENSE_HOURS)
+        .map(|run| {
+            let start = run[0].0;
+            let end = run[run.len() - 1].0 + Duration::hours(1) - Duration::seconds(1);
+            let scrobbles: i64 = run.iter().map(|(_, count)| count).sum();
+            DenseSpan {
+                start,
+                end,
+                hours: run.len() as i64,
+                scrobbles,
+                per_hour: scrobbles as f64 / run.len() as f64,
+                inspect: Link {
+                    method: "GET",
+                    href: format!(
+                        "/api/scrobbles?start={}&end={}&limit={}",
+                        urlencoding::encode(&start.to_rfc3339()),
+                        urlencoding::encode(&end.to_rfc3339()),
+                        scrobbles
+                    ),
+                },
+            }
+        })
+        .collect()
+}
+
+fn flag(scrobble: Scrobble) -> FlaggedScrobble {
+    let href = format!("/api/scrobbles/{}", scrobble.id.unwrap_or_default());
+    FlaggedScrobble {
+        links: ScrobbleLinks {
+            inspect: Link {
+                method: "GET",
+                href: href.clone(),
+            },
+            delete: Link {
+                method: "DELETE",
+                href,
+            },
+        },
+        scrobble,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+
+    fn insert(pool: &DbPool, track: &str, timestamp: DateTime<Utc>, source: &str) {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            track.to_string(),
+            timestamp,
+            source.to_string(),
+        );
+        crate::db::insert_scrobble(pool, &scrobble).unwrap();
+    }
+
+    #[test]
+    fn test_collisions_and_future_scrobbles_are_flagged() {
+        let (pool, _temp_file) = setup_pool();
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let second = now - Duration::hours(1);
+
+        insert(&pool, "One", second, "lastfm");
+        insert(&pool, "Two", second, "lastfm");
+        insert(&pool, "Three", second + Duration::seconds(1), "lastfm");
+        insert(&pool, "Soon", now + Duration::minutes(2), "lastfm");
+        insert(&pool, "Tomorrow", now + Duration::days(1), "lastfm");
+
+        let report = find_anomalies(&pool, now, 10).unwrap();
+
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!(report.collisions[0].timestamp, second);
+        assert_eq!(report.collisions[0].scrobbles.len(), 2);
+
+        assert_eq!(report.future.len(), 1);
+        assert_eq!(report.future[0].scrobble.track, "Tomorrow");
+        let id = report.future[0].scrobble.id.unwrap();
+        assert_eq!(report.future[0].links.delete.method, "DELETE");
+        assert_eq!(
+            report.future[0].links.delete.href,
+            format!("/api/scrobbles/{}", id)
+        );
+
+        assert!(report.dense_spans.is_empty());
+    }
+
+    #[test]
+    fn test_only_sustained_bursts_are_dense() {
+        let (pool, _temp_file) = setup_pool();
+        let start: DateTime<Utc> = "2024-06-01T08:00:00Z".parse().unwrap();
+
+        // 40 an hour for two hours, then a lone overfull hour later on
+        for i in 0..80 {
+            insert(
+                &pool,
+                &format!("Burst {}", i),
+                start + Duration::seconds(i * 90),
+                "lastfm",
+            );
+        }
+        let later = start + Duration::hours(6);
+        for i in 0..40 {
+            insert(
+                &pool,
+                &format!("Short {}", i),
+                later + Duration::seconds(i * 60),
+                "lastfm",
+            );
+        }
+
+        let report = find_anomalies(&pool, start + Duration::days(1), 10).unwrap();
+
+        assert_eq!(report.dense_spans.len(), 1);
+        let span = &report.dense_spans[0];
+        assert_eq!(span.start, start);
+        assert_eq!((span.hours, span.scrobbles), (2, 80));
+        assert_eq!(span.per_hour, 40.0);
+        assert!(span.inspect.href.starts_with("/api/scrobbles?start="));
+    }
+}
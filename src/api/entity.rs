@@ -0,0 +1,164 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Json, Response},
+};
+use std::{collections::HashMap, sync::Arc};
+
+use super::AppState;
+use crate::models::FieldError;
+
+/// Artist, album or track named in the query string. Names like "AC/DC" or
+/// "100% Pure Love" can't be trusted to a path segment: proxies decode `%2F`
+/// before routing, and a bare `%` isn't valid percent-encoding
+pub struct Entity<T>(pub T);
+
+/// Like `Entity`, but refused with 404 unless the entity has scrobbles, so
+/// an unknown name isn't mistaken for one with no plays in range
+pub struct KnownEntity<T>(pub T);
+
+/// Query parameters naming an entity, in order
+pub trait EntityNames {
+    const FIELDS: &'static [&'static str];
+
+    /// Entity type as `first_listens` and `play_counts` name it
+    const ENTITY_TYPE: &'static str;
+
+    /// Build from one non-blank value per field in `FIELDS`
+    fn from_names(names: Vec<String>) -> Self;
+
+    /// Artist and name keys, the name empty for artists
+    fn key(&self) -> (&str, &str);
+}
+
+pub struct ArtistName {
+    pub artist: String,
+}
+
+pub struct AlbumName {
+    pub artist: String,
+    pub album: String,
+}
+
+pub struct TrackName {
+    pub artist: String,
+    pub track: String,
+}
+
+impl EntityNames for ArtistName {
+    const FIELDS: &'static [&'static str] = &["artist"];
+    const ENTITY_TYPE: &'static str = "artist";
+
+    fn from_names(names: Vec<String>) -> Self {
+        let mut names = names.into_iter();
+        Self {
+            artist: names.next().unwrap_or_default(),
+        }
+    }
+
+    fn key(&self) -> (&str, &str) {
+        (&self.artist, "")
+    }
+}
+
+impl EntityNames for AlbumName {
+    const FIELDS: &'static [&'static str] = &["artist", "album"];
+    const ENTITY_TYPE: &'static str = "album";
+
+    fn from_names(names: Vec<String>) -> Self {
+        let mut names = names.into_iter();
+        Self {
+            artist: names.next().unwrap_or_default(),
+            album: names.next().unwrap_or_default(),
+        }
+    }
+
+    fn key(&self) -> (&str, &str) {
+        (&self.artist, &self.album)
+    }
+}
+
+impl EntityNames for TrackName {
+    const FIELDS: &'static [&'static str] = &["artist", "track"];
+    const ENTITY_TYPE: &'static str = "track";
+
+    fn from_names(names: Vec<String>) -> Self {
+        let mut names = names.into_iter();
+        Self {
+            artist: names.next().unwrap_or_default(),
+            track: names.next().unwrap_or_default(),
+        }
+    }
+
+    fn key(&self) -> (&str, &str) {
+        (&self.artist, &self.track)
+    }
+}
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for Entity<T>
+where
+    S: Send + Sync,
+    T: EntityNames,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map_err(IntoResponse::into_response)?;
+
+        let mut names = Vec::new();
+        let mut errors = Vec::new();
+        for &field in T::FIELDS {
+            // Names are matched exactly, so only blank ones are refused
+            match query.get(field).filter(|name| !name.trim().is_empty()) {
+                Some(name) => names.push(name.clone()),
+                None => errors.push(FieldError::new(field, "is required")),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "errors": errors })),
+            )
+                .into_response());
+        }
+        Ok(Entity(T::from_names(names)))
+    }
+}
+
+#[async_trait]
+impl<T> FromRequestParts<Arc<AppState>> for KnownEntity<T>
+where
+    T: EntityNames + Send,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Entity(names) = Entity::<T>::from_request_parts(parts, state).await?;
+
+        let (artist, name) = names.key();
+        match crate::db::entity_exists(&state.pool, T::ENTITY_TYPE, artist, name) {
+            Ok(true) => Ok(KnownEntity(names)),
+            Ok(false) => Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "errors": [FieldError::new(
+                        T::ENTITY_TYPE,
+                        format!("no such {}", T::ENTITY_TYPE),
+                    )]
+                })),
+            )
+                .into_response()),
+            Err(e) => {
+                tracing::error!("Failed to look up {}: {}", T::ENTITY_TYPE, e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+        }
+    }
+}
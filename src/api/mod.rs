@@ -1,28 +1,73 @@
+mod entity;
+mod report_images;
+
 use axum::{
     Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{get, post},
+    body::Body,
+    extract::{
+        Extension, Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{Method, Request, StatusCode},
+    middleware,
+    response::{Html, Json, Response},
+    routing::{delete, get, post, put},
 };
-use chrono::{DateTime, Datelike, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
 
-use crate::db::DbPool;
-use crate::images::{ImageRequest, ImageService};
-use crate::importers::{LastFmImporter, ListenBrainzImporter};
-use crate::models::SyncConfig;
-use crate::reports;
+use crate::anomalies;
+use crate::auth::{self, AuthMode, AuthState, AuthenticatedUser};
+use crate::charts::{self, ChartHistory, ChartRecord};
+use crate::conflicts::{self, Conflict};
+use crate::credits::ArtistCredit;
+use crate::db::{DbPool, Standing, TimeBucket};
+use crate::images::{EntityType, ImageCacheFilter, ImageCacheStats, ImageProvider, ImageRequest};
+use crate::importers;
+use crate::live::{LiveEvent, LiveHub};
+use crate::manual;
+use crate::models::{
+    Achievement, AlertKind, AlertRule, Annotation, AnnotationKind, ChartEntry, ChartKind,
+    DetectionStatus, FieldError, IgnoreRule, ImportJob, ImportStatus, ListenFilter, MediaType,
+    MediaTypeRule, MilestoneMetric, MilestoneRule, Note, Provenance, RatingKind, SHARE_SCOPES,
+    Scrobble, ShareToken, SleepDetection, SyncConfig,
+};
+use crate::normalizer::Normalizer;
+use crate::playlists::{Playlist, PlaylistEntry, PlaylistFormat};
+use crate::recommendations;
+use crate::reports::{self, movement::Movement};
+use crate::settings::{self, Settings};
 use crate::sync::SyncScheduler;
+use entity::{AlbumName, ArtistName, Entity, KnownEntity, TrackName};
 
 type DateRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: DbPool,
-    pub image_service: Arc<ImageService>,
+    pub image_service: Arc<dyn ImageProvider>,
     pub sync_scheduler: SyncScheduler,
+    pub live_hub: LiveHub,
+    pub normalizer: Normalizer,
+    pub options: InstanceOptions,
+    pub started_at: DateTime<Utc>,
+}
+
+/// How this instance was started, beyond what the other arguments to
+/// `create_router` already say
+#[derive(Debug, Clone, Default)]
+pub struct InstanceOptions {
+    /// Refuse every request that would change the database
+    pub read_only: bool,
+    pub tls: bool,
+    /// A Last.fm API key is set, so artwork is looked up
+    pub image_lookups: bool,
+    pub alert_webhook: bool,
+    /// New releases by top artists are looked up on MusicBrainz
+    pub release_radar: bool,
 }
 
 #[derive(Deserialize)]
@@ -39,6 +84,11 @@ pub struct ImportParams {
     username: String,
     api_key: Option<String>,
     token: Option<String>,
+    /// Also fetch loved tracks and artist tags (Last.fm only)
+    #[serde(default)]
+    enrich: bool,
+    /// Resume a failed import from this timestamp (ListenBrainz only)
+    max_ts: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -46,85 +96,793 @@ pub struct ImportResponse {
     success: bool,
     count: usize,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<i64>,
+}
+
+/// POST endpoints that only read, still served in read-only mode
+const READ_ONLY_POSTS: &[&str] = &[
+    "/api/admin/integrity-check",
+    "/api/reports/compare-remote",
+    "/api/sync/config/validate",
+];
+
+/// Middleware for read-only instances (public demos, dashboards served off a
+/// replica): anything that would change the database is refused with 403
+pub async fn reject_writes(request: Request<Body>, next: middleware::Next) -> Response {
+    let method = request.method();
+    let allowed = method == Method::GET
+        || method == Method::HEAD
+        || method == Method::OPTIONS
+        || (method == Method::POST && READ_ONLY_POSTS.contains(&request.uri().path()));
+
+    if !allowed {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from("This instance is read-only"))
+            .unwrap();
+    }
+    next.run(request).await
 }
 
 pub fn create_router(
     pool: DbPool,
-    image_service: Arc<ImageService>,
+    image_service: Arc<dyn ImageProvider>,
     sync_scheduler: SyncScheduler,
+    live_hub: LiveHub,
+    auth_mode: Option<AuthMode>,
+    normalizer: Normalizer,
+    options: InstanceOptions,
 ) -> Router {
-    let state = AppState {
+    let auth_pool = pool.clone();
+    let read_only = options.read_only;
+
+    let state = Arc::new(AppState {
         pool,
         image_service,
         sync_scheduler,
-    };
+        live_hub,
+        normalizer,
+        options,
+        started_at: Utc::now(),
+    });
 
-    Router::new()
+    let mut router = Router::new()
         .route("/", get(root_handler))
-        .route("/api/scrobbles", get(get_scrobbles_handler))
+        .route("/api/me", get(get_me_handler))
+        .route("/api/instance", get(get_instance_handler))
+        .route(
+            "/api/settings",
+            get(get_settings_handler).put(update_settings_handler),
+        )
+        .route(
+            "/api/settings/listen-filter",
+            get(get_listen_filter_handler).put(set_listen_filter_handler),
+        )
+        .route(
+            "/api/scrobbles",
+            get(get_scrobbles_handler).post(submit_scrobbles_handler),
+        )
+        .route("/api/scrobbles/bulk", post(submit_album_handler))
+        .route(
+            "/api/scrobbles/:id",
+            get(get_scrobble_handler).delete(delete_scrobble_handler),
+        )
         .route("/api/stats", get(get_stats_handler))
         .route("/api/stats/ui", get(get_stats_ui_handler))
+        .route("/api/overview", get(get_overview_handler))
         .route("/api/years", get(get_available_years_handler))
         .route("/api/pulse", get(get_pulse_handler))
+        .route("/api/ws", get(live_ws_handler))
         .route("/api/import", post(import_handler))
+        .route("/api/imports", get(get_import_jobs_handler))
+        .route("/api/imports/:id/resume", post(resume_import_job_handler))
         .route("/api/sync/config", post(create_sync_config_handler))
         .route("/api/sync/config", get(get_sync_configs_handler))
+        .route(
+            "/api/sync/config/validate",
+            post(validate_sync_config_handler),
+        )
         .route(
             "/api/sync/config/:id",
             get(get_sync_config_handler)
                 .post(update_sync_config_handler)
                 .delete(delete_sync_config_handler),
         )
+        .route("/api/sync/status", get(get_sync_status_handler))
         .route("/api/sync/config/:id/trigger", post(trigger_sync_handler))
+        .route("/api/sync/config/:id/rescan", post(rescan_sync_handler))
+        .route(
+            "/api/sync/config/:id/playlists",
+            post(pull_playlists_handler),
+        )
+        .route(
+            "/api/sync/config/:id/legacy-charts",
+            post(pull_legacy_charts_handler),
+        )
+        .route(
+            "/api/sync/config/:id/pause",
+            post(pause_sync_config_handler),
+        )
+        .route(
+            "/api/sync/config/:id/resume",
+            post(resume_sync_config_handler),
+        )
         .route("/api/export", get(export_handler))
+        .route("/api/export/playlist", get(export_playlist_handler))
+        .route("/api/image", get(image_proxy_handler))
+        .route("/api/images/cache", delete(purge_image_cache_handler))
+        .route("/api/images/cache/stats", get(image_cache_stats_handler))
+        .route("/api/images/cache/refresh", post(refresh_image_handler))
         .route("/api/reports/:type", get(get_report_handler))
         .route("/api/reports/monthly", get(get_monthly_report_handler))
         .route("/api/reports/heatmap", get(get_heatmap_handler))
+        .route("/api/reports/dayparts", get(get_day_parts_handler))
+        .route("/api/reports/sleep", get(get_sleep_report_handler))
+        .route(
+            "/api/sleep-detections/scan",
+            post(scan_sleep_detections_handler),
+        )
+        .route(
+            "/api/sleep-detections/:id/confirm",
+            post(confirm_sleep_detection_handler),
+        )
+        .route(
+            "/api/sleep-detections/:id/dismiss",
+            post(dismiss_sleep_detection_handler),
+        )
         .route("/api/reports/novelty", get(get_novelty_handler))
         .route("/api/reports/transitions", get(get_transitions_handler))
         .route("/api/reports/diversity", get(get_diversity_handler))
+        .route("/api/reports/skips", get(get_skips_handler))
+        .route("/api/reports/album-listens", get(get_album_listens_handler))
+        .route("/api/reports/tenure", get(get_tenure_handler))
+        .route("/api/reports/half-life", get(get_half_life_handler))
+        .route("/api/reports/consistency", get(get_consistency_handler))
+        .route("/api/reports/records", get(get_records_handler))
+        .route(
+            "/api/reports/listening-styles",
+            get(get_listening_styles_handler),
+        )
+        .route("/api/reports/compare-remote", post(compare_remote_handler))
         .route("/api/reports/yearly/:year", get(get_yearly_handler))
+        .route("/api/reports/wrapped/:year", get(get_wrapped_handler))
+        .route("/api/recommendations/revisit", get(revisit_handler))
+        .route("/api/releases/new", get(new_releases_handler))
+        .route("/api/charts/rebuild", post(rebuild_charts_handler))
+        .route("/api/digest", get(get_digest_handler))
+        .route("/api/summaries/rebuild", post(rebuild_summaries_handler))
+        .route("/api/charts/:chart", get(get_chart_handler))
+        .route("/api/charts/:chart/leaders", get(get_chart_leaders_handler))
+        .route(
+            "/api/charts/artists/history",
+            get(get_artist_chart_history_handler),
+        )
+        .route(
+            "/api/charts/tracks/history",
+            get(get_track_chart_history_handler),
+        )
+        .route(
+            "/api/recommendations/listenbrainz",
+            get(get_recommended_playlists_handler),
+        )
+        .route(
+            "/api/recommendations/listenbrainz/:mbid",
+            get(get_recommended_playlist_handler),
+        )
         .route("/api/timeline", get(get_timeline_handler))
-        .route("/api/artist/:artist", get(get_artist_handler))
-        .route("/api/album/:artist/:album", get(get_album_handler))
-        .route("/api/track/:artist/:track", get(get_track_handler))
-        .with_state(Arc::new(state))
+        .route("/api/calendar/:year/:month", get(get_calendar_handler))
+        .route(
+            "/api/notes",
+            get(get_notes_handler).post(create_note_handler),
+        )
+        .route(
+            "/api/notes/:id",
+            get(get_note_handler)
+                .put(update_note_handler)
+                .delete(delete_note_handler),
+        )
+        .route(
+            "/api/annotations",
+            get(get_annotations_handler).post(create_annotation_handler),
+        )
+        .route(
+            "/api/annotations/:id",
+            get(get_annotation_handler)
+                .put(update_annotation_handler)
+                .delete(delete_annotation_handler),
+        )
+        .route(
+            "/api/ignore-rules",
+            get(get_ignore_rules_handler).post(create_ignore_rule_handler),
+        )
+        .route(
+            "/api/ignore-rules/:id",
+            get(get_ignore_rule_handler)
+                .put(update_ignore_rule_handler)
+                .delete(delete_ignore_rule_handler),
+        )
+        .route(
+            "/api/alerts",
+            get(get_alert_rules_handler).post(create_alert_rule_handler),
+        )
+        .route("/api/alerts/:id", delete(delete_alert_rule_handler))
+        .route("/api/achievements", get(get_achievements_handler))
+        .route(
+            "/api/milestones/rules",
+            get(get_milestone_rules_handler).post(create_milestone_rule_handler),
+        )
+        .route(
+            "/api/milestones/rules/:id",
+            delete(delete_milestone_rule_handler),
+        )
+        .route(
+            "/api/media-rules",
+            get(get_media_type_rules_handler).post(create_media_type_rule_handler),
+        )
+        .route(
+            "/api/media-rules/:id",
+            delete(delete_media_type_rule_handler),
+        )
+        .route("/api/conflicts", get(get_conflicts_handler))
+        .route("/api/conflicts/resolve", post(resolve_conflict_handler))
+        .route("/api/artist", get(get_artist_handler))
+        .route("/api/album", get(get_album_handler))
+        .route("/api/track", get(get_track_handler))
+        .route(
+            "/api/album/rating",
+            put(set_album_rating_handler).delete(delete_album_rating_handler),
+        )
+        .route(
+            "/api/track/rating",
+            put(set_track_rating_handler).delete(delete_track_rating_handler),
+        )
+        .route("/api/ratings", get(get_ratings_handler))
+        .route("/api/reports/ratings", get(get_ratings_report_handler))
+        .route("/api/admin/vacuum", post(admin_vacuum_handler))
+        .route("/api/admin/analyze", post(admin_analyze_handler))
+        .route("/api/admin/db-stats", get(admin_db_stats_handler))
+        .route("/api/admin/backup.sqlite", get(admin_backup_handler))
+        .route(
+            "/api/admin/integrity-check",
+            post(admin_integrity_check_handler),
+        )
+        .route("/api/maintenance/reattribute", post(reattribute_handler))
+        .route(
+            "/api/maintenance/compilations",
+            get(get_compilations_handler).post(mark_compilation_handler),
+        )
+        .route(
+            "/api/maintenance/shift-timestamps",
+            post(shift_timestamps_handler),
+        )
+        .route(
+            "/api/maintenance/archive",
+            get(get_archive_handler).post(archive_scrobbles_handler),
+        )
+        .route(
+            "/api/maintenance/archive/restore",
+            post(restore_archive_handler),
+        )
+        .route("/api/maintenance/log", get(maintenance_log_handler))
+        .route("/api/maintenance/anomalies", get(anomalies_handler))
+        .route(
+            "/api/share",
+            get(get_share_tokens_handler).post(create_share_token_handler),
+        )
+        .route("/api/share/:token", delete(delete_share_token_handler));
+
+    #[cfg(feature = "dev-tools")]
+    {
+        router = router.route("/api/dev/seed", post(dev_seed_handler));
+    }
+
+    router = router.layer(middleware::from_fn_with_state(
+        state.clone(),
+        report_images::attach_report_images,
+    ));
+
+    let mut login_routes = Router::new();
+    match auth_mode {
+        Some(AuthMode::Proxy(config)) => {
+            router = router.layer(middleware::from_fn_with_state(
+                AuthState {
+                    pool: auth_pool,
+                    config,
+                },
+                auth::require_remote_user,
+            ));
+        }
+        Some(AuthMode::Oidc(client)) => {
+            let oidc = auth::oidc::OidcState {
+                pool: auth_pool,
+                client,
+            };
+            router = router.layer(middleware::from_fn_with_state(
+                oidc.clone(),
+                auth::oidc::require_session,
+            ));
+            login_routes = auth::oidc::routes(oidc);
+        }
+        None => {}
+    }
+
+    // Outside authentication, which records the user
+    if read_only {
+        router = router.layer(middleware::from_fn(reject_writes));
+    }
+
+    // Share links are public: the token itself is the credential
+    let share_routes = Router::new()
+        .route("/share/:token/stats", get(share_stats_handler))
+        .route("/share/:token/years", get(share_years_handler))
+        .route("/share/:token/reports/:type", get(share_report_handler));
+
+    router
+        .merge(share_routes)
+        .merge(login_routes)
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state)
+}
+
+/// Root span for every request, tagged with the `x-request-id` assigned by
+/// `SetRequestIdLayer` so importer and sync logs can be traced back to it
+fn make_request_span(request: &Request<Body>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    )
 }
 
 async fn root_handler() -> Html<String> {
     Html(include_str!("../../templates/index.html").to_string())
 }
 
+async fn get_me_handler(
+    user: Option<Extension<AuthenticatedUser>>,
+) -> Result<Json<AuthenticatedUser>, StatusCode> {
+    // Only populated when proxy or OIDC authentication is enabled
+    user.map(|Extension(user)| Json(user))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Stored preferences, or the defaults if they can't be read
+fn preferences(state: &AppState) -> Settings {
+    settings::load(&state.pool).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read settings, using defaults: {}", e);
+        Settings::default()
+    })
+}
+
+async fn get_settings_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Settings>, StatusCode> {
+    match settings::load(&state.pool) {
+        Ok(settings) => Ok(Json(settings)),
+        Err(e) => {
+            tracing::error!("Failed to read settings: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SettingsResponse {
+    success: bool,
+    settings: Option<Settings>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Change some settings; the body names only the ones to change
+async fn update_settings_handler(
+    State(state): State<Arc<AppState>>,
+    Json(changes): Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<(StatusCode, Json<SettingsResponse>), StatusCode> {
+    match settings::update(&state.pool, &changes) {
+        Ok(Ok(settings)) => Ok((
+            StatusCode::OK,
+            Json(SettingsResponse {
+                success: true,
+                settings: Some(settings),
+                errors: Vec::new(),
+            }),
+        )),
+        Ok(Err(errors)) => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(SettingsResponse {
+                success: false,
+                settings: None,
+                errors,
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to save settings: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_listen_filter_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ListenFilter>, StatusCode> {
+    match crate::db::get_listen_filter(&state.pool) {
+        Ok(filter) => Ok(Json(filter)),
+        Err(e) => {
+            tracing::error!("Failed to read listen filter: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListenFilterResponse {
+    success: bool,
+    filter: Option<ListenFilter>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Change the minimum play criteria for plays submitted with their duration
+async fn set_listen_filter_handler(
+    State(state): State<Arc<AppState>>,
+    Json(filter): Json<ListenFilter>,
+) -> Result<(StatusCode, Json<ListenFilterResponse>), StatusCode> {
+    let errors = filter.validate();
+    if !errors.is_empty() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(ListenFilterResponse {
+                success: false,
+                filter: None,
+                errors,
+            }),
+        ));
+    }
+
+    match crate::db::set_listen_filter(&state.pool, &filter) {
+        Ok(()) => Ok((
+            StatusCode::OK,
+            Json(ListenFilterResponse {
+                success: true,
+                filter: Some(filter),
+                errors: Vec::new(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to save listen filter: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct InstanceInfo {
+    version: &'static str,
+    started_at: DateTime<Utc>,
+    uptime_seconds: i64,
+    database_size_bytes: i64,
+    scrobble_count: i64,
+    earliest_scrobble: Option<DateTime<Utc>>,
+    latest_scrobble: Option<DateTime<Utc>>,
+    sources: Vec<InstanceSource>,
+    features: InstanceFeatures,
+}
+
+/// A configured sync, without its credentials
+#[derive(Serialize)]
+pub struct InstanceSource {
+    source: String,
+    username: String,
+    enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct InstanceFeatures {
+    read_only: bool,
+    auth: bool,
+    tls: bool,
+    image_lookups: bool,
+    alert_webhook: bool,
+    release_radar: bool,
+    sync_scheduler: bool,
+    normalize_rules: Vec<&'static str>,
+}
+
+/// Version, uptime and archive size, for the about page and monitoring
+async fn get_instance_handler(
+    State(state): State<Arc<AppState>>,
+    user: Option<Extension<AuthenticatedUser>>,
+) -> Result<Json<InstanceInfo>, StatusCode> {
+    let pool = &state.pool;
+    let load = || -> anyhow::Result<_> {
+        Ok((
+            crate::db::get_database_size(pool)?,
+            crate::db::get_scrobbles_count(pool)?,
+            crate::db::get_first_scrobble_timestamp(pool)?,
+            crate::db::get_last_scrobble_timestamp(pool)?,
+            crate::db::get_all_sync_configs(pool)?,
+        ))
+    };
+    let (database_size_bytes, scrobble_count, earliest_scrobble, latest_scrobble, configs) = load()
+        .map_err(|e| {
+            tracing::error!("Failed to read instance statistics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let now = Utc::now();
+    Ok(Json(InstanceInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        started_at: state.started_at,
+        uptime_seconds: (now - state.started_at).num_seconds(),
+        database_size_bytes,
+        scrobble_count,
+        earliest_scrobble,
+        latest_scrobble,
+        sources: configs
+            .into_iter()
+            .map(|config| InstanceSource {
+                source: config.source,
+                username: config.username,
+                enabled: config.enabled,
+            })
+            .collect(),
+        features: InstanceFeatures {
+            read_only: state.options.read_only,
+            // Requests only carry a user when an authentication layer ran
+            auth: user.is_some(),
+            tls: state.options.tls,
+            image_lookups: state.options.image_lookups,
+            alert_webhook: state.options.alert_webhook,
+            release_radar: state.options.release_radar,
+            sync_scheduler: state.sync_scheduler.is_running().await,
+            normalize_rules: state
+                .normalizer
+                .rules()
+                .iter()
+                .map(|r| r.as_str())
+                .collect(),
+        },
+    }))
+}
+
+async fn live_ws_handler(State(state): State<Arc<AppState>>, ws: WebSocketUpgrade) -> Response {
+    let live_hub = state.live_hub.clone();
+    ws.on_upgrade(move |socket| stream_live_events(socket, live_hub))
+}
+
+/// Push live events to a WebSocket client until it disconnects
+async fn stream_live_events(mut socket: WebSocket, live_hub: LiveHub) {
+    let mut events = live_hub.subscribe();
+
+    // Start with the current now-playing state so overlays render immediately
+    for event in live_hub.now_playing_snapshot().await {
+        if send_live_event(&mut socket, &event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if send_live_event(&mut socket, &event).await.is_err() {
+                        break;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client lagged, skipped {} events", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Incoming messages are ignored; pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn send_live_event(socket: &mut WebSocket, event: &LiveEvent) -> Result<(), axum::Error> {
+    let payload = serde_json::to_string(event).map_err(axum::Error::new)?;
+    socket.send(Message::Text(payload)).await
+}
+
+#[derive(Deserialize)]
+pub struct ScrobbleListParams {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: Option<i64>,
+    /// With `end`, only scrobbles in this inclusive range, of any media type
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
 async fn get_scrobbles_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<PaginationParams>,
+    Query(params): Query<ScrobbleListParams>,
 ) -> Result<Json<Vec<crate::models::Scrobble>>, StatusCode> {
-    match crate::db::get_scrobbles(&state.pool, params.limit, params.offset) {
+    let result = match (params.start, params.end) {
+        (None, None) => crate::db::get_scrobbles(&state.pool, params.limit, params.offset),
+        (Some(start), Some(end)) if start <= end => crate::db::get_scrobbles_between(
+            &state.pool,
+            start,
+            end,
+            params.limit.unwrap_or(100),
+            params.offset.unwrap_or(0),
+        ),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match result {
         Ok(scrobbles) => Ok(Json(scrobbles)),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-async fn get_stats_handler(
+async fn get_scrobble_handler(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match (
-        crate::db::get_scrobbles_count(&state.pool),
-        crate::db::get_top_artists(&state.pool, 10, None, None),
-        crate::db::get_top_tracks(&state.pool, 10, None, None),
-    ) {
-        (Ok(count), Ok(artists), Ok(tracks)) => {
-            let stats = serde_json::json!({
-                "total_scrobbles": count,
-                "top_artists": artists,
-                "top_tracks": tracks,
-            });
-            Ok(Json(stats))
+    Path(id): Path<i64>,
+) -> Result<Json<crate::models::Scrobble>, StatusCode> {
+    match crate::db::get_scrobble(&state.pool, id) {
+        Ok(Some(scrobble)) => Ok(Json(scrobble)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn delete_scrobble_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_scrobble(&state.pool, id) {
+        Ok(true) => {
+            tracing::info!("Deleted scrobble {}", id);
+            StatusCode::NO_CONTENT
+        }
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::error!("Failed to delete scrobble {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// One manual scrobble or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManualScrobbles {
+    One(manual::ManualScrobble),
+    Many(Vec<manual::ManualScrobble>),
+}
+
+#[derive(Serialize, Default)]
+struct SubmitScrobblesResponse {
+    success: bool,
+    inserted: usize,
+    duplicates: usize,
+    filtered: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<manual::EntryError>,
+}
+
+/// Log listens no service tracked (records, CDs, concerts). Nothing is stored
+/// unless every entry is valid
+async fn submit_scrobbles_handler(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<ManualScrobbles>,
+) -> Result<(StatusCode, Json<SubmitScrobblesResponse>), StatusCode> {
+    let entries = match body {
+        ManualScrobbles::One(entry) => vec![entry],
+        ManualScrobbles::Many(entries) => entries,
+    };
+
+    store_manual_scrobbles(&state, &entries)
+}
+
+/// Backdate a whole album listened to offline, one scrobble per track from
+/// `start`, with the tracklist given or fetched from MusicBrainz
+async fn submit_album_handler(
+    State(state): State<Arc<AppState>>,
+    Json(listen): Json<manual::AlbumListen>,
+) -> Result<(StatusCode, Json<SubmitScrobblesResponse>), StatusCode> {
+    let listen = listen.resolve(&reqwest::Client::new()).await.map_err(|e| {
+        tracing::warn!("Failed to look up release: {:#}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    match listen.to_entries() {
+        Ok(entries) => store_manual_scrobbles(&state, &entries),
+        Err(error) => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(SubmitScrobblesResponse {
+                errors: vec![error],
+                ..Default::default()
+            }),
+        )),
+    }
+}
+
+fn store_manual_scrobbles(
+    state: &AppState,
+    entries: &[manual::ManualScrobble],
+) -> Result<(StatusCode, Json<SubmitScrobblesResponse>), StatusCode> {
+    let errors = manual::validate(entries, Utc::now());
+    if !errors.is_empty() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(SubmitScrobblesResponse {
+                errors,
+                ..Default::default()
+            }),
+        ));
+    }
+
+    match manual::submit(&state.pool, &state.normalizer, entries) {
+        Ok(submission) => Ok((
+            StatusCode::OK,
+            Json(SubmitScrobblesResponse {
+                success: true,
+                inserted: submission.inserted,
+                duplicates: submission.duplicates,
+                filtered: submission.filtered,
+                errors: Vec::new(),
+            }),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to store manual scrobbles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
-        _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
+#[derive(Deserialize)]
+pub struct StatsParams {
+    #[serde(default = "default_stats_limit")]
+    limit: i64,
+    #[serde(default)]
+    credit: ArtistCredit,
+}
+
+fn default_stats_limit() -> i64 {
+    10
+}
+
+async fn get_stats_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StatsParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    load_stats(&state.pool, params.limit, params.credit).map(Json)
+}
+
+fn load_stats(
+    pool: &DbPool,
+    limit: i64,
+    credit: ArtistCredit,
+) -> Result<serde_json::Value, StatusCode> {
+    let limit = limit.clamp(1, 500);
+    crate::db::with_read_txn(pool, |conn| {
+        Ok(serde_json::json!({
+            "total_scrobbles": crate::db::query_scrobbles_count(conn)?,
+            "top_artists": crate::db::query_top_credited_artists(conn, credit, limit, None, None)?,
+            "top_tracks": crate::db::query_top_tracks(conn, limit, None, None)?,
+        }))
+    })
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 async fn get_available_years_handler(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<i32>>, StatusCode> {
@@ -138,70 +896,167 @@ async fn import_handler(
     State(state): State<Arc<AppState>>,
     Json(params): Json<ImportParams>,
 ) -> Result<Json<ImportResponse>, StatusCode> {
-    let count = match params.source.as_str() {
+    let mut job = ImportJob::new(params.source, params.username);
+    match job.source.as_str() {
         "lastfm" => {
-            if let Some(api_key) = params.api_key {
-                let importer = LastFmImporter::new(api_key, params.username);
-                importer.import_all(&state.pool).await
-            } else {
+            if params.api_key.is_none() {
                 return Ok(Json(ImportResponse {
                     success: false,
                     count: 0,
                     message: "API key required for Last.fm".to_string(),
+                    job_id: None,
                 }));
             }
+            job.api_key = params.api_key;
+            job.enrich = params.enrich;
         }
         "listenbrainz" => {
-            let importer = ListenBrainzImporter::new(params.username, params.token);
-            importer.import_all(&state.pool).await
+            job.token = params.token;
+            job.checkpoint = params.max_ts;
         }
         _ => {
             return Ok(Json(ImportResponse {
                 success: false,
                 count: 0,
-                message: format!("Unknown source: {}", params.source),
+                message: format!("Unknown source: {}", job.source),
+                job_id: None,
             }));
         }
-    };
+    }
+
+    job.id = Some(
+        crate::db::insert_import_job(&state.pool, &job).map_err(|e| {
+            tracing::error!("Failed to create import job: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?,
+    );
+
+    Ok(Json(run_import(&state, &job).await))
+}
 
-    match count {
-        Ok(n) => Ok(Json(ImportResponse {
+async fn run_import(state: &AppState, job: &ImportJob) -> ImportResponse {
+    match importers::jobs::run_import_job(&state.pool, &state.normalizer, job).await {
+        Ok(n) => ImportResponse {
             success: true,
             count: n,
             message: format!("Successfully imported {} scrobbles", n),
-        })),
-        Err(e) => Ok(Json(ImportResponse {
+            job_id: job.id,
+        },
+        Err(e) => ImportResponse {
             success: false,
             count: 0,
-            message: format!("Import failed: {}", e),
-        })),
+            message: format!("Import failed: {:#}", e),
+            job_id: job.id,
+        },
     }
 }
 
-async fn get_report_handler(
+async fn get_import_jobs_handler(
     State(state): State<Arc<AppState>>,
-    Path(report_type): Path<String>,
-) -> Result<Json<reports::Report>, StatusCode> {
-    let report = match report_type.as_str() {
-        "alltime" => reports::generate_all_time_report(&state.pool),
-        "lastmonth" => reports::generate_last_month_report(&state.pool),
-        year if year.len() == 4 => {
-            if let Ok(y) = year.parse::<i32>() {
-                if (1970..=2100).contains(&y) {
-                    reports::generate_yearly_report(&state.pool, y)
-                } else {
+) -> Result<Json<Vec<ImportJob>>, StatusCode> {
+    match crate::db::get_import_jobs(&state.pool, None) {
+        Ok(jobs) => Ok(Json(jobs)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Continue a failed import from its last checkpoint
+async fn resume_import_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ImportResponse>, StatusCode> {
+    let mut job = match crate::db::get_import_job(&state.pool, id) {
+        Ok(Some(job)) => job,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    // Running jobs are already being worked on, completed ones have nothing left
+    if job.status != ImportStatus::Failed {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    job.status = ImportStatus::Running;
+    crate::db::set_import_job_status(&state.pool, id, ImportStatus::Running, None, None)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(run_import(&state, &job).await))
+}
+
+async fn get_report_handler(
+    State(state): State<Arc<AppState>>,
+    Path(report_type): Path<String>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    versioned(&build_report(&state.pool, &report_type)?, &schema)
+}
+
+fn build_report(pool: &DbPool, report_type: &str) -> Result<reports::Report, StatusCode> {
+    let report = match report_type {
+        "alltime" => reports::generate_all_time_report(pool),
+        "lastmonth" => reports::generate_last_month_report(pool),
+        year if year.len() == 4 => {
+            if let Ok(y) = year.parse::<i32>() {
+                if (1970..=2100).contains(&y) {
+                    reports::generate_yearly_report(pool, y)
+                } else {
                     return Err(StatusCode::BAD_REQUEST);
                 }
             } else {
                 return Err(StatusCode::BAD_REQUEST);
             }
         }
-        _ => return Err(StatusCode::BAD_REQUEST),
+        period => match parse_sub_year_period(period) {
+            Some((year, 'Q', quarter)) => reports::generate_quarterly_report(pool, year, quarter),
+            Some((year, 'H', half)) => reports::generate_half_year_report(pool, year, half),
+            _ => return Err(StatusCode::BAD_REQUEST),
+        },
     };
 
-    match report {
-        Ok(r) => Ok(Json(r)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    report.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[derive(Deserialize)]
+struct SchemaParams {
+    #[serde(default = "default_schema_version")]
+    schema_version: u32,
+}
+
+fn default_schema_version() -> u32 {
+    reports::schema::REPORT_SCHEMA_VERSION
+}
+
+/// Render a report in the schema version the caller asked for
+fn versioned<R: reports::schema::VersionedReport>(
+    report: &R,
+    schema: &SchemaParams,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let supported =
+        reports::schema::MIN_REPORT_SCHEMA_VERSION..=reports::schema::REPORT_SCHEMA_VERSION;
+    if !supported.contains(&schema.schema_version) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    reports::schema::render(report, schema.schema_version)
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Split "2024-Q3" or "2024-H1" into year, unit and index, checking ranges
+fn parse_sub_year_period(period: &str) -> Option<(i32, char, u32)> {
+    let (year, rest) = period.split_once('-')?;
+    let year = year
+        .parse::<i32>()
+        .ok()
+        .filter(|y| (1970..=2100).contains(y))?;
+
+    let mut chars = rest.chars();
+    let unit = chars.next()?.to_ascii_uppercase();
+    let index = chars.as_str().parse::<u32>().ok()?;
+
+    match unit {
+        'Q' if (1..=4).contains(&index) => Some((year, unit, index)),
+        'H' if (1..=2).contains(&index) => Some((year, unit, index)),
+        _ => None,
     }
 }
 
@@ -214,52 +1069,196 @@ struct MonthlyReportParams {
 async fn get_monthly_report_handler(
     State(state): State<Arc<AppState>>,
     Query(params): Query<MonthlyReportParams>,
-) -> Result<Json<reports::Report>, StatusCode> {
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
     if !(1..=12).contains(&params.month) {
         return Err(StatusCode::BAD_REQUEST);
     }
 
     match reports::generate_monthly_report(&state.pool, params.year, params.month) {
-        Ok(r) => Ok(Json(r)),
+        Ok(r) => versioned(&r, &schema),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
+#[derive(Deserialize)]
+struct TimelineParams {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    offset: Option<i64>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+    /// "session" groups the page into listening sessions
+    group: Option<String>,
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+}
+
+fn default_session_gap() -> i64 {
+    reports::sessions::DEFAULT_SESSION_GAP_MINUTES
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum TimelineResponse {
+    Scrobbles(Vec<TimelineEntry>),
+    Sessions(Vec<TimelineSession>),
+}
+
+/// Block of consecutive scrobbles, e.g. "Tuesday evening — 14 tracks, 3 artists"
+#[derive(Serialize)]
+pub struct TimelineSession {
+    label: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    duration_minutes: i64,
+    track_count: usize,
+    artist_count: usize,
+    top_artist: Option<String>,
+    scrobbles: Vec<TimelineEntry>,
+}
+
+/// Timeline scrobble with its notes. Day notes are attached to the first
+/// (most recent) scrobble of that day in the page.
+#[derive(Serialize)]
+pub struct TimelineEntry {
+    #[serde(flatten)]
+    scrobble: Scrobble,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<Note>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    day_notes: Vec<Note>,
+}
+
 async fn get_timeline_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<PaginationParams>,
-) -> Result<Json<Vec<crate::models::Scrobble>>, StatusCode> {
-    match crate::db::get_scrobbles(&state.pool, params.limit, params.offset) {
-        Ok(scrobbles) => Ok(Json(scrobbles)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Query(params): Query<TimelineParams>,
+) -> Result<Json<TimelineResponse>, StatusCode> {
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let scrobbles = crate::db::get_scrobbles(&state.pool, params.limit, params.offset)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let entries = attach_notes(&state.pool, scrobbles, timezone)?;
+
+    match params.group.as_deref() {
+        None => Ok(Json(TimelineResponse::Scrobbles(entries))),
+        Some("session") => {
+            let sessions = reports::sessions::detect_sessions(
+                entries,
+                |entry| entry.scrobble.timestamp,
+                params.gap_minutes.max(1),
+            )
+            .into_iter()
+            .map(|session| summarize_session(session, timezone))
+            .collect();
+            Ok(Json(TimelineResponse::Sessions(sessions)))
+        }
+        Some(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn summarize_session(scrobbles: Vec<TimelineEntry>, timezone: chrono_tz::Tz) -> TimelineSession {
+    let timestamps = scrobbles.iter().map(|e| e.scrobble.timestamp);
+    let start = timestamps.clone().min().unwrap_or_default();
+    let end = timestamps.max().unwrap_or_default();
+
+    let mut artist_counts: std::collections::HashMap<&str, usize> =
+        std::collections::HashMap::new();
+    for entry in &scrobbles {
+        *artist_counts
+            .entry(entry.scrobble.artist.as_str())
+            .or_insert(0) += 1;
+    }
+    let top_artist = artist_counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(artist, _)| artist.to_string());
+    let artist_count = artist_counts.len();
+
+    TimelineSession {
+        label: reports::sessions::session_label(start, timezone),
+        start,
+        end,
+        duration_minutes: (end - start).num_minutes(),
+        track_count: scrobbles.len(),
+        artist_count,
+        top_artist,
+        scrobbles,
     }
 }
 
+fn attach_notes(
+    pool: &DbPool,
+    scrobbles: Vec<Scrobble>,
+    timezone: chrono_tz::Tz,
+) -> Result<Vec<TimelineEntry>, StatusCode> {
+    let local_date = |scrobble: &Scrobble| scrobble.timestamp.with_timezone(&timezone).date_naive();
+
+    let ids: Vec<i64> = scrobbles.iter().filter_map(|s| s.id).collect();
+    let mut dates: Vec<chrono::NaiveDate> = scrobbles.iter().map(local_date).collect();
+    dates.dedup();
+
+    let notes = crate::db::get_notes_for(pool, &ids, &dates)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut seen_dates = std::collections::HashSet::new();
+    let entries = scrobbles
+        .into_iter()
+        .map(|scrobble| {
+            let date = local_date(&scrobble);
+            let day_notes = if seen_dates.insert(date) {
+                notes
+                    .iter()
+                    .filter(|n| n.date == Some(date))
+                    .cloned()
+                    .collect()
+            } else {
+                vec![]
+            };
+            let scrobble_notes = notes
+                .iter()
+                .filter(|n| n.scrobble_id.is_some() && n.scrobble_id == scrobble.id)
+                .cloned()
+                .collect();
+
+            TimelineEntry {
+                scrobble,
+                notes: scrobble_notes,
+                day_notes,
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+// Note handlers
 #[derive(Deserialize)]
-struct HeatmapParams {
-    start: Option<String>,
-    end: Option<String>,
-    #[serde(default = "default_timezone")]
-    timezone: String,
-    #[serde(default)]
-    normalize: bool,
+pub struct CreateNoteParams {
+    scrobble_id: Option<i64>,
+    date: Option<chrono::NaiveDate>,
+    body: String,
 }
 
-fn default_timezone() -> String {
-    "UTC".to_string()
+#[derive(Deserialize)]
+pub struct UpdateNoteParams {
+    body: String,
 }
 
-async fn get_heatmap_handler(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<HeatmapParams>,
-) -> Result<Json<reports::heatmap::HeatmapReport>, StatusCode> {
-    // Parse timezone
-    let timezone = params
-        .timezone
-        .parse::<chrono_tz::Tz>()
-        .unwrap_or(chrono_tz::UTC);
+#[derive(Deserialize)]
+struct NotesParams {
+    start: Option<String>,
+    end: Option<String>,
+}
 
-    // Parse date strings
+async fn get_notes_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<NotesParams>,
+) -> Result<Json<Vec<Note>>, StatusCode> {
     let start = params
         .start
         .as_deref()
@@ -272,688 +1271,701 @@ async fn get_heatmap_handler(
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
         .map(|dt| dt.with_timezone(&Utc));
 
-    match reports::heatmap::generate_heatmap(&state.pool, start, end, timezone, params.normalize) {
-        Ok(report) => Ok(Json(report)),
+    match crate::db::get_notes(&state.pool, start, end) {
+        Ok(notes) => Ok(Json(notes)),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-#[derive(Deserialize)]
-struct NoveltyParams {
-    start: Option<String>,
-    end: Option<String>,
-    #[serde(default = "default_granularity")]
-    granularity: String,
-}
+async fn create_note_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateNoteParams>,
+) -> Result<Json<Note>, StatusCode> {
+    if params.body.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-fn default_granularity() -> String {
-    "week".to_string()
+    let mut note = match (params.scrobble_id, params.date) {
+        (Some(scrobble_id), None) => {
+            let exists = crate::db::scrobble_exists(&state.pool, scrobble_id)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if !exists {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            Note::for_scrobble(scrobble_id, params.body)
+        }
+        (None, Some(date)) => Note::for_day(date, params.body),
+        // A note targets exactly one scrobble or one day
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match crate::db::insert_note(&state.pool, &note) {
+        Ok(id) => {
+            note.id = Some(id);
+            Ok(Json(note))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create note: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-async fn get_novelty_handler(
+async fn get_note_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<NoveltyParams>,
-) -> Result<Json<reports::novelty::NoveltyReport>, StatusCode> {
-    // Parse granularity
-    let granularity = match params.granularity.to_lowercase().as_str() {
-        "day" => reports::novelty::Granularity::Day,
-        "week" => reports::novelty::Granularity::Week,
-        "month" => reports::novelty::Granularity::Month,
-        "year" => reports::novelty::Granularity::Year,
-        _ => reports::novelty::Granularity::Week,
-    };
+    Path(id): Path<i64>,
+) -> Result<Json<Note>, StatusCode> {
+    match crate::db::get_note(&state.pool, id) {
+        Ok(Some(note)) => Ok(Json(note)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
 
-    // Parse date strings
-    let start = params
-        .start
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
+async fn update_note_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(params): Json<UpdateNoteParams>,
+) -> Result<Json<Note>, StatusCode> {
+    if params.body.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    let end = params
-        .end
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
+    match crate::db::update_note(&state.pool, id, &params.body) {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 
-    match reports::novelty::generate_novelty_report(&state.pool, start, end, granularity) {
-        Ok(report) => Ok(Json(report)),
+    match crate::db::get_note(&state.pool, id) {
+        Ok(Some(note)) => Ok(Json(note)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
+async fn delete_note_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_note(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// Annotation handlers
 #[derive(Deserialize)]
-struct TransitionsParams {
-    start: Option<String>,
-    end: Option<String>,
-    #[serde(default = "default_gap_minutes")]
-    gap_minutes: i64,
-    #[serde(default = "default_min_count")]
-    min_count: i64,
+pub struct AnnotationParams {
+    artist: Option<String>,
+    date: chrono::NaiveDate,
+    end_date: Option<chrono::NaiveDate>,
     #[serde(default)]
-    include_self_transitions: bool,
+    kind: AnnotationKind,
+    label: String,
 }
 
-fn default_gap_minutes() -> i64 {
-    45
+impl AnnotationParams {
+    /// `None` for a blank label or a span ending before it starts
+    fn into_annotation(self) -> Option<Annotation> {
+        let label = self.label.trim().to_string();
+        if label.is_empty() || self.end_date.is_some_and(|end| end < self.date) {
+            return None;
+        }
+        let artist = self
+            .artist
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+        Some(Annotation::new(
+            artist,
+            self.date,
+            self.end_date,
+            self.kind,
+            label,
+        ))
+    }
 }
 
-fn default_min_count() -> i64 {
-    2
+#[derive(Deserialize)]
+struct AnnotationsParams {
+    artist: Option<String>,
+    start: Option<chrono::NaiveDate>,
+    end: Option<chrono::NaiveDate>,
 }
 
-async fn get_transitions_handler(
+async fn get_annotations_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<TransitionsParams>,
-) -> Result<Json<reports::transitions::TransitionsReport>, StatusCode> {
-    // Parse date strings
-    let start = params
-        .start
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
-
-    let end = params
-        .end
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
-
-    match reports::transitions::generate_transitions_report(
+    Query(params): Query<AnnotationsParams>,
+) -> Result<Json<Vec<Annotation>>, StatusCode> {
+    match crate::db::get_annotations(
         &state.pool,
-        start,
-        end,
-        params.gap_minutes,
-        params.min_count,
-        params.include_self_transitions,
+        params.artist.as_deref(),
+        params.start,
+        params.end,
     ) {
-        Ok(report) => Ok(Json(report)),
+        Ok(annotations) => Ok(Json(annotations)),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-#[derive(Deserialize)]
-struct DiversityParams {
-    #[serde(default = "default_granularity")]
-    granularity: String,
-    start: Option<String>,
-    end: Option<String>,
+async fn create_annotation_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<AnnotationParams>,
+) -> Result<Json<Annotation>, StatusCode> {
+    let mut annotation = params.into_annotation().ok_or(StatusCode::BAD_REQUEST)?;
+
+    match crate::db::insert_annotation(&state.pool, &annotation) {
+        Ok(id) => {
+            annotation.id = Some(id);
+            Ok(Json(annotation))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create annotation: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-async fn get_diversity_handler(
+async fn get_annotation_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<DiversityParams>,
-) -> Result<Json<reports::diversity::DiversityReport>, StatusCode> {
-    let start = params
-        .start
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
+    Path(id): Path<i64>,
+) -> Result<Json<Annotation>, StatusCode> {
+    match crate::db::get_annotation(&state.pool, id) {
+        Ok(Some(annotation)) => Ok(Json(annotation)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
 
-    let end = params
-        .end
-        .as_deref()
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
+async fn update_annotation_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(params): Json<AnnotationParams>,
+) -> Result<Json<Annotation>, StatusCode> {
+    let annotation = params.into_annotation().ok_or(StatusCode::BAD_REQUEST)?;
 
-    let granularity = match params.granularity.as_str() {
-        "day" => reports::diversity::Granularity::Day,
-        "week" => reports::diversity::Granularity::Week,
-        "month" => reports::diversity::Granularity::Month,
-        _ => reports::diversity::Granularity::Week,
-    };
+    match crate::db::update_annotation(&state.pool, id, &annotation) {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 
-    match reports::diversity::generate_diversity_report(&state.pool, start, end, granularity) {
-        Ok(report) => Ok(Json(report)),
+    match crate::db::get_annotation(&state.pool, id) {
+        Ok(Some(annotation)) => Ok(Json(annotation)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-async fn get_yearly_handler(
+async fn delete_annotation_handler(
     State(state): State<Arc<AppState>>,
-    Path(year): Path<i32>,
-) -> Result<Json<reports::yearly::YearlyReport>, StatusCode> {
-    match reports::yearly::generate_yearly_report(&state.pool, year) {
-        Ok(report) => Ok(Json(report)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_annotation(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
+// Ignore rule handlers
 #[derive(Deserialize)]
-struct StatsUiParams {
-    #[serde(default = "default_period")]
-    period: String,
-    start: Option<String>,
-    end: Option<String>,
+pub struct IgnoreRuleParams {
+    source: Option<String>,
+    artist: Option<String>,
+    track: Option<String>,
 }
 
-fn default_period() -> String {
-    "alltime".to_string()
+impl IgnoreRuleParams {
+    /// Blank fields mean "any"; a rule must name an artist or a track
+    fn into_rule(self) -> Option<IgnoreRule> {
+        let clean = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let rule = IgnoreRule::new(clean(self.source), clean(self.artist), clean(self.track));
+        (rule.artist.is_some() || rule.track.is_some()).then_some(rule)
+    }
 }
 
-#[derive(Serialize)]
-struct ArtistWithImage {
-    name: String,
-    count: i64,
-    image_url: Option<String>,
+async fn get_ignore_rules_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<IgnoreRule>>, StatusCode> {
+    match crate::db::get_ignore_rules(&state.pool, None) {
+        Ok(rules) => Ok(Json(rules)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 }
 
-#[derive(Serialize)]
-struct TrackWithImage {
-    artist: String,
-    track: String,
-    count: i64,
-    image_url: Option<String>,
-}
+async fn create_ignore_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<IgnoreRuleParams>,
+) -> Result<Json<IgnoreRule>, StatusCode> {
+    let mut rule = params.into_rule().ok_or(StatusCode::BAD_REQUEST)?;
 
-#[derive(Serialize)]
-struct AlbumWithImage {
-    artist: String,
-    album: String,
-    count: i64,
-    image_url: Option<String>,
+    match crate::db::insert_ignore_rule(&state.pool, &rule) {
+        Ok(id) => {
+            rule.id = Some(id);
+            Ok(Json(rule))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create ignore rule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-#[derive(Serialize)]
-struct PulsePoint {
-    day: String,
-    count: i64,
+async fn get_ignore_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<IgnoreRule>, StatusCode> {
+    match crate::db::get_ignore_rule(&state.pool, id) {
+        Ok(Some(rule)) => Ok(Json(rule)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 }
 
-async fn get_stats_ui_handler(
+async fn update_ignore_rule_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<StatsUiParams>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Calculate date range based on period
-    let (start_date, end_date) = match params.period.as_str() {
-        "today" => get_today_range(),
-        "week" => get_week_range(),
-        "month" => get_month_range(),
-        "year" => get_year_range(),
-        "custom" => parse_custom_range(params.start.as_deref(), params.end.as_deref())
-            .ok_or(StatusCode::BAD_REQUEST)?,
-        "alltime" => (None, None),
-        _ => (None, None),
-    };
-
-    // Fetch stats from database
-    let top_artists = crate::db::get_top_artists(&state.pool, 15, start_date, end_date)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let top_tracks = crate::db::get_top_tracks(&state.pool, 15, start_date, end_date)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let top_albums = crate::db::get_top_albums(&state.pool, 15, start_date, end_date)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let period_count = crate::db::get_scrobbles_count_in_range(&state.pool, start_date, end_date)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Fetch images for artists
-    let mut artists_with_images = Vec::new();
-    for (name, count) in top_artists {
-        let mut image_url: Option<String> = state
-            .image_service
-            .get_image_url(ImageRequest::artist(name.clone()))
-            .await
-            .ok()
-            .flatten();
+    Path(id): Path<i64>,
+    Json(params): Json<IgnoreRuleParams>,
+) -> Result<Json<IgnoreRule>, StatusCode> {
+    let rule = params.into_rule().ok_or(StatusCode::BAD_REQUEST)?;
 
-        // fallback: use top album cover for this artist
-        if image_url.is_none()
-            && let Ok(Some(album)) = crate::db::get_top_album_for_artist(&state.pool, &name)
-        {
-            image_url = state
-                .image_service
-                .get_image_url(ImageRequest::album(name.clone(), album))
-                .await
-                .ok()
-                .flatten();
-        }
-        artists_with_images.push(ArtistWithImage {
-            name,
-            count,
-            image_url,
-        });
+    match crate::db::update_ignore_rule(&state.pool, id, &rule) {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 
-    // Fetch images for tracks (try track image first, then artist, then album)
-    let mut tracks_with_images = Vec::new();
-    for (artist, track, count) in top_tracks {
-        let mut image_url: Option<String> = state
-            .image_service
-            .get_image_url(ImageRequest::track(artist.clone(), track.clone()))
-            .await
-            .ok()
-            .flatten();
-
-        // fallback 1: try artist image
-        if image_url.is_none() {
-            image_url = state
-                .image_service
-                .get_image_url(ImageRequest::artist(artist.clone()))
-                .await
-                .ok()
-                .flatten();
-        }
-
-        // fallback 2: try the most common album for this track
-        if image_url.is_none()
-            && let Ok(Some(album)) = crate::db::get_album_for_track(&state.pool, &artist, &track)
-        {
-            image_url = state
-                .image_service
-                .get_image_url(ImageRequest::album(artist.clone(), album))
-                .await
-                .ok()
-                .flatten();
-        }
-        tracks_with_images.push(TrackWithImage {
-            artist,
-            track,
-            count,
-            image_url,
-        });
+    match crate::db::get_ignore_rule(&state.pool, id) {
+        Ok(Some(rule)) => Ok(Json(rule)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+}
 
-    // Fetch images for albums
-    let mut albums_with_images = Vec::new();
-    for (artist, album, count) in top_albums {
-        let image_url: Option<String> = state
-            .image_service
-            .get_image_url(ImageRequest::album(artist.clone(), album.clone()))
-            .await
-            .ok()
-            .flatten();
-        albums_with_images.push(AlbumWithImage {
-            artist,
-            album,
-            count,
-            image_url,
-        });
+async fn delete_ignore_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_ignore_rule(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
-
-    Ok(Json(serde_json::json!({
-        "period": params.period,
-        "period_scrobbles": period_count,
-        "top_artists": artists_with_images,
-        "top_tracks": tracks_with_images,
-        "top_albums": albums_with_images,
-    })))
 }
 
+// Alert rule handlers
 #[derive(Deserialize)]
-struct PulseParams {
-    #[serde(default = "default_period")]
-    period: String,
-    start: Option<String>,
-    end: Option<String>,
+pub struct CreateAlertRuleParams {
+    kind: AlertKind,
+    threshold_hours: i64,
 }
 
-async fn get_pulse_handler(
+async fn get_alert_rules_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<PulseParams>,
-) -> Result<Json<Vec<PulsePoint>>, StatusCode> {
-    let (start_date, end_date) = match params.period.as_str() {
-        "today" => get_today_range(),
-        "week" => get_week_range(),
-        "month" => get_month_range(),
-        "year" => get_year_range(),
-        "custom" => parse_custom_range(params.start.as_deref(), params.end.as_deref())
-            .ok_or(StatusCode::BAD_REQUEST)?,
-        "alltime" => (None, None),
-        _ => (None, None),
-    };
+) -> Result<Json<Vec<AlertRule>>, StatusCode> {
+    match crate::db::get_alert_rules(&state.pool) {
+        Ok(rules) => Ok(Json(rules)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
 
-    let data = crate::db::get_scrobbles_per_day(&state.pool, start_date, end_date)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+async fn create_alert_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateAlertRuleParams>,
+) -> Result<Json<AlertRule>, StatusCode> {
+    // Between an hour and a year
+    if !(1..=8760).contains(&params.threshold_hours) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    Ok(Json(
-        data.into_iter()
-            .map(|(day, count)| PulsePoint { day, count })
-            .collect(),
-    ))
+    let mut rule = AlertRule::new(params.kind, params.threshold_hours);
+    match crate::db::insert_alert_rule(&state.pool, &rule) {
+        Ok(id) => {
+            rule.id = Some(id);
+            Ok(Json(rule))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create alert rule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-fn get_today_range() -> DateRange {
-    let now = Utc::now();
-    let today_start = now
-        .date_naive()
-        .and_hms_opt(0, 0, 0)
-        .and_then(|dt| dt.and_local_timezone(Utc).single());
-    (today_start, Some(now))
+async fn delete_alert_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_alert_rule(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
 }
 
-fn get_week_range() -> DateRange {
-    let now = Utc::now();
-    let week_ago = now - Duration::days(7);
-    (Some(week_ago), Some(now))
+// Achievement handlers
+#[derive(Deserialize)]
+pub struct AchievementsParams {
+    year: Option<i32>,
 }
 
-fn get_month_range() -> DateRange {
-    let now = Utc::now();
-    let month_start = now
-        .date_naive()
-        .with_day(1)
-        .and_then(|d| d.and_hms_opt(0, 0, 0))
-        .and_then(|dt| dt.and_local_timezone(Utc).single());
-    (month_start, Some(now))
+async fn get_achievements_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AchievementsParams>,
+) -> Result<Json<Vec<Achievement>>, StatusCode> {
+    match crate::db::get_achievements(&state.pool, params.year) {
+        Ok(achievements) => Ok(Json(achievements)),
+        Err(e) => {
+            tracing::error!("Failed to get achievements: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-fn get_year_range() -> DateRange {
-    let now = Utc::now();
-    let year_start = now
-        .date_naive()
-        .with_month(1)
-        .and_then(|d| d.with_day(1))
-        .and_then(|d| d.and_hms_opt(0, 0, 0))
-        .and_then(|dt| dt.and_local_timezone(Utc).single());
-    (year_start, Some(now))
+// Milestone rule handlers
+#[derive(Deserialize)]
+pub struct CreateMilestoneRuleParams {
+    title: String,
+    #[serde(default)]
+    description: String,
+    metric: MilestoneMetric,
+    threshold: f64,
+    #[serde(default = "default_milestone_icon")]
+    icon: reports::yearly::MilestoneIcon,
 }
 
-fn parse_custom_range(start: Option<&str>, end: Option<&str>) -> Option<DateRange> {
-    let start_dt = start
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
-    let end_dt = end
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
+fn default_milestone_icon() -> reports::yearly::MilestoneIcon {
+    reports::yearly::MilestoneIcon::Trophy
+}
 
-    match (start_dt, end_dt) {
-        (Some(s), Some(e)) => Some((Some(s), Some(e))),
-        _ => None,
+async fn get_milestone_rules_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<MilestoneRule>>, StatusCode> {
+    match crate::db::get_milestone_rules(&state.pool) {
+        Ok(rules) => Ok(Json(rules)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-// Sync configuration handlers
-#[derive(Deserialize)]
-pub struct CreateSyncConfigParams {
-    source: String,
-    username: String,
-    api_key: Option<String>,
-    token: Option<String>,
-    #[serde(default = "default_sync_interval")]
-    sync_interval_minutes: i32,
-    #[serde(default = "default_enabled")]
-    enabled: bool,
-}
+async fn create_milestone_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateMilestoneRuleParams>,
+) -> Result<Json<MilestoneRule>, StatusCode> {
+    let title = params.title.trim();
+    if title.is_empty() || !params.threshold.is_finite() || params.threshold < 0.0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-fn default_sync_interval() -> i32 {
-    60 // Default to 60 minutes
+    let mut rule = MilestoneRule::new(
+        title.to_string(),
+        params.description.trim().to_string(),
+        params.metric,
+        params.threshold,
+        params.icon,
+    );
+    match crate::db::insert_milestone_rule(&state.pool, &rule) {
+        Ok(id) => {
+            rule.id = Some(id);
+            Ok(Json(rule))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create milestone rule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
-fn default_enabled() -> bool {
-    true
+async fn delete_milestone_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_milestone_rule(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
 }
 
-#[derive(Serialize)]
-pub struct SyncConfigResponse {
-    success: bool,
-    message: String,
-    config: Option<SyncConfig>,
+// Media type rule handlers
+#[derive(Deserialize)]
+pub struct MediaTypeRuleParams {
+    artist: Option<String>,
+    album: Option<String>,
+    media_type: MediaType,
 }
 
 #[derive(Serialize)]
-pub struct SyncTriggerResponse {
-    success: bool,
-    count: usize,
-    message: String,
+pub struct MediaTypeRuleResponse {
+    #[serde(flatten)]
+    rule: MediaTypeRule,
+    /// Existing scrobbles the rule reclassified
+    reclassified: usize,
 }
 
-async fn create_sync_config_handler(
+async fn get_media_type_rules_handler(
     State(state): State<Arc<AppState>>,
-    Json(params): Json<CreateSyncConfigParams>,
-) -> Result<Json<SyncConfigResponse>, StatusCode> {
-    let mut config = SyncConfig::new(
-        params.source.clone(),
-        params.username.clone(),
-        params.sync_interval_minutes,
-    )
-    .with_enabled(params.enabled);
-
-    if let Some(api_key) = params.api_key {
-        config = config.with_api_key(api_key);
+) -> Result<Json<Vec<MediaTypeRule>>, StatusCode> {
+    match crate::db::get_media_type_rules(&state.pool) {
+        Ok(rules) => Ok(Json(rules)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+}
 
-    if let Some(token) = params.token {
-        config = config.with_token(token);
+async fn create_media_type_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<MediaTypeRuleParams>,
+) -> Result<Json<MediaTypeRuleResponse>, StatusCode> {
+    let clean = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let mut rule = MediaTypeRule::new(clean(params.artist), clean(params.album), params.media_type);
+    if rule.artist.is_none() && rule.album.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
     }
 
-    match crate::db::insert_sync_config(&state.pool, &config) {
-        Ok(_) => Ok(Json(SyncConfigResponse {
-            success: true,
-            message: format!(
-                "Sync configuration created for {} user {}",
-                params.source, params.username
-            ),
-            config: Some(config),
-        })),
+    match crate::db::insert_media_type_rule(&state.pool, &rule) {
+        Ok((id, reclassified)) => {
+            rule.id = Some(id);
+            Ok(Json(MediaTypeRuleResponse { rule, reclassified }))
+        }
         Err(e) => {
-            tracing::error!("Failed to create sync config: {}", e);
+            tracing::error!("Failed to create media type rule: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-async fn get_sync_configs_handler(
+async fn delete_media_type_rule_handler(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<SyncConfig>>, StatusCode> {
-    match crate::db::get_all_sync_configs(&state.pool) {
-        Ok(configs) => Ok(Json(configs)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Path(id): Path<i64>,
+) -> StatusCode {
+    match crate::db::delete_media_type_rule(&state.pool, id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
-async fn get_sync_config_handler(
+// Conflict handlers
+#[derive(Deserialize)]
+pub struct ResolveConflictParams {
+    /// Scrobble whose artist, album and track become canonical
+    keep_id: i64,
+    /// Scrobble rewritten to match it
+    other_id: i64,
+}
+
+async fn get_conflicts_handler(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
-) -> Result<Json<SyncConfig>, StatusCode> {
-    match crate::db::get_sync_config(&state.pool, id) {
-        Ok(Some(config)) => Ok(Json(config)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Vec<Conflict>>, StatusCode> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500) as usize;
+    let offset = params.offset.unwrap_or(0).max(0) as usize;
+
+    match conflicts::find_conflicts(&state.pool, limit, offset) {
+        Ok(conflicts) => Ok(Json(conflicts)),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-async fn update_sync_config_handler(
+async fn resolve_conflict_handler(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
-    Json(params): Json<CreateSyncConfigParams>,
-) -> Result<Json<SyncConfigResponse>, StatusCode> {
-    // Verify the config exists
-    match crate::db::get_sync_config(&state.pool, id) {
-        Ok(Some(_)) => {
-            let mut config = SyncConfig::new(
-                params.source.clone(),
-                params.username.clone(),
-                params.sync_interval_minutes,
-            )
-            .with_enabled(params.enabled);
+    Json(params): Json<ResolveConflictParams>,
+) -> Result<Json<crate::models::Scrobble>, StatusCode> {
+    let load = |id| match crate::db::get_scrobble(&state.pool, id) {
+        Ok(Some(scrobble)) => Ok(scrobble),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let keep = load(params.keep_id)?;
+    let other = load(params.other_id)?;
 
-            if let Some(api_key) = params.api_key {
-                config = config.with_api_key(api_key);
-            }
+    if !conflicts::is_conflict(&keep, &other) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-            if let Some(token) = params.token {
-                config = config.with_token(token);
-            }
-
-            match crate::db::insert_sync_config(&state.pool, &config) {
-                Ok(_) => Ok(Json(SyncConfigResponse {
-                    success: true,
-                    message: format!(
-                        "Sync configuration updated for {} user {}",
-                        params.source, params.username
-                    ),
-                    config: Some(config),
-                })),
-                Err(e) => {
-                    tracing::error!("Failed to update sync config: {}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            }
-        }
+    match crate::db::apply_canonical_metadata(&state.pool, &keep, &other) {
+        Ok(Some(updated)) => Ok(Json(updated)),
         Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => {
+            tracing::error!("Failed to resolve conflict: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-async fn delete_sync_config_handler(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
-) -> Result<Json<SyncConfigResponse>, StatusCode> {
-    match crate::db::delete_sync_config(&state.pool, id) {
-        Ok(_) => Ok(Json(SyncConfigResponse {
-            success: true,
-            message: "Sync configuration deleted".to_string(),
-            config: None,
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+#[derive(Deserialize)]
+struct CalendarParams {
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
 }
 
-async fn trigger_sync_handler(
+async fn get_calendar_handler(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
-) -> Result<Json<SyncTriggerResponse>, StatusCode> {
-    match state.sync_scheduler.trigger_sync(id).await {
-        Ok(count) => Ok(Json(SyncTriggerResponse {
-            success: true,
-            count,
-            message: format!("Successfully synced {} new scrobbles", count),
-        })),
-        Err(e) => Ok(Json(SyncTriggerResponse {
-            success: false,
-            count: 0,
-            message: format!("Sync failed: {}", e),
-        })),
+    Path((year, month)): Path<(i32, u32)>,
+    Query(params): Query<CalendarParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !(1970..=2100).contains(&year) || !(1..=12).contains(&month) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match reports::calendar::generate_calendar_month(&state.pool, year, month, timezone) {
+        Ok(calendar) => versioned(&calendar, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
 #[derive(Deserialize)]
-struct ExportParams {
-    #[serde(default = "default_export_format")]
-    format: String,
+struct DigestParams {
+    #[serde(default = "default_digest_days")]
+    days: i64,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
 }
 
-fn default_export_format() -> String {
-    "json".to_string()
+fn default_digest_days() -> i64 {
+    7
 }
 
-async fn export_handler(
+/// The last finished days against the ones before, from the daily summaries
+async fn get_digest_handler(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<ExportParams>,
-) -> Result<axum::response::Response, StatusCode> {
-    use axum::body::Body;
-    use axum::http::header;
-    use axum::response::Response;
-
-    match crate::db::get_scrobbles(&state.pool, Some(1000000), Some(0)) {
-        Ok(scrobbles) => {
-            let (content_type, body) = match params.format.as_str() {
-                "json" => {
-                    let json = serde_json::to_string_pretty(&scrobbles)
-                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-                    ("application/json", json)
-                }
-                "csv" => {
-                    let mut csv = String::from("timestamp,artist,album,track,source\n");
-                    for scrobble in scrobbles {
-                        let album = scrobble.album.unwrap_or_default();
-                        csv.push_str(&format!(
-                            "{},{},{},{},{}\n",
-                            scrobble.timestamp.to_rfc3339(),
-                            escape_csv(&scrobble.artist),
-                            escape_csv(&album),
-                            escape_csv(&scrobble.track),
-                            scrobble.source
-                        ));
-                    }
-                    ("text/csv", csv)
-                }
-                _ => return Err(StatusCode::BAD_REQUEST),
-            };
-
-            let filename = format!(
-                "footprints_export_{}.{}",
-                Utc::now().format("%Y-%m-%d"),
-                params.format
-            );
+    Query(params): Query<DigestParams>,
+) -> Result<Json<crate::summaries::Digest>, StatusCode> {
+    if !(1..=366).contains(&params.days) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-            Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, content_type)
-                .header(
-                    header::CONTENT_DISPOSITION,
-                    format!("attachment; filename=\"{}\"", filename),
-                )
-                .body(Body::from(body))
-                .unwrap())
+    match crate::summaries::digest(&state.pool, Utc::now(), params.days, timezone) {
+        Ok(digest) => Ok(Json(digest)),
+        Err(e) => {
+            tracing::error!("Failed to build digest: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
-fn escape_csv(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') {
-        format!("\"{}\"", s.replace('"', "\"\""))
-    } else {
-        s.to_string()
+#[derive(Serialize)]
+struct RebuildSummariesResponse {
+    days: usize,
+}
+
+/// Summarize every finished day again, e.g. after importing older history
+async fn rebuild_summaries_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RebuildSummariesResponse>, StatusCode> {
+    match crate::summaries::rebuild(&state.pool, Utc::now()) {
+        Ok(days) => Ok(Json(RebuildSummariesResponse { days })),
+        Err(e) => {
+            tracing::error!("Failed to rebuild daily summaries: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-// Entity detail handlers
 #[derive(Deserialize)]
-struct EntityParams {
+struct HeatmapParams {
     start: Option<String>,
     end: Option<String>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+    #[serde(default)]
+    normalize: bool,
+    /// The instance's heatmap normalization setting when omitted
+    normalize_by: Option<reports::heatmap::Normalization>,
+    artist: Option<String>,
+    genre: Option<String>,
+    /// Language tag for weekday names, English when unsupported
+    locale: Option<String>,
 }
 
-#[derive(Serialize)]
-struct ArtistDetail {
-    stats: serde_json::Value,
-    top_tracks: Vec<TrackItem>,
-    top_albums: Vec<AlbumItem>,
-    scrobbles_over_time: Vec<TimePoint>,
-    image_url: Option<String>,
-}
+async fn get_heatmap_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HeatmapParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let preferences = preferences(&state);
+    let timezone = preferences
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let normalization = params
+        .normalize_by
+        .unwrap_or(preferences.heatmap_normalization);
 
-#[derive(Serialize)]
-struct TrackItem {
-    name: String,
-    count: i64,
-}
+    // Parse date strings
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
 
-#[derive(Serialize)]
-struct AlbumItem {
-    name: String,
-    count: i64,
-    image_url: Option<String>,
-}
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
 
-#[derive(Serialize)]
-struct TimePoint {
-    date: String,
-    count: i64,
-}
+    let filter = crate::db::ScrobbleFilter {
+        artist: params.artist.filter(|a| !a.trim().is_empty()),
+        genre: params.genre.filter(|g| !g.trim().is_empty()),
+    };
 
-#[derive(Serialize)]
-struct AlbumDetail {
-    stats: serde_json::Value,
-    tracks: Vec<TrackItem>,
-    scrobbles_over_time: Vec<TimePoint>,
-    image_url: Option<String>,
+    match reports::heatmap::generate_heatmap(
+        &state.pool,
+        start,
+        end,
+        timezone,
+        params.normalize.then_some(normalization),
+        &filter,
+        reports::locale::Locale::from_tag(params.locale.as_deref().unwrap_or_default()),
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 }
 
-#[derive(Serialize)]
-struct TrackDetail {
-    stats: serde_json::Value,
-    scrobbles_over_time: Vec<TimePoint>,
-    image_url: Option<String>,
+#[derive(Deserialize)]
+struct DayPartsParams {
+    start: Option<String>,
+    end: Option<String>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+    /// Comma-separated name:start_hour pairs, e.g. "morning:6,work:9,night:22"
+    parts: Option<String>,
+    artist: Option<String>,
+    genre: Option<String>,
 }
 
-async fn get_artist_handler(
+async fn get_day_parts_handler(
     State(state): State<Arc<AppState>>,
-    Path(artist): Path<String>,
-    Query(params): Query<EntityParams>,
-) -> Result<Json<ArtistDetail>, StatusCode> {
+    Query(params): Query<DayPartsParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let parts = match params.parts.as_deref() {
+        Some(spec) => reports::heatmap::dayparts::parse_day_parts(spec)
+            .map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => reports::heatmap::dayparts::default_day_parts(),
+    };
+
     let start = params
         .start
         .as_deref()
@@ -966,72 +1978,46 @@ async fn get_artist_handler(
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
         .map(|dt| dt.with_timezone(&Utc));
 
-    let stats = crate::db::get_artist_stats(&state.pool, &artist, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let top_tracks = crate::db::get_artist_top_tracks(&state.pool, &artist, 20, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .into_iter()
-        .map(|(name, count)| TrackItem { name, count })
-        .collect();
-
-    let top_albums_data = crate::db::get_artist_top_albums(&state.pool, &artist, 20, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let filter = crate::db::ScrobbleFilter {
+        artist: params.artist.filter(|a| !a.trim().is_empty()),
+        genre: params.genre.filter(|g| !g.trim().is_empty()),
+    };
 
-    let mut top_albums = Vec::new();
-    for (name, count) in top_albums_data {
-        let image_url = state
-            .image_service
-            .get_image_url(ImageRequest::album(artist.clone(), name.clone()))
-            .await
-            .ok()
-            .flatten();
-        top_albums.push(AlbumItem {
-            name,
-            count,
-            image_url,
-        });
+    match reports::heatmap::dayparts::generate_day_parts(
+        &state.pool,
+        start,
+        end,
+        timezone,
+        &parts,
+        &filter,
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+}
 
-    let scrobbles_over_time =
-        crate::db::get_artist_scrobbles_over_time(&state.pool, &artist, start, end)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            .into_iter()
-            .map(|(date, count)| TimePoint { date, count })
-            .collect();
-
-    let mut image_url = state
-        .image_service
-        .get_image_url(ImageRequest::artist(artist.clone()))
-        .await
-        .ok()
-        .flatten();
+#[derive(Deserialize)]
+struct SleepParams {
+    start: Option<String>,
+    end: Option<String>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+    min_tracks: Option<usize>,
+}
 
-    if image_url.is_none()
-        && let Ok(Some(album)) = crate::db::get_top_album_for_artist(&state.pool, &artist)
-    {
-        image_url = state
-            .image_service
-            .get_image_url(ImageRequest::album(artist.clone(), album))
-            .await
-            .ok()
-            .flatten();
-    }
+type SleepScope = (
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    chrono_tz::Tz,
+    reports::sleep::SleepOptions,
+);
 
-    Ok(Json(ArtistDetail {
-        stats,
-        top_tracks,
-        top_albums,
-        scrobbles_over_time,
-        image_url,
-    }))
-}
+/// Range, timezone and detection options a sleep request asks for
+fn sleep_scope(state: &AppState, params: &SleepParams) -> Result<SleepScope, StatusCode> {
+    let timezone = preferences(state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-async fn get_album_handler(
-    State(state): State<Arc<AppState>>,
-    Path((artist, album)): Path<(String, String)>,
-    Query(params): Query<EntityParams>,
-) -> Result<Json<AlbumDetail>, StatusCode> {
     let start = params
         .start
         .as_deref()
@@ -1044,42 +2030,100 @@ async fn get_album_handler(
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
         .map(|dt| dt.with_timezone(&Utc));
 
-    let stats = crate::db::get_album_stats(&state.pool, &artist, &album, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut options = reports::sleep::SleepOptions::default();
+    if let Some(min_tracks) = params.min_tracks {
+        options.min_tracks = min_tracks.clamp(2, 200);
+    }
 
-    let tracks = crate::db::get_album_tracks(&state.pool, &artist, &album, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .into_iter()
-        .map(|(name, count)| TrackItem { name, count })
-        .collect();
+    Ok((start, end, timezone, options))
+}
 
-    let scrobbles_over_time =
-        crate::db::get_album_scrobbles_over_time(&state.pool, &artist, &album, start, end)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            .into_iter()
-            .map(|(date, count)| TimePoint { date, count })
-            .collect();
+async fn get_sleep_report_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SleepParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (start, end, timezone, options) = sleep_scope(&state, &params)?;
 
-    let image_url = state
-        .image_service
-        .get_image_url(ImageRequest::album(artist.clone(), album.clone()))
-        .await
-        .ok()
-        .flatten();
+    match reports::sleep::generate_sleep_report(&state.pool, start, end, timezone, options) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate sleep report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
 
-    Ok(Json(AlbumDetail {
-        stats,
-        tracks,
-        scrobbles_over_time,
-        image_url,
-    }))
+#[derive(Serialize)]
+struct SleepScanResponse {
+    recorded: usize,
 }
 
-async fn get_track_handler(
+/// Record the overnight runs found in the range for review
+async fn scan_sleep_detections_handler(
     State(state): State<Arc<AppState>>,
-    Path((artist, track)): Path<(String, String)>,
-    Query(params): Query<EntityParams>,
-) -> Result<Json<TrackDetail>, StatusCode> {
+    Query(params): Query<SleepParams>,
+) -> Result<Json<SleepScanResponse>, StatusCode> {
+    let (start, end, timezone, options) = sleep_scope(&state, &params)?;
+
+    match reports::sleep::record_sleep_runs(&state.pool, start, end, timezone, options) {
+        Ok(recorded) => Ok(Json(SleepScanResponse { recorded })),
+        Err(e) => {
+            tracing::error!("Failed to record sleep detections: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn confirm_sleep_detection_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SleepDetection>, StatusCode> {
+    review_sleep_detection(&state.pool, id, DetectionStatus::Confirmed)
+}
+
+async fn dismiss_sleep_detection_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SleepDetection>, StatusCode> {
+    review_sleep_detection(&state.pool, id, DetectionStatus::Dismissed)
+}
+
+fn review_sleep_detection(
+    pool: &DbPool,
+    id: i64,
+    status: DetectionStatus,
+) -> Result<Json<SleepDetection>, StatusCode> {
+    match crate::db::set_sleep_detection_status(pool, id, status) {
+        Ok(Some(detection)) => Ok(Json(detection)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Deserialize)]
+struct NoveltyParams {
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_granularity")]
+    granularity: String,
+}
+
+fn default_granularity() -> String {
+    "week".to_string()
+}
+
+async fn get_novelty_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<NoveltyParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let granularity = params
+        .granularity
+        .parse()
+        .unwrap_or(reports::period::Granularity::Week);
+
+    // Parse date strings
     let start = params
         .start
         .as_deref()
@@ -1092,46 +2136,3139 @@ async fn get_track_handler(
         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
         .map(|dt| dt.with_timezone(&Utc));
 
-    let stats = crate::db::get_track_stats(&state.pool, &artist, &track, start, end)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match reports::novelty::generate_novelty_report(&state.pool, start, end, granularity) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
 
-    let scrobbles_over_time =
-        crate::db::get_track_scrobbles_over_time(&state.pool, &artist, &track, start, end)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            .into_iter()
-            .map(|(date, count)| TimePoint { date, count })
-            .collect();
+#[derive(Deserialize)]
+struct TransitionsParams {
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+    #[serde(default = "default_min_count")]
+    min_count: i64,
+    #[serde(default)]
+    include_self_transitions: bool,
+    #[serde(default)]
+    level: reports::transitions::TransitionLevel,
+    #[serde(default = "default_top_n")]
+    top_n: usize,
+    #[serde(default = "default_transitions_limit")]
+    limit: usize,
+    #[serde(default = "default_max_nodes")]
+    max_nodes: usize,
+}
 
-    let mut image_url = state
-        .image_service
-        .get_image_url(ImageRequest::track(artist.clone(), track.clone()))
-        .await
-        .ok()
-        .flatten();
+fn default_transitions_limit() -> usize {
+    1000
+}
 
-    if image_url.is_none() {
-        image_url = state
-            .image_service
-            .get_image_url(ImageRequest::artist(artist.clone()))
-            .await
-            .ok()
-            .flatten();
+fn default_max_nodes() -> usize {
+    100
+}
+
+fn default_top_n() -> usize {
+    50
+}
+
+fn default_min_count() -> i64 {
+    2
+}
+
+async fn get_transitions_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TransitionsParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    // Parse date strings
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    match reports::transitions::generate_transitions_report(
+        &state.pool,
+        start,
+        end,
+        &reports::transitions::TransitionsOptions {
+            gap_minutes: params.gap_minutes,
+            min_count: params.min_count,
+            include_self_transitions: params.include_self_transitions,
+            level: params.level,
+            top_n: params.top_n.clamp(1, 500),
+            limit: params.limit.clamp(1, 10_000),
+            max_nodes: params.max_nodes.clamp(1, 1000),
+        },
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+}
 
-    if image_url.is_none()
-        && let Ok(Some(album)) = crate::db::get_album_for_track(&state.pool, &artist, &track)
-    {
-        image_url = state
-            .image_service
-            .get_image_url(ImageRequest::album(artist.clone(), album))
-            .await
-            .ok()
-            .flatten();
+#[derive(Deserialize)]
+struct DiversityParams {
+    #[serde(default = "default_granularity")]
+    granularity: String,
+    start: Option<String>,
+    end: Option<String>,
+}
+
+async fn get_diversity_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DiversityParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let granularity = params
+        .granularity
+        .parse()
+        .unwrap_or(reports::period::Granularity::Week);
+
+    match reports::diversity::generate_diversity_report(&state.pool, start, end, granularity) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+}
 
-    Ok(Json(TrackDetail {
-        stats,
-        scrobbles_over_time,
-        image_url,
-    }))
+#[derive(Deserialize)]
+struct SkipParams {
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_skip_min_plays")]
+    min_plays: i64,
+}
+
+fn default_skip_min_plays() -> i64 {
+    5
+}
+
+async fn get_skips_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SkipParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    match reports::skips::generate_skip_report(&state.pool, start, end, params.min_plays) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Deserialize)]
+struct ListeningStylesParams {
+    #[serde(default = "default_styles_granularity")]
+    granularity: String,
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+}
+
+fn default_styles_granularity() -> String {
+    "month".to_string()
+}
+
+async fn get_listening_styles_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListeningStylesParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let granularity = params
+        .granularity
+        .parse()
+        .unwrap_or(reports::period::Granularity::Month);
+
+    match reports::listening_styles::generate_listening_styles_report(
+        &state.pool,
+        start,
+        end,
+        granularity,
+        params.gap_minutes.max(1),
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate listening styles report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ConsistencyParams {
+    #[serde(default = "default_styles_granularity")]
+    granularity: String,
+    start: Option<String>,
+    end: Option<String>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
 }
+
+async fn get_consistency_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ConsistencyParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let granularity = params
+        .granularity
+        .parse()
+        .unwrap_or(reports::period::Granularity::Month);
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match reports::consistency::generate_consistency_report(
+        &state.pool,
+        start,
+        end,
+        granularity,
+        Utc::now(),
+        timezone,
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate consistency report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RecordsParams {
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+}
+
+async fn get_records_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RecordsParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match reports::records::generate_records_report(
+        &state.pool,
+        params.gap_minutes.max(1),
+        timezone,
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate records report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AlbumListensParams {
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+    #[serde(default = "default_album_min_tracks")]
+    min_tracks: usize,
+}
+
+fn default_album_min_tracks() -> usize {
+    reports::album_listens::DEFAULT_MIN_TRACKS
+}
+
+async fn get_album_listens_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AlbumListensParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let options = reports::album_listens::AlbumListensOptions {
+        gap_minutes: params.gap_minutes.max(1),
+        min_tracks: params.min_tracks.max(2),
+        min_coverage: reports::album_listens::DEFAULT_MIN_COVERAGE,
+    };
+
+    match reports::album_listens::generate_album_listens_report(&state.pool, start, end, &options) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate album listens report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TenureParams {
+    #[serde(default = "default_tenure_limit")]
+    limit: i64,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+}
+
+fn default_tenure_limit() -> i64 {
+    reports::tenure::DEFAULT_TENURE_ARTISTS
+}
+
+async fn get_tenure_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TenureParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match reports::tenure::generate_tenure_report(
+        &state.pool,
+        params.limit.clamp(1, 500),
+        Utc::now(),
+        timezone,
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate tenure report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HalfLifeParams {
+    #[serde(default = "default_half_life_min_plays")]
+    min_plays: i64,
+    #[serde(default = "default_half_life_window_days")]
+    window_days: i64,
+    #[serde(default = "default_half_life_limit")]
+    limit: usize,
+}
+
+fn default_half_life_min_plays() -> i64 {
+    reports::half_life::DEFAULT_MIN_PLAYS
+}
+
+fn default_half_life_window_days() -> i64 {
+    reports::half_life::DEFAULT_WINDOW_DAYS
+}
+
+fn default_half_life_limit() -> usize {
+    20
+}
+
+async fn get_half_life_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HalfLifeParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match reports::half_life::generate_half_life_report(
+        &state.pool,
+        params.min_plays.max(2),
+        params.window_days.clamp(1, 365),
+        params.limit.clamp(1, 500),
+        Utc::now(),
+    ) {
+        Ok(report) => versioned(&report, &schema),
+        Err(e) => {
+            tracing::error!("Failed to generate half-life report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RevisitParams {
+    #[serde(default = "default_revisit_limit")]
+    limit: usize,
+    #[serde(default = "default_revisit_min_plays")]
+    min_plays: i64,
+    #[serde(default = "default_revisit_idle_days")]
+    idle_days: i64,
+    #[serde(default = "default_export_format")]
+    format: String,
+}
+
+fn default_revisit_limit() -> usize {
+    25
+}
+
+fn default_revisit_min_plays() -> i64 {
+    recommendations::DEFAULT_MIN_PLAYS
+}
+
+fn default_revisit_idle_days() -> i64 {
+    recommendations::DEFAULT_IDLE_DAYS
+}
+
+async fn revisit_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RevisitParams>,
+) -> Result<axum::response::Response, StatusCode> {
+    use axum::response::IntoResponse;
+
+    let format = match params.format.as_str() {
+        "json" => None,
+        "m3u" => Some(PlaylistFormat::M3u),
+        "xspf" => Some(PlaylistFormat::Xspf),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    if params.min_plays < 1 || !(1..=36500).contains(&params.idle_days) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let playlist = recommendations::revisit(
+        &state.pool,
+        Utc::now(),
+        params.min_plays,
+        params.idle_days,
+        params.limit.clamp(1, 500),
+    )
+    .map_err(|e| {
+        tracing::error!("Failed to build revisit playlist: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    match format {
+        Some(format) => Ok(playlist_download(
+            &playlist.to_playlist(),
+            format,
+            "footprints_revisit",
+        )),
+        None => Ok(Json(playlist).into_response()),
+    }
+}
+
+/// Where an exported playlist's tracks come from
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PlaylistSource {
+    #[default]
+    Top,
+    Revisit,
+}
+
+#[derive(Deserialize)]
+struct PlaylistExportParams {
+    #[serde(default)]
+    source: PlaylistSource,
+    /// For top tracks: today, week, month, year, custom or alltime
+    #[serde(default = "default_period")]
+    period: String,
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_revisit_limit")]
+    limit: usize,
+    #[serde(default)]
+    format: PlaylistFormat,
+}
+
+async fn export_playlist_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PlaylistExportParams>,
+) -> Result<axum::response::Response, StatusCode> {
+    let limit = params.limit.clamp(1, 500);
+    // Unknown periods mean all time, and the name ends up in the file name
+    let period = match params.period.as_str() {
+        "today" | "week" | "month" | "year" | "custom" => params.period.as_str(),
+        _ => "alltime",
+    };
+
+    let playlist = match params.source {
+        PlaylistSource::Top => {
+            let (start, end) = period_range(period, params.start.as_deref(), params.end.as_deref())
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            let tracks =
+                crate::db::get_top_tracks(&state.pool, limit as i64, start, end).map_err(|e| {
+                    tracing::error!("Failed to export top tracks playlist: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            Playlist {
+                title: format!("Top tracks ({})", period),
+                entries: tracks
+                    .into_iter()
+                    .map(|(artist, track, _)| PlaylistEntry {
+                        artist,
+                        track,
+                        album: None,
+                    })
+                    .collect(),
+            }
+        }
+        PlaylistSource::Revisit => recommendations::revisit(
+            &state.pool,
+            Utc::now(),
+            recommendations::DEFAULT_MIN_PLAYS,
+            recommendations::DEFAULT_IDLE_DAYS,
+            limit,
+        )
+        .map_err(|e| {
+            tracing::error!("Failed to export revisit playlist: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .to_playlist(),
+    };
+
+    let name = match params.source {
+        PlaylistSource::Top => format!("footprints_top_{}", period),
+        PlaylistSource::Revisit => "footprints_revisit".to_string(),
+    };
+    Ok(playlist_download(&playlist, params.format, &name))
+}
+
+fn playlist_download(
+    playlist: &Playlist,
+    format: PlaylistFormat,
+    name: &str,
+) -> axum::response::Response {
+    use axum::http::header;
+    use axum::response::IntoResponse;
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+            ),
+        ],
+        playlist.render(format),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct NewReleasesParams {
+    /// YYYY-MM-DD, the first of the current month by default
+    since: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct NewReleasesResponse {
+    since: NaiveDate,
+    /// When the radar last looked anything up; `None` if it never ran
+    last_checked: Option<DateTime<Utc>>,
+    releases: Vec<crate::db::Release>,
+}
+
+async fn new_releases_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<NewReleasesParams>,
+) -> Result<Json<NewReleasesResponse>, StatusCode> {
+    let since = match params.since.as_deref() {
+        Some(since) => {
+            NaiveDate::parse_from_str(since, "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST)?
+        }
+        None => Utc::now().date_naive().with_day(1).unwrap_or_default(),
+    };
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    let releases = crate::db::get_releases_since(&state.pool, since, limit);
+    let last_checked = crate::db::get_release_check(&state.pool, None);
+    match (releases, last_checked) {
+        (Ok(releases), Ok(last_checked)) => Ok(Json(NewReleasesResponse {
+            since,
+            last_checked,
+            releases,
+        })),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!("Failed to list new releases: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn parse_chart_kind(chart: &str) -> Result<ChartKind, StatusCode> {
+    match chart {
+        "artists" => Ok(ChartKind::Artist),
+        "tracks" => Ok(ChartKind::Track),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChartParams {
+    /// Any day of the week; the last recorded week by default
+    week: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+struct ChartResponse {
+    chart: ChartKind,
+    week_start: NaiveDate,
+    entries: Vec<ChartEntry>,
+}
+
+/// A recorded weekly chart; 404 for weeks not recorded yet
+async fn get_chart_handler(
+    State(state): State<Arc<AppState>>,
+    Path(chart): Path<String>,
+    Query(params): Query<ChartParams>,
+) -> Result<Json<ChartResponse>, StatusCode> {
+    let kind = parse_chart_kind(&chart)?;
+    let week_start = match params.week {
+        Some(day) => reports::period::IsoWeek::of(day).monday(),
+        None => match crate::db::get_last_chart_week(&state.pool) {
+            Ok(Some(week)) => week,
+            Ok(None) => return Err(StatusCode::NOT_FOUND),
+            Err(e) => {
+                tracing::error!("Failed to find the last chart week: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        },
+    };
+
+    match crate::db::get_chart(&state.pool, kind, week_start) {
+        Ok(Some(entries)) => Ok(Json(ChartResponse {
+            chart: kind,
+            week_start,
+            entries,
+        })),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get {} chart of {}: {}", chart, week_start, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChartLeadersParams {
+    #[serde(default = "default_chart_leaders_limit")]
+    limit: usize,
+}
+
+fn default_chart_leaders_limit() -> usize {
+    20
+}
+
+/// All-time chart records: weeks at #1, weeks on chart and longest #1 runs
+async fn get_chart_leaders_handler(
+    State(state): State<Arc<AppState>>,
+    Path(chart): Path<String>,
+    Query(params): Query<ChartLeadersParams>,
+) -> Result<Json<Vec<ChartRecord>>, StatusCode> {
+    let kind = parse_chart_kind(&chart)?;
+    match charts::leaders(&state.pool, kind, params.limit.clamp(1, 500)) {
+        Ok(records) => Ok(Json(records)),
+        Err(e) => {
+            tracing::error!("Failed to get {} chart leaders: {}", chart, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_artist_chart_history_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(ArtistName { artist }): Entity<ArtistName>,
+) -> Result<Json<ChartHistory>, StatusCode> {
+    match charts::history(&state.pool, ChartKind::Artist, &artist, None) {
+        Ok(history) => Ok(Json(history)),
+        Err(e) => {
+            tracing::error!("Failed to get chart history of {}: {}", artist, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_track_chart_history_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(TrackName { artist, track }): Entity<TrackName>,
+) -> Result<Json<ChartHistory>, StatusCode> {
+    match charts::history(&state.pool, ChartKind::Track, &artist, Some(&track)) {
+        Ok(history) => Ok(Json(history)),
+        Err(e) => {
+            tracing::error!(
+                "Failed to get chart history of {} - {}: {}",
+                artist,
+                track,
+                e
+            );
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RebuildChartsResponse {
+    weeks: usize,
+}
+
+/// Record every finished week's charts again, e.g. after importing older history
+async fn rebuild_charts_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RebuildChartsResponse>, StatusCode> {
+    match charts::rebuild(&state.pool, Utc::now()) {
+        Ok(weeks) => Ok(Json(RebuildChartsResponse { weeks })),
+        Err(e) => {
+            tracing::error!("Failed to rebuild weekly charts: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CompareRemoteParams {
+    source: String,
+    username: Option<String>,
+    api_key: Option<String>,
+    url: Option<String>,
+    share_token: Option<String>,
+    #[serde(default = "default_compare_limit")]
+    limit: i64,
+}
+
+fn default_compare_limit() -> i64 {
+    50
+}
+
+async fn compare_remote_handler(
+    State(state): State<Arc<AppState>>,
+    Query(schema): Query<SchemaParams>,
+    Json(params): Json<CompareRemoteParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let limit = params.limit.clamp(1, 500);
+
+    let (friend, theirs) = match params.source.as_str() {
+        "footprints" => {
+            let url = params.url.ok_or(StatusCode::BAD_REQUEST)?;
+            let base_url = reports::compare::parse_instance_url(&url).map_err(|e| {
+                tracing::warn!("Refusing to compare with {}: {}", url, e);
+                StatusCode::BAD_REQUEST
+            })?;
+            let lists = reports::compare::fetch_footprints_top_lists(
+                &base_url,
+                params.share_token.as_deref(),
+                limit,
+            )
+            .await;
+            (url, lists)
+        }
+        "lastfm" => {
+            let (Some(username), Some(api_key)) = (params.username, params.api_key) else {
+                return Err(StatusCode::BAD_REQUEST);
+            };
+            let lists = reports::compare::fetch_lastfm_top_lists(&username, &api_key, limit).await;
+            (username, lists)
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let theirs = theirs.map_err(|e| {
+        tracing::error!("Failed to fetch top lists for {}: {}", friend, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let yours = reports::compare::local_top_lists(&state.pool, limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    versioned(
+        &reports::compare::compare_top_lists(friend, params.source, &yours, &theirs),
+        &schema,
+    )
+}
+
+#[derive(Deserialize)]
+struct YearlyParams {
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+    /// Language tag for milestone text, English when unsupported
+    locale: Option<String>,
+    /// Milestone icons as emoji rather than symbolic names, as they used to be
+    #[serde(default)]
+    emoji_icons: bool,
+}
+
+async fn get_yearly_handler(
+    State(state): State<Arc<AppState>>,
+    Path(year): Path<i32>,
+    Query(params): Query<YearlyParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let locale = reports::locale::Locale::from_tag(params.locale.as_deref().unwrap_or_default());
+    match reports::yearly::generate_yearly_report(
+        &state.pool,
+        year,
+        params.gap_minutes.max(1),
+        locale,
+    ) {
+        Ok(report) => {
+            let mut rendered = versioned(&report, &schema)?;
+            if params.emoji_icons
+                && let Some(object) = rendered.as_object_mut()
+            {
+                reports::yearly::use_emoji_icons(object);
+            }
+            Ok(rendered)
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Deserialize)]
+struct WrappedParams {
+    #[serde(default = "default_session_gap")]
+    gap_minutes: i64,
+    /// Entries in each top list
+    #[serde(default = "default_wrapped_limit")]
+    limit: usize,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+    /// Language tag for report text, English when unsupported
+    locale: Option<String>,
+    /// Milestone icons as emoji rather than symbolic names, as they used to be
+    #[serde(default)]
+    emoji_icons: bool,
+}
+
+fn default_wrapped_limit() -> usize {
+    10
+}
+
+#[derive(Serialize)]
+struct WrappedArtist {
+    #[serde(flatten)]
+    artist: reports::yearly::TopArtist,
+    image_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WrappedTrack {
+    #[serde(flatten)]
+    track: reports::yearly::TopTrack,
+    image_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WrappedAlbum {
+    #[serde(flatten)]
+    album: reports::yearly::TopAlbum,
+    image_url: Option<String>,
+}
+
+/// Everything a year in review shows, in the requested schema version
+#[derive(Serialize)]
+struct WrappedBundle {
+    schema_version: u32,
+    year: i32,
+    /// The yearly report, milestones included
+    yearly: serde_json::Value,
+    top_artists: Vec<WrappedArtist>,
+    top_tracks: Vec<WrappedTrack>,
+    top_albums: Vec<WrappedAlbum>,
+    heatmap: serde_json::Value,
+    novelty: reports::novelty::NoveltySummary,
+}
+
+/// The yearly report, its top lists with artwork, the year's heatmap and its
+/// novelty summary in one response. The reports are computed side by side,
+/// then each top list's artwork is looked up alongside the others
+async fn get_wrapped_handler(
+    State(state): State<Arc<AppState>>,
+    Path(year): Path<i32>,
+    Query(params): Query<WrappedParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<WrappedBundle>, StatusCode> {
+    let supported =
+        reports::schema::MIN_REPORT_SCHEMA_VERSION..=reports::schema::REPORT_SCHEMA_VERSION;
+    if !supported.contains(&schema.schema_version) || !(1970..=2100).contains(&year) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Year bounds in UTC, as the yearly report takes them
+    let (Some(first_day), Some(next_year)) = (
+        NaiveDate::from_ymd_opt(year, 1, 1),
+        NaiveDate::from_ymd_opt(year + 1, 1, 1),
+    ) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let start = first_day.and_time(Default::default()).and_utc();
+    let end = next_year.and_time(Default::default()).and_utc() - Duration::seconds(1);
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let gap_minutes = params.gap_minutes.max(1);
+    let locale = reports::locale::Locale::from_tag(params.locale.as_deref().unwrap_or_default());
+
+    let (yearly, heatmap, novelty) = {
+        let (yearly_pool, heatmap_pool, novelty_pool) =
+            (state.pool.clone(), state.pool.clone(), state.pool.clone());
+        tokio::join!(
+            tokio::task::spawn_blocking(move || {
+                reports::yearly::generate_yearly_report(&yearly_pool, year, gap_minutes, locale)
+            }),
+            tokio::task::spawn_blocking(move || {
+                reports::heatmap::generate_heatmap(
+                    &heatmap_pool,
+                    Some(start),
+                    Some(end),
+                    timezone,
+                    None,
+                    &crate::db::ScrobbleFilter::default(),
+                    locale,
+                )
+            }),
+            tokio::task::spawn_blocking(move || {
+                reports::novelty::generate_novelty_report(
+                    &novelty_pool,
+                    Some(start),
+                    Some(end),
+                    reports::period::Granularity::Month,
+                )
+            }),
+        )
+    };
+    let (yearly, heatmap, novelty) = match (yearly, heatmap, novelty) {
+        (Ok(Ok(yearly)), Ok(Ok(heatmap)), Ok(Ok(novelty))) => (yearly, heatmap, novelty),
+        _ => {
+            tracing::error!("Failed to generate the wrapped bundle for {}", year);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let limit = params.limit.clamp(1, 50);
+    let top = &yearly.top_content;
+    let images = &state.image_service;
+    let (top_artists, top_tracks, top_albums) = tokio::join!(
+        async {
+            let mut artists = Vec::new();
+            for artist in top.top_artists.iter().take(limit) {
+                let request = ImageRequest::artist(artist.artist.clone());
+                artists.push(images.get_best_image(request).await);
+            }
+            artists
+        },
+        async {
+            let mut tracks = Vec::new();
+            for track in top.top_tracks.iter().take(limit) {
+                let request = ImageRequest::track(track.artist.clone(), track.track.clone());
+                tracks.push(images.get_best_image(request).await);
+            }
+            tracks
+        },
+        async {
+            let mut albums = Vec::new();
+            for album in top.top_albums.iter().take(limit) {
+                let request = ImageRequest::album(album.artist.clone(), album.album.clone());
+                albums.push(images.get_image_url(request).await.ok().flatten());
+            }
+            albums
+        },
+    );
+
+    let render = |value: anyhow::Result<serde_json::Value>| {
+        value.map_err(|e| {
+            tracing::error!("Failed to render the wrapped bundle for {}: {}", year, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    };
+    let mut yearly_value = render(reports::schema::render(&yearly, schema.schema_version))?;
+    if params.emoji_icons
+        && let Some(object) = yearly_value.as_object_mut()
+    {
+        reports::yearly::use_emoji_icons(object);
+    }
+    let heatmap = render(reports::schema::render(&heatmap, schema.schema_version))?;
+
+    let top_content = yearly.top_content;
+    Ok(Json(WrappedBundle {
+        schema_version: schema.schema_version,
+        year,
+        yearly: yearly_value,
+        top_artists: top_content
+            .top_artists
+            .into_iter()
+            .zip(top_artists)
+            .map(|(artist, image_url)| WrappedArtist { artist, image_url })
+            .collect(),
+        top_tracks: top_content
+            .top_tracks
+            .into_iter()
+            .zip(top_tracks)
+            .map(|(track, image_url)| WrappedTrack { track, image_url })
+            .collect(),
+        top_albums: top_content
+            .top_albums
+            .into_iter()
+            .zip(top_albums)
+            .map(|(album, image_url)| WrappedAlbum { album, image_url })
+            .collect(),
+        heatmap,
+        novelty: novelty.summary,
+    }))
+}
+
+#[derive(Deserialize)]
+struct StatsUiParams {
+    #[serde(default = "default_period")]
+    period: String,
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default)]
+    credit: ArtistCredit,
+}
+
+fn default_period() -> String {
+    "alltime".to_string()
+}
+
+#[derive(Serialize)]
+struct ArtistWithImage {
+    name: String,
+    count: i64,
+    image_url: Option<String>,
+    /// Plays taken from legacy Last.fm charts, on all-time lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    legacy_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<Provenance>,
+    #[serde(flatten)]
+    movement: Movement,
+}
+
+/// Plays from legacy Last.fm charts of each all-time top artist and track, in
+/// list order
+struct LegacyCounts {
+    artists: Vec<i64>,
+    tracks: Vec<i64>,
+}
+
+/// The period before the requested one and the requested one's change from it
+#[derive(Serialize)]
+struct PreviousPeriod {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    scrobbles: i64,
+    count_change: i64,
+    change_percent: Option<f64>,
+}
+
+// Standings of the current top entries over the previous period
+struct PreviousStandings {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    scrobbles: i64,
+    artists: std::collections::HashMap<String, Standing>,
+    tracks: std::collections::HashMap<(String, String), Standing>,
+    albums: std::collections::HashMap<(String, String), Standing>,
+}
+
+#[derive(Serialize)]
+struct TrackWithImage {
+    artist: String,
+    track: String,
+    count: i64,
+    image_url: Option<String>,
+    /// Plays taken from legacy Last.fm charts, on all-time lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    legacy_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<Provenance>,
+    #[serde(flatten)]
+    movement: Movement,
+}
+
+#[derive(Serialize)]
+struct AlbumWithImage {
+    artist: String,
+    album: String,
+    count: i64,
+    image_url: Option<String>,
+    #[serde(flatten)]
+    movement: Movement,
+}
+
+#[derive(Deserialize)]
+struct OverviewParams {
+    #[serde(default = "default_period")]
+    period: String,
+    start: Option<String>,
+    end: Option<String>,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+}
+
+/// Totals, this week's digest, the listening streak, now playing and short
+/// top lists in one response, for the homepage's first paint. Top lists skip
+/// artwork, which /api/stats/ui provides
+async fn get_overview_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<OverviewParams>,
+) -> Result<Json<crate::overview::Overview>, StatusCode> {
+    let (start, end) = period_range(
+        &params.period,
+        params.start.as_deref(),
+        params.end.as_deref(),
+    )
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut overview = crate::overview::build(&state.pool, Utc::now(), timezone, start, end)
+        .map_err(|e| {
+            tracing::error!("Failed to build overview: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    overview.now_playing = state
+        .live_hub
+        .now_playing_snapshot()
+        .await
+        .into_iter()
+        .filter_map(|event| match event {
+            LiveEvent::NowPlaying {
+                source,
+                username,
+                track: Some(track),
+            } => Some(crate::overview::NowPlayingEntry {
+                source,
+                username,
+                track,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(Json(overview))
+}
+
+#[derive(Serialize)]
+struct PulsePoint {
+    /// Bucket label; kept as `day` for existing clients at every granularity
+    day: String,
+    count: i64,
+}
+
+async fn get_stats_ui_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StatsUiParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (start_date, end_date) = period_range(
+        &params.period,
+        params.start.as_deref(),
+        params.end.as_deref(),
+    )
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Fetch stats from database in a single snapshot
+    let size = preferences(&state).top_list_size;
+    let credit = params.credit;
+    let (top_artists, top_tracks, top_albums, period_count, previous, legacy) =
+        crate::db::with_read_txn(&state.pool, |conn| {
+            // All time blends in the charts imported from before the first scrobble
+            let (top_artists, top_tracks, legacy) = if start_date.is_none() && end_date.is_none() {
+                let artists = crate::db::query_blended_top_artists(conn, credit, size)?;
+                let tracks = crate::db::query_blended_top_tracks(conn, size)?;
+                let legacy = LegacyCounts {
+                    artists: artists.iter().map(|(_, _, legacy)| *legacy).collect(),
+                    tracks: tracks.iter().map(|(_, _, _, legacy)| *legacy).collect(),
+                };
+                (
+                    artists
+                        .into_iter()
+                        .map(|(name, count, _)| (name, count))
+                        .collect::<Vec<_>>(),
+                    tracks
+                        .into_iter()
+                        .map(|(artist, track, count, _)| (artist, track, count))
+                        .collect::<Vec<_>>(),
+                    Some(legacy),
+                )
+            } else {
+                (
+                    crate::db::query_top_credited_artists(
+                        conn, credit, size, start_date, end_date,
+                    )?,
+                    crate::db::query_top_tracks(conn, size, start_date, end_date)?,
+                    None,
+                )
+            };
+            let top_albums = crate::db::query_top_albums(conn, size, start_date, end_date)?;
+            let period_count =
+                crate::db::query_scrobbles_count_in_range(conn, start_date, end_date)?;
+
+            // All time has nothing before it to compare with
+            let previous_range = match (start_date, end_date) {
+                (Some(start), Some(end)) => {
+                    reports::movement::previous_range(&params.period, start, end)
+                }
+                _ => None,
+            };
+            let previous = match previous_range {
+                Some((start, end)) => Some(PreviousStandings {
+                    start,
+                    end,
+                    scrobbles: crate::db::query_scrobbles_count_in_range(
+                        conn,
+                        Some(start),
+                        Some(end),
+                    )?,
+                    artists: crate::db::query_artist_standings(
+                        conn,
+                        credit,
+                        start,
+                        end,
+                        &top_artists
+                            .iter()
+                            .map(|(name, _)| name.clone())
+                            .collect::<Vec<_>>(),
+                    )?,
+                    tracks: crate::db::query_track_standings(
+                        conn,
+                        start,
+                        end,
+                        &top_tracks
+                            .iter()
+                            .map(|(artist, track, _)| (artist.clone(), track.clone()))
+                            .collect::<Vec<_>>(),
+                    )?,
+                    albums: crate::db::query_album_standings(
+                        conn,
+                        start,
+                        end,
+                        &top_albums
+                            .iter()
+                            .map(|(artist, album, _)| (artist.clone(), album.clone()))
+                            .collect::<Vec<_>>(),
+                    )?,
+                }),
+                None => None,
+            };
+
+            Ok((
+                top_artists,
+                top_tracks,
+                top_albums,
+                period_count,
+                previous,
+                legacy,
+            ))
+        })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let artist_movements = reports::movement::movements(
+        &top_artists
+            .iter()
+            .map(|(name, count)| (name.clone(), *count))
+            .collect::<Vec<_>>(),
+        previous.as_ref().map(|p| &p.artists),
+    );
+    let track_movements = reports::movement::movements(
+        &top_tracks
+            .iter()
+            .map(|(artist, track, count)| ((artist.clone(), track.clone()), *count))
+            .collect::<Vec<_>>(),
+        previous.as_ref().map(|p| &p.tracks),
+    );
+    let album_movements = reports::movement::movements(
+        &top_albums
+            .iter()
+            .map(|(artist, album, count)| ((artist.clone(), album.clone()), *count))
+            .collect::<Vec<_>>(),
+        previous.as_ref().map(|p| &p.albums),
+    );
+
+    // Fetch images for artists
+    let mut artists_with_images = Vec::new();
+    for (index, ((name, count), movement)) in
+        top_artists.into_iter().zip(artist_movements).enumerate()
+    {
+        let image_url = state
+            .image_service
+            .get_best_image(ImageRequest::artist(name.clone()))
+            .await;
+        let legacy_count = legacy.as_ref().map(|legacy| legacy.artists[index]);
+        artists_with_images.push(ArtistWithImage {
+            name,
+            count,
+            image_url,
+            legacy_count,
+            provenance: legacy_count.map(|legacy| Provenance::of(count, legacy)),
+            movement,
+        });
+    }
+
+    // Fetch images for tracks (try track image first, then artist, then album)
+    let mut tracks_with_images = Vec::new();
+    for (index, ((artist, track, count), movement)) in
+        top_tracks.into_iter().zip(track_movements).enumerate()
+    {
+        let image_url = state
+            .image_service
+            .get_best_image(ImageRequest::track(artist.clone(), track.clone()))
+            .await;
+        let legacy_count = legacy.as_ref().map(|legacy| legacy.tracks[index]);
+        tracks_with_images.push(TrackWithImage {
+            artist,
+            track,
+            count,
+            image_url,
+            legacy_count,
+            provenance: legacy_count.map(|legacy| Provenance::of(count, legacy)),
+            movement,
+        });
+    }
+
+    // Fetch images for albums
+    let mut albums_with_images = Vec::new();
+    for ((artist, album, count), movement) in top_albums.into_iter().zip(album_movements) {
+        let image_url: Option<String> = state
+            .image_service
+            .get_image_url(ImageRequest::album(artist.clone(), album.clone()))
+            .await
+            .ok()
+            .flatten();
+        albums_with_images.push(AlbumWithImage {
+            artist,
+            album,
+            count,
+            image_url,
+            movement,
+        });
+    }
+
+    let previous_period = previous.map(|previous| PreviousPeriod {
+        start: previous.start,
+        end: previous.end,
+        scrobbles: previous.scrobbles,
+        count_change: period_count - previous.scrobbles,
+        change_percent: (previous.scrobbles > 0).then(|| {
+            (period_count - previous.scrobbles) as f64 / previous.scrobbles as f64 * 100.0
+        }),
+    });
+
+    Ok(Json(serde_json::json!({
+        "period": params.period,
+        "period_scrobbles": period_count,
+        "previous_period": previous_period,
+        "top_artists": artists_with_images,
+        "top_tracks": tracks_with_images,
+        "top_albums": albums_with_images,
+    })))
+}
+
+#[derive(Deserialize)]
+struct PulseParams {
+    #[serde(default = "default_period")]
+    period: String,
+    start: Option<String>,
+    end: Option<String>,
+    #[serde(default = "default_pulse_granularity")]
+    granularity: String,
+    /// The instance's timezone setting when omitted
+    timezone: Option<String>,
+}
+
+fn default_pulse_granularity() -> String {
+    "day".to_string()
+}
+
+async fn get_pulse_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PulseParams>,
+) -> Result<Json<Vec<PulsePoint>>, StatusCode> {
+    let (start_date, end_date) = period_range(
+        &params.period,
+        params.start.as_deref(),
+        params.end.as_deref(),
+    )
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let granularity: TimeBucket = params
+        .granularity
+        .parse()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let timezone = preferences(&state)
+        .timezone_or(params.timezone.as_deref())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let data = crate::db::get_scrobbles_per_bucket(
+        &state.pool,
+        granularity,
+        start_date,
+        end_date,
+        timezone,
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(
+        data.into_iter()
+            .map(|(day, count)| PulsePoint { day, count })
+            .collect(),
+    ))
+}
+
+fn get_today_range() -> DateRange {
+    let now = Utc::now();
+    let today_start = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(Utc).single());
+    (today_start, Some(now))
+}
+
+fn get_week_range() -> DateRange {
+    let now = Utc::now();
+    let week_ago = now - Duration::days(7);
+    (Some(week_ago), Some(now))
+}
+
+fn get_month_range() -> DateRange {
+    let now = Utc::now();
+    let month_start = now
+        .date_naive()
+        .with_day(1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|dt| dt.and_local_timezone(Utc).single());
+    (month_start, Some(now))
+}
+
+fn get_year_range() -> DateRange {
+    let now = Utc::now();
+    let year_start = now
+        .date_naive()
+        .with_month(1)
+        .and_then(|d| d.with_day(1))
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|dt| dt.and_local_timezone(Utc).single());
+    (year_start, Some(now))
+}
+
+/// Date range for a named period; `None` for a custom one missing its bounds
+fn period_range(period: &str, start: Option<&str>, end: Option<&str>) -> Option<DateRange> {
+    match period {
+        "today" => Some(get_today_range()),
+        "week" => Some(get_week_range()),
+        "month" => Some(get_month_range()),
+        "year" => Some(get_year_range()),
+        "custom" => parse_custom_range(start, end),
+        _ => Some((None, None)),
+    }
+}
+
+fn parse_custom_range(start: Option<&str>, end: Option<&str>) -> Option<DateRange> {
+    let start_dt = start
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    let end_dt = end
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    match (start_dt, end_dt) {
+        (Some(s), Some(e)) => Some((Some(s), Some(e))),
+        _ => None,
+    }
+}
+
+// Sync configuration handlers
+#[derive(Deserialize)]
+pub struct CreateSyncConfigParams {
+    source: String,
+    username: String,
+    api_key: Option<String>,
+    token: Option<String>,
+    #[serde(default = "default_sync_interval")]
+    sync_interval_minutes: i32,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    /// On creation, also import the account's full history in the background
+    #[serde(default)]
+    initial_import: bool,
+}
+
+fn default_sync_interval() -> i32 {
+    60 // Default to 60 minutes
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl CreateSyncConfigParams {
+    fn to_config(&self) -> SyncConfig {
+        let mut config = SyncConfig::new(
+            self.source.clone(),
+            self.username.clone(),
+            self.sync_interval_minutes,
+        )
+        .with_enabled(self.enabled);
+
+        if let Some(api_key) = &self.api_key {
+            config = config.with_api_key(api_key.clone());
+        }
+
+        if let Some(token) = &self.token {
+            config = config.with_token(token.clone());
+        }
+
+        config
+    }
+}
+
+#[derive(Serialize)]
+pub struct SyncConfigResponse {
+    success: bool,
+    message: String,
+    config: Option<SyncConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    import_job_id: Option<i64>,
+}
+
+impl SyncConfigResponse {
+    fn invalid(errors: Vec<FieldError>) -> (StatusCode, Json<Self>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(Self {
+                success: false,
+                message: "Invalid sync configuration".to_string(),
+                config: None,
+                errors,
+                import_job_id: None,
+            }),
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct SyncConfigValidation {
+    valid: bool,
+    errors: Vec<FieldError>,
+}
+
+#[derive(Serialize)]
+pub struct SyncTriggerResponse {
+    success: bool,
+    count: usize,
+    message: String,
+}
+
+async fn create_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateSyncConfigParams>,
+) -> Result<(StatusCode, Json<SyncConfigResponse>), StatusCode> {
+    let config = params.to_config();
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return Ok(SyncConfigResponse::invalid(errors));
+    }
+
+    if let Err(e) = crate::db::insert_sync_config(&state.pool, &config) {
+        tracing::error!("Failed to create sync config: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // The scheduler only back-fills the last day for a new config
+    let mut message = format!(
+        "Sync configuration created for {} user {}",
+        params.source, params.username
+    );
+    let import_job_id = if params.initial_import {
+        let id = importers::jobs::spawn_initial_import(
+            state.pool.clone(),
+            state.normalizer.clone(),
+            &config,
+        )
+        .map_err(|e| {
+            tracing::error!("Failed to create import job: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        message.push_str(&format!(", full import started as job {}", id));
+        Some(id)
+    } else {
+        None
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(SyncConfigResponse {
+            success: true,
+            message,
+            config: Some(config),
+            errors: Vec::new(),
+            import_job_id,
+        }),
+    ))
+}
+
+/// Check a sync configuration, including its credentials with the source,
+/// without saving it
+async fn validate_sync_config_handler(
+    Json(params): Json<CreateSyncConfigParams>,
+) -> Result<Json<SyncConfigValidation>, StatusCode> {
+    let config = params.to_config();
+    let mut errors = config.validate();
+
+    if errors.is_empty() {
+        let remote = match config.source.as_str() {
+            "lastfm" => {
+                importers::LastFmImporter::new(
+                    config.api_key.clone().unwrap_or_default(),
+                    config.username.clone(),
+                )
+                .verify_credentials()
+                .await
+            }
+            _ => {
+                importers::ListenBrainzImporter::new(config.username.clone(), config.token.clone())
+                    .verify_credentials()
+                    .await
+            }
+        };
+
+        match remote {
+            Ok(remote_errors) => errors.extend(remote_errors),
+            Err(e) => {
+                tracing::warn!("Could not verify {} credentials: {:#}", config.source, e);
+                return Err(StatusCode::BAD_GATEWAY);
+            }
+        }
+    }
+
+    Ok(Json(SyncConfigValidation {
+        valid: errors.is_empty(),
+        errors,
+    }))
+}
+
+/// A sync config as listed, with whether it's paused and when it runs next
+#[derive(Serialize)]
+pub struct SyncConfigListing {
+    #[serde(flatten)]
+    config: SyncConfig,
+    paused: bool,
+    next_sync_at: Option<DateTime<Utc>>,
+}
+
+impl SyncConfigListing {
+    fn new(config: SyncConfig) -> Self {
+        let next_sync_at = config
+            .enabled
+            .then(|| crate::sync::scheduler::next_sync_at(&config, Utc::now()));
+        Self {
+            paused: !config.enabled,
+            next_sync_at,
+            config,
+        }
+    }
+}
+
+async fn get_sync_configs_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SyncConfigListing>>, StatusCode> {
+    match crate::db::get_all_sync_configs(&state.pool) {
+        Ok(configs) => Ok(Json(
+            configs.into_iter().map(SyncConfigListing::new).collect(),
+        )),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn get_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncConfigListing>, StatusCode> {
+    match crate::db::get_sync_config(&state.pool, id) {
+        Ok(Some(config)) => Ok(Json(SyncConfigListing::new(config))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn pause_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncConfigListing>, StatusCode> {
+    set_sync_config_enabled(&state, id, false)
+}
+
+async fn resume_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncConfigListing>, StatusCode> {
+    set_sync_config_enabled(&state, id, true)
+}
+
+fn set_sync_config_enabled(
+    state: &AppState,
+    id: i64,
+    enabled: bool,
+) -> Result<Json<SyncConfigListing>, StatusCode> {
+    match crate::db::set_sync_config_enabled(&state.pool, id, enabled) {
+        Ok(true) => match crate::db::get_sync_config(&state.pool, id) {
+            Ok(Some(config)) => Ok(Json(SyncConfigListing::new(config))),
+            Ok(None) => Err(StatusCode::NOT_FOUND),
+            Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        },
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to update sync config {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn update_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(params): Json<CreateSyncConfigParams>,
+) -> Result<(StatusCode, Json<SyncConfigResponse>), StatusCode> {
+    // Verify the config exists
+    match crate::db::get_sync_config(&state.pool, id) {
+        Ok(Some(_)) => {
+            let config = params.to_config();
+            let errors = config.validate();
+            if !errors.is_empty() {
+                return Ok(SyncConfigResponse::invalid(errors));
+            }
+
+            match crate::db::insert_sync_config(&state.pool, &config) {
+                Ok(_) => Ok((
+                    StatusCode::OK,
+                    Json(SyncConfigResponse {
+                        success: true,
+                        message: format!(
+                            "Sync configuration updated for {} user {}",
+                            params.source, params.username
+                        ),
+                        config: Some(config),
+                        errors: Vec::new(),
+                        import_job_id: None,
+                    }),
+                )),
+                Err(e) => {
+                    tracing::error!("Failed to update sync config: {}", e);
+                    Err(StatusCode::INTERNAL_SERVER_ERROR)
+                }
+            }
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn delete_sync_config_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncConfigResponse>, StatusCode> {
+    match crate::db::delete_sync_config(&state.pool, id) {
+        Ok(_) => Ok(Json(SyncConfigResponse {
+            success: true,
+            message: "Sync configuration deleted".to_string(),
+            config: None,
+            errors: Vec::new(),
+            import_job_id: None,
+        })),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Deserialize)]
+struct RescanParams {
+    since: Option<String>,
+}
+
+async fn rescan_sync_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(params): Query<RescanParams>,
+) -> Result<Json<SyncTriggerResponse>, StatusCode> {
+    // Verify the config exists
+    match crate::db::get_sync_config(&state.pool, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    let since = match params.since.as_deref() {
+        Some(since) => DateTime::parse_from_rfc3339(since)
+            .map_err(|_| StatusCode::BAD_REQUEST)?
+            .with_timezone(&Utc),
+        None => Utc::now() - Duration::days(crate::sync::scheduler::DEFAULT_RESCAN_DAYS),
+    };
+    if since >= Utc::now() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match state.sync_scheduler.rescan(id, since).await {
+        Ok(count) => Ok(Json(SyncTriggerResponse {
+            success: true,
+            count,
+            message: format!(
+                "Rescan since {} found {} new scrobbles",
+                since.to_rfc3339(),
+                count
+            ),
+        })),
+        Err(e) => Ok(Json(SyncTriggerResponse {
+            success: false,
+            count: 0,
+            message: format!("Rescan failed: {}", e),
+        })),
+    }
+}
+
+async fn get_sync_status_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<crate::sync::scheduler::SyncStatus>, StatusCode> {
+    state
+        .sync_scheduler
+        .status()
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn trigger_sync_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncTriggerResponse>, StatusCode> {
+    match state.sync_scheduler.trigger_sync(id).await {
+        Ok(count) => Ok(Json(SyncTriggerResponse {
+            success: true,
+            count,
+            message: format!("Successfully synced {} new scrobbles", count),
+        })),
+        Err(e) => Ok(Json(SyncTriggerResponse {
+            success: false,
+            count: 0,
+            message: format!("Sync failed: {}", e),
+        })),
+    }
+}
+
+async fn pull_playlists_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncTriggerResponse>, StatusCode> {
+    match state.sync_scheduler.pull_playlists(id).await {
+        Ok(count) => Ok(Json(SyncTriggerResponse {
+            success: true,
+            count,
+            message: format!("Stored {} new playlists", count),
+        })),
+        Err(e) => Ok(Json(SyncTriggerResponse {
+            success: false,
+            count: 0,
+            message: format!("Playlist pull failed: {}", e),
+        })),
+    }
+}
+
+async fn pull_legacy_charts_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SyncTriggerResponse>, StatusCode> {
+    match state.sync_scheduler.pull_legacy_charts(id).await {
+        Ok(count) => Ok(Json(SyncTriggerResponse {
+            success: true,
+            count,
+            message: format!("Stored {} legacy chart entries", count),
+        })),
+        Err(e) => Ok(Json(SyncTriggerResponse {
+            success: false,
+            count: 0,
+            message: format!("Legacy chart import failed: {}", e),
+        })),
+    }
+}
+
+async fn get_recommended_playlists_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Vec<crate::db::RecommendedPlaylistSummary>>, StatusCode> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    match crate::db::get_recommended_playlists(&state.pool, limit) {
+        Ok(playlists) => Ok(Json(playlists)),
+        Err(e) => {
+            tracing::error!("Failed to list recommended playlists: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RecommendedPlaylistDetail {
+    #[serde(flatten)]
+    playlist: crate::db::RecommendedPlaylist,
+    tracks: Vec<crate::db::RecommendedTrackPlays>,
+}
+
+async fn get_recommended_playlist_handler(
+    State(state): State<Arc<AppState>>,
+    Path(mbid): Path<String>,
+) -> Result<Json<RecommendedPlaylistDetail>, StatusCode> {
+    match crate::db::get_recommended_playlist(&state.pool, &mbid) {
+        Ok(Some((playlist, tracks))) => Ok(Json(RecommendedPlaylistDetail { playlist, tracks })),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get recommended playlist {}: {}", mbid, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    #[serde(default = "default_export_format")]
+    format: String,
+}
+
+fn default_export_format() -> String {
+    "json".to_string()
+}
+
+async fn export_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ExportParams>,
+) -> Result<axum::response::Response, StatusCode> {
+    use axum::body::Body;
+    use axum::http::header;
+    use axum::response::Response;
+
+    match crate::db::get_scrobbles(&state.pool, Some(1000000), Some(0)) {
+        Ok(scrobbles) => {
+            let (content_type, body) = match params.format.as_str() {
+                "json" => {
+                    let json = serde_json::to_string_pretty(&scrobbles)
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                    ("application/json", json)
+                }
+                "csv" => {
+                    let mut csv = String::from("timestamp,artist,album,track,source\n");
+                    for scrobble in scrobbles {
+                        let album = scrobble.album.unwrap_or_default();
+                        csv.push_str(&format!(
+                            "{},{},{},{},{}\n",
+                            scrobble.timestamp.to_rfc3339(),
+                            escape_csv(&scrobble.artist),
+                            escape_csv(&album),
+                            escape_csv(&scrobble.track),
+                            scrobble.source
+                        ));
+                    }
+                    ("text/csv", csv)
+                }
+                _ => return Err(StatusCode::BAD_REQUEST),
+            };
+
+            let filename = format!(
+                "footprints_export_{}.{}",
+                Utc::now().format("%Y-%m-%d"),
+                params.format
+            );
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, content_type)
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                )
+                .body(Body::from(body))
+                .unwrap())
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn escape_csv(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct ImageProxyParams {
+    url: String,
+    size: Option<u32>,
+}
+
+async fn image_proxy_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ImageProxyParams>,
+) -> Result<axum::response::Response, StatusCode> {
+    use axum::body::Body;
+    use axum::http::header;
+    use axum::response::Response;
+
+    let source_url =
+        crate::images::parse_source_url(&params.url).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let size = crate::images::thumbnail_size(params.size);
+
+    match state.image_service.get_thumbnail(&source_url, size).await {
+        Ok(thumbnail) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, thumbnail.content_type)
+            // Thumbnails for a given URL and size never change
+            .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+            .body(Body::from(thumbnail.data))
+            .unwrap()),
+        Err(e) => {
+            tracing::warn!("Image proxy failed for {}: {:#}", source_url, e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+async fn image_cache_stats_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ImageCacheStats>, StatusCode> {
+    state
+        .image_service
+        .cache_stats()
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[derive(Deserialize)]
+struct PurgeImageCacheParams {
+    entity_type: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    track: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PurgeImageCacheResponse {
+    deleted: usize,
+}
+
+async fn purge_image_cache_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PurgeImageCacheParams>,
+) -> Result<Json<PurgeImageCacheResponse>, StatusCode> {
+    let entity_type = match params.entity_type.as_deref() {
+        Some(value) => Some(
+            value
+                .parse::<EntityType>()
+                .map_err(|_| StatusCode::BAD_REQUEST)?,
+        ),
+        None => None,
+    };
+    let filter = ImageCacheFilter {
+        entity_type,
+        artist_name: params.artist,
+        secondary_name: params.album.or(params.track),
+    };
+
+    match state.image_service.purge_cache(&filter) {
+        Ok(deleted) => Ok(Json(PurgeImageCacheResponse { deleted })),
+        Err(e) => {
+            tracing::error!("Failed to purge image cache: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RefreshImageParams {
+    entity_type: String,
+    artist: String,
+    album: Option<String>,
+    track: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RefreshImageResponse {
+    image_url: Option<String>,
+}
+
+async fn refresh_image_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<RefreshImageParams>,
+) -> Result<Json<RefreshImageResponse>, StatusCode> {
+    let entity_type = params
+        .entity_type
+        .parse::<EntityType>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let request = match (entity_type, params.album, params.track) {
+        (EntityType::Artist, _, _) => ImageRequest::artist(params.artist),
+        (EntityType::Album, Some(album), _) => ImageRequest::album(params.artist, album),
+        (EntityType::Track, _, Some(track)) => ImageRequest::track(params.artist, track),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match state.image_service.refresh_image_url(request).await {
+        Ok(image_url) => Ok(Json(RefreshImageResponse { image_url })),
+        Err(e) => {
+            tracing::error!("Failed to refresh image: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Entity detail handlers
+#[derive(Deserialize)]
+struct EntityParams {
+    start: Option<String>,
+    end: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ArtistDetail {
+    stats: serde_json::Value,
+    top_tracks: Vec<TrackItem>,
+    top_albums: Vec<AlbumItem>,
+    scrobbles_over_time: Vec<TimePoint>,
+    /// Markers for the chart: this artist's annotations in the range
+    annotations: Vec<Annotation>,
+    image_url: Option<String>,
+    /// Artists most often played in the same sessions
+    similar: Vec<reports::transitions::SimilarArtist>,
+}
+
+#[derive(Serialize)]
+struct TrackItem {
+    name: String,
+    count: i64,
+}
+
+#[derive(Serialize)]
+struct AlbumItem {
+    name: String,
+    count: i64,
+    image_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TimePoint {
+    date: String,
+    count: i64,
+}
+
+#[derive(Serialize)]
+struct AlbumDetail {
+    stats: serde_json::Value,
+    tracks: Vec<TrackItem>,
+    scrobbles_over_time: Vec<TimePoint>,
+    image_url: Option<String>,
+    rating: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct TrackDetail {
+    stats: serde_json::Value,
+    scrobbles_over_time: Vec<TimePoint>,
+    image_url: Option<String>,
+    rating: Option<u8>,
+}
+
+const SIMILAR_ARTISTS_LIMIT: usize = 10;
+
+async fn get_artist_handler(
+    State(state): State<Arc<AppState>>,
+    KnownEntity(ArtistName { artist }): KnownEntity<ArtistName>,
+    Query(params): Query<EntityParams>,
+) -> Result<Json<ArtistDetail>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let stats = crate::db::get_artist_stats(&state.pool, &artist, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let top_tracks = crate::db::get_artist_top_tracks(&state.pool, &artist, 20, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|(name, count)| TrackItem { name, count })
+        .collect();
+
+    let top_albums_data = crate::db::get_artist_top_albums(&state.pool, &artist, 20, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut top_albums = Vec::new();
+    for (name, count) in top_albums_data {
+        let image_url = state
+            .image_service
+            .get_image_url(ImageRequest::album(artist.clone(), name.clone()))
+            .await
+            .ok()
+            .flatten();
+        top_albums.push(AlbumItem {
+            name,
+            count,
+            image_url,
+        });
+    }
+
+    let scrobbles_over_time =
+        crate::db::get_artist_scrobbles_over_time(&state.pool, &artist, start, end)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .map(|(date, count)| TimePoint { date, count })
+            .collect();
+
+    let annotations = crate::db::get_annotations(
+        &state.pool,
+        Some(&artist),
+        start.map(|s| s.date_naive()),
+        end.map(|e| e.date_naive()),
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let similar = reports::transitions::similar_artists(
+        &state.pool,
+        &artist,
+        start,
+        end,
+        SIMILAR_ARTISTS_LIMIT,
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let image_url = state
+        .image_service
+        .get_best_image(ImageRequest::artist(artist.clone()))
+        .await;
+
+    Ok(Json(ArtistDetail {
+        stats,
+        top_tracks,
+        top_albums,
+        scrobbles_over_time,
+        annotations,
+        image_url,
+        similar,
+    }))
+}
+
+async fn get_album_handler(
+    State(state): State<Arc<AppState>>,
+    KnownEntity(AlbumName { artist, album }): KnownEntity<AlbumName>,
+    Query(params): Query<EntityParams>,
+) -> Result<Json<AlbumDetail>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let stats = crate::db::get_album_stats(&state.pool, &artist, &album, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tracks = crate::db::get_album_tracks(&state.pool, &artist, &album, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|(name, count)| TrackItem { name, count })
+        .collect();
+
+    let scrobbles_over_time =
+        crate::db::get_album_scrobbles_over_time(&state.pool, &artist, &album, start, end)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .map(|(date, count)| TimePoint { date, count })
+            .collect();
+
+    let image_url = state
+        .image_service
+        .get_image_url(ImageRequest::album(artist.clone(), album.clone()))
+        .await
+        .ok()
+        .flatten();
+
+    let rating = crate::db::get_rating(&state.pool, RatingKind::Album, &artist, &album)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AlbumDetail {
+        stats,
+        tracks,
+        scrobbles_over_time,
+        image_url,
+        rating,
+    }))
+}
+
+async fn get_track_handler(
+    State(state): State<Arc<AppState>>,
+    KnownEntity(TrackName { artist, track }): KnownEntity<TrackName>,
+    Query(params): Query<EntityParams>,
+) -> Result<Json<TrackDetail>, StatusCode> {
+    let start = params
+        .start
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let end = params
+        .end
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let stats = crate::db::get_track_stats(&state.pool, &artist, &track, start, end)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let scrobbles_over_time =
+        crate::db::get_track_scrobbles_over_time(&state.pool, &artist, &track, start, end)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .map(|(date, count)| TimePoint { date, count })
+            .collect();
+
+    let image_url = state
+        .image_service
+        .get_best_image(ImageRequest::track(artist.clone(), track.clone()))
+        .await;
+
+    let rating = crate::db::get_rating(&state.pool, RatingKind::Track, &artist, &track)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(TrackDetail {
+        stats,
+        scrobbles_over_time,
+        image_url,
+        rating,
+    }))
+}
+
+// Rating handlers
+#[derive(Deserialize)]
+pub struct SetRatingParams {
+    rating: u8,
+}
+
+#[derive(Deserialize)]
+pub struct RatingsParams {
+    #[serde(default = "default_rating_kind")]
+    kind: String,
+}
+
+fn default_rating_kind() -> String {
+    "track".to_string()
+}
+
+fn parse_rating_kind(kind: &str) -> Result<RatingKind, StatusCode> {
+    match kind {
+        "track" => Ok(RatingKind::Track),
+        "album" => Ok(RatingKind::Album),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn set_rating(
+    pool: &DbPool,
+    kind: RatingKind,
+    artist: &str,
+    name: &str,
+    rating: u8,
+) -> Result<StatusCode, StatusCode> {
+    if !(1..=5).contains(&rating) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    crate::db::set_rating(pool, kind, artist, name, rating).map_err(|e| {
+        tracing::error!("Failed to set rating: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn delete_rating(pool: &DbPool, kind: RatingKind, artist: &str, name: &str) -> StatusCode {
+    match crate::db::delete_rating(pool, kind, artist, name) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn set_track_rating_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(TrackName { artist, track }): Entity<TrackName>,
+    Json(params): Json<SetRatingParams>,
+) -> Result<StatusCode, StatusCode> {
+    set_rating(
+        &state.pool,
+        RatingKind::Track,
+        &artist,
+        &track,
+        params.rating,
+    )
+}
+
+async fn delete_track_rating_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(TrackName { artist, track }): Entity<TrackName>,
+) -> StatusCode {
+    delete_rating(&state.pool, RatingKind::Track, &artist, &track)
+}
+
+async fn set_album_rating_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(AlbumName { artist, album }): Entity<AlbumName>,
+    Json(params): Json<SetRatingParams>,
+) -> Result<StatusCode, StatusCode> {
+    set_rating(
+        &state.pool,
+        RatingKind::Album,
+        &artist,
+        &album,
+        params.rating,
+    )
+}
+
+async fn delete_album_rating_handler(
+    State(state): State<Arc<AppState>>,
+    Entity(AlbumName { artist, album }): Entity<AlbumName>,
+) -> StatusCode {
+    delete_rating(&state.pool, RatingKind::Album, &artist, &album)
+}
+
+async fn get_ratings_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RatingsParams>,
+) -> Result<Json<Vec<crate::models::Rating>>, StatusCode> {
+    let kind = parse_rating_kind(&params.kind)?;
+    crate::db::get_ratings_with_plays(&state.pool, kind)
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_ratings_report_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RatingsParams>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let kind = parse_rating_kind(&params.kind)?;
+    match reports::ratings::generate_ratings_report(&state.pool, kind) {
+        Ok(report) => versioned(&report, &schema),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// Admin maintenance handlers
+#[derive(Serialize)]
+pub struct AdminActionResponse {
+    success: bool,
+    message: String,
+}
+
+async fn admin_vacuum_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AdminActionResponse>, StatusCode> {
+    match crate::db::vacuum_database(&state.pool) {
+        Ok(()) => Ok(Json(AdminActionResponse {
+            success: true,
+            message: "Database vacuumed".to_string(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to vacuum database: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn admin_analyze_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AdminActionResponse>, StatusCode> {
+    match crate::db::analyze_database(&state.pool) {
+        Ok(()) => Ok(Json(AdminActionResponse {
+            success: true,
+            message: "Database statistics updated".to_string(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to analyze database: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Download a consistent snapshot of the live database. The snapshot is
+/// written to a temporary file that is unlinked once open, so it goes away
+/// when the download ends
+async fn admin_backup_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<axum::response::Response, StatusCode> {
+    use axum::http::header;
+
+    let now = Utc::now();
+    let path = std::env::temp_dir().join(format!(
+        "footprints-backup-{}-{}.sqlite",
+        std::process::id(),
+        now.timestamp_nanos_opt().unwrap_or_default()
+    ));
+
+    let pool = state.pool.clone();
+    let dest = path.clone();
+    let result = tokio::task::spawn_blocking(move || crate::db::backup_database(&pool, &dest))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Err(e) = result {
+        tracing::error!("Failed to back up database: {}", e);
+        let _ = std::fs::remove_file(&path);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let file = tokio::fs::File::open(&path).await;
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!("Failed to remove backup file {}: {}", path.display(), e);
+    }
+    let file = file.map_err(|e| {
+        tracing::error!("Failed to open database backup: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let length = file
+        .metadata()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .len();
+
+    let filename = format!("footprints_backup_{}.sqlite", now.format("%Y-%m-%d_%H%M%S"));
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/vnd.sqlite3")
+        .header(header::CONTENT_LENGTH, length)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(Body::from_stream(tokio_util::io::ReaderStream::new(file)))
+        .unwrap())
+}
+
+#[derive(Deserialize)]
+pub struct IntegrityCheckParams {
+    /// "full" (default) or "quick"
+    mode: Option<String>,
+}
+
+async fn admin_integrity_check_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<IntegrityCheckParams>,
+) -> Result<Json<crate::db::IntegrityReport>, StatusCode> {
+    let full = match params.mode.as_deref() {
+        None | Some("full") => true,
+        Some("quick") => false,
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || crate::db::check_integrity(&pool, full))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match result {
+        Ok(report) => {
+            if !report.ok {
+                tracing::error!(
+                    "Database integrity check found problems: {:?}",
+                    report.problems
+                );
+            }
+            Ok(Json(report))
+        }
+        Err(e) => {
+            tracing::error!("Failed to check database integrity: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn admin_db_stats_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match crate::db::get_database_stats(&state.pool) {
+        Ok(stats) => Ok(Json(stats)),
+        Err(e) => {
+            tracing::error!("Failed to read database stats: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReattributeParams {
+    /// Source to give the matching scrobbles
+    new_source: String,
+    source: Option<String>,
+    artist: Option<String>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReattributeResponse {
+    dry_run: bool,
+    matched: usize,
+    updated: usize,
+    /// Matches left alone because the same listen already exists under the new source
+    skipped: usize,
+}
+
+async fn reattribute_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<ReattributeParams>,
+) -> Result<Json<ReattributeResponse>, StatusCode> {
+    let clean = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let new_source = params.new_source.trim();
+    let filter = crate::db::ReattributeFilter {
+        source: clean(params.source),
+        artist: clean(params.artist),
+        start: params.start,
+        end: params.end,
+    };
+    if new_source.is_empty() || filter.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match crate::db::reattribute_scrobbles(&state.pool, &filter, new_source, params.dry_run) {
+        Ok((matched, updated)) => {
+            if !params.dry_run {
+                tracing::info!("Re-attributed {} scrobbles to {}", updated, new_source);
+            }
+            Ok(Json(ReattributeResponse {
+                dry_run: params.dry_run,
+                matched,
+                updated,
+                skipped: matched - updated,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to re-attribute scrobbles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Album artist compilations are credited to unless the request names another
+const VARIOUS_ARTISTS: &str = "Various Artists";
+
+#[derive(Deserialize)]
+pub struct MarkCompilationParams {
+    album: String,
+    album_artist: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct MarkCompilationResponse {
+    dry_run: bool,
+    album: String,
+    album_artist: String,
+    matched: usize,
+    updated: usize,
+}
+
+async fn get_compilations_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<crate::db::Compilation>>, StatusCode> {
+    match crate::db::get_compilations(&state.pool) {
+        Ok(compilations) => Ok(Json(compilations)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn mark_compilation_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<MarkCompilationParams>,
+) -> Result<Json<MarkCompilationResponse>, StatusCode> {
+    let album = params.album.trim();
+    let album_artist = params
+        .album_artist
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .unwrap_or(VARIOUS_ARTISTS);
+    if album.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match crate::db::mark_compilation(&state.pool, album, album_artist, params.dry_run) {
+        Ok((matched, updated)) => {
+            if !params.dry_run {
+                tracing::info!(
+                    "Credited {} scrobbles of {} to {}",
+                    updated,
+                    album,
+                    album_artist
+                );
+            }
+            Ok(Json(MarkCompilationResponse {
+                dry_run: params.dry_run,
+                album: album.to_string(),
+                album_artist: album_artist.to_string(),
+                matched,
+                updated,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to mark compilation: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveParams {
+    /// Scrobbles older than this many years are archived
+    older_than_years: u32,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct ArchiveResponse {
+    dry_run: bool,
+    before: DateTime<Utc>,
+    archived: usize,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreArchiveParams {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct RestoreArchiveResponse {
+    dry_run: bool,
+    restored: usize,
+}
+
+async fn get_archive_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<crate::db::ArchiveStats>, StatusCode> {
+    match crate::db::get_archive_stats(&state.pool) {
+        Ok(stats) => Ok(Json(stats)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn archive_scrobbles_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<ArchiveParams>,
+) -> Result<Json<ArchiveResponse>, StatusCode> {
+    if params.older_than_years == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let before = Utc::now()
+        .checked_sub_months(Months::new(params.older_than_years.saturating_mul(12)))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        crate::db::archive_scrobbles(&pool, before, params.dry_run)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match result {
+        Ok(archived) => {
+            if !params.dry_run {
+                tracing::info!("Archived {} scrobbles from before {}", archived, before);
+            }
+            Ok(Json(ArchiveResponse {
+                dry_run: params.dry_run,
+                before,
+                archived,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to archive scrobbles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn restore_archive_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<RestoreArchiveParams>,
+) -> Result<Json<RestoreArchiveResponse>, StatusCode> {
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        crate::db::restore_archived_scrobbles(&pool, params.dry_run)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match result {
+        Ok(restored) => {
+            if !params.dry_run {
+                tracing::info!("Restored {} archived scrobbles", restored);
+            }
+            Ok(Json(RestoreArchiveResponse {
+                dry_run: params.dry_run,
+                restored,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to restore archived scrobbles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Largest correction accepted, a full day either way
+const MAX_TIME_SHIFT_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Deserialize)]
+pub struct ShiftTimestampsParams {
+    source: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    /// Added to each timestamp; negative moves scrobbles earlier
+    offset_seconds: i64,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct ShiftTimestampsResponse {
+    dry_run: bool,
+    matched: usize,
+    updated: usize,
+    /// Matches left alone because the shifted listen already exists
+    skipped: usize,
+}
+
+async fn shift_timestamps_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<ShiftTimestampsParams>,
+) -> Result<Json<ShiftTimestampsResponse>, StatusCode> {
+    let source = params.source.trim();
+    if source.is_empty()
+        || params.start > params.end
+        || params.offset_seconds == 0
+        || params.offset_seconds.abs() > MAX_TIME_SHIFT_SECONDS
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let filter = crate::db::TimeShiftFilter {
+        source: source.to_string(),
+        start: params.start,
+        end: params.end,
+    };
+    match crate::db::shift_scrobble_timestamps(
+        &state.pool,
+        &filter,
+        params.offset_seconds,
+        params.dry_run,
+    ) {
+        Ok((matched, updated)) => {
+            if !params.dry_run {
+                tracing::info!(
+                    "Shifted {} {} scrobbles by {}s",
+                    updated,
+                    source,
+                    params.offset_seconds
+                );
+            }
+            Ok(Json(ShiftTimestampsResponse {
+                dry_run: params.dry_run,
+                matched,
+                updated,
+                skipped: matched - updated,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to shift scrobble timestamps: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn maintenance_log_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Vec<crate::db::MaintenanceLogEntry>>, StatusCode> {
+    match crate::db::get_maintenance_log(&state.pool, params.limit.unwrap_or(100)) {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => {
+            tracing::error!("Failed to read maintenance log: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn anomalies_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<anomalies::AnomalyReport>, StatusCode> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500) as usize;
+
+    match anomalies::find_anomalies(&state.pool, Utc::now(), limit) {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            tracing::error!("Failed to look for anomalies: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Share tokens
+
+// Longest a share link can be set to last, ten years
+const MAX_SHARE_DAYS: i64 = 3650;
+
+#[derive(Deserialize)]
+pub struct CreateShareTokenParams {
+    label: Option<String>,
+    #[serde(default = "default_share_scopes")]
+    scopes: Vec<String>,
+    expires_in_days: Option<i64>,
+}
+
+fn default_share_scopes() -> Vec<String> {
+    SHARE_SCOPES.iter().map(|s| s.to_string()).collect()
+}
+
+async fn create_share_token_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<CreateShareTokenParams>,
+) -> Result<Json<ShareToken>, StatusCode> {
+    if params.scopes.is_empty()
+        || params
+            .scopes
+            .iter()
+            .any(|scope| !SHARE_SCOPES.contains(&scope.as_str()))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    let mut share = ShareToken::new(token, params.scopes);
+
+    if let Some(label) = params.label {
+        share = share.with_label(label);
+    }
+
+    if let Some(days) = params.expires_in_days {
+        if !(1..=MAX_SHARE_DAYS).contains(&days) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let expires_at = Duration::try_days(days)
+            .and_then(|duration| Utc::now().checked_add_signed(duration))
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        share = share.with_expires_at(expires_at);
+    }
+
+    match crate::db::insert_share_token(&state.pool, &share) {
+        Ok(_) => Ok(Json(share)),
+        Err(e) => {
+            tracing::error!("Failed to create share token: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_share_tokens_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ShareToken>>, StatusCode> {
+    match crate::db::get_all_share_tokens(&state.pool) {
+        Ok(tokens) => Ok(Json(tokens)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn delete_share_token_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> StatusCode {
+    match crate::db::delete_share_token(&state.pool, &token) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Check that a share token exists, has not expired and grants `scope`
+fn authorize_share(pool: &DbPool, token: &str, scope: &str) -> Result<(), StatusCode> {
+    let share = crate::db::get_share_token(pool, token)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter(|share| !share.is_expired())
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if share.allows(scope) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+async fn share_stats_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Query(params): Query<StatsParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_share(&state.pool, &token, "stats")?;
+    load_stats(&state.pool, params.limit, params.credit).map(Json)
+}
+
+async fn share_years_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Json<Vec<i32>>, StatusCode> {
+    authorize_share(&state.pool, &token, "reports")?;
+    crate::db::get_available_years(&state.pool)
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn share_report_handler(
+    State(state): State<Arc<AppState>>,
+    Path((token, report_type)): Path<(String, String)>,
+    Query(schema): Query<SchemaParams>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_share(&state.pool, &token, "reports")?;
+    versioned(&build_report(&state.pool, &report_type)?, &schema)
+}
+
+// Enough for load tests without tying up the server for too long
+#[cfg(feature = "dev-tools")]
+const MAX_SEED_SCROBBLES: usize = 5_000_000;
+
+#[cfg(feature = "dev-tools")]
+fn default_seed() -> u64 {
+    42
+}
+
+#[cfg(feature = "dev-tools")]
+#[derive(Deserialize)]
+pub struct SeedParams {
+    scrobbles: usize,
+    #[serde(default = "default_seed")]
+    seed: u64,
+}
+
+#[cfg(feature = "dev-tools")]
+#[derive(Serialize)]
+pub struct SeedResponse {
+    inserted: usize,
+    source: &'static str,
+    end: DateTime<Utc>,
+}
+
+/// Fill the instance with synthetic scrobbles ending at the start of the
+/// current day, so seeding twice on the same day adds nothing
+#[cfg(feature = "dev-tools")]
+async fn dev_seed_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SeedParams>,
+) -> Result<Json<SeedResponse>, StatusCode> {
+    if params.scrobbles == 0 || params.scrobbles > MAX_SEED_SCROBBLES {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let end = Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        crate::demo::populate_synthetic(&pool, params.scrobbles, end, params.seed)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match result {
+        Ok(inserted) => {
+            tracing::info!("Seeded {} synthetic scrobbles", inserted);
+            Ok(Json(SeedResponse {
+                inserted,
+                source: crate::demo::DEMO_SOURCE,
+                end,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to seed synthetic scrobbles: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
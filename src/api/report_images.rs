@@ -0,0 +1,169 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{Request, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use super::AppState;
+use crate::images::{EntityType, ImageProvider, ImageRequest};
+
+// Image lookups in flight at once for one report
+const CONCURRENT_LOOKUPS: usize = 8;
+
+#[derive(Deserialize)]
+struct ImagesParams {
+    #[serde(default)]
+    include_images: bool,
+}
+
+/// Artist, album or track as named in a report object
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ImageKey {
+    entity_type: EntityType,
+    artist: String,
+    name: Option<String>,
+}
+
+impl ImageKey {
+    /// The entity an object with an `artist` names: its album or track when
+    /// it has one, the artist otherwise
+    fn of(object: &serde_json::Map<String, Value>) -> Option<Self> {
+        let artist = object.get("artist")?.as_str()?.to_string();
+        let named = |field: &str| object.get(field).and_then(Value::as_str).map(String::from);
+        let (entity_type, name) = if let Some(album) = named("album") {
+            (EntityType::Album, Some(album))
+        } else if let Some(track) = named("track") {
+            (EntityType::Track, Some(track))
+        } else {
+            (EntityType::Artist, None)
+        };
+        Some(Self {
+            entity_type,
+            artist,
+            name,
+        })
+    }
+
+    /// Albums only take their own cover; artists and tracks fall back to
+    /// related artwork, as the stats endpoints do
+    async fn resolve(self, images: &dyn ImageProvider) -> Option<String> {
+        match (self.entity_type, self.name) {
+            (EntityType::Album, Some(album)) => images
+                .get_image_url(ImageRequest::album(self.artist, album))
+                .await
+                .ok()
+                .flatten(),
+            (EntityType::Track, Some(track)) => {
+                images
+                    .get_best_image(ImageRequest::track(self.artist, track))
+                    .await
+            }
+            _ => {
+                images
+                    .get_best_image(ImageRequest::artist(self.artist))
+                    .await
+            }
+        }
+    }
+}
+
+/// With `include_images=true`, give every object of a `/api/reports/`
+/// response that names an artist an `image_url` for it, or for its album or
+/// track. Each distinct entity is looked up once, a few at a time, so the
+/// frontend doesn't follow up with a request per name
+pub async fn attach_report_images(
+    State(state): State<Arc<AppState>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let wanted = request.uri().path().starts_with("/api/reports/")
+        && Query::<ImagesParams>::try_from_uri(request.uri())
+            .is_ok_and(|Query(params)| params.include_images);
+    let response = next.run(request).await;
+    if !wanted || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(mut report) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let mut keys = HashSet::new();
+    collect_keys(&report, &mut keys);
+    let urls = resolve(&state.image_service, keys).await;
+    attach_urls(&mut report, &urls);
+
+    let body = serde_json::to_vec(&report).unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn collect_keys(value: &Value, keys: &mut HashSet<ImageKey>) {
+    match value {
+        Value::Object(object) => {
+            if !object.contains_key("image_url")
+                && let Some(key) = ImageKey::of(object)
+            {
+                keys.insert(key);
+            }
+            object.values().for_each(|value| collect_keys(value, keys));
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_keys(value, keys)),
+        _ => {}
+    }
+}
+
+async fn resolve(
+    images: &Arc<dyn ImageProvider>,
+    keys: HashSet<ImageKey>,
+) -> HashMap<ImageKey, Option<String>> {
+    let permits = Arc::new(Semaphore::new(CONCURRENT_LOOKUPS));
+    let mut lookups = JoinSet::new();
+    for key in keys {
+        let images = images.clone();
+        let permits = permits.clone();
+        lookups.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let url = key.clone().resolve(images.as_ref()).await;
+            (key, url)
+        });
+    }
+
+    let mut urls = HashMap::new();
+    while let Some(lookup) = lookups.join_next().await {
+        if let Ok((key, url)) = lookup {
+            urls.insert(key, url);
+        }
+    }
+    urls
+}
+
+fn attach_urls(value: &mut Value, urls: &HashMap<ImageKey, Option<String>>) {
+    match value {
+        Value::Object(object) => {
+            if !object.contains_key("image_url")
+                && let Some(url) = ImageKey::of(object).and_then(|key| urls.get(&key))
+            {
+                object.insert("image_url".to_string(), url.clone().into());
+            }
+            object
+                .values_mut()
+                .for_each(|value| attach_urls(value, urls));
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| attach_urls(value, urls)),
+        _ => {}
+    }
+}
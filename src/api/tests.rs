@@ -0,0 +1,2293 @@
+use super::*;
+use crate::images::{ImageService, StubImages};
+use crate::importers::http::MockHttp;
+use axum::body::to_bytes;
+use serde_json::{Value, json};
+use tempfile::NamedTempFile;
+use tower::ServiceExt;
+
+const RADIOHEAD_PICTURE: &str = "https://images.test/radiohead.jpg";
+const DUMMY_COVER: &str = "https://images.test/dummy.jpg";
+
+/// The whole API over a temporary database, with artwork that never leaves
+/// the process
+struct TestApp {
+    router: Router,
+    pool: DbPool,
+    _db: NamedTempFile,
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: axum::http::HeaderMap,
+    body: Vec<u8>,
+}
+
+impl TestResponse {
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!(
+                "{} is not JSON ({}): {}",
+                self.status,
+                e,
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+impl TestApp {
+    fn new() -> Self {
+        Self::with_options(InstanceOptions::default())
+    }
+
+    /// Artwork for Radiohead and for Portishead's Dummy only
+    fn with_options(options: InstanceOptions) -> Self {
+        Self::build(options, |_| {
+            Arc::new(
+                StubImages::default()
+                    .artist("Radiohead", RADIOHEAD_PICTURE)
+                    .album("Portishead", "Dummy", DUMMY_COVER),
+            )
+        })
+    }
+
+    /// The real image service and its caches, with Last.fm and Deezer
+    /// answered by `http`
+    fn with_image_service(http: MockHttp) -> Self {
+        Self::build(InstanceOptions::default(), |pool| {
+            Arc::new(ImageService::new(pool.clone(), "key".to_string()).with_http(Arc::new(http)))
+        })
+    }
+
+    fn build(
+        options: InstanceOptions,
+        images: impl FnOnce(&DbPool) -> Arc<dyn ImageProvider>,
+    ) -> Self {
+        let db = NamedTempFile::new().unwrap();
+        let pool = crate::db::create_pool(db.path().to_str().unwrap()).unwrap();
+        crate::db::init_database(&pool).unwrap();
+
+        let router = create_router(
+            pool.clone(),
+            images(&pool),
+            SyncScheduler::new(pool.clone()),
+            LiveHub::new(pool.clone()),
+            None,
+            Normalizer::default(),
+            options,
+        );
+
+        Self {
+            router,
+            pool,
+            _db: db,
+        }
+    }
+
+    /// The same database served the way `READ_ONLY=true` serves it
+    fn reopen_read_only(self) -> Self {
+        let pool = crate::db::create_read_only_pool(self._db.path().to_str().unwrap()).unwrap();
+        let router = create_router(
+            pool.clone(),
+            Arc::new(StubImages::default().artist("Radiohead", RADIOHEAD_PICTURE)),
+            SyncScheduler::new(pool.clone()),
+            LiveHub::new(pool.clone()),
+            None,
+            Normalizer::default(),
+            InstanceOptions {
+                read_only: true,
+                ..InstanceOptions::default()
+            },
+        );
+
+        Self {
+            router,
+            pool,
+            _db: self._db,
+        }
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let builder = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        TestResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap()
+                .to_vec(),
+        }
+    }
+
+    async fn get(&self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    async fn put(&self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::PUT, uri, Some(body)).await
+    }
+
+    async fn delete(&self, uri: &str) -> TestResponse {
+        self.request(Method::DELETE, uri, None).await
+    }
+
+    /// Insert scrobbles directly, bypassing the API
+    fn seed(&self, scrobbles: &[Scrobble]) {
+        crate::db::insert_scrobbles_batch(&self.pool, scrobbles).unwrap();
+    }
+}
+
+fn at(timestamp: &str) -> DateTime<Utc> {
+    timestamp.parse().unwrap()
+}
+
+fn play(artist: &str, album: &str, track: &str, timestamp: DateTime<Utc>) -> Scrobble {
+    Scrobble::new(
+        artist.to_string(),
+        track.to_string(),
+        timestamp,
+        "lastfm".to_string(),
+    )
+    .with_album(album.to_string())
+}
+
+/// A small library: an evening of OK Computer in 2023, a mixed session in
+/// March 2024 and a play an hour ago
+fn library() -> TestApp {
+    let app = TestApp::new();
+    let mut scrobbles = Vec::new();
+
+    let evening = at("2023-06-01T20:00:00Z");
+    for (i, track) in [
+        "Airbag",
+        "Paranoid Android",
+        "Subterranean Homesick Alien",
+        "Exit Music",
+        "Let Down",
+    ]
+    .iter()
+    .enumerate()
+    {
+        scrobbles.push(play(
+            "Radiohead",
+            "OK Computer",
+            track,
+            evening + Duration::minutes(i as i64 * 5),
+        ));
+    }
+
+    let session = at("2024-03-10T21:00:00Z");
+    for (i, (artist, album, track)) in [
+        ("Radiohead", "OK Computer", "Airbag"),
+        ("Portishead", "Dummy", "Mysterons"),
+        ("Radiohead", "OK Computer", "Karma Police"),
+        ("Portishead", "Dummy", "Sour Times"),
+        ("Radiohead", "OK Computer", "Airbag"),
+        ("Portishead", "Dummy", "Roads"),
+    ]
+    .iter()
+    .enumerate()
+    {
+        scrobbles.push(play(
+            artist,
+            album,
+            track,
+            session + Duration::minutes(i as i64 * 4),
+        ));
+    }
+
+    scrobbles.push(play(
+        "Portishead",
+        "Dummy",
+        "Glory Box",
+        Utc::now() - Duration::hours(1),
+    ));
+
+    app.seed(&scrobbles);
+    app
+}
+
+/// Id of the newest scrobble of `track`
+fn scrobble_id(app: &TestApp, track: &str) -> i64 {
+    crate::db::get_scrobbles(&app.pool, None, None)
+        .unwrap()
+        .into_iter()
+        .find(|s| s.track == track)
+        .and_then(|s| s.id)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_index_instance_and_unknown_routes() {
+    let app = library();
+
+    let index = app.get("/").await;
+    assert_eq!(index.status, StatusCode::OK);
+    assert!(index.text().contains("<html"));
+
+    // No proxy authentication configured, so nobody is signed in
+    assert_eq!(app.get("/api/me").await.status, StatusCode::NOT_FOUND);
+
+    let instance = app.get("/api/instance").await;
+    assert_eq!(instance.status, StatusCode::OK);
+    let instance = instance.json();
+    assert_eq!(instance["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(instance["scrobble_count"], 12);
+    assert_eq!(instance["earliest_scrobble"], "2023-06-01T20:00:00Z");
+    assert_eq!(instance["sources"], json!([]));
+    assert_eq!(instance["features"]["read_only"], false);
+    assert_eq!(instance["features"]["auth"], false);
+    assert_eq!(instance["features"]["sync_scheduler"], false);
+
+    assert_eq!(app.get("/api/nothing").await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        app.delete("/api/stats").await.status,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    // Upgrading to a WebSocket needs the handshake headers
+    assert!(app.get("/api/ws").await.status.is_client_error());
+}
+
+#[tokio::test]
+async fn test_scrobble_pages_and_ranges() {
+    let app = library();
+
+    let page = app.get("/api/scrobbles?limit=2").await;
+    assert_eq!(page.status, StatusCode::OK);
+    let page = page.json();
+    assert_eq!(page.as_array().unwrap().len(), 2);
+    assert_eq!(page[0]["track"], "Glory Box");
+    assert_eq!(page[1]["track"], "Roads");
+    for field in ["id", "artist", "album", "track", "timestamp", "source"] {
+        assert!(page[0].get(field).is_some(), "scrobbles have {}", field);
+    }
+
+    let next = app.get("/api/scrobbles?limit=2&offset=2").await.json();
+    assert_eq!(next[0]["track"], "Airbag");
+    assert_eq!(next[1]["track"], "Sour Times");
+    let past_the_end = app.get("/api/scrobbles?limit=5&offset=100").await.json();
+    assert_eq!(past_the_end, json!([]));
+
+    let march = app
+        .get("/api/scrobbles?start=2024-03-01T00:00:00Z&end=2024-03-31T23:59:59Z")
+        .await
+        .json();
+    assert_eq!(march.as_array().unwrap().len(), 6);
+
+    // Both bounds or neither, in order
+    for uri in [
+        "/api/scrobbles?start=2024-03-01T00:00:00Z",
+        "/api/scrobbles?start=2024-04-01T00:00:00Z&end=2024-03-01T00:00:00Z",
+    ] {
+        assert_eq!(
+            app.get(uri).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+    // Malformed query strings are rejected with an explanation
+    let invalid = app.get("/api/scrobbles?limit=many").await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    assert!(invalid.text().contains("invalid digit"));
+}
+
+#[tokio::test]
+async fn test_get_and_delete_a_scrobble() {
+    let app = library();
+    let id = scrobble_id(&app, "Glory Box");
+
+    let scrobble = app.get(&format!("/api/scrobbles/{}", id)).await;
+    assert_eq!(scrobble.status, StatusCode::OK);
+    assert_eq!(scrobble.json()["artist"], "Portishead");
+
+    assert_eq!(
+        app.get("/api/scrobbles/999999").await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        app.get("/api/scrobbles/latest").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let uri = format!("/api/scrobbles/{}", id);
+    let deleted = app.delete(&uri).await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert!(deleted.body.is_empty());
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(app.get(&uri).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_submit_manual_scrobbles() {
+    let app = TestApp::new();
+    let entry = json!({
+        "artist": "Low",
+        "track": "Words",
+        "album": "I Could Live in Hope",
+        "timestamp": "2024-05-01T20:00:00Z",
+    });
+
+    let submitted = app.post("/api/scrobbles", entry.clone()).await;
+    assert_eq!(submitted.status, StatusCode::OK);
+    assert_eq!(
+        submitted.json(),
+        json!({"success": true, "inserted": 1, "duplicates": 0, "filtered": 0})
+    );
+    let again = app.post("/api/scrobbles", json!([entry])).await.json();
+    assert_eq!(again["inserted"], 0);
+    assert_eq!(again["duplicates"], 1);
+
+    let stored = app.get("/api/scrobbles").await.json();
+    assert_eq!(stored[0]["source"], "manual");
+
+    // Nothing is stored when any entry is invalid
+    let invalid = app
+        .post(
+            "/api/scrobbles",
+            json!([
+                {"artist": "Low", "track": "Lazy", "timestamp": "2024-05-01T20:05:00Z"},
+                {"artist": " ", "track": "Lazy", "timestamp": "2099-01-01T00:00:00Z"},
+            ]),
+        )
+        .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    let invalid = invalid.json();
+    assert_eq!(invalid["success"], false);
+    assert_eq!(invalid["inserted"], 0);
+    let errors = invalid["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0],
+        json!({"index": 1, "field": "artist", "message": "is required"})
+    );
+    assert_eq!(errors[1]["field"], "timestamp");
+    assert_eq!(crate::db::get_scrobbles_count(&app.pool).unwrap(), 1);
+
+    let empty = app.post("/api/scrobbles", json!([])).await;
+    assert_eq!(empty.status, StatusCode::BAD_REQUEST);
+    assert_eq!(empty.json()["errors"][0]["field"], "scrobbles");
+}
+
+#[tokio::test]
+async fn test_submit_album_with_tracklist() {
+    let app = TestApp::new();
+
+    let submitted = app
+        .post(
+            "/api/scrobbles/bulk",
+            json!({
+                "artist": "Slint",
+                "album": "Spiderland",
+                "start": "2024-05-01T20:00:00Z",
+                "tracks": ["Breadcrumb Trail", {"title": "Nosferatu Man", "duration_seconds": 336}, "Don, Aman"],
+            }),
+        )
+        .await;
+    assert_eq!(submitted.status, StatusCode::OK);
+    assert_eq!(submitted.json()["inserted"], 3);
+
+    let stored = app.get("/api/scrobbles").await.json();
+    assert_eq!(stored[0]["track"], "Don, Aman");
+    assert_eq!(stored[2]["album"], "Spiderland");
+
+    // A tracklist without an artist can't be stored
+    let invalid = app
+        .post(
+            "/api/scrobbles/bulk",
+            json!({"start": "2024-05-01T20:00:00Z", "tracks": ["Washer"]}),
+        )
+        .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.json()["errors"][0]["field"], "artist");
+
+    // Durations too long to add up are rejected, not a crash
+    let overflow = app
+        .post(
+            "/api/scrobbles/bulk",
+            json!({
+                "artist": "Slint",
+                "start": "2024-05-01T20:00:00Z",
+                "tracks": [{"title": "Good Morning, Captain", "duration_seconds": i64::MAX}, "Washer"],
+            }),
+        )
+        .await;
+    assert_eq!(overflow.status, StatusCode::BAD_REQUEST);
+    assert_eq!(overflow.json()["errors"][0]["field"], "duration_seconds");
+    assert_eq!(crate::db::get_scrobbles_count(&app.pool).unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_json_body_rejections() {
+    let app = TestApp::new();
+
+    let malformed = app.request(Method::POST, "/api/notes", None).await;
+    assert_eq!(malformed.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/notes")
+        .header("content-type", "application/json")
+        .body(Body::from("{not json"))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let missing_field = app.post("/api/notes", json!({"date": "2024-05-01"})).await;
+    assert_eq!(missing_field.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(missing_field.text().contains("body"));
+}
+
+#[tokio::test]
+async fn test_settings_and_listen_filter() {
+    let app = TestApp::new();
+
+    let settings = app.get("/api/settings").await;
+    assert_eq!(settings.status, StatusCode::OK);
+    let settings = settings.json();
+    assert_eq!(settings["timezone"], "UTC");
+    assert_eq!(settings["top_list_size"], 15);
+    assert!(settings["image_providers"].is_array());
+
+    let updated = app
+        .put(
+            "/api/settings",
+            json!({"top_list_size": 5, "timezone": "Europe/Paris"}),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    let updated = updated.json();
+    assert_eq!(updated["success"], true);
+    assert_eq!(updated["settings"]["top_list_size"], 5);
+    assert!(updated.get("errors").is_none());
+    assert_eq!(
+        app.get("/api/settings").await.json()["timezone"],
+        "Europe/Paris"
+    );
+
+    let invalid = app
+        .put("/api/settings", json!({"timezone": "Mars/Olympus"}))
+        .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    let invalid = invalid.json();
+    assert_eq!(invalid["success"], false);
+    assert_eq!(invalid["settings"], Value::Null);
+    assert_eq!(invalid["errors"][0]["field"], "timezone");
+
+    let filter = app.get("/api/settings/listen-filter").await.json();
+    for field in ["min_track_seconds", "min_play_seconds", "min_play_percent"] {
+        assert!(filter[field].is_i64(), "listen filter has {}", field);
+    }
+    let changed = json!({"min_track_seconds": 20, "min_play_seconds": 120, "min_play_percent": 40});
+    let saved = app
+        .put("/api/settings/listen-filter", changed.clone())
+        .await;
+    assert_eq!(saved.status, StatusCode::OK);
+    assert_eq!(saved.json()["filter"], changed);
+    assert_eq!(app.get("/api/settings/listen-filter").await.json(), changed);
+
+    let invalid = app
+        .put(
+            "/api/settings/listen-filter",
+            json!({"min_track_seconds": 20, "min_play_seconds": 120, "min_play_percent": 140}),
+        )
+        .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.json()["errors"][0]["field"], "min_play_percent");
+}
+
+#[tokio::test]
+async fn test_stats_with_artwork_and_movement() {
+    let app = library();
+
+    let stats = app.get("/api/stats?limit=1").await;
+    assert_eq!(stats.status, StatusCode::OK);
+    assert_eq!(
+        stats.json(),
+        json!({
+            "total_scrobbles": 12,
+            "top_artists": [["Radiohead", 8]],
+            "top_tracks": [["Radiohead", "Airbag", 3]],
+        })
+    );
+
+    let march = app
+        .get("/api/stats/ui?period=custom&start=2024-03-01T00:00:00Z&end=2024-03-31T23:59:59Z")
+        .await;
+    assert_eq!(march.status, StatusCode::OK);
+    let march = march.json();
+    assert_eq!(march["period"], "custom");
+    assert_eq!(march["period_scrobbles"], 6);
+    assert_eq!(march["previous_period"]["scrobbles"], 0);
+    assert_eq!(march["previous_period"]["count_change"], 6);
+    assert_eq!(march["previous_period"]["change_percent"], Value::Null);
+
+    // Portishead has no picture
+    let artists = march["top_artists"].as_array().unwrap();
+    assert_eq!(artists.len(), 2);
+    let radiohead = artists.iter().find(|a| a["name"] == "Radiohead").unwrap();
+    assert_eq!(radiohead["image_url"], RADIOHEAD_PICTURE);
+    assert_eq!(radiohead["count"], 3);
+    assert_eq!(radiohead["previous_rank"], Value::Null);
+    let portishead = artists.iter().find(|a| a["name"] == "Portishead").unwrap();
+    assert_eq!(portishead["image_url"], Value::Null);
+    assert_eq!(march["top_tracks"][0]["track"], "Airbag");
+    assert_eq!(march["top_tracks"][0]["image_url"], RADIOHEAD_PICTURE);
+    let albums = march["top_albums"].as_array().unwrap();
+    assert_eq!(albums.len(), 2);
+    let dummy = albums.iter().find(|a| a["album"] == "Dummy").unwrap();
+    assert_eq!(dummy["image_url"], DUMMY_COVER);
+
+    // All time has no previous period
+    let all_time = app.get("/api/stats/ui").await.json();
+    assert_eq!(all_time["period_scrobbles"], 12);
+    assert_eq!(all_time["previous_period"], Value::Null);
+
+    assert_eq!(
+        app.get("/api/stats/ui?period=custom&start=2024-03-01T00:00:00Z")
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn test_overview_years_and_pulse() {
+    let app = library();
+
+    let overview = app.get("/api/overview").await;
+    assert_eq!(overview.status, StatusCode::OK);
+    let overview = overview.json();
+    assert_eq!(
+        overview["totals"],
+        json!({"scrobbles": 12, "artists": 2, "albums": 2, "tracks": 10})
+    );
+    assert_eq!(overview["week"]["scrobbles"], 1);
+    assert_eq!(overview["streak"]["days"], 1);
+    assert_eq!(overview["now_playing"], json!([]));
+    assert_eq!(
+        overview["top_artists"][0],
+        json!({"name": "Radiohead", "count": 8})
+    );
+    assert_eq!(
+        app.get("/api/overview?period=custom").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let this_year = Utc::now().year();
+    assert_eq!(
+        app.get("/api/years").await.json(),
+        json!([this_year, 2024, 2023])
+    );
+
+    let pulse = app
+        .get("/api/pulse?period=custom&start=2024-03-10T00:00:00Z&end=2024-03-10T23:59:59Z&granularity=hour")
+        .await
+        .json();
+    let hours = pulse.as_array().unwrap();
+    assert_eq!(hours.len(), 24);
+    assert_eq!(hours[21], json!({"day": "2024-03-10T21:00", "count": 6}));
+    assert_eq!(
+        app.get("/api/pulse?period=custom").await.status,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        app.get("/api/pulse?granularity=fortnight").await.status,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_timezone_is_rejected() {
+    let app = library();
+
+    for uri in [
+        "/api/pulse?timezone=Mars/Olympus",
+        "/api/timeline?timezone=Mars/Olympus",
+        "/api/calendar/2024/3?timezone=Mars/Olympus",
+        "/api/reports/heatmap?timezone=Mars/Olympus",
+        "/api/reports/sleep?timezone=Mars/Olympus",
+        "/api/digest?timezone=Mars/Olympus",
+    ] {
+        assert_eq!(
+            app.get(uri).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+    assert_eq!(
+        app.get("/api/calendar/2024/3?timezone=Europe/Paris")
+            .await
+            .status,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_period_reports() {
+    let app = library();
+
+    let year = app.get("/api/reports/2024").await;
+    assert_eq!(year.status, StatusCode::OK);
+    let year = year.json();
+    assert_eq!(year["period"], "Year 2024");
+    assert_eq!(year["total_scrobbles"], 6);
+    assert_eq!(year["top_tracks"][0], json!(["Radiohead", "Airbag", 2]));
+    assert_eq!(
+        year["schema_version"],
+        reports::schema::REPORT_SCHEMA_VERSION
+    );
+
+    for (uri, scrobbles) in [
+        ("/api/reports/alltime", 12),
+        ("/api/reports/2024-Q1", 6),
+        ("/api/reports/2024-H2", 0),
+        ("/api/reports/2023", 5),
+        ("/api/reports/monthly?year=2023&month=6", 5),
+    ] {
+        let report = app.get(uri).await;
+        assert_eq!(report.status, StatusCode::OK, "{}", uri);
+        assert_eq!(report.json()["total_scrobbles"], scrobbles, "{}", uri);
+    }
+    assert_eq!(
+        app.get("/api/reports/lastmonth").await.status,
+        StatusCode::OK
+    );
+
+    for uri in [
+        "/api/reports/1969",
+        "/api/reports/20x4",
+        "/api/reports/2024-Q5",
+        "/api/reports/someday",
+        "/api/reports/monthly?year=2024&month=13",
+        "/api/reports/2024?schema_version=0",
+        "/api/reports/2024?schema_version=99",
+    ] {
+        assert_eq!(
+            app.get(uri).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+
+    let yearly = app.get("/api/reports/yearly/2024").await;
+    assert_eq!(yearly.status, StatusCode::OK);
+    let yearly = yearly.json();
+    assert_eq!(yearly["discoveries"]["new_artists"], 1);
+    assert_eq!(
+        yearly["discoveries"]["first_artist"]["artist"],
+        "Portishead"
+    );
+    assert_eq!(yearly["listening_patterns"]["peak_hour"], 21);
+    assert_eq!(yearly["milestones"][0]["icon"], "stopwatch");
+
+    // Emoji on request, and for clients of the first schema
+    for uri in [
+        "/api/reports/yearly/2024?emoji_icons=true",
+        "/api/reports/yearly/2024?schema_version=1",
+    ] {
+        let yearly = app.get(uri).await.json();
+        assert_eq!(
+            yearly["milestones"][0]["icon"], "\u{23f1}\u{fe0f}",
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_milestone_rules() {
+    let app = library();
+
+    let rule = app
+        .post(
+            "/api/milestones/rules",
+            json!({
+                "title": "Regular",
+                "description": "{value} plays of one artist",
+                "metric": "top_artist_plays",
+                "threshold": 3
+            }),
+        )
+        .await;
+    assert_eq!(rule.status, StatusCode::OK);
+    let rule = rule.json();
+    assert_eq!(rule["icon"], "trophy");
+    for invalid in [
+        json!({"title": " ", "metric": "total_hours", "threshold": 1}),
+        json!({"title": "Negative", "metric": "total_hours", "threshold": -1}),
+    ] {
+        assert_eq!(
+            app.post("/api/milestones/rules", invalid).await.status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+    assert_eq!(
+        app.post(
+            "/api/milestones/rules",
+            json!({"title": "Odd", "metric": "sometimes", "threshold": 1})
+        )
+        .await
+        .status,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    // Awarded after the built-in milestones
+    let yearly = app.get("/api/reports/yearly/2024").await.json();
+    let last = yearly["milestones"].as_array().unwrap().last().unwrap();
+    assert_eq!(last["title"], "Regular");
+    assert_eq!(last["description"], "3 plays of one artist");
+
+    let uri = format!("/api/milestones/rules/{}", rule["id"]);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        app.get("/api/milestones/rules")
+            .await
+            .json()
+            .as_array()
+            .unwrap()
+            .len(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_achievements() {
+    let app = library();
+    assert_eq!(app.get("/api/achievements").await.json(), json!([]));
+
+    let earned = crate::achievements::record_earned(&app.pool, at("2024-12-31T12:00:00Z")).unwrap();
+    assert!(!earned.is_empty());
+
+    let achievements = app.get("/api/achievements?year=2024").await.json();
+    assert_eq!(achievements.as_array().unwrap().len(), earned.len());
+    assert_eq!(achievements[0]["year"], 2024);
+    assert_eq!(achievements[0]["icon"], "stopwatch");
+    assert_eq!(
+        app.get("/api/achievements?year=2023").await.json(),
+        json!([])
+    );
+}
+
+#[tokio::test]
+async fn test_wrapped_bundle() {
+    let app = library();
+
+    let wrapped = app.get("/api/reports/wrapped/2024").await.json();
+    assert_eq!(wrapped["year"], 2024);
+    assert_eq!(wrapped["yearly"]["overview"]["total_scrobbles"], 6);
+    assert!(wrapped["yearly"]["milestones"].is_array());
+    assert_eq!(wrapped["heatmap"]["total_scrobbles"], 6);
+    assert_eq!(wrapped["novelty"]["total_scrobbles"], 6);
+
+    let radiohead = wrapped["top_artists"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|artist| artist["artist"] == "Radiohead")
+        .unwrap();
+    assert_eq!(radiohead["play_count"], 3);
+    assert_eq!(radiohead["image_url"], RADIOHEAD_PICTURE);
+    let dummy = wrapped["top_albums"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|album| album["album"] == "Dummy")
+        .unwrap();
+    assert_eq!(dummy["image_url"], DUMMY_COVER);
+
+    let v1 = app
+        .get("/api/reports/wrapped/2024?schema_version=1")
+        .await
+        .json();
+    assert!(v1["yearly"].get("monthly_breakdown").is_none());
+    assert_eq!(
+        app.get("/api/reports/wrapped/2024?schema_version=9")
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn test_report_images() {
+    let app = library();
+
+    let plain = app.get("/api/reports/tenure").await.json();
+    assert!(plain["artists"][0].get("image_url").is_none());
+
+    let tenure = app
+        .get("/api/reports/tenure?include_images=true")
+        .await
+        .json();
+    for artist in tenure["artists"].as_array().unwrap() {
+        let expected = match artist["artist"].as_str().unwrap() {
+            "Radiohead" => json!(RADIOHEAD_PICTURE),
+            _ => json!(null),
+        };
+        assert_eq!(artist["image_url"], expected);
+    }
+
+    // Albums take their cover, not the artist's picture
+    let albums = app
+        .get("/api/reports/album-listens?include_images=true")
+        .await
+        .json();
+    assert_eq!(albums["albums"][0]["album"], "OK Computer");
+    assert_eq!(albums["albums"][0]["image_url"], json!(null));
+}
+
+#[tokio::test]
+async fn test_listening_reports() {
+    let app = library();
+
+    let heatmap = app.get("/api/reports/heatmap").await;
+    assert_eq!(heatmap.status, StatusCode::OK);
+    let grid = heatmap.json()["grid"].clone();
+    assert_eq!(grid.as_array().unwrap().len(), 7);
+    assert_eq!(grid[0]["hours"].as_array().unwrap().len(), 24);
+
+    let dayparts = app.get("/api/reports/dayparts").await.json();
+    assert_eq!(dayparts["peak_part"], "evening");
+    assert_eq!(dayparts["total_scrobbles"], 12);
+    let custom = app
+        .get("/api/reports/dayparts?parts=day:8,night:20")
+        .await
+        .json();
+    assert_eq!(custom["parts"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        app.get("/api/reports/dayparts?parts=noon").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let novelty = app
+        .get("/api/reports/novelty?granularity=month")
+        .await
+        .json();
+    assert_eq!(novelty["summary"]["total_unique_artists"], 2);
+    assert_eq!(novelty["new_artists_discovered"][0]["artist"], "Portishead");
+
+    let transitions = app.get("/api/reports/transitions").await.json();
+    assert_eq!(
+        transitions["summary"]["most_common_transition"]["from_artist"],
+        "Radiohead"
+    );
+    assert_eq!(
+        transitions["network_data"]["nodes"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    let limited = app.get("/api/reports/transitions?limit=1").await.json();
+    assert_eq!(limited["transitions"].as_array().unwrap().len(), 1);
+    assert_eq!(limited["summary"]["unique_transitions"], 2);
+
+    let diversity = app.get("/api/reports/diversity").await.json();
+    assert_eq!(diversity["summary"]["total_scrobbles"], 12);
+
+    let albums = app.get("/api/reports/album-listens").await.json();
+    assert_eq!(albums["albums"][0]["album"], "OK Computer");
+    assert_eq!(albums["albums"][0]["full_listens"], 1);
+
+    let styles = app.get("/api/reports/listening-styles").await.json();
+    assert_eq!(styles["periods"][0]["period"], "2023-06");
+
+    let tenure = app.get("/api/reports/tenure").await.json();
+    let radiohead = tenure["artists"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|artist| artist["artist"] == "Radiohead")
+        .unwrap();
+    assert_eq!(radiohead["first_listen"], "2023-06-01T20:00:00Z");
+    assert_eq!(radiohead["span_months"], 10);
+    assert_eq!(radiohead["active_months"], 2);
+    assert_eq!(radiohead["kind"], "occasional");
+
+    let half_life = app.get("/api/reports/half-life?min_plays=2").await.json();
+    assert_eq!(half_life["window_days"], 28);
+    assert!(half_life["tracks"].is_array());
+
+    let consistency = app
+        .get("/api/reports/consistency?granularity=year")
+        .await
+        .json();
+    assert_eq!(consistency["periods"][0]["period"], "2023");
+    assert_eq!(consistency["summary"]["scrobbles"], 12);
+
+    let records = app.get("/api/reports/records").await.json();
+    assert_eq!(records["most_scrobbles_in_month"]["period"], "2024-03");
+    assert_eq!(records["most_scrobbles_in_month"]["scrobbles"], 6);
+    assert_eq!(records["longest_session"]["duration_minutes"], 20);
+
+    // No play durations are known, so nothing counts as a skip
+    let skips = app.get("/api/reports/skips").await.json();
+    assert_eq!(skips["summary"]["tracked_plays"], 0);
+
+    let sleep = app.get("/api/reports/sleep").await.json();
+    assert_eq!(sleep["detections"], json!([]));
+    assert_eq!(sleep["candidates"], json!([]));
+    let scan = app.post("/api/sleep-detections/scan", json!({})).await;
+    assert_eq!(scan.status, StatusCode::OK);
+    assert_eq!(scan.json()["recorded"], 0);
+    for action in ["confirm", "dismiss"] {
+        let uri = format!("/api/sleep-detections/42/{}", action);
+        assert_eq!(
+            app.post(&uri, json!({})).await.status,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    let calendar = app.get("/api/calendar/2024/3").await;
+    assert_eq!(calendar.status, StatusCode::OK);
+    let days = calendar.json()["days"].clone();
+    assert_eq!(days.as_array().unwrap().len(), 31);
+    assert_eq!(days[9]["count"], 6);
+    assert_eq!(days[9]["new_artists"], json!(["Portishead"]));
+    for uri in ["/api/calendar/2024/13", "/api/calendar/1900/1"] {
+        assert_eq!(
+            app.get(uri).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_timeline_and_notes() {
+    let app = library();
+    let roads = scrobble_id(&app, "Roads");
+
+    let note = app
+        .post(
+            "/api/notes",
+            json!({"scrobble_id": roads, "body": "On the night bus"}),
+        )
+        .await;
+    assert_eq!(note.status, StatusCode::OK);
+    let note = note.json();
+    let note_id = note["id"].as_i64().unwrap();
+    assert_eq!(note["scrobble_id"], roads);
+
+    let day_note = app
+        .post(
+            "/api/notes",
+            json!({"date": "2024-03-10", "body": "Rainy Sunday"}),
+        )
+        .await
+        .json();
+    assert_eq!(day_note["date"], "2024-03-10");
+
+    for invalid in [
+        json!({"body": "Nowhere"}),
+        json!({"scrobble_id": roads, "date": "2024-03-10", "body": "Both"}),
+        json!({"date": "2024-03-10", "body": "  "}),
+    ] {
+        assert_eq!(
+            app.post("/api/notes", invalid.clone()).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            invalid
+        );
+    }
+    assert_eq!(
+        app.post("/api/notes", json!({"scrobble_id": 999999, "body": "Lost"}))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+
+    // The timeline attaches notes to their scrobble and to the day's first entry
+    let timeline = app.get("/api/timeline?limit=3").await.json();
+    assert_eq!(timeline[1]["track"], "Roads");
+    assert_eq!(timeline[1]["notes"][0]["body"], "On the night bus");
+    assert_eq!(timeline[1]["day_notes"][0]["body"], "Rainy Sunday");
+    assert!(timeline[2].get("notes").is_none());
+
+    let sessions = app.get("/api/timeline?group=session").await.json();
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions[1]["track_count"], 6);
+    assert_eq!(sessions[1]["artist_count"], 2);
+    assert_eq!(sessions[1]["duration_minutes"], 20);
+    assert_eq!(
+        app.get("/api/timeline?group=album").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let uri = format!("/api/notes/{}", note_id);
+    assert_eq!(app.get(&uri).await.json()["body"], "On the night bus");
+    let updated = app.put(&uri, json!({"body": "On the last bus"})).await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(updated.json()["body"], "On the last bus");
+    assert_eq!(
+        app.post(&uri, json!({"body": "On the bus"})).await.status,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    assert_eq!(
+        app.put(&uri, json!({"body": ""})).await.status,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        app.put("/api/notes/999999", json!({"body": "Lost"}))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        app.get("/api/notes").await.json().as_array().unwrap().len(),
+        2
+    );
+
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(app.get(&uri).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_annotations() {
+    let app = library();
+
+    let created = app
+        .post(
+            "/api/annotations",
+            json!({"date": "2024-03-09", "kind": "concert", "label": "Portishead live", "artist": "Portishead"}),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::OK);
+    let created = created.json();
+    let uri = format!("/api/annotations/{}", created["id"]);
+    assert_eq!(created["kind"], "concert");
+
+    for invalid in [
+        json!({"date": "2024-03-09", "label": " "}),
+        json!({"date": "2024-03-09", "end_date": "2024-03-01", "label": "Backwards"}),
+    ] {
+        assert_eq!(
+            app.post("/api/annotations", invalid.clone()).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            invalid
+        );
+    }
+
+    let portishead = app.get("/api/annotations?artist=Portishead").await.json();
+    assert_eq!(portishead.as_array().unwrap().len(), 1);
+    let radiohead = app.get("/api/annotations?artist=Radiohead").await.json();
+    assert_eq!(radiohead, json!([]));
+
+    // Shown as markers on the artist page
+    let artist = app.get("/api/artist?artist=Portishead").await.json();
+    assert_eq!(artist["annotations"][0]["label"], "Portishead live");
+
+    let updated = app
+        .put(
+            &uri,
+            json!({"date": "2024-03-09", "kind": "concert", "label": "Portishead, Bristol", "artist": "Portishead"}),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(app.get(&uri).await.json()["label"], "Portishead, Bristol");
+    assert_eq!(
+        app.post(&uri, json!({"date": "2024-03-09", "label": "Bristol"}))
+            .await
+            .status,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.get(&uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        app.put(&uri, json!({"date": "2024-03-09", "label": "Gone"}))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_ignore_alert_and_media_rules() {
+    let app = library();
+
+    let rule = app
+        .post(
+            "/api/ignore-rules",
+            json!({"artist": "White Noise", "source": " "}),
+        )
+        .await;
+    assert_eq!(rule.status, StatusCode::OK);
+    let rule = rule.json();
+    assert_eq!(rule["source"], Value::Null);
+    let uri = format!("/api/ignore-rules/{}", rule["id"]);
+    assert_eq!(
+        app.post("/api/ignore-rules", json!({"source": "lastfm"}))
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+    let updated = app.put(&uri, json!({"track": "Rain Sounds"})).await.json();
+    assert_eq!(updated["artist"], Value::Null);
+    assert_eq!(updated["track"], "Rain Sounds");
+    assert_eq!(
+        app.post(&uri, json!({"track": "Rain"})).await.status,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    assert_eq!(
+        app.get("/api/ignore-rules")
+            .await
+            .json()
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.get(&uri).await.status, StatusCode::NOT_FOUND);
+
+    let alert = app
+        .post(
+            "/api/alerts",
+            json!({"kind": "no_scrobbles", "threshold_hours": 48}),
+        )
+        .await;
+    assert_eq!(alert.status, StatusCode::OK);
+    let alert = alert.json();
+    assert_eq!(alert["kind"], "no_scrobbles");
+    for threshold in [0, 8761] {
+        assert_eq!(
+            app.post(
+                "/api/alerts",
+                json!({"kind": "sync_failing", "threshold_hours": threshold})
+            )
+            .await
+            .status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+    assert_eq!(
+        app.post(
+            "/api/alerts",
+            json!({"kind": "sometimes", "threshold_hours": 1})
+        )
+        .await
+        .status,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let uri = format!("/api/alerts/{}", alert["id"]);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+
+    // Existing scrobbles are reclassified right away
+    let media = app
+        .post(
+            "/api/media-rules",
+            json!({"artist": "Portishead", "media_type": "podcast"}),
+        )
+        .await;
+    assert_eq!(media.status, StatusCode::OK);
+    let media = media.json();
+    assert_eq!(media["reclassified"], 4);
+    assert_eq!(media["media_type"], "podcast");
+    assert_eq!(
+        app.post(
+            "/api/media-rules",
+            json!({"album": " ", "media_type": "podcast"})
+        )
+        .await
+        .status,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        app.get("/api/media-rules")
+            .await
+            .json()
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    let uri = format!("/api/media-rules/{}", media["id"]);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_entity_pages_and_ratings() {
+    let app = library();
+
+    let artist = app.get("/api/artist?artist=Radiohead").await;
+    assert_eq!(artist.status, StatusCode::OK);
+    let artist = artist.json();
+    assert_eq!(artist["stats"]["total_scrobbles"], 8);
+    assert_eq!(
+        artist["top_tracks"][0],
+        json!({"name": "Airbag", "count": 3})
+    );
+    assert_eq!(artist["top_albums"][0]["name"], "OK Computer");
+    assert_eq!(artist["image_url"], RADIOHEAD_PICTURE);
+    assert_eq!(artist["similar"][0]["artist"], "Portishead");
+    assert_eq!(
+        artist["scrobbles_over_time"],
+        json!([{"date": "2023-06-01", "count": 5}, {"date": "2024-03-10", "count": 3}])
+    );
+
+    let album = app
+        .get("/api/album?artist=Radiohead&album=OK%20Computer")
+        .await
+        .json();
+    assert_eq!(album["stats"]["unique_tracks"], 6);
+    assert_eq!(album["rating"], Value::Null);
+
+    let track = app
+        .get("/api/track?artist=Radiohead&track=Airbag")
+        .await
+        .json();
+    assert_eq!(track["stats"]["total_scrobbles"], 3);
+    // No track artwork, so the artist's picture stands in
+    assert_eq!(track["image_url"], RADIOHEAD_PICTURE);
+
+    let rating_uri = "/api/album/rating?artist=Radiohead&album=OK%20Computer";
+    let rated = app.put(rating_uri, json!({"rating": 5})).await;
+    assert_eq!(rated.status, StatusCode::NO_CONTENT);
+    for rating in [0, 6] {
+        assert_eq!(
+            app.put(rating_uri, json!({"rating": rating})).await.status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+    assert_eq!(
+        app.put(
+            "/api/track/rating?artist=Radiohead&track=Airbag",
+            json!({"rating": 4})
+        )
+        .await
+        .status,
+        StatusCode::NO_CONTENT
+    );
+
+    let album = app
+        .get("/api/album?artist=Radiohead&album=OK%20Computer")
+        .await
+        .json();
+    assert_eq!(album["rating"], 5);
+    let albums = app.get("/api/ratings?kind=album").await.json();
+    assert_eq!(albums.as_array().unwrap().len(), 1);
+    assert_eq!(albums[0]["rating"], 5);
+    let tracks = app.get("/api/ratings").await.json();
+    assert_eq!(tracks[0]["name"], "Airbag");
+    assert_eq!(
+        app.get("/api/ratings?kind=artist").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let report = app.get("/api/reports/ratings?kind=track").await.json();
+    assert_eq!(report["rated_count"], 1);
+    assert_eq!(
+        app.get("/api/reports/ratings?kind=genre").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    assert_eq!(app.delete(rating_uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(rating_uri).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_unknown_entities_are_not_found() {
+    let app = library();
+
+    for (uri, field) in [
+        ("/api/artist?artist=Radiohed", "artist"),
+        ("/api/album?artist=Radiohead&album=Dummy", "album"),
+        ("/api/track?artist=Portishead&track=Airbag", "track"),
+    ] {
+        let page = app.get(uri).await;
+        assert_eq!(page.status, StatusCode::NOT_FOUND, "{}", uri);
+        assert_eq!(
+            page.json(),
+            json!({"errors": [{"field": field, "message": format!("no such {}", field)}]})
+        );
+    }
+
+    // A known artist with nothing in range is still found, with empty stats
+    let page = app
+        .get("/api/artist?artist=Radiohead&start=2025-01-01T00:00:00Z&end=2025-02-01T00:00:00Z")
+        .await;
+    assert_eq!(page.status, StatusCode::OK);
+    let page = page.json();
+    assert_eq!(page["stats"]["total_scrobbles"], 0);
+    assert_eq!(page["top_tracks"], json!([]));
+    let page = app
+        .get("/api/track?artist=Radiohead&track=Airbag&start=2019-01-01T00:00:00Z&end=2020-01-01T00:00:00Z")
+        .await;
+    assert_eq!(page.status, StatusCode::OK);
+    assert_eq!(page.json()["stats"]["total_scrobbles"], 0);
+}
+
+/// Query string for an entity route, encoded the way browsers do
+fn entity_query(names: &[(&str, &str)]) -> String {
+    names
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[tokio::test]
+async fn test_entity_names_with_reserved_characters() {
+    let app = TestApp::new();
+    let names = [
+        ("AC/DC", "Highway to Hell", "Highway to Hell"),
+        ("Crystal Waters", "Storyteller", "100% Pure Love"),
+        ("Simon & Garfunkel", "Bookends", "America?"),
+        ("Sigur Rós", "( )", "#1"),
+    ];
+    let mut scrobbles = Vec::new();
+    for (i, (artist, album, track)) in names.iter().enumerate() {
+        scrobbles.push(play(
+            artist,
+            album,
+            track,
+            at("2024-05-01T20:00:00Z") + Duration::minutes(i as i64 * 5),
+        ));
+    }
+    app.seed(&scrobbles);
+
+    for (artist, album, track) in names {
+        let page = app
+            .get(&format!(
+                "/api/artist?{}",
+                entity_query(&[("artist", artist)])
+            ))
+            .await;
+        assert_eq!(page.status, StatusCode::OK, "{}", artist);
+        let page = page.json();
+        assert_eq!(page["stats"]["total_scrobbles"], 1, "{}", artist);
+        assert_eq!(page["top_tracks"][0]["name"], track);
+
+        let album_query = entity_query(&[("artist", artist), ("album", album)]);
+        let page = app.get(&format!("/api/album?{}", album_query)).await.json();
+        assert_eq!(page["stats"]["album"], album);
+        assert_eq!(page["tracks"][0]["name"], track);
+
+        let track_query = entity_query(&[("artist", artist), ("track", track)]);
+        let page = app.get(&format!("/api/track?{}", track_query)).await.json();
+        assert_eq!(page["stats"]["track"], track);
+        assert_eq!(page["stats"]["total_scrobbles"], 1);
+
+        let rating_uri = format!("/api/track/rating?{}", track_query);
+        assert_eq!(
+            app.put(&rating_uri, json!({"rating": 3})).await.status,
+            StatusCode::NO_CONTENT,
+            "{}",
+            track
+        );
+        let page = app.get(&format!("/api/track?{}", track_query)).await.json();
+        assert_eq!(page["rating"], 3);
+        assert_eq!(app.delete(&rating_uri).await.status, StatusCode::NO_CONTENT);
+    }
+
+    // Names are matched exactly, but a name is required
+    let missing = app.get("/api/album?artist=AC%2FDC&album=%20").await;
+    assert_eq!(missing.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        missing.json(),
+        json!({"errors": [{"field": "album", "message": "is required"}]})
+    );
+    let missing = app.delete("/api/track/rating").await;
+    assert_eq!(missing.status, StatusCode::BAD_REQUEST);
+    assert_eq!(missing.json()["errors"].as_array().unwrap().len(), 2);
+    // A stray percent sign that wasn't encoded is taken literally
+    let unencoded = app
+        .get("/api/track?artist=Crystal+Waters&track=100%+Pure+Love")
+        .await;
+    assert_eq!(unencoded.status, StatusCode::OK);
+    assert_eq!(unencoded.json()["stats"]["total_scrobbles"], 1);
+
+    // Names no longer go in the path
+    assert_eq!(
+        app.get("/api/artist/AC%2FDC").await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_resolve_conflicts() {
+    let app = library();
+    // The same listen of Roads, as another source reported it
+    app.seed(&[Scrobble::new(
+        "portishead".to_string(),
+        "Roads".to_string(),
+        at("2024-03-10T21:20:30Z"),
+        "listenbrainz".to_string(),
+    )
+    .with_album("Dummy (Deluxe)".to_string())]);
+    let ids: Vec<(String, i64)> = crate::db::get_scrobbles(&app.pool, None, None)
+        .unwrap()
+        .into_iter()
+        .filter(|s| s.track == "Roads")
+        .map(|s| (s.source, s.id.unwrap()))
+        .collect();
+    let other_id = ids[0].1;
+    let roads = ids[1].1;
+    assert_eq!(ids[1].0, "lastfm");
+
+    let conflicts = app.get("/api/conflicts").await;
+    assert_eq!(conflicts.status, StatusCode::OK);
+    assert_eq!(conflicts.json().as_array().unwrap().len(), 1);
+
+    let glory_box = scrobble_id(&app, "Glory Box");
+    assert_eq!(
+        app.post(
+            "/api/conflicts/resolve",
+            json!({"keep_id": roads, "other_id": glory_box})
+        )
+        .await
+        .status,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        app.post(
+            "/api/conflicts/resolve",
+            json!({"keep_id": roads, "other_id": 999999})
+        )
+        .await
+        .status,
+        StatusCode::NOT_FOUND
+    );
+
+    let resolved = app
+        .post(
+            "/api/conflicts/resolve",
+            json!({"keep_id": roads, "other_id": other_id}),
+        )
+        .await;
+    assert_eq!(resolved.status, StatusCode::OK);
+    let resolved = resolved.json();
+    assert_eq!(resolved["id"], other_id);
+    assert_eq!(resolved["artist"], "Portishead");
+    assert_eq!(resolved["album"], "Dummy");
+    assert_eq!(app.get("/api/conflicts").await.json(), json!([]));
+}
+
+#[tokio::test]
+async fn test_sync_config_lifecycle() {
+    let app = TestApp::new();
+
+    let invalid = app
+        .post(
+            "/api/sync/config",
+            json!({"source": "lastfm", "username": " ", "sync_interval_minutes": 0}),
+        )
+        .await;
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    let invalid = invalid.json();
+    assert_eq!(invalid["success"], false);
+    assert_eq!(invalid["config"], Value::Null);
+    let fields: Vec<&str> = invalid["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["username", "api_key", "sync_interval_minutes"]);
+
+    // Invalid settings are reported before any credentials are checked
+    let validation = app
+        .post(
+            "/api/sync/config/validate",
+            json!({"source": "spotify", "username": "alice"}),
+        )
+        .await;
+    assert_eq!(validation.status, StatusCode::OK);
+    let validation = validation.json();
+    assert_eq!(validation["valid"], false);
+    assert_eq!(validation["errors"][0]["field"], "source");
+
+    let created = app
+        .post(
+            "/api/sync/config",
+            json!({"source": "listenbrainz", "username": "alice", "sync_interval_minutes": 30}),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::OK);
+    let created = created.json();
+    assert_eq!(created["success"], true);
+    assert_eq!(created["config"]["username"], "alice");
+    assert!(created.get("errors").is_none());
+    assert!(created.get("import_job_id").is_none());
+
+    let configs = app.get("/api/sync/config").await.json();
+    assert_eq!(configs.as_array().unwrap().len(), 1);
+    assert_eq!(configs[0]["paused"], false);
+    assert!(configs[0]["next_sync_at"].is_string());
+    let uri = format!("/api/sync/config/{}", configs[0]["id"]);
+
+    let paused = app.post(&format!("{}/pause", uri), json!({})).await.json();
+    assert_eq!(paused["paused"], true);
+    assert_eq!(paused["next_sync_at"], Value::Null);
+    let resumed = app.post(&format!("{}/resume", uri), json!({})).await.json();
+    assert_eq!(resumed["paused"], false);
+
+    let updated = app
+        .post(
+            &uri,
+            json!({"source": "listenbrainz", "username": "alice", "sync_interval_minutes": 120}),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    assert_eq!(app.get(&uri).await.json()["sync_interval_minutes"], 120);
+
+    // Rescans look back from a past instant
+    let future = (Utc::now() + Duration::days(1)).to_rfc3339();
+    for since in [future.as_str(), "yesterday"] {
+        let rescan = format!("{}/rescan?since={}", uri, urlencoding::encode(since));
+        assert_eq!(
+            app.post(&rescan, json!({})).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            since
+        );
+    }
+    assert_eq!(
+        app.post("/api/sync/config/999999/rescan", json!({}))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+
+    let status = app.get("/api/sync/status").await.json();
+    assert_eq!(status["running"], false);
+    assert_eq!(status["syncing"], json!([]));
+
+    let deleted = app.delete(&uri).await;
+    assert_eq!(deleted.status, StatusCode::OK);
+    assert_eq!(deleted.json()["success"], true);
+    assert_eq!(app.get(&uri).await.status, StatusCode::NOT_FOUND);
+    for action in ["pause", "resume"] {
+        assert_eq!(
+            app.post(&format!("{}/{}", uri, action), json!({}))
+                .await
+                .status,
+            StatusCode::NOT_FOUND
+        );
+    }
+    assert_eq!(
+        app.post(&uri, json!({"source": "listenbrainz", "username": "alice"}))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+
+    // Triggering a missing config is reported in the body
+    for action in ["trigger", "playlists", "legacy-charts"] {
+        let response = app.post(&format!("{}/{}", uri, action), json!({})).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let response = response.json();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["count"], 0);
+    }
+}
+
+#[tokio::test]
+async fn test_imports() {
+    let app = TestApp::new();
+
+    let unknown = app
+        .post(
+            "/api/import",
+            json!({"source": "spotify", "username": "alice"}),
+        )
+        .await;
+    assert_eq!(unknown.status, StatusCode::OK);
+    assert_eq!(
+        unknown.json(),
+        json!({"success": false, "count": 0, "message": "Unknown source: spotify"})
+    );
+    let keyless = app
+        .post(
+            "/api/import",
+            json!({"source": "lastfm", "username": "alice"}),
+        )
+        .await
+        .json();
+    assert_eq!(keyless["success"], false);
+    assert_eq!(keyless["message"], "API key required for Last.fm");
+
+    assert_eq!(app.get("/api/imports").await.json(), json!([]));
+    assert_eq!(
+        app.post("/api/imports/42/resume", json!({})).await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_exports() {
+    let app = library();
+
+    let export = app.get("/api/export").await;
+    assert_eq!(export.status, StatusCode::OK);
+    assert_eq!(export.headers["content-type"], "application/json");
+    let disposition = export.headers["content-disposition"].to_str().unwrap();
+    assert!(disposition.starts_with("attachment; filename=\"footprints_export_"));
+    assert!(disposition.ends_with(".json\""));
+    assert_eq!(export.json().as_array().unwrap().len(), 12);
+
+    let csv = app.get("/api/export?format=csv").await;
+    assert_eq!(csv.headers["content-type"], "text/csv");
+    let csv = csv.text();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("timestamp,artist,album,track,source"));
+    assert_eq!(lines.count(), 12);
+
+    assert_eq!(
+        app.get("/api/export?format=xml").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let playlist = app.get("/api/export/playlist?format=m3u&limit=2").await;
+    assert_eq!(playlist.status, StatusCode::OK);
+    assert!(
+        playlist.headers["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains("footprints_top_alltime.m3u")
+    );
+    assert!(playlist.text().starts_with("#EXTM3U"));
+    assert!(playlist.text().contains("Radiohead - Airbag"));
+
+    let revisit = app.get("/api/recommendations/revisit").await.json();
+    assert_eq!(revisit["min_plays"], 10);
+    assert_eq!(revisit["tracks"], json!([]));
+    let xspf = app.get("/api/recommendations/revisit?format=xspf").await;
+    assert_eq!(xspf.status, StatusCode::OK);
+    assert!(xspf.text().contains("<playlist"));
+    for uri in [
+        "/api/recommendations/revisit?format=pls",
+        "/api/recommendations/revisit?idle_days=0",
+        "/api/recommendations/revisit?min_plays=0",
+    ] {
+        assert_eq!(
+            app.get(uri).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_image_cache() {
+    // Only Radiohead has a picture; every other lookup fails, as if the
+    // providers were unreachable
+    let app = TestApp::with_image_service(MockHttp::default().respond(
+        "api.deezer.com/search/artist?q=Radiohead",
+        200,
+        &json!({"data": [{"picture_xl": RADIOHEAD_PICTURE}]}).to_string(),
+    ));
+
+    assert_eq!(
+        app.get("/api/image?url=http://localhost/cover.jpg")
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let artist = app
+        .post(
+            "/api/images/cache/refresh",
+            json!({"entity_type": "artist", "artist": "Radiohead"}),
+        )
+        .await;
+    assert_eq!(artist.status, StatusCode::OK);
+    assert_eq!(artist.json(), json!({"image_url": RADIOHEAD_PICTURE}));
+    for invalid in [
+        json!({"entity_type": "genre", "artist": "Radiohead"}),
+        json!({"entity_type": "album", "artist": "Radiohead"}),
+    ] {
+        assert_eq!(
+            app.post("/api/images/cache/refresh", invalid.clone())
+                .await
+                .status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            invalid
+        );
+    }
+
+    let stats = app.get("/api/images/cache/stats").await.json();
+    assert_eq!(stats["entries"], 1);
+    assert_eq!(stats["misses"], 1);
+
+    assert_eq!(
+        app.delete("/api/images/cache?entity_type=genre")
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+    let purged = app.delete("/api/images/cache?entity_type=artist").await;
+    assert_eq!(purged.status, StatusCode::OK);
+    assert_eq!(purged.json(), json!({"deleted": 1}));
+    assert_eq!(
+        app.get("/api/images/cache/stats").await.json()["entries"],
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_admin() {
+    let app = library();
+
+    for (uri, message) in [
+        ("/api/admin/vacuum", "Database vacuumed"),
+        ("/api/admin/analyze", "Database statistics updated"),
+    ] {
+        let response = app.post(uri, json!({})).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.json(),
+            json!({"success": true, "message": message})
+        );
+    }
+
+    let stats = app.get("/api/admin/db-stats").await.json();
+    assert!(stats["file_size_bytes"].as_i64().unwrap() > 0);
+    assert!(stats["indexes"].is_array());
+
+    let backup = app.get("/api/admin/backup.sqlite").await;
+    assert_eq!(backup.status, StatusCode::OK);
+    assert_eq!(backup.headers["content-type"], "application/vnd.sqlite3");
+    assert_eq!(
+        backup.headers["content-length"],
+        backup.body.len().to_string().as_str()
+    );
+    assert!(backup.body.starts_with(b"SQLite format 3\0"));
+
+    for uri in [
+        "/api/admin/integrity-check",
+        "/api/admin/integrity-check?mode=quick",
+    ] {
+        let report = app.post(uri, json!({})).await;
+        assert_eq!(report.status, StatusCode::OK, "{}", uri);
+        assert_eq!(report.json()["ok"], true);
+    }
+    assert_eq!(
+        app.post("/api/admin/integrity-check?mode=deep", json!({}))
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn test_maintenance() {
+    let app = library();
+
+    assert_eq!(
+        app.post(
+            "/api/maintenance/reattribute",
+            json!({"new_source": "listenbrainz"})
+        )
+        .await
+        .status,
+        StatusCode::BAD_REQUEST
+    );
+    let dry_run = app
+        .post(
+            "/api/maintenance/reattribute",
+            json!({"new_source": "listenbrainz", "artist": "Portishead", "dry_run": true}),
+        )
+        .await;
+    assert_eq!(dry_run.status, StatusCode::OK);
+    assert_eq!(
+        dry_run.json(),
+        json!({"dry_run": true, "matched": 4, "updated": 4, "skipped": 0})
+    );
+    let reattributed = app
+        .post(
+            "/api/maintenance/reattribute",
+            json!({"new_source": "listenbrainz", "artist": "Portishead"}),
+        )
+        .await
+        .json();
+    assert_eq!(reattributed["updated"], 4);
+
+    let shifted = app
+        .post(
+            "/api/maintenance/shift-timestamps",
+            json!({
+                "source": "listenbrainz",
+                "start": "2024-03-01T00:00:00Z",
+                "end": "2024-03-31T00:00:00Z",
+                "offset_seconds": -3600,
+            }),
+        )
+        .await;
+    assert_eq!(shifted.status, StatusCode::OK);
+    assert_eq!(shifted.json()["updated"], 3);
+    for offset in [0, 86_401] {
+        let response = app
+            .post(
+                "/api/maintenance/shift-timestamps",
+                json!({
+                    "source": "listenbrainz",
+                    "start": "2024-03-01T00:00:00Z",
+                    "end": "2024-03-31T00:00:00Z",
+                    "offset_seconds": offset,
+                }),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{}", offset);
+    }
+
+    let compilation = app
+        .post(
+            "/api/maintenance/compilations",
+            json!({"album": "Dummy", "dry_run": true}),
+        )
+        .await;
+    assert_eq!(compilation.status, StatusCode::OK);
+    let compilation = compilation.json();
+    assert_eq!(compilation["album_artist"], "Various Artists");
+    assert_eq!(compilation["matched"], 4);
+    assert_eq!(
+        app.post("/api/maintenance/compilations", json!({"album": " "}))
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        app.get("/api/maintenance/compilations").await.json(),
+        json!([])
+    );
+
+    assert_eq!(
+        app.post("/api/maintenance/archive", json!({"older_than_years": 0}))
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+    let archived = app
+        .post("/api/maintenance/archive", json!({"older_than_years": 1}))
+        .await
+        .json();
+    assert_eq!(archived["archived"], 11);
+    let archive = app.get("/api/maintenance/archive").await.json();
+    assert_eq!(archive["scrobbles"], 11);
+    assert_eq!(archive["oldest"], "2023-06-01T20:00:00Z");
+    assert_eq!(app.get("/api/stats").await.json()["total_scrobbles"], 12);
+    assert_eq!(
+        app.get("/api/artist?artist=Portishead").await.status,
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.get("/api/scrobbles")
+            .await
+            .json()
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    let restored = app
+        .post("/api/maintenance/archive/restore", json!({}))
+        .await
+        .json();
+    assert_eq!(restored, json!({"dry_run": false, "restored": 11}));
+
+    let log = app.get("/api/maintenance/log").await.json();
+    assert!(log.as_array().unwrap().len() >= 2);
+
+    let anomalies = app.get("/api/maintenance/anomalies").await;
+    assert_eq!(anomalies.status, StatusCode::OK);
+    assert_eq!(anomalies.json()["future"], json!([]));
+}
+
+#[tokio::test]
+async fn test_share_tokens() {
+    let app = library();
+
+    for invalid in [
+        json!({"scopes": []}),
+        json!({"scopes": ["stats", "admin"]}),
+        json!({"expires_in_days": 0}),
+        json!({"expires_in_days": 3651}),
+        json!({"expires_in_days": i64::MAX}),
+    ] {
+        assert_eq!(
+            app.post("/api/share", invalid.clone()).await.status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            invalid
+        );
+    }
+
+    let stats_only = app
+        .post(
+            "/api/share",
+            json!({"label": "Friends", "scopes": ["stats"], "expires_in_days": 7}),
+        )
+        .await;
+    assert_eq!(stats_only.status, StatusCode::OK);
+    let stats_only = stats_only.json();
+    assert_eq!(stats_only["label"], "Friends");
+    assert!(stats_only["expires_at"].is_string());
+    let token = stats_only["token"].as_str().unwrap().to_string();
+
+    let everything = app.post("/api/share", json!({})).await.json();
+    assert_eq!(everything["scopes"], json!(["stats", "reports"]));
+    assert_eq!(
+        app.get("/api/share").await.json().as_array().unwrap().len(),
+        2
+    );
+
+    let stats = app.get(&format!("/share/{}/stats?limit=1", token)).await;
+    assert_eq!(stats.status, StatusCode::OK);
+    assert_eq!(stats.json()["top_artists"], json!([["Radiohead", 8]]));
+    for uri in ["years", "reports/2024"] {
+        assert_eq!(
+            app.get(&format!("/share/{}/{}", token, uri)).await.status,
+            StatusCode::FORBIDDEN,
+            "{}",
+            uri
+        );
+    }
+    let report = app
+        .get(&format!(
+            "/share/{}/reports/2024",
+            everything["token"].as_str().unwrap()
+        ))
+        .await;
+    assert_eq!(report.status, StatusCode::OK);
+    assert_eq!(report.json()["total_scrobbles"], 6);
+    assert_eq!(
+        app.get("/share/unknown/stats").await.status,
+        StatusCode::NOT_FOUND
+    );
+
+    let uri = format!("/api/share/{}", token);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.delete(&uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        app.get(&format!("/share/{}/stats", token)).await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_read_only_instance() {
+    let app = TestApp::with_options(InstanceOptions {
+        read_only: true,
+        ..InstanceOptions::default()
+    });
+
+    let instance = app.get("/api/instance").await.json();
+    assert_eq!(instance["features"]["read_only"], true);
+
+    for (method, uri) in [
+        (Method::POST, "/api/scrobbles"),
+        (Method::PUT, "/api/settings"),
+        (Method::DELETE, "/api/scrobbles/1"),
+        (Method::POST, "/api/admin/vacuum"),
+    ] {
+        let response = app.request(method, uri, Some(json!({}))).await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_eq!(response.text(), "This instance is read-only");
+    }
+
+    // Reads, and checks that change nothing, still work
+    assert_eq!(app.get("/api/scrobbles").await.status, StatusCode::OK);
+    assert_eq!(
+        app.post("/api/admin/integrity-check?mode=quick", json!({}))
+            .await
+            .status,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_read_only_reads_leave_the_database_untouched() {
+    let app = library();
+    let path = app._db.path().to_path_buf();
+    let snapshot = || {
+        let mut wal = path.clone().into_os_string();
+        wal.push("-wal");
+        (
+            std::fs::read(&path).unwrap(),
+            std::fs::read(&wal).unwrap_or_default(),
+        )
+    };
+    let before = snapshot();
+
+    let app = app.reopen_read_only();
+    for uri in [
+        "/api/stats",
+        "/api/stats/ui",
+        "/api/overview",
+        "/api/scrobbles",
+        "/api/reports/sleep",
+        "/api/reports/monthly?year=2024&month=3",
+        "/api/calendar/2024/3",
+        "/api/timeline",
+        "/api/digest",
+        "/api/artist?artist=Radiohead",
+        "/api/album?artist=Portishead&album=Dummy",
+        "/api/track?artist=Radiohead&track=Airbag",
+        "/api/achievements",
+        "/api/settings",
+    ] {
+        assert_eq!(app.get(uri).await.status, StatusCode::OK, "{}", uri);
+    }
+
+    assert!(before == snapshot(), "a GET wrote to the database");
+}
+
+#[tokio::test]
+async fn test_recommendations_releases_and_remote_comparison() {
+    let app = library();
+
+    assert_eq!(
+        app.get("/api/recommendations/listenbrainz").await.json(),
+        json!([])
+    );
+    assert_eq!(
+        app.get("/api/recommendations/listenbrainz/0fa8a2b4")
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+
+    let releases = app.get("/api/releases/new?since=2024-01-01").await;
+    assert_eq!(releases.status, StatusCode::OK);
+    assert_eq!(
+        releases.json(),
+        json!({"since": "2024-01-01", "last_checked": null, "releases": []})
+    );
+    assert_eq!(
+        app.get("/api/releases/new?since=January").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    // Incomplete requests are refused before anything is fetched
+    for invalid in [
+        json!({"source": "spotify"}),
+        json!({"source": "footprints"}),
+        json!({"source": "lastfm", "username": "alice"}),
+        json!({"source": "footprints", "url": "http://127.0.0.1:3000"}),
+        json!({"source": "footprints", "url": "file:///etc/passwd"}),
+    ] {
+        assert_eq!(
+            app.post("/api/reports/compare-remote", invalid.clone())
+                .await
+                .status,
+            StatusCode::BAD_REQUEST,
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_daily_summaries() {
+    let app = library();
+
+    let rebuilt = app.post("/api/summaries/rebuild", json!({})).await;
+    assert_eq!(rebuilt.status, StatusCode::OK);
+    assert!(rebuilt.json()["days"].as_i64().unwrap() > 300);
+
+    // The calendar reads the same days back from the rollup
+    let days = app.get("/api/calendar/2024/3").await.json()["days"].clone();
+    assert_eq!(days[9]["count"], 6);
+    assert_eq!(days[9]["new_artists"], json!(["Portishead"]));
+
+    // Deleting one of that day's scrobbles shows up right away
+    let id = crate::db::get_scrobbles_in_range(
+        &app.pool,
+        at("2024-03-10T00:00:00Z"),
+        at("2024-03-11T00:00:00Z"),
+    )
+    .unwrap()[0]
+        .id
+        .unwrap();
+    assert_eq!(
+        app.delete(&format!("/api/scrobbles/{}", id)).await.status,
+        StatusCode::NO_CONTENT
+    );
+    let days = app.get("/api/calendar/2024/3").await.json()["days"].clone();
+    assert_eq!(days[9]["count"], 5);
+
+    let digest = app.get("/api/digest?days=14").await;
+    assert_eq!(digest.status, StatusCode::OK);
+    let digest = digest.json();
+    assert_eq!(digest["days"].as_array().unwrap().len(), 14);
+    assert_eq!(digest["change_percent"], Value::Null);
+    for days in [0, 367] {
+        assert_eq!(
+            app.get(&format!("/api/digest?days={}", days)).await.status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_legacy_charts_blend_into_all_time() {
+    let app = library();
+    crate::db::save_legacy_chart_week(
+        &app.pool,
+        at("2005-01-02T12:00:00Z"),
+        at("2005-01-09T12:00:00Z"),
+        &[("Portishead".to_string(), 20), ("Low".to_string(), 5)],
+        &[("Radiohead".to_string(), "Airbag".to_string(), 2)],
+        at("2024-06-01T00:00:00Z"),
+    )
+    .unwrap();
+
+    let all_time = app.get("/api/stats/ui").await.json();
+    let artists = all_time["top_artists"].as_array().unwrap();
+    assert_eq!(artists[0]["name"], "Portishead");
+    assert_eq!(artists[0]["legacy_count"], 20);
+    assert_eq!(artists[0]["provenance"], "mixed");
+    let radiohead = artists.iter().find(|a| a["name"] == "Radiohead").unwrap();
+    assert_eq!(radiohead["count"], 8);
+    assert_eq!(radiohead["provenance"], "scrobbles");
+    let low = artists.iter().find(|a| a["name"] == "Low").unwrap();
+    assert_eq!(low["count"], 5);
+    assert_eq!(low["provenance"], "legacy");
+    assert_eq!(all_time["top_tracks"][0]["track"], "Airbag");
+    assert_eq!(all_time["top_tracks"][0]["count"], 5);
+    assert_eq!(all_time["top_tracks"][0]["legacy_count"], 2);
+
+    // Other periods only count scrobbles
+    let march = app
+        .get("/api/stats/ui?period=custom&start=2024-03-01T00:00:00Z&end=2024-03-31T23:59:59Z")
+        .await
+        .json();
+    assert!(
+        march["top_artists"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|a| a["name"] != "Low" && a.get("provenance").is_none())
+    );
+}
+
+#[tokio::test]
+async fn test_weekly_charts() {
+    let app = library();
+    assert_eq!(
+        app.get("/api/charts/artists").await.status,
+        StatusCode::NOT_FOUND
+    );
+
+    let rebuilt = app.post("/api/charts/rebuild", json!({})).await;
+    assert_eq!(rebuilt.status, StatusCode::OK);
+    // Every week from the first scrobble's up to the current one, unfinished
+    assert!(rebuilt.json()["weeks"].as_i64().unwrap() > 40);
+
+    // Any day picks its week
+    let chart = app.get("/api/charts/artists?week=2023-06-01").await;
+    assert_eq!(chart.status, StatusCode::OK);
+    assert_eq!(
+        chart.json(),
+        json!({
+            "chart": "artist",
+            "week_start": "2023-05-29",
+            "entries": [{"week_start": "2023-05-29", "rank": 1, "artist": "Radiohead", "plays": 5}]
+        })
+    );
+    let chart = app.get("/api/charts/tracks?week=2024-03-04").await.json();
+    assert_eq!(
+        chart["entries"][0],
+        json!({"week_start": "2024-03-04", "rank": 1, "artist": "Radiohead", "track": "Airbag", "plays": 2})
+    );
+    assert_eq!(chart["entries"][1]["rank"], 2);
+    // Weeks with no plays are recorded empty
+    let chart = app.get("/api/charts/artists?week=2023-07-03").await;
+    assert_eq!(chart.status, StatusCode::OK);
+    assert_eq!(chart.json()["entries"], json!([]));
+    assert_eq!(
+        app.get("/api/charts/artists?week=2020-01-01").await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        app.get("/api/charts/albums").await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let history = app
+        .get("/api/charts/artists/history?artist=Radiohead")
+        .await
+        .json();
+    assert_eq!(history["weeks_on_chart"], 2);
+    assert_eq!(history["weeks_at_number_one"], 2);
+    assert_eq!(history["longest_number_one_run"], 1);
+    assert_eq!(history["first_week"], "2023-05-29");
+    assert_eq!(history["weeks"].as_array().unwrap().len(), 2);
+
+    let history = app
+        .get("/api/charts/tracks/history?artist=Portishead&track=Roads")
+        .await
+        .json();
+    assert_eq!(history["track"], "Roads");
+    assert_eq!(history["peak_rank"], 2);
+    assert_eq!(history["weeks_at_number_one"], 0);
+    assert_eq!(
+        app.get("/api/charts/tracks/history?artist=Portishead")
+            .await
+            .status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let leaders = app.get("/api/charts/artists/leaders?limit=5").await.json();
+    let radiohead = leaders
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|record| record["artist"] == "Radiohead")
+        .unwrap();
+    assert_eq!(radiohead["weeks_at_number_one"], 2);
+    assert_eq!(radiohead["peak_rank"], 1);
+}
+
+#[cfg(feature = "dev-tools")]
+#[tokio::test]
+async fn test_dev_seed() {
+    let app = TestApp::new();
+
+    let seeded = app.post("/api/dev/seed?scrobbles=500", json!({})).await;
+    assert_eq!(seeded.status, StatusCode::OK);
+    let seeded = seeded.json();
+    assert_eq!(seeded["inserted"], 500);
+    assert_eq!(seeded["source"], crate::demo::DEMO_SOURCE);
+    assert_eq!(app.get("/api/instance").await.json()["scrobble_count"], 500);
+
+    for count in [0, 5_000_001] {
+        let uri = format!("/api/dev/seed?scrobbles={}", count);
+        assert_eq!(
+            app.post(&uri, json!({})).await.status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
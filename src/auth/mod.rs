@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderName, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::db::DbPool;
+
+pub mod oidc;
+
+const DEFAULT_REMOTE_USER_HEADER: &str = "remote-user";
+
+/// Reverse-proxy authentication settings.
+///
+/// When `AUTH_TRUSTED_PROXIES` is set, every request must come from one of the
+/// listed proxy addresses and carry the authenticated username in the
+/// `AUTH_REMOTE_USER_HEADER` header (default `Remote-User`).
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    trusted_proxies: Vec<IpAddr>,
+    header: HeaderName,
+}
+
+impl AuthConfig {
+    pub fn new(trusted_proxies: Vec<IpAddr>, header: HeaderName) -> Self {
+        Self {
+            trusted_proxies,
+            header,
+        }
+    }
+
+    /// Build the config from the environment, or `None` when proxy auth is disabled
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(proxies) = std::env::var("AUTH_TRUSTED_PROXIES") else {
+            return Ok(None);
+        };
+
+        let trusted_proxies = proxies
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                p.parse::<IpAddr>()
+                    .with_context(|| format!("Invalid address in AUTH_TRUSTED_PROXIES: {}", p))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if trusted_proxies.is_empty() {
+            anyhow::bail!("AUTH_TRUSTED_PROXIES is set but lists no addresses");
+        }
+
+        let header = std::env::var("AUTH_REMOTE_USER_HEADER")
+            .unwrap_or_else(|_| DEFAULT_REMOTE_USER_HEADER.to_string());
+        let header = HeaderName::try_from(header.to_lowercase())
+            .context("Invalid header name in AUTH_REMOTE_USER_HEADER")?;
+
+        Ok(Some(Self::new(trusted_proxies, header)))
+    }
+
+    pub fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.trusted_proxies.contains(&addr.to_canonical())
+    }
+}
+
+/// How requests are authenticated, when they are
+#[derive(Clone)]
+pub enum AuthMode {
+    /// The username header set by a trusted reverse proxy
+    Proxy(AuthConfig),
+    /// This instance's own OpenID Connect login and session cookie
+    Oidc(Arc<oidc::OidcClient>),
+}
+
+#[derive(Clone)]
+pub struct AuthState {
+    pub pool: DbPool,
+    pub config: AuthConfig,
+}
+
+/// User resolved from the proxy header or the session, attached to the
+/// request extensions
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthenticatedUser {
+    pub id: i64,
+    pub username: String,
+}
+
+/// Middleware accepting only requests forwarded by a trusted proxy with an
+/// authenticated username, which is mapped to (or creates) a local user
+pub async fn require_remote_user(
+    State(auth): State<AuthState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !auth.config.is_trusted(peer.ip()) {
+        tracing::warn!("Rejected request from untrusted address {}", peer.ip());
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let username = request
+        .headers()
+        .get(&auth.config.header)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or(StatusCode::UNAUTHORIZED)?
+        .to_string();
+
+    let id = crate::db::upsert_user(&auth.pool, &username)
+        .map_err(|e| {
+            tracing::error!("Failed to map user {}: {}", username, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or_else(|| {
+            tracing::warn!("Unknown user {} on a read-only instance", username);
+            StatusCode::FORBIDDEN
+        })?;
+
+    request
+        .extensions_mut()
+        .insert(AuthenticatedUser { id, username });
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trusted_proxy_matching() {
+        let config = AuthConfig::new(
+            vec!["127.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+            HeaderName::from_static(DEFAULT_REMOTE_USER_HEADER),
+        );
+
+        assert!(config.is_trusted("127.0.0.1".parse().unwrap()));
+        assert!(config.is_trusted("10.0.0.2".parse().unwrap()));
+        // IPv4-mapped IPv6 peers (dual-stack listeners) match their IPv4 form
+        assert!(config.is_trusted("::ffff:10.0.0.2".parse().unwrap()));
+        assert!(!config.is_trusted("10.0.0.3".parse().unwrap()));
+    }
+}
@@ -0,0 +1,370 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::DbPool;
+use crate::models::{ChartEntry, ChartKind};
+use crate::reports::calendar::local_midnight;
+use crate::reports::period::IsoWeek;
+
+/// Entries recorded on each weekly chart
+pub const CHART_SIZE: i64 = 10;
+
+// How often finished weeks are looked for
+const CHART_TICK_SECS: u64 = 3600;
+
+/// How an artist or track did across the recorded charts
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ChartRecord {
+    pub artist: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+    pub weeks_on_chart: i64,
+    pub weeks_at_number_one: i64,
+    /// Most consecutive weeks at #1
+    pub longest_number_one_run: i64,
+    pub peak_rank: Option<i64>,
+    pub first_week: Option<NaiveDate>,
+    pub last_week: Option<NaiveDate>,
+}
+
+/// One artist's or track's record, with each week it charted
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartHistory {
+    #[serde(flatten)]
+    pub record: ChartRecord,
+    pub weeks: Vec<ChartEntry>,
+}
+
+/// Monday of the week `at` falls in, in `timezone`
+pub fn week_of(at: DateTime<Utc>, timezone: Tz) -> NaiveDate {
+    IsoWeek::of(at.with_timezone(&timezone).date_naive()).monday()
+}
+
+/// Record the charts of every finished week since the last recorded one, or
+/// since the first scrobble, and return how many weeks were recorded
+pub fn record_weeks(pool: &DbPool, now: DateTime<Utc>) -> Result<usize> {
+    let timezone = crate::settings::load(pool)?.timezone();
+    let from = match crate::db::get_last_chart_week(pool)? {
+        Some(week) => week + Duration::weeks(1),
+        None => match crate::db::get_first_scrobble_timestamp(pool)? {
+            Some(first) => week_of(first, timezone),
+            None => return Ok(0),
+        },
+    };
+    record_from(pool, from, now, timezone)
+}
+
+/// Record every finished week's charts again from the first scrobble, after
+/// imports of older history or a timezone change. Weeks whose scrobbles were
+/// all archived keep their charts
+pub fn rebuild(pool: &DbPool, now: DateTime<Utc>) -> Result<usize> {
+    let timezone = crate::settings::load(pool)?.timezone();
+    match crate::db::get_first_scrobble_timestamp(pool)? {
+        Some(first) => record_from(pool, week_of(first, timezone), now, timezone),
+        None => Ok(0),
+    }
+}
+
+fn record_from(pool: &DbPool, from: NaiveDate, now: DateTime<Utc>, timezone: Tz) -> Result<usize> {
+    let current = week_of(now, timezone);
+    let mut week = from;
+    let mut recorded = 0;
+    while week < current {
+        let start = local_midnight(week, timezone)?;
+        let end = local_midnight(week + Duration::weeks(1), timezone)? - Duration::seconds(1);
+
+        let conn = pool.get()?;
+        let artists = crate::db::query_top_artists(&conn, CHART_SIZE, Some(start), Some(end))?
+            .into_iter()
+            .map(|(artist, plays)| (artist, None, plays));
+        let artists = ranked(week, artists);
+        let tracks = crate::db::query_top_tracks(&conn, CHART_SIZE, Some(start), Some(end))?
+            .into_iter()
+            .map(|(artist, track, plays)| (artist, Some(track), plays));
+        let tracks = ranked(week, tracks);
+        drop(conn);
+
+        crate::db::save_chart_week(pool, week, &artists, &tracks, now)?;
+        week += Duration::weeks(1);
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+/// Chart entries for a top list ordered by plays; ties share a rank, as in
+/// the top lists' movements
+fn ranked(
+    week_start: NaiveDate,
+    top: impl Iterator<Item = (String, Option<String>, i64)>,
+) -> Vec<ChartEntry> {
+    let mut entries: Vec<ChartEntry> = Vec::new();
+    for (i, (artist, track, plays)) in top.enumerate() {
+        let rank = match entries.last() {
+            Some(previous) if previous.plays == plays => previous.rank,
+            _ => i as i64 + 1,
+        };
+        entries.push(ChartEntry {
+            week_start,
+            rank,
+            artist,
+            track,
+            plays,
+        });
+    }
+    entries
+}
+
+/// Record of one entity from its chart entries, oldest week first
+fn summarize(artist: &str, track: Option<&str>, entries: &[ChartEntry]) -> ChartRecord {
+    let mut record = ChartRecord {
+        artist: artist.to_string(),
+        track: track.map(str::to_string),
+        weeks_on_chart: entries.len() as i64,
+        peak_rank: entries.iter().map(|entry| entry.rank).min(),
+        first_week: entries.first().map(|entry| entry.week_start),
+        last_week: entries.last().map(|entry| entry.week_start),
+        ..ChartRecord::default()
+    };
+
+    let mut run = 0;
+    let mut previous_top: Option<NaiveDate> = None;
+    for entry in entries.iter().filter(|entry| entry.rank == 1) {
+        record.weeks_at_number_one += 1;
+        run = match previous_top {
+            Some(previous) if entry.week_start - previous == Duration::weeks(1) => run + 1,
+            _ => 1,
+        };
+        record.longest_number_one_run = record.longest_number_one_run.max(run);
+        previous_top = Some(entry.week_start);
+    }
+    record
+}
+
+/// Chart record of `artist`, or of their `track` on the track chart
+pub fn history(
+    pool: &DbPool,
+    kind: ChartKind,
+    artist: &str,
+    track: Option<&str>,
+) -> Result<ChartHistory> {
+    let track = track.filter(|_| kind == ChartKind::Track);
+    let weeks =
+        crate::db::get_chart_entries(pool, kind, Some((artist, track.unwrap_or_default())))?;
+    Ok(ChartHistory {
+        record: summarize(artist, track, &weeks),
+        weeks,
+    })
+}
+
+/// All-time chart records, most weeks at #1 first, then most weeks on chart
+pub fn leaders(pool: &DbPool, kind: ChartKind, limit: usize) -> Result<Vec<ChartRecord>> {
+    let mut by_entity: HashMap<(String, Option<String>), Vec<ChartEntry>> = HashMap::new();
+    for entry in crate::db::get_chart_entries(pool, kind, None)? {
+        by_entity
+            .entry((entry.artist.clone(), entry.track.clone()))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut records: Vec<ChartRecord> = by_entity
+        .iter()
+        .map(|((artist, track), entries)| summarize(artist, track.as_deref(), entries))
+        .collect();
+    records.sort_by(|a, b| {
+        b.weeks_at_number_one
+            .cmp(&a.weeks_at_number_one)
+            .then(b.weeks_on_chart.cmp(&a.weeks_on_chart))
+            .then(b.longest_number_one_run.cmp(&a.longest_number_one_run))
+            .then_with(|| a.artist.cmp(&b.artist))
+            .then_with(|| a.track.cmp(&b.track))
+    });
+    records.truncate(limit);
+    Ok(records)
+}
+
+/// Records each finished week's charts in the background
+#[derive(Clone)]
+pub struct ChartRecorder {
+    pool: DbPool,
+}
+
+impl ChartRecorder {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn start(&self) {
+        let recorder = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs(CHART_TICK_SECS);
+            loop {
+                match record_weeks(&recorder.pool, Utc::now()) {
+                    Ok(0) => {}
+                    Ok(weeks) => tracing::info!("Recorded charts for {} weeks", weeks),
+                    Err(e) => tracing::error!("Failed to record weekly charts: {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn play(pool: &DbPool, artist: &str, track: &str, times: i64, at: DateTime<Utc>) {
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                artist.to_string(),
+                track.to_string(),
+                at + Duration::minutes(i * 5),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn test_records_finished_weeks_once() {
+        let (pool, _temp_file) = setup_pool();
+        // Mondays 2024-05-06, 05-13 and 05-20; nothing in the week of 05-27
+        let first: DateTime<Utc> = "2024-05-07T20:00:00Z".parse().unwrap();
+        play(&pool, "Low", "Lazy", 3, first);
+        play(
+            &pool,
+            "Slint",
+            "Nosferatu Man",
+            2,
+            first + Duration::days(1),
+        );
+        play(&pool, "Low", "Lazy", 2, first + Duration::weeks(1));
+        play(&pool, "Slint", "Don, Aman", 2, first + Duration::weeks(1));
+        play(&pool, "Low", "Sunflower", 4, first + Duration::weeks(2));
+
+        // The current week isn't finished, so isn't recorded
+        let now: DateTime<Utc> = "2024-06-05T12:00:00Z".parse().unwrap();
+        assert_eq!(record_weeks(&pool, now).unwrap(), 4);
+        assert_eq!(record_weeks(&pool, now).unwrap(), 0);
+        assert_eq!(
+            crate::db::get_last_chart_week(&pool).unwrap(),
+            Some(date("2024-05-27"))
+        );
+        let empty = crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-27")).unwrap();
+        assert_eq!(empty, Some(Vec::new()));
+
+        let artists = crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-06"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(artists.len(), 2);
+        assert_eq!(artists[0].artist, "Low");
+        assert_eq!((artists[0].rank, artists[0].plays), (1, 3));
+        assert_eq!(artists[0].track, None);
+
+        // Tied plays share the top spot
+        let artists = crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-13"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            artists.iter().map(|entry| entry.rank).collect::<Vec<_>>(),
+            vec![1, 1]
+        );
+        let tracks = crate::db::get_chart(&pool, ChartKind::Track, date("2024-05-20"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(tracks[0].track.as_deref(), Some("Sunflower"));
+
+        // Later plays in an already recorded week need a rebuild
+        play(&pool, "Slint", "Breadcrumb Trail", 5, first);
+        assert_eq!(record_weeks(&pool, now).unwrap(), 0);
+        assert_eq!(rebuild(&pool, now).unwrap(), 4);
+        let artists = crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-06"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(artists[0].artist, "Slint");
+        assert_eq!(artists[0].plays, 7);
+    }
+
+    #[test]
+    fn test_weeks_follow_the_instance_timezone() {
+        let (pool, _temp_file) = setup_pool();
+        let changes = serde_json::json!({"timezone": "Asia/Tokyo"});
+        crate::settings::update(&pool, changes.as_object().unwrap())
+            .unwrap()
+            .unwrap();
+
+        // Sunday night in UTC is already Monday in Tokyo
+        play(
+            &pool,
+            "Low",
+            "Lazy",
+            1,
+            "2024-05-12T20:00:00Z".parse().unwrap(),
+        );
+        record_weeks(&pool, "2024-05-30T00:00:00Z".parse().unwrap()).unwrap();
+        assert_eq!(
+            crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-06")).unwrap(),
+            None
+        );
+        assert_eq!(
+            crate::db::get_chart(&pool, ChartKind::Artist, date("2024-05-13"))
+                .unwrap()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_number_one_runs_and_leaders() {
+        let (pool, _temp_file) = setup_pool();
+        let first: DateTime<Utc> = "2024-01-02T20:00:00Z".parse().unwrap();
+        // Low at #1 for two weeks, Slint for one, then Low again
+        for (week, top) in ["Low", "Low", "Slint", "Low"].iter().enumerate() {
+            let at = first + Duration::weeks(week as i64);
+            play(&pool, top, "Song", 3, at);
+            let other = if *top == "Low" { "Slint" } else { "Low" };
+            play(&pool, other, "Song", 1, at + Duration::hours(1));
+        }
+        play(&pool, "Codeine", "Song", 1, first);
+        record_weeks(&pool, "2024-03-01T00:00:00Z".parse().unwrap()).unwrap();
+
+        let low = history(&pool, ChartKind::Artist, "Low", None).unwrap();
+        assert_eq!(
+            low.record,
+            ChartRecord {
+                artist: "Low".to_string(),
+                track: None,
+                weeks_on_chart: 4,
+                weeks_at_number_one: 3,
+                longest_number_one_run: 2,
+                peak_rank: Some(1),
+                first_week: Some(date("2024-01-01")),
+                last_week: Some(date("2024-01-22")),
+            }
+        );
+        assert_eq!(
+            low.weeks.iter().map(|entry| entry.rank).collect::<Vec<_>>(),
+            vec![1, 1, 2, 1]
+        );
+
+        let leaders = leaders(&pool, ChartKind::Artist, 10).unwrap();
+        let names: Vec<&str> = leaders.iter().map(|r| r.artist.as_str()).collect();
+        assert_eq!(names, vec!["Low", "Slint", "Codeine"]);
+        assert_eq!(leaders[2].peak_rank, Some(2));
+        assert_eq!(leaders[2].weeks_at_number_one, 0);
+
+        let song = history(&pool, ChartKind::Track, "Slint", Some("Song")).unwrap();
+        assert_eq!(song.record.weeks_at_number_one, 1);
+        assert_eq!(song.weeks[0].track.as_deref(), Some("Song"));
+    }
+}
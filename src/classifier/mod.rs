@@ -0,0 +1,138 @@
+use anyhow::Result;
+
+use crate::db::DbPool;
+use crate::models::{MediaType, MediaTypeRule, Scrobble};
+
+/// Decides whether an incoming scrobble is music, a podcast or an audiobook:
+/// manual rules first, then name-based heuristics
+#[derive(Debug, Clone, Default)]
+pub struct MediaClassifier {
+    rules: Vec<MediaTypeRule>,
+}
+
+impl MediaClassifier {
+    pub fn new(rules: Vec<MediaTypeRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn load(pool: &DbPool) -> Result<Self> {
+        Ok(Self::new(crate::db::get_media_type_rules(pool)?))
+    }
+
+    pub fn classify(&self, scrobble: &Scrobble) -> MediaType {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(scrobble))
+            .map(|rule| rule.media_type)
+            .unwrap_or_else(|| guess_media_type(scrobble))
+    }
+
+    /// Set the scrobble's media type from `classify`
+    pub fn apply(&self, mut scrobble: Scrobble) -> Scrobble {
+        scrobble.media_type = self.classify(&scrobble);
+        scrobble
+    }
+}
+
+// Words in artist or album names that give away spoken-word releases. Track
+// names alone say too little: plenty of songs are called "Chapter 1"
+const AUDIOBOOK_MARKERS: &[&str] = &["audiobook", "hörbuch", "unabridged", "livre audio"];
+const PODCAST_MARKERS: &[&str] = &["podcast"];
+
+/// Classify a scrobble from its artist and album names alone
+pub fn guess_media_type(scrobble: &Scrobble) -> MediaType {
+    let artist = scrobble.artist.to_lowercase();
+    let album = scrobble.album.as_deref().unwrap_or_default().to_lowercase();
+
+    let mentions = |markers: &[&str]| {
+        markers
+            .iter()
+            .any(|m| artist.contains(m) || album.contains(m))
+    };
+
+    if mentions(AUDIOBOOK_MARKERS) {
+        MediaType::Audiobook
+    } else if mentions(PODCAST_MARKERS) {
+        MediaType::Podcast
+    } else {
+        MediaType::Music
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_scrobble_from_rfc3339;
+
+    fn scrobble(artist: &str, album: Option<&str>, track: &str) -> Scrobble {
+        let mut scrobble = test_scrobble_from_rfc3339(artist, track, "2024-01-01T12:00:00Z");
+        scrobble.album = album.map(str::to_string);
+        scrobble
+    }
+
+    #[test]
+    fn test_guess_media_type() {
+        let cases = [
+            (
+                scrobble("Radiohead", Some("OK Computer"), "Airbag"),
+                MediaType::Music,
+            ),
+            (
+                scrobble("Radiohead", Some("My Iron Lung EP"), "Lewis"),
+                MediaType::Music,
+            ),
+            (
+                scrobble("The Daily Podcast", None, "Monday"),
+                MediaType::Podcast,
+            ),
+            (
+                scrobble("Some Host", Some("Tech Talk Podcast"), "Episode 42: Guests"),
+                MediaType::Podcast,
+            ),
+            (
+                scrobble("Narrator", Some("Dune (Unabridged)"), "Part 1"),
+                MediaType::Audiobook,
+            ),
+            (
+                scrobble("Narrator", Some("Dune (Hörbuch)"), "Kapitel 3"),
+                MediaType::Audiobook,
+            ),
+            // Songs named like episodes or chapters are still songs
+            (
+                scrobble("Jimmy Eat World", Some("Bleed American"), "Chapter 1"),
+                MediaType::Music,
+            ),
+            (
+                scrobble("Thursday", None, "Chapter 2: Home"),
+                MediaType::Music,
+            ),
+            (
+                scrobble("LCD Soundsystem", None, "Episode 1"),
+                MediaType::Music,
+            ),
+            (scrobble("Some Band", None, "Ep. 12"), MediaType::Music),
+        ];
+
+        for (scrobble, expected) in cases {
+            assert_eq!(guess_media_type(&scrobble), expected, "{}", scrobble.track);
+        }
+    }
+
+    #[test]
+    fn test_rules_take_precedence() {
+        let show = scrobble("Talk Show", Some("Season 1"), "Pilot");
+        let mislabeled = scrobble("Podcast Killers", None, "Chapter 7 Blues");
+
+        let classifier = MediaClassifier::new(vec![
+            MediaTypeRule::new(Some("talk show".into()), None, MediaType::Podcast),
+            MediaTypeRule::new(Some("Podcast Killers".into()), None, MediaType::Music),
+        ]);
+
+        assert_eq!(classifier.classify(&show), MediaType::Podcast);
+        assert_eq!(classifier.classify(&mislabeled), MediaType::Music);
+        assert_eq!(
+            MediaClassifier::default().classify(&mislabeled),
+            MediaType::Podcast
+        );
+    }
+}
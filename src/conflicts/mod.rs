@@ -0,0 +1,224 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::db::DbPool;
+use crate::models::Scrobble;
+
+/// Scrobbles from two sources this close together may be the same listen
+pub const CONFLICT_WINDOW_SECONDS: i64 = 60;
+
+// Shorter names can't be told apart from truncations by prefix alone
+const MIN_TRUNCATED_LEN: usize = 3;
+
+/// One listen reported by two sources with different metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub left: Scrobble,
+    pub right: Scrobble,
+    /// Which of "artist", "album" and "track" differ
+    pub fields: Vec<&'static str>,
+}
+
+/// Conflicting cross-source pairs, newest first
+pub fn find_conflicts(pool: &DbPool, limit: usize, offset: usize) -> Result<Vec<Conflict>> {
+    let candidates = crate::db::get_cross_source_candidates(pool, CONFLICT_WINDOW_SECONDS)?;
+
+    let mut conflicts = Vec::new();
+    for ((left_id, _, _), (right_id, _, _)) in candidates
+        .into_iter()
+        .filter(|(left, right)| same_listen(&left.1, &left.2, &right.1, &right.2))
+        .skip(offset)
+        .take(limit)
+    {
+        let (Some(left), Some(right)) = (
+            crate::db::get_scrobble(pool, left_id)?,
+            crate::db::get_scrobble(pool, right_id)?,
+        ) else {
+            continue;
+        };
+
+        conflicts.push(Conflict {
+            fields: differing_fields(&left, &right),
+            left,
+            right,
+        });
+    }
+
+    Ok(conflicts)
+}
+
+/// Whether two scrobbles are one listen with conflicting metadata: different
+/// sources, close in time, loosely the same artist and track, not identical
+pub fn is_conflict(a: &Scrobble, b: &Scrobble) -> bool {
+    a.source != b.source
+        && (a.timestamp - b.timestamp).num_seconds().abs() <= CONFLICT_WINDOW_SECONDS
+        && same_listen(&a.artist, &a.track, &b.artist, &b.track)
+        && !differing_fields(a, b).is_empty()
+}
+
+fn same_listen(a_artist: &str, a_track: &str, b_artist: &str, b_track: &str) -> bool {
+    compatible(&loose_key(a_artist), &loose_key(b_artist))
+        && compatible(&loose_key(a_track), &loose_key(b_track))
+}
+
+/// Lowercased letters and digits only, so spelling and punctuation don't matter
+fn loose_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Equal, or one is a truncation of the other
+fn compatible(a: &str, b: &str) -> bool {
+    a == b || (a.len().min(b.len()) >= MIN_TRUNCATED_LEN && (a.starts_with(b) || b.starts_with(a)))
+}
+
+fn differing_fields(a: &Scrobble, b: &Scrobble) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if a.artist != b.artist {
+        fields.push("artist");
+    }
+    if a.album != b.album {
+        fields.push("album");
+    }
+    if a.track != b.track {
+        fields.push("track");
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+
+    fn scrobble(artist: &str, album: &str, track: &str, ts: &str, source: &str) -> Scrobble {
+        Scrobble::new(
+            artist.to_string(),
+            track.to_string(),
+            ts.parse().unwrap(),
+            source.to_string(),
+        )
+        .with_album(album.to_string())
+    }
+
+    #[test]
+    fn test_truncated_and_respelled_names_conflict() {
+        let a = scrobble(
+            "Godspeed You! Black Emperor",
+            "Lift Your Skinny Fists Like Antennas to Heaven",
+            "Storm",
+            "2024-03-01T12:00:00Z",
+            "lastfm",
+        );
+        let b = scrobble(
+            "Godspeed You",
+            "Lift Yr. Skinny Fists Like Antennas to Heaven!",
+            "Storm",
+            "2024-03-01T12:00:30Z",
+            "listenbrainz",
+        );
+
+        assert!(is_conflict(&a, &b));
+        assert_eq!(differing_fields(&a, &b), vec!["artist", "album"]);
+    }
+
+    #[test]
+    fn test_different_listens_are_not_conflicts() {
+        let a = scrobble("Artist", "Album", "Song", "2024-03-01T12:00:00Z", "lastfm");
+
+        // Another track right after
+        let other_track = scrobble(
+            "Artist",
+            "Album",
+            "Other",
+            "2024-03-01T12:00:30Z",
+            "listenbrainz",
+        );
+        assert!(!is_conflict(&a, &other_track));
+
+        // Same source, or too far apart
+        let same_source = scrobble("Artist", "Albm", "Song", "2024-03-01T12:00:30Z", "lastfm");
+        assert!(!is_conflict(&a, &same_source));
+        let later = scrobble(
+            "Artist",
+            "Albm",
+            "Song",
+            "2024-03-01T12:05:00Z",
+            "listenbrainz",
+        );
+        assert!(!is_conflict(&a, &later));
+
+        // Identical metadata is a duplicate, not a conflict
+        let identical = scrobble(
+            "Artist",
+            "Album",
+            "Song",
+            "2024-03-01T12:00:30Z",
+            "listenbrainz",
+        );
+        assert!(!is_conflict(&a, &identical));
+    }
+
+    #[test]
+    fn test_find_and_resolve_conflicts() {
+        let (pool, _temp_file) = setup_pool();
+        let lastfm = scrobble(
+            "Boards of Canada",
+            "Music Has the Right to Children",
+            "Roygbiv",
+            "2024-03-01T12:00:00Z",
+            "lastfm",
+        );
+        let listenbrainz = scrobble(
+            "Boards Of Canada",
+            "Music Has The Right To Children (Remastered)",
+            "Roygbiv",
+            "2024-03-01T12:00:10Z",
+            "listenbrainz",
+        );
+        crate::db::insert_scrobble(&pool, &lastfm).unwrap();
+        crate::db::insert_scrobble(&pool, &listenbrainz).unwrap();
+        crate::db::insert_scrobble(
+            &pool,
+            &scrobble(
+                "Unrelated",
+                "Other",
+                "Song",
+                "2024-03-01T12:00:20Z",
+                "listenbrainz",
+            ),
+        )
+        .unwrap();
+
+        let conflicts = find_conflicts(&pool, 10, 0).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].left.source, "lastfm");
+        assert_eq!(conflicts[0].fields, vec!["artist", "album"]);
+
+        let plays = |artist: &str| {
+            crate::db::entity_play_count(&mut pool.get().unwrap(), "artist", artist, "")
+                .unwrap()
+                .plays
+        };
+        assert_eq!(plays("Boards Of Canada"), 1);
+
+        let updated =
+            crate::db::apply_canonical_metadata(&pool, &conflicts[0].left, &conflicts[0].right)
+                .unwrap()
+                .unwrap();
+        assert_eq!(updated.artist, "Boards of Canada");
+        assert_eq!(updated.raw_metadata.unwrap().artist, "Boards Of Canada");
+
+        // Both names' derived counts follow the move
+        assert_eq!(plays("Boards Of Canada"), 0);
+        assert_eq!(plays("Boards of Canada"), 2);
+        let before = "2024-03-02T00:00:00Z".parse().unwrap();
+        let first_heard = crate::db::get_artists_first_heard_before(&pool, before).unwrap();
+        assert!(first_heard.contains("Boards of Canada"));
+        assert!(!first_heard.contains("Boards Of Canada"));
+
+        assert!(find_conflicts(&pool, 10, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbPool;
+
+/// Separators splitting an artist credit by default. Joiners like "&" or ","
+/// also appear inside band names, so they are opt-in
+pub const DEFAULT_SEPARATORS: [&str; 3] = ["feat.", "ft.", "featuring"];
+
+/// How top-artist queries credit a scrobble
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtistCredit {
+    /// The artist field as stored, "A feat. B" being its own artist
+    #[default]
+    Full,
+    /// Only the first artist of the credit
+    Primary,
+    /// The primary and every featured artist, one play each
+    All,
+}
+
+/// Artists named in an artist credit, the primary one first. The credit is
+/// split on `separators` (matched as whole words, ignoring ASCII case), unless
+/// the ListenBrainz `metadata` of one of its scrobbles says otherwise: a
+/// single artist MBID keeps the credit whole, and artist names listed next to
+/// the MBIDs are taken as they are
+pub fn split(
+    credit: &str,
+    separators: &[String],
+    metadata: Option<&serde_json::Value>,
+) -> Vec<String> {
+    let credit = credit.trim();
+    let mbids = metadata
+        .and_then(|m| m.get("artist_mbids"))
+        .and_then(|v| v.as_array())
+        .filter(|mbids| !mbids.is_empty());
+
+    if let Some(mbids) = mbids {
+        if mbids.len() == 1 {
+            return vec![credit.to_string()];
+        }
+        let names: Vec<String> = metadata
+            .and_then(|m| m.get("artist_names"))
+            .and_then(|v| v.as_array())
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|n| n.as_str())
+                    .map(|n| n.trim().to_string())
+                    .filter(|n| !n.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if names.len() == mbids.len() {
+            return names;
+        }
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = credit;
+    while let Some((at, len)) = find_separator(rest, separators) {
+        parts.push(rest[..at].trim().to_string());
+        rest = &rest[at + len..];
+    }
+    parts.push(rest.trim().to_string());
+    parts.retain(|part| !part.is_empty());
+
+    // With MBIDs, a split that doesn't find as many artists is a wrong guess
+    match mbids {
+        Some(mbids) if mbids.len() != parts.len() => vec![credit.to_string()],
+        _ if parts.is_empty() => vec![credit.to_string()],
+        _ => parts,
+    }
+}
+
+/// Byte offset and length of the first separator in `value`. A separator must
+/// be followed by whitespace, and preceded by it when it starts with a letter
+/// or digit, so "ft." doesn't match inside "Daft. Punk" but "," does in "A, B"
+fn find_separator(value: &str, separators: &[String]) -> Option<(usize, usize)> {
+    value.char_indices().find_map(|(at, _)| {
+        let before = value[..at].chars().next_back();
+        separators.iter().map(|s| s.trim()).find_map(|sep| {
+            let candidate = value.get(at..at + sep.len())?;
+            let after = value[at + sep.len()..].chars().next();
+            let needs_space_before = sep.starts_with(|c: char| c.is_alphanumeric());
+            (!sep.is_empty()
+                && candidate.eq_ignore_ascii_case(sep)
+                && after.is_some_and(char::is_whitespace)
+                && before.is_some_and(|c| !needs_space_before || c.is_whitespace()))
+            .then_some((at, sep.len()))
+        })
+    })
+}
+
+/// Split every artist name not credited yet, so credited queries see the
+/// whole history. Returns how many names were split
+pub fn refresh(pool: &DbPool, separators: &[String]) -> Result<usize> {
+    let pending = crate::db::get_uncredited_artists(pool)?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let credits: Vec<(String, Vec<String>)> = pending
+        .into_iter()
+        .map(|(artist, metadata)| {
+            let metadata = metadata.and_then(|m| serde_json::from_str(&m).ok());
+            let names = split(&artist, separators, metadata.as_ref());
+            (artist, names)
+        })
+        .collect();
+    crate::db::save_artist_credits(pool, &credits)
+}
+
+/// `refresh` with the separators of the instance's settings
+pub fn refresh_from_settings(pool: &DbPool) -> Result<usize> {
+    let separators = crate::settings::load(pool)?.artist_separators;
+    refresh(pool, &separators)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+    use chrono::{Duration, Utc};
+
+    fn defaults() -> Vec<String> {
+        DEFAULT_SEPARATORS.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_on_separators() {
+        assert_eq!(
+            split("Artist A feat. Artist B", &defaults(), None),
+            vec!["Artist A", "Artist B"]
+        );
+        assert_eq!(
+            split("A FT. B featuring C", &defaults(), None),
+            vec!["A", "B", "C"]
+        );
+        assert_eq!(split("Daft. Punk", &defaults(), None), vec!["Daft. Punk"]);
+        assert_eq!(
+            split("Simon & Garfunkel", &defaults(), None),
+            vec!["Simon & Garfunkel"]
+        );
+
+        let joiners = vec!["&".to_string(), ",".to_string()];
+        assert_eq!(split("A, B & C", &joiners, None), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_split_follows_mbids() {
+        let joiners = vec!["&".to_string()];
+        let single = serde_json::json!({"artist_mbids": ["5c3b9f5e"]});
+        assert_eq!(
+            split("Simon & Garfunkel", &joiners, Some(&single)),
+            vec!["Simon & Garfunkel"]
+        );
+
+        let named = serde_json::json!({
+            "artist_mbids": ["a", "b"],
+            "artist_names": ["Artist A", "Artist B"],
+        });
+        assert_eq!(
+            split("Artist A x Artist B", &defaults(), Some(&named)),
+            vec!["Artist A", "Artist B"]
+        );
+
+        // Two MBIDs but only one part found: don't guess
+        let unnamed = serde_json::json!({"artist_mbids": ["a", "b"]});
+        assert_eq!(
+            split("Artist A x Artist B", &defaults(), Some(&unnamed)),
+            vec!["Artist A x Artist B"]
+        );
+    }
+
+    #[test]
+    fn test_top_artists_credit_featured_artists() {
+        let (pool, _temp_file) = setup_pool();
+
+        let now = Utc::now() - Duration::days(1);
+        for (i, artist) in ["A feat. B", "A feat. B", "A", "B"].iter().enumerate() {
+            let scrobble = Scrobble::new(
+                artist.to_string(),
+                format!("Track {}", i),
+                now + Duration::minutes(i as i64 * 5),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+        }
+
+        assert_eq!(refresh(&pool, &defaults()).unwrap(), 3);
+        assert_eq!(refresh(&pool, &defaults()).unwrap(), 0);
+
+        let conn = pool.get().unwrap();
+        let top =
+            |credit| crate::db::query_top_credited_artists(&conn, credit, 10, None, None).unwrap();
+        let full = top(ArtistCredit::Full);
+        assert_eq!(full.len(), 3);
+        assert_eq!(full[0], ("A feat. B".to_string(), 2));
+        assert_eq!(
+            top(ArtistCredit::Primary),
+            vec![("A".to_string(), 3), ("B".to_string(), 1)]
+        );
+        assert_eq!(
+            top(ArtistCredit::All),
+            vec![("A".to_string(), 3), ("B".to_string(), 3)]
+        );
+
+        // Changing the separators starts over
+        crate::db::clear_artist_credits(&pool).unwrap();
+        assert_eq!(refresh(&pool, &[]).unwrap(), 3);
+        assert_eq!(top(ArtistCredit::All)[0], ("A feat. B".to_string(), 2));
+    }
+}
@@ -1,19 +1,61 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc,
+};
+use chrono_tz::Tz;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::{Connection, OpenFlags, params, params_from_iter};
+use std::collections::{HashMap, HashSet};
 
-use crate::models::{Scrobble, SyncConfig};
+use crate::credits::ArtistCredit;
+use crate::models::{
+    Achievement, AlertRule, Annotation, AnnotationKind, ChartEntry, ChartKind, DailySummary,
+    DetectionStatus, IgnoreRule, ImportJob, ImportStatus, ListenFilter, MediaTypeRule,
+    MilestoneRule, Note, Rating, RatingKind, RawMetadata, Scrobble, ShareToken, SleepDetection,
+    SyncConfig,
+};
+use crate::reports::period::IsoWeek;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 
+/// Statements each pooled connection keeps prepared. Queries go through
+/// `prepare_cached`, and rusqlite's default of 16 is fewer than a dashboard
+/// load runs
+const STATEMENT_CACHE_CAPACITY: usize = 256;
+
 pub fn create_pool(db_path: &str) -> Result<DbPool> {
-    let manager = SqliteConnectionManager::file(db_path);
+    pool_with_flags(db_path, OpenFlags::default())
+}
+
+/// Pool over an existing database that can't write to it: every write fails
+/// with SQLITE_READONLY instead of quietly changing the file
+pub fn create_read_only_pool(db_path: &str) -> Result<DbPool> {
+    pool_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_URI
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+}
+
+fn pool_with_flags(db_path: &str, flags: OpenFlags) -> Result<DbPool> {
+    let manager = SqliteConnectionManager::file(db_path)
+        .with_flags(flags)
+        .with_init(|conn| {
+            conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+            Ok(())
+        });
     let pool = Pool::new(manager)?;
     Ok(pool)
 }
 
+/// Whether `conn` was opened read-only, so caches filled on read are skipped
+pub fn is_read_only(conn: &Connection) -> bool {
+    conn.is_readonly(rusqlite::DatabaseName::Main)
+        .unwrap_or(false)
+}
+
 pub fn init_database(pool: &DbPool) -> Result<()> {
     let conn = pool.get()?;
 
@@ -26,19 +68,54 @@ pub fn init_database(pool: &DbPool) -> Result<()> {
             timestamp INTEGER NOT NULL,
             source TEXT NOT NULL,
             source_id TEXT,
+            ms_played INTEGER,
+            skipped INTEGER,
             UNIQUE(artist, track, timestamp, source)
         )",
         [],
     )?;
 
+    // Partial play metadata, added after the initial schema
+    add_column_if_missing(&conn, "scrobbles", "ms_played", "INTEGER")?;
+    add_column_if_missing(&conn, "scrobbles", "skipped", "INTEGER")?;
+    add_column_if_missing(&conn, "scrobbles", "raw_metadata", "TEXT")?;
+    add_column_if_missing(&conn, "scrobbles", "source_metadata", "TEXT")?;
+    add_column_if_missing(&conn, "scrobbles", "album_artist", "TEXT")?;
+    add_column_if_missing(
+        &conn,
+        "scrobbles",
+        "media_type",
+        "TEXT NOT NULL DEFAULT 'music'",
+    )?;
+    add_column_if_missing(
+        &conn,
+        "scrobbles",
+        "sleep_flagged",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+
     // Create indices for better query performance
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_timestamp ON scrobbles(timestamp DESC)",
         [],
     )?;
 
+    // Artist pages look albums up by artist; (artist, album) also serves
+    // lookups by artist alone, so it replaces the old single-column index
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_artist_album ON scrobbles(artist, album)",
+        [],
+    )?;
+    conn.execute("DROP INDEX IF EXISTS idx_artist", [])?;
+
+    // Top lists over a date range only count music that isn't sleep autoplay:
+    // partial indexes matching that filter let them read just the range, and
+    // cover top artists and tracks without touching the table. Lookups by
+    // (artist, track, timestamp) use the UNIQUE constraint's index
     conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_artist ON scrobbles(artist)",
+        "CREATE INDEX IF NOT EXISTS idx_listens_timestamp
+         ON scrobbles(timestamp, artist, track)
+         WHERE media_type = 'music' AND sleep_flagged = 0",
         [],
     )?;
 
@@ -76,6 +153,20 @@ pub fn init_database(pool: &DbPool) -> Result<()> {
         [],
     )?;
 
+    // Create image_thumbnails table for resized artwork served by the image proxy
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_thumbnails (
+            source_url TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            content_type TEXT NOT NULL,
+            data BLOB NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            last_accessed INTEGER NOT NULL,
+            PRIMARY KEY(source_url, size)
+        )",
+        [],
+    )?;
+
     // Create sync_configs table for automatic sync configuration
     conn.execute(
         "CREATE TABLE IF NOT EXISTS sync_configs (
@@ -94,6 +185,10 @@ pub fn init_database(pool: &DbPool) -> Result<()> {
         [],
     )?;
 
+    // Failure tracking for sync alerts, added after the initial schema
+    add_column_if_missing(&conn, "sync_configs", "failing_since", "INTEGER")?;
+    add_column_if_missing(&conn, "sync_configs", "last_error", "TEXT")?;
+
     // Create index for enabled sync configs
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_sync_configs_enabled
@@ -101,529 +196,4854 @@ pub fn init_database(pool: &DbPool) -> Result<()> {
         [],
     )?;
 
-    Ok(())
-}
-
-pub fn insert_scrobble(pool: &DbPool, scrobble: &Scrobble) -> Result<i64> {
-    let conn = pool.get()?;
+    // Create first listens index: earliest scrobble of each artist, track and
+    // album, so "first time heard" doesn't require scanning the history
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS first_listens (
+            entity_type TEXT NOT NULL,
+            artist TEXT NOT NULL,
+            name TEXT NOT NULL DEFAULT '',
+            first_timestamp INTEGER NOT NULL,
+            PRIMARY KEY(entity_type, artist, name)
+        )",
+        [],
+    )?;
 
     conn.execute(
-        "INSERT OR IGNORE INTO scrobbles (artist, album, track, timestamp, source, source_id)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            scrobble.artist,
-            scrobble.album,
-            scrobble.track,
-            scrobble.timestamp.timestamp(),
-            scrobble.source,
-            scrobble.source_id,
-        ],
+        "CREATE INDEX IF NOT EXISTS idx_first_listens_timestamp
+         ON first_listens(entity_type, first_timestamp)",
+        [],
     )?;
 
-    Ok(conn.last_insert_rowid())
-}
+    // Create loved tracks table, filled by importer enrichment
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS loved_tracks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            artist TEXT NOT NULL,
+            track TEXT NOT NULL,
+            loved_at INTEGER,
+            source TEXT NOT NULL,
+            UNIQUE(artist, track)
+        )",
+        [],
+    )?;
 
-pub fn insert_scrobbles_batch(pool: &DbPool, scrobbles: &[Scrobble]) -> Result<usize> {
-    if scrobbles.is_empty() {
-        return Ok(0);
-    }
+    // Create artist tags (genre) table, filled by importer enrichment
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS artist_tags (
+            artist TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            weight INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            PRIMARY KEY(artist, tag)
+        )",
+        [],
+    )?;
 
-    let mut conn = pool.get()?;
-    let tx = conn.transaction()?;
+    // When each artist's tags were last fetched, so artists without any are
+    // only asked about again once the retry delay has passed
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS artist_tag_fetches (
+            artist TEXT PRIMARY KEY,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-    let mut inserted = 0;
-    for scrobble in scrobbles {
-        let changes = tx.execute(
-            "INSERT OR IGNORE INTO scrobbles (artist, album, track, timestamp, source, source_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                scrobble.artist,
-                scrobble.album,
-                scrobble.track,
-                scrobble.timestamp.timestamp(),
-                scrobble.source,
-                scrobble.source_id,
-            ],
-        )?;
-        inserted += changes;
-    }
+    // Create annotations table: timeline markers on a day or a span, optionally for one artist
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS annotations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            artist TEXT,
+            date TEXT NOT NULL,
+            end_date TEXT,
+            kind TEXT NOT NULL,
+            label TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-    tx.commit()?;
-    Ok(inserted)
-}
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_annotations_artist ON annotations(artist, date)",
+        [],
+    )?;
 
-pub fn get_scrobbles(
-    pool: &DbPool,
-    limit: Option<i64>,
-    offset: Option<i64>,
-) -> Result<Vec<Scrobble>> {
-    let conn = pool.get()?;
-    let limit = limit.unwrap_or(100);
-    let offset = offset.unwrap_or(0);
+    // Create ratings table for 1-5 star track and album ratings
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ratings (
+            kind TEXT NOT NULL,
+            artist TEXT NOT NULL,
+            name TEXT NOT NULL,
+            rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY(kind, artist, name)
+        )",
+        [],
+    )?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, artist, album, track, timestamp, source, source_id
-         FROM scrobbles
-         ORDER BY timestamp DESC
-         LIMIT ?1 OFFSET ?2",
+    // Create notes table: each note targets either a scrobble or a day
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scrobble_id INTEGER,
+            date TEXT,
+            body TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            CHECK ((scrobble_id IS NULL) <> (date IS NULL))
+        )",
+        [],
     )?;
 
-    let scrobbles = stmt
-        .query_map(params![limit, offset], |row| {
-            let timestamp_value: i64 = row.get(4)?;
-            let timestamp = DateTime::from_timestamp(timestamp_value, 0).unwrap_or_else(|| {
-                tracing::warn!(
-                    "Invalid timestamp {} in database for scrobble id {:?}, using current time",
-                    timestamp_value,
-                    row.get::<_, i64>(0).ok()
-                );
-                Utc::now()
-            });
-            Ok(Scrobble {
-                id: Some(row.get(0)?),
-                artist: row.get(1)?,
-                album: row.get(2)?,
-                track: row.get(3)?,
-                timestamp,
-                source: row.get(5)?,
-                source_id: row.get(6)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notes_scrobble ON notes(scrobble_id)",
+        [],
+    )?;
 
-    Ok(scrobbles)
-}
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notes_date ON notes(date)",
+        [],
+    )?;
 
-pub fn get_scrobbles_in_range(
-    pool: &DbPool,
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-) -> Result<Vec<Scrobble>> {
-    let conn = pool.get()?;
+    // Create share tokens table for public read-only links
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS share_tokens (
+            token TEXT PRIMARY KEY,
+            label TEXT,
+            scopes TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER
+        )",
+        [],
+    )?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, artist, album, track, timestamp, source, source_id
-         FROM scrobbles
-         WHERE timestamp >= ?1 AND timestamp <= ?2
-         ORDER BY timestamp ASC",
+    // Create users table for identities authenticated by a reverse proxy
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE,
+            created_at INTEGER NOT NULL,
+            last_seen_at INTEGER NOT NULL
+        )",
+        [],
     )?;
 
-    let scrobbles = stmt
-        .query_map(
-            params![start_date.timestamp(), end_date.timestamp()],
-            |row| {
-                let timestamp_value: i64 = row.get(4)?;
-                let timestamp = DateTime::from_timestamp(timestamp_value, 0).unwrap_or_else(|| {
-                    tracing::warn!(
-                        "Invalid timestamp {} in database for scrobble id {:?}, using current time",
-                        timestamp_value,
-                        row.get::<_, i64>(0).ok()
-                    );
-                    Utc::now()
-                });
-                Ok(Scrobble {
-                    id: Some(row.get(0)?),
-                    artist: row.get(1)?,
-                    album: row.get(2)?,
-                    track: row.get(3)?,
-                    timestamp,
-                    source: row.get(5)?,
-                    source_id: row.get(6)?,
-                })
-            },
-        )?
-        .collect::<Result<Vec<_>, _>>()?;
+    // Create ignore rules table: artists/tracks importers should not store
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ignore_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT,
+            artist TEXT,
+            track TEXT,
+            suppressed_count INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            CHECK (artist IS NOT NULL OR track IS NOT NULL)
+        )",
+        [],
+    )?;
 
-    Ok(scrobbles)
-}
+    // Create sleep detections table: overnight autoplay runs awaiting review
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sleep_detections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            start_ts INTEGER NOT NULL,
+            end_ts INTEGER NOT NULL,
+            scrobble_count INTEGER NOT NULL,
+            distinct_artists INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at INTEGER NOT NULL,
+            UNIQUE(start_ts, end_ts)
+        )",
+        [],
+    )?;
 
-pub fn get_scrobbles_count(pool: &DbPool) -> Result<i64> {
-    let conn = pool.get()?;
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM scrobbles", [], |row| row.get(0))?;
-    Ok(count)
-}
+    // Create alert rules table for archive health notifications
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alert_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            threshold_hours INTEGER NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            active INTEGER NOT NULL DEFAULT 0,
+            last_triggered_at INTEGER,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-pub fn get_scrobbles_count_in_range(
-    pool: &DbPool,
-    start_date: Option<DateTime<Utc>>,
-    end_date: Option<DateTime<Utc>>,
-) -> Result<i64> {
-    let conn = pool.get()?;
+    // Create milestone rules table: custom badges of the yearly report
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS milestone_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            threshold REAL NOT NULL,
+            icon TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-    if let (Some(start), Some(end)) = (start_date, end_date) {
-        let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM scrobbles WHERE timestamp >= ?1 AND timestamp <= ?2",
-            params![start.timestamp(), end.timestamp()],
-            |row| row.get(0),
-        )?;
-        Ok(count)
-    } else {
-        get_scrobbles_count(pool)
-    }
-}
+    // Create achievements table: milestones as first earned, once per year
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS achievements (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            year INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            value TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            earned_at INTEGER NOT NULL,
+            UNIQUE(year, key)
+        )",
+        [],
+    )?;
 
-pub fn get_top_artists(
-    pool: &DbPool,
-    limit: i64,
-    start_date: Option<DateTime<Utc>>,
-    end_date: Option<DateTime<Utc>>,
-) -> Result<Vec<(String, i64)>> {
-    let conn = pool.get()?;
+    // Create import jobs table: progress of full imports, for resuming them
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL,
+            username TEXT NOT NULL,
+            api_key TEXT,
+            token TEXT,
+            enrich INTEGER NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'running',
+            checkpoint INTEGER,
+            imported_count INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            started_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-    if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
-            "SELECT artist, COUNT(*) as count FROM scrobbles
-             WHERE timestamp >= ?1 AND timestamp <= ?2
-             GROUP BY artist ORDER BY count DESC LIMIT ?3",
-        )?;
-        let artists_iter = stmt
-            .query_map(params![start.timestamp(), end.timestamp(), limit], |row| {
-                Ok((row.get(0)?, row.get(1)?))
-            })?;
-        let artists: Vec<(String, i64)> = artists_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(artists)
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT artist, COUNT(*) as count FROM scrobbles
-             GROUP BY artist ORDER BY count DESC LIMIT ?1",
-        )?;
-        let artists_iter = stmt.query_map(params![limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
-        let artists: Vec<(String, i64)> = artists_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(artists)
-    }
-}
+    // Create musicbrainz_entities table: resolved MBIDs, reused by image and metadata lookups
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS musicbrainz_entities (
+            entity_type TEXT NOT NULL,
+            artist TEXT NOT NULL,
+            name TEXT NOT NULL DEFAULT '',
+            mbid TEXT NOT NULL,
+            resolved_at INTEGER NOT NULL,
+            PRIMARY KEY(entity_type, artist, name)
+        )",
+        [],
+    )?;
 
-pub fn get_top_tracks(
-    pool: &DbPool,
-    limit: i64,
-    start_date: Option<DateTime<Utc>>,
-    end_date: Option<DateTime<Utc>>,
-) -> Result<Vec<(String, String, i64)>> {
-    let conn = pool.get()?;
+    // Create media type rules table: manual podcast/audiobook classification
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS media_type_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            artist TEXT,
+            album TEXT,
+            media_type TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            CHECK (artist IS NOT NULL OR album IS NOT NULL)
+        )",
+        [],
+    )?;
 
-    if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
-            "SELECT artist, track, COUNT(*) as count FROM scrobbles
-             WHERE timestamp >= ?1 AND timestamp <= ?2
-             GROUP BY artist, track ORDER BY count DESC LIMIT ?3",
-        )?;
-        let tracks_iter = stmt
-            .query_map(params![start.timestamp(), end.timestamp(), limit], |row| {
-                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-            })?;
-        let tracks: Vec<(String, String, i64)> = tracks_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(tracks)
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT artist, track, COUNT(*) as count FROM scrobbles
-             GROUP BY artist, track ORDER BY count DESC LIMIT ?1",
-        )?;
-        let tracks_iter = stmt.query_map(params![limit], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        })?;
-        let tracks: Vec<(String, String, i64)> = tracks_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(tracks)
-    }
-}
+    // Create maintenance log table: audit trail of bulk corrections to scrobbles
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            action TEXT NOT NULL,
+            details TEXT NOT NULL,
+            affected INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
 
-pub fn get_top_albums(
+    // Create settings table: per-instance preferences, one JSON value per key
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create releases table: recent release groups by top artists, found by the release radar
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS releases (
+            mbid TEXT PRIMARY KEY,
+            artist TEXT NOT NULL,
+            title TEXT NOT NULL,
+            release_type TEXT,
+            release_date TEXT NOT NULL,
+            discovered_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_releases_date ON releases(release_date)",
+        [],
+    )?;
+
+    // Create release_radar_checks table: when each artist's releases were last looked up
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS release_radar_checks (
+            artist TEXT PRIMARY KEY,
+            checked_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create chart_snapshots table: each finished week's top artists and tracks,
+    // with track '' on the artist chart
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chart_snapshots (
+            chart TEXT NOT NULL,
+            week_start TEXT NOT NULL,
+            rank INTEGER NOT NULL,
+            artist TEXT NOT NULL,
+            track TEXT NOT NULL DEFAULT '',
+            plays INTEGER NOT NULL,
+            PRIMARY KEY (chart, week_start, artist, track)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_chart_snapshots_entity
+         ON chart_snapshots(chart, artist, track, week_start)",
+        [],
+    )?;
+
+    // Create chart_weeks table: weeks whose charts were recorded, empty ones included
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chart_weeks (
+            week_start TEXT PRIMARY KEY,
+            recorded_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create legacy_charts table: Last.fm weekly charts from before the first
+    // scrobble, with track '' on the artist chart
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS legacy_charts (
+            chart TEXT NOT NULL,
+            week_from INTEGER NOT NULL,
+            artist TEXT NOT NULL,
+            track TEXT NOT NULL DEFAULT '',
+            plays INTEGER NOT NULL,
+            PRIMARY KEY (chart, week_from, artist, track)
+        )",
+        [],
+    )?;
+
+    // Create legacy_chart_weeks table: Last.fm chart weeks imported, empty ones included
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS legacy_chart_weeks (
+            week_from INTEGER PRIMARY KEY,
+            week_to INTEGER NOT NULL,
+            imported_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create daily_summaries table: each finished local day, rolled up nightly
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_summaries (
+            date TEXT PRIMARY KEY,
+            timezone TEXT NOT NULL,
+            scrobbles INTEGER NOT NULL,
+            top_artist TEXT,
+            top_artist_count INTEGER NOT NULL,
+            new_artists TEXT NOT NULL,
+            sessions INTEGER NOT NULL,
+            recorded_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Any change to a scrobble drops the summaries from the day before it
+    // (local dates can trail UTC by a day) onward: later days can change too,
+    // as the artists that are new on them depend on earlier plays. Reads
+    // compute dropped days again, and the next rollup stores them back.
+    // Scrobbles moved to the archive are still listens, so archiving (which
+    // copies each row there before deleting it) keeps the summaries
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS daily_summaries_insert AFTER INSERT ON scrobbles
+         BEGIN
+            DELETE FROM daily_summaries
+            WHERE date >= date(NEW.timestamp, 'unixepoch', '-1 day');
+         END;
+
+         DROP TRIGGER IF EXISTS daily_summaries_delete;
+         CREATE TRIGGER daily_summaries_delete AFTER DELETE ON scrobbles
+         WHEN NOT EXISTS (SELECT 1 FROM scrobbles_archive WHERE id = OLD.id)
+         BEGIN
+            DELETE FROM daily_summaries
+            WHERE date >= date(OLD.timestamp, 'unixepoch', '-1 day');
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS daily_summaries_update
+         AFTER UPDATE OF artist, timestamp ON scrobbles
+         BEGIN
+            DELETE FROM daily_summaries
+            WHERE date >= date(MIN(OLD.timestamp, NEW.timestamp), 'unixepoch', '-1 day');
+         END;",
+    )?;
+
+    // Create recommended_playlists table: playlists ListenBrainz generated for a user
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recommended_playlists (
+            mbid TEXT PRIMARY KEY,
+            username TEXT NOT NULL,
+            title TEXT NOT NULL,
+            kind TEXT,
+            created_at INTEGER NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recommended_playlist_tracks (
+            playlist_mbid TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            artist TEXT NOT NULL,
+            track TEXT NOT NULL,
+            album TEXT,
+            recording_mbid TEXT,
+            PRIMARY KEY(playlist_mbid, position)
+        )",
+        [],
+    )?;
+
+    // Create compilations table: albums credited to one album artist whatever
+    // their track artists, e.g. "Various Artists"
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS compilations (
+            album TEXT PRIMARY KEY COLLATE NOCASE,
+            album_artist TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create artist_credits table: the artists an artist field names, the
+    // primary one at position 0 and featured ones after it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS artist_credits (
+            artist TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            PRIMARY KEY(artist, position)
+        )",
+        [],
+    )?;
+
+    // Create scrobbles archive: old scrobbles moved out of the hot table, with
+    // their ids kept so notes still point at them once restored
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scrobbles_archive (
+            id INTEGER PRIMARY KEY,
+            artist TEXT NOT NULL,
+            album TEXT,
+            track TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            source_id TEXT,
+            ms_played INTEGER,
+            skipped INTEGER,
+            raw_metadata TEXT,
+            source_metadata TEXT,
+            album_artist TEXT,
+            media_type TEXT NOT NULL DEFAULT 'music',
+            sleep_flagged INTEGER NOT NULL DEFAULT 0,
+            archived_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scrobbles_archive_listen
+         ON scrobbles_archive(artist, track, timestamp)",
+        [],
+    )?;
+
+    // Daily summaries read archived days by date
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_scrobbles_archive_timestamp
+         ON scrobbles_archive(timestamp)",
+        [],
+    )?;
+
+    // Create play counts cache: plays and first/last play of artists, tracks
+    // and albums (keyed by album artist) over their whole history, filled on
+    // first read. Inserts keep cached entries up to date; any other change to
+    // a scrobble drops the entries it touches, which are counted again on
+    // next read
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS play_counts (
+            entity_type TEXT NOT NULL,
+            artist TEXT NOT NULL,
+            name TEXT NOT NULL DEFAULT '',
+            plays INTEGER NOT NULL,
+            first_timestamp INTEGER NOT NULL,
+            last_timestamp INTEGER NOT NULL,
+            PRIMARY KEY(entity_type, artist, name)
+        )",
+        [],
+    )?;
+
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS play_counts_insert AFTER INSERT ON scrobbles
+         BEGIN
+            UPDATE play_counts
+            SET plays = plays + 1,
+                first_timestamp = MIN(first_timestamp, NEW.timestamp),
+                last_timestamp = MAX(last_timestamp, NEW.timestamp)
+            WHERE (entity_type = 'artist' AND artist = NEW.artist AND name = '')
+               OR (entity_type = 'track' AND artist = NEW.artist AND name = NEW.track)
+               OR (entity_type = 'album' AND artist = COALESCE(NEW.album_artist, NEW.artist)
+                   AND name = NEW.album);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS play_counts_delete AFTER DELETE ON scrobbles
+         BEGIN
+            DELETE FROM play_counts
+            WHERE (entity_type = 'artist' AND artist = OLD.artist AND name = '')
+               OR (entity_type = 'track' AND artist = OLD.artist AND name = OLD.track)
+               OR (entity_type = 'album' AND artist = COALESCE(OLD.album_artist, OLD.artist)
+                   AND name = OLD.album);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS play_counts_update
+         AFTER UPDATE OF artist, album, track, album_artist, timestamp ON scrobbles
+         BEGIN
+            DELETE FROM play_counts
+            WHERE (entity_type = 'artist' AND artist IN (OLD.artist, NEW.artist) AND name = '')
+               OR (entity_type = 'track' AND artist = OLD.artist AND name = OLD.track)
+               OR (entity_type = 'track' AND artist = NEW.artist AND name = NEW.track)
+               OR (entity_type = 'album' AND artist = COALESCE(OLD.album_artist, OLD.artist)
+                   AND name = OLD.album)
+               OR (entity_type = 'album' AND artist = COALESCE(NEW.album_artist, NEW.artist)
+                   AND name = NEW.album);
+         END;",
+    )?;
+
+    // Backfill the first listens index for databases created before it existed
+    let needs_backfill: bool = conn.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM first_listens)
+            AND EXISTS (SELECT 1 FROM scrobbles)",
+        [],
+        |row| row.get(0),
+    )?;
+    if needs_backfill {
+        tracing::info!("Building first listens index from scrobble history");
+        conn.execute_batch(&format!("BEGIN; {} COMMIT;", REBUILD_FIRST_LISTENS_SQL))?;
+    }
+
+    Ok(())
+}
+
+/// Add a column to an existing table, for databases created before it existed
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Run `f` inside a single read transaction so that every query it issues
+/// observes the same snapshot, even while an import is writing.
+pub fn with_read_txn<T>(pool: &DbPool, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let result = f(&tx)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+const DEDUP_WINDOW_KEY: &str = "dedup_window_seconds";
+
+/// How many seconds apart two scrobbles of the same track, from any sources,
+/// must be to both be stored; 0, the default, only rejects exact duplicates
+/// from the same source. Set through the `dedup_window_seconds` setting
+fn dedup_window_seconds(conn: &Connection) -> Result<i64> {
+    let value: Option<String> = match conn
+        .prepare_cached("SELECT value FROM settings WHERE key = ?1")?
+        .query_row(params![DEDUP_WINDOW_KEY], |row| row.get(0))
+    {
+        Ok(value) => Some(value),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(value
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(0)
+        .max(0))
+}
+
+pub fn insert_scrobble(pool: &DbPool, scrobble: &Scrobble) -> Result<i64> {
+    let conn = pool.get()?;
+
+    insert_scrobble_deduped(&conn, scrobble, dedup_window_seconds(&conn)?)?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn insert_scrobbles_batch(pool: &DbPool, scrobbles: &[Scrobble]) -> Result<usize> {
+    if scrobbles.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let window = dedup_window_seconds(&tx)?;
+    let mut inserted = 0;
+    for scrobble in scrobbles {
+        inserted += insert_scrobble_deduped(&tx, scrobble, window)?;
+    }
+
+    tx.commit()?;
+    Ok(inserted)
+}
+
+/// Insert a scrobble unless the same artist and track is already stored
+/// within `window` seconds of it, from any source, or at the same second from
+/// the same source when `window` is 0. Returns the number of rows inserted.
+fn insert_scrobble_deduped(conn: &Connection, scrobble: &Scrobble, window: i64) -> Result<usize> {
+    let raw_metadata = scrobble
+        .raw_metadata
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let source_metadata = scrobble
+        .source_metadata
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO scrobbles (artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata, media_type, source_metadata, album_artist)
+         SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?10, ?11, ?12,
+                COALESCE((SELECT album_artist FROM compilations WHERE album = ?2), ?13)
+         WHERE NOT EXISTS (
+            SELECT 1 FROM scrobbles
+            WHERE artist = ?1 AND track = ?3 AND (?9 > 0 OR source = ?5)
+              AND timestamp BETWEEN ?4 - ?9 AND ?4 + ?9
+         )
+         AND NOT EXISTS (
+            SELECT 1 FROM scrobbles_archive
+            WHERE artist = ?1 AND track = ?3 AND (?9 > 0 OR source = ?5)
+              AND timestamp BETWEEN ?4 - ?9 AND ?4 + ?9
+         )",
+    )?;
+
+    let changes = stmt.execute(params![
+        scrobble.artist,
+        scrobble.album,
+        scrobble.track,
+        scrobble.timestamp.timestamp(),
+        scrobble.source,
+        scrobble.source_id,
+        scrobble.ms_played,
+        scrobble.skipped,
+        window,
+        raw_metadata,
+        scrobble.media_type.as_str(),
+        source_metadata,
+        scrobble.album_artist,
+    ])?;
+
+    if changes > 0 {
+        record_first_listen(conn, scrobble)?;
+    }
+
+    Ok(changes)
+}
+
+/// Keep `first_listens` up to date for a newly inserted scrobble. Imports can
+/// arrive out of order, so an earlier timestamp replaces the stored one.
+fn record_first_listen(conn: &Connection, scrobble: &Scrobble) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO first_listens (entity_type, artist, name, first_timestamp)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(entity_type, artist, name)
+         DO UPDATE SET first_timestamp = MIN(first_timestamp, excluded.first_timestamp)",
+    )?;
+
+    let ts = scrobble.timestamp.timestamp();
+    stmt.execute(params!["artist", scrobble.artist, "", ts])?;
+    stmt.execute(params!["track", scrobble.artist, scrobble.track, ts])?;
+    if let Some(album) = &scrobble.album {
+        stmt.execute(params!["album", scrobble.artist, album, ts])?;
+    }
+
+    Ok(())
+}
+
+/// Rebuild `first_listens` from the full scrobble history, archive included
+pub fn rebuild_first_listens(pool: &DbPool) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute_batch(&format!("BEGIN; {} COMMIT;", REBUILD_FIRST_LISTENS_SQL))?;
+    Ok(())
+}
+
+const REBUILD_FIRST_LISTENS_SQL: &str = "
+    DELETE FROM first_listens;
+    WITH history AS (
+        SELECT artist, album, track, timestamp FROM scrobbles
+        UNION ALL
+        SELECT artist, album, track, timestamp FROM scrobbles_archive
+    )
+    INSERT INTO first_listens (entity_type, artist, name, first_timestamp)
+        SELECT 'artist', artist, '', MIN(timestamp) FROM history GROUP BY artist
+        UNION ALL
+        SELECT 'track', artist, track, MIN(timestamp) FROM history GROUP BY artist, track
+        UNION ALL
+        SELECT 'album', artist, album, MIN(timestamp) FROM history
+        WHERE album IS NOT NULL GROUP BY artist, album;";
+
+/// Artists first heard strictly before `before`
+pub fn get_artists_first_heard_before(
+    pool: &DbPool,
+    before: DateTime<Utc>,
+) -> Result<std::collections::HashSet<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist FROM first_listens
+         WHERE entity_type = 'artist' AND first_timestamp < ?1",
+    )?;
+    let artists = stmt
+        .query_map(params![before.timestamp()], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(artists)
+}
+
+/// Distinct artists, albums and tracks ever scrobbled, from the first listens
+/// index
+pub fn query_library_counts(conn: &Connection) -> Result<(i64, i64, i64)> {
+    let mut stmt = conn
+        .prepare_cached("SELECT entity_type, COUNT(*) FROM first_listens GROUP BY entity_type")?;
+    let mut counts = (0, 0, 0);
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    })?;
+    for row in rows {
+        match row? {
+            (kind, count) if kind == "artist" => counts.0 = count,
+            (kind, count) if kind == "album" => counts.1 = count,
+            (kind, count) if kind == "track" => counts.2 = count,
+            _ => {}
+        }
+    }
+    Ok(counts)
+}
+
+/// Number of artists first heard at or after `since`
+pub fn query_new_artists_count(conn: &Connection, since: DateTime<Utc>) -> Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM first_listens
+         WHERE entity_type = 'artist' AND first_timestamp >= ?1",
+        params![since.timestamp()],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// `(artist, track)` pairs first heard strictly before `before`
+pub fn get_tracks_first_heard_before(
+    pool: &DbPool,
+    before: DateTime<Utc>,
+) -> Result<std::collections::HashSet<(String, String)>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist, name FROM first_listens
+         WHERE entity_type = 'track' AND first_timestamp < ?1",
+    )?;
+    let tracks = stmt
+        .query_map(params![before.timestamp()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(tracks)
+}
+
+/// Map a row selected as `id, artist, album, track, timestamp, source,
+/// source_id, ms_played, skipped, raw_metadata, media_type, source_metadata,
+/// album_artist` to a scrobble
+fn row_to_scrobble(row: &rusqlite::Row) -> rusqlite::Result<Scrobble> {
+    let timestamp_value: i64 = row.get(4)?;
+    let timestamp = DateTime::from_timestamp(timestamp_value, 0).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid timestamp {} in database for scrobble id {:?}, using current time",
+            timestamp_value,
+            row.get::<_, i64>(0).ok()
+        );
+        Utc::now()
+    });
+    Ok(Scrobble {
+        id: Some(row.get(0)?),
+        artist: row.get(1)?,
+        album: row.get(2)?,
+        track: row.get(3)?,
+        timestamp,
+        source: row.get(5)?,
+        source_id: row.get(6)?,
+        ms_played: row.get(7)?,
+        skipped: row.get(8)?,
+        raw_metadata: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|raw| serde_json::from_str(&raw).ok()),
+        media_type: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+        source_metadata: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|raw| serde_json::from_str(&raw).ok()),
+        album_artist: row.get(12)?,
+    })
+}
+
+pub fn get_scrobbles(
+    pool: &DbPool,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<Scrobble>> {
+    let conn = pool.get()?;
+    let limit = limit.unwrap_or(100);
+    let offset = offset.unwrap_or(0);
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         ORDER BY timestamp DESC
+         LIMIT ?1 OFFSET ?2",
+    )?;
+
+    let scrobbles = stmt
+        .query_map(params![limit, offset], row_to_scrobble)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(scrobbles)
+}
+
+pub fn get_scrobble(pool: &DbPool, id: i64) -> Result<Option<Scrobble>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles WHERE id = ?1",
+    )?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_scrobble(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Delete one scrobble with its notes, keeping first listens in step.
+/// Returns false when there is no such scrobble
+pub fn delete_scrobble(pool: &DbPool, id: i64) -> Result<bool> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let scrobble = {
+        let mut stmt = tx.prepare_cached(
+            "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                    media_type, source_metadata, album_artist
+             FROM scrobbles WHERE id = ?1",
+        )?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => row_to_scrobble(row)?,
+            None => return Ok(false),
+        }
+    };
+
+    tx.execute("DELETE FROM notes WHERE scrobble_id = ?1", params![id])?;
+    tx.execute("DELETE FROM scrobbles WHERE id = ?1", params![id])?;
+    refresh_first_listens(&tx, &scrobble)?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+/// Recompute the first listens a scrobble contributed to, after it was removed
+/// or renamed
+fn refresh_first_listens(conn: &Connection, scrobble: &Scrobble) -> Result<()> {
+    // ?1 is the artist and ?3 the entity name
+    let mut entities = vec![
+        ("artist", String::new(), "artist = ?1"),
+        (
+            "track",
+            scrobble.track.clone(),
+            "artist = ?1 AND track = ?3",
+        ),
+    ];
+    if let Some(album) = &scrobble.album {
+        entities.push(("album", album.clone(), "artist = ?1 AND album = ?3"));
+    }
+
+    for (entity_type, name, condition) in entities {
+        conn.execute(
+            "DELETE FROM first_listens WHERE artist = ?1 AND entity_type = ?2 AND name = ?3",
+            params![scrobble.artist, entity_type, name],
+        )?;
+        conn.execute(
+            &format!(
+                "INSERT INTO first_listens (entity_type, artist, name, first_timestamp)
+                 SELECT ?2, ?1, ?3, MIN(timestamp) FROM (
+                    SELECT artist, album, track, timestamp FROM scrobbles
+                    UNION ALL
+                    SELECT artist, album, track, timestamp FROM scrobbles_archive
+                 )
+                 WHERE {}
+                 HAVING COUNT(*) > 0",
+                condition
+            ),
+            params![scrobble.artist, entity_type, name],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Every scrobble from `start` to `end` inclusive, whatever its media type,
+/// newest first
+pub fn get_scrobbles_between(
+    pool: &DbPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Scrobble>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         WHERE timestamp >= ?1 AND timestamp <= ?2
+         ORDER BY timestamp DESC
+         LIMIT ?3 OFFSET ?4",
+    )?;
+    let scrobbles = stmt
+        .query_map(
+            params![start.timestamp(), end.timestamp(), limit, offset],
+            row_to_scrobble,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(scrobbles)
+}
+
+/// Scrobbles sharing their exact second with at least one other, grouped by
+/// timestamp, for the `limit` most recent such seconds
+pub fn get_timestamp_collisions(pool: &DbPool, limit: i64) -> Result<Vec<Vec<Scrobble>>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         WHERE timestamp IN (
+             SELECT timestamp FROM scrobbles
+             GROUP BY timestamp HAVING COUNT(*) > 1
+             ORDER BY timestamp DESC LIMIT ?1
+         )
+         ORDER BY timestamp DESC, id",
+    )?;
+    let scrobbles = stmt
+        .query_map(params![limit], row_to_scrobble)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups: Vec<Vec<Scrobble>> = Vec::new();
+    for scrobble in scrobbles {
+        match groups.last_mut() {
+            Some(group) if group[0].timestamp == scrobble.timestamp => group.push(scrobble),
+            _ => groups.push(vec![scrobble]),
+        }
+    }
+    Ok(groups)
+}
+
+/// `(hour start, count)` for each clock hour holding more than `threshold`
+/// scrobbles, oldest first
+pub fn get_hours_over(pool: &DbPool, threshold: i64) -> Result<Vec<(DateTime<Utc>, i64)>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT timestamp / 3600 AS hour, COUNT(*) FROM scrobbles
+         GROUP BY hour HAVING COUNT(*) > ?1
+         ORDER BY hour",
+    )?;
+    let hours = stmt
+        .query_map(params![threshold], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hours
+        .into_iter()
+        .filter_map(|(hour, count)| Some((DateTime::from_timestamp(hour * 3600, 0)?, count)))
+        .collect())
+}
+
+/// Scrobbles stamped after `after`, latest first
+pub fn get_scrobbles_after(
+    pool: &DbPool,
+    after: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Scrobble>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         WHERE timestamp > ?1
+         ORDER BY timestamp DESC
+         LIMIT ?2",
+    )?;
+    let scrobbles = stmt
+        .query_map(params![after.timestamp(), limit], row_to_scrobble)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(scrobbles)
+}
+
+/// A track with how often and how recently it was played
+#[derive(Debug, Clone)]
+pub struct TrackPlays {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub plays: i64,
+    pub last_played: DateTime<Utc>,
+}
+
+/// Tracks played at least `min_plays` times whose last play was before
+/// `played_before`, most played first
+pub fn get_neglected_tracks(
+    pool: &DbPool,
+    min_plays: i64,
+    played_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<TrackPlays>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist, track, MAX(album), COUNT(*) as plays, MAX(timestamp) as last_played
+         FROM scrobbles
+         WHERE media_type = 'music' AND sleep_flagged = 0
+         GROUP BY artist, track
+         HAVING plays >= ?1 AND last_played < ?2
+         ORDER BY plays DESC, last_played ASC, artist, track
+         LIMIT ?3",
+    )?;
+    let tracks = stmt
+        .query_map(
+            params![min_plays, played_before.timestamp(), limit],
+            |row| {
+                Ok(TrackPlays {
+                    artist: row.get(0)?,
+                    track: row.get(1)?,
+                    album: row.get(2)?,
+                    plays: row.get(3)?,
+                    last_played: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tracks)
+}
+
+pub fn get_scrobbles_in_range(
+    pool: &DbPool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> Result<Vec<Scrobble>> {
+    let mut scrobbles = Vec::new();
+    for_each_scrobble_in_range(pool, start_date, end_date, |scrobble| {
+        scrobbles.push(scrobble)
+    })?;
+    Ok(scrobbles)
+}
+
+/// Call `f` with each music scrobble between `start_date` and `end_date`,
+/// oldest first, as rows are read from a single statement, so reports over
+/// the whole history don't hold it all in memory. The connection stays
+/// checked out until the last row
+pub fn for_each_scrobble_in_range(
+    pool: &DbPool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    mut f: impl FnMut(Scrobble),
+) -> Result<()> {
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0
+         ORDER BY timestamp ASC",
+    )?;
+
+    let rows = stmt.query_map(
+        params![start_date.timestamp(), end_date.timestamp()],
+        row_to_scrobble,
+    )?;
+    for scrobble in rows {
+        f(scrobble?);
+    }
+
+    Ok(())
+}
+
+/// Call `f` with each track played at least `min_plays` times and the
+/// timestamps of its music scrobbles, oldest first. Tracks are read one at a
+/// time, in artist and title order
+pub fn for_each_track_history(
+    pool: &DbPool,
+    min_plays: i64,
+    mut f: impl FnMut(&str, &str, &[DateTime<Utc>]),
+) -> Result<()> {
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist, track, timestamp FROM scrobbles
+         WHERE media_type = 'music' AND sleep_flagged = 0
+           AND (artist, track) IN (
+               SELECT artist, track FROM scrobbles
+               WHERE media_type = 'music' AND sleep_flagged = 0
+               GROUP BY artist, track HAVING COUNT(*) >= ?1
+           )
+         ORDER BY artist, track, timestamp",
+    )?;
+    let mut rows = stmt.query(params![min_plays])?;
+
+    let mut current: Option<(String, String)> = None;
+    let mut timestamps = Vec::new();
+    while let Some(row) = rows.next()? {
+        let artist: String = row.get(0)?;
+        let track: String = row.get(1)?;
+        let Some(timestamp) = DateTime::from_timestamp(row.get(2)?, 0) else {
+            continue;
+        };
+
+        if current
+            .as_ref()
+            .is_some_and(|(a, t)| *a != artist || *t != track)
+        {
+            if let Some((a, t)) = current.take() {
+                f(&a, &t, &timestamps);
+            }
+            timestamps.clear();
+        }
+        if current.is_none() {
+            current = Some((artist, track));
+        }
+        timestamps.push(timestamp);
+    }
+    if let Some((artist, track)) = current {
+        f(&artist, &track, &timestamps);
+    }
+
+    Ok(())
+}
+
+/// Optional restrictions applied in SQL when fetching scrobbles
+#[derive(Debug, Clone, Default)]
+pub struct ScrobbleFilter {
+    /// Exact artist name
+    pub artist: Option<String>,
+    /// Artist tag from enrichment, matched case-insensitively
+    pub genre: Option<String>,
+}
+
+impl ScrobbleFilter {
+    pub fn is_empty(&self) -> bool {
+        self.artist.is_none() && self.genre.is_none()
+    }
+}
+
+/// Scrobbles matching `filter`, optionally within a date range, oldest first
+pub fn get_scrobbles_filtered(
+    pool: &DbPool,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+    filter: &ScrobbleFilter,
+) -> Result<Vec<Scrobble>> {
+    let conn = pool.get()?;
+
+    let mut conditions = vec!["media_type = 'music' AND sleep_flagged = 0"];
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let (Some(start), Some(end)) = (start_date, end_date) {
+        conditions.push("timestamp >= ? AND timestamp <= ?");
+        values.push(start.timestamp().into());
+        values.push(end.timestamp().into());
+    }
+    if let Some(artist) = &filter.artist {
+        conditions.push("artist = ?");
+        values.push(artist.clone().into());
+    }
+    if let Some(genre) = &filter.genre {
+        conditions.push("artist IN (SELECT artist FROM artist_tags WHERE tag = ? COLLATE NOCASE)");
+        values.push(genre.clone().into());
+    }
+
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+
+    // The SQL changes with the arguments, so caching it would only churn the cache
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         {}
+         ORDER BY timestamp ASC",
+        where_clause
+    ))?;
+
+    let scrobbles = stmt
+        .query_map(params_from_iter(values), row_to_scrobble)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(scrobbles)
+}
+
+/// `(artist, plays, skips, avg_ms_played)`
+pub type ArtistPlaybackStats = (String, i64, i64, Option<f64>);
+
+/// Per-artist playback stats over scrobbles that carry playback metadata
+pub fn get_playback_stats_by_artist(
+    pool: &DbPool,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<ArtistPlaybackStats>> {
+    let conn = pool.get()?;
+
+    let map_row = |row: &rusqlite::Row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?));
+
+    let stats = if let (Some(start), Some(end)) = (start_date, end_date) {
+        let mut stmt = conn.prepare_cached(
+            "SELECT artist, COUNT(*), SUM(skipped), AVG(ms_played) FROM scrobbles
+             WHERE skipped IS NOT NULL AND timestamp >= ?1 AND timestamp <= ?2
+             GROUP BY artist",
+        )?;
+        stmt.query_map(params![start.timestamp(), end.timestamp()], map_row)?
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        let mut stmt = conn.prepare_cached(
+            "SELECT artist, COUNT(*), SUM(skipped), AVG(ms_played) FROM scrobbles
+             WHERE skipped IS NOT NULL
+             GROUP BY artist",
+        )?;
+        stmt.query_map([], map_row)?
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(stats)
+}
+
+/// `(timestamp, artist, is_first_scrobble_of_artist)` for every scrobble in
+/// `[start_date, end_date)`, archived ones included
+pub fn get_scrobbles_with_discoveries(
+    pool: &DbPool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, String, bool)>> {
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT s.timestamp, s.artist, s.timestamp = f.first_timestamp
+         FROM (
+            SELECT id, artist, timestamp FROM scrobbles
+            WHERE timestamp >= ?1 AND timestamp < ?2
+            UNION ALL
+            SELECT id, artist, timestamp FROM scrobbles_archive
+            WHERE timestamp >= ?1 AND timestamp < ?2
+         ) s
+         JOIN first_listens f
+           ON f.entity_type = 'artist' AND f.artist = s.artist AND f.name = ''
+         ORDER BY s.timestamp ASC, s.id ASC",
+    )?;
+
+    let mut seen_first = std::collections::HashSet::new();
+    let rows = stmt
+        .query_map(
+            params![start_date.timestamp(), end_date.timestamp()],
+            |row| {
+                let ts: i64 = row.get(0)?;
+                Ok((
+                    DateTime::from_timestamp(ts, 0).unwrap_or_default(),
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        // Several scrobbles can share the first timestamp; count the artist once
+        .map(|(ts, artist, is_first)| {
+            let is_first = is_first && seen_first.insert(artist.clone());
+            (ts, artist, is_first)
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Highest scrobble row id, or 0 for an empty database
+pub fn get_max_scrobble_id(pool: &DbPool) -> Result<i64> {
+    let conn = pool.get()?;
+    let id: i64 = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM scrobbles", [], |row| {
+        row.get(0)
+    })?;
+    Ok(id)
+}
+
+/// Scrobbles inserted after the given row id, in insertion order
+pub fn get_scrobbles_after_id(pool: &DbPool, after_id: i64, limit: i64) -> Result<Vec<Scrobble>> {
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, track, timestamp, source, source_id, ms_played, skipped, raw_metadata,
+                media_type, source_metadata, album_artist
+         FROM scrobbles
+         WHERE id > ?1
+         ORDER BY id ASC
+         LIMIT ?2",
+    )?;
+
+    let scrobbles = stmt
+        .query_map(params![after_id, limit], row_to_scrobble)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(scrobbles)
+}
+
+/// Timestamp of the oldest scrobble, if any
+pub fn get_first_scrobble_timestamp(pool: &DbPool) -> Result<Option<DateTime<Utc>>> {
+    let conn = pool.get()?;
+    let first: Option<i64> =
+        conn.query_row("SELECT MIN(timestamp) FROM scrobbles", [], |row| row.get(0))?;
+    Ok(first.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+/// Timestamp of the oldest listen, archived scrobbles included
+pub fn get_first_listen_timestamp(pool: &DbPool) -> Result<Option<DateTime<Utc>>> {
+    let conn = pool.get()?;
+    let first: Option<i64> = conn.query_row(
+        "SELECT MIN(first) FROM (
+            SELECT MIN(timestamp) AS first FROM scrobbles
+            UNION ALL
+            SELECT MIN(timestamp) FROM scrobbles_archive
+         )",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(first.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+pub fn get_last_scrobble_timestamp(pool: &DbPool) -> Result<Option<DateTime<Utc>>> {
+    let conn = pool.get()?;
+    let ts: Option<i64> =
+        conn.query_row("SELECT MAX(timestamp) FROM scrobbles", [], |row| row.get(0))?;
+    Ok(ts.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+pub fn get_scrobbles_count(pool: &DbPool) -> Result<i64> {
+    let conn = pool.get()?;
+    query_scrobbles_count(&conn)
+}
+
+/// Every scrobble ever stored, archived ones included
+pub fn query_scrobbles_count(conn: &Connection) -> Result<i64> {
+    let count: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM scrobbles) + (SELECT COUNT(*) FROM scrobbles_archive)",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+pub fn get_scrobbles_count_in_range(
+    pool: &DbPool,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<i64> {
+    let conn = pool.get()?;
+    query_scrobbles_count_in_range(&conn, start_date, end_date)
+}
+
+pub fn query_scrobbles_count_in_range(
+    conn: &Connection,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<i64> {
+    if let (Some(start), Some(end)) = (start_date, end_date) {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM scrobbles
+             WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0",
+            params![start.timestamp(), end.timestamp()],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    } else {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM scrobbles WHERE media_type = 'music' AND sleep_flagged = 0",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+}
+
+pub fn get_top_artists(
+    pool: &DbPool,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, i64)>> {
+    let conn = pool.get()?;
+    query_top_artists(&conn, limit, start_date, end_date)
+}
+
+pub fn query_top_artists(
+    conn: &Connection,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, i64)>> {
+    query_top_credited_artists(conn, ArtistCredit::Full, limit, start_date, end_date)
+}
+
+/// Top artists, crediting plays as `credit` says. Names missing from
+/// `artist_credits` count as a single artist; see `credits::refresh`
+pub fn query_top_credited_artists(
+    conn: &Connection,
+    credit: ArtistCredit,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, i64)>> {
+    if credit != ArtistCredit::Full {
+        let (start, end) = range_bounds(start_date, end_date);
+        let sql = format!(
+            "SELECT artist, COUNT(*) as count FROM {}
+             WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0
+             GROUP BY artist ORDER BY count DESC, artist LIMIT ?3",
+            credited_scrobbles(credit)
+        );
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let artists = stmt.query_map(params![start, end, limit], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        return Ok(artists.collect::<Result<Vec<_>, _>>()?);
+    }
+
+    let (start, end) = range_bounds(start_date, end_date);
+    let mut stmt = conn.prepare_cached(TOP_ARTISTS_SQL)?;
+    let artists = stmt.query_map(params![start, end, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    Ok(artists.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub(crate) const TOP_ARTISTS_SQL: &str = "
+    SELECT artist, COUNT(*) as count FROM scrobbles
+    WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0
+    GROUP BY artist ORDER BY count DESC LIMIT ?3";
+
+pub(crate) const TOP_TRACKS_SQL: &str = "
+    SELECT artist, track, COUNT(*) as count FROM scrobbles
+    WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0
+    GROUP BY artist, track ORDER BY count DESC LIMIT ?3";
+
+pub(crate) const TOP_ALBUMS_SQL: &str = "
+    SELECT COALESCE(album_artist, artist) AS album_artist, album, COUNT(*) as count
+    FROM scrobbles
+    WHERE album IS NOT NULL AND timestamp >= ?1 AND timestamp <= ?2
+      AND media_type = 'music' AND sleep_flagged = 0
+    GROUP BY 1, album ORDER BY count DESC LIMIT ?3";
+
+/// Timestamps bounding an optional range, open ends spanning all of history
+fn range_bounds(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> (i64, i64) {
+    (
+        start.map(|d| d.timestamp()).unwrap_or(i64::MIN),
+        end.map(|d| d.timestamp()).unwrap_or(i64::MAX),
+    )
+}
+
+// Scrobbles with their artist field replaced by each artist `credit` counts,
+// for queries that group by artist
+fn credited_scrobbles(credit: ArtistCredit) -> &'static str {
+    match credit {
+        ArtistCredit::Full => "scrobbles",
+        ArtistCredit::Primary => {
+            "(SELECT COALESCE(c.name, s.artist) AS artist, s.timestamp, s.media_type, s.sleep_flagged
+              FROM scrobbles s
+              LEFT JOIN artist_credits c ON c.artist = s.artist AND c.position = 0)"
+        }
+        ArtistCredit::All => {
+            "(SELECT COALESCE(c.name, s.artist) AS artist, s.timestamp, s.media_type, s.sleep_flagged
+              FROM scrobbles s
+              LEFT JOIN artist_credits c ON c.artist = s.artist)"
+        }
+    }
+}
+
+/// Artist names with no stored credits yet, each with the source metadata of
+/// one of its scrobbles when any has some
+pub fn get_uncredited_artists(pool: &DbPool) -> Result<Vec<(String, Option<String>)>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT a.artist,
+                (SELECT source_metadata FROM scrobbles m
+                 WHERE m.artist = a.artist AND m.source_metadata IS NOT NULL LIMIT 1)
+         FROM (SELECT DISTINCT artist FROM scrobbles) a
+         WHERE NOT EXISTS (SELECT 1 FROM artist_credits c WHERE c.artist = a.artist)",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Store the artists each artist field names, primary first. Returns how many
+/// artist fields were saved
+pub fn save_artist_credits(pool: &DbPool, credits: &[(String, Vec<String>)]) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    {
+        let mut delete = tx.prepare_cached("DELETE FROM artist_credits WHERE artist = ?1")?;
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO artist_credits (artist, position, name) VALUES (?1, ?2, ?3)",
+        )?;
+        for (artist, names) in credits {
+            delete.execute(params![artist])?;
+            for (position, name) in names.iter().enumerate() {
+                insert.execute(params![artist, position as i64, name])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(credits.len())
+}
+
+/// Forget every split, e.g. after the separators changed
+pub fn clear_artist_credits(pool: &DbPool) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM artist_credits", [])?;
+    Ok(())
+}
+
+pub fn get_top_tracks(
+    pool: &DbPool,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, String, i64)>> {
+    let conn = pool.get()?;
+    query_top_tracks(&conn, limit, start_date, end_date)
+}
+
+pub fn query_top_tracks(
+    conn: &Connection,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, String, i64)>> {
+    let (start, end) = range_bounds(start_date, end_date);
+    let mut stmt = conn.prepare_cached(TOP_TRACKS_SQL)?;
+    let tracks = stmt.query_map(params![start, end, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    Ok(tracks.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub fn get_top_albums(
+    pool: &DbPool,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, String, i64)>> {
+    let conn = pool.get()?;
+    query_top_albums(&conn, limit, start_date, end_date)
+}
+
+pub fn query_top_albums(
+    conn: &Connection,
+    limit: i64,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<(String, String, i64)>> {
+    let (start, end) = range_bounds(start_date, end_date);
+    let mut stmt = conn.prepare_cached(TOP_ALBUMS_SQL)?;
+    let albums = stmt.query_map(params![start, end, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    Ok(albums.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Play count and rank of an entry over a range, 1 being the most played.
+/// Entries with the same count share a rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Standing {
+    pub count: i64,
+    pub rank: i64,
+}
+
+/// Standings of `artists` between `start` and `end`, credited as `credit`
+/// says. Artists not played in the range are left out
+pub fn query_artist_standings(
+    conn: &Connection,
+    credit: ArtistCredit,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    artists: &[String],
+) -> Result<HashMap<String, Standing>> {
+    let keys: Vec<Vec<&str>> = artists.iter().map(|a| vec![a.as_str()]).collect();
+    let source = credited_scrobbles(credit);
+    Ok(
+        query_standings(conn, source, &["artist"], "", start, end, &keys)?
+            .into_iter()
+            .map(|(mut key, standing)| (key.remove(0), standing))
+            .collect(),
+    )
+}
+
+/// Standings of `(artist, track)` pairs between `start` and `end`
+pub fn query_track_standings(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    tracks: &[(String, String)],
+) -> Result<HashMap<(String, String), Standing>> {
+    let keys: Vec<Vec<&str>> = tracks.iter().map(|(a, t)| vec![a.as_str(), t]).collect();
+    Ok(query_standings(
+        conn,
+        "scrobbles",
+        &["artist", "track"],
+        "",
+        start,
+        end,
+        &keys,
+    )?
+    .into_iter()
+    .map(|(mut key, standing)| ((key.remove(0), key.remove(0)), standing))
+    .collect())
+}
+
+/// Standings of `(album artist, album)` pairs between `start` and `end`
+pub fn query_album_standings(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    albums: &[(String, String)],
+) -> Result<HashMap<(String, String), Standing>> {
+    let keys: Vec<Vec<&str>> = albums.iter().map(|(a, t)| vec![a.as_str(), t]).collect();
+    Ok(query_standings(
+        conn,
+        "(SELECT COALESCE(album_artist, artist) AS artist, album, timestamp, media_type,
+                 sleep_flagged
+          FROM scrobbles)",
+        &["artist", "album"],
+        "AND album IS NOT NULL",
+        start,
+        end,
+        &keys,
+    )?
+    .into_iter()
+    .map(|(mut key, standing)| ((key.remove(0), key.remove(0)), standing))
+    .collect())
+}
+
+// Ranks every entry of `source` grouped by `columns` over the range, then
+// keeps `keys`, so ranks match a full top list of that range
+fn query_standings(
+    conn: &Connection,
+    source: &str,
+    columns: &[&str],
+    extra_filter: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    keys: &[Vec<&str>],
+) -> Result<HashMap<Vec<String>, Standing>> {
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let columns_list = columns.join(", ");
+    let row_placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+    let sql = format!(
+        "SELECT {columns}, count, position FROM (
+             SELECT {columns}, COUNT(*) AS count,
+                    RANK() OVER (ORDER BY COUNT(*) DESC) AS position
+             FROM {source}
+             WHERE timestamp >= ? AND timestamp <= ?
+               AND media_type = 'music' AND sleep_flagged = 0 {filter}
+             GROUP BY {columns}
+         ) WHERE ({columns}) IN (VALUES {rows})",
+        columns = columns_list,
+        filter = extra_filter,
+        rows = vec![row_placeholders; keys.len()].join(", "),
+    );
+
+    let mut values: Vec<rusqlite::types::Value> =
+        vec![start.timestamp().into(), end.timestamp().into()];
+    for key in keys {
+        values.extend(key.iter().map(|part| part.to_string().into()));
+    }
+
+    // The SQL changes with the arguments, so caching it would only churn the cache
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        let mut key = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            key.push(row.get::<_, String>(i)?);
+        }
+        Ok((
+            key,
+            Standing {
+                count: row.get(columns.len())?,
+                rank: row.get(columns.len() + 1)?,
+            },
+        ))
+    })?;
+    Ok(rows.collect::<Result<HashMap<_, _>, _>>()?)
+}
+
+/// Bucket size for time-series aggregations such as the pulse chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBucket {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl std::str::FromStr for TimeBucket {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hour" => Ok(TimeBucket::Hour),
+            "day" => Ok(TimeBucket::Day),
+            "week" => Ok(TimeBucket::Week),
+            "month" => Ok(TimeBucket::Month),
+            _ => Err(anyhow::anyhow!("Unknown granularity: {}", s)),
+        }
+    }
+}
+
+impl TimeBucket {
+    /// Start of the bucket containing `dt`
+    pub fn floor(&self, dt: NaiveDateTime) -> NaiveDateTime {
+        let date = dt.date();
+        match self {
+            TimeBucket::Hour => date.and_time(NaiveTime::MIN) + Duration::hours(dt.hour() as i64),
+            TimeBucket::Day => date.and_time(NaiveTime::MIN),
+            TimeBucket::Week => IsoWeek::of(date).monday().and_time(NaiveTime::MIN),
+            TimeBucket::Month => date.with_day(1).unwrap_or(date).and_time(NaiveTime::MIN),
+        }
+    }
+
+    /// Start of the bucket following the one starting at `dt`
+    fn next(&self, dt: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            TimeBucket::Hour => dt + Duration::hours(1),
+            TimeBucket::Day => dt + Duration::days(1),
+            TimeBucket::Week => dt + Duration::weeks(1),
+            TimeBucket::Month => dt
+                .checked_add_months(Months::new(1))
+                .unwrap_or(NaiveDateTime::MAX),
+        }
+    }
+
+    /// Label for a bucket start. Weeks use the reports' ISO labels, e.g. "2025-W01"
+    pub fn label(&self, dt: NaiveDateTime) -> String {
+        match self {
+            TimeBucket::Hour => dt.format("%Y-%m-%dT%H:00").to_string(),
+            TimeBucket::Day => dt.format("%Y-%m-%d").to_string(),
+            TimeBucket::Week => IsoWeek::of(dt.date()).to_string(),
+            TimeBucket::Month => dt.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+// Width of the UTC slots aggregated in SQL before local bucketing. Every UTC
+// offset in use is a multiple of 15 minutes, so a slot never straddles two
+// local hours.
+const BUCKET_SLOT_SECONDS: i64 = 900;
+
+/// Local days in `timezone` with at least one scrobble, from `since` on or
+/// over the whole history
+pub fn query_active_days(
+    conn: &Connection,
+    since: Option<DateTime<Utc>>,
+    timezone: Tz,
+) -> Result<std::collections::BTreeSet<NaiveDate>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT DISTINCT timestamp / ?2 FROM scrobbles
+         WHERE timestamp >= ?1 AND media_type = 'music' AND sleep_flagged = 0",
+    )?;
+    let slots = stmt
+        .query_map(
+            params![
+                since.map(|s| s.timestamp()).unwrap_or(i64::MIN),
+                BUCKET_SLOT_SECONDS
+            ],
+            |row| row.get::<_, i64>(0),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(slots
+        .into_iter()
+        .filter_map(|slot| DateTime::from_timestamp(slot * BUCKET_SLOT_SECONDS, 0))
+        .map(|start| start.with_timezone(&timezone).date_naive())
+        .collect())
+}
+
+/// First listen of each of `artists` from the first listens index, archived
+/// scrobbles included
+pub fn query_artist_first_listens(
+    conn: &Connection,
+    artists: &[String],
+) -> Result<HashMap<String, DateTime<Utc>>> {
+    if artists.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let sql = format!(
+        "SELECT artist, first_timestamp FROM first_listens
+         WHERE entity_type = 'artist' AND name = '' AND artist IN ({})",
+        vec!["?"; artists.len()].join(", ")
+    );
+    // The SQL changes with the arguments, so caching it would only churn the cache
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(artists), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    })?;
+
+    let mut first_listens = HashMap::new();
+    for row in rows {
+        let (artist, timestamp) = row?;
+        if let Some(first) = DateTime::from_timestamp(timestamp, 0) {
+            first_listens.insert(artist, first);
+        }
+    }
+    Ok(first_listens)
+}
+
+/// When an artist was played, from their music scrobbles
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtistActivity {
+    /// First day of each local month with a scrobble
+    pub months: std::collections::BTreeSet<NaiveDate>,
+    pub last_listen: DateTime<Utc>,
+}
+
+/// Local months in `timezone` with music scrobbles of each of `artists`, and
+/// their latest scrobble
+pub fn query_artist_activity(
+    conn: &Connection,
+    artists: &[String],
+    timezone: Tz,
+) -> Result<HashMap<String, ArtistActivity>> {
+    if artists.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let sql = format!(
+        "SELECT artist, timestamp / ?1, MAX(timestamp) FROM scrobbles
+         WHERE artist IN ({}) AND media_type = 'music' AND sleep_flagged = 0
+         GROUP BY artist, timestamp / ?1",
+        vec!["?"; artists.len()].join(", ")
+    );
+    let mut values: Vec<rusqlite::types::Value> = vec![BUCKET_SLOT_SECONDS.into()];
+    values.extend(artists.iter().map(|artist| artist.clone().into()));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+
+    let mut activity: HashMap<String, ArtistActivity> = HashMap::new();
+    for row in rows {
+        let (artist, slot, latest) = row?;
+        let (Some(slot_start), Some(latest)) = (
+            DateTime::from_timestamp(slot * BUCKET_SLOT_SECONDS, 0),
+            DateTime::from_timestamp(latest, 0),
+        ) else {
+            continue;
+        };
+        let date = slot_start.with_timezone(&timezone).date_naive();
+        let entry = activity.entry(artist).or_insert_with(|| ArtistActivity {
+            months: Default::default(),
+            last_listen: latest,
+        });
+        entry.months.insert(date.with_day(1).unwrap_or(date));
+        entry.last_listen = entry.last_listen.max(latest);
+    }
+    Ok(activity)
+}
+
+/// When the first artist was first heard, archived scrobbles included
+pub fn query_first_listen_timestamp(conn: &Connection) -> Result<Option<DateTime<Utc>>> {
+    let first: Option<i64> = conn.query_row(
+        "SELECT MIN(first_timestamp) FROM first_listens WHERE entity_type = 'artist'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(first.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+/// Call `f` with the start of each 15-minute UTC slot, an artist and their
+/// music scrobbles in it, for rolling up into local periods without loading
+/// individual scrobbles
+pub fn for_each_artist_slot(
+    pool: &DbPool,
+    mut f: impl FnMut(DateTime<Utc>, &str, i64),
+) -> Result<()> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT timestamp / ?1 as slot, artist, COUNT(*) FROM scrobbles
+         WHERE media_type = 'music' AND sleep_flagged = 0
+         GROUP BY slot, artist",
+    )?;
+    let mut rows = stmt.query(params![BUCKET_SLOT_SECONDS])?;
+    while let Some(row) = rows.next()? {
+        let artist: String = row.get(1)?;
+        if let Some(slot_start) =
+            DateTime::from_timestamp(row.get::<_, i64>(0)? * BUCKET_SLOT_SECONDS, 0)
+        {
+            f(slot_start, &artist, row.get(2)?);
+        }
+    }
+    Ok(())
+}
+
+/// Count scrobbles per local time bucket in `timezone`, including zero-count
+/// buckets between the bounds. Without an explicit range, the bounds are the
+/// first and last scrobble.
+///
+/// SQLite has no timezone database, so counts are aggregated into 15-minute UTC
+/// slots in SQL and rolled up into local buckets here, which keeps DST
+/// transitions correct without loading individual scrobbles.
+pub fn get_scrobbles_per_bucket(
+    pool: &DbPool,
+    bucket: TimeBucket,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+    timezone: Tz,
+) -> Result<Vec<(String, i64)>> {
+    let conn = pool.get()?;
+
+    let (start, end) = match (start_date, end_date) {
+        (Some(start), Some(end)) => (start.timestamp(), end.timestamp()),
+        _ => {
+            let bounds: (Option<i64>, Option<i64>) = conn.query_row(
+                "SELECT MIN(timestamp), MAX(timestamp) FROM scrobbles WHERE media_type = 'music' AND sleep_flagged = 0",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            match bounds {
+                (Some(min), Some(max)) => (min, max),
+                _ => return Ok(Vec::new()),
+            }
+        }
+    };
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT timestamp / ?3 as slot, COUNT(*) as count
+         FROM scrobbles
+         WHERE timestamp >= ?1 AND timestamp <= ?2 AND media_type = 'music' AND sleep_flagged = 0
+         GROUP BY slot",
+    )?;
+    let slots = stmt
+        .query_map(params![start, end, BUCKET_SLOT_SECONDS], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut counts: HashMap<NaiveDateTime, i64> = HashMap::new();
+    for (slot, count) in slots {
+        if let Some(slot_start) = DateTime::from_timestamp(slot * BUCKET_SLOT_SECONDS, 0) {
+            let local = slot_start.with_timezone(&timezone).naive_local();
+            *counts.entry(bucket.floor(local)).or_insert(0) += count;
+        }
+    }
+
+    let (Some(start), Some(end)) = (
+        DateTime::from_timestamp(start, 0),
+        DateTime::from_timestamp(end, 0),
+    ) else {
+        return Ok(Vec::new());
+    };
+
+    let end = end.with_timezone(&timezone).naive_local();
+    let mut current = bucket.floor(start.with_timezone(&timezone).naive_local());
+    let mut points = Vec::new();
+    while current <= end {
+        let count = counts.get(&current).copied().unwrap_or(0);
+        points.push((bucket.label(current), count));
+        current = bucket.next(current);
+    }
+
+    Ok(points)
+}
+
+pub fn get_top_album_for_artist(pool: &DbPool, artist: &str) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT album, COUNT(*) as count FROM scrobbles
+         WHERE artist = ?1 AND album IS NOT NULL
+         GROUP BY album
+         ORDER BY count DESC
+         LIMIT 1",
+    )?;
+
+    let mut rows = stmt.query(params![artist])?;
+    if let Some(row) = rows.next()? {
+        Ok(row.get(0)?)
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_album_for_track(pool: &DbPool, artist: &str, track: &str) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT album, COUNT(*) as count FROM scrobbles
+         WHERE artist = ?1 AND track = ?2 AND album IS NOT NULL
+         GROUP BY album
+         ORDER BY count DESC
+         LIMIT 1",
+    )?;
+
+    let mut rows = stmt.query(params![artist, track])?;
+    if let Some(row) = rows.next()? {
+        Ok(row.get(0)?)
+    } else {
+        Ok(None)
+    }
+}
+
+// Helper function to safely convert database timestamps
+fn parse_timestamp_with_warning(ts: i64, field_name: &str, id: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(ts, 0).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid {} timestamp {} in sync_config id {}, using current time",
+            field_name,
+            ts,
+            id
+        );
+        Utc::now()
+    })
+}
+
+// Sync configuration database operations
+pub fn insert_sync_config(pool: &DbPool, config: &SyncConfig) -> Result<i64> {
+    let conn = pool.get()?;
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO sync_configs (source, username, api_key, token, sync_interval_minutes, enabled, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(source, username) DO UPDATE SET
+            api_key = ?3,
+            token = ?4,
+            sync_interval_minutes = ?5,
+            enabled = ?6,
+            updated_at = ?8",
+        params![
+            config.source,
+            config.username,
+            config.api_key,
+            config.token,
+            config.sync_interval_minutes,
+            if config.enabled { 1 } else { 0 },
+            now,
+            now,
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_sync_config(pool: &DbPool, id: i64) -> Result<Option<SyncConfig>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
+         FROM sync_configs WHERE id = ?1",
+    )?;
+
+    let mut rows = stmt.query(params![id])?;
+    if let Some(row) = rows.next()? {
+        let config_id: i64 = row.get(0)?;
+        let created_ts: i64 = row.get(8)?;
+        let updated_ts: i64 = row.get(9)?;
+        let last_sync_ts: Option<i64> = row.get(6)?;
+
+        Ok(Some(SyncConfig {
+            id: Some(config_id),
+            source: row.get(1)?,
+            username: row.get(2)?,
+            api_key: row.get(3)?,
+            token: row.get(4)?,
+            sync_interval_minutes: row.get(5)?,
+            last_sync_timestamp: last_sync_ts.and_then(|ts| {
+                DateTime::from_timestamp(ts, 0).or_else(|| {
+                    tracing::warn!(
+                        "Invalid last_sync_timestamp {} in sync_config id {}",
+                        ts,
+                        config_id
+                    );
+                    None
+                })
+            }),
+            enabled: row.get::<_, i32>(7)? != 0,
+            created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
+            updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn get_all_sync_configs(pool: &DbPool) -> Result<Vec<SyncConfig>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
+         FROM sync_configs ORDER BY created_at DESC",
+    )?;
+
+    let configs = stmt
+        .query_map([], |row| {
+            let config_id: i64 = row.get(0)?;
+            let created_ts: i64 = row.get(8)?;
+            let updated_ts: i64 = row.get(9)?;
+            let last_sync_ts: Option<i64> = row.get(6)?;
+
+            Ok(SyncConfig {
+                id: Some(config_id),
+                source: row.get(1)?,
+                username: row.get(2)?,
+                api_key: row.get(3)?,
+                token: row.get(4)?,
+                sync_interval_minutes: row.get(5)?,
+                last_sync_timestamp: last_sync_ts.and_then(|ts| {
+                    DateTime::from_timestamp(ts, 0).or_else(|| {
+                        tracing::warn!(
+                            "Invalid last_sync_timestamp {} in sync_config id {}",
+                            ts,
+                            config_id
+                        );
+                        None
+                    })
+                }),
+                enabled: row.get::<_, i32>(7)? != 0,
+                created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
+                updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(configs)
+}
+
+pub fn get_enabled_sync_configs(pool: &DbPool) -> Result<Vec<SyncConfig>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
+         FROM sync_configs WHERE enabled = 1 ORDER BY created_at DESC",
+    )?;
+
+    let configs = stmt
+        .query_map([], |row| {
+            let config_id: i64 = row.get(0)?;
+            let created_ts: i64 = row.get(8)?;
+            let updated_ts: i64 = row.get(9)?;
+            let last_sync_ts: Option<i64> = row.get(6)?;
+
+            Ok(SyncConfig {
+                id: Some(config_id),
+                source: row.get(1)?,
+                username: row.get(2)?,
+                api_key: row.get(3)?,
+                token: row.get(4)?,
+                sync_interval_minutes: row.get(5)?,
+                last_sync_timestamp: last_sync_ts.and_then(|ts| {
+                    DateTime::from_timestamp(ts, 0).or_else(|| {
+                        tracing::warn!(
+                            "Invalid last_sync_timestamp {} in sync_config id {}",
+                            ts,
+                            config_id
+                        );
+                        None
+                    })
+                }),
+                enabled: row.get::<_, i32>(7)? != 0,
+                created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
+                updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(configs)
+}
+
+/// Record a successful sync, which also ends any failure streak
+pub fn update_sync_timestamp(pool: &DbPool, id: i64, timestamp: DateTime<Utc>) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE sync_configs
+         SET last_sync_timestamp = ?1, updated_at = ?2, failing_since = NULL, last_error = NULL
+         WHERE id = ?3",
+        params![timestamp.timestamp(), Utc::now().timestamp(), id],
+    )?;
+    Ok(())
+}
+
+/// Pause or resume a sync config without touching its credentials; returns
+/// false when there is no config with this id
+pub fn set_sync_config_enabled(pool: &DbPool, id: i64, enabled: bool) -> Result<bool> {
+    let conn = pool.get()?;
+    let updated = conn.execute(
+        "UPDATE sync_configs SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+        params![enabled as i32, Utc::now().timestamp(), id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Record a failed sync; the streak start is kept across repeated failures
+pub fn record_sync_failure(pool: &DbPool, id: i64, error: &str, at: DateTime<Utc>) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE sync_configs SET failing_since = COALESCE(failing_since, ?1), last_error = ?2
+         WHERE id = ?3",
+        params![at.timestamp(), error, id],
+    )?;
+    Ok(())
+}
+
+/// Enabled sync config whose syncs have failed since `failing_since`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SyncFailure {
+    pub config_id: i64,
+    pub source: String,
+    pub username: String,
+    pub failing_since: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+/// Enabled sync configs failing since `before` or earlier
+pub fn get_sync_failures(pool: &DbPool, before: DateTime<Utc>) -> Result<Vec<SyncFailure>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, failing_since, last_error FROM sync_configs
+         WHERE enabled = 1 AND failing_since IS NOT NULL AND failing_since <= ?1
+         ORDER BY failing_since",
+    )?;
+    let failures = stmt
+        .query_map(params![before.timestamp()], |row| {
+            let failing_ts: i64 = row.get(3)?;
+            Ok(SyncFailure {
+                config_id: row.get(0)?,
+                source: row.get(1)?,
+                username: row.get(2)?,
+                failing_since: DateTime::from_timestamp(failing_ts, 0).unwrap_or_else(Utc::now),
+                last_error: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(failures)
+}
+
+pub fn delete_sync_config(pool: &DbPool, id: i64) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM sync_configs WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// Enrichment operations
+/// Store loved tracks as `(artist, track, loved_at)`, returning how many were new
+pub fn insert_loved_tracks(
+    pool: &DbPool,
+    loved: &[(String, String, Option<DateTime<Utc>>)],
+    source: &str,
+) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let mut inserted = 0;
+    for (artist, track, loved_at) in loved {
+        inserted += tx.execute(
+            "INSERT OR IGNORE INTO loved_tracks (artist, track, loved_at, source)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![artist, track, loved_at.map(|ts| ts.timestamp()), source],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(inserted)
+}
+
+/// Days before an artist whose tags came back empty is asked about again
+pub const EMPTY_TAGS_RETRY_DAYS: i64 = 30;
+
+/// Scrobbled artists with no tags stored yet, most played first. Artists
+/// fetched without result in the last `EMPTY_TAGS_RETRY_DAYS` are left out
+pub fn get_artists_without_tags(pool: &DbPool, now: DateTime<Utc>) -> Result<Vec<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist FROM scrobbles
+         WHERE artist NOT IN (SELECT artist FROM artist_tags)
+           AND artist NOT IN (SELECT artist FROM artist_tag_fetches WHERE fetched_at > ?1)
+         GROUP BY artist ORDER BY COUNT(*) DESC",
+    )?;
+
+    let retry_before = (now - chrono::Duration::days(EMPTY_TAGS_RETRY_DAYS)).timestamp();
+    let artists = stmt
+        .query_map(params![retry_before], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(artists)
+}
+
+/// Replace the tags of an artist with `(tag, weight)` pairs, recording the
+/// fetch even when there are none
+pub fn insert_artist_tags(
+    pool: &DbPool,
+    artist: &str,
+    tags: &[(String, i64)],
+    source: &str,
+) -> Result<()> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM artist_tags WHERE artist = ?1", params![artist])?;
+    for (tag, weight) in tags {
+        tx.execute(
+            "INSERT OR IGNORE INTO artist_tags (artist, tag, weight, source)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![artist, tag, weight, source],
+        )?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO artist_tag_fetches (artist, fetched_at) VALUES (?1, ?2)",
+        params![artist, Utc::now().timestamp()],
+    )?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+// Rating operations
+pub fn set_rating(
+    pool: &DbPool,
+    kind: RatingKind,
+    artist: &str,
+    name: &str,
+    rating: u8,
+) -> Result<()> {
+    if !(1..=5).contains(&rating) {
+        return Err(anyhow::anyhow!("Rating must be between 1 and 5"));
+    }
+
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO ratings (kind, artist, name, rating, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(kind, artist, name) DO UPDATE SET rating = ?4, updated_at = ?5",
+        params![kind.as_str(), artist, name, rating, Utc::now().timestamp()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_rating(pool: &DbPool, kind: RatingKind, artist: &str, name: &str) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM ratings WHERE kind = ?1 AND artist = ?2 AND name = ?3",
+        params![kind.as_str(), artist, name],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub fn get_rating(pool: &DbPool, kind: RatingKind, artist: &str, name: &str) -> Result<Option<u8>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT rating FROM ratings WHERE kind = ?1 AND artist = ?2 AND name = ?3",
+    )?;
+    let mut rows = stmt.query(params![kind.as_str(), artist, name])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// All ratings of one kind with the all-time play count of each rated item
+pub fn get_ratings_with_plays(pool: &DbPool, kind: RatingKind) -> Result<Vec<Rating>> {
+    let conn = pool.get()?;
+    let plays_column = match kind {
+        RatingKind::Track => "track",
+        RatingKind::Album => "album",
+    };
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT r.artist, r.name, r.rating, r.updated_at,
+                (SELECT COUNT(*) FROM scrobbles s
+                 WHERE s.artist = r.artist AND s.{} = r.name) AS plays
+         FROM ratings r
+         WHERE r.kind = ?1
+         ORDER BY r.rating DESC, plays DESC",
+        plays_column
+    ))?;
+
+    let ratings = stmt
+        .query_map(params![kind.as_str()], |row| {
+            let updated_ts: i64 = row.get(3)?;
+            Ok(Rating {
+                kind,
+                artist: row.get(0)?,
+                name: row.get(1)?,
+                rating: row.get(2)?,
+                plays: row.get(4)?,
+                updated_at: DateTime::from_timestamp(updated_ts, 0).unwrap_or_else(Utc::now),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ratings)
+}
+
+// Note operations
+const NOTE_COLUMNS: &str = "id, scrobble_id, date, body, created_at, updated_at";
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    let id: i64 = row.get(0)?;
+    let date: Option<String> = row.get(2)?;
+    let created_ts: i64 = row.get(4)?;
+    let updated_ts: i64 = row.get(5)?;
+
+    Ok(Note {
+        id: Some(id),
+        scrobble_id: row.get(1)?,
+        date: date.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+        body: row.get(3)?,
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+        updated_at: DateTime::from_timestamp(updated_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_note(pool: &DbPool, note: &Note) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO notes (scrobble_id, date, body, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            note.scrobble_id,
+            note.date.map(|d| d.format("%Y-%m-%d").to_string()),
+            note.body,
+            note.created_at.timestamp(),
+            note.updated_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_note(pool: &DbPool, id: i64) -> Result<Option<Note>> {
+    let conn = pool.get()?;
+    let mut stmt =
+        conn.prepare_cached(&format!("SELECT {} FROM notes WHERE id = ?1", NOTE_COLUMNS))?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_note(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Notes ordered newest first, optionally limited to day notes in a date range
+/// and scrobble notes whose scrobble falls in it
+pub fn get_notes(
+    pool: &DbPool,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<Vec<Note>> {
+    let conn = pool.get()?;
+
+    let notes = if let (Some(start), Some(end)) = (start_date, end_date) {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM notes
+             WHERE date BETWEEN ?1 AND ?2
+                OR scrobble_id IN (SELECT id FROM scrobbles WHERE timestamp >= ?3 AND timestamp <= ?4)
+             ORDER BY created_at DESC",
+            NOTE_COLUMNS
+        ))?;
+        stmt.query_map(
+            params![
+                start.format("%Y-%m-%d").to_string(),
+                end.format("%Y-%m-%d").to_string(),
+                start.timestamp(),
+                end.timestamp()
+            ],
+            row_to_note,
+        )?
+        .collect::<Result<Vec<_>, _>>()?
+    } else {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM notes ORDER BY created_at DESC",
+            NOTE_COLUMNS
+        ))?;
+        stmt.query_map([], row_to_note)?
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(notes)
+}
+
+/// Notes attached to any of the given scrobbles or days
+pub fn get_notes_for(
+    pool: &DbPool,
+    scrobble_ids: &[i64],
+    dates: &[NaiveDate],
+) -> Result<Vec<Note>> {
+    if scrobble_ids.is_empty() && dates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let conn = pool.get()?;
+    let id_placeholders = vec!["?"; scrobble_ids.len()].join(",");
+    let date_placeholders = vec!["?"; dates.len()].join(",");
+
+    // The SQL changes with the arguments, so caching it would only churn the cache
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM notes
+         WHERE scrobble_id IN ({}) OR date IN ({})
+         ORDER BY created_at ASC",
+        NOTE_COLUMNS, id_placeholders, date_placeholders
+    ))?;
+
+    let values: Vec<rusqlite::types::Value> = scrobble_ids
+        .iter()
+        .map(|id| rusqlite::types::Value::Integer(*id))
+        .chain(
+            dates
+                .iter()
+                .map(|d| rusqlite::types::Value::Text(d.format("%Y-%m-%d").to_string())),
+        )
+        .collect();
+
+    let notes = stmt
+        .query_map(params_from_iter(values), row_to_note)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(notes)
+}
+
+pub fn update_note(pool: &DbPool, id: i64, body: &str) -> Result<bool> {
+    let conn = pool.get()?;
+    let updated = conn.execute(
+        "UPDATE notes SET body = ?1, updated_at = ?2 WHERE id = ?3",
+        params![body, Utc::now().timestamp(), id],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn delete_note(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+// Annotation operations
+const ANNOTATION_COLUMNS: &str = "id, artist, date, end_date, kind, label, created_at, updated_at";
+
+fn row_to_annotation(row: &rusqlite::Row) -> rusqlite::Result<Annotation> {
+    let date: String = row.get(2)?;
+    let end_date: Option<String> = row.get(3)?;
+    let kind: String = row.get(4)?;
+    let created_ts: i64 = row.get(6)?;
+    let updated_ts: i64 = row.get(7)?;
+
+    Ok(Annotation {
+        id: Some(row.get(0)?),
+        artist: row.get(1)?,
+        date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_default(),
+        end_date: end_date.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+        kind: AnnotationKind::parse(&kind),
+        label: row.get(5)?,
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+        updated_at: DateTime::from_timestamp(updated_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_annotation(pool: &DbPool, annotation: &Annotation) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO annotations (artist, date, end_date, kind, label, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            annotation.artist,
+            annotation.date.format("%Y-%m-%d").to_string(),
+            annotation
+                .end_date
+                .map(|d| d.format("%Y-%m-%d").to_string()),
+            annotation.kind.as_str(),
+            annotation.label,
+            annotation.created_at.timestamp(),
+            annotation.updated_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_annotation(pool: &DbPool, id: i64) -> Result<Option<Annotation>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM annotations WHERE id = ?1",
+        ANNOTATION_COLUMNS
+    ))?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_annotation(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Annotations oldest first, optionally only one artist's and only those
+/// overlapping `start..=end`
+pub fn get_annotations(
+    pool: &DbPool,
+    artist: Option<&str>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> Result<Vec<Annotation>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM annotations
+         WHERE (?1 IS NULL OR artist = ?1)
+           AND (?2 IS NULL OR COALESCE(end_date, date) >= ?2)
+           AND (?3 IS NULL OR date <= ?3)
+         ORDER BY date, id",
+        ANNOTATION_COLUMNS
+    ))?;
+    let annotations = stmt
+        .query_map(
+            params![
+                artist,
+                start.map(|d| d.format("%Y-%m-%d").to_string()),
+                end.map(|d| d.format("%Y-%m-%d").to_string())
+            ],
+            row_to_annotation,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(annotations)
+}
+
+/// Replace an annotation's contents, keeping its id and creation time
+pub fn update_annotation(pool: &DbPool, id: i64, annotation: &Annotation) -> Result<bool> {
+    let conn = pool.get()?;
+    let updated = conn.execute(
+        "UPDATE annotations
+         SET artist = ?1, date = ?2, end_date = ?3, kind = ?4, label = ?5, updated_at = ?6
+         WHERE id = ?7",
+        params![
+            annotation.artist,
+            annotation.date.format("%Y-%m-%d").to_string(),
+            annotation
+                .end_date
+                .map(|d| d.format("%Y-%m-%d").to_string()),
+            annotation.kind.as_str(),
+            annotation.label,
+            Utc::now().timestamp(),
+            id
+        ],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn delete_annotation(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM annotations WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+// Ignore rule operations
+const IGNORE_RULE_COLUMNS: &str = "id, source, artist, track, suppressed_count, created_at";
+
+fn row_to_ignore_rule(row: &rusqlite::Row) -> rusqlite::Result<IgnoreRule> {
+    let created_ts: i64 = row.get(5)?;
+
+    Ok(IgnoreRule {
+        id: Some(row.get(0)?),
+        source: row.get(1)?,
+        artist: row.get(2)?,
+        track: row.get(3)?,
+        suppressed_count: row.get(4)?,
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_ignore_rule(pool: &DbPool, rule: &IgnoreRule) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO ignore_rules (source, artist, track, suppressed_count, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            rule.source,
+            rule.artist,
+            rule.track,
+            rule.suppressed_count,
+            rule.created_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_ignore_rule(pool: &DbPool, id: i64) -> Result<Option<IgnoreRule>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM ignore_rules WHERE id = ?1",
+        IGNORE_RULE_COLUMNS
+    ))?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_ignore_rule(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// All ignore rules, or only those that apply to `source` (rules without a
+/// source apply to every source)
+pub fn get_ignore_rules(pool: &DbPool, source: Option<&str>) -> Result<Vec<IgnoreRule>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM ignore_rules
+         WHERE ?1 IS NULL OR source IS NULL OR source = ?1
+         ORDER BY id",
+        IGNORE_RULE_COLUMNS
+    ))?;
+    let rules = stmt
+        .query_map(params![source], row_to_ignore_rule)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+pub fn update_ignore_rule(pool: &DbPool, id: i64, rule: &IgnoreRule) -> Result<bool> {
+    let conn = pool.get()?;
+    let updated = conn.execute(
+        "UPDATE ignore_rules SET source = ?1, artist = ?2, track = ?3 WHERE id = ?4",
+        params![rule.source, rule.artist, rule.track, id],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn delete_ignore_rule(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM ignore_rules WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+/// Add to the number of scrobbles each rule kept out of the database
+pub fn add_suppressed_counts(pool: &DbPool, counts: &HashMap<i64, i64>) -> Result<()> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "UPDATE ignore_rules SET suppressed_count = suppressed_count + ?1 WHERE id = ?2",
+        )?;
+        for (id, count) in counts {
+            stmt.execute(params![count, id])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// Alert rule operations
+fn row_to_alert_rule(row: &rusqlite::Row) -> rusqlite::Result<AlertRule> {
+    let kind: String = row.get(1)?;
+    let last_triggered_ts: Option<i64> = row.get(5)?;
+    let created_ts: i64 = row.get(6)?;
+
+    Ok(AlertRule {
+        id: Some(row.get(0)?),
+        kind: kind.parse().map_err(|e: anyhow::Error| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, e.into())
+        })?,
+        threshold_hours: row.get(2)?,
+        enabled: row.get::<_, i32>(3)? != 0,
+        active: row.get::<_, i32>(4)? != 0,
+        last_triggered_at: last_triggered_ts.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_alert_rule(pool: &DbPool, rule: &AlertRule) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO alert_rules (kind, threshold_hours, enabled, active, created_at)
+         VALUES (?1, ?2, ?3, 0, ?4)",
+        params![
+            rule.kind.as_str(),
+            rule.threshold_hours,
+            rule.enabled,
+            rule.created_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_alert_rules(pool: &DbPool) -> Result<Vec<AlertRule>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, kind, threshold_hours, enabled, active, last_triggered_at, created_at
+         FROM alert_rules ORDER BY id",
+    )?;
+    let rules = stmt
+        .query_map([], row_to_alert_rule)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+/// Mark whether a rule's condition holds; `triggered_at` is set when it fires
+pub fn set_alert_rule_active(
+    pool: &DbPool,
+    id: i64,
+    active: bool,
+    triggered_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE alert_rules
+         SET active = ?1, last_triggered_at = COALESCE(?2, last_triggered_at)
+         WHERE id = ?3",
+        params![active, triggered_at.map(|t| t.timestamp()), id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_alert_rule(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM alert_rules WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+// Milestone rule operations
+fn row_to_milestone_rule(row: &rusqlite::Row) -> rusqlite::Result<MilestoneRule> {
+    let parse_error = |column, e: anyhow::Error| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+    };
+    let metric: String = row.get(3)?;
+    let icon: String = row.get(5)?;
+    let created_ts: i64 = row.get(7)?;
+
+    Ok(MilestoneRule {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        description: row.get(2)?,
+        metric: metric.parse().map_err(|e| parse_error(3, e))?,
+        threshold: row.get(4)?,
+        icon: icon.parse().map_err(|e| parse_error(5, e))?,
+        enabled: row.get::<_, i32>(6)? != 0,
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_milestone_rule(pool: &DbPool, rule: &MilestoneRule) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO milestone_rules
+         (title, description, metric, threshold, icon, enabled, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            rule.title,
+            rule.description,
+            rule.metric.as_str(),
+            rule.threshold,
+            rule.icon.as_str(),
+            rule.enabled,
+            rule.created_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_milestone_rules(pool: &DbPool) -> Result<Vec<MilestoneRule>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, title, description, metric, threshold, icon, enabled, created_at
+         FROM milestone_rules ORDER BY id",
+    )?;
+    let rules = stmt
+        .query_map([], row_to_milestone_rule)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+pub fn delete_milestone_rule(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM milestone_rules WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+// Achievement operations
+fn row_to_achievement(row: &rusqlite::Row) -> rusqlite::Result<Achievement> {
+    let icon: String = row.get(6)?;
+    let earned_ts: i64 = row.get(7)?;
+
+    Ok(Achievement {
+        id: Some(row.get(0)?),
+        year: row.get(1)?,
+        key: row.get(2)?,
+        title: row.get(3)?,
+        description: row.get(4)?,
+        value: row.get(5)?,
+        icon: icon.parse().map_err(|e: anyhow::Error| {
+            rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, e.into())
+        })?,
+        earned_at: DateTime::from_timestamp(earned_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+/// Store an achievement unless that year's one with the same key is already
+/// earned; the id when it's new
+pub fn insert_achievement(pool: &DbPool, achievement: &Achievement) -> Result<Option<i64>> {
+    let conn = pool.get()?;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO achievements
+         (year, key, title, description, value, icon, earned_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            achievement.year,
+            achievement.key,
+            achievement.title,
+            achievement.description,
+            achievement.value,
+            achievement.icon.as_str(),
+            achievement.earned_at.timestamp(),
+        ],
+    )?;
+    Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+}
+
+/// Earned achievements, latest first and in report order within a sync,
+/// optionally of one year only
+pub fn get_achievements(pool: &DbPool, year: Option<i32>) -> Result<Vec<Achievement>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, year, key, title, description, value, icon, earned_at
+         FROM achievements
+         WHERE ?1 IS NULL OR year = ?1
+         ORDER BY earned_at DESC, id",
+    )?;
+    let achievements = stmt
+        .query_map(params![year], row_to_achievement)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(achievements)
+}
+
+// Import job operations
+fn row_to_import_job(row: &rusqlite::Row) -> rusqlite::Result<ImportJob> {
+    let status: String = row.get(6)?;
+    let started_ts: i64 = row.get(10)?;
+    let updated_ts: i64 = row.get(11)?;
+
+    Ok(ImportJob {
+        id: Some(row.get(0)?),
+        source: row.get(1)?,
+        username: row.get(2)?,
+        api_key: row.get(3)?,
+        token: row.get(4)?,
+        enrich: row.get::<_, i32>(5)? != 0,
+        status: status.parse().unwrap_or_default(),
+        checkpoint: row.get(7)?,
+        imported_count: row.get(8)?,
+        last_error: row.get(9)?,
+        started_at: DateTime::from_timestamp(started_ts, 0).unwrap_or_else(Utc::now),
+        updated_at: DateTime::from_timestamp(updated_ts, 0).unwrap_or_else(Utc::now),
+    })
+}
+
+pub fn insert_import_job(pool: &DbPool, job: &ImportJob) -> Result<i64> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO import_jobs (source, username, api_key, token, enrich, status, checkpoint, imported_count, started_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            job.source,
+            job.username,
+            job.api_key,
+            job.token,
+            job.enrich,
+            job.status.as_str(),
+            job.checkpoint,
+            job.imported_count,
+            job.started_at.timestamp(),
+            job.updated_at.timestamp(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_import_job(pool: &DbPool, id: i64) -> Result<Option<ImportJob>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, api_key, token, enrich, status, checkpoint,
+                imported_count, last_error, started_at, updated_at
+         FROM import_jobs WHERE id = ?1",
+    )?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_import_job(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Import jobs, newest first, optionally only those with `status`
+pub fn get_import_jobs(pool: &DbPool, status: Option<ImportStatus>) -> Result<Vec<ImportJob>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, source, username, api_key, token, enrich, status, checkpoint,
+                imported_count, last_error, started_at, updated_at
+         FROM import_jobs
+         WHERE ?1 IS NULL OR status = ?1
+         ORDER BY id DESC",
+    )?;
+    let jobs = stmt
+        .query_map(params![status.map(|s| s.as_str())], row_to_import_job)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+/// Record that everything before `checkpoint` is stored, with `imported_count`
+/// scrobbles imported so far
+pub fn save_import_checkpoint(
+    pool: &DbPool,
+    id: i64,
+    checkpoint: i64,
+    imported_count: i64,
+) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE import_jobs SET checkpoint = ?1, imported_count = ?2, updated_at = ?3 WHERE id = ?4",
+        params![checkpoint, imported_count, Utc::now().timestamp(), id],
+    )?;
+    Ok(())
+}
+
+/// Move a job to `status`; the count is kept when `imported_count` is `None`
+pub fn set_import_job_status(
+    pool: &DbPool,
+    id: i64,
+    status: ImportStatus,
+    imported_count: Option<i64>,
+    error: Option<&str>,
+) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE import_jobs
+         SET status = ?1, imported_count = COALESCE(?2, imported_count), last_error = ?3, updated_at = ?4
+         WHERE id = ?5",
+        params![
+            status.as_str(),
+            imported_count,
+            error,
+            Utc::now().timestamp(),
+            id
+        ],
+    )?;
+    Ok(())
+}
+
+// MusicBrainz id operations
+/// A resolved MusicBrainz id for an artist, or an album or track of that
+/// artist (`name` is `None` for artists)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MusicBrainzId {
+    pub entity_type: &'static str,
+    pub artist: String,
+    pub name: Option<String>,
+    pub mbid: String,
+}
+
+/// Remember resolved MBIDs, replacing older ones for the same entity
+pub fn save_musicbrainz_ids(pool: &DbPool, ids: &[MusicBrainzId]) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let now = Utc::now().timestamp();
+
+    let mut saved = 0;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO musicbrainz_entities (entity_type, artist, name, mbid, resolved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for id in ids {
+            saved += stmt.execute(params![
+                id.entity_type,
+                id.artist,
+                id.name.as_deref().unwrap_or_default(),
+                id.mbid,
+                now
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(saved)
+}
+
+pub fn get_musicbrainz_id(
+    pool: &DbPool,
+    entity_type: &str,
+    artist: &str,
+    name: Option<&str>,
+) -> Result<Option<String>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT mbid FROM musicbrainz_entities
+         WHERE entity_type = ?1 AND artist = ?2 AND name = ?3",
+    )?;
+    let mut rows = stmt.query(params![entity_type, artist, name.unwrap_or_default()])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+// Media type rule operations
+/// Store a rule and reclassify the scrobbles it matches; returns the rule id
+/// and the number of scrobbles updated
+pub fn insert_media_type_rule(pool: &DbPool, rule: &MediaTypeRule) -> Result<(i64, usize)> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO media_type_rules (artist, album, media_type, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            rule.artist,
+            rule.album,
+            rule.media_type.as_str(),
+            rule.created_at.timestamp(),
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    let updated = tx.execute(
+        "UPDATE scrobbles SET media_type = ?1
+         WHERE (?2 IS NULL OR TRIM(artist) = TRIM(?2) COLLATE NOCASE)
+           AND (?3 IS NULL OR TRIM(album) = TRIM(?3) COLLATE NOCASE)",
+        params![rule.media_type.as_str(), rule.artist, rule.album],
+    )?;
+
+    tx.commit()?;
+    Ok((id, updated))
+}
+
+// Settings
+
+const LISTEN_FILTER_KEY: &str = "listen_filter";
+
+pub fn get_setting(pool: &DbPool, key: &str) -> Result<Option<serde_json::Value>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached("SELECT value FROM settings WHERE key = ?1")?;
+    let mut rows = stmt.query(params![key])?;
+    match rows.next()? {
+        Some(row) => {
+            let value: String = row.get(0)?;
+            Ok(Some(serde_json::from_str(&value)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Every stored setting, read in one query
+pub fn get_all_settings(pool: &DbPool) -> Result<HashMap<String, serde_json::Value>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached("SELECT key, value FROM settings")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut settings = HashMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        settings.insert(key, serde_json::from_str(&value)?);
+    }
+    Ok(settings)
+}
+
+pub fn set_setting(pool: &DbPool, key: &str, value: &serde_json::Value) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value.to_string(), Utc::now().timestamp()],
+    )?;
+    Ok(())
+}
+
+/// The stored minimum play criteria, Last.fm's rules until changed
+pub fn get_listen_filter(pool: &DbPool) -> Result<ListenFilter> {
+    match get_setting(pool, LISTEN_FILTER_KEY)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(ListenFilter::default()),
+    }
+}
+
+pub fn set_listen_filter(pool: &DbPool, filter: &ListenFilter) -> Result<()> {
+    set_setting(pool, LISTEN_FILTER_KEY, &serde_json::to_value(filter)?)
+}
+
+/// A release group found by the release radar
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Release {
+    /// MusicBrainz release group id
+    pub mbid: String,
+    pub artist: String,
+    pub title: String,
+    /// Album, Single, EP...
+    pub release_type: Option<String>,
+    pub release_date: NaiveDate,
+    pub discovered_at: DateTime<Utc>,
+}
+
+/// Store releases not seen before, keeping when known ones were discovered
+pub fn save_releases(pool: &DbPool, releases: &[Release]) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let mut saved = 0;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO releases (mbid, artist, title, release_type, release_date, discovered_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for release in releases {
+            saved += stmt.execute(params![
+                release.mbid,
+                release.artist,
+                release.title,
+                release.release_type,
+                release.release_date.format("%Y-%m-%d").to_string(),
+                release.discovered_at.timestamp()
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(saved)
+}
+
+/// Releases out on or after `since`, newest first
+pub fn get_releases_since(pool: &DbPool, since: NaiveDate, limit: i64) -> Result<Vec<Release>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT mbid, artist, title, release_type, release_date, discovered_at
+         FROM releases
+         WHERE release_date >= ?1
+         ORDER BY release_date DESC, artist, title
+         LIMIT ?2",
+    )?;
+    let releases = stmt
+        .query_map(
+            params![since.format("%Y-%m-%d").to_string(), limit],
+            |row| {
+                let release_date: String = row.get(4)?;
+                Ok(Release {
+                    mbid: row.get(0)?,
+                    artist: row.get(1)?,
+                    title: row.get(2)?,
+                    release_type: row.get(3)?,
+                    release_date: NaiveDate::parse_from_str(&release_date, "%Y-%m-%d")
+                        .unwrap_or_default(),
+                    discovered_at: DateTime::from_timestamp(row.get(5)?, 0).unwrap_or_default(),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(releases)
+}
+
+pub fn mark_release_check(pool: &DbPool, artist: &str, checked_at: DateTime<Utc>) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO release_radar_checks (artist, checked_at) VALUES (?1, ?2)",
+        params![artist, checked_at.timestamp()],
+    )?;
+    Ok(())
+}
+
+/// When the radar last looked up `artist`, or any artist when `None`
+pub fn get_release_check(pool: &DbPool, artist: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    let conn = pool.get()?;
+    let checked_at: Option<i64> = match artist {
+        Some(artist) => conn.query_row(
+            "SELECT MAX(checked_at) FROM release_radar_checks WHERE artist = ?1",
+            params![artist],
+            |row| row.get(0),
+        )?,
+        None => conn.query_row(
+            "SELECT MAX(checked_at) FROM release_radar_checks",
+            [],
+            |row| row.get(0),
+        )?,
+    };
+    Ok(checked_at.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+/// Replace the recorded charts of the week starting on `week_start`
+pub fn save_chart_week(
+    pool: &DbPool,
+    week_start: NaiveDate,
+    artists: &[ChartEntry],
+    tracks: &[ChartEntry],
+    recorded_at: DateTime<Utc>,
+) -> Result<()> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let week = week_start.format("%Y-%m-%d").to_string();
+
+    tx.execute(
+        "DELETE FROM chart_snapshots WHERE week_start = ?1",
+        params![week],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO chart_weeks (week_start, recorded_at) VALUES (?1, ?2)",
+        params![week, recorded_at.timestamp()],
+    )?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO chart_snapshots (chart, week_start, rank, artist, track, plays)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for (kind, entries) in [(ChartKind::Artist, artists), (ChartKind::Track, tracks)] {
+            for entry in entries {
+                stmt.execute(params![
+                    kind.as_str(),
+                    week,
+                    entry.rank,
+                    entry.artist,
+                    entry.track.as_deref().unwrap_or(""),
+                    entry.plays
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Start of the latest week whose charts were recorded
+pub fn get_last_chart_week(pool: &DbPool) -> Result<Option<NaiveDate>> {
+    let conn = pool.get()?;
+    let week: Option<String> =
+        conn.query_row("SELECT MAX(week_start) FROM chart_weeks", [], |row| {
+            row.get(0)
+        })?;
+    Ok(week.and_then(|week| NaiveDate::parse_from_str(&week, "%Y-%m-%d").ok()))
+}
+
+/// Store a Last.fm weekly chart of artists `(artist, plays)` and tracks
+/// `(artist, track, plays)`, replacing what was imported for that week before.
+/// Returns the number of chart entries stored
+pub fn save_legacy_chart_week(
+    pool: &DbPool,
+    week_from: DateTime<Utc>,
+    week_to: DateTime<Utc>,
+    artists: &[(String, i64)],
+    tracks: &[(String, String, i64)],
+    imported_at: DateTime<Utc>,
+) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let from = week_from.timestamp();
+
+    tx.execute(
+        "DELETE FROM legacy_charts WHERE week_from = ?1",
+        params![from],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO legacy_chart_weeks (week_from, week_to, imported_at)
+         VALUES (?1, ?2, ?3)",
+        params![from, week_to.timestamp(), imported_at.timestamp()],
+    )?;
+    let mut stored = 0;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO legacy_charts (chart, week_from, artist, track, plays)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (artist, plays) in artists {
+            stored += stmt.execute(params![ChartKind::Artist.as_str(), from, artist, "", plays])?;
+        }
+        for (artist, track, plays) in tracks {
+            stored += stmt.execute(params![
+                ChartKind::Track.as_str(),
+                from,
+                artist,
+                track,
+                plays
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(stored)
+}
+
+/// Start of every Last.fm chart week imported so far
+pub fn get_legacy_chart_weeks(pool: &DbPool) -> Result<HashSet<DateTime<Utc>>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached("SELECT week_from FROM legacy_chart_weeks")?;
+    let weeks = stmt
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(weeks
+        .into_iter()
+        .filter_map(|ts| DateTime::from_timestamp(ts, 0))
+        .collect())
+}
+
+/// All-time top artists as `(artist, plays, legacy plays)`, adding the plays
+/// of legacy Last.fm charts to the scrobbles credited as `credit`. Legacy
+/// names aren't split into credits
+pub fn query_blended_top_artists(
+    conn: &Connection,
+    credit: ArtistCredit,
+    limit: i64,
+) -> Result<Vec<(String, i64, i64)>> {
+    let sql = format!(
+        "SELECT artist, SUM(plays) AS count, SUM(legacy) FROM (
+             SELECT artist, COUNT(*) AS plays, 0 AS legacy FROM {}
+             WHERE media_type = 'music' AND sleep_flagged = 0
+             GROUP BY artist
+             UNION ALL
+             SELECT artist, plays, plays FROM legacy_charts WHERE chart = 'artist'
+         )
+         GROUP BY artist ORDER BY count DESC, artist LIMIT ?1",
+        credited_scrobbles(credit)
+    );
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let artists = stmt.query_map(params![limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    Ok(artists.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// All-time top tracks as `(artist, track, plays, legacy plays)`, adding the
+/// plays of legacy Last.fm charts to the scrobbles
+pub fn query_blended_top_tracks(
+    conn: &Connection,
+    limit: i64,
+) -> Result<Vec<(String, String, i64, i64)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT artist, track, SUM(plays) AS count, SUM(legacy) FROM (
+             SELECT artist, track, COUNT(*) AS plays, 0 AS legacy FROM scrobbles
+             WHERE media_type = 'music' AND sleep_flagged = 0
+             GROUP BY artist, track
+             UNION ALL
+             SELECT artist, track, plays, plays FROM legacy_charts WHERE chart = 'track'
+         )
+         GROUP BY artist, track ORDER BY count DESC, artist, track LIMIT ?1",
+    )?;
+    let tracks = stmt.query_map(params![limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+    Ok(tracks.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Store the summaries of days taken in `timezone`, replacing earlier ones
+pub fn save_daily_summaries(
+    pool: &DbPool,
+    summaries: &[DailySummary],
+    timezone: &str,
+    recorded_at: DateTime<Utc>,
+) -> Result<()> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO daily_summaries
+             (date, timezone, scrobbles, top_artist, top_artist_count, new_artists, sessions,
+              recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for summary in summaries {
+            stmt.execute(params![
+                summary.date.format("%Y-%m-%d").to_string(),
+                timezone,
+                summary.scrobbles,
+                summary.top_artist,
+                summary.top_artist_count,
+                serde_json::to_string(&summary.new_artists)?,
+                summary.sessions,
+                recorded_at.timestamp(),
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Latest summarized day, with the timezone it was taken in
+pub fn get_last_daily_summary(pool: &DbPool) -> Result<Option<(NaiveDate, String)>> {
+    let conn = pool.get()?;
+    let last = conn.query_row(
+        "SELECT date, timezone FROM daily_summaries ORDER BY date DESC LIMIT 1",
+        [],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    );
+    match last {
+        Ok((date, timezone)) => Ok(NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .ok()
+            .map(|date| (date, timezone))),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stored summaries of the days in `from..to` taken in `timezone`, oldest
+/// first; days summarized in another timezone are left out
+pub fn get_daily_summaries(
+    pool: &DbPool,
+    from: NaiveDate,
+    to: NaiveDate,
+    timezone: &str,
+) -> Result<Vec<DailySummary>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT date, scrobbles, top_artist, top_artist_count, new_artists, sessions
+         FROM daily_summaries
+         WHERE date >= ?1 AND date < ?2 AND timezone = ?3
+         ORDER BY date",
+    )?;
+    let summaries = stmt
+        .query_map(
+            params![
+                from.format("%Y-%m-%d").to_string(),
+                to.format("%Y-%m-%d").to_string(),
+                timezone
+            ],
+            |row| {
+                let date: String = row.get(0)?;
+                let new_artists: String = row.get(4)?;
+                Ok(DailySummary {
+                    date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_default(),
+                    scrobbles: row.get(1)?,
+                    top_artist: row.get(2)?,
+                    top_artist_count: row.get(3)?,
+                    new_artists: serde_json::from_str(&new_artists).unwrap_or_default(),
+                    sessions: row.get(5)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(summaries)
+}
+
+fn row_to_chart_entry(row: &rusqlite::Row) -> rusqlite::Result<ChartEntry> {
+    let week_start: String = row.get(0)?;
+    let track: String = row.get(3)?;
+    Ok(ChartEntry {
+        week_start: NaiveDate::parse_from_str(&week_start, "%Y-%m-%d").unwrap_or_default(),
+        rank: row.get(1)?,
+        artist: row.get(2)?,
+        track: (!track.is_empty()).then_some(track),
+        plays: row.get(4)?,
+    })
+}
+
+/// The `kind` chart of the week starting on `week_start`, top first; `None`
+/// if that week wasn't recorded
+pub fn get_chart(
+    pool: &DbPool,
+    kind: ChartKind,
+    week_start: NaiveDate,
+) -> Result<Option<Vec<ChartEntry>>> {
+    let conn = pool.get()?;
+    let recorded: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM chart_weeks WHERE week_start = ?1)",
+        params![week_start.format("%Y-%m-%d").to_string()],
+        |row| row.get(0),
+    )?;
+    if !recorded {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT week_start, rank, artist, track, plays FROM chart_snapshots
+         WHERE chart = ?1 AND week_start = ?2
+         ORDER BY rank, artist, track",
+    )?;
+    let entries = stmt
+        .query_map(
+            params![kind.as_str(), week_start.format("%Y-%m-%d").to_string()],
+            row_to_chart_entry,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(entries))
+}
+
+/// Every recorded `kind` chart entry, oldest week first; only those of
+/// `artist` and `track` when given (`track` is ignored on the artist chart)
+pub fn get_chart_entries(
+    pool: &DbPool,
+    kind: ChartKind,
+    entity: Option<(&str, &str)>,
+) -> Result<Vec<ChartEntry>> {
+    let conn = pool.get()?;
+    let entries = match entity {
+        Some((artist, track)) => {
+            let track = match kind {
+                ChartKind::Artist => "",
+                ChartKind::Track => track,
+            };
+            conn.prepare_cached(
+                "SELECT week_start, rank, artist, track, plays FROM chart_snapshots
+                 WHERE chart = ?1 AND artist = ?2 AND track = ?3
+                 ORDER BY week_start",
+            )?
+            .query_map(params![kind.as_str(), artist, track], row_to_chart_entry)?
+            .collect::<Result<Vec<_>, _>>()?
+        }
+        None => conn
+            .prepare_cached(
+                "SELECT week_start, rank, artist, track, plays FROM chart_snapshots
+                 WHERE chart = ?1
+                 ORDER BY week_start, rank",
+            )?
+            .query_map(params![kind.as_str()], row_to_chart_entry)?
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok(entries)
+}
+
+/// A playlist ListenBrainz generated for a user
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RecommendedPlaylist {
+    pub mbid: String,
+    pub username: String,
+    pub title: String,
+    /// The generating algorithm, e.g. weekly-exploration or weekly-jams
+    pub kind: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RecommendedTrack {
+    pub position: i64,
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub recording_mbid: Option<String>,
+}
+
+/// A stored playlist with how much of it the history already covers
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecommendedPlaylistSummary {
+    #[serde(flatten)]
+    pub playlist: RecommendedPlaylist,
+    pub tracks: i64,
+    /// Tracks scrobbled before the playlist was made
+    pub already_known: i64,
+    /// Tracks scrobbled since the playlist was made
+    pub played_since: i64,
+}
+
+/// A recommended track matched against the scrobbles
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecommendedTrackPlays {
+    #[serde(flatten)]
+    pub track: RecommendedTrack,
+    pub plays_before: i64,
+    pub plays_since: i64,
+}
+
+// Scrobbles of a recommended track `t` before and since its playlist `p` was
+// made. Names are matched as stored; titles ignore case
+const RECOMMENDED_TRACK_PLAYS: &str = "
+    (SELECT COUNT(*) FROM scrobbles s
+     WHERE s.artist = t.artist AND s.track = t.track COLLATE NOCASE
+       AND s.timestamp < p.created_at) AS plays_before,
+    (SELECT COUNT(*) FROM scrobbles s
+     WHERE s.artist = t.artist AND s.track = t.track COLLATE NOCASE
+       AND s.timestamp >= p.created_at) AS plays_since";
+
+pub fn has_recommended_playlist(pool: &DbPool, mbid: &str) -> Result<bool> {
+    let conn = pool.get()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM recommended_playlists WHERE mbid = ?1",
+        params![mbid],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Store a playlist with its tracks, replacing an earlier copy
+pub fn save_recommended_playlist(
+    pool: &DbPool,
+    playlist: &RecommendedPlaylist,
+    tracks: &[RecommendedTrack],
+) -> Result<()> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "DELETE FROM recommended_playlist_tracks WHERE playlist_mbid = ?1",
+        params![playlist.mbid],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO recommended_playlists (mbid, username, title, kind, created_at, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            playlist.mbid,
+            playlist.username,
+            playlist.title,
+            playlist.kind,
+            playlist.created_at.timestamp(),
+            playlist.fetched_at.timestamp()
+        ],
+    )?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO recommended_playlist_tracks
+                 (playlist_mbid, position, artist, track, album, recording_mbid)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for track in tracks {
+            stmt.execute(params![
+                playlist.mbid,
+                track.position,
+                track.artist,
+                track.track,
+                track.album,
+                track.recording_mbid
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn row_to_recommended_playlist(row: &rusqlite::Row) -> rusqlite::Result<RecommendedPlaylist> {
+    Ok(RecommendedPlaylist {
+        mbid: row.get(0)?,
+        username: row.get(1)?,
+        title: row.get(2)?,
+        kind: row.get(3)?,
+        created_at: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+        fetched_at: DateTime::from_timestamp(row.get(5)?, 0).unwrap_or_default(),
+    })
+}
+
+/// Stored playlists, newest first
+pub fn get_recommended_playlists(
     pool: &DbPool,
     limit: i64,
+) -> Result<Vec<RecommendedPlaylistSummary>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT p.mbid, p.username, p.title, p.kind, p.created_at, p.fetched_at,
+                COUNT(t.position),
+                COALESCE(SUM(plays_before > 0), 0),
+                COALESCE(SUM(plays_since > 0), 0)
+         FROM recommended_playlists p
+         LEFT JOIN (
+             SELECT t.playlist_mbid, t.position, {}
+             FROM recommended_playlist_tracks t
+             JOIN recommended_playlists p ON p.mbid = t.playlist_mbid
+         ) t ON t.playlist_mbid = p.mbid
+         GROUP BY p.mbid
+         ORDER BY p.created_at DESC, p.title
+         LIMIT ?1",
+        RECOMMENDED_TRACK_PLAYS
+    ))?;
+    let playlists = stmt
+        .query_map(params![limit], |row| {
+            Ok(RecommendedPlaylistSummary {
+                playlist: row_to_recommended_playlist(row)?,
+                tracks: row.get(6)?,
+                already_known: row.get(7)?,
+                played_since: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(playlists)
+}
+
+/// A stored playlist and its tracks in order, each with its plays
+pub fn get_recommended_playlist(
+    pool: &DbPool,
+    mbid: &str,
+) -> Result<Option<(RecommendedPlaylist, Vec<RecommendedTrackPlays>)>> {
+    let conn = pool.get()?;
+    let playlist = {
+        let mut stmt = conn.prepare_cached(
+            "SELECT mbid, username, title, kind, created_at, fetched_at
+             FROM recommended_playlists WHERE mbid = ?1",
+        )?;
+        let mut rows = stmt.query(params![mbid])?;
+        match rows.next()? {
+            Some(row) => row_to_recommended_playlist(row)?,
+            None => return Ok(None),
+        }
+    };
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT t.position, t.artist, t.track, t.album, t.recording_mbid, {}
+         FROM recommended_playlist_tracks t
+         JOIN recommended_playlists p ON p.mbid = t.playlist_mbid
+         WHERE t.playlist_mbid = ?1
+         ORDER BY t.position",
+        RECOMMENDED_TRACK_PLAYS
+    ))?;
+    let tracks = stmt
+        .query_map(params![mbid], |row| {
+            Ok(RecommendedTrackPlays {
+                track: RecommendedTrack {
+                    position: row.get(0)?,
+                    artist: row.get(1)?,
+                    track: row.get(2)?,
+                    album: row.get(3)?,
+                    recording_mbid: row.get(4)?,
+                },
+                plays_before: row.get(5)?,
+                plays_since: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some((playlist, tracks)))
+}
+
+pub fn get_media_type_rules(pool: &DbPool) -> Result<Vec<MediaTypeRule>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, artist, album, media_type, created_at FROM media_type_rules ORDER BY id",
+    )?;
+    let rules = stmt
+        .query_map([], |row| {
+            let media_type: String = row.get(3)?;
+            let created_ts: i64 = row.get(4)?;
+            Ok(MediaTypeRule {
+                id: Some(row.get(0)?),
+                artist: row.get(1)?,
+                album: row.get(2)?,
+                media_type: media_type.parse().unwrap_or_default(),
+                created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rules)
+}
+
+/// Remove a rule; scrobbles it reclassified keep their media type
+pub fn delete_media_type_rule(pool: &DbPool, id: i64) -> Result<bool> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM media_type_rules WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+// Sleep detection operations
+fn row_to_sleep_detection(row: &rusqlite::Row) -> rusqlite::Result<SleepDetection> {
+    let start_ts: i64 = row.get(1)?;
+    let end_ts: i64 = row.get(2)?;
+    let status: String = row.get(5)?;
+
+    Ok(SleepDetection {
+        id: Some(row.get(0)?),
+        start: DateTime::from_timestamp(start_ts, 0).unwrap_or_else(Utc::now),
+        end: DateTime::from_timestamp(end_ts, 0).unwrap_or_else(Utc::now),
+        scrobble_count: row.get(3)?,
+        distinct_artists: row.get(4)?,
+        status: status.parse().unwrap_or_default(),
+    })
+}
+
+/// Store new detections, returning how many were new; runs already recorded
+/// keep their review status
+pub fn record_sleep_detections(pool: &DbPool, detections: &[SleepDetection]) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let mut recorded = 0;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO sleep_detections
+                (start_ts, end_ts, scrobble_count, distinct_artists, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let now = Utc::now().timestamp();
+        for detection in detections {
+            recorded += stmt.execute(params![
+                detection.start.timestamp(),
+                detection.end.timestamp(),
+                detection.scrobble_count,
+                detection.distinct_artists,
+                detection.status.as_str(),
+                now,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(recorded)
+}
+
+/// Detections overlapping the range (or all of them), oldest first
+pub fn get_sleep_detections(
+    pool: &DbPool,
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
-) -> Result<Vec<(String, String, i64)>> {
+) -> Result<Vec<SleepDetection>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, start_ts, end_ts, scrobble_count, distinct_artists, status
+         FROM sleep_detections
+         WHERE (?1 IS NULL OR end_ts >= ?1) AND (?2 IS NULL OR start_ts <= ?2)
+         ORDER BY start_ts",
+    )?;
+    let detections = stmt
+        .query_map(
+            params![
+                start_date.map(|d| d.timestamp()),
+                end_date.map(|d| d.timestamp())
+            ],
+            row_to_sleep_detection,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(detections)
+}
+
+/// Review a detection: confirming excludes its scrobbles from stats, any other
+/// status counts them again. Returns None for an unknown id.
+pub fn set_sleep_detection_status(
+    pool: &DbPool,
+    id: i64,
+    status: DetectionStatus,
+) -> Result<Option<SleepDetection>> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let updated = tx.execute(
+        "UPDATE sleep_detections SET status = ?1 WHERE id = ?2",
+        params![status.as_str(), id],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+
+    let detection = tx.query_row(
+        "SELECT id, start_ts, end_ts, scrobble_count, distinct_artists, status
+         FROM sleep_detections WHERE id = ?1",
+        params![id],
+        row_to_sleep_detection,
+    )?;
+    tx.execute(
+        "UPDATE scrobbles SET sleep_flagged = ?1 WHERE timestamp >= ?2 AND timestamp <= ?3",
+        params![
+            status == DetectionStatus::Confirmed,
+            detection.start.timestamp(),
+            detection.end.timestamp()
+        ],
+    )?;
+
+    tx.commit()?;
+    Ok(Some(detection))
+}
+
+/// `(id, artist, track)` of one side of a possible conflict
+pub type ConflictSide = (i64, String, String);
+
+/// Scrobble pairs from different sources at most `window_seconds` apart
+/// whose artist, album or track differ, newest first
+pub fn get_cross_source_candidates(
+    pool: &DbPool,
+    window_seconds: i64,
+) -> Result<Vec<(ConflictSide, ConflictSide)>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT a.id, a.artist, a.track, b.id, b.artist, b.track
+         FROM scrobbles a
+         JOIN scrobbles b
+           ON b.timestamp BETWEEN a.timestamp - ?1 AND a.timestamp + ?1
+          AND b.source > a.source
+         WHERE a.artist <> b.artist
+            OR a.track <> b.track
+            OR COALESCE(a.album, '') <> COALESCE(b.album, '')
+         ORDER BY a.timestamp DESC, a.id",
+    )?;
+    let pairs = stmt
+        .query_map(params![window_seconds], |row| {
+            Ok((
+                (row.get(0)?, row.get(1)?, row.get(2)?),
+                (row.get(3)?, row.get(4)?, row.get(5)?),
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(pairs)
+}
+
+/// Give `target` the artist, album and track of `canonical`, keeping the
+/// names it had as raw metadata unless normalization already recorded them
+pub fn apply_canonical_metadata(
+    pool: &DbPool,
+    canonical: &Scrobble,
+    target: &Scrobble,
+) -> Result<Option<Scrobble>> {
+    let (Some(target_id), Some(_)) = (target.id, canonical.id) else {
+        return Ok(None);
+    };
+
+    let raw = target.raw_metadata.clone().unwrap_or_else(|| RawMetadata {
+        artist: target.artist.clone(),
+        album: target.album.clone(),
+        track: target.track.clone(),
+    });
+
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "UPDATE scrobbles SET artist = ?1, album = ?2, track = ?3, raw_metadata = ?4 WHERE id = ?5",
+        params![
+            canonical.artist,
+            canonical.album,
+            canonical.track,
+            serde_json::to_string(&raw)?,
+            target_id
+        ],
+    )?;
+
+    let mut updated = target.clone();
+    updated.artist = canonical.artist.clone();
+    updated.album = canonical.album.clone();
+    updated.track = canonical.track.clone();
+    updated.raw_metadata = Some(raw);
+    record_first_listen(&tx, &updated)?;
+    // The old names may have lost their first play, or their only one;
+    // `play_counts` drops both names' entries through its update trigger
+    refresh_first_listens(&tx, target)?;
+
+    tx.commit()?;
+    Ok(Some(updated))
+}
+
+/// Number of scrobbles excluded from stats as sleep listening
+pub fn get_sleep_flagged_count(pool: &DbPool) -> Result<i64> {
+    let conn = pool.get()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM scrobbles WHERE sleep_flagged = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+pub fn scrobble_exists(pool: &DbPool, id: i64) -> Result<bool> {
     let conn = pool.get()?;
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM scrobbles WHERE id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
 
-    if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
-            "SELECT artist, album, COUNT(*) as count FROM scrobbles
-             WHERE album IS NOT NULL AND timestamp >= ?1 AND timestamp <= ?2
-             GROUP BY artist, album ORDER BY count DESC LIMIT ?3",
-        )?;
-        let albums_iter = stmt
-            .query_map(params![start.timestamp(), end.timestamp(), limit], |row| {
-                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-            })?;
-        let albums: Vec<(String, String, i64)> = albums_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(albums)
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT artist, album, COUNT(*) as count FROM scrobbles
-             WHERE album IS NOT NULL
-             GROUP BY artist, album ORDER BY count DESC LIMIT ?1",
-        )?;
-        let albums_iter = stmt.query_map(params![limit], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        })?;
-        let albums: Vec<(String, String, i64)> = albums_iter.collect::<Result<Vec<_>, _>>()?;
-        Ok(albums)
+// Share token operations
+pub fn insert_share_token(pool: &DbPool, share: &ShareToken) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO share_tokens (token, label, scopes, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            share.token,
+            share.label,
+            share.scopes.join(","),
+            share.created_at.timestamp(),
+            share.expires_at.map(|ts| ts.timestamp()),
+        ],
+    )?;
+    Ok(())
+}
+
+fn row_to_share_token(row: &rusqlite::Row) -> rusqlite::Result<ShareToken> {
+    let scopes: String = row.get(2)?;
+    let created_ts: i64 = row.get(3)?;
+    let expires_ts: Option<i64> = row.get(4)?;
+
+    Ok(ShareToken {
+        token: row.get(0)?,
+        label: row.get(1)?,
+        scopes: scopes
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        created_at: DateTime::from_timestamp(created_ts, 0).unwrap_or_else(Utc::now),
+        // An unreadable expiry must not turn into a link that never expires
+        expires_at: expires_ts.map(|ts| DateTime::from_timestamp(ts, 0).unwrap_or_default()),
+    })
+}
+
+pub fn get_share_token(pool: &DbPool, token: &str) -> Result<Option<ShareToken>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT token, label, scopes, created_at, expires_at
+         FROM share_tokens WHERE token = ?1",
+    )?;
+
+    let mut rows = stmt.query(params![token])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row_to_share_token(row)?)),
+        None => Ok(None),
     }
 }
 
-pub fn get_scrobbles_per_day(
-    pool: &DbPool,
-    start_date: Option<DateTime<Utc>>,
-    end_date: Option<DateTime<Utc>>,
-) -> Result<Vec<(String, i64)>> {
+pub fn get_all_share_tokens(pool: &DbPool) -> Result<Vec<ShareToken>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT token, label, scopes, created_at, expires_at
+         FROM share_tokens ORDER BY created_at DESC",
+    )?;
+
+    let tokens = stmt
+        .query_map([], row_to_share_token)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tokens)
+}
+
+pub fn delete_share_token(pool: &DbPool, token: &str) -> Result<bool> {
     let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM share_tokens WHERE token = ?1", params![token])?;
+    Ok(deleted > 0)
+}
 
-    let (query, params) = if let (Some(start), Some(end)) = (start_date, end_date) {
-        (
-            "SELECT strftime('%Y-%m-%d', datetime(timestamp, 'unixepoch')) as day, COUNT(*) as count
-             FROM scrobbles
-             WHERE timestamp >= ?1 AND timestamp <= ?2
-             GROUP BY day
-             ORDER BY day ASC",
-            params![start.timestamp(), end.timestamp()],
-        )
+// User operations
+/// Map an authenticated username to a local user, creating it on first sight
+pub fn upsert_user(pool: &DbPool, username: &str) -> Result<Option<i64>> {
+    let conn = pool.get()?;
+    let now = Utc::now().timestamp();
+
+    // A read-only instance only knows the users seen while it could write
+    if is_read_only(&conn) {
+        let id = conn.query_row(
+            "SELECT id FROM users WHERE username = ?1",
+            params![username],
+            |row| row.get(0),
+        );
+        return match id {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    let id: i64 = conn.query_row(
+        "INSERT INTO users (username, created_at, last_seen_at)
+         VALUES (?1, ?2, ?2)
+         ON CONFLICT(username) DO UPDATE SET last_seen_at = ?2
+         RETURNING id",
+        params![username, now],
+        |row| row.get(0),
+    )?;
+
+    Ok(Some(id))
+}
+
+/// Get list of years that have scrobbles (sorted descending)
+pub fn get_available_years(pool: &DbPool) -> Result<Vec<i32>> {
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT DISTINCT strftime('%Y', datetime(timestamp, 'unixepoch')) as year
+         FROM scrobbles
+         ORDER BY year DESC",
+    )?;
+
+    let years = stmt
+        .query_map([], |row| {
+            let year_str: String = row.get(0)?;
+            Ok(year_str.parse::<i32>().unwrap_or(0))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(years.into_iter().filter(|&y| y > 0).collect())
+}
+
+// Database maintenance
+pub fn vacuum_database(pool: &DbPool) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute_batch("VACUUM")?;
+    Ok(())
+}
+
+pub fn analyze_database(pool: &DbPool) -> Result<()> {
+    let conn = pool.get()?;
+    conn.execute_batch("ANALYZE")?;
+    Ok(())
+}
+
+/// Copy a consistent snapshot of the live database to a new file at `dest`
+/// with SQLite's online backup API. Unlike copying the file, this can't
+/// catch a write half done
+pub fn backup_database(pool: &DbPool, dest: &std::path::Path) -> Result<()> {
+    let conn = pool.get()?;
+    let mut target = Connection::open(dest)?;
+
+    // Copy in steps, pausing so writers aren't locked out of a large database
+    let backup = rusqlite::backup::Backup::new(&conn, &mut target)?;
+    backup.run_to_completion(1024, std::time::Duration::from_millis(10), None)?;
+
+    Ok(())
+}
+
+/// Outcome of SQLite's own consistency check of the database file
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    /// "quick" skips index contents, "full" cross-checks them against tables
+    pub mode: &'static str,
+    /// What SQLite found wrong, at most 100 entries
+    pub problems: Vec<String>,
+    pub duration_ms: u128,
+}
+
+/// Run `PRAGMA quick_check`, or `PRAGMA integrity_check` when `full` is set.
+/// Corruption comes back as a report; only failing to run the check is an error
+pub fn check_integrity(pool: &DbPool, full: bool) -> Result<IntegrityReport> {
+    let conn = pool.get()?;
+    let started = std::time::Instant::now();
+
+    let pragma = if full {
+        "PRAGMA integrity_check(100)"
     } else {
-        (
-            "SELECT strftime('%Y-%m-%d', datetime(timestamp, 'unixepoch')) as day, COUNT(*) as count
-             FROM scrobbles
-             GROUP BY day
-             ORDER BY day ASC",
-            params![],
-        )
+        "PRAGMA quick_check(100)"
     };
+    let rows: Vec<String> = conn
+        .prepare_cached(pragma)?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    let problems: Vec<String> = rows.into_iter().filter(|row| row != "ok").collect();
 
-    let mut stmt = conn.prepare(query)?;
-    let rows = stmt.query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))?;
-    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    Ok(IntegrityReport {
+        ok: problems.is_empty(),
+        mode: if full { "full" } else { "quick" },
+        problems,
+        duration_ms: started.elapsed().as_millis(),
+    })
 }
 
-pub fn get_top_album_for_artist(pool: &DbPool, artist: &str) -> Result<Option<String>> {
+/// Bytes used by the database, from its page count rather than the file so
+/// it holds for any journal mode
+pub fn get_database_size(pool: &DbPool) -> Result<i64> {
     let conn = pool.get()?;
-    let mut stmt = conn.prepare(
-        "SELECT album, COUNT(*) as count FROM scrobbles
-         WHERE artist = ?1 AND album IS NOT NULL
-         GROUP BY album
-         ORDER BY count DESC
-         LIMIT 1",
+    let size: i64 = conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(size)
+}
+
+/// Storage statistics for the database file: size, per-table row counts,
+/// per-index sizes and how much of the file is free (reclaimable by VACUUM)
+pub fn get_database_stats(pool: &DbPool) -> Result<serde_json::Value> {
+    let conn = pool.get()?;
+
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let freelist_count: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+
+    let file_path: String = conn.query_row(
+        "SELECT file FROM pragma_database_list WHERE name = 'main'",
+        [],
+        |row| row.get(0),
     )?;
+    let file_size_bytes = std::fs::metadata(&file_path)
+        .map(|m| m.len() as i64)
+        .unwrap_or(page_count * page_size);
 
-    let mut rows = stmt.query(params![artist])?;
-    if let Some(row) = rows.next()? {
-        Ok(row.get(0)?)
+    let table_names = conn
+        .prepare_cached(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tables = Vec::new();
+    for name in table_names {
+        let row_count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")),
+            [],
+            |row| row.get(0),
+        )?;
+        tables.push(serde_json::json!({ "name": name, "row_count": row_count }));
+    }
+
+    let indexes = conn
+        .prepare_cached(
+            "SELECT m.name, m.tbl_name, COALESCE(SUM(s.pgsize), 0)
+             FROM sqlite_master m
+             LEFT JOIN dbstat s ON s.name = m.name
+             WHERE m.type = 'index'
+             GROUP BY m.name, m.tbl_name
+             ORDER BY m.name",
+        )?
+        .query_map([], |row| {
+            Ok(serde_json::json!({
+                "name": row.get::<_, String>(0)?,
+                "table": row.get::<_, String>(1)?,
+                "size_bytes": row.get::<_, i64>(2)?,
+            }))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fragmentation_percent = if page_count > 0 {
+        freelist_count as f64 / page_count as f64 * 100.0
     } else {
-        Ok(None)
+        0.0
+    };
+
+    Ok(serde_json::json!({
+        "file_size_bytes": file_size_bytes,
+        "page_size": page_size,
+        "page_count": page_count,
+        "freelist_count": freelist_count,
+        "fragmentation_percent": fragmentation_percent,
+        "tables": tables,
+        "indexes": indexes,
+    }))
+}
+
+/// Which scrobbles to re-attribute; criteria are combined, and at least one
+/// is required so a careless request can't rewrite the whole archive
+#[derive(Debug, Clone, Default)]
+pub struct ReattributeFilter {
+    pub source: Option<String>,
+    pub artist: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl ReattributeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.source.is_none() && self.artist.is_none() && self.start.is_none() && self.end.is_none()
     }
 }
 
-pub fn get_album_for_track(pool: &DbPool, artist: &str, track: &str) -> Result<Option<String>> {
-    let conn = pool.get()?;
-    let mut stmt = conn.prepare(
-        "SELECT album, COUNT(*) as count FROM scrobbles
-         WHERE artist = ?1 AND track = ?2 AND album IS NOT NULL
-         GROUP BY album
-         ORDER BY count DESC
-         LIMIT 1",
+/// Move scrobbles matching `filter` to `new_source` in one transaction,
+/// rolled back when `dry_run` is set. Returns `(matched, updated)`; rows that
+/// already exist under `new_source` are left alone
+pub fn reattribute_scrobbles(
+    pool: &DbPool,
+    filter: &ReattributeFilter,
+    new_source: &str,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    if filter.is_empty() {
+        return Err(anyhow::anyhow!("Re-attribution needs at least one filter"));
+    }
+
+    // Rows already under the new source have nothing to change
+    let mut conditions = vec!["source <> ?"];
+    let mut values: Vec<rusqlite::types::Value> = vec![new_source.to_string().into()];
+
+    if let Some(source) = &filter.source {
+        conditions.push("source = ?");
+        values.push(source.clone().into());
+    }
+    if let Some(artist) = &filter.artist {
+        conditions.push("artist = ?");
+        values.push(artist.clone().into());
+    }
+    if let Some(start) = filter.start {
+        conditions.push("timestamp >= ?");
+        values.push(start.timestamp().into());
+    }
+    if let Some(end) = filter.end {
+        conditions.push("timestamp <= ?");
+        values.push(end.timestamp().into());
+    }
+    let where_clause = conditions.join(" AND ");
+
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let matched: i64 = tx.query_row(
+        &format!("SELECT COUNT(*) FROM scrobbles WHERE {}", where_clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
     )?;
 
-    let mut rows = stmt.query(params![artist, track])?;
-    if let Some(row) = rows.next()? {
-        Ok(row.get(0)?)
+    let mut update_values: Vec<rusqlite::types::Value> = vec![new_source.to_string().into()];
+    update_values.extend(values);
+    let updated = tx.execute(
+        &format!(
+            "UPDATE OR IGNORE scrobbles SET source = ? WHERE {}",
+            where_clause
+        ),
+        params_from_iter(update_values),
+    )?;
+
+    if dry_run {
+        tx.rollback()?;
     } else {
-        Ok(None)
+        tx.commit()?;
     }
+
+    Ok((matched as usize, updated))
 }
 
-// Helper function to safely convert database timestamps
-fn parse_timestamp_with_warning(ts: i64, field_name: &str, id: i64) -> DateTime<Utc> {
-    DateTime::from_timestamp(ts, 0).unwrap_or_else(|| {
-        tracing::warn!(
-            "Invalid {} timestamp {} in sync_config id {}, using current time",
-            field_name,
-            ts,
-            id
-        );
-        Utc::now()
-    })
+/// Scrobbles to move in time: one source's history over an inclusive range
+#[derive(Debug, Clone)]
+pub struct TimeShiftFilter {
+    pub source: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
 }
 
-// Sync configuration database operations
-pub fn insert_sync_config(pool: &DbPool, config: &SyncConfig) -> Result<i64> {
-    let conn = pool.get()?;
-    let now = Utc::now().timestamp();
+/// Move the timestamps of scrobbles matching `filter` by `offset_seconds` in
+/// one transaction, rolled back when `dry_run` is set. Returns
+/// `(matched, updated)`; rows whose shifted listen already exists are left
+/// alone. Applied shifts are recorded in the maintenance log.
+///
+/// Tables keyed by time go stale with the move: first listens are rebuilt,
+/// charts from the earliest week touched are dropped for the next run to
+/// record again, sleep detections over either range are dropped to be
+/// scanned for again (confirmed plays keep their flag), and the daily
+/// summaries' trigger drops those from the earliest day touched
+pub fn shift_scrobble_timestamps(
+    pool: &DbPool,
+    filter: &TimeShiftFilter,
+    offset_seconds: i64,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    if offset_seconds == 0 {
+        return Err(anyhow::anyhow!("Time shift needs a non-zero offset"));
+    }
+    if filter.start > filter.end {
+        return Err(anyhow::anyhow!("Time shift range starts after it ends"));
+    }
 
-    conn.execute(
-        "INSERT INTO sync_configs (source, username, api_key, token, sync_interval_minutes, enabled, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
-         ON CONFLICT(source, username) DO UPDATE SET
-            api_key = ?3,
-            token = ?4,
-            sync_interval_minutes = ?5,
-            enabled = ?6,
-            updated_at = ?8",
-        params![
-            config.source,
-            config.username,
-            config.api_key,
-            config.token,
-            config.sync_interval_minutes,
-            if config.enabled { 1 } else { 0 },
-            now,
-            now,
-        ],
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    // Move rows furthest in the shift direction first, so one scrobble never
+    // lands on another that is about to move out of the way
+    let order = if offset_seconds > 0 { "DESC" } else { "ASC" };
+    let ids: Vec<i64> = tx
+        .prepare_cached(&format!(
+            "SELECT id FROM scrobbles
+             WHERE source = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+             ORDER BY timestamp {}",
+            order
+        ))?
+        .query_map(
+            params![
+                filter.source,
+                filter.start.timestamp(),
+                filter.end.timestamp()
+            ],
+            |row| row.get(0),
+        )?
+        .collect::<Result<_, _>>()?;
+
+    let mut updated = 0;
+    {
+        let mut stmt = tx.prepare_cached(
+            "UPDATE OR IGNORE scrobbles SET timestamp = timestamp + ?1 WHERE id = ?2",
+        )?;
+        for id in &ids {
+            updated += stmt.execute(params![offset_seconds, id])?;
+        }
+    }
+
+    if dry_run {
+        tx.rollback()?;
+        return Ok((ids.len(), updated));
+    }
+
+    tx.execute_batch(REBUILD_FIRST_LISTENS_SQL)?;
+    let earliest = filter
+        .start
+        .timestamp()
+        .min(filter.start.timestamp().saturating_add(offset_seconds));
+    let latest = filter
+        .end
+        .timestamp()
+        .max(filter.end.timestamp().saturating_add(offset_seconds));
+    // A week starts at most six days, plus a timezone's offset, before
+    tx.execute(
+        "DELETE FROM chart_snapshots WHERE week_start >= date(?1, 'unixepoch', '-7 days')",
+        params![earliest],
+    )?;
+    tx.execute(
+        "DELETE FROM chart_weeks WHERE week_start >= date(?1, 'unixepoch', '-7 days')",
+        params![earliest],
+    )?;
+    tx.execute(
+        "DELETE FROM sleep_detections WHERE end_ts >= ?1 AND start_ts <= ?2",
+        params![earliest, latest],
+    )?;
+    log_maintenance(
+        &tx,
+        "shift_timestamps",
+        &serde_json::json!({
+            "source": filter.source,
+            "start": filter.start.to_rfc3339(),
+            "end": filter.end.to_rfc3339(),
+            "offset_seconds": offset_seconds,
+            "matched": ids.len(),
+        }),
+        updated,
+    )?;
+    tx.commit()?;
+
+    Ok((ids.len(), updated))
+}
+
+/// An album credited to a single album artist, so its scrobbles group
+/// together whoever performs each track
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Compilation {
+    pub album: String,
+    pub album_artist: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Credit every scrobble of `album` (names compared case-insensitively) to
+/// `album_artist`, now and for scrobbles stored later, in one transaction
+/// rolled back when `dry_run` is set. Returns `(matched, updated)`
+pub fn mark_compilation(
+    pool: &DbPool,
+    album: &str,
+    album_artist: &str,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO compilations (album, album_artist, created_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(album) DO UPDATE SET album_artist = excluded.album_artist",
+        params![album, album_artist, Utc::now().timestamp()],
+    )?;
+    let matched: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM scrobbles WHERE album = ?1 COLLATE NOCASE",
+        params![album],
+        |row| row.get(0),
+    )?;
+    let updated = tx.execute(
+        "UPDATE scrobbles SET album_artist = ?2
+         WHERE album = ?1 COLLATE NOCASE AND album_artist IS NOT ?2",
+        params![album, album_artist],
     )?;
 
-    Ok(conn.last_insert_rowid())
-}
+    if dry_run {
+        tx.rollback()?;
+        return Ok((matched as usize, updated));
+    }
 
-pub fn get_sync_config(pool: &DbPool, id: i64) -> Result<Option<SyncConfig>> {
-    let conn = pool.get()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
-         FROM sync_configs WHERE id = ?1",
+    log_maintenance(
+        &tx,
+        "mark_compilation",
+        &serde_json::json!({
+            "album": album,
+            "album_artist": album_artist,
+            "matched": matched,
+        }),
+        updated,
     )?;
+    tx.commit()?;
 
-    let mut rows = stmt.query(params![id])?;
-    if let Some(row) = rows.next()? {
-        let config_id: i64 = row.get(0)?;
-        let created_ts: i64 = row.get(8)?;
-        let updated_ts: i64 = row.get(9)?;
-        let last_sync_ts: Option<i64> = row.get(6)?;
-
-        Ok(Some(SyncConfig {
-            id: Some(config_id),
-            source: row.get(1)?,
-            username: row.get(2)?,
-            api_key: row.get(3)?,
-            token: row.get(4)?,
-            sync_interval_minutes: row.get(5)?,
-            last_sync_timestamp: last_sync_ts.and_then(|ts| {
-                DateTime::from_timestamp(ts, 0).or_else(|| {
-                    tracing::warn!(
-                        "Invalid last_sync_timestamp {} in sync_config id {}",
-                        ts,
-                        config_id
-                    );
-                    None
-                })
-            }),
-            enabled: row.get::<_, i32>(7)? != 0,
-            created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
-            updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
-        }))
-    } else {
-        Ok(None)
-    }
+    Ok((matched as usize, updated))
 }
 
-pub fn get_all_sync_configs(pool: &DbPool) -> Result<Vec<SyncConfig>> {
+pub fn get_compilations(pool: &DbPool) -> Result<Vec<Compilation>> {
     let conn = pool.get()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
-         FROM sync_configs ORDER BY created_at DESC",
+    let mut stmt = conn.prepare_cached(
+        "SELECT album, album_artist, created_at FROM compilations ORDER BY album COLLATE NOCASE",
     )?;
-
-    let configs = stmt
+    let compilations = stmt
         .query_map([], |row| {
-            let config_id: i64 = row.get(0)?;
-            let created_ts: i64 = row.get(8)?;
-            let updated_ts: i64 = row.get(9)?;
-            let last_sync_ts: Option<i64> = row.get(6)?;
-
-            Ok(SyncConfig {
-                id: Some(config_id),
-                source: row.get(1)?,
-                username: row.get(2)?,
-                api_key: row.get(3)?,
-                token: row.get(4)?,
-                sync_interval_minutes: row.get(5)?,
-                last_sync_timestamp: last_sync_ts.and_then(|ts| {
-                    DateTime::from_timestamp(ts, 0).or_else(|| {
-                        tracing::warn!(
-                            "Invalid last_sync_timestamp {} in sync_config id {}",
-                            ts,
-                            config_id
-                        );
-                        None
-                    })
-                }),
-                enabled: row.get::<_, i32>(7)? != 0,
-                created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
-                updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
+            Ok(Compilation {
+                album: row.get(0)?,
+                album_artist: row.get(1)?,
+                created_at: DateTime::from_timestamp(row.get(2)?, 0).unwrap_or_default(),
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
+    Ok(compilations)
+}
 
-    Ok(configs)
+const ARCHIVED_COLUMNS: &str = "id, artist, album, track, timestamp, source, source_id, ms_played, \
+     skipped, raw_metadata, source_metadata, album_artist, media_type, sleep_flagged";
+
+/// Move scrobbles older than `before` to `scrobbles_archive`, in one
+/// transaction rolled back when `dry_run` is set. Queries on `scrobbles` no
+/// longer see them, but `first_listens`, the tables keyed by artist or track
+/// and all-time play counts still do. Returns how many scrobbles were archived
+pub fn archive_scrobbles(pool: &DbPool, before: DateTime<Utc>, dry_run: bool) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        &format!(
+            "INSERT INTO scrobbles_archive ({cols}, archived_at)
+             SELECT {cols}, ?2 FROM scrobbles WHERE timestamp < ?1",
+            cols = ARCHIVED_COLUMNS
+        ),
+        params![before.timestamp(), Utc::now().timestamp()],
+    )?;
+    let archived = tx.execute(
+        "DELETE FROM scrobbles WHERE timestamp < ?1",
+        params![before.timestamp()],
+    )?;
+
+    if dry_run {
+        tx.rollback()?;
+        return Ok(archived);
+    }
+
+    log_maintenance(
+        &tx,
+        "archive_scrobbles",
+        &serde_json::json!({ "before": before.to_rfc3339() }),
+        archived,
+    )?;
+    tx.commit()?;
+
+    Ok(archived)
 }
 
-pub fn get_enabled_sync_configs(pool: &DbPool) -> Result<Vec<SyncConfig>> {
-    let conn = pool.get()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, source, username, api_key, token, sync_interval_minutes, last_sync_timestamp, enabled, created_at, updated_at
-         FROM sync_configs WHERE enabled = 1 ORDER BY created_at DESC",
+/// Move every archived scrobble back to `scrobbles`, rolled back when
+/// `dry_run` is set. An archived listen that was stored again since is
+/// dropped. Returns how many scrobbles were restored
+pub fn restore_archived_scrobbles(pool: &DbPool, dry_run: bool) -> Result<usize> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+
+    let restored = tx.execute(
+        &format!(
+            "INSERT OR IGNORE INTO scrobbles ({cols})
+             SELECT {cols} FROM scrobbles_archive",
+            cols = ARCHIVED_COLUMNS
+        ),
+        [],
     )?;
+    tx.execute("DELETE FROM scrobbles_archive", [])?;
+    // Cached counts already had the archived plays, which the insert trigger
+    // just added again
+    tx.execute("DELETE FROM play_counts", [])?;
 
-    let configs = stmt
-        .query_map([], |row| {
-            let config_id: i64 = row.get(0)?;
-            let created_ts: i64 = row.get(8)?;
-            let updated_ts: i64 = row.get(9)?;
-            let last_sync_ts: Option<i64> = row.get(6)?;
+    if dry_run {
+        tx.rollback()?;
+        return Ok(restored);
+    }
 
-            Ok(SyncConfig {
-                id: Some(config_id),
-                source: row.get(1)?,
-                username: row.get(2)?,
-                api_key: row.get(3)?,
-                token: row.get(4)?,
-                sync_interval_minutes: row.get(5)?,
-                last_sync_timestamp: last_sync_ts.and_then(|ts| {
-                    DateTime::from_timestamp(ts, 0).or_else(|| {
-                        tracing::warn!(
-                            "Invalid last_sync_timestamp {} in sync_config id {}",
-                            ts,
-                            config_id
-                        );
-                        None
-                    })
-                }),
-                enabled: row.get::<_, i32>(7)? != 0,
-                created_at: parse_timestamp_with_warning(created_ts, "created_at", config_id),
-                updated_at: parse_timestamp_with_warning(updated_ts, "updated_at", config_id),
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+    log_maintenance(&tx, "restore_archive", &serde_json::json!({}), restored)?;
+    tx.commit()?;
 
-    Ok(configs)
+    Ok(restored)
 }
 
-pub fn update_sync_timestamp(pool: &DbPool, id: i64, timestamp: DateTime<Utc>) -> Result<()> {
+/// Archived scrobbles and the oldest and newest of them
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ArchiveStats {
+    pub scrobbles: i64,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+pub fn get_archive_stats(pool: &DbPool) -> Result<ArchiveStats> {
     let conn = pool.get()?;
+    let (scrobbles, oldest, newest): (i64, Option<i64>, Option<i64>) = conn.query_row(
+        "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM scrobbles_archive",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok(ArchiveStats {
+        scrobbles,
+        oldest: oldest.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        newest: newest.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+    })
+}
+
+/// A bulk correction applied to the scrobble history
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MaintenanceLogEntry {
+    pub id: i64,
+    pub action: String,
+    pub details: serde_json::Value,
+    /// Scrobbles changed
+    pub affected: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+fn log_maintenance(
+    conn: &Connection,
+    action: &str,
+    details: &serde_json::Value,
+    affected: usize,
+) -> Result<()> {
     conn.execute(
-        "UPDATE sync_configs SET last_sync_timestamp = ?1, updated_at = ?2 WHERE id = ?3",
-        params![timestamp.timestamp(), Utc::now().timestamp(), id],
+        "INSERT INTO maintenance_log (action, details, affected, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            action,
+            details.to_string(),
+            affected as i64,
+            Utc::now().timestamp()
+        ],
     )?;
     Ok(())
 }
 
-pub fn delete_sync_config(pool: &DbPool, id: i64) -> Result<()> {
+/// Most recent maintenance log entries first
+pub fn get_maintenance_log(pool: &DbPool, limit: i64) -> Result<Vec<MaintenanceLogEntry>> {
     let conn = pool.get()?;
-    conn.execute("DELETE FROM sync_configs WHERE id = ?1", params![id])?;
-    Ok(())
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, action, details, affected, created_at FROM maintenance_log
+         ORDER BY id DESC LIMIT ?1",
+    )?;
+    let entries = stmt
+        .query_map(params![limit], |row| {
+            let details: String = row.get(2)?;
+            Ok(MaintenanceLogEntry {
+                id: row.get(0)?,
+                action: row.get(1)?,
+                details: serde_json::from_str(&details).unwrap_or(serde_json::Value::Null),
+                affected: row.get::<_, i64>(3)? as usize,
+                created_at: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
 }
 
-/// Get list of years that have scrobbles (sorted descending)
-pub fn get_available_years(pool: &DbPool) -> Result<Vec<i32>> {
+/// Plays of an artist, album or track with its first and last play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayCount {
+    pub plays: i64,
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+}
+
+fn row_to_play_count(row: &rusqlite::Row) -> rusqlite::Result<PlayCount> {
+    Ok(PlayCount {
+        plays: row.get(0)?,
+        first_timestamp: row.get(1)?,
+        last_timestamp: row.get(2)?,
+    })
+}
+
+/// Scrobbles filter for an entity named as in `first_listens`, with its keys
+fn entity_filter<'a>(
+    entity_type: &str,
+    artist: &'a str,
+    name: &'a str,
+) -> Result<(&'static str, Vec<&'a str>)> {
+    match entity_type {
+        "artist" => Ok(("artist = ?1", vec![artist])),
+        "track" => Ok(("artist = ?1 AND track = ?2", vec![artist, name])),
+        "album" => Ok((
+            "COALESCE(album_artist, artist) = ?1 AND album = ?2",
+            vec![artist, name],
+        )),
+        other => Err(anyhow::anyhow!("Unknown entity type {}", other)),
+    }
+}
+
+/// Whether an entity, named as in `entity_play_count`, has any scrobble at
+/// all, archived ones included
+pub fn entity_exists(pool: &DbPool, entity_type: &str, artist: &str, name: &str) -> Result<bool> {
+    let (filter, keys) = entity_filter(entity_type, artist, name)?;
     let conn = pool.get()?;
+    let exists = conn
+        .prepare_cached(&format!(
+            "SELECT EXISTS(SELECT 1 FROM scrobbles WHERE {filter})
+                 OR EXISTS(SELECT 1 FROM scrobbles_archive WHERE {filter})"
+        ))?
+        .query_row(params_from_iter(keys), |row| row.get(0))?;
+    Ok(exists)
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT DISTINCT strftime('%Y', datetime(timestamp, 'unixepoch')) as year
-         FROM scrobbles
-         ORDER BY year DESC",
-    )?;
+/// Whole-history play count of an entity, as `first_listens` names them:
+/// `name` is empty for artists, the track title for tracks and the album for
+/// albums, whose `artist` is the album artist. Archived scrobbles count too.
+/// Read from `play_counts` when cached, counted and cached otherwise
+pub fn entity_play_count(
+    conn: &mut Connection,
+    entity_type: &str,
+    artist: &str,
+    name: &str,
+) -> Result<PlayCount> {
+    let cached = conn
+        .prepare_cached(
+            "SELECT plays, first_timestamp, last_timestamp FROM play_counts
+             WHERE entity_type = ?1 AND artist = ?2 AND name = ?3",
+        )?
+        .query_row(params![entity_type, artist, name], row_to_play_count);
+    match cached {
+        Ok(count) => return Ok(count),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+        Err(e) => return Err(e.into()),
+    }
 
-    let years = stmt
-        .query_map([], |row| {
-            let year_str: String = row.get(0)?;
-            Ok(year_str.parse::<i32>().unwrap_or(0))
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+    let (filter, keys) = entity_filter(entity_type, artist, name)?;
+    let count_sql = format!(
+        "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM (
+            SELECT timestamp FROM scrobbles WHERE {filter}
+            UNION ALL
+            SELECT timestamp FROM scrobbles_archive WHERE {filter}
+        )"
+    );
 
-    Ok(years.into_iter().filter(|&y| y > 0).collect())
+    // A read-only instance counts every time instead
+    if is_read_only(conn) {
+        return Ok(conn
+            .prepare_cached(&count_sql)?
+            .query_row(params_from_iter(keys), row_to_play_count)?);
+    }
+
+    // Count and store in one write transaction, so no scrobble is inserted
+    // between the two and missed by the cache
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let count = tx
+        .prepare_cached(&count_sql)?
+        .query_row(params_from_iter(keys), row_to_play_count)?;
+    if let (Some(first), Some(last)) = (count.first_timestamp, count.last_timestamp) {
+        tx.execute(
+            "INSERT OR REPLACE INTO play_counts
+                (entity_type, artist, name, plays, first_timestamp, last_timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![entity_type, artist, name, count.plays, first, last],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(count)
 }
 
 // Artist-specific queries
@@ -633,7 +5053,7 @@ pub fn get_artist_stats(
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
 ) -> Result<serde_json::Value> {
-    let conn = pool.get()?;
+    let mut conn = pool.get()?;
 
     let (where_clause, params_vec) = if let (Some(start), Some(end)) = (start_date, end_date) {
         (
@@ -651,11 +5071,18 @@ pub fn get_artist_stats(
         )
     };
 
-    let total_scrobbles: i64 = conn.query_row(
-        &format!("SELECT COUNT(*) FROM scrobbles {}", where_clause),
-        rusqlite::params_from_iter(params_vec.iter()),
-        |row| row.get(0),
-    )?;
+    let count = if start_date.is_none() && end_date.is_none() {
+        entity_play_count(&mut conn, "artist", artist, "")?
+    } else {
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM scrobbles {}",
+                where_clause
+            ),
+            rusqlite::params_from_iter(params_vec.iter()),
+            row_to_play_count,
+        )?
+    };
 
     let unique_tracks: i64 = conn.query_row(
         &format!(
@@ -675,29 +5102,13 @@ pub fn get_artist_stats(
         |row| row.get(0),
     )?;
 
-    let first_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MIN(timestamp) FROM scrobbles {}", where_clause),
-            rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
-
-    let last_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MAX(timestamp) FROM scrobbles {}", where_clause),
-            rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
-
     Ok(serde_json::json!({
         "artist": artist,
-        "total_scrobbles": total_scrobbles,
+        "total_scrobbles": count.plays,
         "unique_tracks": unique_tracks,
         "unique_albums": unique_albums,
-        "first_scrobble": first_scrobble,
-        "last_scrobble": last_scrobble,
+        "first_scrobble": count.first_timestamp,
+        "last_scrobble": count.last_timestamp,
     }))
 }
 
@@ -711,7 +5122,7 @@ pub fn get_artist_top_tracks(
     let conn = pool.get()?;
 
     if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT track, COUNT(*) as count FROM scrobbles
              WHERE artist = ?1 AND timestamp >= ?2 AND timestamp <= ?3
              GROUP BY track ORDER BY count DESC LIMIT ?4",
@@ -722,7 +5133,7 @@ pub fn get_artist_top_tracks(
         )?;
         Ok(rows.collect::<Result<Vec<_>, _>>()?)
     } else {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT track, COUNT(*) as count FROM scrobbles
              WHERE artist = ?1
              GROUP BY track ORDER BY count DESC LIMIT ?2",
@@ -742,7 +5153,7 @@ pub fn get_artist_top_albums(
     let conn = pool.get()?;
 
     if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT album, COUNT(*) as count FROM scrobbles
              WHERE artist = ?1 AND album IS NOT NULL AND timestamp >= ?2 AND timestamp <= ?3
              GROUP BY album ORDER BY count DESC LIMIT ?4",
@@ -753,7 +5164,7 @@ pub fn get_artist_top_albums(
         )?;
         Ok(rows.collect::<Result<Vec<_>, _>>()?)
     } else {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT album, COUNT(*) as count FROM scrobbles
              WHERE artist = ?1 AND album IS NOT NULL
              GROUP BY album ORDER BY count DESC LIMIT ?2",
@@ -791,7 +5202,7 @@ pub fn get_artist_scrobbles_over_time(
         )
     };
 
-    let mut stmt = conn.prepare(query)?;
+    let mut stmt = conn.prepare_cached(query)?;
     let rows = stmt.query_map(params_list, |row| Ok((row.get(0)?, row.get(1)?)))?;
     Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
@@ -804,11 +5215,11 @@ pub fn get_album_stats(
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
 ) -> Result<serde_json::Value> {
-    let conn = pool.get()?;
+    let mut conn = pool.get()?;
 
     let (where_clause, params_vec) = if let (Some(start), Some(end)) = (start_date, end_date) {
         (
-            "WHERE artist = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4",
+            "WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4",
             vec![
                 rusqlite::types::Value::Text(artist.to_string()),
                 rusqlite::types::Value::Text(album.to_string()),
@@ -818,7 +5229,7 @@ pub fn get_album_stats(
         )
     } else {
         (
-            "WHERE artist = ?1 AND album = ?2",
+            "WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2",
             vec![
                 rusqlite::types::Value::Text(artist.to_string()),
                 rusqlite::types::Value::Text(album.to_string()),
@@ -826,11 +5237,18 @@ pub fn get_album_stats(
         )
     };
 
-    let total_scrobbles: i64 = conn.query_row(
-        &format!("SELECT COUNT(*) FROM scrobbles {}", where_clause),
-        rusqlite::params_from_iter(params_vec.iter()),
-        |row| row.get(0),
-    )?;
+    let count = if start_date.is_none() && end_date.is_none() {
+        entity_play_count(&mut conn, "album", artist, album)?
+    } else {
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM scrobbles {}",
+                where_clause
+            ),
+            rusqlite::params_from_iter(params_vec.iter()),
+            row_to_play_count,
+        )?
+    };
 
     let unique_tracks: i64 = conn.query_row(
         &format!(
@@ -841,29 +5259,13 @@ pub fn get_album_stats(
         |row| row.get(0),
     )?;
 
-    let first_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MIN(timestamp) FROM scrobbles {}", where_clause),
-            rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
-
-    let last_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MAX(timestamp) FROM scrobbles {}", where_clause),
-            rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
-
     Ok(serde_json::json!({
         "artist": artist,
         "album": album,
-        "total_scrobbles": total_scrobbles,
+        "total_scrobbles": count.plays,
         "unique_tracks": unique_tracks,
-        "first_scrobble": first_scrobble,
-        "last_scrobble": last_scrobble,
+        "first_scrobble": count.first_timestamp,
+        "last_scrobble": count.last_timestamp,
     }))
 }
 
@@ -877,9 +5279,9 @@ pub fn get_album_tracks(
     let conn = pool.get()?;
 
     if let (Some(start), Some(end)) = (start_date, end_date) {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT track, COUNT(*) as count FROM scrobbles
-             WHERE artist = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4
+             WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4
              GROUP BY track ORDER BY count DESC",
         )?;
         let rows = stmt.query_map(
@@ -888,9 +5290,9 @@ pub fn get_album_tracks(
         )?;
         Ok(rows.collect::<Result<Vec<_>, _>>()?)
     } else {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT track, COUNT(*) as count FROM scrobbles
-             WHERE artist = ?1 AND album = ?2
+             WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2
              GROUP BY track ORDER BY count DESC",
         )?;
         let rows = stmt.query_map(params![artist, album], |row| Ok((row.get(0)?, row.get(1)?)))?;
@@ -898,6 +5300,19 @@ pub fn get_album_tracks(
     }
 }
 
+/// Distinct tracks ever scrobbled from each album, keyed by album artist and
+/// album
+pub fn get_album_track_counts(pool: &DbPool) -> Result<HashMap<(String, String), i64>> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT COALESCE(album_artist, artist), album, COUNT(DISTINCT track) FROM scrobbles
+         WHERE album IS NOT NULL AND media_type = 'music'
+         GROUP BY 1, album",
+    )?;
+    let rows = stmt.query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?;
+    Ok(rows.collect::<Result<HashMap<_, _>, _>>()?)
+}
+
 pub fn get_album_scrobbles_over_time(
     pool: &DbPool,
     artist: &str,
@@ -911,7 +5326,7 @@ pub fn get_album_scrobbles_over_time(
         (
             "SELECT strftime('%Y-%m-%d', datetime(timestamp, 'unixepoch')) as day, COUNT(*) as count
              FROM scrobbles
-             WHERE artist = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4
+             WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2 AND timestamp >= ?3 AND timestamp <= ?4
              GROUP BY day
              ORDER BY day ASC",
             params![artist, album, start.timestamp(), end.timestamp()],
@@ -920,14 +5335,14 @@ pub fn get_album_scrobbles_over_time(
         (
             "SELECT strftime('%Y-%m-%d', datetime(timestamp, 'unixepoch')) as day, COUNT(*) as count
              FROM scrobbles
-             WHERE artist = ?1 AND album = ?2
+             WHERE COALESCE(album_artist, artist) = ?1 AND album = ?2
              GROUP BY day
              ORDER BY day ASC",
             params![artist, album],
         )
     };
 
-    let mut stmt = conn.prepare(query)?;
+    let mut stmt = conn.prepare_cached(query)?;
     let rows = stmt.query_map(params_list, |row| Ok((row.get(0)?, row.get(1)?)))?;
     Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
@@ -940,7 +5355,7 @@ pub fn get_track_stats(
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
 ) -> Result<serde_json::Value> {
-    let conn = pool.get()?;
+    let mut conn = pool.get()?;
 
     let (where_clause, params_vec) = if let (Some(start), Some(end)) = (start_date, end_date) {
         (
@@ -962,27 +5377,18 @@ pub fn get_track_stats(
         )
     };
 
-    let total_scrobbles: i64 = conn.query_row(
-        &format!("SELECT COUNT(*) FROM scrobbles {}", where_clause),
-        rusqlite::params_from_iter(params_vec.iter()),
-        |row| row.get(0),
-    )?;
-
-    let first_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MIN(timestamp) FROM scrobbles {}", where_clause),
-            rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
-
-    let last_scrobble: Option<i64> = conn
-        .query_row(
-            &format!("SELECT MAX(timestamp) FROM scrobbles {}", where_clause),
+    let count = if start_date.is_none() && end_date.is_none() {
+        entity_play_count(&mut conn, "track", artist, track)?
+    } else {
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM scrobbles {}",
+                where_clause
+            ),
             rusqlite::params_from_iter(params_vec.iter()),
-            |row| row.get(0),
-        )
-        .ok();
+            row_to_play_count,
+        )?
+    };
 
     // Get most common album for this track
     let album: Option<String> = conn
@@ -1002,9 +5408,9 @@ pub fn get_track_stats(
         "artist": artist,
         "track": track,
         "album": album,
-        "total_scrobbles": total_scrobbles,
-        "first_scrobble": first_scrobble,
-        "last_scrobble": last_scrobble,
+        "total_scrobbles": count.plays,
+        "first_scrobble": count.first_timestamp,
+        "last_scrobble": count.last_timestamp,
     }))
 }
 
@@ -1037,7 +5443,7 @@ pub fn get_track_scrobbles_over_time(
         )
     };
 
-    let mut stmt = conn.prepare(query)?;
+    let mut stmt = conn.prepare_cached(query)?;
     let rows = stmt.query_map(params_list, |row| Ok((row.get(0)?, row.get(1)?)))?;
     Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
@@ -1,24 +1,23 @@
 use super::*;
-use crate::models::Scrobble;
+use crate::models::{
+    Annotation, AnnotationKind, DailySummary, DetectionStatus, IgnoreRule, ImportJob, ImportStatus,
+    MediaType, MediaTypeRule, Note, RatingKind, Scrobble, ShareToken, SleepDetection,
+};
+use crate::normalizer::{Normalizer, Rule};
+use crate::test_utils::setup_pool;
+use chrono_tz::Tz;
 use tempfile::NamedTempFile;
 
-fn setup_test_db() -> (DbPool, NamedTempFile) {
-    let temp_file = NamedTempFile::new().unwrap();
-    let pool = create_pool(temp_file.path().to_str().unwrap()).unwrap();
-    init_database(&pool).unwrap();
-    (pool, temp_file)
-}
-
 #[test]
 fn test_database_init() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
     let count = get_scrobbles_count(&pool).unwrap();
     assert_eq!(count, 0);
 }
 
 #[test]
 fn test_insert_scrobble() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     let scrobble = Scrobble::new(
         "Test Artist".to_string(),
@@ -36,7 +35,7 @@ fn test_insert_scrobble() {
 
 #[test]
 fn test_get_scrobbles() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     for i in 0..5 {
         let scrobble = Scrobble::new(
@@ -54,7 +53,7 @@ fn test_get_scrobbles() {
 
 #[test]
 fn test_duplicate_prevention() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     let timestamp = chrono::Utc::now();
     let scrobble = Scrobble::new(
@@ -73,7 +72,7 @@ fn test_duplicate_prevention() {
 
 #[test]
 fn test_top_artists() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     use std::thread;
     use std::time::Duration;
@@ -117,7 +116,7 @@ fn test_top_artists() {
 fn test_sync_config_crud() {
     use crate::models::SyncConfig;
 
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     // Create a sync config
     let config = SyncConfig::new("lastfm".to_string(), "testuser".to_string(), 60)
@@ -156,7 +155,7 @@ fn test_sync_config_crud() {
 fn test_sync_config_unique_constraint() {
     use crate::models::SyncConfig;
 
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     // Create a sync config
     let config1 = SyncConfig::new("lastfm".to_string(), "testuser".to_string(), 60)
@@ -180,7 +179,7 @@ fn test_sync_config_unique_constraint() {
 fn test_sync_config_disabled() {
     use crate::models::SyncConfig;
 
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     // Create enabled config
     let config1 = SyncConfig::new("lastfm".to_string(), "user1".to_string(), 60);
@@ -200,3 +199,1464 @@ fn test_sync_config_disabled() {
     assert_eq!(enabled_configs.len(), 1);
     assert_eq!(enabled_configs[0].source, "lastfm");
 }
+
+#[test]
+fn test_scrobbles_per_bucket_zero_fills_gaps() {
+    use chrono::TimeZone;
+
+    let (pool, _temp_file) = setup_pool();
+
+    for (day, hour) in [(1, 10), (1, 11), (3, 9)] {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            format!("Track {}-{}", day, hour),
+            chrono::Utc
+                .with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+                .unwrap(),
+            "test".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let start = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let end = chrono::Utc.with_ymd_and_hms(2024, 1, 4, 0, 0, 0).unwrap();
+
+    let days =
+        get_scrobbles_per_bucket(&pool, TimeBucket::Day, Some(start), Some(end), Tz::UTC).unwrap();
+    assert_eq!(
+        days,
+        vec![
+            ("2024-01-01".to_string(), 2),
+            ("2024-01-02".to_string(), 0),
+            ("2024-01-03".to_string(), 1),
+            ("2024-01-04".to_string(), 0),
+        ]
+    );
+
+    // Without a range the bounds come from the data itself
+    let hours = get_scrobbles_per_bucket(&pool, TimeBucket::Hour, None, None, Tz::UTC).unwrap();
+    assert_eq!(hours.len(), 48);
+    assert_eq!(hours[0], ("2024-01-01T10:00".to_string(), 1));
+    assert_eq!(hours[47], ("2024-01-03T09:00".to_string(), 1));
+
+    // 2024-01-01 is a Monday, so everything lands in a single week
+    let weeks = get_scrobbles_per_bucket(&pool, TimeBucket::Week, None, None, Tz::UTC).unwrap();
+    assert_eq!(weeks, vec![("2024-W01".to_string(), 3)]);
+
+    let months = get_scrobbles_per_bucket(&pool, TimeBucket::Month, None, None, Tz::UTC).unwrap();
+    assert_eq!(months, vec![("2024-01".to_string(), 3)]);
+}
+
+#[test]
+fn test_scrobbles_per_bucket_uses_local_days() {
+    use chrono::TimeZone;
+
+    let (pool, _temp_file) = setup_pool();
+
+    // 03:00 UTC on Jan 2 is still Jan 1 in New York (UTC-5)
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "Late Night".to_string(),
+        chrono::Utc.with_ymd_and_hms(2024, 1, 2, 3, 0, 0).unwrap(),
+        "test".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+
+    let tz: Tz = "America/New_York".parse().unwrap();
+    let start = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 5, 0, 0).unwrap();
+    let end = chrono::Utc.with_ymd_and_hms(2024, 1, 3, 4, 59, 59).unwrap();
+
+    let days =
+        get_scrobbles_per_bucket(&pool, TimeBucket::Day, Some(start), Some(end), tz).unwrap();
+    assert_eq!(
+        days,
+        vec![("2024-01-01".to_string(), 1), ("2024-01-02".to_string(), 0),]
+    );
+
+    let utc_days =
+        get_scrobbles_per_bucket(&pool, TimeBucket::Day, Some(start), Some(end), Tz::UTC).unwrap();
+    assert_eq!(utc_days[1], ("2024-01-02".to_string(), 1));
+}
+
+#[test]
+fn test_week_buckets_match_iso_weeks_across_new_year() {
+    use chrono::TimeZone;
+
+    let (pool, _temp_file) = setup_pool();
+
+    // Dec 30, 2024 to Jan 5, 2025 is ISO week 1 of 2025
+    for (day, track) in [(28, "Saturday"), (30, "Monday"), (31, "Tuesday")] {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            track.to_string(),
+            chrono::Utc
+                .with_ymd_and_hms(2024, 12, day, 12, 0, 0)
+                .unwrap(),
+            "test".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "New Year".to_string(),
+        chrono::Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+        "test".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+
+    let weeks = get_scrobbles_per_bucket(&pool, TimeBucket::Week, None, None, Tz::UTC).unwrap();
+    assert_eq!(
+        weeks,
+        vec![("2024-W52".to_string(), 1), ("2025-W01".to_string(), 3)]
+    );
+}
+
+#[test]
+fn test_scrobbles_per_bucket_across_dst_change() {
+    use chrono::TimeZone;
+
+    let (pool, _temp_file) = setup_pool();
+
+    // Paris switches to summer time on 2024-03-31 at 02:00 local (01:00 UTC)
+    for (hour, track) in [(0, "Before"), (1, "After")] {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            track.to_string(),
+            chrono::Utc
+                .with_ymd_and_hms(2024, 3, 31, hour, 30, 0)
+                .unwrap(),
+            "test".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let tz: Tz = "Europe/Paris".parse().unwrap();
+    let hours = get_scrobbles_per_bucket(&pool, TimeBucket::Hour, None, None, tz).unwrap();
+    assert_eq!(
+        hours,
+        vec![
+            ("2024-03-31T01:00".to_string(), 1),
+            ("2024-03-31T02:00".to_string(), 0),
+            ("2024-03-31T03:00".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_with_read_txn_combines_queries() {
+    let (pool, _temp_file) = setup_pool();
+
+    for i in 0..3 {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            format!("Track {}", i),
+            chrono::Utc::now() - chrono::Duration::minutes(i),
+            "test".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let (count, artists) = with_read_txn(&pool, |conn| {
+        Ok((
+            query_scrobbles_count(conn)?,
+            query_top_artists(conn, 10, None, None)?,
+        ))
+    })
+    .unwrap();
+
+    assert_eq!(count, 3);
+    assert_eq!(artists, vec![("Artist".to_string(), 3)]);
+}
+
+#[test]
+fn test_database_maintenance() {
+    let (pool, _temp_file) = setup_pool();
+
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        chrono::Utc::now(),
+        "test".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+
+    analyze_database(&pool).unwrap();
+    vacuum_database(&pool).unwrap();
+
+    let stats = get_database_stats(&pool).unwrap();
+    assert!(stats["file_size_bytes"].as_i64().unwrap() > 0);
+
+    let scrobbles_table = stats["tables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["name"] == "scrobbles")
+        .unwrap();
+    assert_eq!(scrobbles_table["row_count"], 1);
+
+    let timestamp_index = stats["indexes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|i| i["name"] == "idx_timestamp")
+        .unwrap();
+    assert_eq!(timestamp_index["table"], "scrobbles");
+    assert!(timestamp_index["size_bytes"].as_i64().unwrap() > 0);
+}
+
+#[test]
+fn test_upsert_user_is_stable() {
+    let (pool, temp_file) = setup_pool();
+
+    let alice = upsert_user(&pool, "alice").unwrap();
+    let bob = upsert_user(&pool, "bob").unwrap();
+
+    assert_ne!(alice, bob);
+    assert_eq!(upsert_user(&pool, "alice").unwrap(), alice);
+
+    // Read-only, known users are found and new ones aren't created
+    let read_only = create_read_only_pool(temp_file.path().to_str().unwrap()).unwrap();
+    assert_eq!(upsert_user(&read_only, "alice").unwrap(), alice);
+    assert_eq!(upsert_user(&read_only, "carol").unwrap(), None);
+}
+
+#[test]
+fn test_scrobbles_after_id() {
+    let (pool, _temp_file) = setup_pool();
+    assert_eq!(get_max_scrobble_id(&pool).unwrap(), 0);
+
+    for i in 0..3 {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            format!("Track {}", i),
+            Utc::now(),
+            "lastfm".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let first = get_scrobbles_after_id(&pool, 0, 10).unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(first[0].track, "Track 0");
+
+    let cursor = first[0].id.unwrap();
+    let rest = get_scrobbles_after_id(&pool, cursor, 10).unwrap();
+    assert_eq!(rest.len(), 2);
+    assert_eq!(get_max_scrobble_id(&pool).unwrap(), rest[1].id.unwrap());
+}
+
+#[test]
+fn test_share_token_crud() {
+    let (pool, _temp_file) = setup_pool();
+
+    let share = ShareToken::new("abc123".to_string(), vec!["stats".to_string()])
+        .with_label("friends".to_string());
+    insert_share_token(&pool, &share).unwrap();
+
+    let loaded = get_share_token(&pool, "abc123").unwrap().unwrap();
+    assert_eq!(loaded.label.as_deref(), Some("friends"));
+    assert!(loaded.allows("stats"));
+    assert!(!loaded.allows("reports"));
+    assert!(!loaded.is_expired());
+
+    assert_eq!(get_all_share_tokens(&pool).unwrap().len(), 1);
+    assert!(delete_share_token(&pool, "abc123").unwrap());
+    assert!(!delete_share_token(&pool, "abc123").unwrap());
+    assert!(get_share_token(&pool, "missing").unwrap().is_none());
+}
+
+#[test]
+fn test_enrichment_tables() {
+    let (pool, _temp_file) = setup_pool();
+
+    for (i, artist) in ["Artist A", "Artist B", "Artist B"].iter().enumerate() {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            Utc::now() - chrono::Duration::minutes(i as i64),
+            "lastfm".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    // Most played artists are enriched first
+    assert_eq!(
+        get_artists_without_tags(&pool, Utc::now()).unwrap(),
+        vec!["Artist B".to_string(), "Artist A".to_string()]
+    );
+
+    let tags = vec![("rock".to_string(), 100), ("indie".to_string(), 40)];
+    insert_artist_tags(&pool, "Artist B", &tags, "lastfm").unwrap();
+    assert_eq!(
+        get_artists_without_tags(&pool, Utc::now()).unwrap(),
+        vec!["Artist A".to_string()]
+    );
+
+    // An empty answer is remembered until the retry delay has passed
+    insert_artist_tags(&pool, "Artist A", &[], "lastfm").unwrap();
+    assert!(
+        get_artists_without_tags(&pool, Utc::now())
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        get_artists_without_tags(
+            &pool,
+            Utc::now() + chrono::Duration::days(EMPTY_TAGS_RETRY_DAYS + 1)
+        )
+        .unwrap(),
+        vec!["Artist A".to_string()]
+    );
+
+    let loved = vec![
+        (
+            "Artist A".to_string(),
+            "Track".to_string(),
+            Some(Utc::now()),
+        ),
+        ("Artist B".to_string(), "Track".to_string(), None),
+    ];
+    assert_eq!(insert_loved_tracks(&pool, &loved, "lastfm").unwrap(), 2);
+    assert_eq!(insert_loved_tracks(&pool, &loved, "lastfm").unwrap(), 0);
+}
+
+#[test]
+fn test_playback_metadata_round_trip() {
+    let (pool, _temp_file) = setup_pool();
+    let now = Utc::now();
+
+    let plays = [
+        Scrobble::new("A".into(), "T1".into(), now, "spotify".into()).with_playback(200_000, false),
+        Scrobble::new(
+            "A".into(),
+            "T2".into(),
+            now - chrono::Duration::minutes(5),
+            "spotify".into(),
+        )
+        .with_playback(10_000, true),
+        // No playback metadata: ignored by the skip stats
+        Scrobble::new("A".into(), "T3".into(), now, "lastfm".into()),
+    ];
+    for scrobble in &plays {
+        insert_scrobble(&pool, scrobble).unwrap();
+    }
+
+    let stored = get_scrobbles(&pool, None, None).unwrap();
+    assert!(
+        stored
+            .iter()
+            .any(|s| s.skipped == Some(true) && s.ms_played == Some(10_000))
+    );
+    assert!(stored.iter().any(|s| s.skipped.is_none()));
+
+    let stats = get_playback_stats_by_artist(&pool, None, None).unwrap();
+    assert_eq!(stats.len(), 1);
+    let (artist, count, skips, avg_ms) = &stats[0];
+    assert_eq!(artist, "A");
+    assert_eq!((*count, *skips), (2, 1));
+    assert_eq!(*avg_ms, Some(105_000.0));
+}
+
+#[test]
+fn test_playback_columns_added_to_existing_database() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let conn = rusqlite::Connection::open(temp_file.path()).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE scrobbles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            artist TEXT NOT NULL,
+            album TEXT,
+            track TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            source_id TEXT,
+            UNIQUE(artist, track, timestamp, source)
+        )",
+    )
+    .unwrap();
+    drop(conn);
+
+    let pool = create_pool(temp_file.path().to_str().unwrap()).unwrap();
+    init_database(&pool).unwrap();
+
+    let scrobble = Scrobble::new("A".into(), "T".into(), Utc::now(), "spotify".into())
+        .with_playback(1_000, true);
+    insert_scrobble(&pool, &scrobble).unwrap();
+    assert_eq!(
+        get_scrobbles(&pool, None, None).unwrap()[0].skipped,
+        Some(true)
+    );
+}
+
+#[test]
+fn test_ratings() {
+    let (pool, _temp_file) = setup_pool();
+    let now = Utc::now();
+
+    for i in 0..3 {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            "Song".to_string(),
+            now - chrono::Duration::minutes(i),
+            "lastfm".to_string(),
+        )
+        .with_album("Record".to_string());
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    set_rating(&pool, RatingKind::Track, "Artist", "Song", 4).unwrap();
+    set_rating(&pool, RatingKind::Track, "Artist", "Song", 5).unwrap();
+    set_rating(&pool, RatingKind::Album, "Artist", "Record", 2).unwrap();
+    assert!(set_rating(&pool, RatingKind::Track, "Artist", "Song", 6).is_err());
+
+    assert_eq!(
+        get_rating(&pool, RatingKind::Track, "Artist", "Song").unwrap(),
+        Some(5)
+    );
+
+    let tracks = get_ratings_with_plays(&pool, RatingKind::Track).unwrap();
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].plays, 3);
+
+    let albums = get_ratings_with_plays(&pool, RatingKind::Album).unwrap();
+    assert_eq!(albums[0].rating, 2);
+    assert_eq!(albums[0].plays, 3);
+
+    assert!(delete_rating(&pool, RatingKind::Album, "Artist", "Record").unwrap());
+    assert!(
+        get_ratings_with_plays(&pool, RatingKind::Album)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_notes_crud() {
+    let (pool, _temp_file) = setup_pool();
+
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        Utc::now(),
+        "lastfm".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+    let scrobble_id = get_scrobbles(&pool, None, None).unwrap()[0].id.unwrap();
+    assert!(scrobble_exists(&pool, scrobble_id).unwrap());
+
+    let day = chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+    let scrobble_note = insert_note(
+        &pool,
+        &Note::for_scrobble(scrobble_id, "first concert back".to_string()),
+    )
+    .unwrap();
+    insert_note(&pool, &Note::for_day(day, "road trip playlist".to_string())).unwrap();
+
+    let other_day = day.succ_opt().unwrap();
+    let notes = get_notes_for(&pool, &[scrobble_id], &[day, other_day]).unwrap();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(get_notes_for(&pool, &[], &[other_day]).unwrap().len(), 0);
+
+    assert!(update_note(&pool, scrobble_note, "edited").unwrap());
+    assert_eq!(
+        get_note(&pool, scrobble_note).unwrap().unwrap().body,
+        "edited"
+    );
+
+    assert!(delete_note(&pool, scrobble_note).unwrap());
+    assert!(get_note(&pool, scrobble_note).unwrap().is_none());
+    assert_eq!(get_notes(&pool, None, None).unwrap().len(), 1);
+}
+
+#[test]
+fn test_annotations_filtered_by_artist_and_overlap() {
+    let (pool, _temp_file) = setup_pool();
+    let day = |d: &str| d.parse::<chrono::NaiveDate>().unwrap();
+
+    let concert = insert_annotation(
+        &pool,
+        &Annotation::new(
+            Some("Low".to_string()),
+            day("2024-05-10"),
+            None,
+            AnnotationKind::Concert,
+            "Saw them live".to_string(),
+        ),
+    )
+    .unwrap();
+    insert_annotation(
+        &pool,
+        &Annotation::new(
+            None,
+            day("2023-09-01"),
+            Some(day("2024-06-30")),
+            AnnotationKind::Era,
+            "Year abroad".to_string(),
+        ),
+    )
+    .unwrap();
+
+    let low = get_annotations(&pool, Some("Low"), None, None).unwrap();
+    assert_eq!(low.len(), 1);
+    assert_eq!(low[0].kind, AnnotationKind::Concert);
+
+    // The era started before June but still overlaps it
+    let june = get_annotations(
+        &pool,
+        None,
+        Some(day("2024-06-01")),
+        Some(day("2024-06-30")),
+    )
+    .unwrap();
+    assert_eq!(june.len(), 1);
+    assert_eq!(june[0].label, "Year abroad");
+    assert_eq!(
+        get_annotations(&pool, None, Some(day("2024-07-01")), None)
+            .unwrap()
+            .len(),
+        0
+    );
+
+    let mut moved = get_annotation(&pool, concert).unwrap().unwrap();
+    moved.date = day("2024-06-02");
+    assert!(update_annotation(&pool, concert, &moved).unwrap());
+    assert_eq!(
+        get_annotations(&pool, Some("Low"), Some(day("2024-06-01")), None)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    assert!(delete_annotation(&pool, concert).unwrap());
+    assert!(!delete_annotation(&pool, concert).unwrap());
+}
+
+#[test]
+fn test_first_listens_index() {
+    let (pool, _temp_file) = setup_pool();
+    let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+
+    insert_scrobble(
+        &pool,
+        &Scrobble::new(
+            "A".into(),
+            "Late".into(),
+            at("2024-03-01T00:00:00Z"),
+            "lastfm".into(),
+        ),
+    )
+    .unwrap();
+    // An out-of-order import moves the artist's first listen earlier
+    insert_scrobbles_batch(
+        &pool,
+        &[Scrobble::new(
+            "A".into(),
+            "Early".into(),
+            at("2023-06-01T00:00:00Z"),
+            "listenbrainz".into(),
+        )],
+    )
+    .unwrap();
+
+    let cutoff = at("2024-01-01T00:00:00Z");
+    let artists = get_artists_first_heard_before(&pool, cutoff).unwrap();
+    let tracks = get_tracks_first_heard_before(&pool, cutoff).unwrap();
+    assert!(artists.contains("A"));
+    assert!(tracks.contains(&("A".to_string(), "Early".to_string())));
+    assert!(!tracks.contains(&("A".to_string(), "Late".to_string())));
+
+    // Rebuilding from history gives the same answer
+    rebuild_first_listens(&pool).unwrap();
+    assert_eq!(
+        get_tracks_first_heard_before(&pool, cutoff).unwrap(),
+        tracks
+    );
+}
+
+#[test]
+fn test_first_listens_backfilled_for_existing_database() {
+    let (pool, temp_file) = setup_pool();
+    insert_scrobble(
+        &pool,
+        &Scrobble::new("A".into(), "T".into(), Utc::now(), "lastfm".into()),
+    )
+    .unwrap();
+    pool.get()
+        .unwrap()
+        .execute("DELETE FROM first_listens", [])
+        .unwrap();
+
+    let pool = create_pool(temp_file.path().to_str().unwrap()).unwrap();
+    init_database(&pool).unwrap();
+    let artists =
+        get_artists_first_heard_before(&pool, Utc::now() + chrono::Duration::days(1)).unwrap();
+    assert!(artists.contains("A"));
+}
+
+#[test]
+fn test_get_scrobbles_filtered() {
+    let (pool, _temp_file) = setup_pool();
+    let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+
+    for (artist, ts) in [
+        ("Boards of Canada", "2024-01-01T10:00:00Z"),
+        ("Boards of Canada", "2024-02-01T10:00:00Z"),
+        ("Aphex Twin", "2024-01-01T11:00:00Z"),
+        ("Metallica", "2024-01-01T12:00:00Z"),
+    ] {
+        insert_scrobble(
+            &pool,
+            &Scrobble::new(artist.into(), "T".into(), at(ts), "lastfm".into()),
+        )
+        .unwrap();
+    }
+    insert_artist_tags(
+        &pool,
+        "Boards of Canada",
+        &[("electronic".into(), 100)],
+        "lastfm",
+    )
+    .unwrap();
+    insert_artist_tags(&pool, "Aphex Twin", &[("Electronic".into(), 100)], "lastfm").unwrap();
+
+    let by_artist = ScrobbleFilter {
+        artist: Some("Boards of Canada".into()),
+        genre: None,
+    };
+    assert_eq!(
+        get_scrobbles_filtered(&pool, None, None, &by_artist)
+            .unwrap()
+            .len(),
+        2
+    );
+    let january = get_scrobbles_filtered(
+        &pool,
+        Some(at("2024-01-01T00:00:00Z")),
+        Some(at("2024-01-31T23:59:59Z")),
+        &by_artist,
+    )
+    .unwrap();
+    assert_eq!(january.len(), 1);
+
+    let by_genre = ScrobbleFilter {
+        artist: None,
+        genre: Some("ELECTRONIC".into()),
+    };
+    let electronic = get_scrobbles_filtered(&pool, None, None, &by_genre).unwrap();
+    assert_eq!(electronic.len(), 3);
+    assert!(electronic.iter().all(|s| s.artist != "Metallica"));
+}
+
+#[test]
+fn test_dedup_window_rejects_near_duplicates() {
+    let (pool, _temp_file) = setup_pool();
+    let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+    let scrobble = |ts: &str, source: &str| {
+        Scrobble::new("Artist".into(), "Track".into(), at(ts), source.into())
+    };
+
+    // Off by default: only the exact same scrobble is dropped
+    insert_scrobble(&pool, &scrobble("2024-01-01T12:00:00Z", "lastfm")).unwrap();
+    insert_scrobble(&pool, &scrobble("2024-01-01T12:00:00Z", "lastfm")).unwrap();
+    insert_scrobble(&pool, &scrobble("2024-01-01T12:00:01Z", "lastfm")).unwrap();
+    assert_eq!(get_scrobbles_count(&pool).unwrap(), 2);
+
+    set_setting(&pool, "dedup_window_seconds", &serde_json::json!(5)).unwrap();
+    let inserted = insert_scrobbles_batch(
+        &pool,
+        &[
+            scrobble("2024-01-01T11:59:58Z", "lastfm"),
+            // The same listen reported by another source
+            scrobble("2024-01-01T12:00:03Z", "listenbrainz"),
+            scrobble("2024-01-01T12:04:00Z", "lastfm"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(inserted, 1);
+    assert_eq!(get_scrobbles_count(&pool).unwrap(), 3);
+}
+
+#[test]
+fn test_raw_metadata_round_trip() {
+    let (pool, _temp_file) = setup_pool();
+    let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+
+    let normalizer = Normalizer::new(vec![Rule::Whitespace, Rule::Remaster, Rule::Feat]);
+    let scrobble = normalizer.normalize(
+        Scrobble::new(
+            "Artist ft. Guest".into(),
+            "Song (Remastered 2011)".into(),
+            at("2024-01-01T12:00:00Z"),
+            "lastfm".into(),
+        )
+        .with_album("Album  [2011 Remaster]".into()),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+    insert_scrobble(
+        &pool,
+        &Scrobble::new(
+            "Clean".into(),
+            "Name".into(),
+            at("2024-01-02T12:00:00Z"),
+            "lastfm".into(),
+        ),
+    )
+    .unwrap();
+
+    let stored = get_scrobbles(&pool, Some(10), Some(0)).unwrap();
+    let clean = &stored[0];
+    assert!(clean.raw_metadata.is_none());
+
+    let normalized = &stored[1];
+    assert_eq!(normalized.artist, "Artist feat. Guest");
+    assert_eq!(normalized.track, "Song");
+    assert_eq!(normalized.album.as_deref(), Some("Album"));
+    let raw = normalized.raw_metadata.as_ref().unwrap();
+    assert_eq!(raw.artist, "Artist ft. Guest");
+    assert_eq!(raw.track, "Song (Remastered 2011)");
+    assert_eq!(raw.album.as_deref(), Some("Album  [2011 Remaster]"));
+}
+
+#[test]
+fn test_ignore_rules_crud_and_counts() {
+    let (pool, _temp_file) = setup_pool();
+
+    let everywhere = IgnoreRule::new(None, None, Some("White Noise".into()));
+    let lastfm_only = IgnoreRule::new(Some("lastfm".into()), Some("Some Podcast".into()), None);
+    let everywhere_id = insert_ignore_rule(&pool, &everywhere).unwrap();
+    let lastfm_id = insert_ignore_rule(&pool, &lastfm_only).unwrap();
+
+    assert_eq!(get_ignore_rules(&pool, None).unwrap().len(), 2);
+    let listenbrainz = get_ignore_rules(&pool, Some("listenbrainz")).unwrap();
+    assert_eq!(listenbrainz.len(), 1);
+    assert_eq!(listenbrainz[0].id, Some(everywhere_id));
+
+    // Matching ignores case and whitespace and respects the source
+    let at = "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let noise = Scrobble::new(
+        "Rain".into(),
+        "white noise ".into(),
+        at,
+        "listenbrainz".into(),
+    );
+    let podcast = Scrobble::new(
+        "Some Podcast".into(),
+        "Ep. 1".into(),
+        at,
+        "listenbrainz".into(),
+    );
+    assert!(listenbrainz[0].matches(&noise));
+    assert!(!lastfm_only.matches(&podcast));
+
+    let counts = HashMap::from([(everywhere_id, 3), (lastfm_id, 1)]);
+    add_suppressed_counts(&pool, &counts).unwrap();
+    add_suppressed_counts(&pool, &HashMap::from([(everywhere_id, 2)])).unwrap();
+    assert_eq!(
+        get_ignore_rule(&pool, everywhere_id)
+            .unwrap()
+            .unwrap()
+            .suppressed_count,
+        5
+    );
+
+    let broadened = IgnoreRule::new(None, Some("Some Podcast".into()), None);
+    assert!(update_ignore_rule(&pool, lastfm_id, &broadened).unwrap());
+    let updated = get_ignore_rule(&pool, lastfm_id).unwrap().unwrap();
+    assert_eq!(updated.source, None);
+    assert_eq!(updated.suppressed_count, 1);
+
+    assert!(delete_ignore_rule(&pool, lastfm_id).unwrap());
+    assert!(!delete_ignore_rule(&pool, lastfm_id).unwrap());
+    assert!(get_ignore_rule(&pool, lastfm_id).unwrap().is_none());
+}
+
+#[test]
+fn test_music_reports_exclude_other_media() {
+    let (pool, _temp_file) = setup_pool();
+    let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+
+    for minute in 0..5 {
+        let ts = format!("2024-01-01T12:{:02}:00Z", minute * 10);
+        let mut episode = Scrobble::new(
+            "Talk Show".into(),
+            format!("Part {}", minute),
+            at(&ts),
+            "lastfm".into(),
+        );
+        episode.album = Some("Season 1".into());
+        insert_scrobble(&pool, &episode).unwrap();
+    }
+    insert_scrobble(
+        &pool,
+        &Scrobble::new(
+            "Band".into(),
+            "Song".into(),
+            at("2024-01-02T12:00:00Z"),
+            "lastfm".into(),
+        ),
+    )
+    .unwrap();
+
+    // Rules reclassify what is already stored
+    let rule = MediaTypeRule::new(Some("talk show".into()), None, MediaType::Podcast);
+    let (_, reclassified) = insert_media_type_rule(&pool, &rule).unwrap();
+    assert_eq!(reclassified, 5);
+    assert_eq!(get_media_type_rules(&pool).unwrap().len(), 1);
+
+    let top = get_top_artists(&pool, 10, None, None).unwrap();
+    assert_eq!(top, vec![("Band".to_string(), 1)]);
+    let start = at("2024-01-01T00:00:00Z");
+    let end = at("2024-01-31T00:00:00Z");
+    assert_eq!(
+        get_scrobbles_count_in_range(&pool, Some(start), Some(end)).unwrap(),
+        1
+    );
+    assert_eq!(get_scrobbles_in_range(&pool, start, end).unwrap().len(), 1);
+
+    // The timeline still shows everything
+    let all = get_scrobbles(&pool, Some(10), Some(0)).unwrap();
+    assert_eq!(all.len(), 6);
+    assert_eq!(all[1].media_type, MediaType::Podcast);
+}
+
+#[test]
+fn test_reattribute_scrobbles() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    for (track, time, source) in [
+        ("Demo 1", "2024-01-01T10:00:00Z", "lastfm"),
+        ("Demo 2", "2024-01-01T11:00:00Z", "lastfm"),
+        ("Real", "2024-02-01T10:00:00Z", "lastfm"),
+        // Already present under the new source
+        ("Demo 2", "2024-01-01T11:00:00Z", "demo"),
+    ] {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            track.to_string(),
+            ts(time),
+            source.to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let filter = ReattributeFilter {
+        source: Some("lastfm".to_string()),
+        end: Some(ts("2024-01-31T00:00:00Z")),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        reattribute_scrobbles(&pool, &filter, "demo", true).unwrap(),
+        (2, 1)
+    );
+    let sources: Vec<String> = get_scrobbles(&pool, None, None)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.source)
+        .collect();
+    assert_eq!(sources.iter().filter(|s| *s == "lastfm").count(), 3);
+
+    assert_eq!(
+        reattribute_scrobbles(&pool, &filter, "demo", false).unwrap(),
+        (2, 1)
+    );
+    let demo: Vec<String> = get_scrobbles(&pool, None, None)
+        .unwrap()
+        .into_iter()
+        .filter(|s| s.source == "demo")
+        .map(|s| s.track)
+        .collect();
+    assert_eq!(demo.len(), 2);
+    assert!(demo.contains(&"Demo 1".to_string()));
+
+    assert!(reattribute_scrobbles(&pool, &ReattributeFilter::default(), "demo", false).is_err());
+}
+
+#[test]
+fn test_compilations_group_under_album_artist() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    for (artist, track, time) in [
+        ("Nina Simone", "Feeling Good", "2024-01-01T10:00:00Z"),
+        (
+            "Otis Redding",
+            "Try a Little Tenderness",
+            "2024-01-01T10:05:00Z",
+        ),
+        ("Sam Cooke", "Bring It On Home", "2024-01-01T10:10:00Z"),
+        ("Low", "Words", "2024-01-01T11:00:00Z"),
+        ("Low", "Words", "2024-01-01T12:00:00Z"),
+    ] {
+        let album = if artist == "Low" {
+            "I Could Live"
+        } else {
+            "Soul Classics"
+        };
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            track.to_string(),
+            ts(time),
+            "lastfm".to_string(),
+        )
+        .with_album(album.to_string());
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    // Split across track artists, the compilation loses to a single album
+    let top = get_top_albums(&pool, 10, None, None).unwrap();
+    assert_eq!(top[0], ("Low".to_string(), "I Could Live".to_string(), 2));
+
+    assert_eq!(
+        mark_compilation(&pool, "soul classics", "Various Artists", true).unwrap(),
+        (3, 3)
+    );
+    assert!(get_compilations(&pool).unwrap().is_empty());
+
+    mark_compilation(&pool, "soul classics", "Various Artists", false).unwrap();
+    let later = Scrobble::new(
+        "Aretha Franklin".to_string(),
+        "Respect".to_string(),
+        ts("2024-01-02T10:00:00Z"),
+        "lastfm".to_string(),
+    )
+    .with_album("Soul Classics".to_string());
+    insert_scrobble(&pool, &later).unwrap();
+
+    let top = get_top_albums(&pool, 10, None, None).unwrap();
+    assert_eq!(
+        top[0],
+        (
+            "Various Artists".to_string(),
+            "Soul Classics".to_string(),
+            4
+        )
+    );
+    let stats = get_album_stats(&pool, "Various Artists", "Soul Classics", None, None).unwrap();
+    assert_eq!(stats["unique_tracks"], 4);
+    let stored = get_scrobbles(&pool, Some(1), None).unwrap();
+    assert_eq!(stored[0].album_artist.as_deref(), Some("Various Artists"));
+    assert_eq!(
+        get_maintenance_log(&pool, 10).unwrap()[0].action,
+        "mark_compilation"
+    );
+}
+
+#[test]
+fn test_shift_scrobble_timestamps() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    for (track, time, source) in [
+        // Same track an hour apart: the first moves onto the second's old slot
+        ("Loop", "2024-03-01T10:00:00Z", "spotify"),
+        ("Loop", "2024-03-01T11:00:00Z", "spotify"),
+        ("Other", "2024-03-01T12:00:00Z", "lastfm"),
+        ("Later", "2024-04-01T12:00:00Z", "spotify"),
+    ] {
+        let scrobble = Scrobble::new(
+            "Artist".to_string(),
+            track.to_string(),
+            ts(time),
+            source.to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let filter = TimeShiftFilter {
+        source: "spotify".to_string(),
+        start: ts("2024-03-01T00:00:00Z"),
+        end: ts("2024-03-31T23:59:59Z"),
+    };
+    let times = |pool: &DbPool| -> Vec<String> {
+        let mut times: Vec<String> = get_scrobbles(pool, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.timestamp.to_rfc3339())
+            .collect();
+        times.sort();
+        times
+    };
+    let before = times(&pool);
+
+    // Derived tables from before the shift
+    let day = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+    for week in ["2024-02-19", "2024-02-26", "2024-03-04"] {
+        save_chart_week(&pool, day(week), &[], &[], ts("2024-04-08T00:00:00Z")).unwrap();
+    }
+    save_daily_summaries(
+        &pool,
+        &[
+            DailySummary::empty(day("2024-02-20")),
+            DailySummary::empty(day("2024-03-01")),
+        ],
+        "UTC",
+        ts("2024-04-08T00:00:00Z"),
+    )
+    .unwrap();
+    record_sleep_detections(
+        &pool,
+        &[SleepDetection {
+            id: None,
+            start: ts("2024-03-01T09:00:00Z"),
+            end: ts("2024-03-01T11:30:00Z"),
+            scrobble_count: 2,
+            distinct_artists: 1,
+            status: DetectionStatus::Pending,
+        }],
+    )
+    .unwrap();
+
+    assert_eq!(
+        shift_scrobble_timestamps(&pool, &filter, 3600, true).unwrap(),
+        (2, 2)
+    );
+    assert_eq!(times(&pool), before);
+    assert!(get_maintenance_log(&pool, 10).unwrap().is_empty());
+    assert_eq!(get_sleep_detections(&pool, None, None).unwrap().len(), 1);
+
+    assert_eq!(
+        shift_scrobble_timestamps(&pool, &filter, 3600, false).unwrap(),
+        (2, 2)
+    );
+    assert_eq!(
+        times(&pool),
+        vec![
+            "2024-03-01T11:00:00+00:00",
+            "2024-03-01T12:00:00+00:00",
+            "2024-03-01T12:00:00+00:00",
+            "2024-04-01T12:00:00+00:00",
+        ]
+    );
+
+    let log = get_maintenance_log(&pool, 10).unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].action, "shift_timestamps");
+    assert_eq!(log[0].affected, 2);
+    assert_eq!(log[0].details["offset_seconds"], 3600);
+
+    // Only what came before the shifted range is kept
+    assert_eq!(get_last_chart_week(&pool).unwrap(), Some(day("2024-02-19")));
+    assert_eq!(
+        get_last_daily_summary(&pool).unwrap(),
+        Some((day("2024-02-20"), "UTC".to_string()))
+    );
+    assert!(get_sleep_detections(&pool, None, None).unwrap().is_empty());
+
+    assert!(shift_scrobble_timestamps(&pool, &filter, 0, false).is_err());
+}
+
+#[test]
+fn test_archive_and_restore_scrobbles() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    let old = Scrobble::new(
+        "Artist".to_string(),
+        "Old".to_string(),
+        ts("2015-06-01T12:00:00Z"),
+        "lastfm".to_string(),
+    );
+    let recent = Scrobble::new(
+        "Artist".to_string(),
+        "Recent".to_string(),
+        ts("2024-06-01T12:00:00Z"),
+        "lastfm".to_string(),
+    );
+    insert_scrobble(&pool, &old).unwrap();
+    insert_scrobble(&pool, &recent).unwrap();
+    let cutoff = ts("2020-01-01T00:00:00Z");
+
+    assert_eq!(archive_scrobbles(&pool, cutoff, true).unwrap(), 1);
+    assert_eq!(get_scrobbles_count(&pool).unwrap(), 2);
+
+    // Warm the play counts cache, which the move must not leave short
+    let artist_plays = |pool: &DbPool| {
+        entity_play_count(&mut pool.get().unwrap(), "artist", "Artist", "")
+            .unwrap()
+            .plays
+    };
+    assert_eq!(artist_plays(&pool), 2);
+
+    assert_eq!(archive_scrobbles(&pool, cutoff, false).unwrap(), 1);
+    assert_eq!(get_archive_stats(&pool).unwrap().scrobbles, 1);
+    assert_eq!(get_scrobbles(&pool, None, None).unwrap().len(), 1);
+
+    // All-time figures still count the archived play
+    assert_eq!(get_scrobbles_count(&pool).unwrap(), 2);
+    assert_eq!(artist_plays(&pool), 2);
+    assert_eq!(
+        entity_play_count(&mut pool.get().unwrap(), "track", "Artist", "Old")
+            .unwrap()
+            .first_timestamp,
+        Some(old.timestamp.timestamp())
+    );
+    assert!(entity_exists(&pool, "track", "Artist", "Old").unwrap());
+    assert_eq!(
+        get_maintenance_log(&pool, 10).unwrap()[0].action,
+        "archive_scrobbles"
+    );
+
+    // Daily summaries are taken from the first listen, archived or not
+    assert_eq!(
+        get_first_listen_timestamp(&pool).unwrap(),
+        Some(old.timestamp)
+    );
+    crate::summaries::record_days(&pool, ts("2024-06-03T00:00:00Z")).unwrap();
+    let archived_day = get_daily_summaries(
+        &pool,
+        old.timestamp.date_naive(),
+        old.timestamp.date_naive().succ_opt().unwrap(),
+        "UTC",
+    )
+    .unwrap();
+    assert_eq!(archived_day[0].scrobbles, 1);
+    assert_eq!(archived_day[0].new_artists, vec!["Artist".to_string()]);
+
+    // Re-importing an archived listen doesn't bring it back, and the artist
+    // is still first heard in the archive, even after a rebuild
+    assert_eq!(
+        insert_scrobbles_batch(&pool, std::slice::from_ref(&old)).unwrap(),
+        0
+    );
+    rebuild_first_listens(&pool).unwrap();
+    assert!(
+        get_artists_first_heard_before(&pool, cutoff)
+            .unwrap()
+            .contains("Artist")
+    );
+
+    assert_eq!(restore_archived_scrobbles(&pool, false).unwrap(), 1);
+    assert_eq!(get_scrobbles_count(&pool).unwrap(), 2);
+    assert_eq!(get_archive_stats(&pool).unwrap().scrobbles, 0);
+    assert_eq!(artist_plays(&pool), 2);
+}
+
+#[test]
+fn test_play_counts_cache_follows_scrobbles() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let play = |time: &str| {
+        let scrobble = Scrobble::new(
+            "Low".to_string(),
+            "Sunflower".to_string(),
+            ts(time),
+            "lastfm".to_string(),
+        )
+        .with_album("Things We Lost in the Fire".to_string());
+        insert_scrobble(&pool, &scrobble).unwrap()
+    };
+    let cached = |entity_type: &str| -> Option<i64> {
+        let conn = pool.get().unwrap();
+        conn.query_row(
+            "SELECT plays FROM play_counts WHERE entity_type = ?1",
+            params![entity_type],
+            |row| row.get(0),
+        )
+        .ok()
+    };
+
+    play("2024-01-01T10:00:00Z");
+    let first = play("2024-02-01T10:00:00Z");
+    assert_eq!(cached("artist"), None);
+
+    let stats = get_artist_stats(&pool, "Low", None, None).unwrap();
+    assert_eq!(stats["total_scrobbles"], 2);
+    get_album_stats(&pool, "Low", "Things We Lost in the Fire", None, None).unwrap();
+    get_track_stats(&pool, "Low", "Sunflower", None, None).unwrap();
+    assert_eq!(cached("album"), Some(2));
+
+    // Inserts keep cached counts current
+    play("2023-12-01T10:00:00Z");
+    assert_eq!(cached("artist"), Some(3));
+    assert_eq!(cached("track"), Some(3));
+    let stats = get_track_stats(&pool, "Low", "Sunflower", None, None).unwrap();
+    assert_eq!(
+        stats["first_scrobble"],
+        ts("2023-12-01T10:00:00Z").timestamp()
+    );
+
+    // Deletes drop them until the next read
+    delete_scrobble(&pool, first).unwrap();
+    assert_eq!(cached("artist"), None);
+    let stats = get_artist_stats(&pool, "Low", None, None).unwrap();
+    assert_eq!(stats["total_scrobbles"], 2);
+    assert_eq!(
+        stats["last_scrobble"],
+        ts("2024-01-01T10:00:00Z").timestamp()
+    );
+
+    // So do renames
+    mark_compilation(
+        &pool,
+        "Things We Lost in the Fire",
+        "Various Artists",
+        false,
+    )
+    .unwrap();
+    assert_eq!(cached("album"), None);
+    let stats = get_album_stats(
+        &pool,
+        "Various Artists",
+        "Things We Lost in the Fire",
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(stats["total_scrobbles"], 2);
+}
+
+#[test]
+fn test_delete_scrobble_updates_first_listens() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    let bogus = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        ts("2001-01-01T00:00:00Z"),
+        "lastfm".to_string(),
+    );
+    let real = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        ts("2024-01-01T00:00:00Z"),
+        "lastfm".to_string(),
+    );
+    insert_scrobble(&pool, &bogus).unwrap();
+    insert_scrobble(&pool, &real).unwrap();
+    let bogus_id = get_scrobbles(&pool, None, None).unwrap()[1].id.unwrap();
+    assert!(
+        get_artists_first_heard_before(&pool, ts("2020-01-01T00:00:00Z"))
+            .unwrap()
+            .contains("Artist")
+    );
+
+    assert!(delete_scrobble(&pool, bogus_id).unwrap());
+    assert!(!delete_scrobble(&pool, bogus_id).unwrap());
+    assert!(get_scrobble(&pool, bogus_id).unwrap().is_none());
+    assert!(
+        get_artists_first_heard_before(&pool, ts("2020-01-01T00:00:00Z"))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        get_tracks_first_heard_before(&pool, ts("2024-06-01T00:00:00Z"))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_backup_database() {
+    let (pool, _temp_file) = setup_pool();
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        Utc::now(),
+        "lastfm".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("backup.sqlite");
+    backup_database(&pool, &dest).unwrap();
+
+    let copy = create_pool(dest.to_str().unwrap()).unwrap();
+    let scrobbles = get_scrobbles(&copy, None, None).unwrap();
+    assert_eq!(scrobbles.len(), 1);
+    assert_eq!(scrobbles[0].track, "Track");
+    assert!(check_integrity(&copy, true).unwrap().ok);
+}
+
+#[test]
+fn test_check_integrity_of_healthy_database() {
+    let (pool, _temp_file) = setup_pool();
+    let scrobble = Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        Utc::now(),
+        "lastfm".to_string(),
+    );
+    insert_scrobble(&pool, &scrobble).unwrap();
+
+    for full in [false, true] {
+        let report = check_integrity(&pool, full).unwrap();
+        assert!(report.ok);
+        assert!(report.problems.is_empty());
+        assert_eq!(report.mode, if full { "full" } else { "quick" });
+    }
+}
+
+#[test]
+fn test_listen_filter_setting() {
+    let (pool, _temp_file) = setup_pool();
+    assert_eq!(get_listen_filter(&pool).unwrap(), ListenFilter::default());
+
+    let filter = ListenFilter {
+        min_play_percent: 80,
+        ..Default::default()
+    };
+    set_listen_filter(&pool, &filter).unwrap();
+    set_listen_filter(&pool, &filter).unwrap();
+    assert_eq!(get_listen_filter(&pool).unwrap(), filter);
+}
+
+#[test]
+fn test_import_job_progress() {
+    let (pool, _temp_file) = setup_pool();
+
+    let mut job = ImportJob::new("lastfm".to_string(), "user".to_string());
+    job.api_key = Some("key".to_string());
+    let id = insert_import_job(&pool, &job).unwrap();
+
+    save_import_checkpoint(&pool, id, 6, 1000).unwrap();
+    set_import_job_status(&pool, id, ImportStatus::Failed, None, Some("boom")).unwrap();
+
+    let stored = get_import_job(&pool, id).unwrap().unwrap();
+    assert_eq!(stored.status, ImportStatus::Failed);
+    assert_eq!(stored.checkpoint, Some(6));
+    assert_eq!(stored.imported_count, 1000);
+    assert_eq!(stored.api_key.as_deref(), Some("key"));
+    assert_eq!(stored.last_error.as_deref(), Some("boom"));
+
+    assert!(
+        get_import_jobs(&pool, Some(ImportStatus::Running))
+            .unwrap()
+            .is_empty()
+    );
+
+    set_import_job_status(&pool, id, ImportStatus::Completed, Some(1500), None).unwrap();
+    let stored = get_import_job(&pool, id).unwrap().unwrap();
+    assert_eq!(stored.imported_count, 1500);
+    assert_eq!(stored.last_error, None);
+    assert_eq!(get_import_jobs(&pool, None).unwrap().len(), 1);
+}
+
+#[test]
+fn test_musicbrainz_ids_round_trip() {
+    let (pool, _temp_file) = setup_pool();
+
+    let album = |mbid: &str| MusicBrainzId {
+        entity_type: "album",
+        artist: "Talk Talk".to_string(),
+        name: Some("Spirit of Eden".to_string()),
+        mbid: mbid.to_string(),
+    };
+    let artist = MusicBrainzId {
+        entity_type: "artist",
+        artist: "Talk Talk".to_string(),
+        name: None,
+        mbid: "artist-mbid".to_string(),
+    };
+
+    assert_eq!(
+        save_musicbrainz_ids(&pool, &[album("old"), artist]).unwrap(),
+        2
+    );
+    save_musicbrainz_ids(&pool, &[album("new")]).unwrap();
+
+    assert_eq!(
+        get_musicbrainz_id(&pool, "album", "Talk Talk", Some("Spirit of Eden")).unwrap(),
+        Some("new".to_string())
+    );
+    assert_eq!(
+        get_musicbrainz_id(&pool, "artist", "Talk Talk", None).unwrap(),
+        Some("artist-mbid".to_string())
+    );
+    assert_eq!(
+        get_musicbrainz_id(&pool, "track", "Talk Talk", Some("Spirit of Eden")).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_pause_and_resume_sync_config() {
+    use crate::models::SyncConfig;
+
+    let (pool, _temp_file) = setup_pool();
+    let config = SyncConfig::new("lastfm".to_string(), "testuser".to_string(), 60)
+        .with_api_key("secret".to_string());
+    let id = insert_sync_config(&pool, &config).unwrap();
+
+    assert!(set_sync_config_enabled(&pool, id, false).unwrap());
+    let paused = get_sync_config(&pool, id).unwrap().unwrap();
+    assert!(!paused.enabled);
+    assert_eq!(paused.api_key.as_deref(), Some("secret"));
+    assert!(get_enabled_sync_configs(&pool).unwrap().is_empty());
+
+    assert!(set_sync_config_enabled(&pool, id, true).unwrap());
+    assert_eq!(get_enabled_sync_configs(&pool).unwrap().len(), 1);
+
+    assert!(!set_sync_config_enabled(&pool, id + 1, false).unwrap());
+}
+
+/// `EXPLAIN QUERY PLAN` details for `sql`, its parameters left unbound
+fn query_plan(conn: &Connection, sql: &str) -> Vec<String> {
+    let mut stmt = conn
+        .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+        .unwrap();
+    let unbound = vec![rusqlite::types::Null; stmt.parameter_count()];
+    stmt.query_map(params_from_iter(unbound), |row| row.get::<_, String>(3))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_top_queries_read_only_the_range() {
+    let (pool, _temp_file) = setup_pool();
+    let conn = pool.get().unwrap();
+
+    for sql in [TOP_ARTISTS_SQL, TOP_TRACKS_SQL] {
+        assert!(
+            query_plan(&conn, sql)[0]
+                .starts_with("SEARCH scrobbles USING COVERING INDEX idx_listens_timestamp ")
+        );
+    }
+    assert!(
+        query_plan(&conn, TOP_ALBUMS_SQL)[0]
+            .starts_with("SEARCH scrobbles USING INDEX idx_listens_timestamp ")
+    );
+
+    let by_artist = query_plan(
+        &conn,
+        "SELECT album, COUNT(*) FROM scrobbles WHERE artist = ?1 AND album IS NOT NULL
+         GROUP BY album",
+    );
+    assert!(by_artist[0].starts_with("SEARCH scrobbles USING COVERING INDEX idx_artist_album "));
+
+    let listen = query_plan(
+        &conn,
+        "SELECT 1 FROM scrobbles WHERE artist = ?1 AND track = ?2 AND timestamp BETWEEN ?3 AND ?4",
+    );
+    assert!(listen[0].contains("(artist=? AND track=? AND timestamp>? AND timestamp<?)"));
+}
+
+#[test]
+fn test_top_artists_with_open_ended_range() {
+    let (pool, _temp_file) = setup_pool();
+    let ts = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    for (artist, time) in [
+        ("Old", "2020-01-01T12:00:00Z"),
+        ("Old", "2020-01-02T12:00:00Z"),
+        ("New", "2024-01-01T12:00:00Z"),
+    ] {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            ts(time),
+            "lastfm".to_string(),
+        );
+        insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let since = get_top_artists(&pool, 10, Some(ts("2023-01-01T00:00:00Z")), None).unwrap();
+    assert_eq!(since, vec![("New".to_string(), 1)]);
+    let until = get_top_artists(&pool, 10, None, Some(ts("2023-01-01T00:00:00Z"))).unwrap();
+    assert_eq!(until, vec![("Old".to_string(), 2)]);
+    assert_eq!(get_top_artists(&pool, 10, None, None).unwrap().len(), 2);
+}
@@ -0,0 +1,538 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::str::FromStr;
+
+use crate::db::DbPool;
+use crate::models::Scrobble;
+
+/// Source given to generated scrobbles, so they can be told apart and removed
+pub const DEMO_SOURCE: &str = "demo";
+
+/// `(artist, album, tracks)`
+type CatalogEntry = (&'static str, &'static str, &'static [&'static str]);
+
+const JAZZ: &[CatalogEntry] = &[
+    (
+        "Miles Davis",
+        "Kind of Blue",
+        &[
+            "So What",
+            "Freddie Freeloader",
+            "Blue in Green",
+            "All Blues",
+            "Flamenco Sketches",
+        ],
+    ),
+    (
+        "John Coltrane",
+        "A Love Supreme",
+        &["Acknowledgement", "Resolution", "Pursuance", "Psalm"],
+    ),
+    (
+        "Bill Evans Trio",
+        "Waltz for Debby",
+        &[
+            "My Foolish Heart",
+            "Waltz for Debby",
+            "Detour Ahead",
+            "My Romance",
+        ],
+    ),
+    (
+        "Thelonious Monk",
+        "Brilliant Corners",
+        &[
+            "Brilliant Corners",
+            "Ba-Lue Bolivar Ba-Lues-Are",
+            "Pannonica",
+            "Bemsha Swing",
+        ],
+    ),
+    (
+        "Charles Mingus",
+        "Mingus Ah Um",
+        &[
+            "Better Git It in Your Soul",
+            "Goodbye Pork Pie Hat",
+            "Fables of Faubus",
+        ],
+    ),
+];
+
+const ROCK: &[CatalogEntry] = &[
+    (
+        "Radiohead",
+        "OK Computer",
+        &[
+            "Airbag",
+            "Paranoid Android",
+            "Karma Police",
+            "No Surprises",
+            "Lucky",
+        ],
+    ),
+    (
+        "Pixies",
+        "Doolittle",
+        &[
+            "Debaser",
+            "Wave of Mutilation",
+            "Here Comes Your Man",
+            "Monkey Gone to Heaven",
+        ],
+    ),
+    (
+        "Talking Heads",
+        "Remain in Light",
+        &[
+            "Born Under Punches",
+            "Crosseyed and Painless",
+            "Once in a Lifetime",
+        ],
+    ),
+    (
+        "Sonic Youth",
+        "Daydream Nation",
+        &[
+            "Teen Age Riot",
+            "Silver Rocket",
+            "The Sprawl",
+            "Cross the Breeze",
+        ],
+    ),
+];
+
+const ELECTRONIC: &[CatalogEntry] = &[
+    (
+        "Boards of Canada",
+        "Music Has the Right to Children",
+        &[
+            "Wildlife Analysis",
+            "An Eagle in Your Mind",
+            "Roygbiv",
+            "Aquarius",
+        ],
+    ),
+    (
+        "Aphex Twin",
+        "Selected Ambient Works 85-92",
+        &["Xtal", "Tha", "Pulsewidth", "Ageispolis"],
+    ),
+    (
+        "Daft Punk",
+        "Discovery",
+        &[
+            "One More Time",
+            "Aerodynamic",
+            "Digital Love",
+            "Harder, Better, Faster, Stronger",
+        ],
+    ),
+    (
+        "Burial",
+        "Untrue",
+        &["Archangel", "Near Dark", "Ghost Hardware", "Untrue"],
+    ),
+];
+
+const CLASSICAL: &[CatalogEntry] = &[
+    (
+        "Glenn Gould",
+        "Bach: The Goldberg Variations",
+        &[
+            "Aria",
+            "Variatio 1",
+            "Variatio 2",
+            "Variatio 3",
+            "Aria da capo",
+        ],
+    ),
+    (
+        "Martha Argerich",
+        "Chopin: Preludes",
+        &[
+            "Prelude No. 4",
+            "Prelude No. 15",
+            "Prelude No. 20",
+            "Prelude No. 24",
+        ],
+    ),
+    (
+        "Kronos Quartet",
+        "Different Trains",
+        &[
+            "America - Before the War",
+            "Europe - During the War",
+            "After the War",
+        ],
+    ),
+];
+
+/// Musical style of the generated catalog; also stored as the artists' tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Genre {
+    Jazz,
+    Rock,
+    Electronic,
+    Classical,
+}
+
+impl Genre {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Genre::Jazz => "jazz",
+            Genre::Rock => "rock",
+            Genre::Electronic => "electronic",
+            Genre::Classical => "classical",
+        }
+    }
+
+    fn catalog(&self) -> &'static [CatalogEntry] {
+        match self {
+            Genre::Jazz => JAZZ,
+            Genre::Rock => ROCK,
+            Genre::Electronic => ELECTRONIC,
+            Genre::Classical => CLASSICAL,
+        }
+    }
+}
+
+impl FromStr for Genre {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "jazz" => Ok(Genre::Jazz),
+            "rock" => Ok(Genre::Rock),
+            "electronic" => Ok(Genre::Electronic),
+            "classical" => Ok(Genre::Classical),
+            other => Err(anyhow::anyhow!("Unknown demo genre: {}", other)),
+        }
+    }
+}
+
+/// What `generate-demo` produces
+#[derive(Debug, Clone)]
+pub struct DemoOptions {
+    /// Number of scrobbles to generate
+    pub scrobbles: usize,
+    pub genres: Vec<Genre>,
+    /// How many days before `end` the history spans
+    pub days: i64,
+    pub end: DateTime<Utc>,
+    /// Same seed, same data
+    pub seed: u64,
+}
+
+impl Default for DemoOptions {
+    fn default() -> Self {
+        Self {
+            scrobbles: 5000,
+            genres: vec![Genre::Jazz],
+            days: 365,
+            end: Utc::now(),
+            seed: 42,
+        }
+    }
+}
+
+impl DemoOptions {
+    /// Parse `--scrobbles N`, `--genres jazz,rock`, `--days N` and `--seed N`
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("Missing value for {}", flag))?;
+            match flag.as_str() {
+                "--scrobbles" => {
+                    options.scrobbles = value.parse().context("Invalid --scrobbles")?;
+                }
+                "--genres" => {
+                    options.genres = value
+                        .split(',')
+                        .filter(|g| !g.trim().is_empty())
+                        .map(Genre::from_str)
+                        .collect::<Result<_>>()?;
+                }
+                "--days" => options.days = value.parse().context("Invalid --days")?,
+                "--seed" => options.seed = value.parse().context("Invalid --seed")?,
+                other => return Err(anyhow::anyhow!("Unknown generate-demo option: {}", other)),
+            }
+        }
+
+        if options.genres.is_empty() {
+            return Err(anyhow::anyhow!("--genres needs at least one genre"));
+        }
+        if options.days < 1 {
+            return Err(anyhow::anyhow!("--days must be at least 1"));
+        }
+        Ok(options)
+    }
+}
+
+/// Generate demo scrobbles and tag their artists with their genre. Returns
+/// the number of scrobbles inserted
+pub fn populate(pool: &DbPool, options: &DemoOptions) -> Result<usize> {
+    let scrobbles = generate_scrobbles(options);
+    let inserted = crate::db::insert_scrobbles_batch(pool, &scrobbles)?;
+
+    for genre in &options.genres {
+        for (artist, _, _) in genre.catalog() {
+            crate::db::insert_artist_tags(
+                pool,
+                artist,
+                &[(genre.as_str().to_string(), 100)],
+                DEMO_SOURCE,
+            )?;
+        }
+    }
+
+    Ok(inserted)
+}
+
+/// Listening sessions of consecutive album tracks, spread over the span with
+/// a preference for evenings and for the first artists of each catalog
+pub fn generate_scrobbles(options: &DemoOptions) -> Vec<Scrobble> {
+    let mut rng = DemoRng::new(options.seed);
+    let catalog: Vec<CatalogEntry> = options
+        .genres
+        .iter()
+        .flat_map(|genre| genre.catalog().iter().copied())
+        .collect();
+    let start = options.end - Duration::days(options.days);
+
+    let mut scrobbles = Vec::with_capacity(options.scrobbles);
+    while scrobbles.len() < options.scrobbles && !catalog.is_empty() {
+        // Squaring skews towards the front, so some artists are favourites
+        let pick = rng.unit() * rng.unit();
+        let (artist, album, tracks) = catalog[(pick * catalog.len() as f64) as usize];
+
+        let day = rng.below(options.days as u64) as i64;
+        let hour = EVENING_HOURS[rng.below(EVENING_HOURS.len() as u64) as usize];
+        let mut timestamp = start
+            + Duration::days(day)
+            + Duration::hours(hour)
+            + Duration::minutes(rng.below(60) as i64);
+
+        let first_track = rng.below(tracks.len() as u64) as usize;
+        let session_length = 1 + rng.below(tracks.len() as u64) as usize;
+        for track in tracks.iter().cycle().skip(first_track).take(session_length) {
+            if scrobbles.len() == options.scrobbles || timestamp > options.end {
+                break;
+            }
+            scrobbles.push(
+                Scrobble::new(
+                    artist.to_string(),
+                    track.to_string(),
+                    timestamp,
+                    DEMO_SOURCE.to_string(),
+                )
+                .with_album(album.to_string()),
+            );
+            timestamp += Duration::minutes(3 + rng.below(6) as i64);
+        }
+    }
+
+    scrobbles.sort_by_key(|s| s.timestamp);
+    scrobbles
+}
+
+/// Artists in the synthetic catalog, each with a few albums of ten tracks
+const SYNTHETIC_ARTISTS: u64 = 5_000;
+const SYNTHETIC_ALBUMS_PER_ARTIST: u64 = 3;
+const SYNTHETIC_TRACKS_PER_ALBUM: u64 = 10;
+
+/// Days of history the synthetic scrobbles are spread over
+const SYNTHETIC_DAYS: i64 = 3_650;
+
+/// Scrobbles inserted per transaction when seeding large amounts
+const SYNTHETIC_BATCH: usize = 10_000;
+
+/// Load-test data: `count` scrobbles evenly spaced over the ten years before
+/// `end`, drawn from a catalog of thousands of made-up artists with the same
+/// skew towards favourites as the demo catalog. Generated lazily, so millions
+/// of scrobbles don't have to be held at once
+pub fn synthetic_scrobbles(
+    count: usize,
+    end: DateTime<Utc>,
+    seed: u64,
+) -> impl Iterator<Item = Scrobble> {
+    let mut rng = DemoRng::new(seed);
+    let span = Duration::days(SYNTHETIC_DAYS).num_seconds();
+    let step = (span / count.max(1) as i64).max(1);
+    let start = end - Duration::seconds(step * count as i64);
+
+    (0..count).map(move |i| {
+        let artist = (rng.unit() * rng.unit() * SYNTHETIC_ARTISTS as f64) as u64;
+        let album = rng.below(SYNTHETIC_ALBUMS_PER_ARTIST);
+        let track = rng.below(SYNTHETIC_TRACKS_PER_ALBUM);
+        Scrobble::new(
+            format!("Artist {}", artist),
+            format!("Track {}-{}-{}", artist, album, track),
+            start + Duration::seconds(step * i as i64),
+            DEMO_SOURCE.to_string(),
+        )
+        .with_album(format!("Album {}-{}", artist, album))
+    })
+}
+
+/// Insert `synthetic_scrobbles` in batches. Returns the number of scrobbles
+/// inserted, which is lower when some were already there
+pub fn populate_synthetic(
+    pool: &DbPool,
+    count: usize,
+    end: DateTime<Utc>,
+    seed: u64,
+) -> Result<usize> {
+    let mut scrobbles = synthetic_scrobbles(count, end, seed);
+    let mut inserted = 0;
+
+    loop {
+        let batch: Vec<Scrobble> = scrobbles.by_ref().take(SYNTHETIC_BATCH).collect();
+        if batch.is_empty() {
+            break;
+        }
+        inserted += crate::db::insert_scrobbles_batch(pool, &batch)?;
+    }
+
+    Ok(inserted)
+}
+
+// Session start hours, repeated to weight them
+const EVENING_HOURS: &[i64] = &[7, 8, 12, 13, 17, 18, 19, 19, 20, 20, 20, 21, 21, 22, 23];
+
+/// Small xorshift generator; demo data doesn't need more and this keeps the
+/// output reproducible without another dependency
+struct DemoRng(u64);
+
+impl DemoRng {
+    fn new(seed: u64) -> Self {
+        // Xorshift is stuck at zero
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound.max(1)
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+
+    fn options(scrobbles: usize, genres: Vec<Genre>) -> DemoOptions {
+        DemoOptions {
+            scrobbles,
+            genres,
+            days: 30,
+            end: "2024-06-30T00:00:00Z".parse().unwrap(),
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_generates_requested_amount_within_span() {
+        let options = options(500, vec![Genre::Jazz, Genre::Rock]);
+        let scrobbles = generate_scrobbles(&options);
+
+        assert_eq!(scrobbles.len(), 500);
+        let start = options.end - Duration::days(30);
+        assert!(
+            scrobbles
+                .iter()
+                .all(|s| s.timestamp >= start && s.timestamp <= options.end)
+        );
+        assert!(scrobbles.iter().all(|s| s.source == DEMO_SOURCE));
+        assert!(scrobbles.iter().any(|s| s.artist == "Miles Davis"));
+        assert!(scrobbles.iter().any(|s| s.artist == "Radiohead"));
+        assert!(!scrobbles.iter().any(|s| s.artist == "Burial"));
+    }
+
+    #[test]
+    fn test_same_seed_same_data() {
+        let a = generate_scrobbles(&options(100, vec![Genre::Electronic]));
+        let b = generate_scrobbles(&options(100, vec![Genre::Electronic]));
+
+        let key = |s: &Scrobble| (s.artist.clone(), s.track.clone(), s.timestamp);
+        assert_eq!(
+            a.iter().map(key).collect::<Vec<_>>(),
+            b.iter().map(key).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args: Vec<String> = [
+            "--scrobbles",
+            "200",
+            "--genres",
+            "jazz, classical",
+            "--days",
+            "90",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let options = DemoOptions::from_args(&args).unwrap();
+
+        assert_eq!(options.scrobbles, 200);
+        assert_eq!(options.genres, vec![Genre::Jazz, Genre::Classical]);
+        assert_eq!(options.days, 90);
+
+        assert!(DemoOptions::from_args(&["--genres".to_string(), "polka".to_string()]).is_err());
+        assert!(DemoOptions::from_args(&["--days".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_synthetic_scrobbles_are_reproducible() {
+        let end: DateTime<Utc> = "2025-01-01T00:00:00Z".parse().unwrap();
+        let scrobbles: Vec<Scrobble> = synthetic_scrobbles(1000, end, 3).collect();
+
+        assert_eq!(scrobbles.len(), 1000);
+        assert!(
+            scrobbles
+                .windows(2)
+                .all(|w| w[0].timestamp < w[1].timestamp)
+        );
+        assert!(scrobbles.last().unwrap().timestamp < end);
+        assert!(scrobbles[0].timestamp >= end - Duration::days(SYNTHETIC_DAYS));
+
+        let (pool, _temp_file) = setup_pool();
+
+        assert_eq!(populate_synthetic(&pool, 1000, end, 3).unwrap(), 1000);
+        // The same seed gives the same scrobbles, which are all duplicates
+        assert_eq!(populate_synthetic(&pool, 1000, end, 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_populate_inserts_and_tags() {
+        let (pool, _temp_file) = setup_pool();
+
+        let inserted = populate(&pool, &options(300, vec![Genre::Jazz])).unwrap();
+        assert!(inserted > 0);
+        assert_eq!(
+            crate::db::get_scrobbles_count(&pool).unwrap(),
+            inserted as i64
+        );
+        assert!(
+            crate::db::get_artists_without_tags(&pool, chrono::Utc::now())
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,10 +1,79 @@
 use anyhow::Result;
 use chrono::Utc;
 use rusqlite::params;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
 
 use crate::db::DbPool;
 
-use super::types::{ImageMetadata, ImageRequest};
+use super::types::{ImageCacheFilter, ImageMetadata, ImageRequest, Thumbnail};
+
+/// Bounded least-recently-used map kept in front of the SQLite caches so
+/// repeated lookups don't need a pooled connection
+pub struct MemoryCache<K, V> {
+    capacity: usize,
+    inner: Mutex<MemoryCacheInner<K, V>>,
+}
+
+struct MemoryCacheInner<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    tick: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> MemoryCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(MemoryCacheInner {
+                entries: HashMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.get_mut(key).map(|(value, used)| {
+            *used = tick;
+            value.clone()
+        })
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            // A linear scan is fine at the few thousand entries we keep
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+
+        inner.entries.insert(key, (value, tick));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.inner.lock().unwrap().entries.remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
 
 pub struct ImageCache {
     pool: DbPool,
@@ -50,8 +119,12 @@ impl ImageCache {
         }
     }
 
+    /// Store a lookup's result; a read-only instance keeps it in memory only
     pub fn set(&self, request: &ImageRequest, url: Option<String>) -> Result<()> {
         let conn = self.pool.get()?;
+        if crate::db::is_read_only(&conn) {
+            return Ok(());
+        }
         let now = Utc::now().timestamp();
 
         // For tracks, we use entity_album to store track name
@@ -82,6 +155,9 @@ impl ImageCache {
 
     pub fn update_access_time(&self, request: &ImageRequest) -> Result<()> {
         let conn = self.pool.get()?;
+        if crate::db::is_read_only(&conn) {
+            return Ok(());
+        }
         let now = Utc::now().timestamp();
 
         // For tracks, we use entity_album to store track name
@@ -106,4 +182,189 @@ impl ImageCache {
 
         Ok(())
     }
+
+    /// Delete cached lookups matching `filter`, returning how many were removed
+    pub fn delete(&self, filter: &ImageCacheFilter) -> Result<usize> {
+        let conn = self.pool.get()?;
+
+        let deleted = conn.execute(
+            "DELETE FROM image_cache
+             WHERE (?1 IS NULL OR entity_type = ?1)
+               AND (?2 IS NULL OR entity_name = ?2)
+               AND (?3 IS NULL OR entity_album = ?3)",
+            params![
+                filter.entity_type.map(|t| t.as_str()),
+                filter.artist_name,
+                filter.secondary_name
+            ],
+        )?;
+
+        Ok(deleted)
+    }
+
+    /// Total, found and not-found lookup counts
+    pub fn counts(&self) -> Result<(i64, i64, i64)> {
+        let conn = self.pool.get()?;
+
+        let counts = conn.query_row(
+            "SELECT COUNT(*),
+                    COUNT(image_url),
+                    COUNT(*) - COUNT(image_url)
+             FROM image_cache",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        Ok(counts)
+    }
+}
+
+/// Resized proxy artwork, keyed by source URL and edge length
+pub struct ThumbnailCache {
+    pool: DbPool,
+}
+
+impl ThumbnailCache {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn get(&self, source_url: &str, size: u32) -> Result<Option<Thumbnail>> {
+        let conn = self.pool.get()?;
+
+        let result = conn.query_row(
+            "SELECT content_type, data FROM image_thumbnails
+             WHERE source_url = ?1 AND size = ?2",
+            params![source_url, size],
+            |row| {
+                Ok(Thumbnail {
+                    content_type: row.get(0)?,
+                    data: row.get(1)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(thumbnail) if crate::db::is_read_only(&conn) => Ok(Some(thumbnail)),
+            Ok(thumbnail) => {
+                let _ = conn.execute(
+                    "UPDATE image_thumbnails SET last_accessed = ?1
+                     WHERE source_url = ?2 AND size = ?3",
+                    params![Utc::now().timestamp(), source_url, size],
+                );
+                Ok(Some(thumbnail))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store a thumbnail; a read-only instance resizes on every request instead
+    pub fn set(&self, source_url: &str, size: u32, thumbnail: &Thumbnail) -> Result<()> {
+        let conn = self.pool.get()?;
+        if crate::db::is_read_only(&conn) {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT INTO image_thumbnails
+             (source_url, size, content_type, data, fetched_at, last_accessed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(source_url, size)
+             DO UPDATE SET content_type = ?3, data = ?4, fetched_at = ?5, last_accessed = ?6",
+            params![
+                source_url,
+                size,
+                thumbnail.content_type,
+                thumbnail.data,
+                now,
+                now
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Delete every stored thumbnail, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let conn = self.pool.get()?;
+        Ok(conn.execute("DELETE FROM image_thumbnails", [])?)
+    }
+
+    /// Number of thumbnails and their total size in bytes
+    pub fn counts(&self) -> Result<(i64, i64)> {
+        let conn = self.pool.get()?;
+
+        let counts = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(data)), 0) FROM image_thumbnails",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::types::EntityType;
+    use crate::test_utils::setup_pool;
+
+    #[test]
+    fn test_memory_cache_evicts_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+
+        // Touch "a" so "b" becomes the oldest entry
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+
+    #[test]
+    fn test_memory_cache_overwrites_without_evicting() {
+        let cache = MemoryCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("a", 10);
+
+        assert_eq!(cache.get(&"a"), Some(10));
+        assert_eq!(cache.get(&"b"), Some(2));
+    }
+
+    #[test]
+    fn test_delete_by_filter() {
+        let (pool, _temp) = setup_pool();
+        let cache = ImageCache::new(pool);
+
+        let artist = ImageRequest::artist("Nina Simone".to_string());
+        let album = ImageRequest::album("Nina Simone".to_string(), "Pastel Blues".to_string());
+        let other = ImageRequest::artist("Bill Evans".to_string());
+        cache
+            .set(&artist, Some("https://example.com/nina.jpg".to_string()))
+            .unwrap();
+        cache.set(&album, None).unwrap();
+        cache
+            .set(&other, Some("https://example.com/bill.jpg".to_string()))
+            .unwrap();
+        assert_eq!(cache.counts().unwrap(), (3, 2, 1));
+
+        let filter = ImageCacheFilter {
+            entity_type: Some(EntityType::Album),
+            artist_name: Some("Nina Simone".to_string()),
+            secondary_name: None,
+        };
+        assert_eq!(cache.delete(&filter).unwrap(), 1);
+        assert!(cache.get(&album).unwrap().is_none());
+        assert!(cache.get(&artist).unwrap().is_some());
+
+        assert_eq!(cache.delete(&ImageCacheFilter::default()).unwrap(), 2);
+        assert_eq!(cache.counts().unwrap(), (0, 0, 0));
+    }
 }
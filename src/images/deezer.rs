@@ -1,5 +1,8 @@
 use anyhow::Result;
 use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::importers::HttpFetch;
 
 #[derive(Debug, Deserialize)]
 struct ArtistSearchResponse {
@@ -24,32 +27,34 @@ struct AlbumSearchResult {
 }
 
 pub struct DeezerImageClient {
-    client: reqwest::Client,
+    http: Arc<dyn HttpFetch>,
 }
 
 impl DeezerImageClient {
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(10))
-                .build()
-                .unwrap(),
+            http: Arc::new(
+                reqwest::Client::builder()
+                    .timeout(std::time::Duration::from_secs(10))
+                    .build()
+                    .unwrap(),
+            ),
         }
     }
 
+    /// Look images up through this client instead of the network
+    pub fn with_http(mut self, http: Arc<dyn HttpFetch>) -> Self {
+        self.http = http;
+        self
+    }
+
     pub async fn fetch_artist_image(&self, artist_name: &str) -> Result<Option<String>> {
         let search_url = format!(
             "https://api.deezer.com/search/artist?q={}",
             urlencoding::encode(artist_name)
         );
 
-        let response = self
-            .client
-            .get(&search_url)
-            .send()
-            .await?
-            .json::<ArtistSearchResponse>()
-            .await?;
+        let response: ArtistSearchResponse = self.http.get(&search_url, &[]).await?.json()?;
 
         if let Some(artist) = response.data.first() {
             return Ok(artist.picture_xl.clone());
@@ -69,13 +74,7 @@ impl DeezerImageClient {
             urlencoding::encode(&search_query)
         );
 
-        let response = self
-            .client
-            .get(&search_url)
-            .send()
-            .await?
-            .json::<AlbumSearchResponse>()
-            .await?;
+        let response: AlbumSearchResponse = self.http.get(&search_url, &[]).await?.json()?;
 
         if let Some(album) = response.data.first() {
             return Ok(album.cover_xl.clone());
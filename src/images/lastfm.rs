@@ -1,7 +1,9 @@
 use anyhow::Result;
 use serde::Deserialize;
+use std::sync::Arc;
 
 use super::types::ImageSize;
+use crate::importers::HttpFetch;
 
 #[derive(Debug, Deserialize)]
 struct AlbumInfo {
@@ -40,20 +42,28 @@ struct Image {
 
 pub struct LastFmImageClient {
     api_key: String,
-    client: reqwest::Client,
+    http: Arc<dyn HttpFetch>,
 }
 
 impl LastFmImageClient {
     pub fn new(api_key: String) -> Self {
         Self {
             api_key,
-            client: reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(5))
-                .build()
-                .unwrap(),
+            http: Arc::new(
+                reqwest::Client::builder()
+                    .timeout(std::time::Duration::from_secs(5))
+                    .build()
+                    .unwrap(),
+            ),
         }
     }
 
+    /// Look images up through this client instead of the network
+    pub fn with_http(mut self, http: Arc<dyn HttpFetch>) -> Self {
+        self.http = http;
+        self
+    }
+
     pub async fn fetch_album_image(
         &self,
         artist: &str,
@@ -71,13 +81,7 @@ impl LastFmImageClient {
             self.api_key
         );
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await?
-            .json::<AlbumInfo>()
-            .await?;
+        let response: AlbumInfo = self.http.get(&url, &[]).await?.json()?;
 
         Ok(self.extract_image_url(&response.album.image, size))
     }
@@ -99,13 +103,7 @@ impl LastFmImageClient {
             self.api_key
         );
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await?
-            .json::<TrackInfo>()
-            .await?;
+        let response: TrackInfo = self.http.get(&url, &[]).await?.json()?;
 
         // Track images come from the album data within track info
         if let Some(album) = &response.track.album {
@@ -1,41 +1,192 @@
 mod cache;
 mod deezer;
 mod lastfm;
+mod provider;
+mod proxy;
+mod singleflight;
 mod types;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::db::DbPool;
+use crate::importers::HttpFetch;
 
-use cache::ImageCache;
+use cache::{ImageCache, MemoryCache, ThumbnailCache};
 use deezer::DeezerImageClient;
 use lastfm::LastFmImageClient;
-pub use types::{EntityType, ImageRequest};
+pub use provider::ImageProvider;
+#[cfg(test)]
+pub use provider::StubImages;
+pub(crate) use proxy::{is_public_host, public_client};
+pub use proxy::{parse_source_url, thumbnail_size};
+use singleflight::SingleFlight;
+pub use types::{
+    EntityType, ImageCacheFilter, ImageCacheStats, ImageRequest, ImageSize, Thumbnail,
+};
+
+/// Image URL lookups kept in memory; stats pages ask for dozens at a time
+const MEMORY_CACHE_CAPACITY: usize = 4096;
+
+/// Entity type, artist, album or track name, and size of an image lookup
+type ImageKey = (&'static str, String, Option<String>, &'static str);
+
+fn image_key(request: &ImageRequest) -> ImageKey {
+    let secondary = match request.entity_type {
+        EntityType::Track => request.track_name.clone(),
+        _ => request.album_name.clone(),
+    };
+    (
+        request.entity_type.as_str(),
+        request.artist_name.clone(),
+        secondary,
+        request.size.as_str(),
+    )
+}
+
+/// Where image lookups were answered from since startup
+#[derive(Default)]
+struct LookupCounters {
+    memory_hits: AtomicU64,
+    database_hits: AtomicU64,
+    misses: AtomicU64,
+}
 
 pub struct ImageService {
+    pool: DbPool,
+    memory: MemoryCache<ImageKey, Option<String>>,
+    counters: LookupCounters,
+    in_flight: SingleFlight<ImageKey, Option<String>>,
     cache: ImageCache,
     lastfm_client: LastFmImageClient,
     deezer_client: DeezerImageClient,
+    thumbnails: ThumbnailCache,
+    proxy_client: reqwest::Client,
 }
 
 impl ImageService {
     pub fn new(pool: DbPool, lastfm_api_key: String) -> Self {
         Self {
-            cache: ImageCache::new(pool),
+            pool: pool.clone(),
+            memory: MemoryCache::new(MEMORY_CACHE_CAPACITY),
+            counters: LookupCounters::default(),
+            in_flight: SingleFlight::new(),
+            cache: ImageCache::new(pool.clone()),
             lastfm_client: LastFmImageClient::new(lastfm_api_key),
             deezer_client: DeezerImageClient::new(),
+            thumbnails: ThumbnailCache::new(pool),
+            proxy_client: proxy::public_client(std::time::Duration::from_secs(10)),
         }
     }
 
+    /// Query the artwork providers through this client instead of the network
+    pub fn with_http(mut self, http: Arc<dyn HttpFetch>) -> Self {
+        self.lastfm_client = self.lastfm_client.with_http(http.clone());
+        self.deezer_client = self.deezer_client.with_http(http);
+        self
+    }
+
     pub async fn get_image_url(&self, request: ImageRequest) -> Result<Option<String>> {
-        // 1. Check cache first
-        if let Some(cached) = self.cache.get(&request)? {
+        // 1. Check the in-memory cache, then the database
+        let key = image_key(&request);
+        if let Some(url) = self.memory.get(&key) {
+            self.counters.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(url);
+        }
+
+        // Concurrent requests for the same uncached image share one lookup
+        self.in_flight
+            .run(key.clone(), self.lookup_image_url(&request, key))
+            .await
+    }
+
+    /// Image for `request`, falling back to related artwork when the entity
+    /// has none: artists use their most played album's cover, tracks try the
+    /// artist image and then the track's most common album
+    pub async fn get_best_image(&self, request: ImageRequest) -> Option<String> {
+        let entity_type = request.entity_type;
+        let artist = request.artist_name.clone();
+        let track = request.track_name.clone().unwrap_or_default();
+
+        if let Some(url) = self.get_image_url(request).await.ok().flatten() {
+            return Some(url);
+        }
+
+        let album = match entity_type {
+            EntityType::Album => return None,
+            EntityType::Artist => crate::db::get_top_album_for_artist(&self.pool, &artist),
+            EntityType::Track => {
+                let artist_image = self
+                    .get_image_url(ImageRequest::artist(artist.clone()))
+                    .await
+                    .ok()
+                    .flatten();
+                if artist_image.is_some() {
+                    return artist_image;
+                }
+                crate::db::get_album_for_track(&self.pool, &artist, &track)
+            }
+        }
+        .ok()
+        .flatten()?;
+
+        self.get_image_url(ImageRequest::album(artist, album))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Try each artwork provider in the instance's preferred order (Last.fm,
+    /// then Deezer, unless changed) until one has the cover
+    async fn fetch_album_image(
+        &self,
+        artist: &str,
+        album: &str,
+        size: ImageSize,
+    ) -> Option<String> {
+        let providers = crate::settings::load(&self.pool)
+            .map(|settings| settings.image_providers)
+            .unwrap_or_else(|_| {
+                crate::settings::IMAGE_PROVIDERS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect()
+            });
+
+        for provider in providers {
+            let url = match provider.as_str() {
+                "lastfm" => {
+                    self.lastfm_client
+                        .fetch_album_image(artist, album, size)
+                        .await
+                }
+                "deezer" => self.deezer_client.fetch_album_image(artist, album).await,
+                _ => continue,
+            };
+            if let Ok(Some(url)) = url {
+                return Some(url);
+            }
+        }
+        None
+    }
+
+    async fn lookup_image_url(
+        &self,
+        request: &ImageRequest,
+        key: ImageKey,
+    ) -> Result<Option<String>> {
+        if let Some(cached) = self.cache.get(request)? {
             // Update last_accessed timestamp for LRU
-            let _ = self.cache.update_access_time(&request);
+            let _ = self.cache.update_access_time(request);
+            self.counters.database_hits.fetch_add(1, Ordering::Relaxed);
+            self.memory.insert(key, cached.url.clone());
             return Ok(cached.url);
         }
 
         // 2. Fetch from appropriate source
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
         let url = match request.entity_type {
             EntityType::Artist => {
                 // Use Deezer for artist images (free, no API key required, reliable)
@@ -47,24 +198,8 @@ impl ImageService {
             }
             EntityType::Album => {
                 if let Some(album_name) = &request.album_name {
-                    // Try Last.fm first for albums (still works)
-                    let mut url = self
-                        .lastfm_client
-                        .fetch_album_image(&request.artist_name, album_name, request.size)
+                    self.fetch_album_image(&request.artist_name, album_name, request.size)
                         .await
-                        .ok()
-                        .flatten();
-
-                    // Fallback to Deezer if Last.fm fails
-                    if url.is_none() {
-                        url = self
-                            .deezer_client
-                            .fetch_album_image(&request.artist_name, album_name)
-                            .await
-                            .ok()
-                            .flatten();
-                    }
-                    url
                 } else {
                     None
                 }
@@ -83,8 +218,185 @@ impl ImageService {
         };
 
         // 3. Cache the result (even if None, to avoid repeated lookups)
-        self.cache.set(&request, url.clone())?;
+        self.cache.set(request, url.clone())?;
+        self.memory.insert(key, url.clone());
 
         Ok(url)
     }
+
+    /// Entry counts, stored sizes and hit rates of the image caches
+    pub fn cache_stats(&self) -> Result<ImageCacheStats> {
+        let (entries, entries_with_image, entries_without_image) = self.cache.counts()?;
+        let (thumbnails, thumbnail_bytes) = self.thumbnails.counts()?;
+
+        let memory_hits = self.counters.memory_hits.load(Ordering::Relaxed);
+        let database_hits = self.counters.database_hits.load(Ordering::Relaxed);
+        let misses = self.counters.misses.load(Ordering::Relaxed);
+        let lookups = memory_hits + database_hits + misses;
+        let hit_rate = if lookups > 0 {
+            (memory_hits + database_hits) as f64 / lookups as f64
+        } else {
+            0.0
+        };
+
+        Ok(ImageCacheStats {
+            entries,
+            entries_with_image,
+            entries_without_image,
+            thumbnails,
+            thumbnail_bytes,
+            memory_entries: self.memory.len(),
+            memory_hits,
+            database_hits,
+            misses,
+            hit_rate,
+        })
+    }
+
+    /// Forget cached lookups matching `filter`. An empty filter purges
+    /// everything, proxy thumbnails included. Returns the number of rows removed
+    pub fn purge_cache(&self, filter: &ImageCacheFilter) -> Result<usize> {
+        let mut deleted = self.cache.delete(filter)?;
+        if filter.is_empty() {
+            deleted += self.thumbnails.clear()?;
+        }
+        self.memory.clear();
+
+        Ok(deleted)
+    }
+
+    /// Drop the cached lookup for `request` and fetch it again upstream
+    pub async fn refresh_image_url(&self, request: ImageRequest) -> Result<Option<String>> {
+        let key = image_key(&request);
+        self.cache.delete(&ImageCacheFilter {
+            entity_type: Some(request.entity_type),
+            artist_name: Some(key.1.clone()),
+            secondary_name: key.2.clone(),
+        })?;
+        self.memory.remove(&key);
+
+        self.get_image_url(request).await
+    }
+
+    /// Square thumbnail of the artwork at `source_url`, fetched and resized on
+    /// first request and served from the database afterwards
+    pub async fn get_thumbnail(&self, source_url: &Url, size: u32) -> Result<Thumbnail> {
+        if let Some(cached) = self.thumbnails.get(source_url.as_str(), size)? {
+            return Ok(cached);
+        }
+
+        let bytes = self.fetch_source_image(source_url).await?;
+        let thumbnail = tokio::task::spawn_blocking(move || proxy::make_thumbnail(&bytes, size))
+            .await
+            .context("Thumbnail task panicked")??;
+
+        self.thumbnails.set(source_url.as_str(), size, &thumbnail)?;
+
+        Ok(thumbnail)
+    }
+
+    async fn fetch_source_image(&self, source_url: &Url) -> Result<Vec<u8>> {
+        let mut response = self
+            .proxy_client
+            .get(source_url.clone())
+            .send()
+            .await
+            .context("Failed to fetch image")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Image host returned error: {}", response.status());
+        }
+        if response
+            .content_length()
+            .is_some_and(|len| len > proxy::MAX_SOURCE_BYTES as u64)
+        {
+            anyhow::bail!("Image is larger than {} bytes", proxy::MAX_SOURCE_BYTES);
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.context("Failed to read image")? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > proxy::MAX_SOURCE_BYTES {
+                anyhow::bail!("Image is larger than {} bytes", proxy::MAX_SOURCE_BYTES);
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+    use chrono::Utc;
+
+    /// Service whose cache already knows the answers, so no lookup goes upstream
+    fn seeded_service(pool: &DbPool, cached: &[(ImageRequest, Option<&str>)]) -> ImageService {
+        let cache = ImageCache::new(pool.clone());
+        for (request, url) in cached {
+            cache.set(request, url.map(str::to_string)).unwrap();
+        }
+        ImageService::new(pool.clone(), String::new())
+    }
+
+    #[tokio::test]
+    async fn test_best_image_falls_back_to_album_cover() {
+        let (pool, _temp_file) = setup_pool();
+        let scrobble = Scrobble::new(
+            "Slowdive".to_string(),
+            "Alison".to_string(),
+            Utc::now(),
+            "lastfm".to_string(),
+        )
+        .with_album("Souvlaki".to_string());
+        crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+
+        let artist = || ImageRequest::artist("Slowdive".to_string());
+        let track = || ImageRequest::track("Slowdive".to_string(), "Alison".to_string());
+        let album = || ImageRequest::album("Slowdive".to_string(), "Souvlaki".to_string());
+        let service = seeded_service(
+            &pool,
+            &[
+                (artist(), None),
+                (track(), None),
+                (album(), Some("https://example.com/souvlaki.jpg")),
+            ],
+        );
+
+        let cover = Some("https://example.com/souvlaki.jpg".to_string());
+        assert_eq!(service.get_best_image(track()).await, cover);
+        assert_eq!(service.get_best_image(artist()).await, cover);
+    }
+
+    #[tokio::test]
+    async fn test_best_image_prefers_artist_for_tracks() {
+        let (pool, _temp_file) = setup_pool();
+        let track = ImageRequest::track("Slowdive".to_string(), "Alison".to_string());
+        let service = seeded_service(
+            &pool,
+            &[
+                (track.clone(), None),
+                (
+                    ImageRequest::artist("Slowdive".to_string()),
+                    Some("https://example.com/slowdive.jpg"),
+                ),
+            ],
+        );
+
+        assert_eq!(
+            service.get_best_image(track).await.as_deref(),
+            Some("https://example.com/slowdive.jpg")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_best_image_has_no_fallback_for_albums() {
+        let (pool, _temp_file) = setup_pool();
+        let album = ImageRequest::album("Slowdive".to_string(), "Pygmalion".to_string());
+        let service = seeded_service(&pool, &[(album.clone(), None)]);
+
+        assert_eq!(service.get_best_image(album).await, None);
+    }
 }
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use reqwest::Url;
+
+use super::{ImageCacheFilter, ImageCacheStats, ImageRequest, ImageService, Thumbnail};
+use crate::importers::http::BoxFuture;
+
+/// Artwork as the API serves it: lookups, thumbnails and the cache behind
+/// them. Handlers hold a trait object, so tests can answer with canned images
+pub trait ImageProvider: Send + Sync {
+    /// Image for exactly `request`, `None` if no provider has one
+    fn get_image_url(&self, request: ImageRequest) -> BoxFuture<'_, Result<Option<String>>>;
+
+    /// Image for `request`, falling back to related artwork when it has none
+    fn get_best_image(&self, request: ImageRequest) -> BoxFuture<'_, Option<String>>;
+
+    /// Look `request` up again, ignoring what was cached
+    fn refresh_image_url(&self, request: ImageRequest) -> BoxFuture<'_, Result<Option<String>>>;
+
+    /// Square thumbnail of the artwork at `source_url`
+    fn get_thumbnail<'a>(
+        &'a self,
+        source_url: &'a Url,
+        size: u32,
+    ) -> BoxFuture<'a, Result<Thumbnail>>;
+
+    fn cache_stats(&self) -> Result<ImageCacheStats>;
+
+    /// Forget cached lookups matching `filter`, returning how many were removed
+    fn purge_cache(&self, filter: &ImageCacheFilter) -> Result<usize>;
+}
+
+impl ImageProvider for ImageService {
+    fn get_image_url(&self, request: ImageRequest) -> BoxFuture<'_, Result<Option<String>>> {
+        Box::pin(ImageService::get_image_url(self, request))
+    }
+
+    fn get_best_image(&self, request: ImageRequest) -> BoxFuture<'_, Option<String>> {
+        Box::pin(ImageService::get_best_image(self, request))
+    }
+
+    fn refresh_image_url(&self, request: ImageRequest) -> BoxFuture<'_, Result<Option<String>>> {
+        Box::pin(ImageService::refresh_image_url(self, request))
+    }
+
+    fn get_thumbnail<'a>(
+        &'a self,
+        source_url: &'a Url,
+        size: u32,
+    ) -> BoxFuture<'a, Result<Thumbnail>> {
+        Box::pin(ImageService::get_thumbnail(self, source_url, size))
+    }
+
+    fn cache_stats(&self) -> Result<ImageCacheStats> {
+        ImageService::cache_stats(self)
+    }
+
+    fn purge_cache(&self, filter: &ImageCacheFilter) -> Result<usize> {
+        ImageService::purge_cache(self, filter)
+    }
+}
+
+#[cfg(test)]
+pub use stub::StubImages;
+
+#[cfg(test)]
+mod stub {
+    use super::*;
+    use crate::images::EntityType;
+    use std::collections::HashMap;
+
+    /// Fixed artwork for a few artists and albums, with no cache or network
+    /// behind it. Tracks fall back to their artist's picture
+    #[derive(Default)]
+    pub struct StubImages {
+        artists: HashMap<String, String>,
+        albums: HashMap<(String, String), String>,
+    }
+
+    impl StubImages {
+        pub fn artist(mut self, artist: &str, url: &str) -> Self {
+            self.artists.insert(artist.to_string(), url.to_string());
+            self
+        }
+
+        pub fn album(mut self, artist: &str, album: &str, url: &str) -> Self {
+            self.albums
+                .insert((artist.to_string(), album.to_string()), url.to_string());
+            self
+        }
+
+        fn lookup(&self, request: &ImageRequest) -> Option<String> {
+            match request.entity_type {
+                EntityType::Artist => self.artists.get(&request.artist_name).cloned(),
+                EntityType::Album => {
+                    let album = request.album_name.clone().unwrap_or_default();
+                    self.albums
+                        .get(&(request.artist_name.clone(), album))
+                        .cloned()
+                }
+                EntityType::Track => None,
+            }
+        }
+    }
+
+    impl ImageProvider for StubImages {
+        fn get_image_url(&self, request: ImageRequest) -> BoxFuture<'_, Result<Option<String>>> {
+            let url = self.lookup(&request);
+            Box::pin(async move { Ok(url) })
+        }
+
+        fn get_best_image(&self, request: ImageRequest) -> BoxFuture<'_, Option<String>> {
+            let url = self.lookup(&request).or_else(|| match request.entity_type {
+                EntityType::Track => self.artists.get(&request.artist_name).cloned(),
+                _ => None,
+            });
+            Box::pin(async move { url })
+        }
+
+        fn refresh_image_url(
+            &self,
+            request: ImageRequest,
+        ) -> BoxFuture<'_, Result<Option<String>>> {
+            self.get_image_url(request)
+        }
+
+        fn get_thumbnail<'a>(
+            &'a self,
+            source_url: &'a Url,
+            _size: u32,
+        ) -> BoxFuture<'a, Result<Thumbnail>> {
+            Box::pin(async move { anyhow::bail!("No stub thumbnail for {}", source_url) })
+        }
+
+        fn cache_stats(&self) -> Result<ImageCacheStats> {
+            Ok(ImageCacheStats::default())
+        }
+
+        fn purge_cache(&self, _filter: &ImageCacheFilter) -> Result<usize> {
+            Ok(0)
+        }
+    }
+}
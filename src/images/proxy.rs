@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::types::Thumbnail;
+
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 300;
+pub const MIN_THUMBNAIL_SIZE: u32 = 32;
+pub const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Largest source image the proxy will download
+pub const MAX_SOURCE_BYTES: usize = 10 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Edge length for a thumbnail, clamped so callers can't ask for huge renders
+pub fn thumbnail_size(requested: Option<u32>) -> u32 {
+    requested
+        .unwrap_or(DEFAULT_THUMBNAIL_SIZE)
+        .clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE)
+}
+
+/// Parse an artwork URL, accepting only http(s) on hosts that aren't obviously
+/// local. This is only the first check: `public_client` vets the addresses a
+/// host name resolves to when it connects
+pub fn parse_source_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url).context("Invalid image URL")?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!("Unsupported image URL scheme: {}", parsed.scheme());
+    }
+    if !is_public_host(&parsed) {
+        anyhow::bail!("Image URL host is not allowed");
+    }
+
+    Ok(parsed)
+}
+
+pub(crate) fn is_public_host(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => is_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 0.0.0.0/8 "this network" and 100.64.0.0/10 carrier-grade NAT
+                || first == 0
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                // fc00::/7 unique local and fe80::/10 link local
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves host names like the system resolver, but fails unless every
+/// address the name points at is public, so DNS can't smuggle in a local one
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+async fn resolve_public(host: &str) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?
+        .collect();
+
+    if addrs.is_empty() {
+        anyhow::bail!("{} has no addresses", host);
+    }
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        anyhow::bail!("{} resolves to an address that is not allowed", host);
+    }
+
+    Ok(addrs)
+}
+
+/// HTTP client for URLs that came from outside: it only connects to public
+/// addresses, and re-checks every redirect hop (IP literals in a `Location`
+/// skip DNS, so the resolver alone wouldn't see them)
+pub(crate) fn public_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= 5 || !is_public_host(attempt.url()) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .unwrap()
+}
+
+/// Center-crop `bytes` to a square and scale it to `size` pixels. Opaque
+/// images are re-encoded as JPEG, ones with transparency as PNG
+pub fn make_thumbnail(bytes: &[u8], size: u32) -> Result<Thumbnail> {
+    let source = image::load_from_memory(bytes).context("Failed to decode image")?;
+    let resized = source.resize_to_fill(size, size, FilterType::Lanczos3);
+
+    let mut data = Vec::new();
+    let content_type = if resized.color().has_alpha() {
+        resized
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .context("Failed to encode PNG thumbnail")?;
+        "image/png"
+    } else {
+        let rgb = DynamicImage::ImageRgb8(resized.to_rgb8());
+        rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY))
+            .context("Failed to encode JPEG thumbnail")?;
+        "image/jpeg"
+    };
+
+    Ok(Thumbnail {
+        content_type: content_type.to_string(),
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn encode(image: DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_thumbnail_size_is_clamped() {
+        assert_eq!(thumbnail_size(None), DEFAULT_THUMBNAIL_SIZE);
+        assert_eq!(thumbnail_size(Some(1)), MIN_THUMBNAIL_SIZE);
+        assert_eq!(thumbnail_size(Some(64)), 64);
+        assert_eq!(thumbnail_size(Some(100_000)), MAX_THUMBNAIL_SIZE);
+    }
+
+    #[test]
+    fn test_parse_source_url() {
+        assert!(parse_source_url("https://e-cdns-images.dzcdn.net/cover.jpg").is_ok());
+        assert!(parse_source_url("http://lastfm.freetls.fastly.net/i/u/300x300/a.png").is_ok());
+
+        assert!(parse_source_url("not a url").is_err());
+        assert!(parse_source_url("file:///etc/passwd").is_err());
+        assert!(parse_source_url("http://localhost:3000/api/me").is_err());
+        assert!(parse_source_url("http://127.0.0.1/cover.jpg").is_err());
+        assert!(parse_source_url("http://192.168.1.10/cover.jpg").is_err());
+        assert!(parse_source_url("http://169.254.169.254/latest").is_err());
+        assert!(parse_source_url("http://[::1]/cover.jpg").is_err());
+        assert!(parse_source_url("http://100.64.0.1/cover.jpg").is_err());
+        assert!(parse_source_url("http://100.127.255.254/cover.jpg").is_err());
+        assert!(parse_source_url("http://0.0.0.0/cover.jpg").is_err());
+        assert!(parse_source_url("http://[::ffff:127.0.0.1]/cover.jpg").is_err());
+        assert!(parse_source_url("http://[::ffff:10.0.0.1]/cover.jpg").is_err());
+        assert!(parse_source_url("http://100.128.0.1/cover.jpg").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_names_resolving_to_local_addresses_are_refused() {
+        assert!(resolve_public("localhost").await.is_err());
+        assert!(resolve_public("127.0.0.1").await.is_err());
+        assert!(resolve_public("::ffff:127.0.0.1").await.is_err());
+
+        // A server the default client reaches, but the public one won't
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://localhost:{}/cover.jpg",
+            listener.local_addr().unwrap().port()
+        );
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        assert!(reqwest::get(&url).await.unwrap().status().is_success());
+        assert!(
+            public_client(Duration::from_secs(5))
+                .get(&url)
+                .send()
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_is_square_jpeg() {
+        let wide = RgbImage::from_pixel(200, 100, Rgb([200, 30, 30]));
+        let thumbnail = make_thumbnail(&encode(DynamicImage::ImageRgb8(wide)), 64).unwrap();
+
+        assert_eq!(thumbnail.content_type, "image/jpeg");
+        let decoded = image::load_from_memory(&thumbnail.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 64));
+    }
+
+    #[test]
+    fn test_transparent_thumbnail_stays_png() {
+        let icon = RgbaImage::from_pixel(40, 80, Rgba([0, 0, 0, 0]));
+        let thumbnail = make_thumbnail(&encode(DynamicImage::ImageRgba8(icon)), 32).unwrap();
+
+        assert_eq!(thumbnail.content_type, "image/png");
+        let decoded = image::load_from_memory(&thumbnail.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 32));
+    }
+
+    #[test]
+    fn test_garbage_is_rejected() {
+        assert!(make_thumbnail(b"<html>not an image</html>", 64).is_err());
+    }
+}
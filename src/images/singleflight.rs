@@ -0,0 +1,97 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Collapses concurrent lookups for the same key into one: the first caller
+/// runs the future and everyone waiting on that key gets a copy of its result
+pub struct SingleFlight<K, V> {
+    calls: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> SingleFlight<K, V> {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `lookup` unless a call for `key` is already in flight. A failed
+    /// call isn't shared; the next waiter runs its own lookup instead
+    pub async fn run<F>(&self, key: K, lookup: F) -> Result<V>
+    where
+        F: Future<Output = Result<V>>,
+    {
+        let cell = self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let result = cell.get_or_try_init(|| lookup).await.cloned();
+
+        let mut calls = self.calls.lock().unwrap();
+        if calls
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, &cell))
+        {
+            calls.remove(&key);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_lookup() {
+        let flight = SingleFlight::new();
+        let lookups = AtomicUsize::new(0);
+
+        let lookup = || async {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(Some("https://example.com/cover.jpg".to_string()))
+        };
+
+        let (a, b, c) = tokio::join!(
+            flight.run("Miles Davis", lookup()),
+            flight.run("Miles Davis", lookup()),
+            flight.run("Miles Davis", lookup()),
+        );
+
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert!(c.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_finished_calls_are_forgotten() {
+        let flight = SingleFlight::new();
+
+        assert_eq!(flight.run("key", async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(flight.run("key", async { Ok(2) }).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_calls_are_not_shared() {
+        let flight = SingleFlight::new();
+
+        assert!(
+            flight
+                .run("key", async { Err(anyhow::anyhow!("upstream down")) })
+                .await
+                .is_err()
+        );
+        assert_eq!(flight.run("key", async { Ok(3) }).await.unwrap(), 3);
+    }
+}
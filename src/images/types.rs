@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EntityType {
     Artist,
     Album,
@@ -17,6 +18,19 @@ impl EntityType {
     }
 }
 
+impl FromStr for EntityType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "artist" => Ok(EntityType::Artist),
+            "album" => Ok(EntityType::Album),
+            "track" => Ok(EntityType::Track),
+            other => Err(anyhow::anyhow!("Unknown image entity type: {}", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImageSize {
     ExtraLarge, // 300x300 from Last.fm
@@ -76,3 +90,41 @@ pub struct ImageMetadata {
     pub url: Option<String>,
     pub fetched_at: i64,
 }
+
+/// Resized artwork served by the image proxy
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Which cached image lookups to purge; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct ImageCacheFilter {
+    pub entity_type: Option<EntityType>,
+    pub artist_name: Option<String>,
+    /// Album name, or track name for track images
+    pub secondary_name: Option<String>,
+}
+
+impl ImageCacheFilter {
+    pub fn is_empty(&self) -> bool {
+        self.entity_type.is_none() && self.artist_name.is_none() && self.secondary_name.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageCacheStats {
+    pub entries: i64,
+    pub entries_with_image: i64,
+    pub entries_without_image: i64,
+    pub thumbnails: i64,
+    /// Bytes of stored thumbnail data
+    pub thumbnail_bytes: i64,
+    pub memory_entries: usize,
+    pub memory_hits: u64,
+    pub database_hits: u64,
+    pub misses: u64,
+    /// Share of lookups since startup answered without an upstream fetch
+    pub hit_rate: f64,
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Status and body of a fetched API page
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+/// How importers reach the Last.fm and ListenBrainz APIs; tests swap the live
+/// client for recorded responses
+pub trait HttpFetch: Send + Sync {
+    /// GET `url` with extra `(name, value)` headers
+    fn get<'a>(
+        &'a self,
+        url: &'a str,
+        headers: &'a [(&'static str, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse>>;
+
+    /// POST `form` url-encoded to `url`, with extra `(name, value)` headers
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        headers: &'a [(&'static str, String)],
+        form: &'a [(&'static str, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse>>;
+}
+
+impl HttpFetch for reqwest::Client {
+    fn get<'a>(
+        &'a self,
+        url: &'a str,
+        headers: &'a [(&'static str, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut request = reqwest::Client::get(self, url);
+            for (name, value) in headers {
+                request = request.header(*name, value);
+            }
+
+            let response = request.send().await?;
+            Ok(HttpResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        })
+    }
+
+    fn post_form<'a>(
+        &'a self,
+        url: &'a str,
+        headers: &'a [(&'static str, String)],
+        form: &'a [(&'static str, String)],
+    ) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut request = reqwest::Client::post(self, url).form(form);
+            for (name, value) in headers {
+                request = request.header(*name, value);
+            }
+
+            let response = request.send().await?;
+            Ok(HttpResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub use mock::MockHttp;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Canned responses served in order to URLs containing a pattern; the
+    /// first pattern with responses left wins
+    #[derive(Default)]
+    pub struct MockHttp {
+        routes: Mutex<Vec<(String, VecDeque<HttpResponse>)>>,
+        requests: Mutex<Vec<String>>,
+        forms: Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    impl MockHttp {
+        /// Answer the next request whose URL contains `pattern` with `body`
+        pub fn respond(self, pattern: &str, status: u16, body: &str) -> Self {
+            self.add_response(pattern, status, body);
+            self
+        }
+
+        /// Like `respond`, for a mock already handed out, e.g. when the body
+        /// depends on an earlier request
+        pub fn add_response(&self, pattern: &str, status: u16, body: &str) {
+            let response = HttpResponse {
+                status: reqwest::StatusCode::from_u16(status).unwrap(),
+                body: body.to_string(),
+            };
+            let mut routes = self.routes.lock().unwrap();
+            match routes.iter_mut().find(|(p, _)| p == pattern) {
+                Some((_, queue)) => queue.push_back(response),
+                None => routes.push((pattern.to_string(), VecDeque::from([response]))),
+            }
+        }
+
+        /// URLs requested so far
+        pub fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        /// Forms posted so far
+        pub fn forms(&self) -> Vec<Vec<(String, String)>> {
+            self.forms.lock().unwrap().clone()
+        }
+
+        fn next_response(&self, url: &str) -> Result<HttpResponse> {
+            self.requests.lock().unwrap().push(url.to_string());
+            self.routes
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|(pattern, queue)| url.contains(pattern.as_str()) && !queue.is_empty())
+                .and_then(|(_, queue)| queue.pop_front())
+                .ok_or_else(|| anyhow::anyhow!("No mock response for {}", url))
+        }
+    }
+
+    impl HttpFetch for MockHttp {
+        fn get<'a>(
+            &'a self,
+            url: &'a str,
+            _headers: &'a [(&'static str, String)],
+        ) -> BoxFuture<'a, Result<HttpResponse>> {
+            let response = self.next_response(url);
+            Box::pin(async move { response })
+        }
+
+        fn post_form<'a>(
+            &'a self,
+            url: &'a str,
+            _headers: &'a [(&'static str, String)],
+            form: &'a [(&'static str, String)],
+        ) -> BoxFuture<'a, Result<HttpResponse>> {
+            self.forms.lock().unwrap().push(
+                form.iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+            );
+            let response = self.next_response(url);
+            Box::pin(async move { response })
+        }
+    }
+}
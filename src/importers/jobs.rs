@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+
+use crate::db::DbPool;
+use crate::importers::{Checkpoints, LastFmImporter, ListenBrainzImporter};
+use crate::models::{ImportJob, ImportStatus, SyncConfig};
+use crate::normalizer::Normalizer;
+
+/// Run an import job from its checkpoint to the end and record the outcome.
+/// Returns the number of scrobbles imported by this run
+pub async fn run_import_job(
+    pool: &DbPool,
+    normalizer: &Normalizer,
+    job: &ImportJob,
+) -> Result<usize> {
+    let id = job.id.context("Import job has not been stored")?;
+    let checkpoints = Checkpoints::new(id, job.imported_count);
+
+    let result = match job.source.as_str() {
+        "lastfm" => match &job.api_key {
+            Some(api_key) => {
+                let importer = LastFmImporter::new(api_key.clone(), job.username.clone())
+                    .with_enrichment(job.enrich)
+                    .with_normalizer(normalizer.clone())
+                    .with_checkpoints(checkpoints);
+                let start_page = job.checkpoint.unwrap_or(1) as i32;
+                importer.import_all_from_page(pool, start_page).await
+            }
+            None => Err(anyhow::anyhow!("API key required for Last.fm")),
+        },
+        "listenbrainz" => {
+            let importer = ListenBrainzImporter::new(job.username.clone(), job.token.clone())
+                .with_normalizer(normalizer.clone())
+                .with_checkpoints(checkpoints);
+            importer.import_all_before(pool, job.checkpoint).await
+        }
+        other => Err(anyhow::anyhow!("Unknown source: {}", other)),
+    };
+
+    match &result {
+        Ok(count) => crate::db::set_import_job_status(
+            pool,
+            id,
+            ImportStatus::Completed,
+            Some(job.imported_count + *count as i64),
+            None,
+        )?,
+        Err(e) => crate::db::set_import_job_status(
+            pool,
+            id,
+            ImportStatus::Failed,
+            None,
+            Some(&format!("{:#}", e)),
+        )?,
+    }
+
+    result
+}
+
+/// Store a full import of the account behind a sync config and run it in the
+/// background. Returns the new job's id
+pub fn spawn_initial_import(
+    pool: DbPool,
+    normalizer: Normalizer,
+    config: &SyncConfig,
+) -> Result<i64> {
+    let mut job = ImportJob::new(config.source.clone(), config.username.clone());
+    job.api_key = config.api_key.clone();
+    job.token = config.token.clone();
+
+    let id = crate::db::insert_import_job(&pool, &job)?;
+    job.id = Some(id);
+
+    tokio::spawn(async move {
+        tracing::info!(
+            "Starting initial {} import for {}",
+            job.source,
+            job.username
+        );
+        match run_import_job(&pool, &normalizer, &job).await {
+            Ok(count) => tracing::info!("Initial import finished with {} scrobbles", count),
+            Err(e) => tracing::warn!("Initial import failed: {:#}", e),
+        }
+    });
+
+    Ok(id)
+}
+
+/// Pick up, in the background, the imports a crash or restart left running.
+/// Returns how many were resumed
+pub fn resume_interrupted_imports(pool: DbPool, normalizer: Normalizer) -> Result<usize> {
+    let jobs = crate::db::get_import_jobs(&pool, Some(ImportStatus::Running))?;
+    let resumed = jobs.len();
+
+    for job in jobs {
+        let pool = pool.clone();
+        let normalizer = normalizer.clone();
+        tokio::spawn(async move {
+            tracing::info!(
+                "Resuming {} import for {} from checkpoint {:?}",
+                job.source,
+                job.username,
+                job.checkpoint
+            );
+            match run_import_job(&pool, &normalizer, &job).await {
+                Ok(count) => tracing::info!("Resumed import finished with {} new scrobbles", count),
+                Err(e) => tracing::warn!("Resumed import failed: {:#}", e),
+            }
+        });
+    }
+
+    Ok(resumed)
+}
@@ -1,9 +1,14 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
+use crate::classifier::MediaClassifier;
 use crate::db::DbPool;
-use crate::models::Scrobble;
+use crate::importers::retry::{RetryPolicy, fetch_json_with_retry};
+use crate::importers::{Checkpoints, HttpFetch, IgnoreList, Lenient, is_usable_name, listen_time};
+use crate::models::{FieldError, NowPlaying, Scrobble};
+use crate::normalizer::Normalizer;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct LastFmResponse {
@@ -12,7 +17,7 @@ struct LastFmResponse {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct RecentTracks {
-    track: Vec<Track>,
+    track: Vec<Lenient<Track>>,
     #[serde(rename = "@attr")]
     attr: Option<Attributes>,
 }
@@ -59,10 +64,181 @@ struct TrackAttr {
     nowplaying: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct TopArtistsResponse {
+    topartists: TopArtists,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopArtists {
+    artist: Vec<TopArtist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopArtist {
+    name: String,
+    playcount: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTracksResponse {
+    toptracks: TopTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTracks {
+    track: Vec<TopTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTrack {
+    name: String,
+    playcount: String,
+    artist: TopTrackArtist,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTrackArtist {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTracksResponse {
+    lovedtracks: LovedTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTracks {
+    track: Vec<LovedTrack>,
+    #[serde(rename = "@attr")]
+    attr: Option<Attributes>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTrack {
+    name: String,
+    artist: TopTrackArtist,
+    date: Option<DateInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistTopTagsResponse {
+    toptags: ArtistTopTags,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistTopTags {
+    tag: Vec<ArtistTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistTag {
+    name: String,
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyChartListResponse {
+    weeklychartlist: WeeklyChartList,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyChartList {
+    chart: Vec<ChartWeek>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartWeek {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyArtistChartResponse {
+    weeklyartistchart: WeeklyArtistChart,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyArtistChart {
+    artist: Vec<Lenient<WeeklyArtist>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyArtist {
+    name: String,
+    playcount: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyTrackChartResponse {
+    weeklytrackchart: WeeklyTrackChart,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyTrackChart {
+    track: Vec<Lenient<WeeklyTrack>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyTrack {
+    artist: Artist,
+    name: String,
+    playcount: String,
+}
+
+/// Body Last.fm sends with a rejected request
+#[derive(Debug, Deserialize)]
+struct LastFmError {
+    error: i64,
+    message: String,
+}
+
+impl Track {
+    fn is_now_playing(&self) -> bool {
+        self.attr
+            .as_ref()
+            .and_then(|a| a.nowplaying.as_ref())
+            .is_some()
+    }
+}
+
+/// Scrobble for a played track, or `None` when the timestamp is missing or
+/// not a plausible Unix time, or the artist or title is blank
+fn track_to_scrobble(track: &Track) -> Option<Scrobble> {
+    let timestamp = track.date.as_ref()?.uts.trim().parse::<i64>().ok()?;
+    let listened_at = listen_time(timestamp)?;
+    if !is_usable_name(&track.artist.text) || !is_usable_name(&track.name) {
+        return None;
+    }
+
+    let mut scrobble = Scrobble::new(
+        track.artist.text.clone(),
+        track.name.clone(),
+        listened_at,
+        "lastfm".to_string(),
+    );
+
+    if let Some(album) = &track.album
+        && !album.text.is_empty()
+    {
+        scrobble = scrobble.with_album(album.text.clone());
+    }
+
+    // Use timestamp as unique identifier for deduplication
+    Some(scrobble.with_source_id(format!("lastfm_{}", timestamp)))
+}
+
+// Number of tags kept per artist during enrichment
+const MAX_TAGS_PER_ARTIST: usize = 10;
+
 pub struct LastFmImporter {
     api_key: String,
     username: String,
-    client: reqwest::Client,
+    http: Arc<dyn HttpFetch>,
+    retry: RetryPolicy,
+    checkpoints: Option<Checkpoints>,
+    enrich: bool,
+    normalizer: Normalizer,
 }
 
 impl LastFmImporter {
@@ -70,20 +246,56 @@ impl LastFmImporter {
         Self {
             api_key,
             username,
-            client: reqwest::Client::new(),
+            http: Arc::new(reqwest::Client::new()),
+            retry: RetryPolicy::default(),
+            checkpoints: None,
+            enrich: false,
+            normalizer: Normalizer::default(),
         }
     }
 
+    /// Clean up names with this normalizer before storing scrobbles
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Fetch API pages through this client instead of the network
+    pub fn with_http(mut self, http: Arc<dyn HttpFetch>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Retry failing requests with this policy instead of the default
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Save progress of full imports to an import job
+    pub fn with_checkpoints(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Also import loved tracks and artist tags after the scrobbles
+    pub fn with_enrichment(mut self, enrich: bool) -> Self {
+        self.enrich = enrich;
+        self
+    }
+
     pub async fn import_all(&self, pool: &DbPool) -> Result<usize> {
         self.import_all_from_page(pool, 1).await
     }
 
     /// Import all scrobbles starting from a specific page (for resuming failed imports)
+    #[tracing::instrument(name = "lastfm_import", skip(self, pool), fields(username = %self.username))]
     pub async fn import_all_from_page(&self, pool: &DbPool, start_page: i32) -> Result<usize> {
         let mut imported_count = 0;
+        let mut ignore = IgnoreList::load(pool, "lastfm")?;
+        let classifier = MediaClassifier::load(pool)?;
         let mut page = start_page;
         let per_page = 200;
-        const MAX_RETRIES: u32 = 3;
         const BATCH_SIZE: usize = 1000;
 
         let mut batch: Vec<Scrobble> = Vec::with_capacity(BATCH_SIZE);
@@ -96,141 +308,49 @@ impl LastFmImporter {
                 self.username, self.api_key, per_page, page
             );
 
-            // Retry logic for handling transient errors
-            let mut retry_count = 0;
-            let data = loop {
-                let response = self
-                    .client
-                    .get(&url)
-                    .send()
-                    .await
-                    .context("Failed to fetch from Last.fm");
-
-                match response {
-                    Ok(resp) => {
-                        let status = resp.status();
-
-                        // Handle rate limiting or server errors with retry
-                        if status.is_server_error()
-                            || status == reqwest::StatusCode::TOO_MANY_REQUESTS
-                        {
-                            retry_count += 1;
-                            if retry_count >= MAX_RETRIES {
-                                return Err(anyhow::anyhow!(
-                                    "Last.fm API returned error after {} retries: {} (stopped at page {})",
-                                    MAX_RETRIES,
-                                    status,
-                                    page
-                                ));
-                            }
-
-                            let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                            tracing::warn!(
-                                "Last.fm API error: {}, retrying in {:?} (attempt {}/{})",
-                                status,
-                                delay,
-                                retry_count,
-                                MAX_RETRIES
-                            );
-                            tokio::time::sleep(delay).await;
-                            continue;
-                        }
-
-                        if !status.is_success() {
-                            return Err(anyhow::anyhow!(
-                                "Last.fm API returned error: {} (stopped at page {})",
-                                status,
-                                page
-                            ));
-                        }
-
-                        // Parse response
-                        match resp.json::<LastFmResponse>().await {
-                            Ok(data) => break data,
-                            Err(e) => {
-                                retry_count += 1;
-                                if retry_count >= MAX_RETRIES {
-                                    return Err(anyhow::anyhow!(
-                                        "Failed to parse Last.fm response after {} retries: {} (stopped at page {})",
-                                        MAX_RETRIES,
-                                        e,
-                                        page
-                                    ));
-                                }
-
-                                let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                                tracing::warn!(
-                                    "Failed to parse response, retrying in {:?} (attempt {}/{})",
-                                    delay,
-                                    retry_count,
-                                    MAX_RETRIES
-                                );
-                                tokio::time::sleep(delay).await;
-                                continue;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        retry_count += 1;
-                        if retry_count >= MAX_RETRIES {
-                            return Err(anyhow::anyhow!(
-                                "Failed to fetch from Last.fm after {} retries: {} (stopped at page {}). You can resume from page {} by re-running the import.",
-                                MAX_RETRIES,
-                                e,
-                                page,
-                                page
-                            ));
-                        }
-
-                        let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                        tracing::warn!(
-                            "Network error: {}, retrying in {:?} (attempt {}/{})",
-                            e,
-                            delay,
-                            retry_count,
-                            MAX_RETRIES
-                        );
-                        tokio::time::sleep(delay).await;
-                    }
-                }
-            };
+            let data: LastFmResponse = fetch_json_with_retry(
+                self.http.as_ref(),
+                &url,
+                &[],
+                self.retry,
+                "Last.fm",
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Last.fm import stopped at page {}; re-run the import from that page to resume",
+                    page
+                )
+            })?;
 
             if data.recenttracks.track.is_empty() {
                 break;
             }
 
-            for track in &data.recenttracks.track {
+            let mut malformed = 0;
+            for entry in &data.recenttracks.track {
                 // Skip currently playing tracks
-                if track
-                    .attr
-                    .as_ref()
-                    .and_then(|a| a.nowplaying.as_ref())
-                    .is_some()
-                {
+                if entry.valid().is_some_and(Track::is_now_playing) {
                     continue;
                 }
 
-                if let Some(date_info) = &track.date
-                    && let Ok(timestamp) = date_info.uts.parse::<i64>()
-                {
-                    let mut scrobble = Scrobble::new(
-                        track.artist.text.clone(),
-                        track.name.clone(),
-                        DateTime::from_timestamp(timestamp, 0).unwrap_or_else(Utc::now),
-                        "lastfm".to_string(),
-                    );
-
-                    if let Some(album) = &track.album
-                        && !album.text.is_empty()
-                    {
-                        scrobble = scrobble.with_album(album.text.clone());
-                    }
-
-                    // Use timestamp as unique identifier for deduplication
-                    scrobble = scrobble.with_source_id(format!("lastfm_{}", timestamp));
+                let Some(scrobble) = entry.valid().and_then(track_to_scrobble) else {
+                    malformed += 1;
+                    continue;
+                };
 
-                    batch.push(scrobble);
+                let scrobble = classifier.apply(self.normalizer.normalize(scrobble));
+                if ignore.suppresses(&scrobble) {
+                    continue;
                 }
+                batch.push(scrobble);
+            }
+            if malformed > 0 {
+                tracing::warn!(
+                    "Skipped {} malformed tracks on Last.fm page {}",
+                    malformed,
+                    page
+                );
             }
 
             // Insert batch when it reaches the batch size
@@ -239,6 +359,11 @@ impl LastFmImporter {
                 imported_count += inserted;
                 tracing::info!("Inserted batch of {} scrobbles", inserted);
                 batch.clear();
+
+                // Every page so far is stored, so a restart can begin at the next
+                if let Some(checkpoints) = &self.checkpoints {
+                    checkpoints.save(pool, page as i64 + 1, imported_count)?;
+                }
             }
 
             // Check if we have more pages
@@ -268,13 +393,22 @@ impl LastFmImporter {
             tracing::info!("Inserted final batch of {} scrobbles", inserted);
         }
 
-        tracing::info!("Imported {} scrobbles from Last.fm", imported_count);
+        let suppressed = ignore.flush(pool)?;
+        tracing::info!(
+            "Imported {} scrobbles from Last.fm ({} ignored)",
+            imported_count,
+            suppressed
+        );
+        self.run_enrichment(pool).await;
         Ok(imported_count)
     }
 
     /// Import scrobbles since a specific timestamp (for incremental sync)
+    #[tracing::instrument(name = "lastfm_import", skip(self, pool), fields(username = %self.username))]
     pub async fn import_since(&self, pool: &DbPool, since: DateTime<Utc>) -> Result<usize> {
         let mut imported_count = 0;
+        let mut ignore = IgnoreList::load(pool, "lastfm")?;
+        let classifier = MediaClassifier::load(pool)?;
         let mut page = 1;
         let per_page = 200;
         let since_timestamp = since.timestamp();
@@ -291,66 +425,56 @@ impl LastFmImporter {
             );
 
             let response = self
-                .client
-                .get(&url)
-                .send()
+                .http
+                .get(&url, &[])
                 .await
                 .context("Failed to fetch from Last.fm")?;
 
-            if !response.status().is_success() {
+            if !response.status.is_success() {
                 return Err(anyhow::anyhow!(
                     "Last.fm API returned error: {}",
-                    response.status()
+                    response.status
                 ));
             }
 
             let data: LastFmResponse = response
                 .json()
-                .await
                 .context("Failed to parse Last.fm response")?;
 
             if data.recenttracks.track.is_empty() {
                 break;
             }
 
-            for track in &data.recenttracks.track {
+            let mut malformed = 0;
+            for entry in &data.recenttracks.track {
                 // Skip currently playing tracks
-                if track
-                    .attr
-                    .as_ref()
-                    .and_then(|a| a.nowplaying.as_ref())
-                    .is_some()
-                {
+                if entry.valid().is_some_and(Track::is_now_playing) {
                     continue;
                 }
 
-                if let Some(date_info) = &track.date
-                    && let Ok(timestamp) = date_info.uts.parse::<i64>()
-                {
-                    // Skip tracks older than or equal to our "since" timestamp
-                    // We use <= because we want only NEW scrobbles after the last sync
-                    if timestamp <= since_timestamp {
-                        continue;
-                    }
-
-                    let mut scrobble = Scrobble::new(
-                        track.artist.text.clone(),
-                        track.name.clone(),
-                        DateTime::from_timestamp(timestamp, 0).unwrap_or_else(Utc::now),
-                        "lastfm".to_string(),
-                    );
-
-                    if let Some(album) = &track.album
-                        && !album.text.is_empty()
-                    {
-                        scrobble = scrobble.with_album(album.text.clone());
-                    }
+                let Some(scrobble) = entry.valid().and_then(track_to_scrobble) else {
+                    malformed += 1;
+                    continue;
+                };
 
-                    // Use timestamp as unique identifier
-                    scrobble = scrobble.with_source_id(format!("lastfm_{}", timestamp));
+                // Skip tracks older than or equal to our "since" timestamp
+                // We use <= because we want only NEW scrobbles after the last sync
+                if scrobble.timestamp.timestamp() <= since_timestamp {
+                    continue;
+                }
 
-                    batch.push(scrobble);
+                let scrobble = classifier.apply(self.normalizer.normalize(scrobble));
+                if ignore.suppresses(&scrobble) {
+                    continue;
                 }
+                batch.push(scrobble);
+            }
+            if malformed > 0 {
+                tracing::warn!(
+                    "Skipped {} malformed tracks on Last.fm page {}",
+                    malformed,
+                    page
+                );
             }
 
             // Insert batch when it reaches the batch size
@@ -383,11 +507,646 @@ impl LastFmImporter {
             tracing::info!("Inserted final batch of {} scrobbles", inserted);
         }
 
+        let suppressed = ignore.flush(pool)?;
         tracing::info!(
-            "Imported {} new scrobbles from Last.fm since {}",
+            "Imported {} new scrobbles from Last.fm since {} ({} ignored)",
             imported_count,
-            since
+            since,
+            suppressed
         );
+        self.run_enrichment(pool).await;
         Ok(imported_count)
     }
+
+    /// Run the enrichment phase if enabled. Failures are logged rather than
+    /// returned so that they do not mask the scrobbles already imported.
+    async fn run_enrichment(&self, pool: &DbPool) {
+        if !self.enrich {
+            return;
+        }
+
+        match self.import_loved_tracks(pool).await {
+            Ok(count) => tracing::info!("Imported {} loved tracks from Last.fm", count),
+            Err(e) => tracing::warn!("Failed to import loved tracks: {}", e),
+        }
+
+        match self.import_artist_tags(pool).await {
+            Ok(count) => tracing::info!("Fetched tags for {} artists from Last.fm", count),
+            Err(e) => tracing::warn!("Failed to import artist tags: {}", e),
+        }
+    }
+
+    /// Import the user's loved tracks into the loved_tracks table
+    pub async fn import_loved_tracks(&self, pool: &DbPool) -> Result<usize> {
+        let mut imported_count = 0;
+        let mut page = 1;
+
+        loop {
+            let url = format!(
+                "https://ws.audioscrobbler.com/2.0/?method=user.getlovedtracks&user={}&api_key={}&format=json&limit=200&page={}",
+                self.username, self.api_key, page
+            );
+
+            let data: LovedTracksResponse = self.fetch_json(&url).await?;
+
+            if data.lovedtracks.track.is_empty() {
+                break;
+            }
+
+            let loved: Vec<(String, String, Option<DateTime<Utc>>)> = data
+                .lovedtracks
+                .track
+                .into_iter()
+                .map(|t| {
+                    let loved_at = t
+                        .date
+                        .and_then(|d| d.uts.parse::<i64>().ok())
+                        .and_then(|ts| DateTime::from_timestamp(ts, 0));
+                    (t.artist.name, t.name, loved_at)
+                })
+                .collect();
+
+            imported_count += crate::db::insert_loved_tracks(pool, &loved, "lastfm")?;
+
+            let total_pages = data
+                .lovedtracks
+                .attr
+                .and_then(|a| a.total_pages.parse::<i32>().ok())
+                .unwrap_or(1);
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+
+            // Small delay to be nice to Last.fm API
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        Ok(imported_count)
+    }
+
+    /// Fetch top tags for every artist that has none yet, skipping artists
+    /// that recently came back without any
+    pub async fn import_artist_tags(&self, pool: &DbPool) -> Result<usize> {
+        let artists = crate::db::get_artists_without_tags(pool, Utc::now())?;
+        let mut tagged_count = 0;
+
+        for artist in artists {
+            let url = format!(
+                "https://ws.audioscrobbler.com/2.0/?method=artist.gettoptags&artist={}&api_key={}&format=json",
+                urlencoding::encode(&artist),
+                self.api_key
+            );
+
+            match self.fetch_json::<ArtistTopTagsResponse>(&url).await {
+                Ok(data) => {
+                    let tags: Vec<(String, i64)> = data
+                        .toptags
+                        .tag
+                        .into_iter()
+                        .filter(|t| t.count > 0)
+                        .take(MAX_TAGS_PER_ARTIST)
+                        .map(|t| (t.name.to_lowercase(), t.count))
+                        .collect();
+
+                    crate::db::insert_artist_tags(pool, &artist, &tags, "lastfm")?;
+                    tagged_count += 1;
+                }
+                Err(e) => tracing::warn!("Failed to fetch tags for artist {}: {}", artist, e),
+            }
+
+            // Small delay to be nice to Last.fm API
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+
+        Ok(tagged_count)
+    }
+
+    /// Import the user's weekly artist and track charts for the weeks that end
+    /// before the first stored scrobble, when older scrobbles can't be
+    /// recovered. Weeks imported before are skipped, as are weeks reaching
+    /// into the scrobbled era, whose plays would be counted twice. Returns the
+    /// number of chart entries stored
+    #[tracing::instrument(name = "lastfm_legacy_charts", skip(self, pool), fields(username = %self.username))]
+    pub async fn import_legacy_charts(&self, pool: &DbPool) -> Result<usize> {
+        let first_scrobble = crate::db::get_first_scrobble_timestamp(pool)?;
+        let imported = crate::db::get_legacy_chart_weeks(pool)?;
+
+        let url = format!(
+            "https://ws.audioscrobbler.com/2.0/?method=user.getweeklychartlist&user={}&api_key={}&format=json",
+            self.username, self.api_key
+        );
+        let list: WeeklyChartListResponse = self.fetch_json(&url).await?;
+
+        let weeks: Vec<(DateTime<Utc>, DateTime<Utc>)> = list
+            .weeklychartlist
+            .chart
+            .iter()
+            .filter_map(|week| {
+                let from = DateTime::from_timestamp(week.from.trim().parse().ok()?, 0)?;
+                let to = DateTime::from_timestamp(week.to.trim().parse().ok()?, 0)?;
+                Some((from, to))
+            })
+            .filter(|(from, to)| {
+                first_scrobble.is_none_or(|first| *to <= first) && !imported.contains(from)
+            })
+            .collect();
+
+        let mut stored = 0;
+        for (index, (from, to)) in weeks.iter().enumerate() {
+            tracing::info!(
+                "Fetching Last.fm charts for week {}/{}",
+                index + 1,
+                weeks.len()
+            );
+
+            let artists: WeeklyArtistChartResponse = self
+                .fetch_json(&self.weekly_chart_url("user.getweeklyartistchart", *from, *to))
+                .await?;
+            let tracks: WeeklyTrackChartResponse = self
+                .fetch_json(&self.weekly_chart_url("user.getweeklytrackchart", *from, *to))
+                .await?;
+
+            let artists: Vec<(String, i64)> = artists
+                .weeklyartistchart
+                .artist
+                .into_iter()
+                .filter_map(Lenient::into_valid)
+                .filter_map(|a| {
+                    let plays = a.playcount.trim().parse::<i64>().ok()?;
+                    (plays > 0 && is_usable_name(&a.name)).then_some((a.name, plays))
+                })
+                .collect();
+            let tracks: Vec<(String, String, i64)> = tracks
+                .weeklytrackchart
+                .track
+                .into_iter()
+                .filter_map(Lenient::into_valid)
+                .filter_map(|t| {
+                    let plays = t.playcount.trim().parse::<i64>().ok()?;
+                    (plays > 0 && is_usable_name(&t.artist.text) && is_usable_name(&t.name))
+                        .then_some((t.artist.text, t.name, plays))
+                })
+                .collect();
+
+            stored +=
+                crate::db::save_legacy_chart_week(pool, *from, *to, &artists, &tracks, Utc::now())?;
+
+            // Small delay to be nice to Last.fm API
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+
+        tracing::info!(
+            "Imported {} legacy chart entries over {} weeks from Last.fm",
+            stored,
+            weeks.len()
+        );
+        Ok(stored)
+    }
+
+    /// Fetch the track currently playing, if any
+    pub async fn fetch_now_playing(&self) -> Result<Option<NowPlaying>> {
+        let url = format!(
+            "https://ws.audioscrobbler.com/2.0/?method=user.getrecenttracks&user={}&api_key={}&format=json&limit=1",
+            self.username, self.api_key
+        );
+
+        let response = self
+            .http
+            .get(&url, &[])
+            .await
+            .context("Failed to fetch from Last.fm")?;
+
+        if !response.status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Last.fm API returned error: {}",
+                response.status
+            ));
+        }
+
+        let data: LastFmResponse = response
+            .json()
+            .context("Failed to parse Last.fm response")?;
+
+        let now_playing = data
+            .recenttracks
+            .track
+            .into_iter()
+            .filter_map(Lenient::into_valid)
+            .find(Track::is_now_playing)
+            .map(|track| NowPlaying {
+                artist: track.artist.text,
+                album: track.album.map(|a| a.text).filter(|a| !a.is_empty()),
+                track: track.name,
+            });
+
+        Ok(now_playing)
+    }
+
+    /// Fetch the user's all-time top artists as `(artist, playcount)`
+    pub async fn fetch_top_artists(&self, limit: i64) -> Result<Vec<(String, i64)>> {
+        let data: TopArtistsResponse = self.fetch_user_method("user.gettopartists", limit).await?;
+
+        Ok(data
+            .topartists
+            .artist
+            .into_iter()
+            .map(|a| (a.name, a.playcount.parse().unwrap_or(0)))
+            .collect())
+    }
+
+    /// Fetch the user's all-time top tracks as `(artist, track, playcount)`
+    pub async fn fetch_top_tracks(&self, limit: i64) -> Result<Vec<(String, String, i64)>> {
+        let data: TopTracksResponse = self.fetch_user_method("user.gettoptracks", limit).await?;
+
+        Ok(data
+            .toptracks
+            .track
+            .into_iter()
+            .map(|t| (t.artist.name, t.name, t.playcount.parse().unwrap_or(0)))
+            .collect())
+    }
+
+    /// Check the API key and username with Last.fm, returning the fields it
+    /// rejects. Errors mean Last.fm couldn't be asked, not that a field is wrong
+    pub async fn verify_credentials(&self) -> Result<Vec<FieldError>> {
+        let url = format!(
+            "https://ws.audioscrobbler.com/2.0/?method=user.getinfo&user={}&api_key={}&format=json",
+            self.username, self.api_key
+        );
+
+        let response = self
+            .http
+            .get(&url, &[])
+            .await
+            .context("Failed to fetch from Last.fm")?;
+
+        match response.json::<LastFmError>() {
+            // 6: no such user, 10 and 26: invalid or suspended API key
+            Ok(error) => match error.error {
+                6 => Ok(vec![FieldError::new(
+                    "username",
+                    "No Last.fm user with this name",
+                )]),
+                10 | 26 => Ok(vec![FieldError::new("api_key", error.message)]),
+                code => Err(anyhow::anyhow!(
+                    "Last.fm API returned error {}: {}",
+                    code,
+                    error.message
+                )),
+            },
+            Err(_) if response.status.is_success() => Ok(Vec::new()),
+            Err(_) => Err(anyhow::anyhow!(
+                "Last.fm API returned error: {}",
+                response.status
+            )),
+        }
+    }
+
+    async fn fetch_user_method<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        limit: i64,
+    ) -> Result<T> {
+        let url = format!(
+            "https://ws.audioscrobbler.com/2.0/?method={}&user={}&api_key={}&format=json&period=overall&limit={}",
+            method, self.username, self.api_key, limit
+        );
+
+        self.fetch_json(&url).await
+    }
+
+    fn weekly_chart_url(&self, method: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+        format!(
+            "https://ws.audioscrobbler.com/2.0/?method={}&user={}&api_key={}&format=json&from={}&to={}",
+            method,
+            self.username,
+            self.api_key,
+            from.timestamp(),
+            to.timestamp()
+        )
+    }
+
+    async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self
+            .http
+            .get(url, &[])
+            .await
+            .context("Failed to fetch from Last.fm")?;
+
+        if !response.status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Last.fm API returned error: {}",
+                response.status
+            ));
+        }
+
+        response.json().context("Failed to parse Last.fm response")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::http::MockHttp;
+    use crate::test_utils::setup_pool;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    const PAGE_1: &str = include_str!("fixtures/lastfm_recent_tracks_page1.json");
+    const PAGE_2: &str = include_str!("fixtures/lastfm_recent_tracks_page2.json");
+
+    fn scrobbles_of(page: &str) -> Vec<Option<Scrobble>> {
+        let data: LastFmResponse = serde_json::from_str(page).unwrap();
+        data.recenttracks
+            .track
+            .iter()
+            .map(|entry| entry.valid().and_then(track_to_scrobble))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixture_tracks_parse_or_skip() {
+        let scrobbles = scrobbles_of(PAGE_1);
+        assert_eq!(scrobbles.len(), 6);
+
+        // Now playing has no date yet
+        assert!(scrobbles[0].is_none());
+
+        let played = scrobbles[1].as_ref().unwrap();
+        assert_eq!(played.artist, "Stereolab");
+        assert_eq!(played.album.as_deref(), Some("Dots and Loops"));
+        assert_eq!(played.timestamp.timestamp(), 1709294400);
+        assert_eq!(played.source_id.as_deref(), Some("lastfm_1709294400"));
+
+        // Empty album text means no album
+        assert_eq!(scrobbles[2].as_ref().unwrap().album, None);
+
+        // Empty artist, non-numeric timestamp and a malformed entry are skipped
+        assert!(scrobbles[3..].iter().all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_import_all_pages_through_mock_http() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(
+            MockHttp::default()
+                .respond("page=1", 200, PAGE_1)
+                .respond("page=2", 200, PAGE_2),
+        );
+        let importer =
+            LastFmImporter::new("key".to_string(), "fixture".to_string()).with_http(http.clone());
+
+        assert_eq!(importer.import_all(&pool).await.unwrap(), 3);
+        assert_eq!(http.requests().len(), 2);
+
+        let tracks: Vec<String> = crate::db::get_scrobbles(&pool, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.track)
+            .collect();
+        assert_eq!(
+            tracks,
+            vec!["Brakhage", "Come On Let's Go", "Loco Parentis"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_since_stops_on_client_error() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(MockHttp::default().respond("page=1", 403, "{}"));
+        let importer =
+            LastFmImporter::new("key".to_string(), "fixture".to_string()).with_http(http);
+
+        assert!(importer.import_since(&pool, Utc::now()).await.is_err());
+        assert_eq!(crate::db::get_scrobbles_count(&pool).unwrap(), 0);
+    }
+
+    fn generated_page(page: i64, total_pages: i64) -> String {
+        let tracks: Vec<serde_json::Value> = (0..200)
+            .map(|i| {
+                let uts = 1_700_000_000 - page * 1000 - i;
+                json!({
+                    "artist": {"#text": "Artist"},
+                    "name": format!("Track {}", uts),
+                    "date": {"uts": uts.to_string()},
+                })
+            })
+            .collect();
+        json!({"recenttracks": {"track": tracks, "@attr": {
+            "page": page.to_string(),
+            "totalPages": total_pages.to_string(),
+            "perPage": "200",
+            "total": (total_pages * 200).to_string(),
+        }}})
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_marks_stored_pages() {
+        let (pool, _temp_file) = setup_pool();
+        let job_id = crate::db::insert_import_job(
+            &pool,
+            &crate::models::ImportJob::new("lastfm".into(), "fixture".into()),
+        )
+        .unwrap();
+
+        let mut mock = MockHttp::default();
+        for page in 1..=5 {
+            mock = mock.respond(&format!("page={}", page), 200, &generated_page(page, 10));
+        }
+        let importer = LastFmImporter::new("key".to_string(), "fixture".to_string())
+            .with_http(Arc::new(mock.respond("page=6", 404, "")))
+            .with_checkpoints(Checkpoints::new(job_id, 0));
+
+        assert!(importer.import_all(&pool).await.is_err());
+
+        // Five pages of 200 fill one batch; page 6 is where to pick up
+        let job = crate::db::get_import_job(&pool, job_id).unwrap().unwrap();
+        assert_eq!(job.checkpoint, Some(6));
+        assert_eq!(job.imported_count, 1000);
+        assert_eq!(crate::db::get_scrobbles_count(&pool).unwrap(), 1000);
+    }
+
+    fn any_json() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<i64>().prop_map(serde_json::Value::from),
+            ".{0,12}".prop_map(serde_json::Value::from),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::from),
+                prop::collection::hash_map("[a-z#@]{1,6}", inner, 0..4)
+                    .prop_map(|m| serde_json::Value::Object(m.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn uts() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<i64>().prop_map(|ts| ts.to_string()),
+            (1i64..4_000_000_000).prop_map(|ts| ts.to_string()),
+            ".{0,10}",
+        ]
+    }
+
+    fn track_json() -> impl Strategy<Value = serde_json::Value> {
+        (
+            ".{0,10}",
+            ".{0,10}",
+            prop::option::of(".{0,10}"),
+            prop::option::of(uts()),
+        )
+            .prop_map(|(artist, name, album, uts)| {
+                let mut track = json!({"artist": {"#text": artist}, "name": name});
+                if let Some(album) = album {
+                    track["album"] = json!({"#text": album});
+                }
+                if let Some(uts) = uts {
+                    track["date"] = json!({"uts": uts});
+                }
+                track
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_malformed_entries_never_fail_the_page(
+            entries in prop::collection::vec(prop_oneof![track_json(), any_json()], 0..8)
+        ) {
+            let page = json!({"recenttracks": {"track": entries}}).to_string();
+            let data: LastFmResponse = serde_json::from_str(&page).unwrap();
+            prop_assert_eq!(data.recenttracks.track.len(), entries.len());
+        }
+
+        #[test]
+        fn prop_tracks_are_stored_faithfully_or_skipped(track in track_json()) {
+            let parsed: Track = serde_json::from_value(track.clone()).unwrap();
+            let expected_ts = track["date"]["uts"]
+                .as_str()
+                .and_then(|uts| uts.trim().parse::<i64>().ok())
+                .and_then(listen_time);
+
+            match track_to_scrobble(&parsed) {
+                Some(scrobble) => {
+                    prop_assert_eq!(Some(scrobble.timestamp), expected_ts);
+                    prop_assert_eq!(&scrobble.artist, &parsed.artist.text);
+                    prop_assert_eq!(&scrobble.track, &parsed.name);
+                    prop_assert!(is_usable_name(&scrobble.artist) && is_usable_name(&scrobble.track));
+                    prop_assert!(scrobble.album.as_deref() != Some(""));
+                }
+                None => prop_assert!(
+                    expected_ts.is_none()
+                        || !is_usable_name(&parsed.artist.text)
+                        || !is_usable_name(&parsed.name)
+                ),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_credentials() {
+        let importer = |http: MockHttp| {
+            LastFmImporter::new("key".to_string(), "fixture".to_string()).with_http(Arc::new(http))
+        };
+
+        let ok = importer(MockHttp::default().respond(
+            "user.getinfo",
+            200,
+            r#"{"user": {"name": "fixture"}}"#,
+        ));
+        assert!(ok.verify_credentials().await.unwrap().is_empty());
+
+        let bad_key = importer(MockHttp::default().respond(
+            "user.getinfo",
+            403,
+            r#"{"error": 10, "message": "Invalid API key - You must be granted a valid key by last.fm"}"#,
+        ));
+        let errors = bad_key.verify_credentials().await.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "api_key");
+
+        let no_user = importer(MockHttp::default().respond(
+            "user.getinfo",
+            404,
+            r#"{"error": 6, "message": "User not found"}"#,
+        ));
+        assert_eq!(
+            no_user.verify_credentials().await.unwrap()[0].field,
+            "username"
+        );
+
+        let down = importer(MockHttp::default().respond("user.getinfo", 503, ""));
+        assert!(down.verify_credentials().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_legacy_charts_before_first_scrobble() {
+        let (pool, _temp_file) = setup_pool();
+        let first = Scrobble::new(
+            "Low".to_string(),
+            "Sunflower".to_string(),
+            DateTime::from_timestamp(1136073600, 0).unwrap(),
+            "lastfm".to_string(),
+        );
+        crate::db::insert_scrobble(&pool, &first).unwrap();
+
+        // The last week reaches past the first scrobble
+        let chart_list = r#"{"weeklychartlist": {"chart": [
+            {"from": "1134302400", "to": "1134907200"},
+            {"from": "1134907200", "to": "1135512000"},
+            {"from": "1135512000", "to": "1136116800"}
+        ]}}"#;
+        let http = Arc::new(
+            MockHttp::default()
+                .respond("user.getweeklychartlist", 200, chart_list)
+                .respond(
+                    "user.getweeklyartistchart",
+                    200,
+                    r#"{"weeklyartistchart": {"artist": [{"name": "Stereolab", "playcount": "4"}]}}"#,
+                )
+                .respond(
+                    "user.getweeklyartistchart",
+                    200,
+                    r#"{"weeklyartistchart": {"artist": [
+                        {"name": "Low", "playcount": "12"},
+                        {"name": " ", "playcount": "3"},
+                        {"name": "Broadcast"}
+                    ]}}"#,
+                )
+                .respond(
+                    "user.getweeklytrackchart",
+                    200,
+                    r#"{"weeklytrackchart": {"track": []}}"#,
+                )
+                .respond(
+                    "user.getweeklytrackchart",
+                    200,
+                    r##"{"weeklytrackchart": {"track": [
+                        {"artist": {"#text": "Low"}, "name": "Words", "playcount": "5"}
+                    ]}}"##,
+                ),
+        );
+        let importer =
+            LastFmImporter::new("key".to_string(), "fixture".to_string()).with_http(http.clone());
+
+        assert_eq!(importer.import_legacy_charts(&pool).await.unwrap(), 3);
+        assert_eq!(http.requests().len(), 5);
+        assert!(
+            http.requests()
+                .iter()
+                .all(|url| !url.contains("from=1135512000"))
+        );
+        assert_eq!(crate::db::get_legacy_chart_weeks(&pool).unwrap().len(), 2);
+
+        // Imported weeks aren't fetched again
+        let http =
+            Arc::new(MockHttp::default().respond("user.getweeklychartlist", 200, chart_list));
+        let importer =
+            LastFmImporter::new("key".to_string(), "fixture".to_string()).with_http(http.clone());
+        assert_eq!(importer.import_legacy_charts(&pool).await.unwrap(), 0);
+        assert_eq!(http.requests().len(), 1);
+    }
 }
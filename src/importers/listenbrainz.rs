@@ -1,9 +1,14 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-use crate::db::DbPool;
-use crate::models::Scrobble;
+use crate::classifier::MediaClassifier;
+use crate::db::{DbPool, MusicBrainzId, RecommendedPlaylist, RecommendedTrack};
+use crate::importers::retry::{RetryPolicy, fetch_json_with_retry};
+use crate::importers::{Checkpoints, HttpFetch, IgnoreList, Lenient, is_usable_name, listen_time};
+use crate::models::{FieldError, NowPlaying, Scrobble};
+use crate::normalizer::Normalizer;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ListenBrainzResponse {
@@ -13,7 +18,7 @@ struct ListenBrainzResponse {
 #[derive(Debug, Deserialize, Serialize)]
 struct Payload {
     count: i32,
-    listens: Vec<Listen>,
+    listens: Vec<Lenient<Listen>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -23,17 +28,201 @@ struct Listen {
     recording_msid: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct PlayingNowResponse {
+    payload: PlayingNowPayload,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PlayingNowPayload {
+    listens: Vec<PlayingNowListen>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PlayingNowListen {
+    track_metadata: TrackMetadata,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct TrackMetadata {
     artist_name: String,
     track_name: String,
     release_name: Option<String>,
+    /// Music service, duration, Spotify and MusicBrainz ids and the like
+    #[serde(default)]
+    additional_info: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedForResponse {
+    #[serde(default)]
+    playlists: Vec<PlaylistResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistResponse {
+    playlist: Jspf,
+}
+
+/// A playlist in JSPF, the JSON form of XSPF that ListenBrainz speaks
+#[derive(Debug, Deserialize)]
+struct Jspf {
+    /// https://listenbrainz.org/playlist/<mbid>
+    identifier: String,
+    title: String,
+    date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    extension: serde_json::Value,
+    #[serde(default)]
+    track: Vec<JspfTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JspfTrack {
+    title: String,
+    creator: String,
+    album: Option<String>,
+    /// Recording URL, alone or in a list depending on the API version
+    #[serde(default)]
+    identifier: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidateTokenResponse {
+    valid: bool,
+    user_name: Option<String>,
+}
+
+// Pages between saved checkpoints of a full import
+const CHECKPOINT_PAGES: usize = 10;
+
+// Generated playlists looked at per pull; a few weeks' worth
+const PLAYLISTS_PER_PULL: usize = 25;
+
+const JSPF_EXTENSION: &str = "https://musicbrainz.org/doc/jspf#playlist";
+
+/// Scrobble for a listen, or `None` when the timestamp is not a plausible
+/// Unix time or the artist or title is blank
+fn listen_to_scrobble(listen: &Listen) -> Option<Scrobble> {
+    let listened_at = listen_time(listen.listened_at)?;
+    let metadata = &listen.track_metadata;
+    if !is_usable_name(&metadata.artist_name) || !is_usable_name(&metadata.track_name) {
+        return None;
+    }
+
+    let mut scrobble = Scrobble::new(
+        metadata.artist_name.clone(),
+        metadata.track_name.clone(),
+        listened_at,
+        "listenbrainz".to_string(),
+    );
+
+    if let Some(album) = &metadata.release_name
+        && !album.is_empty()
+    {
+        scrobble = scrobble.with_album(album.clone());
+    }
+
+    if let Some(info) = &metadata.additional_info
+        && info.as_object().is_some_and(|info| !info.is_empty())
+    {
+        // Players send the album artist under either name
+        let album_artist = ["release_artist_name", "albumartist"]
+            .iter()
+            .find_map(|key| info.get(key).and_then(|v| v.as_str()))
+            .map(str::trim)
+            .filter(|name| !name.is_empty() && *name != metadata.artist_name.trim());
+        if let Some(album_artist) = album_artist {
+            scrobble = scrobble.with_album_artist(album_artist.to_string());
+        }
+        // Listens are only submitted once they count, so a track length
+        // means a full play of that length
+        let duration_ms = info
+            .get("duration_ms")
+            .and_then(|v| v.as_i64())
+            .or_else(|| {
+                info.get("duration")
+                    .and_then(|v| v.as_i64())
+                    .map(|s| s * 1000)
+            })
+            .filter(|ms| *ms > 0);
+        if let Some(duration_ms) = duration_ms {
+            scrobble = scrobble.with_playback(duration_ms, false);
+        }
+        scrobble = scrobble.with_source_metadata(info.clone());
+    }
+
+    // Use recording_msid or timestamp as unique identifier for deduplication
+    let source_id = if let Some(msid) = &listen.recording_msid {
+        format!("listenbrainz_{}", msid)
+    } else {
+        format!("listenbrainz_{}", listen.listened_at)
+    };
+    Some(scrobble.with_source_id(source_id))
+}
+
+/// Id at the end of a ListenBrainz or MusicBrainz URL
+fn last_segment(url: &str) -> Option<&str> {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|id| !id.is_empty())
+}
+
+/// MusicBrainz ids ListenBrainz attached to a listen, keyed by the stored
+/// (normalized) names so later lookups don't have to search for them again
+fn musicbrainz_ids(scrobble: &Scrobble) -> Vec<MusicBrainzId> {
+    let Some(info) = &scrobble.source_metadata else {
+        return Vec::new();
+    };
+    let mbid = |key: &str| {
+        info.get(key)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    let mut ids = Vec::new();
+    // Only trust artist ids for single-artist credits
+    if let Some([artist]) = info
+        .get("artist_mbids")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        && let Some(artist_mbid) = artist.as_str().filter(|id| !id.is_empty())
+    {
+        ids.push(MusicBrainzId {
+            entity_type: "artist",
+            artist: scrobble.artist.clone(),
+            name: None,
+            mbid: artist_mbid.to_string(),
+        });
+    }
+    if let (Some(album), Some(release_mbid)) = (&scrobble.album, mbid("release_mbid")) {
+        ids.push(MusicBrainzId {
+            entity_type: "album",
+            artist: scrobble.artist.clone(),
+            name: Some(album.clone()),
+            mbid: release_mbid,
+        });
+    }
+    if let Some(recording_mbid) = mbid("recording_mbid") {
+        ids.push(MusicBrainzId {
+            entity_type: "track",
+            artist: scrobble.artist.clone(),
+            name: Some(scrobble.track.clone()),
+            mbid: recording_mbid,
+        });
+    }
+    ids
 }
 
 pub struct ListenBrainzImporter {
     username: String,
     token: Option<String>,
-    client: reqwest::Client,
+    http: Arc<dyn HttpFetch>,
+    retry: RetryPolicy,
+    checkpoints: Option<Checkpoints>,
+    normalizer: Normalizer,
 }
 
 impl ListenBrainzImporter {
@@ -41,15 +230,59 @@ impl ListenBrainzImporter {
         Self {
             username,
             token,
-            client: reqwest::Client::new(),
+            http: Arc::new(reqwest::Client::new()),
+            retry: RetryPolicy::default(),
+            checkpoints: None,
+            normalizer: Normalizer::default(),
         }
     }
 
+    /// Clean up names with this normalizer before storing scrobbles
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Fetch API pages through this client instead of the network
+    pub fn with_http(mut self, http: Arc<dyn HttpFetch>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Retry failing requests with this policy instead of the default
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Save progress of full imports to an import job
+    pub fn with_checkpoints(mut self, checkpoints: Checkpoints) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Authorization header for requests, when a token is configured
+    fn auth_headers(&self) -> Vec<(&'static str, String)> {
+        self.token
+            .iter()
+            .map(|token| ("Authorization", format!("Token {}", token)))
+            .collect()
+    }
+
     pub async fn import_all(&self, pool: &DbPool) -> Result<usize> {
+        self.import_all_before(pool, None).await
+    }
+
+    /// Import all listens older than `max_ts`, walking back in time (for
+    /// resuming failed imports from the timestamp in their error)
+    #[tracing::instrument(name = "listenbrainz_import", skip(self, pool), fields(username = %self.username))]
+    pub async fn import_all_before(&self, pool: &DbPool, max_ts: Option<i64>) -> Result<usize> {
         let mut imported_count = 0;
-        let mut max_ts: Option<i64> = None;
+        let mut ignore = IgnoreList::load(pool, "listenbrainz")?;
+        let classifier = MediaClassifier::load(pool)?;
+        let mut max_ts = max_ts;
+        let mut pages = 0;
         let count = 100;
-        const MAX_RETRIES: u32 = 3;
 
         loop {
             tracing::info!(
@@ -68,141 +301,59 @@ impl ListenBrainzImporter {
                 url.push_str(&format!("&max_ts={}", ts));
             }
 
-            // Retry logic for handling transient errors
-            let mut retry_count = 0;
-            let data = loop {
-                let mut request = self.client.get(&url);
-
-                if let Some(token) = &self.token {
-                    request = request.header("Authorization", format!("Token {}", token));
-                }
-
-                let response = request
-                    .send()
-                    .await
-                    .context("Failed to fetch from ListenBrainz");
-
-                match response {
-                    Ok(resp) => {
-                        let status = resp.status();
-
-                        // Handle rate limiting or server errors with retry
-                        if status.is_server_error()
-                            || status == reqwest::StatusCode::TOO_MANY_REQUESTS
-                        {
-                            retry_count += 1;
-                            if retry_count >= MAX_RETRIES {
-                                return Err(anyhow::anyhow!(
-                                    "ListenBrainz API returned error after {} retries: {} (can resume from timestamp: {:?})",
-                                    MAX_RETRIES,
-                                    status,
-                                    max_ts
-                                ));
-                            }
-
-                            let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                            tracing::warn!(
-                                "ListenBrainz API error: {}, retrying in {:?} (attempt {}/{})",
-                                status,
-                                delay,
-                                retry_count,
-                                MAX_RETRIES
-                            );
-                            tokio::time::sleep(delay).await;
-                            continue;
-                        }
-
-                        if !status.is_success() {
-                            return Err(anyhow::anyhow!(
-                                "ListenBrainz API returned error: {} (can resume from timestamp: {:?})",
-                                status,
-                                max_ts
-                            ));
-                        }
-
-                        // Parse response
-                        match resp.json::<ListenBrainzResponse>().await {
-                            Ok(data) => break data,
-                            Err(e) => {
-                                retry_count += 1;
-                                if retry_count >= MAX_RETRIES {
-                                    return Err(anyhow::anyhow!(
-                                        "Failed to parse ListenBrainz response after {} retries: {} (can resume from timestamp: {:?})",
-                                        MAX_RETRIES,
-                                        e,
-                                        max_ts
-                                    ));
-                                }
-
-                                let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                                tracing::warn!(
-                                    "Failed to parse response, retrying in {:?} (attempt {}/{})",
-                                    delay,
-                                    retry_count,
-                                    MAX_RETRIES
-                                );
-                                tokio::time::sleep(delay).await;
-                                continue;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        retry_count += 1;
-                        if retry_count >= MAX_RETRIES {
-                            return Err(anyhow::anyhow!(
-                                "Failed to fetch from ListenBrainz after {} retries: {} (can resume from timestamp: {:?})",
-                                MAX_RETRIES,
-                                e,
-                                max_ts
-                            ));
-                        }
-
-                        let delay = std::time::Duration::from_secs(2u64.pow(retry_count));
-                        tracing::warn!(
-                            "Network error: {}, retrying in {:?} (attempt {}/{})",
-                            e,
-                            delay,
-                            retry_count,
-                            MAX_RETRIES
-                        );
-                        tokio::time::sleep(delay).await;
-                    }
-                }
-            };
+            let data: ListenBrainzResponse = fetch_json_with_retry(
+                self.http.as_ref(),
+                &url,
+                &self.auth_headers(),
+                self.retry,
+                "ListenBrainz",
+            )
+            .await
+            .with_context(|| match max_ts {
+                Some(ts) => format!("ListenBrainz import stopped, resume from max_ts {}", ts),
+                None => "ListenBrainz import stopped on the first page".to_string(),
+            })?;
 
             if data.payload.listens.is_empty() {
                 break;
             }
 
-            for listen in &data.payload.listens {
-                let mut scrobble = Scrobble::new(
-                    listen.track_metadata.artist_name.clone(),
-                    listen.track_metadata.track_name.clone(),
-                    DateTime::from_timestamp(listen.listened_at, 0).unwrap_or_else(Utc::now),
-                    "listenbrainz".to_string(),
-                );
+            let mut malformed = 0;
+            let mut musicbrainz = Vec::new();
+            for entry in &data.payload.listens {
+                let Some(listen) = entry.valid() else {
+                    malformed += 1;
+                    continue;
+                };
 
-                if let Some(album) = &listen.track_metadata.release_name
-                    && !album.is_empty()
-                {
-                    scrobble = scrobble.with_album(album.clone());
-                }
+                // Update max_ts for pagination, even past listens that are skipped
+                max_ts = Some(listen.listened_at);
 
-                // Use recording_msid or timestamp as unique identifier for deduplication
-                let source_id = if let Some(msid) = &listen.recording_msid {
-                    format!("listenbrainz_{}", msid)
-                } else {
-                    format!("listenbrainz_{}", listen.listened_at)
+                let Some(scrobble) = listen_to_scrobble(listen) else {
+                    malformed += 1;
+                    continue;
                 };
-                scrobble = scrobble.with_source_id(source_id);
+                let scrobble = classifier.apply(self.normalizer.normalize(scrobble));
+                musicbrainz.extend(musicbrainz_ids(&scrobble));
 
                 // insert_scrobble will skip duplicates due to UNIQUE constraint
-                if crate::db::insert_scrobble(pool, &scrobble).is_ok() {
+                if !ignore.suppresses(&scrobble)
+                    && crate::db::insert_scrobble(pool, &scrobble).is_ok()
+                {
                     imported_count += 1;
                 }
+            }
+            if malformed > 0 {
+                tracing::warn!("Skipped {} malformed ListenBrainz listens", malformed);
+            }
+            crate::db::save_musicbrainz_ids(pool, &musicbrainz)?;
 
-                // Update max_ts for pagination
-                max_ts = Some(listen.listened_at);
+            // Listens are stored as they come, so the oldest one seen is a safe restart point
+            pages += 1;
+            if pages % CHECKPOINT_PAGES == 0
+                && let (Some(checkpoints), Some(ts)) = (&self.checkpoints, max_ts)
+            {
+                checkpoints.save(pool, ts, imported_count)?;
             }
 
             // If we got fewer results than requested, we've reached the end
@@ -214,13 +365,21 @@ impl ListenBrainzImporter {
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         }
 
-        tracing::info!("Imported {} scrobbles from ListenBrainz", imported_count);
+        let suppressed = ignore.flush(pool)?;
+        tracing::info!(
+            "Imported {} scrobbles from ListenBrainz ({} ignored)",
+            imported_count,
+            suppressed
+        );
         Ok(imported_count)
     }
 
     /// Import scrobbles since a specific timestamp (for incremental sync)
+    #[tracing::instrument(name = "listenbrainz_import", skip(self, pool), fields(username = %self.username))]
     pub async fn import_since(&self, pool: &DbPool, since: DateTime<Utc>) -> Result<usize> {
         let mut imported_count = 0;
+        let mut ignore = IgnoreList::load(pool, "listenbrainz")?;
+        let classifier = MediaClassifier::load(pool)?;
         let mut max_ts: Option<i64> = None;
         let count = 100;
         let since_timestamp = since.timestamp();
@@ -243,68 +402,53 @@ impl ListenBrainzImporter {
                 url.push_str(&format!("&max_ts={}", ts));
             }
 
-            let mut request = self.client.get(&url);
-
-            if let Some(token) = &self.token {
-                request = request.header("Authorization", format!("Token {}", token));
-            }
-
-            let response = request
-                .send()
-                .await
-                .context("Failed to fetch from ListenBrainz")?;
-
-            if !response.status().is_success() {
-                return Err(anyhow::anyhow!(
-                    "ListenBrainz API returned error: {}",
-                    response.status()
-                ));
-            }
-
-            let data: ListenBrainzResponse = response
-                .json()
-                .await
-                .context("Failed to parse ListenBrainz response")?;
+            let data: ListenBrainzResponse = fetch_json_with_retry(
+                self.http.as_ref(),
+                &url,
+                &self.auth_headers(),
+                self.retry,
+                "ListenBrainz",
+            )
+            .await?;
 
             if data.payload.listens.is_empty() {
                 break;
             }
 
-            for listen in &data.payload.listens {
+            let mut malformed = 0;
+            let mut musicbrainz = Vec::new();
+            for entry in &data.payload.listens {
+                let Some(listen) = entry.valid() else {
+                    malformed += 1;
+                    continue;
+                };
+
                 // Skip listens at or before our "since" timestamp to avoid duplicates
                 // Using <= ensures we don't re-import the exact timestamp from last sync
                 if listen.listened_at <= since_timestamp {
                     continue;
                 }
 
-                let mut scrobble = Scrobble::new(
-                    listen.track_metadata.artist_name.clone(),
-                    listen.track_metadata.track_name.clone(),
-                    DateTime::from_timestamp(listen.listened_at, 0).unwrap_or_else(Utc::now),
-                    "listenbrainz".to_string(),
-                );
-
-                if let Some(album) = &listen.track_metadata.release_name
-                    && !album.is_empty()
-                {
-                    scrobble = scrobble.with_album(album.clone());
-                }
+                // Update max_ts for pagination
+                max_ts = Some(listen.listened_at);
 
-                // Use recording_msid or timestamp as unique identifier
-                let source_id = if let Some(msid) = &listen.recording_msid {
-                    format!("listenbrainz_{}", msid)
-                } else {
-                    format!("listenbrainz_{}", listen.listened_at)
+                let Some(scrobble) = listen_to_scrobble(listen) else {
+                    malformed += 1;
+                    continue;
                 };
-                scrobble = scrobble.with_source_id(source_id);
+                let scrobble = classifier.apply(self.normalizer.normalize(scrobble));
+                musicbrainz.extend(musicbrainz_ids(&scrobble));
 
-                if crate::db::insert_scrobble(pool, &scrobble).is_ok() {
+                if !ignore.suppresses(&scrobble)
+                    && crate::db::insert_scrobble(pool, &scrobble).is_ok()
+                {
                     imported_count += 1;
                 }
-
-                // Update max_ts for pagination
-                max_ts = Some(listen.listened_at);
             }
+            if malformed > 0 {
+                tracing::warn!("Skipped {} malformed ListenBrainz listens", malformed);
+            }
+            crate::db::save_musicbrainz_ids(pool, &musicbrainz)?;
 
             // If we got fewer results than requested, we've reached the end
             if data.payload.listens.len() < count as usize {
@@ -312,11 +456,619 @@ impl ListenBrainzImporter {
             }
         }
 
+        let suppressed = ignore.flush(pool)?;
         tracing::info!(
-            "Imported {} new scrobbles from ListenBrainz since {}",
+            "Imported {} new scrobbles from ListenBrainz since {} ({} ignored)",
             imported_count,
-            since
+            since,
+            suppressed
         );
         Ok(imported_count)
     }
+
+    /// Store the playlists ListenBrainz generated for the user (weekly
+    /// exploration, weekly jams...) that aren't stored yet, returning how many
+    /// were added
+    #[tracing::instrument(name = "listenbrainz_playlists", skip(self, pool), fields(username = %self.username))]
+    pub async fn import_playlists(&self, pool: &DbPool) -> Result<usize> {
+        if self.token.is_none() {
+            return Err(anyhow::anyhow!(
+                "A ListenBrainz token is required to pull playlists"
+            ));
+        }
+
+        let url = format!(
+            "https://api.listenbrainz.org/1/user/{}/playlists/createdfor?count={}",
+            self.username, PLAYLISTS_PER_PULL
+        );
+        let listing: CreatedForResponse = fetch_json_with_retry(
+            self.http.as_ref(),
+            &url,
+            &self.auth_headers(),
+            self.retry,
+            "ListenBrainz",
+        )
+        .await?;
+
+        let mut added = 0;
+        for entry in listing.playlists {
+            let Some(mbid) = last_segment(&entry.playlist.identifier) else {
+                continue;
+            };
+            if crate::db::has_recommended_playlist(pool, mbid)? {
+                continue;
+            }
+
+            // The listing leaves out tracks
+            let url = format!("https://api.listenbrainz.org/1/playlist/{}", mbid);
+            let full: PlaylistResponse = fetch_json_with_retry(
+                self.http.as_ref(),
+                &url,
+                &self.auth_headers(),
+                self.retry,
+                "ListenBrainz",
+            )
+            .await?;
+
+            let (playlist, tracks) = self.recommended_playlist(mbid, full.playlist);
+            crate::db::save_recommended_playlist(pool, &playlist, &tracks)?;
+            added += 1;
+        }
+
+        Ok(added)
+    }
+
+    fn recommended_playlist(
+        &self,
+        mbid: &str,
+        jspf: Jspf,
+    ) -> (RecommendedPlaylist, Vec<RecommendedTrack>) {
+        let now = Utc::now();
+        let kind = jspf.extension[JSPF_EXTENSION]["additional_metadata"]["algorithm_metadata"]
+            ["source_patch"]
+            .as_str()
+            .map(str::to_string);
+
+        let tracks = jspf
+            .track
+            .into_iter()
+            .enumerate()
+            .map(|(position, track)| {
+                let recording = match &track.identifier {
+                    serde_json::Value::Array(ids) => ids.first().and_then(|id| id.as_str()),
+                    id => id.as_str(),
+                };
+                let recording_mbid = recording.and_then(last_segment).map(str::to_string);
+
+                // Normalized like scrobbles, so the two can be matched
+                let mut scrobble =
+                    Scrobble::new(track.creator, track.title, now, "listenbrainz".to_string());
+                scrobble.album = track.album.filter(|album| !album.is_empty());
+                let scrobble = self.normalizer.normalize(scrobble);
+
+                RecommendedTrack {
+                    position: position as i64,
+                    artist: scrobble.artist,
+                    track: scrobble.track,
+                    album: scrobble.album,
+                    recording_mbid,
+                }
+            })
+            .collect();
+
+        let playlist = RecommendedPlaylist {
+            mbid: mbid.to_string(),
+            username: self.username.clone(),
+            title: jspf.title,
+            kind,
+            created_at: jspf.date.unwrap_or(now),
+            fetched_at: now,
+        };
+        (playlist, tracks)
+    }
+
+    /// Check the token and username with ListenBrainz, returning the fields it
+    /// rejects. Errors mean ListenBrainz couldn't be asked, not that a field is wrong
+    pub async fn verify_credentials(&self) -> Result<Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if self.token.is_some() {
+            let response = self
+                .http
+                .get(
+                    "https://api.listenbrainz.org/1/validate-token",
+                    &self.auth_headers(),
+                )
+                .await
+                .context("Failed to fetch from ListenBrainz")?;
+            if !response.status.is_success() {
+                return Err(anyhow::anyhow!(
+                    "ListenBrainz API returned error: {}",
+                    response.status
+                ));
+            }
+
+            let token: ValidateTokenResponse = response
+                .json()
+                .context("Failed to parse ListenBrainz response")?;
+            match token.user_name {
+                _ if !token.valid => {
+                    errors.push(FieldError::new("token", "ListenBrainz rejected this token"))
+                }
+                Some(owner) if !owner.eq_ignore_ascii_case(&self.username) => {
+                    errors.push(FieldError::new(
+                        "token",
+                        format!("belongs to {}, not {}", owner, self.username),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let url = format!(
+            "https://api.listenbrainz.org/1/user/{}/listen-count",
+            self.username
+        );
+        let response = self
+            .http
+            .get(&url, &self.auth_headers())
+            .await
+            .context("Failed to fetch from ListenBrainz")?;
+        if response.status == reqwest::StatusCode::NOT_FOUND {
+            errors.push(FieldError::new(
+                "username",
+                "No ListenBrainz user with this name",
+            ));
+        } else if !response.status.is_success() {
+            return Err(anyhow::anyhow!(
+                "ListenBrainz API returned error: {}",
+                response.status
+            ));
+        }
+
+        Ok(errors)
+    }
+
+    /// Fetch the track currently playing, if any
+    pub async fn fetch_now_playing(&self) -> Result<Option<NowPlaying>> {
+        let url = format!(
+            "https://api.listenbrainz.org/1/user/{}/playing-now",
+            self.username
+        );
+
+        let response = self
+            .http
+            .get(&url, &self.auth_headers())
+            .await
+            .context("Failed to fetch from ListenBrainz")?;
+
+        if !response.status.is_success() {
+            return Err(anyhow::anyhow!(
+                "ListenBrainz API returned error: {}",
+                response.status
+            ));
+        }
+
+        let data: PlayingNowResponse = response
+            .json()
+            .context("Failed to parse ListenBrainz response")?;
+
+        let now_playing = data
+            .payload
+            .listens
+            .into_iter()
+            .next()
+            .map(|listen| NowPlaying {
+                artist: listen.track_metadata.artist_name,
+                album: listen.track_metadata.release_name.filter(|a| !a.is_empty()),
+                track: listen.track_metadata.track_name,
+            });
+
+        Ok(now_playing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::http::MockHttp;
+    use crate::normalizer::Rule;
+    use crate::test_utils::setup_pool;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    const LISTENS: &str = include_str!("fixtures/listenbrainz_listens.json");
+    const CREATED_FOR: &str = include_str!("fixtures/listenbrainz_playlists_createdfor.json");
+    const PLAYLIST: &str = include_str!("fixtures/listenbrainz_playlist.json");
+
+    #[test]
+    fn test_fixture_listens_parse_or_skip() {
+        let data: ListenBrainzResponse = serde_json::from_str(LISTENS).unwrap();
+        let scrobbles: Vec<Option<Scrobble>> = data
+            .payload
+            .listens
+            .iter()
+            .map(|entry| entry.valid().and_then(listen_to_scrobble))
+            .collect();
+        assert_eq!(scrobbles.len(), 5);
+
+        let first = scrobbles[0].as_ref().unwrap();
+        assert_eq!(first.album.as_deref(), Some("Heaven or Las Vegas"));
+        let info = first.source_metadata.as_ref().unwrap();
+        assert_eq!(info["music_service"], "spotify.com");
+        assert_eq!(info["duration_ms"], 299000);
+        assert_eq!(first.ms_played, Some(299000));
+        assert_eq!(first.skipped, Some(false));
+        assert_eq!(
+            first.source_id.as_deref(),
+            Some("listenbrainz_6b7a8e6e-0001-4d1c-9c4b-1a2b3c4d5e6f")
+        );
+
+        // Missing release means no album
+        assert_eq!(scrobbles[1].as_ref().unwrap().album, None);
+        // Nor playback details without a track length
+        assert_eq!(scrobbles[1].as_ref().unwrap().ms_played, None);
+
+        // Blank artist and non-numeric timestamp are skipped
+        assert!(scrobbles[2].is_none());
+        assert!(scrobbles[3].is_none());
+
+        // Without a recording msid the timestamp identifies the listen
+        assert_eq!(
+            scrobbles[4].as_ref().unwrap().source_id.as_deref(),
+            Some("listenbrainz_1709293200")
+        );
+    }
+
+    #[test]
+    fn test_album_artist_from_additional_info() {
+        let page = json!({"payload": {"count": 2, "listens": [
+            {
+                "listened_at": 1709294400,
+                "track_metadata": {
+                    "artist_name": "Nina Simone",
+                    "track_name": "Feeling Good",
+                    "release_name": "Soul Classics",
+                    "additional_info": {"release_artist_name": "Various Artists"},
+                },
+            },
+            {
+                "listened_at": 1709294500,
+                "track_metadata": {
+                    "artist_name": "Low",
+                    "track_name": "Words",
+                    "additional_info": {"albumartist": "Low"},
+                },
+            },
+        ]}});
+        let data: ListenBrainzResponse = serde_json::from_value(page).unwrap();
+        let scrobbles: Vec<Scrobble> = data
+            .payload
+            .listens
+            .iter()
+            .filter_map(|entry| entry.valid().and_then(listen_to_scrobble))
+            .collect();
+
+        assert_eq!(
+            scrobbles[0].album_artist.as_deref(),
+            Some("Various Artists")
+        );
+        // Same as the track artist: nothing to record
+        assert_eq!(scrobbles[1].album_artist, None);
+    }
+
+    #[tokio::test]
+    async fn test_import_all_through_mock_http() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(MockHttp::default().respond("/listens", 200, LISTENS));
+        let importer = ListenBrainzImporter::new("fixture".to_string(), Some("token".to_string()))
+            .with_http(http.clone());
+
+        assert_eq!(importer.import_all(&pool).await.unwrap(), 3);
+        let stored = crate::db::get_scrobbles(&pool, Some(1), None).unwrap();
+        assert_eq!(
+            stored[0].source_metadata.as_ref().unwrap()["recording_mbid"],
+            "a4e6d5c2-7b1b-4b8f-9a1e-3f7a0d3c5b2e"
+        );
+        assert_eq!(
+            http.requests(),
+            vec!["https://api.listenbrainz.org/1/user/fixture/listens?count=100"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_playlists_marks_played_recommendations() {
+        let (pool, _temp_file) = setup_pool();
+        let created_at: DateTime<Utc> = "2024-03-04T00:00:06Z".parse().unwrap();
+        let play = |track: &str, timestamp: DateTime<Utc>| {
+            let scrobble = Scrobble::new(
+                "Cocteau Twins".to_string(),
+                track.to_string(),
+                timestamp,
+                "listenbrainz".to_string(),
+            );
+            crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+        };
+        play(
+            "Heaven or Las Vegas",
+            created_at - chrono::Duration::days(30),
+        );
+        play("Blue Bell Knoll", created_at + chrono::Duration::days(2));
+        play("blue bell knoll", created_at + chrono::Duration::days(3));
+
+        let http = Arc::new(
+            MockHttp::default()
+                .respond("/playlists/createdfor", 200, CREATED_FOR)
+                .respond("/playlists/createdfor", 200, CREATED_FOR)
+                .respond("/playlist/", 200, PLAYLIST),
+        );
+        let importer = ListenBrainzImporter::new("fixture".to_string(), Some("token".to_string()))
+            .with_http(http.clone())
+            .with_normalizer(Normalizer::new(vec![Rule::Remaster]));
+        assert_eq!(importer.import_playlists(&pool).await.unwrap(), 1);
+
+        let playlists = crate::db::get_recommended_playlists(&pool, 10).unwrap();
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(
+            playlists[0].playlist.kind.as_deref(),
+            Some("weekly-exploration")
+        );
+        assert_eq!(
+            playlists[0].playlist.created_at.timestamp(),
+            created_at.timestamp()
+        );
+        assert_eq!(
+            (
+                playlists[0].tracks,
+                playlists[0].already_known,
+                playlists[0].played_since
+            ),
+            (3, 1, 1)
+        );
+
+        let (_, tracks) =
+            crate::db::get_recommended_playlist(&pool, "4f1b2c3d-0001-4e5f-8a9b-0c1d2e3f4a5b")
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            tracks[1].track.recording_mbid.as_deref(),
+            Some("7c2d9e1f-3a4b-4c5d-9e6f-0a1b2c3d4e5f")
+        );
+        assert_eq!((tracks[1].plays_before, tracks[1].plays_since), (0, 0));
+        assert_eq!(tracks[2].track.track, "Blue Bell Knoll");
+        assert_eq!(tracks[2].plays_since, 2);
+
+        // Stored playlists aren't fetched again
+        assert_eq!(importer.import_playlists(&pool).await.unwrap(), 0);
+        assert_eq!(http.requests().len(), 3);
+        assert!(http.requests()[2].ends_with("/createdfor?count=25"));
+
+        let without_token = ListenBrainzImporter::new("fixture".to_string(), None);
+        assert!(without_token.import_playlists(&pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_remembers_musicbrainz_ids() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(MockHttp::default().respond("/listens", 200, LISTENS));
+        let importer =
+            ListenBrainzImporter::new("fixture".to_string(), None).with_http(http.clone());
+        importer.import_all(&pool).await.unwrap();
+
+        let lookup = |entity_type, name| {
+            crate::db::get_musicbrainz_id(&pool, entity_type, "Cocteau Twins", name).unwrap()
+        };
+        assert_eq!(
+            lookup("artist", None).as_deref(),
+            Some("f9f1a9ad-2cf9-4f03-97bd-4a3bd3a1ad0b")
+        );
+        assert_eq!(
+            lookup("album", Some("Heaven or Las Vegas")).as_deref(),
+            Some("0a3b7e0e-7f5d-4a4d-8b9b-6c2f1e8d4a10")
+        );
+        assert_eq!(
+            lookup("track", Some("Heaven or Las Vegas")).as_deref(),
+            Some("a4e6d5c2-7b1b-4b8f-9a1e-3f7a0d3c5b2e")
+        );
+        assert_eq!(lookup("album", Some("Treasure")), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_retries_and_resumes_from_max_ts() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(
+            MockHttp::default()
+                .respond("/listens", 503, "")
+                .respond("/listens", 200, LISTENS),
+        );
+        let importer = ListenBrainzImporter::new("fixture".to_string(), None)
+            .with_http(http.clone())
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                initial_delay: std::time::Duration::ZERO,
+            });
+
+        let imported = importer
+            .import_all_before(&pool, Some(1709294500))
+            .await
+            .unwrap();
+        assert_eq!(imported, 3);
+        assert!(
+            http.requests()
+                .iter()
+                .all(|url| url.ends_with("&max_ts=1709294500"))
+        );
+        assert_eq!(http.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_reports_resume_point() {
+        let (pool, _temp_file) = setup_pool();
+        let page = json!({"payload": {"count": 100, "listens": (0..100).map(|i| json!({
+            "listened_at": 1709294400 - i,
+            "track_metadata": {"artist_name": "Artist", "track_name": format!("Track {}", i)},
+        })).collect::<Vec<_>>()}})
+        .to_string();
+        let http = Arc::new(
+            MockHttp::default()
+                .respond("count=100", 200, &page)
+                .respond("max_ts=", 404, ""),
+        );
+        let importer = ListenBrainzImporter::new("fixture".to_string(), None).with_http(http);
+
+        let err = importer.import_all(&pool).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("resume from max_ts 1709294301"));
+        assert_eq!(crate::db::get_scrobbles_count(&pool).unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_every_few_pages() {
+        let (pool, _temp_file) = setup_pool();
+        let job_id = crate::db::insert_import_job(
+            &pool,
+            &crate::models::ImportJob::new("listenbrainz".into(), "fixture".into()),
+        )
+        .unwrap();
+
+        let listens: Vec<serde_json::Value> = (0..100 * CHECKPOINT_PAGES as i64)
+            .map(|i| {
+                json!({
+                    "listened_at": 1_700_000_000 - i,
+                    "track_metadata": {"artist_name": "Artist", "track_name": format!("Track {}", i)},
+                })
+            })
+            .collect();
+        let mut mock = MockHttp::default();
+        for page in listens.chunks(100) {
+            let body = json!({"payload": {"count": 100, "listens": page}}).to_string();
+            mock = mock.respond("/listens", 200, &body);
+        }
+        let importer = ListenBrainzImporter::new("fixture".to_string(), None)
+            .with_http(Arc::new(mock.respond("/listens", 404, "")))
+            .with_checkpoints(Checkpoints::new(job_id, 0));
+
+        assert!(importer.import_all(&pool).await.is_err());
+
+        let job = crate::db::get_import_job(&pool, job_id).unwrap().unwrap();
+        assert_eq!(job.checkpoint, Some(1_700_000_000 - 999));
+        assert_eq!(job.imported_count, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_import_since_skips_older_listens() {
+        let (pool, _temp_file) = setup_pool();
+        let http = Arc::new(MockHttp::default().respond("/listens", 200, LISTENS));
+        let importer = ListenBrainzImporter::new("fixture".to_string(), None).with_http(http);
+
+        let since = DateTime::from_timestamp(1709294100, 0).unwrap();
+        assert_eq!(importer.import_since(&pool, since).await.unwrap(), 1);
+    }
+
+    fn listen_json() -> impl Strategy<Value = serde_json::Value> {
+        (
+            ".{0,10}",
+            ".{0,10}",
+            prop::option::of(".{0,10}"),
+            prop_oneof![
+                any::<i64>().prop_map(serde_json::Value::from),
+                (1i64..4_000_000_000).prop_map(serde_json::Value::from),
+                ".{0,10}".prop_map(serde_json::Value::from),
+                Just(serde_json::Value::Null),
+            ],
+        )
+            .prop_map(|(artist, track, release, listened_at)| {
+                json!({
+                    "listened_at": listened_at,
+                    "track_metadata": {
+                        "artist_name": artist,
+                        "track_name": track,
+                        "release_name": release,
+                    },
+                })
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_listens_are_stored_faithfully_or_skipped(
+            listens in prop::collection::vec(listen_json(), 0..8)
+        ) {
+            let page = json!({"payload": {"count": listens.len(), "listens": listens}}).to_string();
+            let data: ListenBrainzResponse = serde_json::from_str(&page).unwrap();
+            prop_assert_eq!(data.payload.listens.len(), listens.len());
+
+            for (entry, raw) in data.payload.listens.iter().zip(&listens) {
+                let expected_ts = raw["listened_at"].as_i64().and_then(listen_time);
+                let artist = raw["track_metadata"]["artist_name"].as_str().unwrap();
+                let track = raw["track_metadata"]["track_name"].as_str().unwrap();
+
+                match entry.valid().and_then(listen_to_scrobble) {
+                    Some(scrobble) => {
+                        prop_assert_eq!(Some(scrobble.timestamp), expected_ts);
+                        prop_assert_eq!(scrobble.artist.as_str(), artist);
+                        prop_assert_eq!(scrobble.track.as_str(), track);
+                        prop_assert!(scrobble.album.as_deref() != Some(""));
+                    }
+                    None => prop_assert!(
+                        expected_ts.is_none() || !is_usable_name(artist) || !is_usable_name(track)
+                    ),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_credentials() {
+        let importer = |token: Option<&str>, http: MockHttp| {
+            ListenBrainzImporter::new("fixture".to_string(), token.map(str::to_string))
+                .with_http(Arc::new(http))
+        };
+        let count = r#"{"payload": {"count": 42}}"#;
+
+        let ok = importer(
+            Some("token"),
+            MockHttp::default()
+                .respond(
+                    "validate-token",
+                    200,
+                    r#"{"valid": true, "user_name": "Fixture"}"#,
+                )
+                .respond("listen-count", 200, count),
+        );
+        assert!(ok.verify_credentials().await.unwrap().is_empty());
+
+        let bad_token = importer(
+            Some("token"),
+            MockHttp::default()
+                .respond("validate-token", 200, r#"{"valid": false}"#)
+                .respond("listen-count", 200, count),
+        );
+        assert_eq!(
+            bad_token.verify_credentials().await.unwrap()[0].field,
+            "token"
+        );
+
+        let other_user = importer(
+            Some("token"),
+            MockHttp::default()
+                .respond(
+                    "validate-token",
+                    200,
+                    r#"{"valid": true, "user_name": "someone"}"#,
+                )
+                .respond("listen-count", 404, ""),
+        );
+        let errors = other_user.verify_credentials().await.unwrap();
+        let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["token", "username"]);
+
+        let no_token = MockHttp::default().respond("listen-count", 200, count);
+        assert!(
+            importer(None, no_token)
+                .verify_credentials()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
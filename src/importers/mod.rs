@@ -1,5 +1,121 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::DbPool;
+use crate::models::{IgnoreRule, Scrobble};
+
+pub mod http;
+pub mod jobs;
 pub mod lastfm;
 pub mod listenbrainz;
+pub mod retry;
 
+pub use http::{HttpFetch, HttpResponse};
 pub use lastfm::LastFmImporter;
 pub use listenbrainz::ListenBrainzImporter;
+pub use retry::RetryPolicy;
+
+/// A list entry from an API page that either parses or is kept aside, so one
+/// malformed entry is skipped instead of failing the whole page
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Lenient<T> {
+    Valid(T),
+    Malformed(serde_json::Value),
+}
+
+impl<T> Lenient<T> {
+    pub fn valid(&self) -> Option<&T> {
+        match self {
+            Lenient::Valid(value) => Some(value),
+            Lenient::Malformed(_) => None,
+        }
+    }
+
+    pub fn into_valid(self) -> Option<T> {
+        match self {
+            Lenient::Valid(value) => Some(value),
+            Lenient::Malformed(_) => None,
+        }
+    }
+}
+
+/// Time of a listen from a source's Unix timestamp; zero, negative and
+/// out-of-range values are rejected rather than replaced with the current time
+pub fn listen_time(timestamp: i64) -> Option<DateTime<Utc>> {
+    if timestamp <= 0 {
+        return None;
+    }
+    DateTime::from_timestamp(timestamp, 0)
+}
+
+/// Whether a name from a source is worth storing
+pub fn is_usable_name(name: &str) -> bool {
+    !name.trim().is_empty()
+}
+
+/// Where an import job saves its progress as the importer goes
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoints {
+    job_id: i64,
+    /// Scrobbles the job had imported before this run
+    base_count: i64,
+}
+
+impl Checkpoints {
+    pub fn new(job_id: i64, base_count: i64) -> Self {
+        Self { job_id, base_count }
+    }
+
+    /// Record that everything before `checkpoint` is stored, after `imported`
+    /// scrobbles in this run
+    pub fn save(&self, pool: &DbPool, checkpoint: i64, imported: usize) -> Result<()> {
+        tracing::debug!("Import job {} checkpoint at {}", self.job_id, checkpoint);
+        crate::db::save_import_checkpoint(
+            pool,
+            self.job_id,
+            checkpoint,
+            self.base_count + imported as i64,
+        )
+    }
+}
+
+/// Ignore rules for one import run, tallying how many scrobbles each rule drops
+pub struct IgnoreList {
+    rules: Vec<IgnoreRule>,
+    suppressed: HashMap<i64, i64>,
+}
+
+impl IgnoreList {
+    pub fn load(pool: &DbPool, source: &str) -> Result<Self> {
+        Ok(Self {
+            rules: crate::db::get_ignore_rules(pool, Some(source))?,
+            suppressed: HashMap::new(),
+        })
+    }
+
+    /// Whether a rule matches the scrobble; the first matching rule is counted
+    pub fn suppresses(&mut self, scrobble: &Scrobble) -> bool {
+        match self.rules.iter().find(|rule| rule.matches(scrobble)) {
+            Some(rule) => {
+                if let Some(id) = rule.id {
+                    *self.suppressed.entry(id).or_insert(0) += 1;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Persist the tallies and return how many scrobbles were suppressed
+    pub fn flush(&mut self, pool: &DbPool) -> Result<i64> {
+        let total = self.suppressed.values().sum();
+        if total > 0 {
+            crate::db::add_suppressed_counts(pool, &self.suppressed)?;
+            self.suppressed.clear();
+        }
+        Ok(total)
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+use crate::importers::HttpFetch;
+
+/// How many times to try an API request and how long to wait in between
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub initial_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, retry: u32) -> Duration {
+        self.initial_delay * 2u32.saturating_pow(retry.saturating_sub(1))
+    }
+}
+
+/// GET a JSON page from `api`, retrying network errors, rate limiting, server
+/// errors and unparsable bodies with exponential backoff. Any other error
+/// status fails at once
+pub async fn fetch_json_with_retry<T: DeserializeOwned>(
+    http: &dyn HttpFetch,
+    url: &str,
+    headers: &[(&'static str, String)],
+    policy: RetryPolicy,
+    api: &str,
+) -> Result<T> {
+    let mut retry = 0;
+
+    loop {
+        let failure = match http
+            .get(url, headers)
+            .await
+            .with_context(|| format!("Failed to fetch from {}", api))
+        {
+            Ok(response) => {
+                let status = response.status;
+
+                if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    anyhow::anyhow!("{} API returned error: {}", api, status)
+                } else if !status.is_success() {
+                    return Err(anyhow::anyhow!("{} API returned error: {}", api, status));
+                } else {
+                    match response.json::<T>() {
+                        Ok(data) => return Ok(data),
+                        Err(e) => e.context(format!("Failed to parse {} response", api)),
+                    }
+                }
+            }
+            Err(e) => e,
+        };
+
+        retry += 1;
+        if retry >= policy.max_attempts {
+            return Err(failure.context(format!("Giving up after {} attempts", retry)));
+        }
+
+        let delay = policy.delay(retry);
+        tracing::warn!(
+            "{:#}, retrying in {:?} (attempt {}/{})",
+            failure,
+            delay,
+            retry,
+            policy.max_attempts
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::http::MockHttp;
+
+    const NO_WAIT: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        initial_delay: Duration::ZERO,
+    };
+
+    async fn fetch(http: &MockHttp) -> Result<serde_json::Value> {
+        fetch_json_with_retry(http, "https://api.test/page", &[], NO_WAIT, "Test").await
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        let http = MockHttp::default()
+            .respond("/page", 503, "")
+            .respond("/page", 200, "not json")
+            .respond("/page", 200, r#"{"ok": true}"#);
+
+        let data = fetch(&http).await.unwrap();
+        assert_eq!(data["ok"], true);
+        assert_eq!(http.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let http = MockHttp::default()
+            .respond("/page", 429, "")
+            .respond("/page", 503, "")
+            .respond("/page", 503, "")
+            .respond("/page", 200, "{}");
+
+        let err = fetch(&http).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("503"));
+        assert_eq!(http.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let http = MockHttp::default().respond("/page", 404, "");
+
+        assert!(fetch(&http).await.is_err());
+        assert_eq!(http.requests().len(), 1);
+    }
+}
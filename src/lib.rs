@@ -1,12 +1,30 @@
 // Library modules for Footprints
 // This allows tests to access internal modules
 
+pub mod achievements;
+pub mod alerts;
+pub mod anomalies;
 pub mod api;
+pub mod auth;
+pub mod charts;
+pub mod classifier;
+pub mod conflicts;
+pub mod credits;
 pub mod db;
+pub mod demo;
 pub mod images;
 pub mod importers;
+pub mod live;
+pub mod manual;
 pub mod models;
+pub mod normalizer;
+pub mod overview;
+pub mod playlists;
+pub mod recommendations;
+pub mod releases;
 pub mod reports;
+pub mod settings;
+pub mod summaries;
 pub mod sync;
 
 #[cfg(test)]
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast};
+
+use crate::alerts::Alert;
+use crate::db::DbPool;
+use crate::importers::{LastFmImporter, ListenBrainzImporter};
+use crate::models::{Achievement, NowPlaying, Scrobble, SyncConfig};
+
+// Configurable constants for live updates
+const SCROBBLE_POLL_INTERVAL_SECS: u64 = 2;
+const NOW_PLAYING_POLL_INTERVAL_SECS: u64 = 30;
+const CHANNEL_CAPACITY: usize = 256;
+// More new rows than this in one poll is a bulk import, not live listening
+const MAX_SCROBBLES_PER_POLL: i64 = 50;
+
+/// Event pushed to live subscribers (WebSocket clients)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    Scrobble {
+        scrobble: Scrobble,
+    },
+    NowPlaying {
+        source: String,
+        username: String,
+        track: Option<NowPlaying>,
+    },
+    Alert {
+        alert: Alert,
+    },
+    Achievement {
+        achievement: Achievement,
+    },
+}
+
+/// Broadcasts newly inserted scrobbles and now-playing changes.
+///
+/// New scrobbles are picked up by watching the scrobbles table, so every
+/// insertion path (imports, scheduled syncs) is covered. Now-playing is
+/// polled from the enabled sync configs, only while someone is listening.
+#[derive(Clone)]
+pub struct LiveHub {
+    pool: DbPool,
+    sender: broadcast::Sender<LiveEvent>,
+    now_playing: Arc<RwLock<HashMap<i64, LiveEvent>>>,
+}
+
+impl LiveHub {
+    pub fn new(pool: DbPool) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            pool,
+            sender,
+            now_playing: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Send an event to current subscribers; nobody listening is not an error
+    pub fn publish(&self, event: LiveEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Current now-playing state of every source, sent to new subscribers
+    pub async fn now_playing_snapshot(&self) -> Vec<LiveEvent> {
+        self.now_playing.read().await.values().cloned().collect()
+    }
+
+    /// Start watching for new scrobbles and now-playing changes in the background
+    pub fn start(&self) -> Result<()> {
+        let last_id = crate::db::get_max_scrobble_id(&self.pool)?;
+
+        let hub = self.clone();
+        tokio::spawn(async move {
+            hub.watch_scrobbles(last_id).await;
+        });
+
+        let hub = self.clone();
+        tokio::spawn(async move {
+            hub.watch_now_playing().await;
+        });
+
+        Ok(())
+    }
+
+    async fn watch_scrobbles(&self, mut last_id: i64) {
+        let interval = Duration::from_secs(SCROBBLE_POLL_INTERVAL_SECS);
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match crate::db::get_scrobbles_after_id(&self.pool, last_id, MAX_SCROBBLES_PER_POLL + 1)
+            {
+                Ok(scrobbles) if scrobbles.len() as i64 > MAX_SCROBBLES_PER_POLL => {
+                    // Skip past the bulk import instead of flooding subscribers
+                    match crate::db::get_max_scrobble_id(&self.pool) {
+                        Ok(id) => last_id = id,
+                        Err(e) => tracing::error!("Failed to read latest scrobble id: {}", e),
+                    }
+                }
+                Ok(scrobbles) => {
+                    for scrobble in scrobbles {
+                        last_id = scrobble.id.unwrap_or(last_id);
+                        // Sending only fails when nobody is subscribed
+                        let _ = self.sender.send(LiveEvent::Scrobble { scrobble });
+                    }
+                }
+                Err(e) => tracing::error!("Failed to poll new scrobbles: {}", e),
+            }
+        }
+    }
+
+    async fn watch_now_playing(&self) {
+        let interval = Duration::from_secs(NOW_PLAYING_POLL_INTERVAL_SECS);
+
+        loop {
+            if self.sender.receiver_count() > 0
+                && let Err(e) = self.refresh_now_playing().await
+            {
+                tracing::error!("Failed to refresh now playing: {}", e);
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn refresh_now_playing(&self) -> Result<()> {
+        let configs = crate::db::get_enabled_sync_configs(&self.pool)?;
+
+        for config in configs {
+            let Some(config_id) = config.id else {
+                continue;
+            };
+
+            let track = match fetch_now_playing(&config).await {
+                Ok(track) => track,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to fetch now playing for {} user {}: {}",
+                        config.source,
+                        config.username,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let event = LiveEvent::NowPlaying {
+                source: config.source.clone(),
+                username: config.username.clone(),
+                track,
+            };
+
+            let mut now_playing = self.now_playing.write().await;
+            let changed = match now_playing.get(&config_id) {
+                Some(LiveEvent::NowPlaying {
+                    track: previous, ..
+                }) => !matches!(&event, LiveEvent::NowPlaying { track, .. } if track == previous),
+                _ => true,
+            };
+
+            if changed {
+                now_playing.insert(config_id, event.clone());
+                let _ = self.sender.send(event);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn fetch_now_playing(config: &SyncConfig) -> Result<Option<NowPlaying>> {
+    match config.source.as_str() {
+        "lastfm" => {
+            if let Some(api_key) = &config.api_key {
+                LastFmImporter::new(api_key.clone(), config.username.clone())
+                    .fetch_now_playing()
+                    .await
+            } else {
+                Err(anyhow::anyhow!("API key required for Last.fm"))
+            }
+        }
+        "listenbrainz" => {
+            ListenBrainzImporter::new(config.username.clone(), config.token.clone())
+                .fetch_now_playing()
+                .await
+        }
+        _ => Err(anyhow::anyhow!("Unknown source: {}", config.source)),
+    }
+}
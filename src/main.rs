@@ -1,12 +1,9 @@
-mod api;
-mod db;
-mod images;
-mod importers;
-mod models;
-mod reports;
-mod sync;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use footprints::{
+    achievements, alerts, api, auth, charts, db, demo, images, importers, live, normalizer,
+    releases, summaries, sync,
+};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::services::ServeDir;
@@ -14,49 +11,226 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "footprints=info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
     // Load environment variables if .env exists
     let _ = dotenvy::dotenv();
 
+    // Initialize tracing (LOG_FORMAT=json emits one JSON object per line)
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "footprints=info,tower_http=info".into());
+    let json_logs = std::env::var("LOG_FORMAT")
+        .map(|format| format.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    if json_logs {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true),
+            )
+            .init();
+    } else {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer())
+            .init();
+    }
+
     // Get database path from environment or use default
     let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "footprints.db".to_string());
 
     tracing::info!("Initializing database at {}", db_path);
 
+    // READ_ONLY=true serves the data without ever writing to it: the file is
+    // opened read-only, so it must already have been set up by a writable run
+    let read_only = std::env::var("READ_ONLY")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
     // Create database pool
-    let pool = db::create_pool(&db_path)?;
+    let pool = if read_only {
+        db::create_read_only_pool(&db_path)?
+    } else {
+        db::create_pool(&db_path)?
+    };
+
+    // Check the file before touching it; INTEGRITY_CHECK picks quick, full or off
+    let integrity_mode = std::env::var("INTEGRITY_CHECK").unwrap_or_else(|_| "quick".to_string());
+    if integrity_mode != "off" {
+        let report = db::check_integrity(&pool, integrity_mode == "full")
+            .context("Failed to check database integrity")?;
+        if report.ok {
+            tracing::info!(
+                "Database integrity check ({}) passed in {}ms",
+                report.mode,
+                report.duration_ms
+            );
+        } else {
+            for problem in &report.problems {
+                tracing::error!("Database integrity problem: {}", problem);
+            }
+            let allow_corrupt = std::env::var("ALLOW_CORRUPT_DATABASE")
+                .map(|v| v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+            if !allow_corrupt {
+                anyhow::bail!(
+                    "Database {} failed its integrity check; restore a backup, or set ALLOW_CORRUPT_DATABASE=true to start anyway",
+                    db_path
+                );
+            }
+            tracing::warn!(
+                "Starting with a corrupt database because ALLOW_CORRUPT_DATABASE is set"
+            );
+        }
+    }
 
     // Initialize database schema
-    db::init_database(&pool)?;
+    if !read_only {
+        db::init_database(&pool)?;
+    }
 
     tracing::info!("Database initialized successfully");
 
+    // `footprints generate-demo [--scrobbles N] [--genres jazz,rock] [--days N] [--seed N]`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("generate-demo") {
+        let options = demo::DemoOptions::from_args(&args[1..])?;
+        let inserted = demo::populate(&pool, &options)?;
+        tracing::info!(
+            "Generated {} demo scrobbles (source \"{}\")",
+            inserted,
+            demo::DEMO_SOURCE
+        );
+        return Ok(());
+    }
+
     // Get Last.fm API key from environment
     let lastfm_api_key = std::env::var("LASTFM_API_KEY").unwrap_or_else(|_| {
         tracing::warn!("LASTFM_API_KEY not set; artist/album images will not be fetched");
         String::new()
     });
 
+    let image_lookups = !lastfm_api_key.is_empty();
+
     // Create image service
     let image_service = Arc::new(images::ImageService::new(pool.clone(), lastfm_api_key));
     tracing::info!("Image service initialized");
 
-    // Start sync scheduler
-    let sync_scheduler = sync::SyncScheduler::new(pool.clone());
-    sync_scheduler.start().await;
-    tracing::info!("Sync scheduler started");
+    // Clean up track metadata on ingest; NORMALIZE_RULES picks the rules
+    let normalizer = normalizer::Normalizer::from_env()?;
+    tracing::info!("Metadata normalization rules: {:?}", normalizer.rules());
+
+    // No syncs, imports or alert state, and every mutating endpoint is refused
+    if read_only {
+        tracing::info!("Read-only mode: sync, imports and changes through the API are disabled");
+    }
+
+    // Start pushing new scrobbles and now-playing changes to live clients
+    let live_hub = live::LiveHub::new(pool.clone());
+    live_hub.start()?;
+    tracing::info!("Live update hub started");
+
+    // Start sync scheduler; ACHIEVEMENT_WEBHOOK_URL also receives newly
+    // earned achievements
+    let mut sync_scheduler = sync::SyncScheduler::new(pool.clone())
+        .with_normalizer(normalizer.clone())
+        .with_achievements(
+            achievements::AchievementNotifier::new(pool.clone(), live_hub.clone())
+                .with_webhook_url(std::env::var("ACHIEVEMENT_WEBHOOK_URL").ok()),
+        );
+    if let Some(tick) = std::env::var("SYNC_TICK_SECONDS")
+        .ok()
+        .and_then(|t| t.parse::<u64>().ok())
+    {
+        sync_scheduler = sync_scheduler.with_tick_interval(std::time::Duration::from_secs(tick));
+    }
+    if !read_only {
+        sync_scheduler.start().await;
+        tracing::info!("Sync scheduler started");
+
+        // Full imports cut short by a crash or restart continue from their checkpoint
+        let resumed =
+            importers::jobs::resume_interrupted_imports(pool.clone(), normalizer.clone())?;
+        if resumed > 0 {
+            tracing::info!("Resuming {} interrupted imports", resumed);
+        }
+    }
+
+    // Watch archive health; ALERT_WEBHOOK_URL also receives fired alerts
+    if !read_only {
+        alerts::AlertMonitor::new(pool.clone(), live_hub.clone())
+            .with_webhook_url(std::env::var("ALERT_WEBHOOK_URL").ok())
+            .start();
+        tracing::info!("Alert monitor started");
+    }
+
+    // Record each finished week's top artists and tracks for the chart history
+    if !read_only {
+        charts::ChartRecorder::new(pool.clone()).start();
+        tracing::info!("Chart recorder started");
+    }
+
+    // Roll up each finished day for the calendar and the digest
+    if !read_only {
+        summaries::SummaryRecorder::new(pool.clone()).start();
+        tracing::info!("Daily summary recorder started");
+    }
+
+    // RELEASE_RADAR=true follows the top artists' new releases on MusicBrainz
+    let release_radar = !read_only
+        && std::env::var("RELEASE_RADAR")
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+    if release_radar {
+        releases::ReleaseRadar::new(pool.clone()).start();
+        tracing::info!("Release radar started");
+    }
+
+    // Trust a reverse proxy's Remote-User header, or sign users in with an
+    // OIDC provider, when configured
+    let auth_mode = match (
+        auth::AuthConfig::from_env()?,
+        auth::oidc::OidcConfig::from_env()?,
+    ) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!("AUTH_TRUSTED_PROXIES and OIDC_ISSUER_URL can't be set together")
+        }
+        (Some(config), None) => {
+            tracing::info!("Reverse-proxy authentication enabled");
+            Some(auth::AuthMode::Proxy(config))
+        }
+        (None, Some(config)) => {
+            let http = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()?;
+            let client = auth::oidc::OidcClient::discover(config, Arc::new(http)).await?;
+            tracing::info!("OIDC login enabled");
+            Some(auth::AuthMode::Oidc(Arc::new(client)))
+        }
+        (None, None) => None,
+    };
+
+    let options = api::InstanceOptions {
+        read_only,
+        tls: std::env::var("TLS_CERT").is_ok() && std::env::var("TLS_KEY").is_ok(),
+        image_lookups,
+        alert_webhook: std::env::var("ALERT_WEBHOOK_URL").is_ok(),
+        release_radar,
+    };
 
     // Create router with sync scheduler
-    let app = api::create_router(pool, image_service, sync_scheduler)
-        .nest_service("/static", ServeDir::new("static"));
+    let app = api::create_router(
+        pool,
+        image_service,
+        sync_scheduler,
+        live_hub,
+        auth_mode,
+        normalizer,
+        options,
+    )
+    .nest_service("/static", ServeDir::new("static"));
 
     // Get port from environment or use default
     let port = std::env::var("PORT")
@@ -65,10 +239,39 @@ async fn main() -> Result<()> {
         .unwrap_or(3000);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("Starting server on {}", addr);
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    // Serve HTTPS directly when both a certificate and a key are configured
+    match (
+        std::env::var("TLS_CERT").ok(),
+        std::env::var("TLS_KEY").ok(),
+    ) {
+        (Some(cert_path), Some(key_path)) => {
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let tls_config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load TLS certificate {} or key {}",
+                        cert_path, key_path
+                    )
+                })?;
+
+            tracing::info!("Starting HTTPS server on {}", addr);
+            axum_server::bind_rustls(addr, tls_config)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        (None, None) => {
+            tracing::info!("Starting server on {}", addr);
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?;
+        }
+        _ => anyhow::bail!("TLS_CERT and TLS_KEY must be set together"),
+    }
 
     Ok(())
 }
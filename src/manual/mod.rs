@@ -0,0 +1,622 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::classifier::MediaClassifier;
+use crate::db::DbPool;
+use crate::importers::{HttpFetch, is_usable_name};
+use crate::models::{FieldError, Scrobble};
+use crate::normalizer::Normalizer;
+
+/// Source of scrobbles entered by hand without a tag of their own
+pub const MANUAL_SOURCE: &str = "manual";
+
+/// Most scrobbles accepted in one submission
+pub const MAX_MANUAL_SCROBBLES: usize = 1000;
+
+// Allowance for clocks running a little ahead of the server's
+const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+
+// Longest custom source tag, e.g. "vinyl" or "concert"
+const MAX_SOURCE_LEN: usize = 32;
+
+// Length assumed for album tracks whose duration isn't known
+const DEFAULT_TRACK_SECONDS: i64 = 240;
+
+// Longest album track accepted, a day
+const MAX_TRACK_SECONDS: i64 = 24 * 60 * 60;
+
+// Plays ending this close to the end of the track still count as full
+// listens, allowing for crossfades and players reporting early
+const SKIP_GRACE_MS: i64 = 10_000;
+
+/// A listen logged by hand: a record, a CD, a concert
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManualScrobble {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    /// Artist the album is credited to, when it isn't `artist`
+    pub album_artist: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// Stored as the scrobble's source, `manual` by default
+    pub source: Option<String>,
+    /// How long the track played, from a player integration. With
+    /// `duration_ms`, plays too short to count are dropped
+    pub ms_played: Option<i64>,
+    pub duration_ms: Option<i64>,
+    /// Whether the listener skipped the track, from players that know.
+    /// Otherwise worked out from `ms_played` and `duration_ms`
+    pub skipped: Option<bool>,
+}
+
+/// A rejected entry of a submission
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryError {
+    pub index: usize,
+    #[serde(flatten)]
+    pub error: FieldError,
+}
+
+/// Outcome of a submission that passed validation
+#[derive(Debug, Clone, Serialize)]
+pub struct Submission {
+    pub inserted: usize,
+    /// Entries already stored, or within the dedup window of a stored one
+    pub duplicates: usize,
+    /// Plays too short to count under the instance's listen filter
+    pub filtered: usize,
+}
+
+impl ManualScrobble {
+    /// Problems with this entry, checked against `now`
+    pub fn validate(&self, now: DateTime<Utc>) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if !is_usable_name(&self.artist) {
+            errors.push(FieldError::new("artist", "is required"));
+        }
+        if !is_usable_name(&self.track) {
+            errors.push(FieldError::new("track", "is required"));
+        }
+        if self.timestamp.timestamp() <= 0 {
+            errors.push(FieldError::new("timestamp", "must be after 1970"));
+        } else if self.timestamp > now + Duration::minutes(MAX_CLOCK_SKEW_MINUTES) {
+            errors.push(FieldError::new("timestamp", "is in the future"));
+        }
+        if self.ms_played.is_some_and(|ms| ms < 0) {
+            errors.push(FieldError::new("ms_played", "can't be negative"));
+        }
+        if self.duration_ms.is_some_and(|ms| ms <= 0) {
+            errors.push(FieldError::new("duration_ms", "must be positive"));
+        }
+        if let Some(source) = &self.source
+            && !is_valid_source(source)
+        {
+            errors.push(FieldError::new(
+                "source",
+                format!(
+                    "must be 1-{} lowercase letters, digits, '-' or '_'",
+                    MAX_SOURCE_LEN
+                ),
+            ));
+        }
+
+        errors
+    }
+
+    pub fn to_scrobble(&self) -> Scrobble {
+        let mut scrobble = Scrobble::new(
+            self.artist.trim().to_string(),
+            self.track.trim().to_string(),
+            self.timestamp,
+            self.source
+                .clone()
+                .unwrap_or_else(|| MANUAL_SOURCE.to_string()),
+        );
+        if let Some(album) = self.album.as_deref().map(str::trim)
+            && !album.is_empty()
+        {
+            scrobble = scrobble.with_album(album.to_string());
+        }
+        if let Some(album_artist) = self.album_artist.as_deref().map(str::trim)
+            && !album_artist.is_empty()
+        {
+            scrobble = scrobble.with_album_artist(album_artist.to_string());
+        }
+        scrobble.ms_played = self.ms_played;
+        scrobble.skipped = self.skipped.or_else(|| {
+            let (played, duration) = self.ms_played.zip(self.duration_ms)?;
+            Some(played + SKIP_GRACE_MS < duration)
+        });
+        scrobble
+    }
+}
+
+fn is_valid_source(source: &str) -> bool {
+    !source.is_empty()
+        && source.len() <= MAX_SOURCE_LEN
+        && source
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Every problem in a submission, by entry index. Empty when it can be stored
+pub fn validate(entries: &[ManualScrobble], now: DateTime<Utc>) -> Vec<EntryError> {
+    if entries.is_empty() {
+        return vec![EntryError {
+            index: 0,
+            error: FieldError::new("scrobbles", "at least one scrobble is required"),
+        }];
+    }
+    if entries.len() > MAX_MANUAL_SCROBBLES {
+        return vec![EntryError {
+            index: MAX_MANUAL_SCROBBLES,
+            error: FieldError::new(
+                "scrobbles",
+                format!("at most {} scrobbles per request", MAX_MANUAL_SCROBBLES),
+            ),
+        }];
+    }
+
+    entries
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entry)| {
+            entry
+                .validate(now)
+                .into_iter()
+                .map(move |error| EntryError { index, error })
+        })
+        .collect()
+}
+
+/// Store validated entries through the same normalization and media
+/// classification as imports, dropping plays the listen filter rejects
+pub fn submit(
+    pool: &DbPool,
+    normalizer: &Normalizer,
+    entries: &[ManualScrobble],
+) -> Result<Submission> {
+    let filter = crate::db::get_listen_filter(pool)?;
+    let classifier = MediaClassifier::load(pool)?;
+    let scrobbles: Vec<Scrobble> = entries
+        .iter()
+        .filter(|entry| filter.accepts(entry.ms_played, entry.duration_ms))
+        .map(|entry| classifier.apply(normalizer.normalize(entry.to_scrobble())))
+        .collect();
+
+    let inserted = crate::db::insert_scrobbles_batch(pool, &scrobbles)?;
+    Ok(Submission {
+        inserted,
+        duplicates: scrobbles.len() - inserted,
+        filtered: entries.len() - scrobbles.len(),
+    })
+}
+
+/// A track of an album listened to offline
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum AlbumTrack {
+    Title(String),
+    Timed {
+        title: String,
+        duration_seconds: Option<i64>,
+    },
+}
+
+impl AlbumTrack {
+    fn title(&self) -> &str {
+        match self {
+            AlbumTrack::Title(title) | AlbumTrack::Timed { title, .. } => title,
+        }
+    }
+
+    /// How long the track plays, or `None` when the given duration is longer
+    /// than a day
+    fn duration(&self) -> Option<Duration> {
+        let seconds = match self {
+            AlbumTrack::Timed {
+                duration_seconds: Some(seconds),
+                ..
+            } if *seconds > 0 => *seconds,
+            _ => DEFAULT_TRACK_SECONDS,
+        };
+        if seconds > MAX_TRACK_SECONDS {
+            return None;
+        }
+        Duration::try_seconds(seconds)
+    }
+}
+
+/// A whole album played from `start`, given as a tracklist or looked up by
+/// MusicBrainz release id
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlbumListen {
+    /// Required with a tracklist; taken from the release otherwise
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    #[serde(default)]
+    pub tracks: Vec<AlbumTrack>,
+    pub release_mbid: Option<String>,
+    pub start: DateTime<Utc>,
+    pub source: Option<String>,
+}
+
+impl AlbumListen {
+    /// One scrobble per track, each starting when the previous one ends.
+    /// Fails on the first track longer than a day or ending past the last
+    /// representable time
+    pub fn to_entries(&self) -> Result<Vec<ManualScrobble>, EntryError> {
+        let mut timestamp = self.start;
+        let mut entries = Vec::with_capacity(self.tracks.len());
+        for (index, track) in self.tracks.iter().enumerate() {
+            entries.push(ManualScrobble {
+                artist: self.artist.clone().unwrap_or_default(),
+                track: track.title().to_string(),
+                album: self.album.clone(),
+                album_artist: self.album_artist.clone(),
+                timestamp,
+                source: self.source.clone(),
+                ms_played: None,
+                duration_ms: None,
+                skipped: None,
+            });
+            timestamp = track
+                .duration()
+                .and_then(|duration| timestamp.checked_add_signed(duration))
+                .ok_or_else(|| EntryError {
+                    index,
+                    error: FieldError::new(
+                        "duration_seconds",
+                        format!("must be at most {} seconds", MAX_TRACK_SECONDS),
+                    ),
+                })?;
+        }
+        Ok(entries)
+    }
+
+    /// Fill in the artist, album and tracklist from MusicBrainz when a
+    /// release id is given; values already set are kept
+    pub async fn resolve(mut self, http: &dyn HttpFetch) -> Result<Self> {
+        let Some(mbid) = self.release_mbid.clone() else {
+            return Ok(self);
+        };
+
+        let release = fetch_release(http, &mbid).await?;
+        self.artist.get_or_insert(release.artist);
+        self.album.get_or_insert(release.title);
+        if self.tracks.is_empty() {
+            self.tracks = release.tracks;
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzRelease {
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<MusicBrainzCredit>,
+    #[serde(default)]
+    media: Vec<MusicBrainzMedium>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzMedium {
+    #[serde(default)]
+    tracks: Vec<MusicBrainzTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicBrainzTrack {
+    title: String,
+    /// Milliseconds
+    length: Option<i64>,
+}
+
+struct Release {
+    artist: String,
+    title: String,
+    tracks: Vec<AlbumTrack>,
+}
+
+async fn fetch_release(http: &dyn HttpFetch, mbid: &str) -> Result<Release> {
+    let url = format!(
+        "https://musicbrainz.org/ws/2/release/{}?inc=recordings+artist-credits&fmt=json",
+        urlencoding::encode(mbid)
+    );
+    // MusicBrainz rejects requests without an identifying User-Agent
+    let headers = [(
+        "User-Agent",
+        format!(
+            "footprints/{} ( https://github.com/dbeley/footprints )",
+            env!("CARGO_PKG_VERSION")
+        ),
+    )];
+
+    let response = http
+        .get(&url, &headers)
+        .await
+        .context("Failed to fetch from MusicBrainz")?;
+    if !response.status.is_success() {
+        return Err(anyhow::anyhow!(
+            "MusicBrainz API returned error: {}",
+            response.status
+        ));
+    }
+    let release: MusicBrainzRelease = response
+        .json()
+        .context("Failed to parse MusicBrainz response")?;
+
+    Ok(Release {
+        artist: release
+            .artist_credit
+            .iter()
+            .map(|credit| format!("{}{}", credit.name, credit.joinphrase))
+            .collect(),
+        title: release.title,
+        tracks: release
+            .media
+            .into_iter()
+            .flat_map(|medium| medium.tracks)
+            .map(|track| AlbumTrack::Timed {
+                title: track.title,
+                duration_seconds: track.length.map(|ms| (ms + 500) / 1000),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ListenFilter;
+    use crate::test_utils::setup_pool;
+
+    const RELEASE: &str = r#"{
+        "title": "Kind of Blue",
+        "artist-credit": [{"name": "Miles Davis", "joinphrase": ""}],
+        "media": [{"tracks": [
+            {"title": "So What", "length": 562000},
+            {"title": "Freddie Freeloader", "length": 585600},
+            {"title": "Blue in Green", "length": null}
+        ]}]
+    }"#;
+
+    fn entry(artist: &str, track: &str, timestamp: &str) -> ManualScrobble {
+        ManualScrobble {
+            artist: artist.to_string(),
+            track: track.to_string(),
+            album: Some("Kind of Blue".to_string()),
+            album_artist: None,
+            timestamp: timestamp.parse().unwrap(),
+            source: None,
+            ms_played: None,
+            duration_ms: None,
+            skipped: None,
+        }
+    }
+
+    #[test]
+    fn test_validation_reports_each_bad_field() {
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let mut tagged = entry("Miles Davis", "So What", "2024-06-01T11:00:00Z");
+        tagged.source = Some("vinyl".to_string());
+        let mut bad_tag = entry("Miles Davis", "Blue in Green", "2024-06-01T11:10:00Z");
+        bad_tag.source = Some("My Turntable".to_string());
+
+        let errors = validate(
+            &[
+                tagged,
+                entry(" ", "Freddie Freeloader", "2024-06-01T11:05:00Z"),
+                bad_tag,
+                entry("Miles Davis", "All Blues", "2024-06-02T12:00:00Z"),
+            ],
+            now,
+        );
+
+        let found: Vec<_> = errors.iter().map(|e| (e.index, e.error.field)).collect();
+        assert_eq!(found, vec![(1, "artist"), (2, "source"), (3, "timestamp")]);
+    }
+
+    #[test]
+    fn test_empty_and_oversized_submissions_are_rejected() {
+        let now = Utc::now();
+        assert_eq!(validate(&[], now).len(), 1);
+
+        let many = vec![entry("Miles Davis", "So What", "2024-06-01T11:00:00Z"); 1001];
+        assert_eq!(validate(&many, now)[0].error.field, "scrobbles");
+    }
+
+    #[test]
+    fn test_submit_stores_manual_scrobbles_once() {
+        let (pool, _temp_file) = setup_pool();
+        let entries = vec![
+            entry("Miles Davis", "So What", "2024-06-01T11:00:00Z"),
+            entry("Miles Davis", "Freddie Freeloader", "2024-06-01T11:09:00Z"),
+        ];
+
+        let first = submit(&pool, &Normalizer::default(), &entries).unwrap();
+        assert_eq!(
+            (first.inserted, first.duplicates, first.filtered),
+            (2, 0, 0)
+        );
+        let again = submit(&pool, &Normalizer::default(), &entries).unwrap();
+        assert_eq!((again.inserted, again.duplicates), (0, 2));
+
+        let stored = crate::db::get_scrobbles(&pool, Some(10), None).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|s| s.source == MANUAL_SOURCE));
+        assert_eq!(stored[0].album.as_deref(), Some("Kind of Blue"));
+    }
+
+    #[test]
+    fn test_short_plays_are_filtered() {
+        let (pool, _temp_file) = setup_pool();
+        let played = |track: &str, timestamp: &str, ms_played: i64, duration_ms: Option<i64>| {
+            let mut entry = entry("Miles Davis", track, timestamp);
+            entry.ms_played = Some(ms_played);
+            entry.duration_ms = duration_ms;
+            entry
+        };
+
+        let entries = vec![
+            // Half of the track
+            played("So What", "2024-06-01T11:00:00Z", 281_000, Some(562_000)),
+            // Four minutes of a long track
+            played(
+                "Flamenco Sketches",
+                "2024-06-01T11:10:00Z",
+                240_000,
+                Some(566_000),
+            ),
+            // Skipped after a minute
+            played("All Blues", "2024-06-01T11:20:00Z", 60_000, Some(693_000)),
+            // A 20 second interlude played in full
+            played("Interlude", "2024-06-01T11:30:00Z", 20_000, Some(20_000)),
+            // No track length: only the minimum length applies
+            played("Blue in Green", "2024-06-01T11:40:00Z", 45_000, None),
+        ];
+
+        let submission = submit(&pool, &Normalizer::default(), &entries).unwrap();
+        assert_eq!((submission.inserted, submission.filtered), (3, 2));
+
+        crate::db::set_listen_filter(
+            &pool,
+            &ListenFilter {
+                min_play_percent: 0,
+                min_track_seconds: 0,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let submission = submit(&pool, &Normalizer::default(), &entries).unwrap();
+        assert_eq!((submission.duplicates, submission.filtered), (3, 0));
+
+        let stored = crate::db::get_scrobbles(&pool, Some(10), None).unwrap();
+        assert_eq!(stored.len(), 5);
+        assert!(stored.iter().all(|s| s.ms_played.is_some()));
+    }
+
+    #[test]
+    fn test_skips_from_playback() {
+        let played = |ms_played: Option<i64>, duration_ms: Option<i64>, skipped: Option<bool>| {
+            let mut entry = entry("Miles Davis", "So What", "2024-06-01T11:00:00Z");
+            entry.ms_played = ms_played;
+            entry.duration_ms = duration_ms;
+            entry.skipped = skipped;
+            entry.to_scrobble().skipped
+        };
+
+        assert_eq!(played(Some(281_000), Some(562_000), None), Some(true));
+        // Played to the end but for the fade out
+        assert_eq!(played(Some(555_000), Some(562_000), None), Some(false));
+        // The player's word wins
+        assert_eq!(
+            played(Some(281_000), Some(562_000), Some(false)),
+            Some(false)
+        );
+        // Unknown without the track length
+        assert_eq!(played(Some(281_000), None, None), None);
+    }
+
+    #[test]
+    fn test_album_tracks_play_back_to_back() {
+        let listen = AlbumListen {
+            artist: Some("Miles Davis".to_string()),
+            album: Some("Kind of Blue".to_string()),
+            album_artist: None,
+            tracks: vec![
+                AlbumTrack::Timed {
+                    title: "So What".to_string(),
+                    duration_seconds: Some(562),
+                },
+                AlbumTrack::Title("Freddie Freeloader".to_string()),
+                AlbumTrack::Title("Blue in Green".to_string()),
+            ],
+            release_mbid: None,
+            start: "2024-06-01T20:00:00Z".parse().unwrap(),
+            source: Some("vinyl".to_string()),
+        };
+
+        let entries = listen.to_entries().unwrap();
+        let times: Vec<_> = entries.iter().map(|e| e.timestamp.to_rfc3339()).collect();
+        assert_eq!(
+            times,
+            vec![
+                "2024-06-01T20:00:00+00:00",
+                "2024-06-01T20:09:22+00:00",
+                "2024-06-01T20:13:22+00:00",
+            ]
+        );
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.album.as_deref() == Some("Kind of Blue"))
+        );
+    }
+
+    #[test]
+    fn test_album_track_durations_are_bounded() {
+        let listen = |duration_seconds| AlbumListen {
+            artist: Some("Slint".to_string()),
+            album: None,
+            album_artist: None,
+            tracks: vec![
+                AlbumTrack::Title("Breadcrumb Trail".to_string()),
+                AlbumTrack::Timed {
+                    title: "Nosferatu Man".to_string(),
+                    duration_seconds: Some(duration_seconds),
+                },
+                AlbumTrack::Title("Don, Aman".to_string()),
+            ],
+            release_mbid: None,
+            start: "2024-05-01T20:00:00Z".parse().unwrap(),
+            source: None,
+        };
+
+        let error = listen(i64::MAX).to_entries().unwrap_err();
+        assert_eq!(error.index, 1);
+        assert_eq!(error.error.field, "duration_seconds");
+        assert!(listen(MAX_TRACK_SECONDS + 1).to_entries().is_err());
+        assert_eq!(listen(MAX_TRACK_SECONDS).to_entries().unwrap().len(), 3);
+
+        // Tracks can't run past the last representable time
+        let mut late = listen(60);
+        late.start = DateTime::<Utc>::MAX_UTC - Duration::seconds(30);
+        assert!(late.to_entries().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_release_fills_in_missing_details() {
+        let http = crate::importers::http::MockHttp::default().respond("/release/", 200, RELEASE);
+        let listen = AlbumListen {
+            artist: None,
+            album: None,
+            album_artist: None,
+            tracks: Vec::new(),
+            release_mbid: Some("mbid".to_string()),
+            start: "2024-06-01T20:00:00Z".parse().unwrap(),
+            source: None,
+        };
+
+        let entries = listen.resolve(&http).await.unwrap().to_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].artist, "Miles Davis");
+        assert_eq!(entries[1].track, "Freddie Freeloader");
+        assert_eq!(
+            entries[2].timestamp.to_rfc3339(),
+            "2024-06-01T20:19:08+00:00"
+        );
+        assert!(http.requests()[0].contains("/release/mbid?"));
+    }
+}
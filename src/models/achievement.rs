@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::reports::yearly::{Milestone, MilestoneIcon};
+
+/// Yearly milestone as it was the first time it was earned; each is earned
+/// at most once per year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Achievement {
+    pub id: Option<i64>,
+    pub year: i32,
+    /// Rule that awarded it, see [`Milestone::key`]
+    pub key: String,
+    pub title: String,
+    pub description: String,
+    pub value: String,
+    pub icon: MilestoneIcon,
+    pub earned_at: DateTime<Utc>,
+}
+
+impl Achievement {
+    pub fn earned(year: i32, milestone: Milestone, earned_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            year,
+            key: milestone.key,
+            title: milestone.title,
+            description: milestone.description,
+            value: milestone.value,
+            icon: milestone.icon,
+            earned_at,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Condition an alert rule watches for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The newest scrobble is older than the threshold
+    NoScrobbles,
+    /// A sync config has kept failing for longer than the threshold
+    SyncFailing,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::NoScrobbles => "no_scrobbles",
+            AlertKind::SyncFailing => "sync_failing",
+        }
+    }
+}
+
+impl FromStr for AlertKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "no_scrobbles" => Ok(AlertKind::NoScrobbles),
+            "sync_failing" => Ok(AlertKind::SyncFailing),
+            other => Err(anyhow::anyhow!("Unknown alert kind: {}", other)),
+        }
+    }
+}
+
+/// Alert that fires once when its condition has held for `threshold_hours`,
+/// and again only after the condition cleared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: Option<i64>,
+    pub kind: AlertKind,
+    pub threshold_hours: i64,
+    pub enabled: bool,
+    /// Whether the condition currently holds and was already notified
+    pub active: bool,
+    pub last_triggered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    pub fn new(kind: AlertKind, threshold_hours: i64) -> Self {
+        Self {
+            id: None,
+            kind,
+            threshold_hours,
+            enabled: true,
+            active: false,
+            last_triggered_at: None,
+            created_at: Utc::now(),
+        }
+    }
+}
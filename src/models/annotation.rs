@@ -0,0 +1,70 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What an annotation marks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    Concert,
+    Release,
+    /// A stretch of time, like "first year in Berlin"
+    Era,
+    #[default]
+    Other,
+}
+
+impl AnnotationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationKind::Concert => "concert",
+            AnnotationKind::Release => "release",
+            AnnotationKind::Era => "era",
+            AnnotationKind::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "concert" => AnnotationKind::Concert,
+            "release" => AnnotationKind::Release,
+            "era" => AnnotationKind::Era,
+            _ => AnnotationKind::Other,
+        }
+    }
+}
+
+/// Marker on the listening timeline, on a day or over `date..=end_date`.
+/// Without an artist it applies to the whole history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: Option<i64>,
+    pub artist: Option<String>,
+    pub date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub kind: AnnotationKind,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Annotation {
+    pub fn new(
+        artist: Option<String>,
+        date: NaiveDate,
+        end_date: Option<NaiveDate>,
+        kind: AnnotationKind,
+        label: String,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: None,
+            artist,
+            date,
+            end_date,
+            kind,
+            label,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
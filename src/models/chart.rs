@@ -0,0 +1,33 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// What a weekly chart ranks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    Artist,
+    Track,
+}
+
+impl ChartKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartKind::Artist => "artist",
+            ChartKind::Track => "track",
+        }
+    }
+}
+
+/// One place on a recorded weekly chart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartEntry {
+    /// Monday the week starts on, in the instance's timezone
+    pub week_start: NaiveDate,
+    /// Ties share a rank
+    pub rank: i64,
+    pub artist: String,
+    /// `None` on the artist chart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+    pub plays: i64,
+}
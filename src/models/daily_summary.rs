@@ -0,0 +1,25 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// One local day of listening, as the nightly rollup stores it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub scrobbles: i64,
+    pub top_artist: Option<String>,
+    pub top_artist_count: i64,
+    /// Artists scrobbled for the very first time on this day
+    pub new_artists: Vec<String>,
+    /// Listening sessions among the day's scrobbles
+    pub sessions: i64,
+}
+
+impl DailySummary {
+    /// A day without scrobbles
+    pub fn empty(date: NaiveDate) -> Self {
+        Self {
+            date,
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::Scrobble;
+
+/// Artist and/or track that importers drop instead of storing, optionally
+/// limited to one source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoreRule {
+    pub id: Option<i64>,
+    pub source: Option<String>,
+    pub artist: Option<String>,
+    pub track: Option<String>,
+    pub suppressed_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IgnoreRule {
+    pub fn new(source: Option<String>, artist: Option<String>, track: Option<String>) -> Self {
+        Self {
+            id: None,
+            source,
+            artist,
+            track,
+            suppressed_count: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Every field the rule sets must match, names compared case-insensitively
+    pub fn matches(&self, scrobble: &Scrobble) -> bool {
+        let same = |pattern: &Option<String>, value: &str| {
+            pattern
+                .as_deref()
+                .is_none_or(|p| p.trim().to_lowercase() == value.trim().to_lowercase())
+        };
+
+        (self.artist.is_some() || self.track.is_some())
+            && self.source.as_deref().is_none_or(|s| s == scrobble.source)
+            && same(&self.artist, &scrobble.artist)
+            && same(&self.track, &scrobble.track)
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    /// In progress, or interrupted by a crash or restart
+    #[default]
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ImportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportStatus::Running => "running",
+            ImportStatus::Completed => "completed",
+            ImportStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for ImportStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "running" => Ok(ImportStatus::Running),
+            "completed" => Ok(ImportStatus::Completed),
+            "failed" => Ok(ImportStatus::Failed),
+            other => Err(anyhow::anyhow!("Unknown import status: {}", other)),
+        }
+    }
+}
+
+/// A full historical import, with enough saved to pick it up where it stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportJob {
+    pub id: Option<i64>,
+    pub source: String, // "lastfm" or "listenbrainz"
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing)]
+    pub token: Option<String>,
+    /// Also fetch loved tracks and artist tags (Last.fm only)
+    pub enrich: bool,
+    pub status: ImportStatus,
+    /// Next page to fetch for Last.fm, `max_ts` for ListenBrainz
+    pub checkpoint: Option<i64>,
+    /// Scrobbles stored up to the checkpoint
+    pub imported_count: i64,
+    pub last_error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ImportJob {
+    pub fn new(source: String, username: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: None,
+            source,
+            username,
+            api_key: None,
+            token: None,
+            enrich: false,
+            status: ImportStatus::Running,
+            checkpoint: None,
+            imported_count: 0,
+            last_error: None,
+            started_at: now,
+            updated_at: now,
+        }
+    }
+}
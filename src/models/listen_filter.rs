@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use super::FieldError;
+
+/// When a play reported with its duration counts as a listen. The defaults
+/// are Last.fm's rules: tracks over 30 seconds, played for half their length
+/// or 4 minutes, whichever comes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListenFilter {
+    /// Shorter tracks (jingles, interludes) are never scrobbled
+    pub min_track_seconds: i64,
+    /// Playing this long always counts, however long the track is
+    pub min_play_seconds: i64,
+    /// Playing this share of the track counts, 0-100
+    pub min_play_percent: i64,
+}
+
+impl Default for ListenFilter {
+    fn default() -> Self {
+        Self {
+            min_track_seconds: 30,
+            min_play_seconds: 240,
+            min_play_percent: 50,
+        }
+    }
+}
+
+impl ListenFilter {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.min_track_seconds < 0 {
+            errors.push(FieldError::new("min_track_seconds", "can't be negative"));
+        }
+        if self.min_play_seconds < 0 {
+            errors.push(FieldError::new("min_play_seconds", "can't be negative"));
+        }
+        if !(0..=100).contains(&self.min_play_percent) {
+            errors.push(FieldError::new(
+                "min_play_percent",
+                "must be between 0 and 100",
+            ));
+        }
+        errors
+    }
+
+    /// Whether a play becomes a scrobble. Plays without playback details are
+    /// taken as listens; without the track length only the minimum track
+    /// length is checked against the time played
+    pub fn accepts(&self, ms_played: Option<i64>, duration_ms: Option<i64>) -> bool {
+        let Some(played) = ms_played else {
+            return true;
+        };
+
+        match duration_ms {
+            Some(duration) => {
+                duration > self.min_track_seconds * 1000
+                    && (played >= self.min_play_seconds * 1000
+                        || played * 100 >= duration * self.min_play_percent)
+            }
+            None => played > self.min_track_seconds * 1000,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{MediaType, Scrobble};
+
+/// Manual classification of an artist and/or album, taking precedence over
+/// the media type heuristics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaTypeRule {
+    pub id: Option<i64>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub media_type: MediaType,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaTypeRule {
+    pub fn new(artist: Option<String>, album: Option<String>, media_type: MediaType) -> Self {
+        Self {
+            id: None,
+            artist,
+            album,
+            media_type,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Every field the rule sets must match, names compared case-insensitively
+    pub fn matches(&self, scrobble: &Scrobble) -> bool {
+        let same = |pattern: &Option<String>, value: Option<&str>| {
+            pattern.as_deref().is_none_or(|p| {
+                value.is_some_and(|v| v.trim().to_lowercase() == p.trim().to_lowercase())
+            })
+        };
+
+        (self.artist.is_some() || self.album.is_some())
+            && same(&self.artist, Some(&scrobble.artist))
+            && same(&self.album, scrobble.album.as_deref())
+    }
+}
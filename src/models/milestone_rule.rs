@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::reports::yearly::MilestoneIcon;
+
+/// Figure of a year in review a milestone rule sets a threshold on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneMetric {
+    TotalHours,
+    TotalScrobbles,
+    /// Plays of the year's most played artist
+    TopArtistPlays,
+    NewArtists,
+    NewTracks,
+    /// Percentage of plays between 8 PM and 6 AM
+    NightOwlScore,
+    /// Percentage of plays between 6 AM and noon
+    EarlyBirdScore,
+    LongestSessionMinutes,
+    /// Most consecutive days with a scrobble
+    LongestStreakDays,
+    /// Months with a scrobble on every one of their days
+    FullMonths,
+}
+
+impl MilestoneMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MilestoneMetric::TotalHours => "total_hours",
+            MilestoneMetric::TotalScrobbles => "total_scrobbles",
+            MilestoneMetric::TopArtistPlays => "top_artist_plays",
+            MilestoneMetric::NewArtists => "new_artists",
+            MilestoneMetric::NewTracks => "new_tracks",
+            MilestoneMetric::NightOwlScore => "night_owl_score",
+            MilestoneMetric::EarlyBirdScore => "early_bird_score",
+            MilestoneMetric::LongestSessionMinutes => "longest_session_minutes",
+            MilestoneMetric::LongestStreakDays => "longest_streak_days",
+            MilestoneMetric::FullMonths => "full_months",
+        }
+    }
+}
+
+impl FromStr for MilestoneMetric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "total_hours" => Ok(MilestoneMetric::TotalHours),
+            "total_scrobbles" => Ok(MilestoneMetric::TotalScrobbles),
+            "top_artist_plays" => Ok(MilestoneMetric::TopArtistPlays),
+            "new_artists" => Ok(MilestoneMetric::NewArtists),
+            "new_tracks" => Ok(MilestoneMetric::NewTracks),
+            "night_owl_score" => Ok(MilestoneMetric::NightOwlScore),
+            "early_bird_score" => Ok(MilestoneMetric::EarlyBirdScore),
+            "longest_session_minutes" => Ok(MilestoneMetric::LongestSessionMinutes),
+            "longest_streak_days" => Ok(MilestoneMetric::LongestStreakDays),
+            "full_months" => Ok(MilestoneMetric::FullMonths),
+            other => Err(anyhow::anyhow!("Unknown milestone metric: {}", other)),
+        }
+    }
+}
+
+/// User-defined badge, awarded in a year's review when `metric` reaches
+/// `threshold`. `{value}` and `{artist}` in the title and description stand
+/// for the figure and the year's top artist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MilestoneRule {
+    pub id: Option<i64>,
+    pub title: String,
+    pub description: String,
+    pub metric: MilestoneMetric,
+    pub threshold: f64,
+    pub icon: MilestoneIcon,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MilestoneRule {
+    pub fn new(
+        title: String,
+        description: String,
+        metric: MilestoneMetric,
+        threshold: f64,
+        icon: MilestoneIcon,
+    ) -> Self {
+        Self {
+            id: None,
+            title,
+            description,
+            metric,
+            threshold,
+            icon,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+}
@@ -1,5 +1,37 @@
+pub mod achievement;
+pub mod alert_rule;
+pub mod annotation;
+pub mod chart;
+pub mod daily_summary;
+pub mod ignore_rule;
+pub mod import_job;
+pub mod listen_filter;
+pub mod media_type_rule;
+pub mod milestone_rule;
+pub mod note;
+pub mod now_playing;
+pub mod provenance;
+pub mod rating;
 pub mod scrobble;
+pub mod share_token;
+pub mod sleep_detection;
 pub mod sync_config;
 
-pub use scrobble::Scrobble;
-pub use sync_config::SyncConfig;
+pub use achievement::Achievement;
+pub use alert_rule::{AlertKind, AlertRule};
+pub use annotation::{Annotation, AnnotationKind};
+pub use chart::{ChartEntry, ChartKind};
+pub use daily_summary::DailySummary;
+pub use ignore_rule::IgnoreRule;
+pub use import_job::{ImportJob, ImportStatus};
+pub use listen_filter::ListenFilter;
+pub use media_type_rule::MediaTypeRule;
+pub use milestone_rule::{MilestoneMetric, MilestoneRule};
+pub use note::Note;
+pub use now_playing::NowPlaying;
+pub use provenance::Provenance;
+pub use rating::{Rating, RatingKind};
+pub use scrobble::{MediaType, RawMetadata, Scrobble};
+pub use share_token::{SHARE_SCOPES, ShareToken};
+pub use sleep_detection::{DetectionStatus, SleepDetection};
+pub use sync_config::{FieldError, SyncConfig};
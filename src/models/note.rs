@@ -0,0 +1,35 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Freeform journal note attached to either a single scrobble or a whole day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub id: Option<i64>,
+    pub scrobble_id: Option<i64>,
+    pub date: Option<NaiveDate>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Note {
+    pub fn for_scrobble(scrobble_id: i64, body: String) -> Self {
+        Self::new(Some(scrobble_id), None, body)
+    }
+
+    pub fn for_day(date: NaiveDate, body: String) -> Self {
+        Self::new(None, Some(date), body)
+    }
+
+    fn new(scrobble_id: Option<i64>, date: Option<NaiveDate>, body: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: None,
+            scrobble_id,
+            date,
+            body,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Track currently reported as playing by a scrobbling service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NowPlaying {
+    pub artist: String,
+    pub album: Option<String>,
+    pub track: String,
+}
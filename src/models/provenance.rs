@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the plays counted for an all-time top list entry come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provenance {
+    /// Only stored scrobbles
+    Scrobbles,
+    /// Only imported Last.fm weekly charts from before the first scrobble
+    Legacy,
+    /// Both
+    Mixed,
+}
+
+impl Provenance {
+    /// Provenance of `count` plays, `legacy_count` of them from legacy charts
+    pub fn of(count: i64, legacy_count: i64) -> Self {
+        if legacy_count <= 0 {
+            Provenance::Scrobbles
+        } else if legacy_count >= count {
+            Provenance::Legacy
+        } else {
+            Provenance::Mixed
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What a rating applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RatingKind {
+    Track,
+    Album,
+}
+
+impl RatingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RatingKind::Track => "track",
+            RatingKind::Album => "album",
+        }
+    }
+}
+
+/// 1–5 star rating of a track or album, with its all-time play count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub kind: RatingKind,
+    pub artist: String,
+    pub name: String,
+    pub rating: u8,
+    pub plays: i64,
+    pub updated_at: DateTime<Utc>,
+}
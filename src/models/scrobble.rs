@@ -1,5 +1,39 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What kind of audio a scrobble is; music reports only count music
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    #[default]
+    Music,
+    Podcast,
+    Audiobook,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Music => "music",
+            MediaType::Podcast => "podcast",
+            MediaType::Audiobook => "audiobook",
+        }
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "music" => Ok(MediaType::Music),
+            "podcast" => Ok(MediaType::Podcast),
+            "audiobook" => Ok(MediaType::Audiobook),
+            other => Err(anyhow::anyhow!("Unknown media type: {}", other)),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scrobble {
@@ -10,6 +44,27 @@ pub struct Scrobble {
     pub timestamp: DateTime<Utc>,
     pub source: String,            // "lastfm" or "listenbrainz"
     pub source_id: Option<String>, // Unique ID from source API to prevent duplicates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ms_played: Option<i64>, // How long the track actually played, when the source knows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<bool>, // Whether the listener skipped before the end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_metadata: Option<RawMetadata>, // Names as received, when normalization changed them
+    #[serde(default)]
+    pub media_type: MediaType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_metadata: Option<serde_json::Value>, // Extra details the source sent, e.g. ListenBrainz additional_info
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_artist: Option<String>, // Artist the album is credited to when it isn't the track's, e.g. "Various Artists"
+}
+
+/// Artist, album and track exactly as the source sent them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawMetadata {
+    pub artist: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    pub track: String,
 }
 
 impl Scrobble {
@@ -22,6 +77,12 @@ impl Scrobble {
             timestamp,
             source,
             source_id: None,
+            ms_played: None,
+            skipped: None,
+            raw_metadata: None,
+            media_type: MediaType::Music,
+            source_metadata: None,
+            album_artist: None,
         }
     }
 
@@ -34,4 +95,20 @@ impl Scrobble {
         self.source_id = Some(source_id);
         self
     }
+
+    pub fn with_source_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.source_metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_album_artist(mut self, album_artist: String) -> Self {
+        self.album_artist = Some(album_artist);
+        self
+    }
+
+    pub fn with_playback(mut self, ms_played: i64, skipped: bool) -> Self {
+        self.ms_played = Some(ms_played);
+        self.skipped = Some(skipped);
+        self
+    }
 }
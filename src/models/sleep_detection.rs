@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Whether the listener agreed that a run was played while asleep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionStatus {
+    #[default]
+    Pending,
+    /// The run's scrobbles are excluded from stats
+    Confirmed,
+    Dismissed,
+}
+
+impl DetectionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectionStatus::Pending => "pending",
+            DetectionStatus::Confirmed => "confirmed",
+            DetectionStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl FromStr for DetectionStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "pending" => Ok(DetectionStatus::Pending),
+            "confirmed" => Ok(DetectionStatus::Confirmed),
+            "dismissed" => Ok(DetectionStatus::Dismissed),
+            other => Err(anyhow::anyhow!("Unknown detection status: {}", other)),
+        }
+    }
+}
+
+/// Overnight run of scrobbles that looks like autoplay while asleep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SleepDetection {
+    pub id: Option<i64>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub scrobble_count: i64,
+    pub distinct_artists: i64,
+    pub status: DetectionStatus,
+}
@@ -1,6 +1,28 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub const SYNC_SOURCES: [&str; 2] = ["lastfm", "listenbrainz"];
+
+/// Syncs can't run more often than the scheduler checks for due configs
+pub const MIN_SYNC_INTERVAL_MINUTES: i32 = 1;
+pub const MAX_SYNC_INTERVAL_MINUTES: i32 = 7 * 24 * 60;
+
+/// A problem with one field of a submitted configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncConfig {
     pub id: Option<i64>,
@@ -46,4 +68,35 @@ impl SyncConfig {
         self.enabled = enabled;
         self
     }
+
+    /// Problems that can be spotted without contacting the source
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if !SYNC_SOURCES.contains(&self.source.as_str()) {
+            errors.push(FieldError::new(
+                "source",
+                format!("must be one of {}", SYNC_SOURCES.join(", ")),
+            ));
+        }
+        if self.username.trim().is_empty() {
+            errors.push(FieldError::new("username", "is required"));
+        }
+        if self.source == "lastfm" && self.api_key.as_deref().is_none_or(str::is_empty) {
+            errors.push(FieldError::new("api_key", "is required for Last.fm"));
+        }
+        if !(MIN_SYNC_INTERVAL_MINUTES..=MAX_SYNC_INTERVAL_MINUTES)
+            .contains(&self.sync_interval_minutes)
+        {
+            errors.push(FieldError::new(
+                "sync_interval_minutes",
+                format!(
+                    "must be between {} and {}",
+                    MIN_SYNC_INTERVAL_MINUTES, MAX_SYNC_INTERVAL_MINUTES
+                ),
+            ));
+        }
+
+        errors
+    }
 }
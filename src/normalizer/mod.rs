@@ -0,0 +1,270 @@
+use anyhow::Result;
+use std::str::FromStr;
+
+use crate::models::{RawMetadata, Scrobble};
+
+/// One cleanup step applied to incoming track metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Collapse runs of whitespace and trim
+    Whitespace,
+    /// Drop "(Remastered 2011)", "[2011 Remaster]", "- Remastered" suffixes
+    Remaster,
+    /// Drop "(Live)", "- Live at Wembley" suffixes
+    Live,
+    /// Spell "ft.", "feat", "featuring" as "feat."
+    Feat,
+}
+
+impl Rule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rule::Whitespace => "whitespace",
+            Rule::Remaster => "remaster",
+            Rule::Live => "live",
+            Rule::Feat => "feat",
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "whitespace" => Ok(Rule::Whitespace),
+            "remaster" => Ok(Rule::Remaster),
+            "live" => Ok(Rule::Live),
+            "feat" => Ok(Rule::Feat),
+            other => Err(anyhow::anyhow!("Unknown normalization rule: {}", other)),
+        }
+    }
+}
+
+/// Cleans up artist, album and track names on ingest, keeping the original
+/// values on the scrobble when anything changed
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    rules: Vec<Rule>,
+}
+
+impl Default for Normalizer {
+    /// Names are stored as received: the rules only apply to scrobbles
+    /// stored after they're turned on, so enabling them is left to the user
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Normalizer {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Rules from NORMALIZE_RULES, a comma-separated list such as
+    /// "whitespace,remaster,live,feat", or "none" to store names untouched
+    pub fn from_env() -> Result<Self> {
+        let Ok(value) = std::env::var("NORMALIZE_RULES") else {
+            return Ok(Self::default());
+        };
+
+        if value.trim().eq_ignore_ascii_case("none") {
+            return Ok(Self::new(Vec::new()));
+        }
+
+        let rules = value
+            .split(',')
+            .filter(|r| !r.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Rule>>>()?;
+        Ok(Self::new(rules))
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn normalize(&self, mut scrobble: Scrobble) -> Scrobble {
+        let artist = self.apply(&scrobble.artist, &[Rule::Whitespace, Rule::Feat]);
+        let track = self.apply(
+            &scrobble.track,
+            &[Rule::Whitespace, Rule::Remaster, Rule::Live, Rule::Feat],
+        );
+        let album = scrobble
+            .album
+            .as_deref()
+            .map(|a| self.apply(a, &[Rule::Whitespace, Rule::Remaster, Rule::Live]));
+
+        let changed =
+            artist != scrobble.artist || track != scrobble.track || album != scrobble.album;
+
+        // An empty result means the rules ate the whole name; keep the original
+        if changed && !artist.is_empty() && !track.is_empty() {
+            let raw = RawMetadata {
+                artist: std::mem::replace(&mut scrobble.artist, artist),
+                album: std::mem::replace(&mut scrobble.album, album.filter(|a| !a.is_empty())),
+                track: std::mem::replace(&mut scrobble.track, track),
+            };
+            scrobble.raw_metadata = Some(raw);
+        }
+
+        scrobble
+    }
+
+    /// Apply the enabled rules among `applicable`, in a fixed order
+    fn apply(&self, value: &str, applicable: &[Rule]) -> String {
+        let enabled = |rule: Rule| applicable.contains(&rule) && self.rules.contains(&rule);
+        let mut value = value.to_string();
+
+        if enabled(Rule::Whitespace) {
+            value = collapse_whitespace(&value);
+        }
+        if enabled(Rule::Remaster) {
+            value = strip_suffixes(&value, |s| s.contains("remaster"));
+        }
+        if enabled(Rule::Live) {
+            value = strip_suffixes(&value, |s| s == "live" || s.starts_with("live "));
+        }
+        if enabled(Rule::Feat) {
+            value = normalize_feat(&value);
+        }
+
+        value
+    }
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Repeatedly remove a trailing "(...)", "[...]" or " - ..." segment whose
+/// lowercased contents match `matches`
+fn strip_suffixes(value: &str, matches: impl Fn(&str) -> bool) -> String {
+    let mut value = value.trim_end().to_string();
+
+    loop {
+        let stripped = if let Some(close) = value.chars().last().filter(|c| *c == ')' || *c == ']')
+        {
+            let open = if close == ')' { '(' } else { '[' };
+            value.rfind(open).and_then(|start| {
+                let inner = value[start + 1..value.len() - 1].trim().to_lowercase();
+                matches(&inner).then(|| value[..start].trim_end().to_string())
+            })
+        } else {
+            value.rfind(" - ").and_then(|start| {
+                let suffix = value[start + 3..].trim().to_lowercase();
+                matches(&suffix).then(|| value[..start].trim_end().to_string())
+            })
+        };
+
+        match stripped {
+            Some(s) if !s.is_empty() => value = s,
+            _ => return value,
+        }
+    }
+}
+
+/// Spell featuring credits consistently as "feat."
+fn normalize_feat(value: &str) -> String {
+    value
+        .split(' ')
+        .map(|word| {
+            let prefix_len = word
+                .find(|c: char| c != '(' && c != '[')
+                .unwrap_or(word.len());
+            let (prefix, rest) = word.split_at(prefix_len);
+            match rest.to_lowercase().as_str() {
+                "ft" | "ft." | "feat" | "feat." | "featuring" => format!("{}feat.", prefix),
+                _ => word.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_scrobble_from_rfc3339;
+
+    fn all_rules() -> Normalizer {
+        Normalizer::new(vec![
+            Rule::Whitespace,
+            Rule::Remaster,
+            Rule::Live,
+            Rule::Feat,
+        ])
+    }
+
+    #[test]
+    fn test_strips_remaster_suffixes() {
+        let normalizer = all_rules();
+        assert_eq!(
+            normalizer.apply("Here Comes the Sun (Remastered 2009)", &[Rule::Remaster]),
+            "Here Comes the Sun"
+        );
+        assert_eq!(
+            normalizer.apply("Heroes - 2017 Remaster", &[Rule::Remaster]),
+            "Heroes"
+        );
+        assert_eq!(
+            normalizer.apply("Song [2011 Remaster] (Remastered)", &[Rule::Remaster]),
+            "Song"
+        );
+        // Parentheses that aren't about remastering stay
+        assert_eq!(
+            normalizer.apply("Song (Acoustic)", &[Rule::Remaster]),
+            "Song (Acoustic)"
+        );
+    }
+
+    #[test]
+    fn test_rules_are_opt_in() {
+        let track = "Creep - Live at Glastonbury";
+        assert_eq!(Normalizer::default().apply(track, &[Rule::Live]), track);
+        assert_eq!(
+            Normalizer::default().apply(" Song (Remastered) ", &[Rule::Whitespace, Rule::Remaster]),
+            " Song (Remastered) "
+        );
+        assert_eq!(all_rules().apply(track, &[Rule::Live]), "Creep");
+        assert_eq!(all_rules().apply("Alive", &[Rule::Live]), "Alive");
+        assert_eq!(all_rules().apply("Song (Live)", &[Rule::Live]), "Song");
+    }
+
+    #[test]
+    fn test_feat_and_whitespace() {
+        let normalizer = all_rules();
+        assert_eq!(
+            normalizer.apply("  Song   (ft. Someone) ", &[Rule::Whitespace, Rule::Feat]),
+            "Song (feat. Someone)"
+        );
+        assert_eq!(
+            normalizer.apply("Artist featuring Guest", &[Rule::Feat]),
+            "Artist feat. Guest"
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_raw_metadata() {
+        let scrobble = test_scrobble_from_rfc3339(
+            "The Beatles",
+            "Let It Be (Remastered 2009)",
+            "2024-01-01T12:00:00Z",
+        );
+
+        let normalized = all_rules().normalize(scrobble);
+        assert_eq!(normalized.track, "Let It Be");
+        let raw = normalized.raw_metadata.unwrap();
+        assert_eq!(raw.track, "Let It Be (Remastered 2009)");
+        assert_eq!(raw.artist, "The Beatles");
+
+        let clean = test_scrobble_from_rfc3339("Artist", "Track", "2024-01-01T12:00:00Z");
+        assert!(all_rules().normalize(clean).raw_metadata.is_none());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!("Live".parse::<Rule>().unwrap(), Rule::Live);
+        assert!("uppercase".parse::<Rule>().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::db::DbPool;
+use crate::models::NowPlaying;
+
+/// Entries in each of the overview's top lists
+pub const OVERVIEW_TOP_LIST_SIZE: i64 = 5;
+
+// Days of history read for the streak before widening to the whole history
+const STREAK_WINDOW_DAYS: i64 = 366;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Totals {
+    pub scrobbles: i64,
+    pub artists: i64,
+    pub albums: i64,
+    pub tracks: i64,
+}
+
+/// The last 7 days against the 7 before
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekDigest {
+    pub scrobbles: i64,
+    pub previous_scrobbles: i64,
+    /// None when there was nothing to compare with
+    pub change_percent: Option<f64>,
+    pub new_artists: i64,
+}
+
+/// Consecutive local days with listening, up to today. A streak without a
+/// listen yet today is still running until the day is over
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Streak {
+    pub days: i64,
+    pub since: Option<NaiveDate>,
+    pub active_today: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverviewArtist {
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverviewTrack {
+    pub artist: String,
+    pub track: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverviewAlbum {
+    pub artist: String,
+    pub album: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NowPlayingEntry {
+    pub source: String,
+    pub username: String,
+    pub track: NowPlaying,
+}
+
+/// Everything the homepage shows on load
+#[derive(Debug, Clone, Serialize)]
+pub struct Overview {
+    pub generated_at: DateTime<Utc>,
+    pub timezone: String,
+    pub totals: Totals,
+    pub week: WeekDigest,
+    pub streak: Streak,
+    pub now_playing: Vec<NowPlayingEntry>,
+    pub top_artists: Vec<OverviewArtist>,
+    pub top_tracks: Vec<OverviewTrack>,
+    pub top_albums: Vec<OverviewAlbum>,
+}
+
+/// Build the overview from one database snapshot, with top lists over
+/// `start`..`end` (the whole history when unbounded). Now playing comes from
+/// the live hub, so it's left empty here
+pub fn build(
+    pool: &DbPool,
+    now: DateTime<Utc>,
+    timezone: Tz,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Overview> {
+    let week_start = now - Duration::days(7);
+    let previous_start = week_start - Duration::days(7);
+
+    crate::db::with_read_txn(pool, |conn| {
+        let (artists, albums, tracks) = crate::db::query_library_counts(conn)?;
+        let totals = Totals {
+            scrobbles: crate::db::query_scrobbles_count_in_range(conn, None, None)?,
+            artists,
+            albums,
+            tracks,
+        };
+
+        let scrobbles =
+            crate::db::query_scrobbles_count_in_range(conn, Some(week_start), Some(now))?;
+        let previous_scrobbles = crate::db::query_scrobbles_count_in_range(
+            conn,
+            Some(previous_start),
+            Some(week_start - Duration::seconds(1)),
+        )?;
+        let week = WeekDigest {
+            scrobbles,
+            previous_scrobbles,
+            change_percent: (previous_scrobbles > 0).then(|| {
+                (scrobbles - previous_scrobbles) as f64 / previous_scrobbles as f64 * 100.0
+            }),
+            new_artists: crate::db::query_new_artists_count(conn, week_start)?,
+        };
+
+        let today = now.with_timezone(&timezone).date_naive();
+        let window_start = now - Duration::days(STREAK_WINDOW_DAYS);
+        let mut streak = current_streak(
+            &crate::db::query_active_days(conn, Some(window_start), timezone)?,
+            today,
+        );
+        // A streak reaching the window's edge may go further back
+        if streak.since.is_some_and(|since| {
+            since <= window_start.with_timezone(&timezone).date_naive() + Duration::days(1)
+        }) {
+            streak = current_streak(&crate::db::query_active_days(conn, None, timezone)?, today);
+        }
+
+        let size = OVERVIEW_TOP_LIST_SIZE;
+        Ok(Overview {
+            generated_at: now,
+            timezone: timezone.name().to_string(),
+            totals,
+            week,
+            streak,
+            now_playing: Vec::new(),
+            top_artists: crate::db::query_top_artists(conn, size, start, end)?
+                .into_iter()
+                .map(|(name, count)| OverviewArtist { name, count })
+                .collect(),
+            top_tracks: crate::db::query_top_tracks(conn, size, start, end)?
+                .into_iter()
+                .map(|(artist, track, count)| OverviewTrack {
+                    artist,
+                    track,
+                    count,
+                })
+                .collect(),
+            top_albums: crate::db::query_top_albums(conn, size, start, end)?
+                .into_iter()
+                .map(|(artist, album, count)| OverviewAlbum {
+                    artist,
+                    album,
+                    count,
+                })
+                .collect(),
+        })
+    })
+}
+
+/// The run of active days ending today, or yesterday if nothing was played
+/// yet today
+pub fn current_streak(active_days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Streak {
+    let active_today = active_days.contains(&today);
+    let mut day = if active_today {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+
+    let mut streak = Streak {
+        active_today,
+        ..Streak::default()
+    };
+    while active_days.contains(&day) {
+        streak.days += 1;
+        streak.since = Some(day);
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn listen(pool: &DbPool, artist: &str, timestamp: DateTime<Utc>) {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            timestamp,
+            "lastfm".to_string(),
+        );
+        crate::db::insert_scrobble(pool, &scrobble).unwrap();
+    }
+
+    #[test]
+    fn test_streak_survives_until_the_day_is_over() {
+        let today: NaiveDate = "2024-06-10".parse().unwrap();
+        let days: BTreeSet<NaiveDate> = ["2024-06-05", "2024-06-07", "2024-06-08", "2024-06-09"]
+            .iter()
+            .map(|d| d.parse().unwrap())
+            .collect();
+
+        let streak = current_streak(&days, today);
+        assert_eq!(streak.days, 3);
+        assert_eq!(streak.since, Some("2024-06-07".parse().unwrap()));
+        assert!(!streak.active_today);
+
+        assert_eq!(
+            current_streak(&days, "2024-06-11".parse().unwrap()),
+            Streak::default()
+        );
+    }
+
+    #[test]
+    fn test_overview_counts_week_and_local_days() {
+        let (pool, _temp_file) = setup_pool();
+        let now: DateTime<Utc> = "2024-06-10T12:00:00Z".parse().unwrap();
+
+        listen(&pool, "Low", now - Duration::days(30));
+        listen(&pool, "Low", now - Duration::days(10));
+        listen(&pool, "Low", now - Duration::hours(2));
+        listen(&pool, "Slint", now - Duration::hours(1));
+        // 23:30 in Paris the day before, so the streak spans two local days
+        listen(&pool, "Low", "2024-06-09T21:30:00Z".parse().unwrap());
+
+        let overview = build(&pool, now, chrono_tz::Europe::Paris, None, None).unwrap();
+        assert_eq!(overview.totals.scrobbles, 5);
+        assert_eq!(overview.totals.artists, 2);
+        assert_eq!(overview.week.scrobbles, 3);
+        assert_eq!(overview.week.previous_scrobbles, 1);
+        assert_eq!(overview.week.change_percent, Some(200.0));
+        assert_eq!(overview.week.new_artists, 1);
+        assert_eq!(overview.streak.days, 2);
+        assert!(overview.streak.active_today);
+        assert_eq!(overview.top_artists[0].name, "Low");
+        assert_eq!(overview.top_artists[0].count, 4);
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Deserialize;
+
+/// File formats a playlist can be exported to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    #[default]
+    M3u,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "audio/x-mpegurl",
+            PlaylistFormat::Xspf => "application/xspf+xml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u => "m3u",
+            PlaylistFormat::Xspf => "xspf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+}
+
+/// Tracks named by their tags. The history has no file paths, so players
+/// (Navidrome, foobar2000, VLC with a tag matcher...) resolve entries against
+/// their own library
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playlist {
+    pub title: String,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+impl Playlist {
+    pub fn render(&self, format: PlaylistFormat) -> String {
+        match format {
+            PlaylistFormat::M3u => self.to_m3u(),
+            PlaylistFormat::Xspf => self.to_xspf(),
+        }
+    }
+
+    /// Extended M3U, each entry named "Artist - Track"
+    pub fn to_m3u(&self) -> String {
+        let mut m3u = format!("#EXTM3U\n#PLAYLIST:{}\n", one_line(&self.title));
+        for entry in &self.entries {
+            let title = format!("{} - {}", one_line(&entry.artist), one_line(&entry.track));
+            if let Some(album) = &entry.album {
+                m3u.push_str(&format!("#EXTALB:{}\n", one_line(album)));
+            }
+            m3u.push_str(&format!("#EXTINF:-1,{}\n{}\n", title, title));
+        }
+        m3u
+    }
+
+    /// XSPF, which carries artist, title and album as separate fields
+    pub fn to_xspf(&self) -> String {
+        let mut xspf = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+        );
+        xspf.push_str(&format!("  <title>{}</title>\n", escape_xml(&self.title)));
+        xspf.push_str("  <trackList>\n");
+        for entry in &self.entries {
+            xspf.push_str("    <track>\n");
+            xspf.push_str(&format!(
+                "      <creator>{}</creator>\n",
+                escape_xml(&entry.artist)
+            ));
+            xspf.push_str(&format!(
+                "      <title>{}</title>\n",
+                escape_xml(&entry.track)
+            ));
+            if let Some(album) = &entry.album {
+                xspf.push_str(&format!("      <album>{}</album>\n", escape_xml(album)));
+            }
+            xspf.push_str("    </track>\n");
+        }
+        xspf.push_str("  </trackList>\n</playlist>\n");
+        xspf
+    }
+}
+
+// A line break in a tag would start a new playlist entry
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist() -> Playlist {
+        Playlist {
+            title: "Top tracks".to_string(),
+            entries: vec![
+                PlaylistEntry {
+                    artist: "Simon & Garfunkel".to_string(),
+                    track: "The Boxer".to_string(),
+                    album: Some("Bridge over Troubled Water".to_string()),
+                },
+                PlaylistEntry {
+                    artist: "Low".to_string(),
+                    track: "Lullaby\n".to_string(),
+                    album: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_m3u_names_entries_by_artist_and_track() {
+        let m3u = playlist().render(PlaylistFormat::M3u);
+
+        assert!(m3u.starts_with("#EXTM3U\n#PLAYLIST:Top tracks\n"));
+        assert!(m3u.contains(
+            "#EXTALB:Bridge over Troubled Water\n\
+             #EXTINF:-1,Simon & Garfunkel - The Boxer\n\
+             Simon & Garfunkel - The Boxer\n"
+        ));
+        assert!(m3u.ends_with("#EXTINF:-1,Low - Lullaby \nLow - Lullaby \n"));
+    }
+
+    #[test]
+    fn test_xspf_escapes_tags() {
+        let xspf = playlist().render(PlaylistFormat::Xspf);
+
+        assert!(xspf.contains("<creator>Simon &amp; Garfunkel</creator>"));
+        assert!(xspf.contains("<album>Bridge over Troubled Water</album>"));
+        assert_eq!(xspf.matches("<track>").count(), 2);
+        assert_eq!(xspf.matches("<album>").count(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::db::DbPool;
+use crate::playlists::{Playlist, PlaylistEntry};
+
+/// Plays a track needs to count as a past favorite
+pub const DEFAULT_MIN_PLAYS: i64 = 10;
+
+/// Days without a play before a favorite counts as neglected
+pub const DEFAULT_IDLE_DAYS: i64 = 180;
+
+/// A favorite that dropped out of rotation
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisitTrack {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub plays: i64,
+    pub last_played: DateTime<Utc>,
+    pub days_since: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisitPlaylist {
+    pub generated_at: DateTime<Utc>,
+    pub min_plays: i64,
+    pub idle_days: i64,
+    pub tracks: Vec<RevisitTrack>,
+}
+
+/// Up to `limit` tracks played at least `min_plays` times but not in the
+/// last `idle_days`, most played first
+pub fn revisit(
+    pool: &DbPool,
+    now: DateTime<Utc>,
+    min_plays: i64,
+    idle_days: i64,
+    limit: usize,
+) -> Result<RevisitPlaylist> {
+    let tracks = crate::db::get_neglected_tracks(
+        pool,
+        min_plays,
+        now - Duration::days(idle_days),
+        limit as i64,
+    )?
+    .into_iter()
+    .map(|t| RevisitTrack {
+        days_since: (now - t.last_played).num_days(),
+        artist: t.artist,
+        track: t.track,
+        album: t.album,
+        plays: t.plays,
+        last_played: t.last_played,
+    })
+    .collect();
+
+    Ok(RevisitPlaylist {
+        generated_at: now,
+        min_plays,
+        idle_days,
+        tracks,
+    })
+}
+
+impl RevisitPlaylist {
+    pub fn to_playlist(&self) -> Playlist {
+        Playlist {
+            title: "Neglected favorites".to_string(),
+            entries: self
+                .tracks
+                .iter()
+                .map(|track| PlaylistEntry {
+                    artist: track.artist.clone(),
+                    track: track.track.clone(),
+                    album: track.album.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn play(pool: &DbPool, artist: &str, track: &str, times: i64, last: DateTime<Utc>) {
+        for i in 0..times {
+            let mut scrobble = Scrobble::new(
+                artist.to_string(),
+                track.to_string(),
+                last - Duration::days(i),
+                "lastfm".to_string(),
+            );
+            scrobble.album = Some("Album".to_string());
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_only_idle_favorites_are_suggested() {
+        let (pool, _temp_file) = setup_pool();
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let year_ago = now - Duration::days(365);
+
+        play(&pool, "Low", "Lullaby", 12, year_ago);
+        play(&pool, "Slint", "Breadcrumb Trail", 20, year_ago);
+        // Still in rotation
+        play(&pool, "Codeine", "D", 30, now - Duration::days(3));
+        // Never a favorite
+        play(&pool, "Bedhead", "Bedside Table", 2, year_ago);
+
+        let playlist = revisit(&pool, now, 10, 180, 10).unwrap();
+
+        let tracks: Vec<_> = playlist.tracks.iter().map(|t| t.track.as_str()).collect();
+        assert_eq!(tracks, vec!["Breadcrumb Trail", "Lullaby"]);
+        assert_eq!(playlist.tracks[0].plays, 20);
+        assert_eq!(playlist.tracks[0].days_since, 365);
+
+        let entries = playlist.to_playlist().entries;
+        assert_eq!(entries[0].artist, "Slint");
+        assert_eq!(entries[0].album.as_deref(), Some("Album"));
+    }
+}
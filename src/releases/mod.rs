@@ -0,0 +1,297 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::db::{DbPool, MusicBrainzId, Release};
+use crate::importers::http::HttpFetch;
+
+/// Top artists of the past year whose releases are followed
+pub const RADAR_ARTISTS: i64 = 50;
+
+/// Releases older than this when found aren't kept
+pub const RADAR_LOOKBACK_DAYS: i64 = 365;
+
+// An artist's releases are looked up again after this long
+const RECHECK_DAYS: i64 = 7;
+
+const RADAR_TICK_SECS: u64 = 3600;
+
+// MusicBrainz allows one request per second from each client
+const REQUEST_DELAY: std::time::Duration = std::time::Duration::from_millis(1100);
+
+// Search results under this score are likely another artist with a similar name
+const MIN_ARTIST_SCORE: i64 = 90;
+
+// Release groups fetched per artist, in pages of 100
+const MAX_RELEASE_GROUP_PAGES: usize = 5;
+
+#[derive(Debug, Deserialize)]
+struct ArtistSearch {
+    #[serde(default)]
+    artists: Vec<ArtistMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistMatch {
+    id: String,
+    #[serde(default)]
+    score: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseGroupPage {
+    #[serde(rename = "release-groups", default)]
+    release_groups: Vec<ReleaseGroup>,
+    #[serde(rename = "release-group-count", default)]
+    count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseGroup {
+    id: String,
+    title: String,
+    #[serde(rename = "primary-type")]
+    primary_type: Option<String>,
+    #[serde(rename = "first-release-date", default)]
+    first_release_date: String,
+}
+
+/// Follows the top artists' new releases on MusicBrainz
+#[derive(Clone)]
+pub struct ReleaseRadar {
+    pool: DbPool,
+    client: reqwest::Client,
+}
+
+impl ReleaseRadar {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn start(&self) {
+        let radar = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs(RADAR_TICK_SECS);
+            loop {
+                match scan(&radar.pool, &radar.client, Utc::now(), REQUEST_DELAY).await {
+                    Ok(0) => {}
+                    Ok(found) => tracing::info!("Release radar found {} new releases", found),
+                    Err(e) => tracing::error!("Release radar failed: {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+/// Look up the top artists not checked in the last `RECHECK_DAYS`, waiting
+/// `delay` after each request, and return the number of new releases stored
+pub async fn scan(
+    pool: &DbPool,
+    http: &dyn HttpFetch,
+    now: DateTime<Utc>,
+    delay: std::time::Duration,
+) -> Result<usize> {
+    let artists = crate::db::get_top_artists(
+        pool,
+        RADAR_ARTISTS,
+        Some(now - Duration::days(365)),
+        Some(now),
+    )?;
+
+    let mut found = 0;
+    for (artist, _) in artists {
+        let recent = crate::db::get_release_check(pool, Some(&artist))?
+            .is_some_and(|checked| now - checked < Duration::days(RECHECK_DAYS));
+        if recent {
+            continue;
+        }
+
+        // One artist failing (MusicBrainz hiccup) shouldn't stop the rest; it's
+        // retried on the next tick
+        match check_artist(pool, http, &artist, now, delay).await {
+            Ok(new) => {
+                found += new;
+                crate::db::mark_release_check(pool, &artist, now)?;
+            }
+            Err(e) => tracing::warn!("Release radar skipped {}: {}", artist, e),
+        }
+    }
+
+    Ok(found)
+}
+
+async fn check_artist(
+    pool: &DbPool,
+    http: &dyn HttpFetch,
+    artist: &str,
+    now: DateTime<Utc>,
+    delay: std::time::Duration,
+) -> Result<usize> {
+    let mbid = match crate::db::get_musicbrainz_id(pool, "artist", artist, None)? {
+        Some(mbid) => mbid,
+        None => {
+            let Some(mbid) = search_artist(http, artist).await? else {
+                return Ok(0);
+            };
+            tokio::time::sleep(delay).await;
+            crate::db::save_musicbrainz_ids(
+                pool,
+                &[MusicBrainzId {
+                    entity_type: "artist",
+                    artist: artist.to_string(),
+                    name: None,
+                    mbid: mbid.clone(),
+                }],
+            )?;
+            mbid
+        }
+    };
+
+    let cutoff = (now - Duration::days(RADAR_LOOKBACK_DAYS)).date_naive();
+    let mut releases = Vec::new();
+    for page in 0..MAX_RELEASE_GROUP_PAGES {
+        let groups = fetch_release_groups(http, &mbid, page * 100).await?;
+        tokio::time::sleep(delay).await;
+
+        // Only full dates: a year or month alone can't be placed in a range
+        releases.extend(groups.release_groups.into_iter().filter_map(|group| {
+            let date = NaiveDate::parse_from_str(&group.first_release_date, "%Y-%m-%d").ok()?;
+            (date >= cutoff).then(|| Release {
+                mbid: group.id,
+                artist: artist.to_string(),
+                title: group.title,
+                release_type: group.primary_type,
+                release_date: date,
+                discovered_at: now,
+            })
+        }));
+
+        if (page + 1) * 100 >= groups.count {
+            break;
+        }
+    }
+
+    crate::db::save_releases(pool, &releases)
+}
+
+async fn search_artist(http: &dyn HttpFetch, artist: &str) -> Result<Option<String>> {
+    let query = format!("artist:\"{}\"", artist.replace('"', ""));
+    let url = format!(
+        "https://musicbrainz.org/ws/2/artist/?query={}&limit=1&fmt=json",
+        urlencoding::encode(&query)
+    );
+    let search: ArtistSearch = get_json(http, &url).await?;
+    Ok(search
+        .artists
+        .into_iter()
+        .find(|found| found.score >= MIN_ARTIST_SCORE)
+        .map(|found| found.id))
+}
+
+async fn fetch_release_groups(
+    http: &dyn HttpFetch,
+    mbid: &str,
+    offset: usize,
+) -> Result<ReleaseGroupPage> {
+    let url = format!(
+        "https://musicbrainz.org/ws/2/release-group?artist={}&limit=100&offset={}&fmt=json",
+        urlencoding::encode(mbid),
+        offset
+    );
+    get_json(http, &url).await
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(http: &dyn HttpFetch, url: &str) -> Result<T> {
+    // MusicBrainz rejects requests without an identifying User-Agent
+    let headers = [(
+        "User-Agent",
+        format!(
+            "footprints/{} ( https://github.com/dbeley/footprints )",
+            env!("CARGO_PKG_VERSION")
+        ),
+    )];
+
+    let response = http
+        .get(url, &headers)
+        .await
+        .context("Failed to fetch from MusicBrainz")?;
+    if !response.status.is_success() {
+        return Err(anyhow::anyhow!(
+            "MusicBrainz API returned error: {}",
+            response.status
+        ));
+    }
+    response
+        .json()
+        .context("Failed to parse MusicBrainz response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::http::MockHttp;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    const SEARCH: &str = r#"{"artists": [{"id": "low-mbid", "name": "Low", "score": 100}]}"#;
+
+    const RELEASE_GROUPS: &str = r#"{
+        "release-group-count": 3,
+        "release-groups": [
+            {"id": "rg-new", "title": "Hey What", "primary-type": "Album", "first-release-date": "2024-04-10"},
+            {"id": "rg-old", "title": "Things We Lost in the Fire", "primary-type": "Album", "first-release-date": "2001-01-30"},
+            {"id": "rg-vague", "title": "Untitled", "primary-type": "Single", "first-release-date": "2024"}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_scan_keeps_recent_releases_and_skips_checked_artists() {
+        let (pool, _temp_file) = setup_pool();
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let scrobble = Scrobble::new(
+            "Low".to_string(),
+            "Days Like These".to_string(),
+            now - Duration::days(2),
+            "lastfm".to_string(),
+        );
+        crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+
+        let http = MockHttp::default()
+            .respond("/artist/", 200, SEARCH)
+            .respond("/release-group", 200, RELEASE_GROUPS);
+        let found = scan(&pool, &http, now, std::time::Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(found, 1);
+
+        let releases =
+            crate::db::get_releases_since(&pool, "2024-01-01".parse().unwrap(), 10).unwrap();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].title, "Hey What");
+        assert_eq!(releases[0].release_type.as_deref(), Some("Album"));
+        assert_eq!(
+            crate::db::get_musicbrainz_id(&pool, "artist", "Low", None).unwrap(),
+            Some("low-mbid".to_string())
+        );
+
+        // Checked an hour ago: no requests until the week is up
+        let http = MockHttp::default();
+        scan(
+            &pool,
+            &http,
+            now + Duration::hours(1),
+            std::time::Duration::ZERO,
+        )
+        .await
+        .unwrap();
+        assert!(http.requests().is_empty());
+        assert_eq!(
+            crate::db::get_release_check(&pool, None).unwrap(),
+            Some(now)
+        );
+    }
+}
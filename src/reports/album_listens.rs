@@ -0,0 +1,219 @@
+use crate::db::DbPool;
+use crate::models::Scrobble;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use crate::reports::sessions::for_each_session;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Fewest tracks a run needs to count as a full album listen, so singles and
+/// EPs don't count for a handful of plays
+pub const DEFAULT_MIN_TRACKS: usize = 5;
+
+/// Share of an album's known tracks a run must cover
+pub const DEFAULT_MIN_COVERAGE: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AlbumListensOptions {
+    pub gap_minutes: i64,
+    pub min_tracks: usize,
+    pub min_coverage: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlbumListenStats {
+    /// Album artist, or the track artist when there is none
+    pub artist: String,
+    pub album: String,
+    pub full_listens: i64,
+    /// Distinct tracks ever scrobbled from the album
+    pub known_tracks: i64,
+    pub last_full_listen: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlbumListensSummary {
+    pub full_listens: i64,
+    pub albums: usize,
+    /// Share of album scrobbles that were part of a full listen
+    pub full_listen_share: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlbumListensReport {
+    pub schema_version: u32,
+    pub albums: Vec<AlbumListenStats>,
+    pub summary: AlbumListensSummary,
+}
+
+impl VersionedReport for AlbumListensReport {}
+
+/// Albums played through: runs of consecutive scrobbles within a session from
+/// the same album, each track played once, covering at least
+/// `min_coverage` of the tracks known for the album and `min_tracks` tracks.
+/// Scrobbles don't carry track numbers, so playing the tracks back to back
+/// stands in for playing them in order
+pub fn generate_album_listens_report(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    options: &AlbumListensOptions,
+) -> Result<AlbumListensReport> {
+    let known_tracks = crate::db::get_album_track_counts(pool)?;
+
+    let mut albums: HashMap<(String, String), AlbumListenStats> = HashMap::new();
+    let mut album_scrobbles = 0;
+    let mut full_listen_scrobbles = 0;
+
+    for_each_session(
+        pool,
+        start.unwrap_or(DateTime::UNIX_EPOCH),
+        end.unwrap_or_else(Utc::now),
+        options.gap_minutes,
+        |session| {
+            for run in album_runs(&session) {
+                album_scrobbles += run.len();
+
+                let key = album_key(run[0]);
+                let known = known_tracks.get(&key).copied().unwrap_or(0);
+                let covered = run.len();
+                if covered < options.min_tracks
+                    || (covered as f64) < known as f64 * options.min_coverage
+                {
+                    continue;
+                }
+
+                full_listen_scrobbles += covered;
+                let finished = run[covered - 1].timestamp;
+                let stats = albums
+                    .entry(key.clone())
+                    .or_insert_with(|| AlbumListenStats {
+                        artist: key.0,
+                        album: key.1,
+                        full_listens: 0,
+                        known_tracks: known,
+                        last_full_listen: finished,
+                    });
+                stats.full_listens += 1;
+                stats.last_full_listen = stats.last_full_listen.max(finished);
+            }
+        },
+    )?;
+
+    let mut albums: Vec<AlbumListenStats> = albums.into_values().collect();
+    albums.sort_by(|a, b| {
+        b.full_listens
+            .cmp(&a.full_listens)
+            .then_with(|| b.last_full_listen.cmp(&a.last_full_listen))
+    });
+
+    Ok(AlbumListensReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        summary: AlbumListensSummary {
+            full_listens: albums.iter().map(|a| a.full_listens).sum(),
+            albums: albums.len(),
+            full_listen_share: if album_scrobbles > 0 {
+                full_listen_scrobbles as f64 / album_scrobbles as f64 * 100.0
+            } else {
+                0.0
+            },
+        },
+        albums,
+    })
+}
+
+fn album_key(scrobble: &Scrobble) -> (String, String) {
+    (
+        scrobble
+            .album_artist
+            .clone()
+            .unwrap_or_else(|| scrobble.artist.clone()),
+        scrobble.album.clone().unwrap_or_default(),
+    )
+}
+
+/// Split a session into runs of distinct tracks from one album. A scrobble
+/// without an album ends the run, and a repeated track starts a new one
+pub(crate) fn album_runs(session: &[Scrobble]) -> Vec<Vec<&Scrobble>> {
+    let mut runs: Vec<Vec<&Scrobble>> = Vec::new();
+    let mut current: Vec<&Scrobble> = Vec::new();
+    let mut tracks: HashSet<&str> = HashSet::new();
+
+    for scrobble in session {
+        let continues = scrobble.album.is_some()
+            && current
+                .first()
+                .is_some_and(|first| album_key(first) == album_key(scrobble))
+            && !tracks.contains(scrobble.track.as_str());
+        if !continues {
+            if !current.is_empty() {
+                runs.push(std::mem::take(&mut current));
+            }
+            tracks.clear();
+        }
+        if scrobble.album.is_some() {
+            tracks.insert(&scrobble.track);
+            current.push(scrobble);
+        }
+    }
+    if !current.is_empty() {
+        runs.push(current);
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+    use chrono::Duration;
+
+    #[test]
+    fn test_counts_albums_played_through() {
+        let (pool, _temp_file) = setup_pool();
+
+        let start: DateTime<Utc> = "2024-05-01T20:00:00Z".parse().unwrap();
+        let mut at = start;
+        let mut play = |artist: &str, album: &str, track: String| {
+            let scrobble = Scrobble::new(artist.to_string(), track, at, "lastfm".to_string())
+                .with_album(album.to_string());
+            crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+            at += Duration::minutes(4);
+        };
+
+        // Spirit of Eden played through twice
+        for _ in 0..2 {
+            for i in 1..=6 {
+                play("Talk Talk", "Spirit of Eden", format!("Track {}", i));
+            }
+            play("Slint", "Spiderland", "Nosferatu Man".to_string());
+        }
+        // A repeated track breaks the run into two short ones
+        for track in ["Track 1", "Track 2", "Track 1", "Track 3"] {
+            play("Talk Talk", "Spirit of Eden", track.to_string());
+        }
+        // Five of Spiderland's six tracks: enough coverage
+        for i in 1..=5 {
+            play("Slint", "Spiderland", format!("Track {}", i));
+        }
+
+        let options = AlbumListensOptions {
+            gap_minutes: 45,
+            min_tracks: DEFAULT_MIN_TRACKS,
+            min_coverage: DEFAULT_MIN_COVERAGE,
+        };
+        let report = generate_album_listens_report(&pool, None, None, &options).unwrap();
+
+        assert_eq!(report.albums.len(), 2);
+        assert_eq!(report.albums[0].album, "Spirit of Eden");
+        assert_eq!(report.albums[0].full_listens, 2);
+        assert_eq!(report.albums[0].known_tracks, 6);
+        assert_eq!(report.albums[1].album, "Spiderland");
+        assert_eq!(report.albums[1].full_listens, 1);
+        assert_eq!(report.summary.full_listens, 3);
+        // 17 of the 23 scrobbles were part of a full listen
+        assert!((report.summary.full_listen_share - 17.0 / 23.0 * 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::db::DbPool;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use chrono::{Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarMonth {
+    pub schema_version: u32,
+    pub year: i32,
+    pub month: u32,
+    pub total_scrobbles: i64,
+    pub days: Vec<CalendarDay>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub count: i64,
+    pub top_artist: Option<String>,
+    pub top_artist_count: i64,
+    /// Artists scrobbled for the very first time on this day
+    pub new_artists: Vec<String>,
+}
+
+/// Per-day summaries for one month, with days taken in `timezone`
+pub fn generate_calendar_month(
+    pool: &DbPool,
+    year: i32,
+    month: u32,
+    timezone: Tz,
+) -> Result<CalendarMonth> {
+    let first_day = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid month {}-{}", year, month))?;
+    let next_month = first_day
+        .checked_add_months(Months::new(1))
+        .ok_or_else(|| anyhow::anyhow!("Invalid month {}-{}", year, month))?;
+
+    // Finished days come from the nightly rollup
+    let days: Vec<CalendarDay> =
+        crate::summaries::daily_summaries(pool, first_day, next_month, timezone)?
+            .into_iter()
+            .map(|summary| CalendarDay {
+                date: summary.date,
+                count: summary.scrobbles,
+                top_artist: summary.top_artist,
+                top_artist_count: summary.top_artist_count,
+                new_artists: summary.new_artists,
+            })
+            .collect();
+    let total_scrobbles = days.iter().map(|day| day.count).sum();
+
+    Ok(CalendarMonth {
+        schema_version: REPORT_SCHEMA_VERSION,
+        year: first_day.year(),
+        month: first_day.month(),
+        total_scrobbles,
+        days,
+    })
+}
+
+/// Start of a local day in UTC; on DST gaps the earliest valid instant is used
+pub(crate) fn local_midnight(date: NaiveDate, timezone: Tz) -> Result<chrono::DateTime<Utc>> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    timezone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(midnight + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| anyhow::anyhow!("Invalid local midnight for {}", date))
+}
+
+impl VersionedReport for CalendarMonth {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn insert(pool: &DbPool, artist: &str, timestamp: &str) {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            timestamp.parse().unwrap(),
+            "test".to_string(),
+        );
+        crate::db::insert_scrobble(pool, &scrobble).unwrap();
+    }
+
+    #[test]
+    fn test_calendar_month_days() {
+        let (pool, _temp_file) = setup_pool();
+        insert(&pool, "Old Friend", "2024-01-15T12:00:00Z");
+        insert(&pool, "Old Friend", "2024-02-03T10:00:00Z");
+        insert(&pool, "Old Friend", "2024-02-03T11:00:00Z");
+        insert(&pool, "Newcomer", "2024-02-03T12:00:00Z");
+        insert(&pool, "Newcomer", "2024-02-10T12:00:00Z");
+
+        let calendar = generate_calendar_month(&pool, 2024, 2, chrono_tz::UTC).unwrap();
+
+        assert_eq!(calendar.days.len(), 29);
+        assert_eq!(calendar.total_scrobbles, 4);
+
+        let feb3 = &calendar.days[2];
+        assert_eq!(feb3.count, 3);
+        assert_eq!(feb3.top_artist.as_deref(), Some("Old Friend"));
+        assert_eq!(feb3.new_artists, vec!["Newcomer".to_string()]);
+
+        let feb10 = &calendar.days[9];
+        assert_eq!(feb10.count, 1);
+        assert!(feb10.new_artists.is_empty());
+
+        assert_eq!(calendar.days[0].count, 0);
+    }
+
+    #[test]
+    fn test_calendar_month_uses_timezone() {
+        let (pool, _temp_file) = setup_pool();
+        // Late evening on Feb 29 in New York is already March 1 in UTC
+        insert(&pool, "Artist", "2024-03-01T03:00:00Z");
+
+        let utc = generate_calendar_month(&pool, 2024, 2, chrono_tz::UTC).unwrap();
+        assert_eq!(utc.total_scrobbles, 0);
+
+        let ny = generate_calendar_month(&pool, 2024, 2, chrono_tz::America::New_York).unwrap();
+        assert_eq!(ny.total_scrobbles, 1);
+        assert_eq!(ny.days[28].count, 1);
+    }
+}
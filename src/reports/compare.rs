@@ -0,0 +1,310 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::DbPool;
+use crate::importers::LastFmImporter;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+
+// Artists ranked this high by both sides count as a shared obsession
+const OBSESSION_RANK: usize = 10;
+
+// The report waits on the friend's side, so don't let a slow host hang it
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Top artists and tracks of one listener, ordered by play count
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TopLists {
+    #[serde(rename = "top_artists")]
+    pub artists: Vec<(String, i64)>,
+    #[serde(rename = "top_tracks")]
+    pub tracks: Vec<(String, String, i64)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompareReport {
+    pub schema_version: u32,
+    pub friend: String,
+    pub source: String,
+    pub artist_similarity: f64,
+    pub track_similarity: f64,
+    pub compatibility_score: f64,
+    pub shared_artists: Vec<SharedArtist>,
+    pub shared_tracks: Vec<SharedTrack>,
+    pub shared_obsessions: Vec<SharedArtist>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedArtist {
+    pub artist: String,
+    pub your_rank: usize,
+    pub your_count: i64,
+    pub friend_rank: usize,
+    pub friend_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedTrack {
+    pub artist: String,
+    pub track: String,
+    pub your_rank: usize,
+    pub friend_rank: usize,
+}
+
+/// Top lists from the local database
+pub fn local_top_lists(pool: &DbPool, limit: i64) -> Result<TopLists> {
+    Ok(TopLists {
+        artists: crate::db::get_top_artists(pool, limit, None, None)?,
+        tracks: crate::db::get_top_tracks(pool, limit, None, None)?,
+    })
+}
+
+/// Check a friend's instance URL the same way proxied artwork is checked:
+/// http(s) only, and never a host on this server's own network
+pub fn parse_instance_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url).context("Invalid instance URL")?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!("Unsupported instance URL scheme: {}", parsed.scheme());
+    }
+    if !crate::images::is_public_host(&parsed) {
+        anyhow::bail!("Instance URL host is not allowed");
+    }
+
+    Ok(parsed)
+}
+
+/// Top lists from another Footprints instance, optionally through a share link
+pub async fn fetch_footprints_top_lists(
+    base_url: &Url,
+    share_token: Option<&str>,
+    limit: i64,
+) -> Result<TopLists> {
+    let base_url = base_url.as_str().trim_end_matches('/');
+    let url = match share_token {
+        Some(token) => format!("{}/share/{}/stats?limit={}", base_url, token, limit),
+        None => format!("{}/api/stats?limit={}", base_url, limit),
+    };
+
+    let response = crate::images::public_client(REMOTE_TIMEOUT)
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to fetch from remote Footprints")?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Remote Footprints returned error: {}",
+            response.status()
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .context("Failed to parse remote Footprints stats")
+}
+
+/// Top lists of a Last.fm user
+pub async fn fetch_lastfm_top_lists(username: &str, api_key: &str, limit: i64) -> Result<TopLists> {
+    let http = reqwest::Client::builder()
+        .timeout(REMOTE_TIMEOUT)
+        .build()
+        .context("Failed to build HTTP client")?;
+    let importer =
+        LastFmImporter::new(api_key.to_string(), username.to_string()).with_http(Arc::new(http));
+
+    Ok(TopLists {
+        artists: importer.fetch_top_artists(limit).await?,
+        tracks: importer.fetch_top_tracks(limit).await?,
+    })
+}
+
+/// Compare two listeners' top lists: Jaccard similarity of the top artists and
+/// tracks, plus the artists and tracks they share ordered by combined rank
+pub fn compare_top_lists(
+    friend: String,
+    source: String,
+    yours: &TopLists,
+    theirs: &TopLists,
+) -> CompareReport {
+    let your_artists = rank_index(yours.artists.iter().map(|(a, c)| (artist_key(a), *c)));
+    let their_artists = rank_index(theirs.artists.iter().map(|(a, c)| (artist_key(a), *c)));
+
+    let mut shared_artists: Vec<SharedArtist> = yours
+        .artists
+        .iter()
+        .filter_map(|(artist, _)| {
+            let key = artist_key(artist);
+            let &(your_rank, your_count) = your_artists.get(&key)?;
+            let &(friend_rank, friend_count) = their_artists.get(&key)?;
+            Some(SharedArtist {
+                artist: artist.clone(),
+                your_rank,
+                your_count,
+                friend_rank,
+                friend_count,
+            })
+        })
+        .collect();
+    shared_artists.sort_by_key(|a| (a.your_rank + a.friend_rank, a.your_rank));
+
+    let your_tracks = rank_index(yours.tracks.iter().map(|(a, t, c)| (track_key(a, t), *c)));
+    let their_tracks = rank_index(theirs.tracks.iter().map(|(a, t, c)| (track_key(a, t), *c)));
+
+    let mut shared_tracks: Vec<SharedTrack> = yours
+        .tracks
+        .iter()
+        .filter_map(|(artist, track, _)| {
+            let key = track_key(artist, track);
+            let &(your_rank, _) = your_tracks.get(&key)?;
+            let &(friend_rank, _) = their_tracks.get(&key)?;
+            Some(SharedTrack {
+                artist: artist.clone(),
+                track: track.clone(),
+                your_rank,
+                friend_rank,
+            })
+        })
+        .collect();
+    shared_tracks.sort_by_key(|t| (t.your_rank + t.friend_rank, t.your_rank));
+
+    let shared_obsessions: Vec<SharedArtist> = shared_artists
+        .iter()
+        .filter(|a| a.your_rank <= OBSESSION_RANK && a.friend_rank <= OBSESSION_RANK)
+        .cloned()
+        .collect();
+
+    let artist_similarity = jaccard(&your_artists, &their_artists);
+    let track_similarity = jaccard(&your_tracks, &their_tracks);
+
+    // Artist overlap matters more than exact track overlap
+    let compatibility_score =
+        ((artist_similarity * 0.7 + track_similarity * 0.3) * 1000.0).round() / 10.0;
+
+    CompareReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        friend,
+        source,
+        artist_similarity,
+        track_similarity,
+        compatibility_score,
+        shared_artists,
+        shared_tracks,
+        shared_obsessions,
+    }
+}
+
+fn artist_key(artist: &str) -> String {
+    artist.trim().to_lowercase()
+}
+
+fn track_key(artist: &str, track: &str) -> String {
+    format!(
+        "{}\u{1f}{}",
+        artist_key(artist),
+        track.trim().to_lowercase()
+    )
+}
+
+/// Map each key to its 1-based rank and play count, keeping the first occurrence
+fn rank_index(entries: impl Iterator<Item = (String, i64)>) -> HashMap<String, (usize, i64)> {
+    let mut index = HashMap::new();
+    for (rank, (key, count)) in entries.enumerate() {
+        index.entry(key).or_insert((rank + 1, count));
+    }
+    index
+}
+
+fn jaccard<V>(a: &HashMap<String, V>, b: &HashMap<String, V>) -> f64 {
+    let a: HashSet<&String> = a.keys().collect();
+    let b: HashSet<&String> = b.keys().collect();
+    let union = a.union(&b).count();
+
+    if union == 0 {
+        0.0
+    } else {
+        a.intersection(&b).count() as f64 / union as f64
+    }
+}
+
+impl VersionedReport for CompareReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lists(artists: &[&str], tracks: &[(&str, &str)]) -> TopLists {
+        TopLists {
+            artists: artists
+                .iter()
+                .enumerate()
+                .map(|(i, a)| (a.to_string(), 100 - i as i64))
+                .collect(),
+            tracks: tracks
+                .iter()
+                .enumerate()
+                .map(|(i, (a, t))| (a.to_string(), t.to_string(), 50 - i as i64))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_identical_lists_are_fully_compatible() {
+        let mine = lists(&["A", "B"], &[("A", "Song")]);
+        let report = compare_top_lists("friend".into(), "lastfm".into(), &mine, &mine);
+
+        assert_eq!(report.artist_similarity, 1.0);
+        assert_eq!(report.track_similarity, 1.0);
+        assert_eq!(report.compatibility_score, 100.0);
+        assert_eq!(report.shared_obsessions.len(), 2);
+    }
+
+    #[test]
+    fn test_partial_overlap_is_case_insensitive() {
+        let mine = lists(
+            &["Radiohead", "Björk", "Portishead"],
+            &[("Radiohead", "Creep")],
+        );
+        let theirs = lists(&["portishead", "RADIOHEAD", "Massive Attack"], &[]);
+        let report = compare_top_lists("friend".into(), "footprints".into(), &mine, &theirs);
+
+        // 2 shared out of 4 distinct artists
+        assert_eq!(report.artist_similarity, 0.5);
+        assert_eq!(report.track_similarity, 0.0);
+        assert_eq!(report.compatibility_score, 35.0);
+
+        // Radiohead: ranks 1 + 2, Portishead: ranks 3 + 1
+        assert_eq!(report.shared_artists[0].artist, "Radiohead");
+        assert_eq!(report.shared_artists[1].artist, "Portishead");
+        assert_eq!(report.shared_artists[1].friend_rank, 1);
+    }
+
+    #[test]
+    fn test_instance_url_must_be_public() {
+        assert!(parse_instance_url("https://footprints.example.org").is_ok());
+
+        assert!(parse_instance_url("footprints.example.org").is_err());
+        assert!(parse_instance_url("ftp://footprints.example.org").is_err());
+        assert!(parse_instance_url("http://localhost:3000").is_err());
+        assert!(parse_instance_url("http://10.0.0.2:3000").is_err());
+        assert!(parse_instance_url("http://100.100.1.1").is_err());
+        assert!(parse_instance_url("http://[::ffff:127.0.0.1]").is_err());
+    }
+
+    #[test]
+    fn test_empty_lists() {
+        let report = compare_top_lists(
+            "friend".into(),
+            "lastfm".into(),
+            &TopLists::default(),
+            &TopLists::default(),
+        );
+
+        assert_eq!(report.compatibility_score, 0.0);
+        assert!(report.shared_artists.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::db::{DbPool, TimeBucket};
+use crate::overview::Streak;
+use crate::reports::period::Granularity;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How the daily scrobble counts of a run of days spread
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailySpread {
+    pub days: usize,
+    pub active_days: usize,
+    pub scrobbles: i64,
+    pub daily_mean: f64,
+    /// Population variance of the daily counts, idle days included
+    pub daily_variance: f64,
+    pub daily_std_dev: f64,
+    /// 0-100: 100 when every day had the same number of scrobbles, near 0
+    /// when they all fell on a single day
+    pub consistency_score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsistencyPeriod {
+    pub period: String,
+    #[serde(flatten)]
+    pub spread: DailySpread,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsistencySummary {
+    #[serde(flatten)]
+    pub spread: DailySpread,
+    pub current_streak: Streak,
+    pub longest_streak_days: i64,
+    /// Highest scoring period, the latest on ties
+    pub most_consistent_period: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    pub schema_version: u32,
+    pub periods: Vec<ConsistencyPeriod>,
+    pub summary: ConsistencySummary,
+}
+
+impl VersionedReport for ConsistencyReport {}
+
+/// Day-to-day listening volume per period, with days taken in `timezone`.
+/// Without an explicit range, days run from the first scrobble to today
+pub fn generate_consistency_report(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    granularity: Granularity,
+    now: DateTime<Utc>,
+    timezone: Tz,
+) -> Result<ConsistencyReport> {
+    let mut daily: BTreeMap<NaiveDate, i64> =
+        crate::db::get_scrobbles_per_bucket(pool, TimeBucket::Day, start, end, timezone)?
+            .into_iter()
+            .filter_map(|(day, count)| Some((day.parse().ok()?, count)))
+            .collect();
+
+    // Idle days since the last scrobble count too
+    let today = now.with_timezone(&timezone).date_naive();
+    if end.is_none()
+        && let Some(mut day) = daily.keys().next_back().copied()
+    {
+        while day < today {
+            day += Duration::days(1);
+            daily.insert(day, 0);
+        }
+    }
+
+    let mut periods: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for (day, count) in &daily {
+        periods
+            .entry(granularity.format_period(&day.and_time(Default::default()).and_utc()))
+            .or_default()
+            .push(*count);
+    }
+    let periods: Vec<ConsistencyPeriod> = periods
+        .into_iter()
+        .map(|(period, counts)| ConsistencyPeriod {
+            period,
+            spread: daily_spread(&counts),
+        })
+        .collect();
+
+    let most_consistent_period = periods
+        .iter()
+        .filter(|p| p.spread.scrobbles > 0)
+        .max_by(|a, b| {
+            a.spread
+                .consistency_score
+                .total_cmp(&b.spread.consistency_score)
+        })
+        .map(|p| p.period.clone());
+
+    let active_days: BTreeSet<NaiveDate> = daily
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(day, _)| *day)
+        .collect();
+    let counts: Vec<i64> = daily.into_values().collect();
+
+    Ok(ConsistencyReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        periods,
+        summary: ConsistencySummary {
+            spread: daily_spread(&counts),
+            current_streak: crate::overview::current_streak(&active_days, today),
+            longest_streak_days: longest_streak(&active_days),
+            most_consistent_period,
+        },
+    })
+}
+
+fn daily_spread(counts: &[i64]) -> DailySpread {
+    let days = counts.len();
+    let scrobbles: i64 = counts.iter().sum();
+    if days == 0 {
+        return DailySpread {
+            days,
+            active_days: 0,
+            scrobbles,
+            daily_mean: 0.0,
+            daily_variance: 0.0,
+            daily_std_dev: 0.0,
+            consistency_score: 0.0,
+        };
+    }
+
+    let daily_mean = scrobbles as f64 / days as f64;
+    let daily_variance = counts
+        .iter()
+        .map(|count| (*count as f64 - daily_mean).powi(2))
+        .sum::<f64>()
+        / days as f64;
+
+    DailySpread {
+        days,
+        active_days: counts.iter().filter(|count| **count > 0).count(),
+        scrobbles,
+        daily_mean,
+        daily_variance,
+        daily_std_dev: daily_variance.sqrt(),
+        consistency_score: (1.0 - gini(counts)) * 100.0,
+    }
+}
+
+/// Gini coefficient of the daily counts: 0 when every day is equal, close to
+/// 1 when one day holds everything. No listening at all is perfectly uneven
+fn gini(counts: &[i64]) -> f64 {
+    let total: i64 = counts.iter().sum();
+    if total == 0 {
+        return 1.0;
+    }
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len() as f64;
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, count)| (i as f64 + 1.0) * *count as f64)
+        .sum();
+    (2.0 * weighted) / (n * total as f64) - (n + 1.0) / n
+}
+
+/// Longest run of consecutive active days
+pub fn longest_streak(active_days: &BTreeSet<NaiveDate>) -> i64 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in active_days {
+        run = match previous {
+            Some(previous) if previous + Duration::days(1) == *day => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn plays(pool: &DbPool, at: &str, times: usize) {
+        let timestamp: DateTime<Utc> = at.parse().unwrap();
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                "Low".to_string(),
+                format!("Track {}", i),
+                timestamp + Duration::minutes(i as i64 * 4),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_daily_spread() {
+        let even = daily_spread(&[4, 4, 4, 4]);
+        assert_eq!(even.daily_mean, 4.0);
+        assert_eq!(even.daily_variance, 0.0);
+        assert_eq!(even.consistency_score, 100.0);
+
+        let binge = daily_spread(&[0, 0, 0, 16]);
+        assert_eq!(binge.daily_mean, 4.0);
+        assert_eq!(binge.daily_variance, 48.0);
+        assert_eq!(binge.active_days, 1);
+        assert_eq!(binge.consistency_score, 25.0);
+
+        assert_eq!(daily_spread(&[0, 0]).consistency_score, 0.0);
+        assert_eq!(daily_spread(&[]).days, 0);
+    }
+
+    #[test]
+    fn test_longest_streak() {
+        let days: BTreeSet<NaiveDate> = [
+            "2024-03-01",
+            "2024-03-02",
+            "2024-03-04",
+            "2024-03-05",
+            "2024-03-06",
+        ]
+        .iter()
+        .map(|d| d.parse().unwrap())
+        .collect();
+        assert_eq!(longest_streak(&days), 3);
+        assert_eq!(longest_streak(&BTreeSet::new()), 0);
+    }
+
+    #[test]
+    fn test_consistency_per_month() {
+        let (pool, _temp_file) = setup_pool();
+        // Every day of January, all of February's listening on one day
+        for day in 1..=31 {
+            plays(&pool, &format!("2024-01-{:02}T20:00:00Z", day), 2);
+        }
+        plays(&pool, "2024-02-10T20:00:00Z", 10);
+        let now: DateTime<Utc> = "2024-02-29T12:00:00Z".parse().unwrap();
+
+        let report =
+            generate_consistency_report(&pool, None, None, Granularity::Month, now, chrono_tz::UTC)
+                .unwrap();
+
+        let names: Vec<&str> = report.periods.iter().map(|p| p.period.as_str()).collect();
+        assert_eq!(names, vec!["2024-01", "2024-02"]);
+        let january = &report.periods[0].spread;
+        assert_eq!(january.consistency_score, 100.0);
+        assert_eq!(january.daily_std_dev, 0.0);
+        // Idle days up to today are part of February
+        let february = &report.periods[1].spread;
+        assert_eq!((february.days, february.active_days), (29, 1));
+        assert!(february.consistency_score < 5.0);
+
+        assert_eq!(
+            report.summary.most_consistent_period.as_deref(),
+            Some("2024-01")
+        );
+        assert_eq!(report.summary.spread.scrobbles, 72);
+        assert_eq!(report.summary.longest_streak_days, 31);
+        assert_eq!(report.summary.current_streak, Streak::default());
+    }
+}
@@ -1,30 +1,12 @@
 use crate::db::DbPool;
 use crate::models::Scrobble;
+use crate::reports::period::Granularity;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum Granularity {
-    Day,
-    Week,
-    Month,
-    Year,
-}
-
-impl Granularity {
-    pub fn format_period(&self, date: &DateTime<Utc>) -> String {
-        match self {
-            Granularity::Day => date.format("%Y-%m-%d").to_string(),
-            Granularity::Week => date.format("%Y-W%W").to_string(),
-            Granularity::Month => date.format("%Y-%m").to_string(),
-            Granularity::Year => date.format("%Y").to_string(),
-        }
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DiversityPoint {
     pub period: String,
@@ -50,6 +32,7 @@ pub struct DiversitySummary {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiversityReport {
+    pub schema_version: u32,
     pub timeline: Vec<DiversityPoint>,
     pub summary: DiversitySummary,
 }
@@ -135,6 +118,7 @@ pub fn generate_diversity_report(
 
     if scrobbles.is_empty() {
         return Ok(DiversityReport {
+            schema_version: REPORT_SCHEMA_VERSION,
             timeline: Vec::new(),
             summary: DiversitySummary {
                 total_scrobbles: 0,
@@ -169,7 +153,11 @@ pub fn generate_diversity_report(
     // Compute summary
     let summary = compute_diversity_summary(&timeline, &scrobbles);
 
-    Ok(DiversityReport { timeline, summary })
+    Ok(DiversityReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        timeline,
+        summary,
+    })
 }
 
 fn compute_diversity_point(period: String, scrobbles: &[&Scrobble]) -> DiversityPoint {
@@ -270,10 +258,12 @@ fn compute_diversity_summary(
     }
 }
 
+impl VersionedReport for DiversityReport {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::Scrobble;
+    use crate::models::{MediaType, Scrobble};
 
     fn test_scrobble(timestamp: &str, artist: &str, track: &str) -> Scrobble {
         Scrobble {
@@ -284,6 +274,12 @@ mod tests {
             timestamp: timestamp.parse().unwrap(),
             source: "test".to_string(),
             source_id: None,
+            ms_played: None,
+            skipped: None,
+            raw_metadata: None,
+            media_type: MediaType::Music,
+            source_metadata: None,
+            album_artist: None,
         }
     }
 
@@ -0,0 +1,231 @@
+use crate::db::DbPool;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Tracks with fewer plays than this have no peak worth measuring
+pub const DEFAULT_MIN_PLAYS: i64 = 10;
+
+/// Width of the trailing window play rates are measured over. Drops faster
+/// than the window can't be told apart
+pub const DEFAULT_WINDOW_DAYS: i64 = 28;
+
+// A peak of fewer plays in one window halves on a single play
+const MIN_PEAK_PLAYS: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackHalfLife {
+    pub artist: String,
+    pub track: String,
+    pub plays: i64,
+    /// When the track's play rate was highest
+    pub peak_at: DateTime<Utc>,
+    /// Plays in the window ending at the peak
+    pub peak_plays: i64,
+    /// Days from the peak until the play rate fell to half of it
+    pub half_life_days: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HalfLifeSummary {
+    pub tracks_analyzed: usize,
+    /// Tracks whose play rate halved since their peak
+    pub burned_out: usize,
+    /// Tracks still played at over half their peak rate
+    pub still_going: usize,
+    /// Average half-life of the burned out tracks: the lower, the faster
+    /// tracks wear out
+    pub avg_half_life_days: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HalfLifeReport {
+    pub schema_version: u32,
+    pub window_days: i64,
+    pub min_plays: i64,
+    pub summary: HalfLifeSummary,
+    /// Fastest burned out first
+    pub tracks: Vec<TrackHalfLife>,
+}
+
+/// How quickly each track played at least `min_plays` times fell off after
+/// its personal peak. The play rate at a time is the number of plays in the
+/// `window_days` before it; the half-life is how long after the peak that
+/// rate first dropped to half, as of `now`
+pub fn generate_half_life_report(
+    pool: &DbPool,
+    min_plays: i64,
+    window_days: i64,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> Result<HalfLifeReport> {
+    let window = Duration::days(window_days);
+    let mut tracks_analyzed = 0;
+    let mut still_going = 0;
+    let mut burned_out = Vec::new();
+
+    crate::db::for_each_track_history(pool, min_plays, |artist, track, timestamps| {
+        let Some((peak, peak_plays)) = peak(timestamps, window) else {
+            return;
+        };
+        tracks_analyzed += 1;
+        match half_life(timestamps, peak, peak_plays, window, now) {
+            Some(half_life) => burned_out.push(TrackHalfLife {
+                artist: artist.to_string(),
+                track: track.to_string(),
+                plays: timestamps.len() as i64,
+                peak_at: timestamps[peak],
+                peak_plays: peak_plays as i64,
+                half_life_days: half_life.num_days(),
+            }),
+            None => still_going += 1,
+        }
+    })?;
+
+    let avg_half_life_days = (!burned_out.is_empty()).then(|| {
+        burned_out.iter().map(|t| t.half_life_days).sum::<i64>() as f64 / burned_out.len() as f64
+    });
+    let summary = HalfLifeSummary {
+        tracks_analyzed,
+        burned_out: burned_out.len(),
+        still_going,
+        avg_half_life_days,
+    };
+
+    burned_out.sort_by(|a, b| {
+        a.half_life_days
+            .cmp(&b.half_life_days)
+            .then_with(|| b.peak_plays.cmp(&a.peak_plays))
+            .then_with(|| b.plays.cmp(&a.plays))
+    });
+    burned_out.truncate(limit);
+
+    Ok(HalfLifeReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        window_days,
+        min_plays,
+        summary,
+        tracks: burned_out,
+    })
+}
+
+/// Number of `timestamps` in `(end - window, end]`
+fn plays_in_window(timestamps: &[DateTime<Utc>], end: DateTime<Utc>, window: Duration) -> usize {
+    let from = timestamps.partition_point(|t| *t <= end - window);
+    let to = timestamps.partition_point(|t| *t <= end);
+    to - from
+}
+
+/// Index of the play where the rate peaked first, with the plays in the
+/// window ending there; `None` when the peak is too small to halve
+fn peak(timestamps: &[DateTime<Utc>], window: Duration) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize)> = None;
+    for (i, timestamp) in timestamps.iter().enumerate() {
+        let plays = plays_in_window(timestamps, *timestamp, window);
+        if best.is_none_or(|(_, most)| plays > most) {
+            best = Some((i, plays));
+        }
+    }
+    best.filter(|(_, plays)| *plays >= MIN_PEAK_PLAYS)
+}
+
+/// Time from the peak until the rate first fell to half of `peak_plays`.
+/// The rate only drops when a play leaves the window, so those are the only
+/// moments to check
+fn half_life(
+    timestamps: &[DateTime<Utc>],
+    peak: usize,
+    peak_plays: usize,
+    window: Duration,
+    now: DateTime<Utc>,
+) -> Option<Duration> {
+    let peak_at = timestamps[peak];
+    timestamps
+        .iter()
+        .map(|timestamp| *timestamp + window)
+        .filter(|leaves| *leaves > peak_at)
+        .take_while(|leaves| *leaves <= now)
+        .find(|leaves| plays_in_window(timestamps, *leaves, window) * 2 <= peak_plays)
+        .map(|halved| halved - peak_at)
+}
+
+impl VersionedReport for HalfLifeReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    /// One play of `track` every `every_days` days, `times` times from `from`
+    fn plays(pool: &DbPool, track: &str, from: DateTime<Utc>, every_days: i64, times: i64) {
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                "Low".to_string(),
+                track.to_string(),
+                from + Duration::days(i * every_days),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_half_life_of_a_binge() {
+        let start: DateTime<Utc> = "2024-01-01T20:00:00Z".parse().unwrap();
+        // Eight plays over a week, then nothing
+        let timestamps: Vec<DateTime<Utc>> = (0..8).map(|i| start + Duration::days(i)).collect();
+        let window = Duration::days(28);
+
+        let (peak_index, peak_plays) = peak(&timestamps, window).unwrap();
+        assert_eq!((peak_index, peak_plays), (7, 8));
+        // Halved once the fourth play left the window
+        let now = start + Duration::days(365);
+        assert_eq!(
+            half_life(&timestamps, peak_index, peak_plays, window, now),
+            Some(Duration::days(24))
+        );
+        // Too early to tell
+        assert_eq!(
+            half_life(
+                &timestamps,
+                peak_index,
+                peak_plays,
+                window,
+                start + Duration::days(20)
+            ),
+            None
+        );
+        assert_eq!(peak(&timestamps[..3], window), None);
+    }
+
+    #[test]
+    fn test_fastest_burnouts() {
+        let (pool, _temp_file) = setup_pool();
+        let start: DateTime<Utc> = "2024-01-01T20:00:00Z".parse().unwrap();
+        // Daily for two weeks, then never again
+        plays(&pool, "Lazy", start, 1, 14);
+        // Every other day for two months, then never again
+        plays(&pool, "Sunflower", start, 2, 28);
+        // Every other day all year, until a few days ago
+        plays(&pool, "Words", start, 2, 180);
+        // Too few plays to count
+        plays(&pool, "Starfire", start, 1, 5);
+
+        let now: DateTime<Utc> = "2025-01-01T00:00:00Z".parse().unwrap();
+        let report = generate_half_life_report(&pool, 10, 28, 10, now).unwrap();
+        assert_eq!(report.summary.tracks_analyzed, 3);
+        assert_eq!(report.summary.burned_out, 2);
+        assert_eq!(report.summary.still_going, 1);
+
+        let names: Vec<&str> = report.tracks.iter().map(|t| t.track.as_str()).collect();
+        assert_eq!(names, vec!["Lazy", "Sunflower"]);
+        assert_eq!(report.tracks[0].peak_plays, 14);
+        assert_eq!(report.tracks[0].peak_at, start + Duration::days(13));
+        assert_eq!(report.tracks[0].half_life_days, 21);
+        assert_eq!(report.tracks[1].peak_plays, 14);
+        assert_eq!(report.tracks[1].half_life_days, 42);
+        assert_eq!(report.summary.avg_half_life_days, Some(31.5));
+    }
+}
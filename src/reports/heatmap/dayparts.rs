@@ -0,0 +1,236 @@
+use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::{DbPool, ScrobbleFilter};
+use crate::models::Scrobble;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+
+/// Named stretch of the day starting at `start_hour` and running until the
+/// next part begins, wrapping past midnight
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayPart {
+    pub name: String,
+    pub start_hour: u32,
+}
+
+impl DayPart {
+    fn new(name: &str, start_hour: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            start_hour,
+        }
+    }
+}
+
+/// Morning, commute, work, evening and night
+pub fn default_day_parts() -> Vec<DayPart> {
+    vec![
+        DayPart::new("morning", 6),
+        DayPart::new("commute", 8),
+        DayPart::new("work", 9),
+        DayPart::new("evening", 18),
+        DayPart::new("night", 22),
+    ]
+}
+
+/// Parse "name:hour" pairs such as "morning:6,work:9,night:22", sorted by
+/// start hour; hours must be distinct and within 0-23
+pub fn parse_day_parts(spec: &str) -> Result<Vec<DayPart>> {
+    let mut parts = spec
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(|part| {
+            let (name, hour) = part
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Expected name:hour, got {}", part))?;
+            let name = name.trim();
+            let hour: u32 = hour.trim().parse()?;
+            if name.is_empty() || hour > 23 {
+                return Err(anyhow::anyhow!("Invalid day part: {}", part));
+            }
+            Ok(DayPart::new(name, hour))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    parts.sort_by_key(|p| p.start_hour);
+    if parts.is_empty() {
+        return Err(anyhow::anyhow!("At least one day part is required"));
+    }
+    if parts.windows(2).any(|w| w[0].start_hour == w[1].start_hour) {
+        return Err(anyhow::anyhow!("Day parts must start at different hours"));
+    }
+
+    Ok(parts)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DayPartTotal {
+    pub name: String,
+    pub start_hour: u32,
+    /// Hour the next part starts, exclusive
+    pub end_hour: u32,
+    pub hours: u32,
+    pub count: i64,
+    /// Share of all plays in the period, 0-1
+    pub share: f64,
+    /// Plays per hour of the part, to compare parts of different lengths
+    pub per_hour: f64,
+    pub top_artist: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DayPartsReport {
+    pub schema_version: u32,
+    pub total_scrobbles: i64,
+    pub parts: Vec<DayPartTotal>,
+    /// Part with the most plays per hour
+    pub peak_part: Option<String>,
+}
+
+/// Plays grouped into day parts, with hours taken in `timezone`
+pub fn generate_day_parts(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    timezone: Tz,
+    parts: &[DayPart],
+    filter: &ScrobbleFilter,
+) -> Result<DayPartsReport> {
+    let scrobbles = crate::db::get_scrobbles_filtered(pool, start, end, filter)?;
+    Ok(build_day_parts(&scrobbles, timezone, parts))
+}
+
+fn build_day_parts(scrobbles: &[Scrobble], timezone: Tz, parts: &[DayPart]) -> DayPartsReport {
+    // Hours before the first part belong to the last one, which wraps
+    let part_of_hour = |hour: u32| {
+        parts
+            .iter()
+            .rposition(|p| p.start_hour <= hour)
+            .unwrap_or(parts.len() - 1)
+    };
+
+    let mut counts = vec![0i64; parts.len()];
+    let mut artists: Vec<HashMap<&str, i64>> = vec![HashMap::new(); parts.len()];
+    for scrobble in scrobbles {
+        let hour = scrobble.timestamp.with_timezone(&timezone).hour();
+        let index = part_of_hour(hour);
+        counts[index] += 1;
+        *artists[index].entry(scrobble.artist.as_str()).or_insert(0) += 1;
+    }
+
+    let total_scrobbles = scrobbles.len() as i64;
+    let totals: Vec<DayPartTotal> = parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let end_hour = parts[(i + 1) % parts.len()].start_hour;
+            let hours = match (end_hour + 24 - part.start_hour) % 24 {
+                0 => 24,
+                h => h,
+            };
+            let count = counts[i];
+            let top_artist = artists[i]
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(artist, _)| artist.to_string());
+
+            DayPartTotal {
+                name: part.name.clone(),
+                start_hour: part.start_hour,
+                end_hour,
+                hours,
+                count,
+                share: if total_scrobbles > 0 {
+                    count as f64 / total_scrobbles as f64
+                } else {
+                    0.0
+                },
+                per_hour: count as f64 / hours as f64,
+                top_artist,
+            }
+        })
+        .collect();
+
+    let peak_part = totals
+        .iter()
+        .filter(|p| p.count > 0)
+        .max_by(|a, b| a.per_hour.total_cmp(&b.per_hour))
+        .map(|p| p.name.clone());
+
+    DayPartsReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        total_scrobbles,
+        parts: totals,
+        peak_part,
+    }
+}
+
+impl VersionedReport for DayPartsReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_scrobble_from_rfc3339;
+
+    #[test]
+    fn test_parse_day_parts() {
+        let parts = parse_day_parts("night:22, morning:6,work:9").unwrap();
+        assert_eq!(
+            parts,
+            vec![
+                DayPart::new("morning", 6),
+                DayPart::new("work", 9),
+                DayPart::new("night", 22),
+            ]
+        );
+
+        assert!(parse_day_parts("").is_err());
+        assert!(parse_day_parts("morning:25").is_err());
+        assert!(parse_day_parts("a:6,b:6").is_err());
+        assert!(parse_day_parts("morning").is_err());
+    }
+
+    #[test]
+    fn test_day_parts_wrap_past_midnight() {
+        let scrobbles = vec![
+            test_scrobble_from_rfc3339("Early", "T", "2024-01-01T07:00:00Z"),
+            test_scrobble_from_rfc3339("Desk", "T", "2024-01-01T10:00:00Z"),
+            test_scrobble_from_rfc3339("Desk", "T", "2024-01-01T14:00:00Z"),
+            test_scrobble_from_rfc3339("Owl", "T", "2024-01-01T23:00:00Z"),
+            test_scrobble_from_rfc3339("Owl", "T", "2024-01-02T03:00:00Z"),
+        ];
+
+        let report = build_day_parts(&scrobbles, chrono_tz::UTC, &default_day_parts());
+        let by_name: HashMap<&str, &DayPartTotal> =
+            report.parts.iter().map(|p| (p.name.as_str(), p)).collect();
+
+        let night = by_name["night"];
+        assert_eq!((night.start_hour, night.end_hour, night.hours), (22, 6, 8));
+        assert_eq!(night.count, 2);
+        assert_eq!(night.top_artist.as_deref(), Some("Owl"));
+
+        assert_eq!(by_name["work"].count, 2);
+        assert_eq!(by_name["work"].hours, 9);
+        assert_eq!(by_name["commute"].count, 0);
+        assert_eq!(report.parts.iter().map(|p| p.hours).sum::<u32>(), 24);
+
+        // One play in two morning hours beats two in nine work hours
+        assert_eq!(report.peak_part.as_deref(), Some("morning"));
+    }
+
+    #[test]
+    fn test_day_parts_use_timezone() {
+        let scrobbles = vec![test_scrobble_from_rfc3339("A", "T", "2024-06-01T12:00:00Z")];
+        let parts = parse_day_parts("day:6,night:20").unwrap();
+
+        let utc = build_day_parts(&scrobbles, chrono_tz::UTC, &parts);
+        assert_eq!(utc.parts[0].count, 1);
+
+        // Noon UTC is 9pm in Tokyo
+        let tokyo = build_day_parts(&scrobbles, chrono_tz::Asia::Tokyo, &parts);
+        assert_eq!(tokyo.parts[1].count, 1);
+    }
+}
@@ -1,11 +1,27 @@
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
-use crate::db::DbPool;
+use crate::db::{DbPool, ScrobbleFilter};
 use crate::models::Scrobble;
+use crate::reports::locale::Locale;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport, remove_keys};
+
+pub mod dayparts;
+
+/// How cell counts are turned into rates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    /// Divide by the number of weeks spanned
+    #[default]
+    Week,
+    /// Divide each weekday's cells by how many times that weekday occurs
+    Weekday,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HeatmapCell {
@@ -63,11 +79,18 @@ pub struct PeakHour {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HeatmapReport {
+    pub schema_version: u32,
     pub grid: Vec<DayGrid>,
     pub peak_day: PeakDay,
     pub peak_hour: PeakHour,
     pub total_scrobbles: i64,
     pub is_normalized: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalization: Option<Normalization>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
     // Keep old format for backward compatibility
     #[serde(skip_serializing_if = "Option::is_none")]
     pub heatmap: Option<Vec<HeatmapCell>>,
@@ -79,16 +102,21 @@ pub struct HeatmapReport {
     pub hour_totals: Option<Vec<HourTotal>>,
 }
 
-/// Generate a heatmap showing listening patterns by hour and weekday
+/// Generate a heatmap showing listening patterns by hour and weekday, with
+/// weekdays named in `locale`
 pub fn generate_heatmap(
     pool: &DbPool,
     start: Option<DateTime<Utc>>,
     end: Option<DateTime<Utc>>,
     timezone: Tz,
-    normalize: bool,
+    normalize: Option<Normalization>,
+    filter: &ScrobbleFilter,
+    locale: Locale,
 ) -> Result<HeatmapReport> {
     // Fetch scrobbles in range
-    let scrobbles = if let (Some(s), Some(e)) = (start, end) {
+    let scrobbles = if !filter.is_empty() {
+        crate::db::get_scrobbles_filtered(pool, start, end, filter)?
+    } else if let (Some(s), Some(e)) = (start, end) {
         crate::db::get_scrobbles_in_range(pool, s, e)?
     } else {
         // Get all scrobbles
@@ -96,15 +124,20 @@ pub fn generate_heatmap(
     };
 
     // Build heatmap from scrobbles
-    build_heatmap_from_scrobbles(scrobbles, timezone, normalize, start, end)
+    let mut report =
+        build_heatmap_from_scrobbles(scrobbles, timezone, normalize, start, end, locale)?;
+    report.artist = filter.artist.clone();
+    report.genre = filter.genre.clone();
+    Ok(report)
 }
 
 fn build_heatmap_from_scrobbles(
     scrobbles: Vec<Scrobble>,
     timezone: Tz,
-    normalize: bool,
+    normalize: Option<Normalization>,
     start: Option<DateTime<Utc>>,
     end: Option<DateTime<Utc>>,
+    locale: Locale,
 ) -> Result<HeatmapReport> {
     // Build heatmap matrix (7 weekdays x 24 hours)
     let mut heatmap_matrix: HashMap<(u32, u32), i64> = HashMap::new();
@@ -118,23 +151,47 @@ fn build_heatmap_from_scrobbles(
         *heatmap_matrix.entry((weekday, hour)).or_insert(0) += 1;
     }
 
-    // Compute weeks in range (for normalization)
-    let weeks_in_range = if let (Some(s), Some(e)) = (start, end) {
-        let duration = e.signed_duration_since(s);
-        (duration.num_weeks()).max(1)
-    } else {
-        1
+    // Without an explicit range, normalize over the span the data covers
+    let span = match (start, end) {
+        (Some(s), Some(e)) => Some((s, e)),
+        _ => scrobbles
+            .iter()
+            .map(|s| s.timestamp)
+            .min()
+            .zip(scrobbles.iter().map(|s| s.timestamp).max()),
     };
 
+    // Compute weeks in range (for normalization)
+    let weeks_in_range = span
+        .map(|(s, e)| e.signed_duration_since(s).num_weeks().max(1))
+        .unwrap_or(1);
+
+    // A range ending exactly at midnight doesn't include that last day
+    let weekday_occurrences = span
+        .map(|(s, e)| {
+            let last = if end.is_some() {
+                e - Duration::seconds(1)
+            } else {
+                e
+            };
+            count_weekdays(
+                s.with_timezone(&timezone).date_naive(),
+                last.with_timezone(&timezone).date_naive(),
+            )
+        })
+        .unwrap_or([1; 7]);
+
     // Build heatmap cells
     let mut heatmap = Vec::new();
     for weekday in 0..7 {
         for hour in 0..24 {
             let count = *heatmap_matrix.get(&(weekday, hour)).unwrap_or(&0);
-            let normalized_value = if normalize {
-                count as f64 / weeks_in_range as f64
-            } else {
-                count as f64
+            let normalized_value = match normalize {
+                Some(Normalization::Week) => count as f64 / weeks_in_range as f64,
+                Some(Normalization::Weekday) => {
+                    count as f64 / weekday_occurrences[weekday as usize] as f64
+                }
+                None => count as f64,
             };
 
             heatmap.push(HeatmapCell {
@@ -172,20 +229,11 @@ fn build_heatmap_from_scrobbles(
         *weekday_counts.entry(cell.weekday).or_insert(0) += cell.count;
     }
 
-    let weekday_names = [
-        "Monday",
-        "Tuesday",
-        "Wednesday",
-        "Thursday",
-        "Friday",
-        "Saturday",
-        "Sunday",
-    ];
     let mut weekday_totals: Vec<DayTotal> = weekday_counts
         .into_iter()
         .map(|(weekday, count)| DayTotal {
             weekday,
-            name: weekday_names[weekday as usize].to_string(),
+            name: locale.weekday(weekday).to_string(),
             count,
         })
         .collect();
@@ -245,11 +293,15 @@ fn build_heatmap_from_scrobbles(
         .unwrap_or(PeakHour { hour: 0, count: 0 });
 
     Ok(HeatmapReport {
+        schema_version: REPORT_SCHEMA_VERSION,
         grid,
         peak_day,
         peak_hour,
         total_scrobbles: scrobbles.len() as i64,
-        is_normalized: normalize,
+        is_normalized: normalize.is_some(),
+        normalization: normalize,
+        artist: None,
+        genre: None,
         heatmap: Some(heatmap),
         summary: Some(summary),
         weekday_totals: Some(weekday_totals),
@@ -257,5 +309,35 @@ fn build_heatmap_from_scrobbles(
     })
 }
 
+/// How many times each weekday (0=Monday) occurs between two dates,
+/// inclusive, never less than 1
+fn count_weekdays(first: NaiveDate, last: NaiveDate) -> [i64; 7] {
+    let mut counts = [0i64; 7];
+    let total_days = (last - first).num_days() + 1;
+
+    if total_days > 0 {
+        let first_weekday = first.weekday().num_days_from_monday() as i64;
+        for (weekday, count) in counts.iter_mut().enumerate() {
+            let offset = (weekday as i64 - first_weekday).rem_euclid(7);
+            *count = (total_days - offset + 6) / 7;
+        }
+    }
+
+    counts.map(|c| c.max(1))
+}
+
+impl VersionedReport for HeatmapReport {
+    fn downgrade_to_v1(object: &mut Map<String, Value>) {
+        remove_keys(object, &["normalization", "artist", "genre"]);
+    }
+
+    fn strip_legacy(object: &mut Map<String, Value>) {
+        remove_keys(
+            object,
+            &["heatmap", "summary", "weekday_totals", "hour_totals"],
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,4 +1,5 @@
 use super::*;
+use crate::models::MediaType;
 use chrono::TimeZone;
 
 fn test_scrobble(timestamp_str: &str) -> Scrobble {
@@ -12,6 +13,12 @@ fn test_scrobble(timestamp_str: &str) -> Scrobble {
             .with_timezone(&Utc),
         source: "test".to_string(),
         source_id: None,
+        ms_played: None,
+        skipped: None,
+        raw_metadata: None,
+        media_type: MediaType::Music,
+        source_metadata: None,
+        album_artist: None,
     }
 }
 
@@ -24,7 +31,8 @@ fn test_heatmap_basic() {
         test_scrobble("2024-01-02T14:00:00Z"), // Tuesday 2pm UTC
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Find Monday 9am cell
     let heatmap = report.heatmap.as_ref().unwrap();
@@ -52,7 +60,7 @@ fn test_heatmap_timezone_conversion() {
 
     // Convert to EST (UTC-5)
     let tz: Tz = "America/New_York".parse().unwrap();
-    let report = build_heatmap_from_scrobbles(scrobbles, tz, false, None, None).unwrap();
+    let report = build_heatmap_from_scrobbles(scrobbles, tz, None, None, None, Locale::En).unwrap();
 
     // Should appear at Sunday 7pm EST (previous day, 5 hours earlier)
     let heatmap = report.heatmap.as_ref().unwrap();
@@ -73,8 +81,15 @@ fn test_heatmap_normalization() {
     let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
     let end = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
 
-    let report =
-        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, true, Some(start), Some(end)).unwrap();
+    let report = build_heatmap_from_scrobbles(
+        scrobbles,
+        Tz::UTC,
+        Some(Normalization::Week),
+        Some(start),
+        Some(end),
+        Locale::En,
+    )
+    .unwrap();
 
     let heatmap = report.heatmap.as_ref().unwrap();
     let monday_9am = heatmap
@@ -89,7 +104,8 @@ fn test_heatmap_normalization() {
 
 #[test]
 fn test_empty_heatmap() {
-    let report = build_heatmap_from_scrobbles(vec![], Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(vec![], Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Should have full 7x24 matrix
     let heatmap = report.heatmap.as_ref().unwrap();
@@ -106,7 +122,8 @@ fn test_empty_heatmap() {
 fn test_heatmap_matrix_dimensions() {
     let scrobbles = vec![test_scrobble("2024-01-01T12:00:00Z")];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Should have exactly 168 cells (7 days * 24 hours)
     let heatmap = report.heatmap.as_ref().unwrap();
@@ -132,7 +149,8 @@ fn test_peak_detection() {
         test_scrobble("2024-01-02T14:00:00Z"), // Tuesday 2pm
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Peak should be Monday 9am with 3 scrobbles
     let summary = report.summary.as_ref().unwrap();
@@ -149,7 +167,8 @@ fn test_weekday_totals() {
         test_scrobble("2024-01-02T10:00:00Z"), // Tuesday
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Monday should have 2 scrobbles
     let weekday_totals = report.weekday_totals.as_ref().unwrap();
@@ -171,7 +190,8 @@ fn test_hour_totals() {
         test_scrobble("2024-01-02T14:00:00Z"), // 2pm
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Hour 9 should have 2 scrobbles
     let hour_totals = report.hour_totals.as_ref().unwrap();
@@ -190,13 +210,19 @@ fn test_weekday_names() {
         test_scrobble("2024-01-07T09:00:00Z"), // Sunday
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Check all weekday names are present
     let weekday_totals = report.weekday_totals.as_ref().unwrap();
     let names: Vec<String> = weekday_totals.iter().map(|d| d.name.clone()).collect();
     assert!(names.contains(&"Monday".to_string()));
     assert!(names.contains(&"Sunday".to_string()));
+
+    let scrobbles = vec![test_scrobble("2024-01-01T09:00:00Z")];
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::Fr).unwrap();
+    assert_eq!(report.weekday_totals.unwrap()[0].name, "lundi");
 }
 
 #[test]
@@ -207,7 +233,8 @@ fn test_weeks_calculation() {
     let scrobbles = vec![test_scrobble("2024-01-15T12:00:00Z")];
 
     let report =
-        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, Some(start), Some(end)).unwrap();
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, Some(start), Some(end), Locale::En)
+            .unwrap();
 
     // 28 days = 4 weeks
     assert_eq!(report.summary.as_ref().unwrap().weeks_in_range, 4);
@@ -221,7 +248,8 @@ fn test_midnight_edge_case() {
         test_scrobble("2024-01-02T00:00:00Z"),
     ];
 
-    let report = build_heatmap_from_scrobbles(scrobbles, Tz::UTC, false, None, None).unwrap();
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
 
     // Both should be in hour 0
     let hour_0 = report
@@ -241,7 +269,8 @@ fn test_different_timezones() {
 
     // UTC: should be Monday 12:00
     let report_utc =
-        build_heatmap_from_scrobbles(scrobbles.clone(), Tz::UTC, false, None, None).unwrap();
+        build_heatmap_from_scrobbles(scrobbles.clone(), Tz::UTC, None, None, None, Locale::En)
+            .unwrap();
     let heatmap_utc = report_utc.heatmap.as_ref().unwrap();
     let utc_cell = heatmap_utc.iter().find(|c| c.weekday == 0 && c.hour == 12);
     assert!(utc_cell.is_some());
@@ -250,7 +279,8 @@ fn test_different_timezones() {
     // Tokyo (UTC+9): should be Monday 21:00
     let tz_tokyo: Tz = "Asia/Tokyo".parse().unwrap();
     let report_tokyo =
-        build_heatmap_from_scrobbles(scrobbles.clone(), tz_tokyo, false, None, None).unwrap();
+        build_heatmap_from_scrobbles(scrobbles.clone(), tz_tokyo, None, None, None, Locale::En)
+            .unwrap();
     let heatmap_tokyo = report_tokyo.heatmap.as_ref().unwrap();
     let tokyo_cell = heatmap_tokyo
         .iter()
@@ -260,9 +290,104 @@ fn test_different_timezones() {
 
     // Los Angeles (UTC-8): should be Monday 04:00
     let tz_la: Tz = "America/Los_Angeles".parse().unwrap();
-    let report_la = build_heatmap_from_scrobbles(scrobbles, tz_la, false, None, None).unwrap();
+    let report_la =
+        build_heatmap_from_scrobbles(scrobbles, tz_la, None, None, None, Locale::En).unwrap();
     let heatmap_la = report_la.heatmap.as_ref().unwrap();
     let la_cell = heatmap_la.iter().find(|c| c.weekday == 0 && c.hour == 4);
     assert!(la_cell.is_some());
     assert_eq!(la_cell.unwrap().count, 1);
 }
+
+#[test]
+fn test_heatmap_normalization_uses_data_span_without_range() {
+    let scrobbles = vec![
+        test_scrobble("2024-01-01T09:00:00Z"),
+        test_scrobble("2024-01-08T09:00:00Z"),
+        test_scrobble("2024-01-15T09:00:00Z"),
+        test_scrobble("2024-01-29T09:00:00Z"),
+    ];
+
+    let report = build_heatmap_from_scrobbles(
+        scrobbles,
+        Tz::UTC,
+        Some(Normalization::Week),
+        None,
+        None,
+        Locale::En,
+    )
+    .unwrap();
+
+    // Four weeks of history, not a single week
+    assert_eq!(report.summary.as_ref().unwrap().weeks_in_range, 4);
+    let monday_9am = report
+        .heatmap
+        .as_ref()
+        .unwrap()
+        .iter()
+        .find(|c| c.weekday == 0 && c.hour == 9)
+        .unwrap();
+    assert_eq!(monday_9am.normalized, 1.0);
+}
+
+#[test]
+fn test_heatmap_weekday_normalization() {
+    // Jan 1-10, 2024: two Mondays, one Sunday
+    let scrobbles = vec![
+        test_scrobble("2024-01-01T09:00:00Z"),
+        test_scrobble("2024-01-07T09:00:00Z"),
+        test_scrobble("2024-01-07T09:30:00Z"),
+        test_scrobble("2024-01-08T09:00:00Z"),
+    ];
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2024, 1, 11, 0, 0, 0).unwrap();
+
+    let report = build_heatmap_from_scrobbles(
+        scrobbles,
+        Tz::UTC,
+        Some(Normalization::Weekday),
+        Some(start),
+        Some(end),
+        Locale::En,
+    )
+    .unwrap();
+
+    let cell = |weekday: u32| {
+        report
+            .heatmap
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|c| c.weekday == weekday && c.hour == 9)
+            .unwrap()
+            .normalized
+    };
+    assert_eq!(cell(0), 1.0);
+    assert_eq!(cell(6), 2.0);
+    assert_eq!(report.normalization, Some(Normalization::Weekday));
+}
+
+#[test]
+fn test_count_weekdays() {
+    let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(); // Monday
+    let last = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(); // Wednesday
+
+    assert_eq!(count_weekdays(first, last), [2, 2, 2, 1, 1, 1, 1]);
+    assert_eq!(count_weekdays(first, first), [1; 7]);
+}
+
+#[test]
+fn test_heatmap_schema_versions() {
+    use crate::reports::schema::render;
+
+    let scrobbles = vec![test_scrobble("2024-01-01T09:00:00Z")];
+    let report =
+        build_heatmap_from_scrobbles(scrobbles, Tz::UTC, None, None, None, Locale::En).unwrap();
+
+    let current = render(&report, 2).unwrap();
+    assert!(current.get("heatmap").is_none());
+    assert!(current.get("grid").is_some());
+
+    let v1 = render(&report, 1).unwrap();
+    assert_eq!(v1["schema_version"], 1);
+    assert_eq!(v1["heatmap"].as_array().unwrap().len(), 7 * 24);
+}
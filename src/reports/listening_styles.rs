@@ -0,0 +1,165 @@
+use crate::db::DbPool;
+use crate::reports::period::Granularity;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use crate::reports::sessions::{SessionStyle, classify_session, for_each_session};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Sessions of each listening style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleCounts {
+    pub album: i64,
+    pub deep_dive: i64,
+    pub shuffle: i64,
+}
+
+impl StyleCounts {
+    fn add(&mut self, style: SessionStyle) {
+        match style {
+            SessionStyle::Album => self.album += 1,
+            SessionStyle::DeepDive => self.deep_dive += 1,
+            SessionStyle::Shuffle => self.shuffle += 1,
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.album + self.deep_dive + self.shuffle
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StylePeriod {
+    pub period: String,
+    #[serde(flatten)]
+    pub sessions: StyleCounts,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListeningStylesSummary {
+    #[serde(flatten)]
+    pub sessions: StyleCounts,
+    /// Sessions too short to classify
+    pub unclassified: i64,
+    pub dominant_style: Option<SessionStyle>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListeningStylesReport {
+    pub schema_version: u32,
+    pub periods: Vec<StylePeriod>,
+    pub summary: ListeningStylesSummary,
+}
+
+impl VersionedReport for ListeningStylesReport {}
+
+/// Classify every session as album listening, an artist deep-dive or shuffle,
+/// counted per period of its start
+pub fn generate_listening_styles_report(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    granularity: Granularity,
+    gap_minutes: i64,
+) -> Result<ListeningStylesReport> {
+    let mut periods: BTreeMap<String, StyleCounts> = BTreeMap::new();
+    let mut total = StyleCounts::default();
+    let mut unclassified = 0;
+
+    for_each_session(
+        pool,
+        start.unwrap_or(DateTime::UNIX_EPOCH),
+        end.unwrap_or_else(Utc::now),
+        gap_minutes,
+        |session| {
+            let Some(style) = classify_session(&session) else {
+                unclassified += 1;
+                return;
+            };
+            periods
+                .entry(granularity.format_period(&session[0].timestamp))
+                .or_default()
+                .add(style);
+            total.add(style);
+        },
+    )?;
+
+    let dominant_style = [
+        (SessionStyle::Album, total.album),
+        (SessionStyle::DeepDive, total.deep_dive),
+        (SessionStyle::Shuffle, total.shuffle),
+    ]
+    .into_iter()
+    .filter(|(_, count)| *count > 0)
+    .max_by_key(|(_, count)| *count)
+    .map(|(style, _)| style);
+
+    Ok(ListeningStylesReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        periods: periods
+            .into_iter()
+            .map(|(period, sessions)| StylePeriod { period, sessions })
+            .collect(),
+        summary: ListeningStylesSummary {
+            sessions: total,
+            unclassified,
+            dominant_style,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+    use chrono::Duration;
+
+    #[test]
+    fn test_styles_per_month() {
+        let (pool, _temp_file) = setup_pool();
+
+        let play = |at: DateTime<Utc>, artists: &[&str], album: Option<&str>| {
+            for (i, artist) in artists.iter().enumerate() {
+                let mut scrobble = Scrobble::new(
+                    artist.to_string(),
+                    format!("Track {}", i),
+                    at + Duration::minutes(i as i64 * 4),
+                    "lastfm".to_string(),
+                );
+                if let Some(album) = album {
+                    scrobble = scrobble.with_album(album.to_string());
+                }
+                crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+            }
+        };
+        let day = |s: &str| format!("{}T20:00:00Z", s).parse::<DateTime<Utc>>().unwrap();
+
+        play(day("2024-01-05"), &["Low"; 6], Some("Secret Name"));
+        play(day("2024-01-12"), &["Low", "Low", "Low", "Slint"], None);
+        play(
+            day("2024-02-02"),
+            &["Low", "Slint", "Codeine", "Bedhead"],
+            None,
+        );
+        play(day("2024-02-09"), &["Low", "Slint"], None);
+
+        let report =
+            generate_listening_styles_report(&pool, None, None, Granularity::Month, 45).unwrap();
+
+        assert_eq!(report.periods.len(), 2);
+        assert_eq!(report.periods[0].period, "2024-01");
+        assert_eq!(
+            report.periods[0].sessions,
+            StyleCounts {
+                album: 1,
+                deep_dive: 1,
+                shuffle: 0
+            }
+        );
+        assert_eq!(report.periods[1].sessions.shuffle, 1);
+        assert_eq!(report.summary.sessions.total(), 3);
+        assert_eq!(report.summary.unclassified, 1);
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Display;
+
+/// Language of the text reports generate: milestone titles and
+/// descriptions, weekday names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    De,
+    Es,
+}
+
+impl Locale {
+    /// Locale for a language tag such as "fr" or "de-AT", English for
+    /// languages without a catalog
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "fr" => Locale::Fr,
+            "de" => Locale::De,
+            "es" => Locale::Es,
+            _ => Locale::En,
+        }
+    }
+
+    /// Name of a weekday, 0 being Monday
+    pub fn weekday(&self, weekday: u32) -> &'static str {
+        let names = match self {
+            Locale::En => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Locale::Fr => [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+            Locale::De => [
+                "Montag",
+                "Dienstag",
+                "Mittwoch",
+                "Donnerstag",
+                "Freitag",
+                "Samstag",
+                "Sonntag",
+            ],
+            Locale::Es => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+        };
+        names[weekday as usize % 7]
+    }
+
+    /// Catalog text for `id`, in English when this locale lacks it
+    pub fn text(&self, id: &str) -> &'static str {
+        lookup(self.catalog(), id)
+            .or_else(|| lookup(EN, id))
+            .unwrap_or_default()
+    }
+
+    /// Catalog text for `id` with each `{name}` replaced by its argument
+    pub fn format(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+        fill(self.text(id), args)
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => EN,
+            Locale::Fr => FR,
+            Locale::De => DE,
+            Locale::Es => ES,
+        }
+    }
+}
+
+/// `text` with each `{name}` replaced by its argument
+pub fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+fn lookup(catalog: &[(&str, &'static str)], id: &str) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(key, _)| *key == id)
+        .map(|(_, text)| *text)
+}
+
+const EN: &[(&str, &str)] = &[
+    ("marathon.title", "Music Marathon"),
+    (
+        "marathon.description",
+        "You listened to {value} hours of music",
+    ),
+    ("marathon.value", "{value} hours"),
+    ("top_artist.title", "Your #1 Artist"),
+    ("top_artist.description", "You played {value} songs"),
+    ("top_artist.value", "{artist}"),
+    ("explorer.title", "Explorer"),
+    ("explorer.description", "You discovered {value} new artists"),
+    ("explorer.value", "{value} artists"),
+    ("night_owl.title", "Night Owl"),
+    (
+        "night_owl.description",
+        "Most of your listening happens after 8 PM",
+    ),
+    ("night_owl.value", "{value}% night listening"),
+    ("early_bird.title", "Early Bird"),
+    ("early_bird.description", "You love morning music sessions"),
+    ("early_bird.value", "{value}% morning listening"),
+    ("long_session.title", "Marathon Listener"),
+    ("long_session.description", "Your longest listening session"),
+    ("long_session.value", "{value} minutes"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("marathon.title", "Marathon musical"),
+    (
+        "marathon.description",
+        "Vous avez écouté {value} heures de musique",
+    ),
+    ("marathon.value", "{value} heures"),
+    ("top_artist.title", "Votre artiste n°1"),
+    ("top_artist.description", "Vous avez écouté {value} titres"),
+    ("top_artist.value", "{artist}"),
+    ("explorer.title", "Explorateur"),
+    (
+        "explorer.description",
+        "Vous avez découvert {value} nouveaux artistes",
+    ),
+    ("explorer.value", "{value} artistes"),
+    ("night_owl.title", "Oiseau de nuit"),
+    (
+        "night_owl.description",
+        "Vous écoutez surtout de la musique après 20 h",
+    ),
+    ("night_owl.value", "{value} % d'écoute nocturne"),
+    ("early_bird.title", "Lève-tôt"),
+    (
+        "early_bird.description",
+        "Vous aimez écouter de la musique le matin",
+    ),
+    ("early_bird.value", "{value} % d'écoute matinale"),
+    ("long_session.title", "Écoute marathon"),
+    (
+        "long_session.description",
+        "Votre plus longue session d'écoute",
+    ),
+    ("long_session.value", "{value} minutes"),
+];
+
+const DE: &[(&str, &str)] = &[
+    ("marathon.title", "Musikmarathon"),
+    (
+        "marathon.description",
+        "Du hast {value} Stunden Musik gehört",
+    ),
+    ("marathon.value", "{value} Stunden"),
+    ("top_artist.title", "Deine Nr. 1"),
+    ("top_artist.description", "Du hast {value} Songs gehört"),
+    ("top_artist.value", "{artist}"),
+    ("explorer.title", "Entdecker"),
+    (
+        "explorer.description",
+        "Du hast {value} neue Künstler entdeckt",
+    ),
+    ("explorer.value", "{value} Künstler"),
+    ("night_owl.title", "Nachteule"),
+    (
+        "night_owl.description",
+        "Du hörst meistens nach 20 Uhr Musik",
+    ),
+    ("night_owl.value", "{value} % nachts gehört"),
+    ("early_bird.title", "Frühaufsteher"),
+    (
+        "early_bird.description",
+        "Du hörst am liebsten morgens Musik",
+    ),
+    ("early_bird.value", "{value} % morgens gehört"),
+    ("long_session.title", "Dauerhörer"),
+    ("long_session.description", "Deine längste Hörsession"),
+    ("long_session.value", "{value} Minuten"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("marathon.title", "Maratón musical"),
+    ("marathon.description", "Escuchaste {value} horas de música"),
+    ("marathon.value", "{value} horas"),
+    ("top_artist.title", "Tu artista n.º 1"),
+    ("top_artist.description", "Escuchaste {value} canciones"),
+    ("top_artist.value", "{artist}"),
+    ("explorer.title", "Explorador"),
+    (
+        "explorer.description",
+        "Descubriste {value} artistas nuevos",
+    ),
+    ("explorer.value", "{value} artistas"),
+    ("night_owl.title", "Noctámbulo"),
+    (
+        "night_owl.description",
+        "Escuchas casi toda tu música después de las 20:00",
+    ),
+    ("night_owl.value", "{value} % de escucha nocturna"),
+    ("early_bird.title", "Madrugador"),
+    (
+        "early_bird.description",
+        "Te encanta escuchar música por la mañana",
+    ),
+    ("early_bird.value", "{value} % de escucha matinal"),
+    ("long_session.title", "Escucha maratoniana"),
+    ("long_session.description", "Tu sesión de escucha más larga"),
+    ("long_session.value", "{value} minutos"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("fr"), Locale::Fr);
+        assert_eq!(Locale::from_tag("de-AT"), Locale::De);
+        assert_eq!(Locale::from_tag("ES_mx"), Locale::Es);
+        assert_eq!(Locale::from_tag("pt-BR"), Locale::En);
+        assert_eq!(Locale::from_tag(""), Locale::En);
+    }
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        for locale in [Locale::Fr, Locale::De, Locale::Es] {
+            for (id, english) in EN {
+                let text = lookup(locale.catalog(), id);
+                assert!(text.is_some(), "{:?} lacks {}", locale, id);
+                // Translations keep every placeholder
+                for placeholder in english.split('{').skip(1) {
+                    let name = placeholder.split('}').next().unwrap();
+                    assert!(
+                        text.unwrap().contains(&format!("{{{}}}", name)),
+                        "{:?} {} lacks {{{}}}",
+                        locale,
+                        id,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Locale::Fr.format("marathon.value", &[("value", &12)]),
+            "12 heures"
+        );
+        assert_eq!(Locale::En.weekday(6), "Sunday");
+        assert_eq!(Locale::De.weekday(0), "Montag");
+        assert_eq!(Locale::Es.text("no.such.message"), "");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::models::{MilestoneMetric, MilestoneRule};
+use crate::reports::locale::{Locale, fill};
+use crate::reports::yearly::{Milestone, MilestoneIcon};
+use chrono::{Datelike, Months, NaiveDate};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+
+/// Figures of a year that milestone rules are checked against
+#[derive(Debug, Default)]
+pub struct YearFigures {
+    pub total_hours: i64,
+    pub total_scrobbles: i64,
+    pub top_artist: Option<String>,
+    pub top_artist_plays: i64,
+    pub new_artists: i64,
+    pub new_tracks: i64,
+    pub night_owl_score: f64,
+    pub early_bird_score: f64,
+    pub longest_session_minutes: i64,
+    pub longest_streak_days: i64,
+    pub full_months: i64,
+}
+
+impl YearFigures {
+    pub fn value(&self, metric: MilestoneMetric) -> f64 {
+        match metric {
+            MilestoneMetric::TotalHours => self.total_hours as f64,
+            MilestoneMetric::TotalScrobbles => self.total_scrobbles as f64,
+            MilestoneMetric::TopArtistPlays => self.top_artist_plays as f64,
+            MilestoneMetric::NewArtists => self.new_artists as f64,
+            MilestoneMetric::NewTracks => self.new_tracks as f64,
+            MilestoneMetric::NightOwlScore => self.night_owl_score,
+            MilestoneMetric::EarlyBirdScore => self.early_bird_score,
+            MilestoneMetric::LongestSessionMinutes => self.longest_session_minutes as f64,
+            MilestoneMetric::LongestStreakDays => self.longest_streak_days as f64,
+            MilestoneMetric::FullMonths => self.full_months as f64,
+        }
+    }
+
+    /// Set the figures taken from the days something was played
+    pub fn with_active_days(mut self, days: &BTreeSet<NaiveDate>) -> Self {
+        self.longest_streak_days = crate::reports::consistency::longest_streak(days);
+        self.full_months = full_months(days);
+        self
+    }
+}
+
+/// Milestone every year in review checks, with its text under `key` in the
+/// locale catalogs
+struct BuiltinRule {
+    key: &'static str,
+    metric: MilestoneMetric,
+    threshold: f64,
+    icon: MilestoneIcon,
+}
+
+const BUILTIN_RULES: [BuiltinRule; 6] = [
+    BuiltinRule {
+        key: "marathon",
+        metric: MilestoneMetric::TotalHours,
+        threshold: 0.0,
+        icon: MilestoneIcon::Stopwatch,
+    },
+    BuiltinRule {
+        key: "top_artist",
+        metric: MilestoneMetric::TopArtistPlays,
+        threshold: 1.0,
+        icon: MilestoneIcon::Microphone,
+    },
+    BuiltinRule {
+        key: "explorer",
+        metric: MilestoneMetric::NewArtists,
+        threshold: 0.0,
+        icon: MilestoneIcon::Map,
+    },
+    // Night and morning hours don't overlap, so at most one of these two
+    BuiltinRule {
+        key: "night_owl",
+        metric: MilestoneMetric::NightOwlScore,
+        threshold: 60.0,
+        icon: MilestoneIcon::Owl,
+    },
+    BuiltinRule {
+        key: "early_bird",
+        metric: MilestoneMetric::EarlyBirdScore,
+        threshold: 60.0,
+        icon: MilestoneIcon::Bird,
+    },
+    BuiltinRule {
+        key: "long_session",
+        metric: MilestoneMetric::LongestSessionMinutes,
+        threshold: 180.0,
+        icon: MilestoneIcon::Runner,
+    },
+];
+
+/// Milestones whose metric reached their threshold: the built-in ones in
+/// `locale`, then the enabled custom `rules`
+pub fn award_milestones(
+    figures: &YearFigures,
+    rules: &[MilestoneRule],
+    locale: Locale,
+) -> Vec<Milestone> {
+    let artist = figures.top_artist.as_deref().unwrap_or_default();
+    let reached = |metric, threshold| figures.value(metric) >= threshold;
+    // Scores and counts alike read as whole numbers
+    let shown = |metric| figures.value(metric) as i64;
+
+    let builtin = BUILTIN_RULES
+        .iter()
+        .filter(|rule| reached(rule.metric, rule.threshold))
+        .map(|rule| {
+            let value = shown(rule.metric);
+            let args: [(&str, &dyn Display); 2] = [("value", &value), ("artist", &artist)];
+            Milestone {
+                key: rule.key.to_string(),
+                title: locale.format(&format!("{}.title", rule.key), &args),
+                description: locale.format(&format!("{}.description", rule.key), &args),
+                value: locale.format(&format!("{}.value", rule.key), &args),
+                icon: rule.icon,
+            }
+        });
+
+    let custom = rules
+        .iter()
+        .filter(|rule| rule.enabled && reached(rule.metric, rule.threshold))
+        .map(|rule| {
+            let value = shown(rule.metric);
+            let args: [(&str, &dyn Display); 2] = [("value", &value), ("artist", &artist)];
+            Milestone {
+                key: format!("rule:{}", rule.id.unwrap_or_default()),
+                title: fill(&rule.title, &args),
+                description: fill(&rule.description, &args),
+                value: value.to_string(),
+                icon: rule.icon,
+            }
+        });
+
+    builtin.chain(custom).collect()
+}
+
+/// Months with every one of their days in `days`
+fn full_months(days: &BTreeSet<NaiveDate>) -> i64 {
+    let mut per_month: HashMap<NaiveDate, i64> = HashMap::new();
+    for day in days {
+        *per_month
+            .entry(day.with_day(1).unwrap_or(*day))
+            .or_insert(0) += 1;
+    }
+    per_month
+        .into_iter()
+        .filter(|(first, count)| {
+            first
+                .checked_add_months(Months::new(1))
+                .is_some_and(|next| (next - *first).num_days() == *count)
+        })
+        .count() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figures() -> YearFigures {
+        YearFigures {
+            total_hours: 120,
+            total_scrobbles: 2057,
+            top_artist: Some("Low".to_string()),
+            top_artist_plays: 1204,
+            new_artists: 12,
+            night_owl_score: 72.5,
+            early_bird_score: 10.0,
+            longest_session_minutes: 95,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_milestones() {
+        let milestones = award_milestones(&figures(), &[], Locale::En);
+        let icons: Vec<MilestoneIcon> = milestones.iter().map(|m| m.icon).collect();
+        assert_eq!(
+            icons,
+            vec![
+                MilestoneIcon::Stopwatch,
+                MilestoneIcon::Microphone,
+                MilestoneIcon::Map,
+                MilestoneIcon::Owl,
+            ]
+        );
+        assert_eq!(milestones[0].value, "120 hours");
+        assert_eq!(milestones[1].value, "Low");
+        assert_eq!(milestones[1].description, "You played 1204 songs");
+        assert_eq!(milestones[3].value, "72% night listening");
+
+        // Nothing played yet: no top artist to speak of
+        let quiet = award_milestones(&YearFigures::default(), &[], Locale::En);
+        assert!(quiet.iter().all(|m| m.icon != MilestoneIcon::Microphone));
+    }
+
+    #[test]
+    fn test_custom_milestones() {
+        let mut devoted = MilestoneRule::new(
+            "Devoted".to_string(),
+            "{value} plays of {artist}".to_string(),
+            MilestoneMetric::TopArtistPlays,
+            1000.0,
+            MilestoneIcon::Trophy,
+        );
+        let every_day = MilestoneRule::new(
+            "Every Single Day".to_string(),
+            "You listened every day of a month".to_string(),
+            MilestoneMetric::FullMonths,
+            1.0,
+            MilestoneIcon::Calendar,
+        );
+
+        let milestones = award_milestones(
+            &figures(),
+            &[devoted.clone(), every_day.clone()],
+            Locale::En,
+        );
+        let devoted_milestone = milestones.last().unwrap();
+        assert_eq!(devoted_milestone.title, "Devoted");
+        assert_eq!(devoted_milestone.description, "1204 plays of Low");
+        assert_eq!(devoted_milestone.value, "1204");
+        assert_eq!(devoted_milestone.icon, MilestoneIcon::Trophy);
+
+        devoted.enabled = false;
+        let figures = figures().with_active_days(
+            &(1..=29)
+                .map(|day| NaiveDate::from_ymd_opt(2024, 2, day).unwrap())
+                .collect(),
+        );
+        let milestones = award_milestones(&figures, &[devoted, every_day], Locale::En);
+        assert_eq!(milestones.last().unwrap().title, "Every Single Day");
+        assert!(milestones.iter().all(|m| m.title != "Devoted"));
+    }
+
+    #[test]
+    fn test_full_months() {
+        let days: BTreeSet<NaiveDate> = (1..=30)
+            .map(|day| NaiveDate::from_ymd_opt(2024, 4, day).unwrap())
+            .chain((1..=30).map(|day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap()))
+            .collect();
+        // All of April; May lacks the 31st
+        assert_eq!(full_months(&days), 1);
+        assert_eq!(full_months(&BTreeSet::new()), 0);
+    }
+}
@@ -1,26 +1,54 @@
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::db::DbPool;
+use schema::{REPORT_SCHEMA_VERSION, VersionedReport, remove_keys};
+use serde_json::{Map, Value};
 
+pub mod album_listens;
+pub mod calendar;
+pub mod compare;
+pub mod consistency;
 pub mod diversity;
+pub mod half_life;
 pub mod heatmap;
+pub mod listening_styles;
+pub mod locale;
+pub mod milestones;
+pub mod movement;
 pub mod novelty;
+pub mod period;
+pub mod ratings;
+pub mod records;
+pub mod schema;
+pub mod sessions;
+pub mod skips;
+pub mod sleep;
+pub mod tenure;
 pub mod transitions;
 pub mod yearly;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Report {
+    pub schema_version: u32,
     pub period: String,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
+    /// Number of calendar days between start and end, inclusive
+    pub span_days: i64,
     pub total_scrobbles: i64,
     pub top_artists: Vec<(String, i64)>,
     pub top_tracks: Vec<(String, String, i64)>,
     pub top_albums: Vec<(String, String, i64)>,
 }
 
+impl VersionedReport for Report {
+    fn downgrade_to_v1(object: &mut Map<String, Value>) {
+        remove_keys(object, &["span_days"]);
+    }
+}
+
 pub fn generate_yearly_report(pool: &DbPool, year: i32) -> Result<Report> {
     if !(1970..=2100).contains(&year) {
         return Err(anyhow::anyhow!("Year must be between 1970 and 2100"));
@@ -39,33 +67,49 @@ pub fn generate_yearly_report(pool: &DbPool, year: i32) -> Result<Report> {
 }
 
 pub fn generate_monthly_report(pool: &DbPool, year: i32, month: u32) -> Result<Report> {
-    if !(1970..=2100).contains(&year) {
-        return Err(anyhow::anyhow!("Year must be between 1970 and 2100"));
-    }
     if !(1..=12).contains(&month) {
         return Err(anyhow::anyhow!("Month must be between 1 and 12"));
     }
 
+    let (start_date, end_date) = month_span(year, month, 1)?;
+    generate_report(pool, start_date, end_date, format!("{}-{:02}", year, month))
+}
+
+pub fn generate_quarterly_report(pool: &DbPool, year: i32, quarter: u32) -> Result<Report> {
+    if !(1..=4).contains(&quarter) {
+        return Err(anyhow::anyhow!("Quarter must be between 1 and 4"));
+    }
+
+    let (start_date, end_date) = month_span(year, (quarter - 1) * 3 + 1, 3)?;
+    generate_report(pool, start_date, end_date, format!("{}-Q{}", year, quarter))
+}
+
+pub fn generate_half_year_report(pool: &DbPool, year: i32, half: u32) -> Result<Report> {
+    if !(1..=2).contains(&half) {
+        return Err(anyhow::anyhow!("Half must be 1 or 2"));
+    }
+
+    let (start_date, end_date) = month_span(year, (half - 1) * 6 + 1, 6)?;
+    generate_report(pool, start_date, end_date, format!("{}-H{}", year, half))
+}
+
+/// Range covering `months` months starting at `first_month`, ending one
+/// second before the following month starts
+fn month_span(year: i32, first_month: u32, months: u32) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    if !(1970..=2100).contains(&year) {
+        return Err(anyhow::anyhow!("Year must be between 1970 and 2100"));
+    }
+
     let start_date = chrono::Utc
-        .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .with_ymd_and_hms(year, first_month, 1, 0, 0, 0)
         .single()
         .ok_or_else(|| anyhow::anyhow!("Invalid start date"))?;
 
-    let next_month = if month == 12 {
-        chrono::Utc
-            .with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
-            .single()
-            .ok_or_else(|| anyhow::anyhow!("Invalid next month date"))?
-    } else {
-        chrono::Utc
-            .with_ymd_and_hms(year, month + 1, 1, 0, 0, 0)
-            .single()
-            .ok_or_else(|| anyhow::anyhow!("Invalid next month date"))?
-    };
-
-    let end_date = next_month - Duration::seconds(1);
+    let next_period = start_date
+        .checked_add_months(Months::new(months))
+        .ok_or_else(|| anyhow::anyhow!("Invalid end date"))?;
 
-    generate_report(pool, start_date, end_date, format!("{}-{:02}", year, month))
+    Ok((start_date, next_period - Duration::seconds(1)))
 }
 
 pub fn generate_last_month_report(pool: &DbPool) -> Result<Report> {
@@ -80,11 +124,8 @@ pub fn generate_last_month_report(pool: &DbPool) -> Result<Report> {
 }
 
 pub fn generate_all_time_report(pool: &DbPool) -> Result<Report> {
-    let start_date = chrono::Utc
-        .with_ymd_and_hms(2000, 1, 1, 0, 0, 0)
-        .single()
-        .ok_or_else(|| anyhow::anyhow!("Invalid start date"))?;
     let end_date = Utc::now();
+    let start_date = crate::db::get_first_scrobble_timestamp(pool)?.unwrap_or(end_date);
 
     generate_report(pool, start_date, end_date, "All Time".to_string())
 }
@@ -95,22 +136,24 @@ fn generate_report(
     end_date: DateTime<Utc>,
     period: String,
 ) -> Result<Report> {
-    let top_artists = crate::db::get_top_artists(pool, 50, Some(start_date), Some(end_date))?;
-    let top_tracks = crate::db::get_top_tracks(pool, 50, Some(start_date), Some(end_date))?;
-    let top_albums = crate::db::get_top_albums(pool, 50, Some(start_date), Some(end_date))?;
-
-    // Get actual total scrobbles for the period
-    let conn = pool.get()?;
-    let total_scrobbles: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM scrobbles WHERE timestamp >= ?1 AND timestamp <= ?2",
-        rusqlite::params![start_date.timestamp(), end_date.timestamp()],
-        |row| row.get(0),
-    )?;
+    let (top_artists, top_tracks, top_albums, total_scrobbles) =
+        crate::db::with_read_txn(pool, |conn| {
+            let start = Some(start_date);
+            let end = Some(end_date);
+            Ok((
+                crate::db::query_top_artists(conn, 50, start, end)?,
+                crate::db::query_top_tracks(conn, 50, start, end)?,
+                crate::db::query_top_albums(conn, 50, start, end)?,
+                crate::db::query_scrobbles_count_in_range(conn, start, end)?,
+            ))
+        })?;
 
     Ok(Report {
+        schema_version: REPORT_SCHEMA_VERSION,
         period,
         start_date,
         end_date,
+        span_days: (end_date.date_naive() - start_date.date_naive()).num_days() + 1,
         total_scrobbles,
         top_artists,
         top_tracks,
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::db::Standing;
+
+/// Where a top entry stands against the previous period. The previous fields
+/// are None when there's no previous period (all time), and a previous rank
+/// of None with a count marks an entry new to the chart
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Movement {
+    pub rank: i64,
+    pub previous_count: Option<i64>,
+    pub count_change: Option<i64>,
+    pub previous_rank: Option<i64>,
+    /// Positive when the entry climbed
+    pub rank_change: Option<i64>,
+}
+
+/// The stretch before `start`..`end` to compare it with. Calendar periods
+/// ("today", "month", "year") compare with the same elapsed part of the
+/// previous day, month or year; anything else with the same length right
+/// before it
+pub fn previous_range(
+    period: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let elapsed = end - start;
+    let previous_start = match period {
+        "today" => start - Duration::days(1),
+        "month" => start.checked_sub_months(Months::new(1))?,
+        "year" => start.checked_sub_months(Months::new(12))?,
+        _ => start - elapsed - Duration::seconds(1),
+    };
+    // A shorter previous month can't run past the current one's start
+    let previous_end = (previous_start + elapsed).min(start - Duration::seconds(1));
+    Some((previous_start, previous_end))
+}
+
+/// Movements of a top list ordered by count, against `previous` standings
+pub fn movements<K: Eq + Hash>(
+    current: &[(K, i64)],
+    previous: Option<&HashMap<K, Standing>>,
+) -> Vec<Movement> {
+    current
+        .iter()
+        .map(|(key, count)| {
+            // Same ranking as the previous standings: ties share a rank
+            let rank = 1 + current.iter().filter(|(_, c)| c > count).count() as i64;
+            let Some(previous) = previous else {
+                return Movement {
+                    rank,
+                    ..Movement::default()
+                };
+            };
+            let standing = previous.get(key);
+            let previous_count = standing.map(|s| s.count).unwrap_or(0);
+            Movement {
+                rank,
+                previous_count: Some(previous_count),
+                count_change: Some(count - previous_count),
+                previous_rank: standing.map(|s| s.rank),
+                rank_change: standing.map(|s| s.rank - rank),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DbPool;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn play(pool: &DbPool, artist: &str, times: i64, at: DateTime<Utc>) {
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                artist.to_string(),
+                "Track".to_string(),
+                at + Duration::minutes(i * 5),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_movements_against_previous_week() {
+        let (pool, _temp_file) = setup_pool();
+        let end: DateTime<Utc> = "2024-06-15T00:00:00Z".parse().unwrap();
+        let start = end - Duration::days(7);
+        let (previous_start, previous_end) = previous_range("week", start, end).unwrap();
+        assert_eq!(previous_end, start - Duration::seconds(1));
+
+        play(&pool, "Low", 5, previous_start + Duration::days(1));
+        play(&pool, "Slint", 3, previous_start + Duration::days(2));
+        play(&pool, "Codeine", 1, previous_start + Duration::days(3));
+        play(&pool, "Slint", 6, start + Duration::days(1));
+        play(&pool, "Low", 2, start + Duration::days(2));
+        play(&pool, "Bedhead", 2, start + Duration::days(3));
+
+        let top = crate::db::get_top_artists(&pool, 10, Some(start), Some(end)).unwrap();
+        let names: Vec<String> = top.iter().map(|(name, _)| name.clone()).collect();
+        let conn = pool.get().unwrap();
+        let standings = crate::db::query_artist_standings(
+            &conn,
+            crate::credits::ArtistCredit::Full,
+            previous_start,
+            previous_end,
+            &names,
+        )
+        .unwrap();
+        assert_eq!(standings.len(), 2);
+        assert_eq!(standings["Slint"], Standing { count: 3, rank: 2 });
+
+        let artists = movements(&top, Some(&standings));
+        assert_eq!(
+            artists[0],
+            Movement {
+                rank: 1,
+                previous_count: Some(3),
+                count_change: Some(3),
+                previous_rank: Some(2),
+                rank_change: Some(1),
+            }
+        );
+        // Low and Bedhead tie for second; Bedhead is new
+        let low = top.iter().position(|(name, _)| name == "Low").unwrap();
+        assert_eq!(artists[low].rank, 2);
+        assert_eq!(artists[low].rank_change, Some(-1));
+        assert_eq!(artists[low].count_change, Some(-3));
+        let bedhead = top.iter().position(|(name, _)| name == "Bedhead").unwrap();
+        assert_eq!(artists[bedhead].previous_rank, None);
+        assert_eq!(artists[bedhead].previous_count, Some(0));
+
+        assert_eq!(
+            movements(&top, None)[0],
+            Movement {
+                rank: 1,
+                ..Movement::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_month_compares_with_same_elapsed_part() {
+        let start: DateTime<Utc> = "2024-03-01T00:00:00Z".parse().unwrap();
+        let (previous_start, previous_end) =
+            previous_range("month", start, start + Duration::days(10)).unwrap();
+        assert_eq!(
+            previous_start,
+            "2024-02-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            previous_end,
+            "2024-02-11T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        let (_, previous_end) = previous_range("month", start, start + Duration::days(30)).unwrap();
+        assert_eq!(previous_end, start - Duration::seconds(1));
+    }
+}
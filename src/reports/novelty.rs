@@ -1,5 +1,7 @@
 use crate::db::DbPool;
 use crate::models::Scrobble;
+use crate::reports::period::Granularity;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -7,6 +9,7 @@ use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NoveltyReport {
+    pub schema_version: u32,
     pub timeline: Vec<NoveltyPoint>,
     pub summary: NoveltySummary,
     pub new_artists_discovered: Vec<ArtistDiscovery>,
@@ -41,110 +44,64 @@ pub struct ArtistDiscovery {
     pub total_plays: i64,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Granularity {
-    Day,
-    Week,
-    Month,
-    Year,
-}
-
-impl Granularity {
-    pub fn format_period(&self, dt: &DateTime<Utc>) -> String {
-        match self {
-            Granularity::Day => dt.format("%Y-%m-%d").to_string(),
-            Granularity::Week => dt.format("%Y-W%V").to_string(),
-            Granularity::Month => dt.format("%Y-%m").to_string(),
-            Granularity::Year => dt.format("%Y").to_string(),
-        }
-    }
-}
-
 pub fn generate_novelty_report(
     pool: &DbPool,
     start: Option<DateTime<Utc>>,
     end: Option<DateTime<Utc>>,
     granularity: Granularity,
 ) -> Result<NoveltyReport> {
-    // Fetch all scrobbles in range
-    let mut scrobbles = if let (Some(s), Some(e)) = (start, end) {
-        crate::db::get_scrobbles_in_range(pool, s, e)?
-    } else {
-        crate::db::get_scrobbles(pool, Some(1_000_000), Some(0))?
+    // With a start, anything first heard before it is already known
+    let (mut seen_tracks_ever, mut seen_artists_ever) = match start {
+        Some(s) => (
+            crate::db::get_tracks_first_heard_before(pool, s)?,
+            crate::db::get_artists_first_heard_before(pool, s)?,
+        ),
+        None => (HashSet::new(), HashSet::new()),
     };
-
-    // IMPORTANT: Sort by timestamp ascending (oldest first) to track novelty correctly
-    // Without a date range, get_scrobbles returns DESC order, so we must sort
-    scrobbles.sort_by_key(|s| s.timestamp);
-
-    if scrobbles.is_empty() {
-        return Ok(NoveltyReport {
-            timeline: Vec::new(),
-            summary: NoveltySummary {
-                total_scrobbles: 0,
-                total_unique_tracks: 0,
-                total_unique_artists: 0,
-                avg_novelty_ratio: 0.0,
-                most_exploratory_period: String::new(),
-                least_exploratory_period: String::new(),
-            },
-            new_artists_discovered: Vec::new(),
-        });
-    }
-
-    // Build timeline chronologically, tracking cumulative history
-    let mut timeline = Vec::new();
-    let mut seen_tracks_ever: HashSet<(String, String)> = HashSet::new();
-    let mut seen_artists_ever: HashSet<String> = HashSet::new();
     let mut artist_discoveries: Vec<ArtistDiscovery> = Vec::new();
 
-    // Group scrobbles by period while maintaining order
-    let mut period_groups: Vec<(String, Vec<&Scrobble>)> = Vec::new();
-    let mut current_period: Option<String> = None;
-    let mut current_group: Vec<&Scrobble> = Vec::new();
-
-    for scrobble in &scrobbles {
-        let period = granularity.format_period(&scrobble.timestamp);
-
-        if current_period.as_ref() != Some(&period) {
-            if let Some(p) = current_period {
-                period_groups.push((p, current_group));
-                current_group = Vec::new();
+    // Stream scrobbles oldest first, closing a period when the next begins
+    let mut timeline = Vec::new();
+    let mut current: Option<PeriodNovelty> = None;
+    let mut unique_tracks: HashSet<(String, String)> = HashSet::new();
+    let mut artist_play_counts: HashMap<String, i64> = HashMap::new();
+    let mut total_scrobbles = 0;
+
+    crate::db::for_each_scrobble_in_range(
+        pool,
+        start.unwrap_or(DateTime::<Utc>::MIN_UTC),
+        end.unwrap_or(DateTime::<Utc>::MAX_UTC),
+        |scrobble| {
+            let period = granularity.format_period(&scrobble.timestamp);
+            if current.as_ref().is_some_and(|c| c.period != period) {
+                timeline.extend(current.take().map(PeriodNovelty::finish));
             }
-            current_period = Some(period);
-        }
-        current_group.push(scrobble);
-    }
-
-    // Don't forget the last group
-    if let Some(p) = current_period {
-        period_groups.push((p, current_group));
-    }
-
-    // Process each period chronologically
-    for (period, period_scrobbles) in period_groups {
-        let point = compute_novelty_point_cumulative(
-            period.clone(),
-            &period_scrobbles,
-            &mut seen_tracks_ever,
-            &mut seen_artists_ever,
-            &mut artist_discoveries,
-            granularity,
-        );
-        timeline.push(point);
-    }
-
-    // Compute summary
-    let summary = compute_novelty_summary(&timeline, &scrobbles);
+            current
+                .get_or_insert_with(|| PeriodNovelty::new(period))
+                .add(
+                    &scrobble,
+                    &mut seen_tracks_ever,
+                    &mut seen_artists_ever,
+                    &mut artist_discoveries,
+                );
+
+            total_scrobbles += 1;
+            *artist_play_counts
+                .entry(scrobble.artist.clone())
+                .or_insert(0) += 1;
+            unique_tracks.insert((scrobble.artist, scrobble.track));
+        },
+    )?;
+    timeline.extend(current.map(PeriodNovelty::finish));
+
+    let summary = compute_novelty_summary(
+        &timeline,
+        total_scrobbles,
+        unique_tracks.len() as i64,
+        artist_play_counts.len() as i64,
+    );
 
     // Count total plays for each discovered artist
-    let mut artist_play_counts: HashMap<String, i64> = HashMap::new();
-    for scrobble in &scrobbles {
-        *artist_play_counts
-            .entry(scrobble.artist.clone())
-            .or_insert(0) += 1;
-    }
-
     for discovery in &mut artist_discoveries {
         discovery.total_plays = artist_play_counts
             .get(&discovery.artist)
@@ -159,84 +116,94 @@ pub fn generate_novelty_report(
     artist_discoveries.reverse();
 
     Ok(NoveltyReport {
+        schema_version: REPORT_SCHEMA_VERSION,
         timeline,
         summary,
         new_artists_discovered: artist_discoveries,
     })
 }
 
-fn compute_novelty_point_cumulative(
+/// Novelty of the period being streamed, against everything heard before it
+struct PeriodNovelty {
     period: String,
-    scrobbles: &[&Scrobble],
-    seen_tracks_ever: &mut HashSet<(String, String)>,
-    seen_artists_ever: &mut HashSet<String>,
-    artist_discoveries: &mut Vec<ArtistDiscovery>,
-    _granularity: Granularity,
-) -> NoveltyPoint {
-    let total_scrobbles = scrobbles.len() as i64;
-
-    let mut new_tracks = 0;
-    let mut new_artists = 0;
-
-    for scrobble in scrobbles {
+    total_scrobbles: i64,
+    new_tracks: i64,
+    new_artists: i64,
+    artists: HashSet<String>,
+}
+
+impl PeriodNovelty {
+    fn new(period: String) -> Self {
+        Self {
+            period,
+            total_scrobbles: 0,
+            new_tracks: 0,
+            new_artists: 0,
+            artists: HashSet::new(),
+        }
+    }
+
+    fn add(
+        &mut self,
+        scrobble: &Scrobble,
+        seen_tracks_ever: &mut HashSet<(String, String)>,
+        seen_artists_ever: &mut HashSet<String>,
+        artist_discoveries: &mut Vec<ArtistDiscovery>,
+    ) {
+        self.total_scrobbles += 1;
+
         let track_key = (scrobble.artist.clone(), scrobble.track.clone());
 
         // Check if this is the first time seeing this track EVER
         if !seen_tracks_ever.contains(&track_key) {
-            new_tracks += 1;
+            self.new_tracks += 1;
             seen_tracks_ever.insert(track_key);
         }
 
         // Check if this is the first time seeing this artist EVER
         if !seen_artists_ever.contains(&scrobble.artist) {
-            new_artists += 1;
+            self.new_artists += 1;
             seen_artists_ever.insert(scrobble.artist.clone());
 
             // Record this discovery
             artist_discoveries.push(ArtistDiscovery {
                 artist: scrobble.artist.clone(),
                 first_heard: scrobble.timestamp,
-                period: period.clone(),
+                period: self.period.clone(),
                 total_plays: 0, // Will be counted later
             });
         }
-    }
-
-    let repeat_tracks = total_scrobbles - new_tracks;
-    let repeat_artists = scrobbles
-        .iter()
-        .map(|s| s.artist.as_str())
-        .collect::<HashSet<_>>()
-        .len() as i64
-        - new_artists;
 
-    let novelty_ratio = if total_scrobbles > 0 {
-        new_tracks as f64 / total_scrobbles as f64
-    } else {
-        0.0
-    };
+        if !self.artists.contains(&scrobble.artist) {
+            self.artists.insert(scrobble.artist.clone());
+        }
+    }
 
-    NoveltyPoint {
-        period,
-        total_scrobbles,
-        new_tracks,
-        repeat_tracks,
-        new_artists,
-        repeat_artists,
-        novelty_ratio,
+    fn finish(self) -> NoveltyPoint {
+        let novelty_ratio = if self.total_scrobbles > 0 {
+            self.new_tracks as f64 / self.total_scrobbles as f64
+        } else {
+            0.0
+        };
+
+        NoveltyPoint {
+            period: self.period,
+            total_scrobbles: self.total_scrobbles,
+            new_tracks: self.new_tracks,
+            repeat_tracks: self.total_scrobbles - self.new_tracks,
+            new_artists: self.new_artists,
+            repeat_artists: self.artists.len() as i64 - self.new_artists,
+            novelty_ratio,
+        }
     }
 }
 
-fn compute_novelty_summary(timeline: &[NoveltyPoint], scrobbles: &[Scrobble]) -> NoveltySummary {
-    let total_scrobbles = scrobbles.len() as i64;
-
-    let unique_tracks: HashSet<_> = scrobbles
-        .iter()
-        .map(|s| (s.artist.clone(), s.track.clone()))
-        .collect();
-
-    let unique_artists: HashSet<_> = scrobbles.iter().map(|s| s.artist.clone()).collect();
-
+fn compute_novelty_summary(
+    timeline: &[NoveltyPoint],
+    total_scrobbles: i64,
+    unique_tracks: i64,
+    unique_artists: i64,
+) -> NoveltySummary {
     let avg_novelty_ratio = if !timeline.is_empty() {
         timeline.iter().map(|p| p.novelty_ratio).sum::<f64>() / timeline.len() as f64
     } else {
@@ -257,17 +224,39 @@ fn compute_novelty_summary(timeline: &[NoveltyPoint], scrobbles: &[Scrobble]) ->
 
     NoveltySummary {
         total_scrobbles,
-        total_unique_tracks: unique_tracks.len() as i64,
-        total_unique_artists: unique_artists.len() as i64,
+        total_unique_tracks: unique_tracks,
+        total_unique_artists: unique_artists,
         avg_novelty_ratio,
         most_exploratory_period: most_exploratory,
         least_exploratory_period: least_exploratory,
     }
 }
 
+impl VersionedReport for NoveltyReport {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::MediaType;
+
+    fn compute_novelty_point_cumulative(
+        period: String,
+        scrobbles: &[&Scrobble],
+        seen_tracks_ever: &mut HashSet<(String, String)>,
+        seen_artists_ever: &mut HashSet<String>,
+        artist_discoveries: &mut Vec<ArtistDiscovery>,
+    ) -> NoveltyPoint {
+        let mut point = PeriodNovelty::new(period);
+        for scrobble in scrobbles {
+            point.add(
+                scrobble,
+                seen_tracks_ever,
+                seen_artists_ever,
+                artist_discoveries,
+            );
+        }
+        point.finish()
+    }
 
     fn test_scrobble(timestamp: &str, artist: &str, track: &str) -> Scrobble {
         Scrobble {
@@ -278,6 +267,12 @@ mod tests {
             timestamp: timestamp.parse().unwrap(),
             source: "test".to_string(),
             source_id: None,
+            ms_played: None,
+            skipped: None,
+            raw_metadata: None,
+            media_type: MediaType::Music,
+            source_metadata: None,
+            album_artist: None,
         }
     }
 
@@ -300,7 +295,6 @@ mod tests {
             &mut seen_tracks,
             &mut seen_artists,
             &mut discoveries,
-            Granularity::Day,
         );
 
         assert_eq!(point.total_scrobbles, 3);
@@ -331,7 +325,6 @@ mod tests {
             &mut seen_tracks,
             &mut seen_artists,
             &mut discoveries,
-            Granularity::Day,
         );
 
         assert_eq!(point1.new_tracks, 1);
@@ -345,7 +338,6 @@ mod tests {
             &mut seen_tracks,
             &mut seen_artists,
             &mut discoveries,
-            Granularity::Day,
         );
 
         assert_eq!(point2.new_tracks, 0);
@@ -374,7 +366,6 @@ mod tests {
             &mut seen_tracks,
             &mut seen_artists,
             &mut discoveries,
-            Granularity::Week,
         );
 
         assert_eq!(point1.new_tracks, 2);
@@ -390,7 +381,6 @@ mod tests {
             &mut seen_tracks,
             &mut seen_artists,
             &mut discoveries,
-            Granularity::Week,
         );
 
         assert_eq!(point2.new_tracks, 1); // Only Track 3 is new
@@ -402,13 +392,7 @@ mod tests {
     #[test]
     fn test_novelty_chronological_order() {
         // Integration test: verify novelty decreases over time as expected
-        use crate::db::{create_pool, init_database};
-        use tempfile::NamedTempFile;
-
-        let temp_file = NamedTempFile::new().unwrap();
-        let db_path = temp_file.path().to_str().unwrap();
-        let pool = create_pool(db_path).unwrap();
-        init_database(&pool).unwrap();
+        let (pool, _temp_file) = crate::test_utils::setup_pool();
 
         // Insert scrobbles in a realistic pattern:
         // Month 1: All new tracks
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An ISO 8601 week. Weeks start on Monday and belong to the year holding
+/// their Thursday, so Dec 30, 2024 is in 2025-W01 and Jan 1, 2027 in 2026-W53.
+/// Every report and chart bucketing by week goes through this
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IsoWeek {
+    pub year: i32,
+    pub week: u32,
+}
+
+impl IsoWeek {
+    /// The week containing `date`
+    pub fn of(date: NaiveDate) -> Self {
+        let iso = date.iso_week();
+        IsoWeek {
+            year: iso.year(),
+            week: iso.week(),
+        }
+    }
+
+    /// First day of the week
+    pub fn monday(&self) -> NaiveDate {
+        NaiveDate::from_isoywd_opt(self.year, self.week, Weekday::Mon)
+            .expect("ISO weeks are built from valid dates")
+    }
+}
+
+impl fmt::Display for IsoWeek {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.week)
+    }
+}
+
+/// Bucket size shared by the timeline reports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Granularity {
+    /// Label of the period containing `date`, e.g. "2024-03-09", "2024-W10",
+    /// "2024-03", "2024-Q1" or "2024". Weeks follow ISO 8601.
+    pub fn format_period(&self, date: &DateTime<Utc>) -> String {
+        match self {
+            Granularity::Day => date.format("%Y-%m-%d").to_string(),
+            Granularity::Week => IsoWeek::of(date.date_naive()).to_string(),
+            Granularity::Month => date.format("%Y-%m").to_string(),
+            Granularity::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+            Granularity::Year => date.format("%Y").to_string(),
+        }
+    }
+}
+
+impl FromStr for Granularity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(Granularity::Day),
+            "week" => Ok(Granularity::Week),
+            "month" => Ok(Granularity::Month),
+            "quarter" => Ok(Granularity::Quarter),
+            "year" => Ok(Granularity::Year),
+            _ => Err(anyhow::anyhow!("Unknown granularity: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_period() {
+        let date: DateTime<Utc> = "2024-08-15T12:00:00Z".parse().unwrap();
+
+        assert_eq!(Granularity::Day.format_period(&date), "2024-08-15");
+        assert_eq!(Granularity::Week.format_period(&date), "2024-W33");
+        assert_eq!(Granularity::Month.format_period(&date), "2024-08");
+        assert_eq!(Granularity::Quarter.format_period(&date), "2024-Q3");
+        assert_eq!(Granularity::Year.format_period(&date), "2024");
+    }
+
+    #[test]
+    fn test_iso_week_crosses_year_boundary() {
+        // Dec 30, 2024 is a Monday in ISO week 1 of 2025
+        let date: DateTime<Utc> = "2024-12-30T12:00:00Z".parse().unwrap();
+        assert_eq!(Granularity::Week.format_period(&date), "2025-W01");
+    }
+
+    #[test]
+    fn test_iso_week_monday_and_week_53() {
+        let week = IsoWeek::of(NaiveDate::from_ymd_opt(2027, 1, 1).unwrap());
+        assert_eq!(
+            week,
+            IsoWeek {
+                year: 2026,
+                week: 53
+            }
+        );
+        assert_eq!(week.to_string(), "2026-W53");
+        assert_eq!(
+            week.monday(),
+            NaiveDate::from_ymd_opt(2026, 12, 28).unwrap()
+        );
+        assert!(week < IsoWeek::of(NaiveDate::from_ymd_opt(2027, 1, 4).unwrap()));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("Year".parse::<Granularity>().unwrap(), Granularity::Year);
+        assert_eq!(
+            "quarter".parse::<Granularity>().unwrap(),
+            Granularity::Quarter
+        );
+        assert!("fortnight".parse::<Granularity>().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::db::DbPool;
+use crate::models::{Rating, RatingKind};
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// Ratings at or above this are "highly rated", at or below LOW_RATING "disliked"
+const HIGH_RATING: u8 = 4;
+const LOW_RATING: u8 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RatingsReport {
+    pub schema_version: u32,
+    pub kind: RatingKind,
+    pub rated_count: usize,
+    pub median_plays: f64,
+    /// Pearson correlation between rating and play count, when defined
+    pub correlation: Option<f64>,
+    pub average_plays_by_rating: Vec<RatingBucket>,
+    /// Highly rated items played less than the median rated item
+    pub underplayed: Vec<Rating>,
+    /// Low rated items played more than the median rated item
+    pub overplayed: Vec<Rating>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RatingBucket {
+    pub rating: u8,
+    pub count: usize,
+    pub average_plays: f64,
+}
+
+pub fn generate_ratings_report(pool: &DbPool, kind: RatingKind) -> Result<RatingsReport> {
+    let ratings = crate::db::get_ratings_with_plays(pool, kind)?;
+    Ok(build_ratings_report(kind, ratings))
+}
+
+fn build_ratings_report(kind: RatingKind, ratings: Vec<Rating>) -> RatingsReport {
+    let mut plays: Vec<i64> = ratings.iter().map(|r| r.plays).collect();
+    plays.sort_unstable();
+    let median_plays = median(&plays);
+
+    let average_plays_by_rating = (1..=5)
+        .map(|stars| {
+            let bucket: Vec<i64> = ratings
+                .iter()
+                .filter(|r| r.rating == stars)
+                .map(|r| r.plays)
+                .collect();
+            RatingBucket {
+                rating: stars,
+                count: bucket.len(),
+                average_plays: if bucket.is_empty() {
+                    0.0
+                } else {
+                    bucket.iter().sum::<i64>() as f64 / bucket.len() as f64
+                },
+            }
+        })
+        .collect();
+
+    let correlation = pearson(
+        &ratings
+            .iter()
+            .map(|r| (r.rating as f64, r.plays as f64))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut underplayed: Vec<Rating> = ratings
+        .iter()
+        .filter(|r| r.rating >= HIGH_RATING && (r.plays as f64) < median_plays)
+        .cloned()
+        .collect();
+    underplayed.sort_by_key(|r| (std::cmp::Reverse(r.rating), r.plays));
+
+    let mut overplayed: Vec<Rating> = ratings
+        .iter()
+        .filter(|r| r.rating <= LOW_RATING && (r.plays as f64) > median_plays)
+        .cloned()
+        .collect();
+    overplayed.sort_by_key(|r| (r.rating, std::cmp::Reverse(r.plays)));
+
+    RatingsReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        kind,
+        rated_count: ratings.len(),
+        median_plays,
+        correlation,
+        average_plays_by_rating,
+        underplayed,
+        overplayed,
+    }
+}
+
+fn median(sorted: &[i64]) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 0 => (sorted[n / 2 - 1] + sorted[n / 2]) as f64 / 2.0,
+        n => sorted[n / 2] as f64,
+    }
+}
+
+fn pearson(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let cov: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let var_x: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let var_y: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+
+    if var_x == 0.0 || var_y == 0.0 {
+        None
+    } else {
+        Some(cov / (var_x.sqrt() * var_y.sqrt()))
+    }
+}
+
+impl VersionedReport for RatingsReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn rating(name: &str, stars: u8, plays: i64) -> Rating {
+        Rating {
+            kind: RatingKind::Track,
+            artist: "Artist".to_string(),
+            name: name.to_string(),
+            rating: stars,
+            plays,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_underplayed_and_overplayed() {
+        let report = build_ratings_report(
+            RatingKind::Track,
+            vec![
+                rating("Gem", 5, 2),
+                rating("Favourite", 5, 80),
+                rating("Filler", 3, 20),
+                rating("Guilty pleasure", 1, 60),
+            ],
+        );
+
+        assert_eq!(report.rated_count, 4);
+        assert_eq!(report.median_plays, 40.0);
+        assert_eq!(report.underplayed.len(), 1);
+        assert_eq!(report.underplayed[0].name, "Gem");
+        assert_eq!(report.overplayed.len(), 1);
+        assert_eq!(report.overplayed[0].name, "Guilty pleasure");
+        assert_eq!(report.average_plays_by_rating[4].average_plays, 41.0);
+    }
+
+    #[test]
+    fn test_pearson() {
+        assert!((pearson(&[(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]).unwrap() - 1.0).abs() < 1e-9);
+        assert!(pearson(&[(1.0, 1.0)]).is_none());
+        assert!(pearson(&[(1.0, 1.0), (1.0, 5.0)]).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use crate::db::{DbPool, TimeBucket};
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use crate::reports::sessions::{for_each_session, session_label};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Most scrobbles in one local hour, day, week or month
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VolumeRecord {
+    /// e.g. "2024-03-09T21:00", "2024-03-09", "2024-W10" or "2024-03"
+    pub period: String,
+    /// Local day the period starts on
+    pub date: NaiveDate,
+    pub scrobbles: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtistsRecord {
+    pub date: NaiveDate,
+    pub artists: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionRecord {
+    /// e.g. "Saturday night"
+    pub label: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_minutes: i64,
+    pub scrobbles: usize,
+}
+
+/// Personal bests, each `None` until something was scrobbled. On ties the
+/// first time a record was set holds it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordsReport {
+    pub schema_version: u32,
+    pub most_scrobbles_in_hour: Option<VolumeRecord>,
+    pub most_scrobbles_in_day: Option<VolumeRecord>,
+    pub most_scrobbles_in_week: Option<VolumeRecord>,
+    pub most_scrobbles_in_month: Option<VolumeRecord>,
+    pub most_artists_in_day: Option<ArtistsRecord>,
+    pub longest_session: Option<SessionRecord>,
+}
+
+impl VersionedReport for RecordsReport {}
+
+/// Records over the whole history, with periods taken in `timezone` and
+/// sessions split at gaps longer than `gap_minutes`
+pub fn generate_records_report(
+    pool: &DbPool,
+    gap_minutes: i64,
+    timezone: Tz,
+) -> Result<RecordsReport> {
+    let buckets = [
+        TimeBucket::Hour,
+        TimeBucket::Day,
+        TimeBucket::Week,
+        TimeBucket::Month,
+    ];
+    let mut volumes: [BTreeMap<NaiveDateTime, i64>; 4] = Default::default();
+    let mut artists_per_day: BTreeMap<NaiveDate, HashSet<String>> = BTreeMap::new();
+
+    crate::db::for_each_artist_slot(pool, |slot_start, artist, count| {
+        let local = slot_start.with_timezone(&timezone).naive_local();
+        for (bucket, counts) in buckets.iter().zip(volumes.iter_mut()) {
+            *counts.entry(bucket.floor(local)).or_insert(0) += count;
+        }
+        let artists = artists_per_day.entry(local.date()).or_default();
+        if !artists.contains(artist) {
+            artists.insert(artist.to_string());
+        }
+    })?;
+
+    let [hour, day, week, month] = std::array::from_fn(|i| volume_record(buckets[i], &volumes[i]));
+
+    let most_artists_in_day = first_max(
+        artists_per_day
+            .iter()
+            .map(|(date, artists)| (*date, artists.len())),
+    )
+    .map(|(date, artists)| ArtistsRecord { date, artists });
+
+    let mut longest_session: Option<SessionRecord> = None;
+    for_each_session(
+        pool,
+        DateTime::UNIX_EPOCH,
+        Utc::now(),
+        gap_minutes,
+        |session| {
+            let (Some(first), Some(last)) = (session.first(), session.last()) else {
+                return;
+            };
+            let duration_minutes = (last.timestamp - first.timestamp).num_minutes();
+            if longest_session
+                .as_ref()
+                .is_none_or(|longest| duration_minutes > longest.duration_minutes)
+            {
+                longest_session = Some(SessionRecord {
+                    label: session_label(first.timestamp, timezone),
+                    start: first.timestamp,
+                    end: last.timestamp,
+                    duration_minutes,
+                    scrobbles: session.len(),
+                });
+            }
+        },
+    )?;
+
+    Ok(RecordsReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        most_scrobbles_in_hour: hour,
+        most_scrobbles_in_day: day,
+        most_scrobbles_in_week: week,
+        most_scrobbles_in_month: month,
+        most_artists_in_day,
+        longest_session,
+    })
+}
+
+fn volume_record(
+    bucket: TimeBucket,
+    counts: &BTreeMap<NaiveDateTime, i64>,
+) -> Option<VolumeRecord> {
+    first_max(counts.iter().map(|(start, count)| (*start, *count))).map(|(start, scrobbles)| {
+        VolumeRecord {
+            period: bucket.label(start),
+            date: start.date(),
+            scrobbles,
+        }
+    })
+}
+
+/// The first entry with the highest value, in iteration order
+fn first_max<K, V: Ord>(entries: impl Iterator<Item = (K, V)>) -> Option<(K, V)> {
+    let mut best: Option<(K, V)> = None;
+    for (key, value) in entries {
+        if best.as_ref().is_none_or(|(_, most)| value > *most) {
+            best = Some((key, value));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+    use chrono::Duration;
+
+    /// `times` plays `every_minutes` apart from `at`, cycling through `artists`
+    fn plays(pool: &DbPool, at: &str, artists: &[&str], times: i64, every_minutes: i64) {
+        let start: DateTime<Utc> = at.parse().unwrap();
+        for i in 0..times {
+            let scrobble = Scrobble::new(
+                artists[i as usize % artists.len()].to_string(),
+                format!("Track {}", i),
+                start + Duration::minutes(i * every_minutes),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(pool, &scrobble).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_first_max_keeps_the_earliest() {
+        let entries = [("a", 1), ("b", 3), ("c", 3), ("d", 2)];
+        assert_eq!(first_max(entries.into_iter()), Some(("b", 3)));
+        assert_eq!(first_max(std::iter::empty::<(&str, i64)>()), None);
+    }
+
+    #[test]
+    fn test_records() {
+        let (pool, _temp_file) = setup_pool();
+        // A four-hour session on a Saturday evening
+        plays(&pool, "2024-03-09T17:00:00Z", &["Low"], 60, 4);
+        // Twenty plays in one hour, from five artists
+        plays(
+            &pool,
+            "2024-03-20T21:00:00Z",
+            &["Slint", "Codeine", "Bedhead", "Duster", "Galaxie 500"],
+            20,
+            3,
+        );
+
+        let report = generate_records_report(&pool, 30, chrono_tz::UTC).unwrap();
+
+        let hour = report.most_scrobbles_in_hour.unwrap();
+        assert_eq!(hour.period, "2024-03-20T21:00");
+        assert_eq!(hour.scrobbles, 20);
+        let day = report.most_scrobbles_in_day.unwrap();
+        assert_eq!((day.period.as_str(), day.scrobbles), ("2024-03-09", 60));
+        let week = report.most_scrobbles_in_week.unwrap();
+        assert_eq!(week.period, "2024-W10");
+        assert_eq!(week.date, "2024-03-04".parse::<NaiveDate>().unwrap());
+        assert_eq!(report.most_scrobbles_in_month.unwrap().scrobbles, 80);
+
+        let artists = report.most_artists_in_day.unwrap();
+        assert_eq!(artists.artists, 5);
+        assert_eq!(artists.date, "2024-03-20".parse::<NaiveDate>().unwrap());
+
+        let session = report.longest_session.unwrap();
+        assert_eq!(session.duration_minutes, 236);
+        assert_eq!(session.scrobbles, 60);
+        assert_eq!(session.label, "Saturday evening");
+    }
+
+    #[test]
+    fn test_records_follow_the_timezone() {
+        let (pool, _temp_file) = setup_pool();
+        // Late on the 9th in New York, already the 10th in UTC
+        plays(&pool, "2024-03-10T02:00:00Z", &["Low"], 3, 5);
+        plays(&pool, "2024-03-09T15:00:00Z", &["Low"], 2, 5);
+
+        let utc = generate_records_report(&pool, 30, chrono_tz::UTC).unwrap();
+        assert_eq!(utc.most_scrobbles_in_day.unwrap().period, "2024-03-10");
+        let new_york = generate_records_report(&pool, 30, chrono_tz::America::New_York).unwrap();
+        let day = new_york.most_scrobbles_in_day.unwrap();
+        assert_eq!((day.period.as_str(), day.scrobbles), ("2024-03-09", 5));
+    }
+
+    #[test]
+    fn test_no_records_yet() {
+        let (pool, _temp_file) = setup_pool();
+        let report = generate_records_report(&pool, 30, chrono_tz::UTC).unwrap();
+        assert!(report.most_scrobbles_in_day.is_none());
+        assert!(report.longest_session.is_none());
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Version of the report JSON shapes served by default
+pub const REPORT_SCHEMA_VERSION: u32 = 2;
+
+/// Oldest version that can still be rendered on request
+pub const MIN_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// A report whose JSON shape is versioned. Implementations describe how to
+/// turn the current shape into older ones so third-party consumers can keep
+/// asking for the version they were built against.
+pub trait VersionedReport: Serialize {
+    /// Rewrite the current JSON object into its v1 shape
+    fn downgrade_to_v1(_object: &mut Map<String, Value>) {}
+
+    /// Drop anything the current version no longer serves
+    fn strip_legacy(_object: &mut Map<String, Value>) {}
+}
+
+/// Serialize `report` in the shape of schema `version`
+pub fn render<R: VersionedReport>(report: &R, version: u32) -> Result<Value> {
+    if !(MIN_REPORT_SCHEMA_VERSION..=REPORT_SCHEMA_VERSION).contains(&version) {
+        return Err(anyhow::anyhow!(
+            "Unsupported schema version {}, expected {}..={}",
+            version,
+            MIN_REPORT_SCHEMA_VERSION,
+            REPORT_SCHEMA_VERSION
+        ));
+    }
+
+    let mut value = serde_json::to_value(report)?;
+    if let Value::Object(object) = &mut value {
+        if version == 1 {
+            R::downgrade_to_v1(object);
+        } else {
+            R::strip_legacy(object);
+        }
+        object.insert("schema_version".to_string(), version.into());
+    }
+
+    Ok(value)
+}
+
+/// Remove `keys` from a JSON object, used by the downgrade implementations
+pub(crate) fn remove_keys(object: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        object.remove(*key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Example {
+        schema_version: u32,
+        kept: i64,
+        added_in_v2: i64,
+        legacy: Option<i64>,
+    }
+
+    impl VersionedReport for Example {
+        fn downgrade_to_v1(object: &mut Map<String, Value>) {
+            remove_keys(object, &["added_in_v2"]);
+        }
+
+        fn strip_legacy(object: &mut Map<String, Value>) {
+            remove_keys(object, &["legacy"]);
+        }
+    }
+
+    fn example() -> Example {
+        Example {
+            schema_version: REPORT_SCHEMA_VERSION,
+            kept: 1,
+            added_in_v2: 2,
+            legacy: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_render_current_version() {
+        let value = render(&example(), REPORT_SCHEMA_VERSION).unwrap();
+        assert_eq!(value["schema_version"], 2);
+        assert_eq!(value["added_in_v2"], 2);
+        assert!(value.get("legacy").is_none());
+    }
+
+    #[test]
+    fn test_render_v1() {
+        let value = render(&example(), 1).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["kept"], 1);
+        assert_eq!(value["legacy"], 3);
+        assert!(value.get("added_in_v2").is_none());
+    }
+
+    #[test]
+    fn test_render_unknown_version() {
+        assert!(render(&example(), 0).is_err());
+        assert!(render(&example(), REPORT_SCHEMA_VERSION + 1).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::DbPool;
+use crate::models::Scrobble;
+use crate::reports::album_listens::album_runs;
+
+/// Default gap between scrobbles that ends a listening session
+pub const DEFAULT_SESSION_GAP_MINUTES: i64 = 45;
+
+/// Shortest session worth classifying; two tracks say nothing about habits
+pub const MIN_CLASSIFIED_SESSION: usize = 4;
+
+// Share of a session its albums or its top artist must take up
+const DOMINANT_SHARE: f64 = 0.6;
+
+// Tracks in a row from one album that count as playing the album
+const MIN_ALBUM_RUN: usize = 4;
+
+/// How a session was listened to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStyle {
+    /// Mostly albums played through, several tracks in a row from each
+    Album,
+    /// Mostly one artist, across albums or out of album order
+    DeepDive,
+    /// Everything else: many artists with little order
+    Shuffle,
+}
+
+/// Classify a session from its artist and album variety and how its tracks
+/// follow each other, or `None` when it's too short to tell
+pub fn classify_session(session: &[Scrobble]) -> Option<SessionStyle> {
+    if session.len() < MIN_CLASSIFIED_SESSION {
+        return None;
+    }
+    let share = |count: usize| count as f64 / session.len() as f64;
+
+    let in_album_runs: usize = album_runs(session)
+        .iter()
+        .map(Vec::len)
+        .filter(|len| *len >= MIN_ALBUM_RUN)
+        .sum();
+    if share(in_album_runs) >= DOMINANT_SHARE {
+        return Some(SessionStyle::Album);
+    }
+
+    let mut artists: HashMap<&str, usize> = HashMap::new();
+    for scrobble in session {
+        *artists.entry(scrobble.artist.as_str()).or_insert(0) += 1;
+    }
+    let top_artist = artists.values().copied().max().unwrap_or(0);
+    if share(top_artist) >= DOMINANT_SHARE {
+        return Some(SessionStyle::DeepDive);
+    }
+
+    Some(SessionStyle::Shuffle)
+}
+
+/// Split scrobbles into listening sessions: consecutive scrobbles belong to
+/// the same session while they are at most `gap_minutes` apart. Input order
+/// (ascending or descending) is preserved, both across and within sessions.
+pub fn detect_sessions<T>(
+    items: Vec<T>,
+    timestamp: impl Fn(&T) -> DateTime<Utc>,
+    gap_minutes: i64,
+) -> Vec<Vec<T>> {
+    let mut sessions: Vec<Vec<T>> = Vec::new();
+    let mut previous: Option<DateTime<Utc>> = None;
+
+    for item in items {
+        let ts = timestamp(&item);
+        let continues = previous.is_some_and(|prev| (ts - prev).num_minutes().abs() <= gap_minutes);
+
+        match sessions.last_mut() {
+            Some(session) if continues => session.push(item),
+            _ => sessions.push(vec![item]),
+        }
+        previous = Some(ts);
+    }
+
+    sessions
+}
+
+/// Call `f` with each listening session of the music scrobbles between
+/// `start` and `end`, oldest first, split as `detect_sessions` does. Scrobbles
+/// are streamed from the database, so only one session is held at a time
+pub fn for_each_session(
+    pool: &DbPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    gap_minutes: i64,
+    mut f: impl FnMut(Vec<Scrobble>),
+) -> Result<()> {
+    let mut session: Vec<Scrobble> = Vec::new();
+
+    crate::db::for_each_scrobble_in_range(pool, start, end, |scrobble| {
+        let continues = session
+            .last()
+            .is_some_and(|prev| (scrobble.timestamp - prev.timestamp).num_minutes() <= gap_minutes);
+        if !continues && !session.is_empty() {
+            f(std::mem::take(&mut session));
+        }
+        session.push(scrobble);
+    })?;
+
+    if !session.is_empty() {
+        f(session);
+    }
+    Ok(())
+}
+
+/// Human label for a session start, e.g. "Tuesday evening"
+pub fn session_label(start: DateTime<Utc>, timezone: Tz) -> String {
+    let local = start.with_timezone(&timezone);
+    let part_of_day = match local.hour() {
+        5..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=21 => "evening",
+        _ => "night",
+    };
+    format!("{} {}", local.format("%A"), part_of_day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::{setup_pool, test_scrobble_from_rfc3339};
+
+    fn detect_scrobble_sessions(scrobbles: Vec<Scrobble>, gap_minutes: i64) -> Vec<Vec<Scrobble>> {
+        detect_sessions(scrobbles, |s| s.timestamp, gap_minutes)
+    }
+
+    #[test]
+    fn test_detect_sessions_splits_on_gap() {
+        let scrobbles = vec![
+            test_scrobble_from_rfc3339("A", "1", "2024-01-02T20:00:00Z"),
+            test_scrobble_from_rfc3339("A", "2", "2024-01-02T20:04:00Z"),
+            test_scrobble_from_rfc3339("B", "3", "2024-01-02T20:30:00Z"),
+            test_scrobble_from_rfc3339("C", "4", "2024-01-02T22:00:00Z"),
+        ];
+
+        let sessions = detect_scrobble_sessions(scrobbles, 30);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].len(), 3);
+        assert_eq!(sessions[1][0].track, "4");
+    }
+
+    #[test]
+    fn test_detect_sessions_descending_order() {
+        let scrobbles = vec![
+            test_scrobble_from_rfc3339("C", "4", "2024-01-02T22:00:00Z"),
+            test_scrobble_from_rfc3339("B", "3", "2024-01-02T20:30:00Z"),
+            test_scrobble_from_rfc3339("A", "2", "2024-01-02T20:04:00Z"),
+        ];
+
+        let sessions = detect_scrobble_sessions(scrobbles, 30);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[1].len(), 2);
+        assert_eq!(sessions[1][0].track, "3");
+    }
+
+    #[test]
+    fn test_for_each_session_streams_from_database() {
+        let (pool, _temp_file) = setup_pool();
+
+        for (artist, track, timestamp) in [
+            ("A", "1", "2024-01-02T20:00:00Z"),
+            ("A", "2", "2024-01-02T20:04:00Z"),
+            ("B", "3", "2024-01-02T20:30:00Z"),
+            ("C", "4", "2024-01-02T22:00:00Z"),
+        ] {
+            let scrobble = test_scrobble_from_rfc3339(artist, track, timestamp);
+            crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+        }
+
+        let mut sessions = Vec::new();
+        for_each_session(&pool, DateTime::UNIX_EPOCH, Utc::now(), 30, |session| {
+            sessions.push(session.len())
+        })
+        .unwrap();
+        assert_eq!(sessions, vec![3, 1]);
+    }
+
+    fn session(plays: &[(&str, &str, &str)]) -> Vec<Scrobble> {
+        plays
+            .iter()
+            .enumerate()
+            .map(|(i, (artist, album, track))| {
+                let timestamp = format!("2024-01-02T20:{:02}:00Z", i * 4);
+                test_scrobble_from_rfc3339(artist, track, &timestamp).with_album(album.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_session() {
+        let album = session(&[
+            ("Low", "Things We Lost", "1"),
+            ("Low", "Things We Lost", "2"),
+            ("Low", "Things We Lost", "3"),
+            ("Low", "Things We Lost", "4"),
+            ("Slint", "Spiderland", "5"),
+        ]);
+        assert_eq!(classify_session(&album), Some(SessionStyle::Album));
+
+        // One artist hopping between albums
+        let deep_dive = session(&[
+            ("Low", "Things We Lost", "1"),
+            ("Low", "Secret Name", "2"),
+            ("Low", "C'mon", "3"),
+            ("Low", "Things We Lost", "4"),
+            ("Slint", "Spiderland", "5"),
+        ]);
+        assert_eq!(classify_session(&deep_dive), Some(SessionStyle::DeepDive));
+
+        let shuffle = session(&[
+            ("Low", "Things We Lost", "1"),
+            ("Slint", "Spiderland", "2"),
+            ("Codeine", "Frigid Stars", "3"),
+            ("Low", "Secret Name", "4"),
+        ]);
+        assert_eq!(classify_session(&shuffle), Some(SessionStyle::Shuffle));
+        assert_eq!(classify_session(&shuffle[..3]), None);
+    }
+
+    #[test]
+    fn test_session_label_uses_timezone() {
+        let start = "2024-01-02T20:00:00Z".parse().unwrap();
+        assert_eq!(session_label(start, chrono_tz::UTC), "Tuesday evening");
+        assert_eq!(
+            session_label(start, chrono_tz::Asia::Tokyo),
+            "Wednesday morning"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::db::DbPool;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArtistSkipStats {
+    pub artist: String,
+    pub plays: i64,
+    pub skips: i64,
+    pub full_listens: i64,
+    pub skip_rate: f64,
+    pub avg_ms_played: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkipSummary {
+    pub tracked_plays: i64,
+    pub skips: i64,
+    pub full_listens: i64,
+    pub skip_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkipReport {
+    pub schema_version: u32,
+    pub artists: Vec<ArtistSkipStats>,
+    pub summary: SkipSummary,
+}
+
+/// Skip rate per artist, computed only over scrobbles whose source recorded
+/// playback metadata. Artists with fewer than `min_plays` such plays are left
+/// out so a single skip doesn't dominate the ranking.
+pub fn generate_skip_report(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    min_plays: i64,
+) -> Result<SkipReport> {
+    let rows = crate::db::get_playback_stats_by_artist(pool, start, end)?;
+
+    let tracked_plays: i64 = rows.iter().map(|(_, plays, _, _)| plays).sum();
+    let skips: i64 = rows.iter().map(|(_, _, skips, _)| skips).sum();
+
+    let mut artists: Vec<ArtistSkipStats> = rows
+        .into_iter()
+        .filter(|(_, plays, _, _)| *plays >= min_plays)
+        .map(|(artist, plays, skips, avg_ms_played)| ArtistSkipStats {
+            artist,
+            plays,
+            skips,
+            full_listens: plays - skips,
+            skip_rate: skip_rate(skips, plays),
+            avg_ms_played,
+        })
+        .collect();
+
+    // Most skipped first, ties broken by play count
+    artists.sort_by(|a, b| {
+        b.skip_rate
+            .total_cmp(&a.skip_rate)
+            .then_with(|| b.plays.cmp(&a.plays))
+    });
+
+    Ok(SkipReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        artists,
+        summary: SkipSummary {
+            tracked_plays,
+            skips,
+            full_listens: tracked_plays - skips,
+            skip_rate: skip_rate(skips, tracked_plays),
+        },
+    })
+}
+
+fn skip_rate(skips: i64, plays: i64) -> f64 {
+    if plays > 0 {
+        (skips as f64 / plays as f64) * 100.0
+    } else {
+        0.0
+    }
+}
+
+impl VersionedReport for SkipReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_rate() {
+        assert_eq!(skip_rate(1, 4), 25.0);
+        assert_eq!(skip_rate(0, 0), 0.0);
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::db::{DbPool, ScrobbleFilter};
+use crate::models::{DetectionStatus, Scrobble, SleepDetection};
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use crate::reports::sessions::detect_sessions;
+
+/// What counts as a probable sleep-listening run
+#[derive(Debug, Clone, Copy)]
+pub struct SleepOptions {
+    /// Local hours [start, end) considered overnight
+    pub start_hour: u32,
+    pub end_hour: u32,
+    /// Longest pause between two tracks of the same run; autoplay doesn't pause
+    pub gap_minutes: i64,
+    pub min_tracks: usize,
+    /// Highest distinct artists / tracks ratio; autoplay tends to stay put
+    pub max_artist_ratio: f64,
+}
+
+impl Default for SleepOptions {
+    fn default() -> Self {
+        Self {
+            start_hour: 1,
+            end_hour: 7,
+            gap_minutes: 15,
+            min_tracks: 10,
+            max_artist_ratio: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SleepReport {
+    pub schema_version: u32,
+    pub detections: Vec<SleepDetection>,
+    /// Runs found in the range that weren't recorded for review yet
+    pub candidates: Vec<SleepDetection>,
+    pub pending: usize,
+    pub confirmed: usize,
+    pub dismissed: usize,
+    /// Scrobbles currently left out of stats because their run was confirmed
+    pub excluded_scrobbles: i64,
+}
+
+/// List every recorded detection in the range with its status, and the runs
+/// found there that weren't recorded yet. Nothing is stored; see
+/// `record_sleep_runs`
+pub fn generate_sleep_report(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    timezone: Tz,
+    options: SleepOptions,
+) -> Result<SleepReport> {
+    let scrobbles =
+        crate::db::get_scrobbles_filtered(pool, start, end, &ScrobbleFilter::default())?;
+    let detections = crate::db::get_sleep_detections(pool, start, end)?;
+    let recorded: HashSet<(DateTime<Utc>, DateTime<Utc>)> =
+        detections.iter().map(|d| (d.start, d.end)).collect();
+    let candidates = detect_sleep_runs(scrobbles, timezone, &options)
+        .into_iter()
+        .filter(|run| !recorded.contains(&(run.start, run.end)))
+        .collect();
+
+    let with_status =
+        |status: DetectionStatus| detections.iter().filter(|d| d.status == status).count();
+
+    Ok(SleepReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        pending: with_status(DetectionStatus::Pending),
+        confirmed: with_status(DetectionStatus::Confirmed),
+        dismissed: with_status(DetectionStatus::Dismissed),
+        excluded_scrobbles: crate::db::get_sleep_flagged_count(pool)?,
+        detections,
+        candidates,
+    })
+}
+
+/// Detect overnight autoplay runs in the range and record new ones for
+/// review, returning how many were new. Runs already recorded keep their
+/// review status
+pub fn record_sleep_runs(
+    pool: &DbPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    timezone: Tz,
+    options: SleepOptions,
+) -> Result<usize> {
+    let scrobbles =
+        crate::db::get_scrobbles_filtered(pool, start, end, &ScrobbleFilter::default())?;
+    crate::db::record_sleep_detections(pool, &detect_sleep_runs(scrobbles, timezone, &options))
+}
+
+/// Unbroken overnight runs, oldest first, that are long and repetitive enough
+/// to look like the player kept going after the listener fell asleep
+pub fn detect_sleep_runs(
+    scrobbles: Vec<Scrobble>,
+    timezone: Tz,
+    options: &SleepOptions,
+) -> Vec<SleepDetection> {
+    let mut overnight: Vec<Scrobble> = scrobbles
+        .into_iter()
+        .filter(|s| {
+            let hour = s.timestamp.with_timezone(&timezone).hour();
+            hour >= options.start_hour && hour < options.end_hour
+        })
+        .collect();
+    overnight.sort_by_key(|s| s.timestamp);
+
+    detect_sessions(overnight, |s| s.timestamp, options.gap_minutes)
+        .into_iter()
+        .filter(|run| run.len() >= options.min_tracks)
+        .filter_map(|run| {
+            let distinct_artists = run
+                .iter()
+                .map(|s| s.artist.to_lowercase())
+                .collect::<HashSet<_>>()
+                .len();
+            let ratio = distinct_artists as f64 / run.len() as f64;
+
+            (ratio <= options.max_artist_ratio).then(|| SleepDetection {
+                id: None,
+                start: run[0].timestamp,
+                end: run[run.len() - 1].timestamp,
+                scrobble_count: run.len() as i64,
+                distinct_artists: distinct_artists as i64,
+                status: DetectionStatus::Pending,
+            })
+        })
+        .collect()
+}
+
+impl VersionedReport for SleepReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+    use chrono::Duration;
+
+    fn run(artists: &[&str], first: &str, minutes_apart: i64) -> Vec<Scrobble> {
+        let start: DateTime<Utc> = first.parse().unwrap();
+        artists
+            .iter()
+            .enumerate()
+            .map(|(i, artist)| {
+                Scrobble::new(
+                    artist.to_string(),
+                    format!("Track {}", i),
+                    start + Duration::minutes(minutes_apart * i as i64),
+                    "test".to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_repetitive_overnight_run() {
+        let mut scrobbles = run(&["Ambient"; 12], "2024-03-02T02:00:00Z", 4);
+        // Varied late listening isn't autoplay
+        let varied: Vec<String> = (0..12).map(|i| format!("Artist {}", i)).collect();
+        let varied: Vec<&str> = varied.iter().map(String::as_str).collect();
+        scrobbles.extend(run(&varied, "2024-03-03T02:00:00Z", 4));
+        // Too short
+        scrobbles.extend(run(&["Ambient"; 5], "2024-03-04T02:00:00Z", 4));
+        // Same pattern in the evening
+        scrobbles.extend(run(&["Ambient"; 12], "2024-03-05T20:00:00Z", 4));
+
+        let runs = detect_sleep_runs(scrobbles, chrono_tz::UTC, &SleepOptions::default());
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].scrobble_count, 12);
+        assert_eq!(runs[0].distinct_artists, 1);
+        assert_eq!(
+            runs[0].start,
+            "2024-03-02T02:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_stops_at_window_edge() {
+        // 06:00 to 07:56 UTC: only the tracks before 7am are overnight
+        let scrobbles = run(&["Ambient"; 30], "2024-03-02T06:00:00Z", 4);
+        let runs = detect_sleep_runs(scrobbles, chrono_tz::UTC, &SleepOptions::default());
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].scrobble_count, 15);
+    }
+
+    #[test]
+    fn test_confirming_excludes_scrobbles_from_stats() {
+        let (pool, _temp_file) = setup_pool();
+
+        crate::db::insert_scrobbles_batch(&pool, &run(&["Ambient"; 12], "2024-03-02T02:00:00Z", 4))
+            .unwrap();
+        crate::db::insert_scrobbles_batch(&pool, &run(&["Band"; 3], "2024-03-02T18:00:00Z", 4))
+            .unwrap();
+
+        // Reading the report records nothing
+        let report =
+            generate_sleep_report(&pool, None, None, chrono_tz::UTC, SleepOptions::default())
+                .unwrap();
+        assert_eq!((report.pending, report.candidates.len()), (0, 1));
+        assert!(
+            crate::db::get_sleep_detections(&pool, None, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        let recorded =
+            record_sleep_runs(&pool, None, None, chrono_tz::UTC, SleepOptions::default());
+        assert_eq!(recorded.unwrap(), 1);
+        let report =
+            generate_sleep_report(&pool, None, None, chrono_tz::UTC, SleepOptions::default())
+                .unwrap();
+        assert_eq!(report.pending, 1);
+        assert!(report.candidates.is_empty());
+        let id = report.detections[0].id.unwrap();
+
+        crate::db::set_sleep_detection_status(&pool, id, DetectionStatus::Confirmed).unwrap();
+        let top = crate::db::get_top_artists(&pool, 10, None, None).unwrap();
+        assert_eq!(top, vec![("Band".to_string(), 3)]);
+
+        // Recording again doesn't duplicate the reviewed run
+        let recorded =
+            record_sleep_runs(&pool, None, None, chrono_tz::UTC, SleepOptions::default());
+        assert_eq!(recorded.unwrap(), 0);
+        let report =
+            generate_sleep_report(&pool, None, None, chrono_tz::UTC, SleepOptions::default())
+                .unwrap();
+        assert_eq!((report.pending, report.confirmed), (0, 1));
+        assert_eq!(report.excluded_scrobbles, 12);
+
+        crate::db::set_sleep_detection_status(&pool, id, DetectionStatus::Dismissed).unwrap();
+        assert_eq!(
+            crate::db::get_top_artists(&pool, 10, None, None).unwrap()[0].1,
+            12
+        );
+    }
+}
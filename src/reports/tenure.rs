@@ -0,0 +1,262 @@
+use crate::db::DbPool;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Top artists looked at by default
+pub const DEFAULT_TENURE_ARTISTS: i64 = 50;
+
+/// Tenure score from which an artist is a staple
+pub const STAPLE_SCORE: f64 = 50.0;
+
+/// Artists played only within this many calendar months are flings
+pub const FLING_MONTHS: i64 = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TenureKind {
+    /// Played across most of the history, most months
+    Staple,
+    /// Played within a few months only
+    Fling,
+    Occasional,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArtistTenure {
+    pub artist: String,
+    pub plays: i64,
+    pub first_listen: DateTime<Utc>,
+    pub last_listen: DateTime<Utc>,
+    pub span_days: i64,
+    /// Calendar months from the first listen's to the last's, both included
+    pub span_months: i64,
+    pub active_months: i64,
+    /// Share of the span's months with plays, as a percentage
+    pub consistency: f64,
+    /// 0-100: how much of the listening history the artist spans, weighed
+    /// by how steadily they were played across it
+    pub tenure_score: f64,
+    pub kind: TenureKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TenureSummary {
+    pub staples: usize,
+    pub flings: usize,
+    pub occasional: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TenureReport {
+    pub schema_version: u32,
+    /// Calendar months from the first listen of anything to now
+    pub history_months: i64,
+    pub artists: Vec<ArtistTenure>,
+    pub summary: TenureSummary,
+}
+
+/// Tenure of the `limit` most played artists, highest score first. First
+/// listens come from the first listens index and count archived scrobbles;
+/// active months only count scrobbles still in the library
+pub fn generate_tenure_report(
+    pool: &DbPool,
+    limit: i64,
+    now: DateTime<Utc>,
+    timezone: Tz,
+) -> Result<TenureReport> {
+    let (top, first_listens, activity, history_start) = crate::db::with_read_txn(pool, |conn| {
+        let top = crate::db::query_top_artists(conn, limit, None, None)?;
+        let names: Vec<String> = top.iter().map(|(artist, _)| artist.clone()).collect();
+        Ok((
+            top,
+            crate::db::query_artist_first_listens(conn, &names)?,
+            crate::db::query_artist_activity(conn, &names, timezone)?,
+            crate::db::query_first_listen_timestamp(conn)?,
+        ))
+    })?;
+
+    let history_months = history_start
+        .map(|start| months_between(local_month(start, timezone), local_month(now, timezone)))
+        .unwrap_or(0);
+
+    let mut artists: Vec<ArtistTenure> = top
+        .into_iter()
+        .filter_map(|(artist, plays)| {
+            let first_listen = *first_listens.get(&artist)?;
+            let activity = activity.get(&artist)?;
+            let span_months = months_between(
+                local_month(first_listen, timezone),
+                local_month(activity.last_listen, timezone),
+            );
+            let active_months = activity.months.len() as i64;
+            let tenure_score = tenure_score(span_months, active_months, history_months);
+            Some(ArtistTenure {
+                plays,
+                first_listen,
+                last_listen: activity.last_listen,
+                span_days: (activity.last_listen - first_listen).num_days(),
+                span_months,
+                active_months,
+                consistency: active_months as f64 * 100.0 / span_months as f64,
+                tenure_score,
+                kind: if span_months <= FLING_MONTHS {
+                    TenureKind::Fling
+                } else if tenure_score >= STAPLE_SCORE {
+                    TenureKind::Staple
+                } else {
+                    TenureKind::Occasional
+                },
+                artist,
+            })
+        })
+        .collect();
+
+    artists.sort_by(|a, b| {
+        b.tenure_score
+            .total_cmp(&a.tenure_score)
+            .then_with(|| b.plays.cmp(&a.plays))
+    });
+
+    let count = |kind| artists.iter().filter(|a| a.kind == kind).count();
+    let summary = TenureSummary {
+        staples: count(TenureKind::Staple),
+        flings: count(TenureKind::Fling),
+        occasional: count(TenureKind::Occasional),
+    };
+
+    Ok(TenureReport {
+        schema_version: REPORT_SCHEMA_VERSION,
+        history_months,
+        artists,
+        summary,
+    })
+}
+
+/// First day of the local month `at` falls in
+fn local_month(at: DateTime<Utc>, timezone: Tz) -> NaiveDate {
+    let date = at.with_timezone(&timezone).date_naive();
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Calendar months from `first`'s to `last`'s, both included
+fn months_between(first: NaiveDate, last: NaiveDate) -> i64 {
+    let months =
+        (last.year() - first.year()) as i64 * 12 + last.month() as i64 - first.month() as i64;
+    months.max(0) + 1
+}
+
+/// Geometric mean of the share of the history spanned and the share of the
+/// span with plays, so neither a fling played daily for a month nor an
+/// artist heard once a year scores high
+fn tenure_score(span_months: i64, active_months: i64, history_months: i64) -> f64 {
+    if span_months == 0 || history_months == 0 {
+        return 0.0;
+    }
+    let coverage = (span_months as f64 / history_months as f64).min(1.0);
+    let consistency = active_months as f64 / span_months as f64;
+    (coverage * consistency).sqrt() * 100.0
+}
+
+impl VersionedReport for TenureReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+    use chrono::Duration;
+
+    fn play(pool: &DbPool, artist: &str, at: &str) {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            at.parse().unwrap(),
+            "lastfm".to_string(),
+        );
+        crate::db::insert_scrobble(pool, &scrobble).unwrap();
+    }
+
+    #[test]
+    fn test_months_between() {
+        let date = |d: &str| d.parse::<NaiveDate>().unwrap();
+        assert_eq!(months_between(date("2024-03-01"), date("2024-03-01")), 1);
+        assert_eq!(months_between(date("2023-11-01"), date("2024-02-01")), 4);
+    }
+
+    #[test]
+    fn test_tenure_score() {
+        // Every month of the whole history
+        assert_eq!(tenure_score(24, 24, 24), 100.0);
+        // One busy month out of two years
+        assert!(tenure_score(1, 1, 24) < 25.0);
+        // Spanning the history, but a month a year
+        assert!(tenure_score(24, 2, 24) < 30.0);
+        assert_eq!(tenure_score(0, 0, 24), 0.0);
+    }
+
+    #[test]
+    fn test_staples_and_flings() {
+        let (pool, _temp_file) = setup_pool();
+        let now: DateTime<Utc> = "2024-12-15T12:00:00Z".parse().unwrap();
+
+        // Low every month of 2024
+        for month in 1..=12 {
+            play(&pool, "Low", &format!("2024-{:02}-10T20:00:00Z", month));
+        }
+        // Slint a lot, but only in March
+        for day in 1..=20 {
+            play(&pool, "Slint", &format!("2024-03-{:02}T20:00:00Z", day));
+        }
+        // Codeine in January and November
+        play(&pool, "Codeine", "2024-01-20T20:00:00Z");
+        play(&pool, "Codeine", "2024-11-20T20:00:00Z");
+
+        let report = generate_tenure_report(&pool, 10, now, chrono_tz::UTC).unwrap();
+        assert_eq!(report.history_months, 12);
+
+        let names: Vec<&str> = report.artists.iter().map(|a| a.artist.as_str()).collect();
+        assert_eq!(names, vec!["Low", "Codeine", "Slint"]);
+
+        let low = &report.artists[0];
+        assert_eq!(low.kind, TenureKind::Staple);
+        assert_eq!((low.span_months, low.active_months), (12, 12));
+        assert_eq!(low.consistency, 100.0);
+        assert_eq!(low.span_days, 335);
+
+        let codeine = &report.artists[1];
+        assert_eq!(codeine.kind, TenureKind::Occasional);
+        assert_eq!((codeine.span_months, codeine.active_months), (11, 2));
+
+        let slint = &report.artists[2];
+        assert_eq!(slint.kind, TenureKind::Fling);
+        assert_eq!(slint.plays, 20);
+        assert_eq!(slint.span_days, 19);
+
+        assert_eq!(report.summary.staples, 1);
+        assert_eq!(report.summary.flings, 1);
+        assert_eq!(report.summary.occasional, 1);
+    }
+
+    #[test]
+    fn test_months_follow_the_timezone() {
+        let (pool, _temp_file) = setup_pool();
+        // New Year's Eve in New York is already January in UTC
+        play(&pool, "Low", "2024-01-01T02:00:00Z");
+        play(&pool, "Low", "2024-01-20T20:00:00Z");
+        let now: DateTime<Utc> = "2024-01-31T00:00:00Z".parse().unwrap();
+
+        let utc = generate_tenure_report(&pool, 10, now, chrono_tz::UTC).unwrap();
+        assert_eq!(utc.artists[0].active_months, 1);
+        let new_york =
+            generate_tenure_report(&pool, 10, now, chrono_tz::America::New_York).unwrap();
+        assert_eq!(new_york.history_months, 2);
+        assert_eq!(new_york.artists[0].active_months, 2);
+        assert_eq!(
+            new_york.artists[0].last_listen - new_york.artists[0].first_listen,
+            Duration::hours(474)
+        );
+    }
+}
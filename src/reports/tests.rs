@@ -1,23 +1,116 @@
-use super::{generate_all_time_report, generate_monthly_report, generate_yearly_report};
-use tempfile::NamedTempFile;
-
-fn setup_test_db() -> (crate::db::DbPool, NamedTempFile) {
-    let temp_file = NamedTempFile::new().unwrap();
-    let pool = crate::db::create_pool(temp_file.path().to_str().unwrap()).unwrap();
-    crate::db::init_database(&pool).unwrap();
-    (pool, temp_file)
-}
+use super::{
+    generate_all_time_report, generate_half_year_report, generate_monthly_report,
+    generate_quarterly_report, generate_yearly_report,
+};
+use crate::test_utils::setup_pool;
 
 #[test]
 fn test_yearly_report_generation() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
     let result = generate_yearly_report(&pool, 2024);
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_yearly_monthly_breakdown() {
+    let (pool, _temp_file) = setup_pool();
+    for (artist, timestamp) in [
+        ("Old Favorite", "2023-06-01T12:00:00Z"),
+        ("Old Favorite", "2024-01-10T12:00:00Z"),
+        ("Old Favorite", "2024-01-11T12:00:00Z"),
+        ("Newcomer", "2024-01-12T12:00:00Z"),
+        ("Newcomer", "2024-03-05T12:00:00Z"),
+    ] {
+        let scrobble = crate::models::Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            timestamp.parse().unwrap(),
+            "test".to_string(),
+        );
+        crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let report = crate::reports::yearly::generate_yearly_report(
+        &pool,
+        2024,
+        crate::reports::sessions::DEFAULT_SESSION_GAP_MINUTES,
+        crate::reports::locale::Locale::En,
+    )
+    .unwrap();
+    let months = &report.monthly_breakdown;
+
+    assert_eq!(months.len(), 12);
+    assert_eq!(months[0].scrobbles, 3);
+    assert_eq!(months[0].top_artist.as_deref(), Some("Old Favorite"));
+    assert_eq!(months[0].new_artists, 1);
+    assert_eq!(months[1].scrobbles, 0);
+    assert_eq!(months[1].top_artist, None);
+    assert_eq!(months[2].new_artists, 0);
+}
+
+#[test]
+fn test_yearly_sessions_follow_gap() {
+    let (pool, _temp_file) = setup_pool();
+    for timestamp in ["2024-05-01T20:00:00Z", "2024-05-01T20:40:00Z"] {
+        let scrobble = crate::models::Scrobble::new(
+            "Artist".to_string(),
+            timestamp.to_string(),
+            timestamp.parse().unwrap(),
+            "test".to_string(),
+        );
+        crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+    }
+
+    let patterns = |gap| {
+        crate::reports::yearly::generate_yearly_report(
+            &pool,
+            2024,
+            gap,
+            crate::reports::locale::Locale::En,
+        )
+        .unwrap()
+        .listening_patterns
+    };
+
+    // One 40-minute session with the default gap, two single plays with 30
+    assert_eq!(patterns(45).longest_session_minutes, 40);
+    assert_eq!(patterns(30).longest_session_minutes, 0);
+    assert_eq!(patterns(30).avg_session_minutes, 0.0);
+}
+
+#[test]
+fn test_yearly_milestones_are_localized() {
+    let (pool, _temp_file) = setup_pool();
+    let scrobble = crate::models::Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        "2024-05-01T20:00:00Z".parse().unwrap(),
+        "test".to_string(),
+    );
+    crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+
+    let milestones = |locale| {
+        crate::reports::yearly::generate_yearly_report(&pool, 2024, 30, locale)
+            .unwrap()
+            .milestones
+    };
+    let english = milestones(crate::reports::locale::Locale::En);
+    assert_eq!(english[1].title, "Your #1 Artist");
+    assert_eq!(english[1].description, "You played 1 songs");
+    let french = milestones(crate::reports::locale::Locale::Fr);
+    assert_eq!(french[1].title, "Votre artiste n°1");
+    assert_eq!(french[2].value, "1 artistes");
+    // Names aren't translated
+    assert_eq!(french[1].value, "Artist");
+    assert_eq!(
+        french[1].icon,
+        crate::reports::yearly::MilestoneIcon::Microphone
+    );
+}
+
 #[test]
 fn test_yearly_report_invalid_year() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     let result = generate_yearly_report(&pool, 1900);
     assert!(result.is_err());
@@ -28,7 +121,7 @@ fn test_yearly_report_invalid_year() {
 
 #[test]
 fn test_monthly_report_invalid_month() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
 
     let result = generate_monthly_report(&pool, 2024, 0);
     assert!(result.is_err());
@@ -39,7 +132,42 @@ fn test_monthly_report_invalid_month() {
 
 #[test]
 fn test_all_time_report() {
-    let (pool, _temp_file) = setup_test_db();
+    let (pool, _temp_file) = setup_pool();
     let result = generate_all_time_report(&pool);
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_all_time_report_starts_at_first_scrobble() {
+    let (pool, _temp_file) = setup_pool();
+    let scrobble = crate::models::Scrobble::new(
+        "Artist".to_string(),
+        "Track".to_string(),
+        "1998-03-14T12:00:00Z".parse().unwrap(),
+        "test".to_string(),
+    );
+    crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+
+    let report = generate_all_time_report(&pool).unwrap();
+    assert_eq!(report.start_date, scrobble.timestamp);
+    assert_eq!(report.total_scrobbles, 1);
+    assert!(report.span_days > 365 * 25);
+}
+
+#[test]
+fn test_quarter_and_half_year_ranges() {
+    let (pool, _temp_file) = setup_pool();
+
+    let q3 = generate_quarterly_report(&pool, 2024, 3).unwrap();
+    assert_eq!(q3.period, "2024-Q3");
+    assert_eq!(q3.span_days, 92);
+    assert_eq!(q3.start_date.to_rfc3339(), "2024-07-01T00:00:00+00:00");
+    assert_eq!(q3.end_date.to_rfc3339(), "2024-09-30T23:59:59+00:00");
+
+    let h2 = generate_half_year_report(&pool, 2024, 2).unwrap();
+    assert_eq!(h2.start_date.to_rfc3339(), "2024-07-01T00:00:00+00:00");
+    assert_eq!(h2.end_date.to_rfc3339(), "2024-12-31T23:59:59+00:00");
+
+    assert!(generate_quarterly_report(&pool, 2024, 5).is_err());
+    assert!(generate_half_year_report(&pool, 2024, 0).is_err());
+}
@@ -1,13 +1,46 @@
 use crate::db::DbPool;
+use crate::models::Scrobble;
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport, remove_keys};
+use crate::reports::sessions::{DEFAULT_SESSION_GAP_MINUTES, for_each_session};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Label propagation usually settles in a handful of rounds; cap it anyway
+const MAX_PROPAGATION_ROUNDS: usize = 20;
+
+/// Whether transitions are counted between artists only or also between tracks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionLevel {
+    #[default]
+    Artist,
+    Track,
+}
+
+pub struct TransitionsOptions {
+    pub gap_minutes: i64,
+    pub min_count: i64,
+    pub include_self_transitions: bool,
+    pub level: TransitionLevel,
+    /// Maximum number of entries in the top lists
+    pub top_n: usize,
+    /// Maximum number of pairs in the full transitions list
+    pub limit: usize,
+    /// Maximum number of nodes kept in the network graph, by degree
+    pub max_nodes: usize,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TransitionsReport {
+    pub schema_version: u32,
     pub transitions: Vec<Transition>,
     pub top_transitions: Vec<Transition>,
+    pub next_artists: Vec<NextArtist>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_transitions: Option<Vec<TrackTransition>>,
     pub network_data: NetworkGraph,
     pub summary: TransitionsSummary,
 }
@@ -20,10 +53,44 @@ pub struct Transition {
     pub percentage: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackTransition {
+    pub from_artist: String,
+    pub from_track: String,
+    pub to_artist: String,
+    pub to_track: String,
+    pub count: i64,
+    pub percentage: f64,
+}
+
+/// The artist most often played right after `artist`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NextArtist {
+    pub artist: String,
+    pub next_artist: String,
+    pub count: i64,
+    /// Share of `artist`'s outgoing transitions going to `next_artist`
+    pub percentage: f64,
+}
+
+/// An artist often played in the same listening sessions as another
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarArtist {
+    pub artist: String,
+    /// Sessions in which both artists were played
+    pub sessions: i64,
+    /// Times one of the two was played right after the other
+    pub transitions: i64,
+    /// Share of the other artist's sessions that include this one
+    pub percentage: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NetworkGraph {
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
+    /// Number of communities found among the kept nodes
+    pub communities: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,6 +98,10 @@ pub struct Node {
     pub id: String,
     pub label: String,
     pub size: i64,
+    /// Number of distinct artists this one transitions to or from
+    pub degree: usize,
+    /// Community index, 0 being the community with the most plays
+    pub community: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -53,29 +124,88 @@ pub fn generate_transitions_report(
     pool: &DbPool,
     start: Option<DateTime<Utc>>,
     end: Option<DateTime<Utc>>,
-    gap_minutes: i64,
-    min_count: i64,
-    include_self_transitions: bool,
+    options: &TransitionsOptions,
 ) -> Result<TransitionsReport> {
-    // Get scrobbles from database
-    let scrobbles = if let (Some(s), Some(e)) = (start, end) {
-        crate::db::get_scrobbles_in_range(pool, s, e)?
-    } else {
-        crate::db::get_scrobbles(pool, Some(1_000_000), Some(0))?
-    };
+    let TransitionsOptions {
+        gap_minutes,
+        min_count,
+        include_self_transitions,
+        level,
+        top_n,
+        limit,
+        max_nodes,
+    } = *options;
 
     // Extract transitions directly from scrobbles
     let mut transition_counts: HashMap<(String, String), i64> = HashMap::new();
+    let mut track_transition_counts: HashMap<TrackPair, i64> = HashMap::new();
     let mut artist_counts: HashMap<String, i64> = HashMap::new();
     let mut session_count = 0;
 
-    if scrobbles.is_empty() {
+    // Stream scrobbles oldest first and detect transitions based on gap
+    let mut prev_scrobble: Option<Scrobble> = None;
+    let mut current_session_has_transition = false;
+
+    crate::db::for_each_scrobble_in_range(
+        pool,
+        start.unwrap_or(DateTime::<Utc>::MIN_UTC),
+        end.unwrap_or(DateTime::<Utc>::MAX_UTC),
+        |curr_scrobble| {
+            if let Some(prev_scrobble) = &prev_scrobble {
+                // Calculate gap between consecutive scrobbles in minutes
+                let gap = (curr_scrobble.timestamp - prev_scrobble.timestamp).num_minutes();
+
+                // If gap is too large, start a new session
+                if gap > gap_minutes {
+                    if current_session_has_transition {
+                        session_count += 1;
+                    }
+                    current_session_has_transition = false;
+                } else {
+                    // Within same session, count transition
+                    let from = &prev_scrobble.artist;
+                    let to = &curr_scrobble.artist;
+
+                    // Skip self-transitions if not requested
+                    if include_self_transitions || from != to {
+                        let key = (from.clone(), to.clone());
+                        *transition_counts.entry(key).or_insert(0) += 1;
+                        current_session_has_transition = true;
+
+                        // Count artist appearances
+                        *artist_counts.entry(from.clone()).or_insert(0) += 1;
+                    }
+
+                    if level == TransitionLevel::Track {
+                        let from = (&prev_scrobble.artist, &prev_scrobble.track);
+                        let to = (&curr_scrobble.artist, &curr_scrobble.track);
+
+                        if include_self_transitions || from != to {
+                            let key = (
+                                (from.0.clone(), from.1.clone()),
+                                (to.0.clone(), to.1.clone()),
+                            );
+                            *track_transition_counts.entry(key).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+
+            prev_scrobble = Some(curr_scrobble);
+        },
+    )?;
+
+    let Some(last_scrobble) = prev_scrobble else {
         return Ok(TransitionsReport {
+            schema_version: REPORT_SCHEMA_VERSION,
             transitions: vec![],
             top_transitions: vec![],
+            next_artists: vec![],
+            track_transitions: (level == TransitionLevel::Track).then(Vec::new),
             network_data: NetworkGraph {
                 nodes: vec![],
                 edges: vec![],
+                communities: 0,
             },
             summary: TransitionsSummary {
                 total_transitions: 0,
@@ -85,40 +215,7 @@ pub fn generate_transitions_report(
                 avg_transitions_per_session: 0.0,
             },
         });
-    }
-
-    // Process scrobbles and detect transitions based on gap
-    let mut prev_scrobble = &scrobbles[0];
-    let mut current_session_has_transition = false;
-
-    for curr_scrobble in scrobbles.iter().skip(1) {
-        // Calculate gap between consecutive scrobbles in minutes
-        let gap = (curr_scrobble.timestamp - prev_scrobble.timestamp).num_minutes();
-
-        // If gap is too large, start a new session
-        if gap > gap_minutes {
-            if current_session_has_transition {
-                session_count += 1;
-            }
-            current_session_has_transition = false;
-        } else {
-            // Within same session, count transition
-            let from = &prev_scrobble.artist;
-            let to = &curr_scrobble.artist;
-
-            // Skip self-transitions if not requested
-            if include_self_transitions || from != to {
-                let key = (from.clone(), to.clone());
-                *transition_counts.entry(key).or_insert(0) += 1;
-                current_session_has_transition = true;
-
-                // Count artist appearances
-                *artist_counts.entry(from.clone()).or_insert(0) += 1;
-            }
-        }
-
-        prev_scrobble = curr_scrobble;
-    }
+    };
 
     // Count last session if it had transitions
     if current_session_has_transition {
@@ -126,9 +223,7 @@ pub fn generate_transitions_report(
     }
 
     // Count last artist
-    *artist_counts
-        .entry(scrobbles.last().unwrap().artist.clone())
-        .or_insert(0) += 1;
+    *artist_counts.entry(last_scrobble.artist).or_insert(0) += 1;
 
     // Build transitions list
     let total_transitions: i64 = transition_counts.values().sum();
@@ -148,13 +243,15 @@ pub fn generate_transitions_report(
         .collect();
 
     // Sort by count descending
-    transitions.sort_by(|a, b| b.count.cmp(&a.count));
+    transitions.sort_by_key(|t| std::cmp::Reverse(t.count));
 
-    // Get top transitions (limit to 50)
-    let top_transitions: Vec<Transition> = transitions.iter().take(50).cloned().collect();
+    let top_transitions: Vec<Transition> = transitions.iter().take(top_n).cloned().collect();
+    let next_artists = most_common_next_artists(&transition_counts, min_count, top_n);
+    let track_transitions = (level == TransitionLevel::Track)
+        .then(|| build_track_transitions(&track_transition_counts, min_count, top_n));
 
     // Build network graph
-    let network_data = build_network_graph(&transitions, &artist_counts);
+    let network_data = build_network_graph(&transitions, &artist_counts, max_nodes);
 
     // Compute summary
     let summary = compute_summary(
@@ -164,20 +261,191 @@ pub fn generate_transitions_report(
         total_transitions,
     );
 
+    // The graph and summary cover every pair; the listing is capped
+    transitions.truncate(limit);
+
     Ok(TransitionsReport {
+        schema_version: REPORT_SCHEMA_VERSION,
         transitions,
         top_transitions,
+        next_artists,
+        track_transitions,
         network_data,
         summary,
     })
 }
 
+type TrackPair = ((String, String), (String, String));
+
+/// Track-to-track pairs seen at least `min_count` times, most common first
+fn build_track_transitions(
+    counts: &HashMap<TrackPair, i64>,
+    min_count: i64,
+    top_n: usize,
+) -> Vec<TrackTransition> {
+    let total: i64 = counts.values().sum();
+    let mut transitions: Vec<TrackTransition> = counts
+        .iter()
+        .filter(|&(_, &count)| count >= min_count)
+        .map(
+            |(((from_artist, from_track), (to_artist, to_track)), &count)| TrackTransition {
+                from_artist: from_artist.clone(),
+                from_track: from_track.clone(),
+                to_artist: to_artist.clone(),
+                to_track: to_track.clone(),
+                count,
+                percentage: if total > 0 {
+                    (count as f64 / total as f64) * 100.0
+                } else {
+                    0.0
+                },
+            },
+        )
+        .collect();
+
+    transitions.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.from_artist.cmp(&b.from_artist))
+            .then_with(|| a.from_track.cmp(&b.from_track))
+    });
+    transitions.truncate(top_n);
+    transitions
+}
+
+/// For each artist, the artist it most often leads to, keeping the `top_n`
+/// strongest pairs
+fn most_common_next_artists(
+    transition_counts: &HashMap<(String, String), i64>,
+    min_count: i64,
+    top_n: usize,
+) -> Vec<NextArtist> {
+    let mut outgoing: HashMap<&str, i64> = HashMap::new();
+    let mut best: HashMap<&str, (&str, i64)> = HashMap::new();
+
+    for ((from, to), &count) in transition_counts {
+        *outgoing.entry(from).or_insert(0) += count;
+
+        let entry = best.entry(from).or_insert((to, count));
+        if count > entry.1 || (count == entry.1 && to.as_str() < entry.0) {
+            *entry = (to, count);
+        }
+    }
+
+    let mut next_artists: Vec<NextArtist> = best
+        .into_iter()
+        .filter(|&(_, (_, count))| count >= min_count)
+        .map(|(artist, (next_artist, count))| NextArtist {
+            artist: artist.to_string(),
+            next_artist: next_artist.to_string(),
+            count,
+            percentage: (count as f64 / outgoing[artist] as f64) * 100.0,
+        })
+        .collect();
+
+    next_artists.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.artist.cmp(&b.artist)));
+    next_artists.truncate(top_n);
+    next_artists
+}
+
+/// Artists most often played near `artist`, ranked by the sessions they
+/// share with it and then by direct transitions between the two
+pub fn similar_artists(
+    pool: &DbPool,
+    artist: &str,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Vec<SimilarArtist>> {
+    let mut tally = SimilarArtistsTally::new(artist);
+    for_each_session(
+        pool,
+        start.unwrap_or(DateTime::<Utc>::MIN_UTC),
+        end.unwrap_or(DateTime::<Utc>::MAX_UTC),
+        DEFAULT_SESSION_GAP_MINUTES,
+        |session| tally.add_session(&session),
+    )?;
+    Ok(tally.rank(limit))
+}
+
+/// Sessions and transitions each artist shares with one artist, counted a
+/// session at a time
+struct SimilarArtistsTally<'a> {
+    artist: &'a str,
+    shared_sessions: HashMap<String, i64>,
+    transitions: HashMap<String, i64>,
+    artist_sessions: i64,
+}
+
+impl<'a> SimilarArtistsTally<'a> {
+    fn new(artist: &'a str) -> Self {
+        Self {
+            artist,
+            shared_sessions: HashMap::new(),
+            transitions: HashMap::new(),
+            artist_sessions: 0,
+        }
+    }
+
+    fn add_session(&mut self, session: &[Scrobble]) {
+        let artist = self.artist;
+        if !session.iter().any(|s| s.artist == artist) {
+            return;
+        }
+        self.artist_sessions += 1;
+
+        let others: HashSet<&str> = session
+            .iter()
+            .map(|s| s.artist.as_str())
+            .filter(|&a| a != artist)
+            .collect();
+        for other in others {
+            *self.shared_sessions.entry(other.to_string()).or_insert(0) += 1;
+        }
+
+        for pair in session.windows(2) {
+            let (from, to) = (&pair[0].artist, &pair[1].artist);
+            if from == artist && to != artist {
+                *self.transitions.entry(to.clone()).or_insert(0) += 1;
+            } else if to == artist && from != artist {
+                *self.transitions.entry(from.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn rank(self, limit: usize) -> Vec<SimilarArtist> {
+        let transitions = self.transitions;
+        let artist_sessions = self.artist_sessions;
+        let mut similar: Vec<SimilarArtist> = self
+            .shared_sessions
+            .into_iter()
+            .map(|(name, sessions)| SimilarArtist {
+                transitions: transitions.get(&name).copied().unwrap_or(0),
+                percentage: (sessions as f64 / artist_sessions as f64) * 100.0,
+                artist: name,
+                sessions,
+            })
+            .collect();
+
+        similar.sort_by(|a, b| {
+            b.sessions
+                .cmp(&a.sessions)
+                .then_with(|| b.transitions.cmp(&a.transitions))
+                .then_with(|| a.artist.cmp(&b.artist))
+        });
+        similar.truncate(limit);
+        similar
+    }
+}
+
 fn build_network_graph(
     transitions: &[Transition],
     artist_counts: &HashMap<String, i64>,
+    max_nodes: usize,
 ) -> NetworkGraph {
     // Build nodes from unique artists in transitions
     let mut nodes_map: HashMap<String, i64> = HashMap::new();
+    let mut neighbors: HashMap<&str, HashSet<&str>> = HashMap::new();
 
     for transition in transitions {
         *nodes_map.entry(transition.from_artist.clone()).or_insert(0) += artist_counts
@@ -188,20 +456,45 @@ fn build_network_graph(
             .get(&transition.to_artist)
             .copied()
             .unwrap_or(0);
+
+        if transition.from_artist != transition.to_artist {
+            neighbors
+                .entry(&transition.from_artist)
+                .or_default()
+                .insert(&transition.to_artist);
+            neighbors
+                .entry(&transition.to_artist)
+                .or_default()
+                .insert(&transition.from_artist);
+        }
     }
 
-    let nodes: Vec<Node> = nodes_map
+    let mut nodes: Vec<Node> = nodes_map
         .into_iter()
         .map(|(artist, size)| Node {
+            degree: neighbors.get(artist.as_str()).map_or(0, |n| n.len()),
             id: artist.clone(),
             label: artist,
             size,
+            community: 0,
         })
         .collect();
 
-    // Build edges from transitions
+    // Keep the best connected artists so large libraries stay drawable
+    nodes.sort_by(|a, b| {
+        b.degree
+            .cmp(&a.degree)
+            .then_with(|| b.size.cmp(&a.size))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    nodes.truncate(max_nodes);
+
+    let kept: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+
+    // Build edges from transitions between kept nodes
     let edges: Vec<Edge> = transitions
         .iter()
+        .filter(|t| kept.contains(t.from_artist.as_str()) && kept.contains(t.to_artist.as_str()))
         .map(|t| Edge {
             source: t.from_artist.clone(),
             target: t.to_artist.clone(),
@@ -209,7 +502,98 @@ fn build_network_graph(
         })
         .collect();
 
-    NetworkGraph { nodes, edges }
+    let communities = label_communities(&mut nodes, &edges);
+
+    NetworkGraph {
+        nodes,
+        edges,
+        communities,
+    }
+}
+
+/// Assign each node a community by weighted label propagation over the
+/// undirected graph, returning the number of communities.
+///
+/// Nodes are visited in id order and ties go to the smallest label, so the
+/// result is deterministic. Communities are renumbered by total plays.
+fn label_communities(nodes: &mut [Node], edges: &[Edge]) -> usize {
+    let mut order: Vec<usize> = (0..nodes.len()).collect();
+    order.sort_by(|&a, &b| nodes[a].id.cmp(&nodes[b].id));
+
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), i))
+        .collect();
+
+    let mut adjacency: Vec<Vec<(usize, i64)>> = vec![Vec::new(); nodes.len()];
+    for edge in edges {
+        let (Some(&a), Some(&b)) = (
+            index.get(edge.source.as_str()),
+            index.get(edge.target.as_str()),
+        ) else {
+            continue;
+        };
+        if a != b {
+            adjacency[a].push((b, edge.weight));
+            adjacency[b].push((a, edge.weight));
+        }
+    }
+
+    let mut labels: Vec<usize> = (0..nodes.len()).collect();
+    for _ in 0..MAX_PROPAGATION_ROUNDS {
+        let mut changed = false;
+
+        for &node in &order {
+            let mut weights: BTreeMap<usize, i64> = BTreeMap::new();
+            for &(neighbor, weight) in &adjacency[node] {
+                *weights.entry(labels[neighbor]).or_insert(0) += weight;
+            }
+
+            // BTreeMap iterates labels in ascending order, so the first
+            // maximum wins ties
+            let best =
+                weights
+                    .into_iter()
+                    .fold(
+                        None,
+                        |best: Option<(usize, i64)>, (label, weight)| match best {
+                            Some((_, w)) if w >= weight => best,
+                            _ => Some((label, weight)),
+                        },
+                    );
+
+            if let Some((label, _)) = best
+                && label != labels[node]
+            {
+                labels[node] = label;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut community_sizes: HashMap<usize, i64> = HashMap::new();
+    for (node, &label) in nodes.iter().zip(&labels) {
+        *community_sizes.entry(label).or_insert(0) += node.size;
+    }
+
+    let mut ranked: Vec<(usize, i64)> = community_sizes.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let renumbered: HashMap<usize, usize> = ranked
+        .iter()
+        .enumerate()
+        .map(|(community, &(label, _))| (label, community))
+        .collect();
+
+    for (node, label) in nodes.iter_mut().zip(labels) {
+        node.community = renumbered[&label];
+    }
+
+    ranked.len()
 }
 
 fn compute_summary(
@@ -241,9 +625,26 @@ fn compute_summary(
     }
 }
 
+impl VersionedReport for TransitionsReport {
+    fn downgrade_to_v1(object: &mut Map<String, Value>) {
+        remove_keys(object, &["next_artists", "track_transitions"]);
+
+        if let Some(Value::Object(network)) = object.get_mut("network_data") {
+            remove_keys(network, &["communities"]);
+            if let Some(Value::Array(nodes)) = network.get_mut("nodes") {
+                for node in nodes.iter_mut().filter_map(Value::as_object_mut) {
+                    remove_keys(node, &["degree", "community"]);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::reports::sessions::detect_sessions;
+    use crate::test_utils::setup_pool;
 
     #[test]
     fn test_transition_extraction() {
@@ -264,6 +665,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_report_splits_whole_history_into_sessions() {
+        let (pool, _temp_file) = setup_pool();
+
+        let start: DateTime<Utc> = "2024-03-01T20:00:00Z".parse().unwrap();
+        for (artist, minutes) in [("A", 0), ("B", 4), ("A", 24 * 60), ("C", 24 * 60 + 4)] {
+            let scrobble = Scrobble::new(
+                artist.to_string(),
+                "Track".to_string(),
+                start + chrono::Duration::minutes(minutes),
+                "lastfm".to_string(),
+            );
+            crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+        }
+
+        let options = TransitionsOptions {
+            gap_minutes: 45,
+            min_count: 1,
+            include_self_transitions: false,
+            level: TransitionLevel::Artist,
+            top_n: 10,
+            limit: 1,
+            max_nodes: 10,
+        };
+        let report = generate_transitions_report(&pool, None, None, &options).unwrap();
+
+        // B to A spans a day, so it isn't a transition
+        assert_eq!(report.summary.total_transitions, 2);
+        assert_eq!(report.summary.avg_transitions_per_session, 1.0);
+        // Only the listing is capped
+        assert_eq!(report.transitions.len(), 1);
+        assert_eq!(report.summary.unique_transitions, 2);
+    }
+
     #[test]
     fn test_self_transitions_excluded() {
         let mut transition_counts: HashMap<(String, String), i64> = HashMap::new();
@@ -311,7 +746,7 @@ mod tests {
         artist_counts.insert("Artist B".to_string(), 20);
         artist_counts.insert("Artist C".to_string(), 10);
 
-        let network = build_network_graph(&transitions, &artist_counts);
+        let network = build_network_graph(&transitions, &artist_counts, 100);
 
         assert_eq!(network.nodes.len(), 3);
         assert_eq!(network.edges.len(), 2);
@@ -322,5 +757,147 @@ mod tests {
             .find(|e| e.source == "Artist A" && e.target == "Artist B")
             .unwrap();
         assert_eq!(edge_ab.weight, 10);
+        assert_eq!(
+            network
+                .nodes
+                .iter()
+                .find(|n| n.id == "Artist B")
+                .unwrap()
+                .degree,
+            2
+        );
+    }
+
+    fn transition(from: &str, to: &str, count: i64) -> Transition {
+        Transition {
+            from_artist: from.to_string(),
+            to_artist: to.to_string(),
+            count,
+            percentage: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_network_graph_pruned_by_degree() {
+        let transitions = vec![
+            transition("Hub", "A", 3),
+            transition("Hub", "B", 3),
+            transition("Hub", "C", 3),
+            transition("A", "B", 1),
+        ];
+        let artist_counts = HashMap::new();
+
+        let network = build_network_graph(&transitions, &artist_counts, 3);
+
+        let ids: HashSet<&str> = network.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, HashSet::from(["Hub", "A", "B"]));
+        // Edges touching the pruned node are dropped
+        assert_eq!(network.edges.len(), 3);
+    }
+
+    #[test]
+    fn test_label_propagation_finds_two_clusters() {
+        let transitions = vec![
+            transition("A1", "A2", 10),
+            transition("A2", "A3", 10),
+            transition("A3", "A1", 10),
+            transition("B1", "B2", 10),
+            transition("B2", "B3", 10),
+            transition("B3", "B1", 10),
+            transition("A3", "B1", 1),
+        ];
+        let artist_counts = HashMap::from([("A1".to_string(), 100)]);
+
+        let network = build_network_graph(&transitions, &artist_counts, 100);
+        let community = |id: &str| network.nodes.iter().find(|n| n.id == id).unwrap().community;
+
+        assert_eq!(network.communities, 2);
+        assert_eq!(community("A1"), community("A2"));
+        assert_eq!(community("A1"), community("A3"));
+        assert_eq!(community("B1"), community("B3"));
+        assert_ne!(community("A1"), community("B1"));
+        // The cluster with the most plays comes first
+        assert_eq!(community("A1"), 0);
+    }
+
+    #[test]
+    fn test_most_common_next_artists() {
+        let mut transition_counts = HashMap::new();
+        transition_counts.insert(("A".to_string(), "B".to_string()), 6);
+        transition_counts.insert(("A".to_string(), "C".to_string()), 2);
+        transition_counts.insert(("B".to_string(), "C".to_string()), 3);
+        transition_counts.insert(("C".to_string(), "A".to_string()), 1);
+
+        let next = most_common_next_artists(&transition_counts, 2, 10);
+
+        // C -> A is below min_count
+        assert_eq!(next.len(), 2);
+        assert_eq!(next[0].artist, "A");
+        assert_eq!(next[0].next_artist, "B");
+        assert_eq!(next[0].percentage, 75.0);
+        assert_eq!(next[1].artist, "B");
+
+        assert_eq!(most_common_next_artists(&transition_counts, 1, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_similar_artists_ranked_by_shared_sessions() {
+        let start: DateTime<Utc> = "2024-03-01T20:00:00Z".parse().unwrap();
+        let play = |artist: &str, minutes: i64| {
+            Scrobble::new(
+                artist.to_string(),
+                "Track".to_string(),
+                start + chrono::Duration::minutes(minutes),
+                "lastfm".to_string(),
+            )
+        };
+        let scrobbles = vec![
+            // Two sessions with Low, one of them shared with Codeine
+            play("Low", 0),
+            play("Galaxie 500", 4),
+            play("Codeine", 8),
+            play("Low", 12),
+            play("Galaxie 500", 24 * 60),
+            play("Low", 24 * 60 + 4),
+            // Never played alongside Low
+            play("Slint", 48 * 60),
+            play("Codeine", 48 * 60 + 4),
+        ];
+
+        let mut tally = SimilarArtistsTally::new("Low");
+        for session in detect_sessions(scrobbles, |s| s.timestamp, 45) {
+            tally.add_session(&session);
+        }
+        let similar = tally.rank(10);
+
+        assert_eq!(similar.len(), 2);
+        assert_eq!(similar[0].artist, "Galaxie 500");
+        assert_eq!((similar[0].sessions, similar[0].transitions), (2, 2));
+        assert_eq!(similar[0].percentage, 100.0);
+        assert_eq!(similar[1].artist, "Codeine");
+        assert_eq!((similar[1].sessions, similar[1].transitions), (1, 1));
+        assert_eq!(similar[1].percentage, 50.0);
+    }
+
+    #[test]
+    fn test_track_transitions_bounded() {
+        let pair = |a: &str, b: &str| {
+            (
+                ("Artist".to_string(), a.to_string()),
+                ("Artist".to_string(), b.to_string()),
+            )
+        };
+        let mut counts = HashMap::new();
+        counts.insert(pair("One", "Two"), 3);
+        counts.insert(pair("Two", "Three"), 5);
+        counts.insert(pair("Three", "One"), 1);
+
+        let transitions = build_track_transitions(&counts, 2, 10);
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].from_track, "Two");
+        assert_eq!(transitions[0].to_track, "Three");
+        assert_eq!(transitions[0].percentage, 5.0 / 9.0 * 100.0);
+
+        assert_eq!(build_track_transitions(&counts, 1, 2).len(), 2);
     }
 }
@@ -1,12 +1,19 @@
 use crate::db::DbPool;
-use crate::models::Scrobble;
+use crate::models::{MilestoneRule, Scrobble};
+use crate::reports::locale::Locale;
+use crate::reports::milestones::{YearFigures, award_milestones};
+use crate::reports::schema::{REPORT_SCHEMA_VERSION, VersionedReport, remove_keys};
+use crate::reports::sessions::detect_sessions;
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct YearlyReport {
+    pub schema_version: u32,
     pub year: i32,
     pub overview: YearOverview,
     pub top_content: TopContent,
@@ -14,6 +21,7 @@ pub struct YearlyReport {
     pub discoveries: Discoveries,
     pub diversity: DiversityStats,
     pub milestones: Vec<Milestone>,
+    pub monthly_breakdown: Vec<MonthSummary>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -100,15 +108,111 @@ pub struct DiversityStats {
     pub exploration_score: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthSummary {
+    pub month: u32,
+    pub scrobbles: i64,
+    pub top_artist: Option<String>,
+    pub new_artists: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Milestone {
+    /// Rule that awarded it: a built-in name such as `night_owl`, or
+    /// `rule:<id>` for a custom rule
+    #[serde(skip)]
+    pub key: String,
     pub title: String,
     pub description: String,
     pub value: String,
-    pub icon: String,
+    pub icon: MilestoneIcon,
+}
+
+/// Symbolic icon of a milestone; drawing it is up to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneIcon {
+    Stopwatch,
+    Microphone,
+    Map,
+    Owl,
+    Bird,
+    Runner,
+    Trophy,
+    Calendar,
+}
+
+impl MilestoneIcon {
+    /// The emoji milestones used to carry, for clients built against them
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            MilestoneIcon::Stopwatch => "\u{23f1}\u{fe0f}",
+            MilestoneIcon::Microphone => "\u{1f3a4}",
+            MilestoneIcon::Map => "\u{1f5fa}\u{fe0f}",
+            MilestoneIcon::Owl => "\u{1f989}",
+            MilestoneIcon::Bird => "\u{1f426}",
+            MilestoneIcon::Runner => "\u{1f3c3}",
+            MilestoneIcon::Trophy => "\u{1f3c6}",
+            MilestoneIcon::Calendar => "\u{1f4c5}",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MilestoneIcon::Stopwatch => "stopwatch",
+            MilestoneIcon::Microphone => "microphone",
+            MilestoneIcon::Map => "map",
+            MilestoneIcon::Owl => "owl",
+            MilestoneIcon::Bird => "bird",
+            MilestoneIcon::Runner => "runner",
+            MilestoneIcon::Trophy => "trophy",
+            MilestoneIcon::Calendar => "calendar",
+        }
+    }
 }
 
-pub fn generate_yearly_report(pool: &DbPool, year: i32) -> Result<YearlyReport> {
+impl FromStr for MilestoneIcon {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "stopwatch" => Ok(MilestoneIcon::Stopwatch),
+            "microphone" => Ok(MilestoneIcon::Microphone),
+            "map" => Ok(MilestoneIcon::Map),
+            "owl" => Ok(MilestoneIcon::Owl),
+            "bird" => Ok(MilestoneIcon::Bird),
+            "runner" => Ok(MilestoneIcon::Runner),
+            "trophy" => Ok(MilestoneIcon::Trophy),
+            "calendar" => Ok(MilestoneIcon::Calendar),
+            other => Err(anyhow::anyhow!("Unknown milestone icon: {}", other)),
+        }
+    }
+}
+
+/// Replace the symbolic milestone icons of a rendered yearly report with
+/// the emoji they stand for, the shape the `icon` field used to have
+pub fn use_emoji_icons(object: &mut Map<String, Value>) {
+    let Some(Value::Array(milestones)) = object.get_mut("milestones") else {
+        return;
+    };
+    for milestone in milestones {
+        let Some(icon) = milestone.get_mut("icon") else {
+            continue;
+        };
+        if let Ok(symbol) = serde_json::from_value::<MilestoneIcon>(icon.clone()) {
+            *icon = symbol.emoji().into();
+        }
+    }
+}
+
+/// The year in review, with milestone text in `locale` and the custom
+/// milestone rules checked after the built-in ones
+pub fn generate_yearly_report(
+    pool: &DbPool,
+    year: i32,
+    gap_minutes: i64,
+    locale: Locale,
+) -> Result<YearlyReport> {
     let start = format!("{}-01-01T00:00:00Z", year).parse()?;
     let end = format!("{}-12-31T23:59:59Z", year).parse()?;
 
@@ -120,12 +224,23 @@ pub fn generate_yearly_report(pool: &DbPool, year: i32) -> Result<YearlyReport>
 
     let overview = compute_overview(&scrobbles, year);
     let top_content = compute_top_content(&scrobbles);
-    let listening_patterns = compute_listening_patterns(&scrobbles);
+    let listening_patterns = compute_listening_patterns(&scrobbles, gap_minutes);
     let discoveries = compute_discoveries(&scrobbles, pool, year)?;
     let diversity = compute_diversity_stats(&scrobbles);
-    let milestones = compute_milestones(&overview, &top_content, &listening_patterns, &discoveries);
+    let rules = crate::db::get_milestone_rules(pool)?;
+    let milestones = compute_milestones(
+        &scrobbles,
+        &overview,
+        &top_content,
+        &listening_patterns,
+        &discoveries,
+        &rules,
+        locale,
+    );
+    let monthly_breakdown = compute_monthly_breakdown(&scrobbles, pool, year)?;
 
     Ok(YearlyReport {
+        schema_version: REPORT_SCHEMA_VERSION,
         year,
         overview,
         top_content,
@@ -133,6 +248,7 @@ pub fn generate_yearly_report(pool: &DbPool, year: i32) -> Result<YearlyReport>
         discoveries,
         diversity,
         milestones,
+        monthly_breakdown,
     })
 }
 
@@ -204,7 +320,7 @@ fn compute_top_content(scrobbles: &[Scrobble]) -> TopContent {
 
     let total_scrobbles = scrobbles.len() as f64;
     let mut top_artists: Vec<_> = artist_counts.into_iter().collect();
-    top_artists.sort_by(|a, b| b.1.cmp(&a.1));
+    top_artists.sort_by_key(|a| std::cmp::Reverse(a.1));
 
     let top_artists: Vec<TopArtist> = top_artists
         .into_iter()
@@ -227,7 +343,7 @@ fn compute_top_content(scrobbles: &[Scrobble]) -> TopContent {
     }
 
     let mut top_tracks: Vec<_> = track_counts.into_iter().collect();
-    top_tracks.sort_by(|a, b| b.1.cmp(&a.1));
+    top_tracks.sort_by_key(|t| std::cmp::Reverse(t.1));
 
     let top_tracks: Vec<TopTrack> = top_tracks
         .into_iter()
@@ -252,7 +368,7 @@ fn compute_top_content(scrobbles: &[Scrobble]) -> TopContent {
     }
 
     let mut top_albums: Vec<_> = album_counts.into_iter().collect();
-    top_albums.sort_by(|a, b| b.1.cmp(&a.1));
+    top_albums.sort_by_key(|a| std::cmp::Reverse(a.1));
 
     let top_albums: Vec<TopAlbum> = top_albums
         .into_iter()
@@ -273,7 +389,7 @@ fn compute_top_content(scrobbles: &[Scrobble]) -> TopContent {
     }
 }
 
-fn compute_listening_patterns(scrobbles: &[Scrobble]) -> ListeningPatterns {
+fn compute_listening_patterns(scrobbles: &[Scrobble], gap_minutes: i64) -> ListeningPatterns {
     // Hour distribution
     let mut hour_counts: HashMap<u32, i64> = HashMap::new();
     for scrobble in scrobbles {
@@ -298,28 +414,18 @@ fn compute_listening_patterns(scrobbles: &[Scrobble]) -> ListeningPatterns {
         .map(|(day, _)| *day)
         .unwrap_or(0);
 
-    // Sessions (simple: gap > 30 min = new session)
-    let mut sorted_scrobbles = scrobbles.to_vec();
+    // Sessions, split the same way as everywhere else
+    let mut sorted_scrobbles: Vec<&Scrobble> = scrobbles.iter().collect();
     sorted_scrobbles.sort_by_key(|s| s.timestamp);
 
-    let mut session_durations = Vec::new();
-    let mut current_session_start = sorted_scrobbles[0].timestamp;
-
-    for window in sorted_scrobbles.windows(2) {
-        let gap = window[1]
-            .timestamp
-            .signed_duration_since(window[0].timestamp)
-            .num_minutes();
-
-        if gap > 30 {
-            let duration = window[0]
-                .timestamp
-                .signed_duration_since(current_session_start)
-                .num_minutes();
-            session_durations.push(duration);
-            current_session_start = window[1].timestamp;
-        }
-    }
+    let session_durations: Vec<i64> =
+        detect_sessions(sorted_scrobbles, |s| s.timestamp, gap_minutes)
+            .iter()
+            .map(|session| match (session.first(), session.last()) {
+                (Some(first), Some(last)) => (last.timestamp - first.timestamp).num_minutes(),
+                _ => 0,
+            })
+            .collect();
 
     let longest_session_minutes = session_durations.iter().max().copied().unwrap_or(0);
     let avg_session_minutes = if !session_durations.is_empty() {
@@ -345,21 +451,10 @@ fn compute_listening_patterns(scrobbles: &[Scrobble]) -> ListeningPatterns {
 }
 
 fn compute_discoveries(scrobbles: &[Scrobble], pool: &DbPool, year: i32) -> Result<Discoveries> {
-    // Get all scrobbles before this year to determine what's "new"
+    // Everything first heard before this year is already "seen"
     let year_start: DateTime<Utc> = format!("{}-01-01T00:00:00Z", year).parse()?;
-    let all_time_scrobbles = crate::db::get_scrobbles(pool, Some(1_000_000), Some(0))?;
-
-    let mut seen_artists: std::collections::HashSet<String> = std::collections::HashSet::new();
-    let mut seen_tracks: std::collections::HashSet<(String, String)> =
-        std::collections::HashSet::new();
-
-    // Mark everything before year as "seen"
-    for scrobble in &all_time_scrobbles {
-        if scrobble.timestamp < year_start {
-            seen_artists.insert(scrobble.artist.clone());
-            seen_tracks.insert((scrobble.artist.clone(), scrobble.track.clone()));
-        }
-    }
+    let mut seen_artists = crate::db::get_artists_first_heard_before(pool, year_start)?;
+    let mut seen_tracks = crate::db::get_tracks_first_heard_before(pool, year_start)?;
 
     let mut new_artists = 0i64;
     let mut new_tracks = 0i64;
@@ -412,6 +507,43 @@ fn compute_discoveries(scrobbles: &[Scrobble], pool: &DbPool, year: i32) -> Resu
     })
 }
 
+/// Scrobbles, top artist and newly discovered artists for each of the 12 months
+fn compute_monthly_breakdown(
+    scrobbles: &[Scrobble],
+    pool: &DbPool,
+    year: i32,
+) -> Result<Vec<MonthSummary>> {
+    let year_start: DateTime<Utc> = format!("{}-01-01T00:00:00Z", year).parse()?;
+    let mut seen_artists = crate::db::get_artists_first_heard_before(pool, year_start)?;
+
+    let mut artist_counts: Vec<HashMap<&str, i64>> = vec![HashMap::new(); 12];
+    let mut new_artists = [0i64; 12];
+
+    for scrobble in scrobbles {
+        let month = scrobble.timestamp.month0() as usize;
+        *artist_counts[month].entry(&scrobble.artist).or_insert(0) += 1;
+
+        if seen_artists.insert(scrobble.artist.clone()) {
+            new_artists[month] += 1;
+        }
+    }
+
+    Ok(artist_counts
+        .into_iter()
+        .zip(new_artists)
+        .enumerate()
+        .map(|(month, (counts, new_artists))| MonthSummary {
+            month: month as u32 + 1,
+            scrobbles: counts.values().sum(),
+            top_artist: counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(artist, _)| artist.to_string()),
+            new_artists,
+        })
+        .collect())
+}
+
 fn compute_diversity_stats(scrobbles: &[Scrobble]) -> DiversityStats {
     let unique_artists: std::collections::HashSet<_> =
         scrobbles.iter().map(|s| s.artist.as_str()).collect();
@@ -443,68 +575,33 @@ fn compute_diversity_stats(scrobbles: &[Scrobble]) -> DiversityStats {
 }
 
 fn compute_milestones(
+    scrobbles: &[Scrobble],
     overview: &YearOverview,
     top_content: &TopContent,
     patterns: &ListeningPatterns,
     discoveries: &Discoveries,
+    rules: &[MilestoneRule],
+    locale: Locale,
 ) -> Vec<Milestone> {
-    let mut milestones = Vec::new();
-
-    // Total listening milestone
-    let hours = overview.total_minutes / 60;
-    milestones.push(Milestone {
-        title: "Music Marathon".to_string(),
-        description: format!("You listened to {} hours of music", hours),
-        value: format!("{} hours", hours),
-        icon: "⏱️".to_string(),
-    });
-
-    // Top artist milestone
-    if let Some(top_artist) = top_content.top_artists.first() {
-        milestones.push(Milestone {
-            title: "Your #1 Artist".to_string(),
-            description: format!("You played {} songs", top_artist.play_count),
-            value: top_artist.artist.clone(),
-            icon: "🎤".to_string(),
-        });
-    }
-
-    // Discovery milestone
-    milestones.push(Milestone {
-        title: "Explorer".to_string(),
-        description: format!("You discovered {} new artists", discoveries.new_artists),
-        value: format!("{} artists", discoveries.new_artists),
-        icon: "🗺️".to_string(),
-    });
-
-    // Personality trait
-    if patterns.night_owl_score > 60.0 {
-        milestones.push(Milestone {
-            title: "Night Owl".to_string(),
-            description: "Most of your listening happens after 8 PM".to_string(),
-            value: format!("{}% night listening", patterns.night_owl_score as i32),
-            icon: "🦉".to_string(),
-        });
-    } else if patterns.early_bird_score > 60.0 {
-        milestones.push(Milestone {
-            title: "Early Bird".to_string(),
-            description: "You love morning music sessions".to_string(),
-            value: format!("{}% morning listening", patterns.early_bird_score as i32),
-            icon: "🐦".to_string(),
-        });
+    let top_artist = top_content.top_artists.first();
+    let active_days: BTreeSet<NaiveDate> =
+        scrobbles.iter().map(|s| s.timestamp.date_naive()).collect();
+
+    let figures = YearFigures {
+        total_hours: overview.total_minutes / 60,
+        total_scrobbles: overview.total_scrobbles,
+        top_artist: top_artist.map(|a| a.artist.clone()),
+        top_artist_plays: top_artist.map(|a| a.play_count).unwrap_or(0),
+        new_artists: discoveries.new_artists,
+        new_tracks: discoveries.new_tracks,
+        night_owl_score: patterns.night_owl_score,
+        early_bird_score: patterns.early_bird_score,
+        longest_session_minutes: patterns.longest_session_minutes,
+        ..Default::default()
     }
+    .with_active_days(&active_days);
 
-    // Longest session
-    if patterns.longest_session_minutes > 180 {
-        milestones.push(Milestone {
-            title: "Marathon Listener".to_string(),
-            description: "Your longest listening session".to_string(),
-            value: format!("{} minutes", patterns.longest_session_minutes),
-            icon: "🏃".to_string(),
-        });
-    }
-
-    milestones
+    award_milestones(&figures, rules, locale)
 }
 
 fn calculate_night_owl_score(hour_counts: &HashMap<u32, i64>) -> f64 {
@@ -554,6 +651,7 @@ fn is_leap_year(year: i32) -> bool {
 
 fn create_empty_report(year: i32) -> YearlyReport {
     YearlyReport {
+        schema_version: REPORT_SCHEMA_VERSION,
         year,
         overview: YearOverview {
             total_scrobbles: 0,
@@ -592,5 +690,20 @@ fn create_empty_report(year: i32) -> YearlyReport {
             exploration_score: 0.0,
         },
         milestones: Vec::new(),
+        monthly_breakdown: (1..=12)
+            .map(|month| MonthSummary {
+                month,
+                scrobbles: 0,
+                top_artist: None,
+                new_artists: 0,
+            })
+            .collect(),
+    }
+}
+
+impl VersionedReport for YearlyReport {
+    fn downgrade_to_v1(object: &mut Map<String, Value>) {
+        remove_keys(object, &["monthly_breakdown"]);
+        use_emoji_icons(object);
     }
 }
@@ -0,0 +1,290 @@
+use anyhow::Result;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::credits::DEFAULT_SEPARATORS;
+use crate::db::DbPool;
+use crate::models::{FieldError, ListenFilter};
+use crate::reports::heatmap::Normalization;
+
+/// Artwork sources, in the default lookup order for album covers
+pub const IMAGE_PROVIDERS: [&str; 2] = ["lastfm", "deezer"];
+
+pub const MAX_TOP_LIST_SIZE: i64 = 100;
+
+pub const MAX_DEDUP_WINDOW_SECONDS: i64 = 600;
+
+// Each field is stored under its own key, so an update only touches the
+// settings it names
+const KEYS: [&str; 7] = [
+    "timezone",
+    "heatmap_normalization",
+    "top_list_size",
+    "image_providers",
+    "listen_filter",
+    "artist_separators",
+    "dedup_window_seconds",
+];
+
+/// Per-instance preferences, used wherever a request doesn't say otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// IANA name for day boundaries in reports
+    pub timezone: String,
+    pub heatmap_normalization: Normalization,
+    /// Entries in the dashboard's top artists, albums and tracks
+    pub top_list_size: i64,
+    /// Order artwork sources are tried in for album covers
+    pub image_providers: Vec<String>,
+    pub listen_filter: ListenFilter,
+    /// Words splitting an artist field into primary and featured artists
+    pub artist_separators: Vec<String>,
+    /// Scrobbles of the same track this close together, whatever their
+    /// sources, are stored once; 0 only drops exact duplicates
+    pub dedup_window_seconds: i64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            heatmap_normalization: Normalization::default(),
+            top_list_size: 15,
+            image_providers: IMAGE_PROVIDERS.iter().map(|p| p.to_string()).collect(),
+            listen_filter: ListenFilter::default(),
+            artist_separators: DEFAULT_SEPARATORS.iter().map(|s| s.to_string()).collect(),
+            dedup_window_seconds: 0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if self.timezone.parse::<Tz>().is_err() {
+            errors.push(FieldError::new(
+                "timezone",
+                "must be an IANA name like Europe/Paris",
+            ));
+        }
+        if !(1..=MAX_TOP_LIST_SIZE).contains(&self.top_list_size) {
+            errors.push(FieldError::new(
+                "top_list_size",
+                format!("must be between 1 and {}", MAX_TOP_LIST_SIZE),
+            ));
+        }
+
+        let providers = &self.image_providers;
+        if providers.is_empty()
+            || providers
+                .iter()
+                .any(|p| !IMAGE_PROVIDERS.contains(&p.as_str()))
+        {
+            errors.push(FieldError::new(
+                "image_providers",
+                format!("must list some of {}", IMAGE_PROVIDERS.join(", ")),
+            ));
+        } else if (1..providers.len()).any(|i| providers[..i].contains(&providers[i])) {
+            errors.push(FieldError::new(
+                "image_providers",
+                "can't list a provider twice",
+            ));
+        }
+
+        if self.artist_separators.iter().any(|s| s.trim().is_empty()) {
+            errors.push(FieldError::new(
+                "artist_separators",
+                "can't contain blank separators",
+            ));
+        }
+
+        if !(0..=MAX_DEDUP_WINDOW_SECONDS).contains(&self.dedup_window_seconds) {
+            errors.push(FieldError::new(
+                "dedup_window_seconds",
+                format!("must be between 0 and {}", MAX_DEDUP_WINDOW_SECONDS),
+            ));
+        }
+
+        errors.extend(self.listen_filter.validate());
+        errors
+    }
+
+    /// The instance's timezone, validated when saved
+    pub fn timezone(&self) -> Tz {
+        self.timezone.parse().unwrap_or(chrono_tz::UTC)
+    }
+
+    /// The requested timezone, or the instance's when none is given. An
+    /// unknown name is an error rather than a silent fallback
+    pub fn timezone_or(&self, requested: Option<&str>) -> Result<Tz> {
+        match requested {
+            Some(name) => name
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Unknown timezone: {}", name)),
+            None => Ok(self.timezone()),
+        }
+    }
+}
+
+/// Stored settings over the defaults
+pub fn load(pool: &DbPool) -> Result<Settings> {
+    let mut merged = serde_json::to_value(Settings::default())?;
+    let mut stored = crate::db::get_all_settings(pool)?;
+    for key in KEYS {
+        if let Some(value) = stored.remove(key) {
+            merged[key] = value;
+        }
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+/// Apply the settings in `changes` and return the result, or every problem
+/// with them. Nothing is saved unless all of them are valid
+pub fn update(
+    pool: &DbPool,
+    changes: &serde_json::Map<String, serde_json::Value>,
+) -> Result<std::result::Result<Settings, Vec<FieldError>>> {
+    let mut errors = Vec::new();
+    let mut merged = serde_json::to_value(load(pool)?)?;
+
+    for (key, value) in changes {
+        match KEYS.iter().find(|k| *k == key) {
+            Some(key) => merged[*key] = value.clone(),
+            None => errors.push(FieldError::new(
+                "settings",
+                format!("unknown setting {}", key),
+            )),
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(Err(errors));
+    }
+
+    let settings: Settings = match serde_json::from_value(merged.clone()) {
+        Ok(settings) => settings,
+        Err(e) => return Ok(Err(vec![FieldError::new("settings", e.to_string())])),
+    };
+    let errors = settings.validate();
+    if !errors.is_empty() {
+        return Ok(Err(errors));
+    }
+
+    for key in changes.keys() {
+        crate::db::set_setting(pool, key, &merged[key.as_str()])?;
+    }
+    // Names split with the old separators are split again right away
+    if changes.contains_key("artist_separators") {
+        crate::db::clear_artist_credits(pool)?;
+        crate::credits::refresh(pool, &settings.artist_separators)?;
+    }
+    Ok(Ok(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+
+    fn changes(json: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        json.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_updates_only_touch_named_settings() {
+        let (pool, _temp_file) = setup_pool();
+        assert_eq!(load(&pool).unwrap(), Settings::default());
+        // Other rows of the settings table are left out
+        crate::db::set_setting(&pool, "unrelated", &serde_json::json!({"a": 1})).unwrap();
+        assert_eq!(load(&pool).unwrap(), Settings::default());
+
+        let updated = update(
+            &pool,
+            &changes(serde_json::json!({
+                "timezone": "Europe/Paris",
+                "image_providers": ["deezer", "lastfm"],
+            })),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(updated.timezone, "Europe/Paris");
+
+        update(&pool, &changes(serde_json::json!({"top_list_size": 25})))
+            .unwrap()
+            .unwrap();
+
+        let settings = load(&pool).unwrap();
+        assert_eq!(settings.timezone, "Europe/Paris");
+        assert_eq!(settings.image_providers, vec!["deezer", "lastfm"]);
+        assert_eq!(settings.top_list_size, 25);
+        assert_eq!(
+            settings.timezone_or(None).unwrap(),
+            chrono_tz::Europe::Paris
+        );
+        assert_eq!(
+            settings.timezone_or(Some("Asia/Tokyo")).unwrap(),
+            chrono_tz::Asia::Tokyo
+        );
+        assert!(settings.timezone_or(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_updates_are_not_saved() {
+        let (pool, _temp_file) = setup_pool();
+
+        let errors = update(
+            &pool,
+            &changes(serde_json::json!({
+                "timezone": "Mars/Olympus",
+                "top_list_size": 0,
+                "image_providers": ["deezer", "deezer"],
+            })),
+        )
+        .unwrap()
+        .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["timezone", "top_list_size", "image_providers"]);
+
+        let errors = update(&pool, &changes(serde_json::json!({"theme": "dark"})))
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(errors[0].message, "unknown setting theme");
+
+        assert_eq!(load(&pool).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn test_new_separators_split_names_right_away() {
+        let (pool, _temp_file) = setup_pool();
+        let scrobble = crate::models::Scrobble::new(
+            "Low & Mimi".to_string(),
+            "Words".to_string(),
+            chrono::Utc::now(),
+            "test".to_string(),
+        );
+        crate::db::insert_scrobble(&pool, &scrobble).unwrap();
+        crate::credits::refresh_from_settings(&pool).unwrap();
+
+        let primary = |pool: &DbPool| {
+            let conn = pool.get().unwrap();
+            crate::db::query_top_credited_artists(
+                &conn,
+                crate::credits::ArtistCredit::Primary,
+                10,
+                None,
+                None,
+            )
+            .unwrap()
+        };
+        assert_eq!(primary(&pool)[0].0, "Low & Mimi");
+
+        update(
+            &pool,
+            &changes(serde_json::json!({"artist_separators": ["&"]})),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(primary(&pool)[0].0, "Low");
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::DbPool;
+use crate::models::DailySummary;
+use crate::reports::calendar::local_midnight;
+use crate::reports::sessions::{DEFAULT_SESSION_GAP_MINUTES, detect_sessions};
+
+/// Finished days summarized again on every run, for scrobbles synced after
+/// their day ended
+pub const REFRESH_DAYS: i64 = 2;
+
+// Days summarized per query while catching up on history
+const CHUNK_DAYS: i64 = 31;
+
+// How often finished days are looked for
+const SUMMARY_TICK_SECS: u64 = 3600;
+
+#[derive(Default)]
+struct DayTally {
+    timestamps: Vec<DateTime<Utc>>,
+    artists: HashMap<String, i64>,
+    new_artists: Vec<String>,
+}
+
+/// Summaries of every day in `from..to`, idle ones included, computed from
+/// the scrobbles with days taken in `timezone`
+pub fn summarize_days(
+    pool: &DbPool,
+    from: NaiveDate,
+    to: NaiveDate,
+    timezone: Tz,
+) -> Result<Vec<DailySummary>> {
+    let rows = crate::db::get_scrobbles_with_discoveries(
+        pool,
+        local_midnight(from, timezone)?,
+        local_midnight(to, timezone)?,
+    )?;
+
+    let mut per_day: HashMap<NaiveDate, DayTally> = HashMap::new();
+    for (timestamp, artist, is_first) in rows {
+        let tally = per_day
+            .entry(timestamp.with_timezone(&timezone).date_naive())
+            .or_default();
+        tally.timestamps.push(timestamp);
+        *tally.artists.entry(artist.clone()).or_insert(0) += 1;
+        if is_first {
+            tally.new_artists.push(artist);
+        }
+    }
+
+    Ok(from
+        .iter_days()
+        .take_while(|date| *date < to)
+        .map(|date| match per_day.remove(&date) {
+            Some(tally) => summarize(date, tally),
+            None => DailySummary::empty(date),
+        })
+        .collect())
+}
+
+fn summarize(date: NaiveDate, tally: DayTally) -> DailySummary {
+    // Most played, alphabetically first on ties
+    let (top_artist, top_artist_count) = tally
+        .artists
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(artist, count)| (Some(artist), count))
+        .unwrap_or((None, 0));
+
+    DailySummary {
+        date,
+        scrobbles: tally.timestamps.len() as i64,
+        top_artist,
+        top_artist_count,
+        new_artists: tally.new_artists,
+        sessions: detect_sessions(tally.timestamps, |t| *t, DEFAULT_SESSION_GAP_MINUTES).len()
+            as i64,
+    }
+}
+
+/// Summaries of every day in `from..to`: the stored ones where the rollup
+/// has them in `timezone`, the others (today, or days not rolled up yet)
+/// computed now
+pub fn daily_summaries(
+    pool: &DbPool,
+    from: NaiveDate,
+    to: NaiveDate,
+    timezone: Tz,
+) -> Result<Vec<DailySummary>> {
+    let mut days: BTreeMap<NaiveDate, DailySummary> =
+        crate::db::get_daily_summaries(pool, from, to, timezone.name())?
+            .into_iter()
+            .map(|summary| (summary.date, summary))
+            .collect();
+
+    // One query from the first missing day to the last
+    let mut missing = from
+        .iter_days()
+        .take_while(|date| *date < to)
+        .filter(|date| !days.contains_key(date));
+    if let Some(first) = missing.next() {
+        let last = missing.last().unwrap_or(first);
+        for summary in summarize_days(pool, first, last + Duration::days(1), timezone)? {
+            days.entry(summary.date).or_insert(summary);
+        }
+    }
+
+    Ok(days.into_values().collect())
+}
+
+/// Store the summaries of every finished day since the last stored one (the
+/// last few again), or since the first scrobble, in the instance's timezone.
+/// Returns how many days were summarized
+pub fn record_days(pool: &DbPool, now: DateTime<Utc>) -> Result<usize> {
+    let timezone = crate::settings::load(pool)?.timezone();
+    let from = match crate::db::get_last_daily_summary(pool)? {
+        Some((last, stored_in)) if stored_in == timezone.name() => {
+            last - Duration::days(REFRESH_DAYS - 1)
+        }
+        // Days of another timezone are all taken again
+        _ => match first_day(pool, timezone)? {
+            Some(first) => first,
+            None => return Ok(0),
+        },
+    };
+    record_from(pool, from, now, timezone)
+}
+
+/// Summarize every finished day again from the first scrobble, e.g. after
+/// importing older history changed which artists were new when
+pub fn rebuild(pool: &DbPool, now: DateTime<Utc>) -> Result<usize> {
+    let timezone = crate::settings::load(pool)?.timezone();
+    match first_day(pool, timezone)? {
+        Some(first) => record_from(pool, first, now, timezone),
+        None => Ok(0),
+    }
+}
+
+fn first_day(pool: &DbPool, timezone: Tz) -> Result<Option<NaiveDate>> {
+    Ok(crate::db::get_first_listen_timestamp(pool)?
+        .map(|first| first.with_timezone(&timezone).date_naive()))
+}
+
+fn record_from(pool: &DbPool, from: NaiveDate, now: DateTime<Utc>, timezone: Tz) -> Result<usize> {
+    let today = now.with_timezone(&timezone).date_naive();
+    let mut start = from;
+    let mut recorded = 0;
+    while start < today {
+        let end = (start + Duration::days(CHUNK_DAYS)).min(today);
+        let summaries = summarize_days(pool, start, end, timezone)?;
+        crate::db::save_daily_summaries(pool, &summaries, timezone.name(), now)?;
+        recorded += summaries.len();
+        start = end;
+    }
+    Ok(recorded)
+}
+
+/// The last `days` finished days against the `days` before them, from the
+/// daily summaries
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub from: NaiveDate,
+    /// Last day included: yesterday
+    pub to: NaiveDate,
+    pub scrobbles: i64,
+    pub previous_scrobbles: i64,
+    /// None when there was nothing to compare with
+    pub change_percent: Option<f64>,
+    pub active_days: i64,
+    pub sessions: i64,
+    pub new_artists: Vec<String>,
+    /// The day with the most scrobbles, the latest on ties
+    pub busiest_day: Option<DailySummary>,
+    pub days: Vec<DailySummary>,
+}
+
+pub fn digest(pool: &DbPool, now: DateTime<Utc>, days: i64, timezone: Tz) -> Result<Digest> {
+    let today = now.with_timezone(&timezone).date_naive();
+    let from = today - Duration::days(days);
+    let mut summaries = daily_summaries(pool, from - Duration::days(days), today, timezone)?;
+    let current = summaries.split_off(days as usize);
+
+    let scrobbles = current.iter().map(|day| day.scrobbles).sum();
+    let previous_scrobbles = summaries.iter().map(|day| day.scrobbles).sum();
+    Ok(Digest {
+        from,
+        to: today - Duration::days(1),
+        scrobbles,
+        previous_scrobbles,
+        change_percent: (previous_scrobbles > 0)
+            .then(|| (scrobbles - previous_scrobbles) as f64 / previous_scrobbles as f64 * 100.0),
+        active_days: current.iter().filter(|day| day.scrobbles > 0).count() as i64,
+        sessions: current.iter().map(|day| day.sessions).sum(),
+        new_artists: current
+            .iter()
+            .flat_map(|day| day.new_artists.iter().cloned())
+            .collect(),
+        busiest_day: current
+            .iter()
+            .filter(|day| day.scrobbles > 0)
+            .max_by_key(|day| day.scrobbles)
+            .cloned(),
+        days: current,
+    })
+}
+
+/// Rolls up each finished day in the background
+#[derive(Clone)]
+pub struct SummaryRecorder {
+    pool: DbPool,
+}
+
+impl SummaryRecorder {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn start(&self) {
+        let recorder = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs(SUMMARY_TICK_SECS);
+            loop {
+                match record_days(&recorder.pool, Utc::now()) {
+                    Ok(0) => {}
+                    Ok(days) => tracing::debug!("Summarized {} days", days),
+                    Err(e) => tracing::error!("Failed to record daily summaries: {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scrobble;
+    use crate::test_utils::setup_pool;
+
+    fn play(pool: &DbPool, artist: &str, at: &str) {
+        let scrobble = Scrobble::new(
+            artist.to_string(),
+            "Track".to_string(),
+            at.parse().unwrap(),
+            "lastfm".to_string(),
+        );
+        crate::db::insert_scrobble(pool, &scrobble).unwrap();
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn test_summarize_days() {
+        let (pool, _temp_file) = setup_pool();
+        play(&pool, "Low", "2024-05-01T20:00:00Z");
+        // Two sessions, one new artist
+        play(&pool, "Low", "2024-05-03T08:00:00Z");
+        play(&pool, "Slint", "2024-05-03T20:00:00Z");
+        play(&pool, "Slint", "2024-05-03T20:05:00Z");
+
+        let days = summarize_days(
+            &pool,
+            date("2024-05-02"),
+            date("2024-05-04"),
+            chrono_tz::UTC,
+        )
+        .unwrap();
+        assert_eq!(days[0], DailySummary::empty(date("2024-05-02")));
+        assert_eq!(
+            days[1],
+            DailySummary {
+                date: date("2024-05-03"),
+                scrobbles: 3,
+                top_artist: Some("Slint".to_string()),
+                top_artist_count: 2,
+                new_artists: vec!["Slint".to_string()],
+                sessions: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_records_finished_days() {
+        let (pool, _temp_file) = setup_pool();
+        assert_eq!(
+            record_days(&pool, "2024-05-10T12:00:00Z".parse().unwrap()).unwrap(),
+            0
+        );
+
+        play(&pool, "Low", "2024-05-01T20:00:00Z");
+        play(&pool, "Low", "2024-05-10T09:00:00Z");
+        // Up to yesterday: today isn't over
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        assert_eq!(record_days(&pool, now).unwrap(), 9);
+        let stored =
+            crate::db::get_daily_summaries(&pool, date("2024-05-01"), date("2024-05-11"), "UTC")
+                .unwrap();
+        assert_eq!(stored.len(), 9);
+        assert_eq!(stored[0].scrobbles, 1);
+
+        // A late sync for yesterday dropped the summaries from the 8th, and
+        // the next run takes them again from the last ones kept
+        play(&pool, "Slint", "2024-05-09T23:00:00Z");
+        assert_eq!(
+            crate::db::get_last_daily_summary(&pool).unwrap(),
+            Some((date("2024-05-07"), "UTC".to_string()))
+        );
+        let tomorrow: DateTime<Utc> = "2024-05-11T01:00:00Z".parse().unwrap();
+        assert_eq!(
+            record_days(&pool, tomorrow).unwrap(),
+            REFRESH_DAYS as usize + 3
+        );
+        let may_9 =
+            crate::db::get_daily_summaries(&pool, date("2024-05-09"), date("2024-05-10"), "UTC")
+                .unwrap();
+        assert_eq!(may_9[0].new_artists, vec!["Slint".to_string()]);
+
+        // Other timezones are computed on the fly
+        assert!(
+            crate::db::get_daily_summaries(
+                &pool,
+                date("2024-05-01"),
+                date("2024-05-11"),
+                "Europe/Paris"
+            )
+            .unwrap()
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_archiving_keeps_summaries() {
+        let (pool, _temp_file) = setup_pool();
+        play(&pool, "Low", "2024-05-01T20:00:00Z");
+        play(&pool, "Slint", "2024-05-03T20:00:00Z");
+        play(&pool, "Low", "2024-05-06T20:00:00Z");
+        let now: DateTime<Utc> = "2024-05-10T12:00:00Z".parse().unwrap();
+        record_days(&pool, now).unwrap();
+
+        crate::db::archive_scrobbles(&pool, "2024-05-05T00:00:00Z".parse().unwrap(), false)
+            .unwrap();
+        assert_eq!(
+            crate::db::get_last_daily_summary(&pool).unwrap(),
+            Some((date("2024-05-09"), "UTC".to_string()))
+        );
+
+        record_days(&pool, now).unwrap();
+        let stored =
+            crate::db::get_daily_summaries(&pool, date("2024-05-01"), date("2024-05-10"), "UTC")
+                .unwrap();
+        assert_eq!(stored.len(), 9);
+        assert_eq!(stored[0].scrobbles, 1);
+        assert_eq!(stored[2].new_artists, vec!["Slint".to_string()]);
+
+        // Rebuilding, e.g. for another timezone, takes archived days too
+        rebuild(&pool, now).unwrap();
+        let stored =
+            crate::db::get_daily_summaries(&pool, date("2024-05-01"), date("2024-05-10"), "UTC")
+                .unwrap();
+        assert_eq!(stored.len(), 9);
+        assert_eq!(stored[0].scrobbles, 1);
+        assert_eq!(stored[0].new_artists, vec!["Low".to_string()]);
+    }
+
+    #[test]
+    fn test_digest() {
+        let (pool, _temp_file) = setup_pool();
+        play(&pool, "Low", "2024-05-02T20:00:00Z");
+        play(&pool, "Low", "2024-05-09T20:00:00Z");
+        play(&pool, "Slint", "2024-05-09T20:05:00Z");
+        play(&pool, "Codeine", "2024-05-11T20:00:00Z");
+        // Today is left out
+        play(&pool, "Duster", "2024-05-14T09:00:00Z");
+        let now: DateTime<Utc> = "2024-05-14T12:00:00Z".parse().unwrap();
+        record_days(&pool, now - Duration::days(2)).unwrap();
+
+        let digest = digest(&pool, now, 7, chrono_tz::UTC).unwrap();
+        assert_eq!(
+            (digest.from, digest.to),
+            (date("2024-05-07"), date("2024-05-13"))
+        );
+        assert_eq!(digest.days.len(), 7);
+        assert_eq!((digest.scrobbles, digest.previous_scrobbles), (3, 1));
+        assert_eq!(digest.change_percent, Some(200.0));
+        assert_eq!((digest.active_days, digest.sessions), (2, 2));
+        assert_eq!(digest.new_artists, vec!["Slint", "Codeine"]);
+        assert_eq!(digest.busiest_day.unwrap().date, date("2024-05-09"));
+    }
+}
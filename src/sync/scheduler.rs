@@ -1,20 +1,86 @@
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
-use crate::db::DbPool;
+use crate::achievements::AchievementNotifier;
+use crate::db::{DbPool, SyncFailure};
 use crate::importers::{LastFmImporter, ListenBrainzImporter};
+use crate::models::SyncConfig;
+use crate::normalizer::Normalizer;
 
 // Configurable constants for sync behavior
-const SYNC_CHECK_INTERVAL_SECS: u64 = 60; // Check for due syncs every minute
+const SYNC_CHECK_INTERVAL_SECS: u64 = 60; // Check for due syncs every minute by default
+const MAX_SYNC_JITTER_SECS: i64 = 300; // Spread configs sharing an interval over 5 minutes
 const DEFAULT_FIRST_SYNC_HOURS: i64 = 24; // On first sync, fetch last 24 hours
+pub const DEFAULT_RESCAN_DAYS: i64 = 30; // Manual rescans go back 30 days unless told otherwise
 
 #[derive(Clone)]
 pub struct SyncScheduler {
     pool: DbPool,
     running: Arc<RwLock<bool>>,
+    normalizer: Normalizer,
+    tick_interval: Duration,
+    activity: Arc<RwLock<SyncActivity>>,
+    achievements: Option<AchievementNotifier>,
+}
+
+/// What the scheduler loop is doing right now
+#[derive(Debug, Clone, Default)]
+struct SyncActivity {
+    last_tick: Option<DateTime<Utc>>,
+    syncing: Vec<ActiveSync>,
+}
+
+/// A sync (scheduled, manual or rescan) in progress
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveSync {
+    pub config_id: Option<i64>,
+    pub source: String,
+    pub username: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Scheduler health, for the UI's sync indicator
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub running: bool,
+    pub tick_interval_secs: u64,
+    pub last_tick: Option<DateTime<Utc>>,
+    /// Enabled configs whose next run has come
+    pub configs_due: Vec<i64>,
+    pub syncing: Vec<ActiveSync>,
+    /// Enabled configs whose latest sync failed
+    pub failing: Vec<SyncFailure>,
+}
+
+/// Fixed per-config offset, in seconds, within its interval. Derived from the
+/// id so the schedule is stable across restarts but differs between configs
+fn sync_offset_secs(config_id: i64, interval_secs: i64) -> i64 {
+    let spread = interval_secs.min(MAX_SYNC_JITTER_SECS);
+    if spread <= 0 {
+        return 0;
+    }
+    // Multiplicative hash so neighbouring ids land far apart
+    ((config_id as u64).wrapping_mul(2_654_435_761) % spread as u64) as i64
+}
+
+/// When `config` is next due: the first boundary of its interval (shifted by
+/// its offset) at least half an interval after the last sync. Configs that
+/// never synced are due at `now`
+pub fn next_sync_at(config: &SyncConfig, now: DateTime<Utc>) -> DateTime<Utc> {
+    let Some(last_sync) = config.last_sync_timestamp else {
+        return now;
+    };
+
+    let interval = config.sync_interval_minutes.max(1) as i64 * 60;
+    let offset = sync_offset_secs(config.id.unwrap_or_default(), interval);
+    let earliest = last_sync.timestamp() + interval / 2;
+    let slot = (earliest - offset).div_euclid(interval) + 1;
+
+    DateTime::from_timestamp(slot * interval + offset, 0).unwrap_or(now)
 }
 
 impl SyncScheduler {
@@ -22,9 +88,31 @@ impl SyncScheduler {
         Self {
             pool,
             running: Arc::new(RwLock::new(false)),
+            normalizer: Normalizer::default(),
+            tick_interval: Duration::from_secs(SYNC_CHECK_INTERVAL_SECS),
+            activity: Arc::new(RwLock::new(SyncActivity::default())),
+            achievements: None,
         }
     }
 
+    /// How often to look for due configs
+    pub fn with_tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.tick_interval = tick_interval.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Normalizer applied to every synced scrobble
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Record and announce achievements after syncs that brought new scrobbles
+    pub fn with_achievements(mut self, achievements: AchievementNotifier) -> Self {
+        self.achievements = Some(achievements);
+        self
+    }
+
     /// Start the sync scheduler in the background
     pub async fn start(&self) {
         let mut running = self.running.write().await;
@@ -52,14 +140,34 @@ impl SyncScheduler {
     }
 
     /// Check if the scheduler is running
-    #[allow(dead_code)]
     pub async fn is_running(&self) -> bool {
         *self.running.read().await
     }
 
+    /// Current scheduler health: last tick, due and running syncs, failures
+    pub async fn status(&self) -> Result<SyncStatus> {
+        let now = Utc::now();
+        let configs_due = crate::db::get_enabled_sync_configs(&self.pool)?
+            .iter()
+            .filter(|config| next_sync_at(config, now) <= now)
+            .filter_map(|config| config.id)
+            .collect();
+        let failing = crate::db::get_sync_failures(&self.pool, now)?;
+        let activity = self.activity.read().await.clone();
+
+        Ok(SyncStatus {
+            running: self.is_running().await,
+            tick_interval_secs: self.tick_interval.as_secs(),
+            last_tick: activity.last_tick,
+            configs_due,
+            syncing: activity.syncing,
+            failing,
+        })
+    }
+
     /// Main sync loop
     async fn run_loop(&self) {
-        let check_interval = Duration::from_secs(SYNC_CHECK_INTERVAL_SECS);
+        let check_interval = self.tick_interval;
 
         loop {
             // Check if we should stop
@@ -67,28 +175,42 @@ impl SyncScheduler {
                 break;
             }
 
+            self.activity.write().await.last_tick = Some(Utc::now());
+
             // Process all enabled sync configs
             if let Err(e) = self.process_sync_configs().await {
                 tracing::error!("Error processing sync configs: {}", e);
             }
 
+            // Split the artist names stored since the last tick, whatever stored them
+            self.refresh_credits().await;
+
             // Wait before next check
             tokio::time::sleep(check_interval).await;
         }
     }
 
+    /// Credit new artist names for credited top-artist queries; failures are
+    /// only logged
+    async fn refresh_credits(&self) {
+        let pool = self.pool.clone();
+        match tokio::task::spawn_blocking(move || crate::credits::refresh_from_settings(&pool))
+            .await
+        {
+            Ok(Ok(0)) => {}
+            Ok(Ok(count)) => tracing::info!("Split {} new artist credits", count),
+            Ok(Err(e)) => tracing::error!("Failed to split artist credits: {}", e),
+            Err(e) => tracing::error!("Artist credit refresh panicked: {}", e),
+        }
+    }
+
     /// Process all enabled sync configurations
     async fn process_sync_configs(&self) -> Result<()> {
         let configs = crate::db::get_enabled_sync_configs(&self.pool)?;
 
         for config in configs {
-            let should_sync = if let Some(last_sync) = config.last_sync_timestamp {
-                let elapsed_minutes = (Utc::now() - last_sync).num_minutes();
-                elapsed_minutes >= config.sync_interval_minutes as i64
-            } else {
-                // Never synced before, sync now
-                true
-            };
+            let now = Utc::now();
+            let should_sync = next_sync_at(&config, now) <= now;
 
             if should_sync && let Some(config_id) = config.id {
                 tracing::info!(
@@ -123,6 +245,18 @@ impl SyncScheduler {
                             config.username,
                             e
                         );
+                        if let Err(e) = crate::db::record_sync_failure(
+                            &self.pool,
+                            config_id,
+                            &e.to_string(),
+                            Utc::now(),
+                        ) {
+                            tracing::error!(
+                                "Failed to record sync failure for config {}: {}",
+                                config_id,
+                                e
+                            );
+                        }
                     }
                 }
             }
@@ -132,15 +266,108 @@ impl SyncScheduler {
     }
 
     /// Sync a specific configuration
-    async fn sync_config(&self, config: &crate::models::SyncConfig) -> Result<usize> {
+    #[tracing::instrument(
+        name = "sync",
+        skip(self, config),
+        fields(config_id = ?config.id, source = %config.source, username = %config.username)
+    )]
+    async fn sync_config(&self, config: &SyncConfig) -> Result<usize> {
         let since = config
             .last_sync_timestamp
             .unwrap_or_else(|| Utc::now() - chrono::Duration::hours(DEFAULT_FIRST_SYNC_HOURS));
 
+        let count = self.fetch_since(config, since).await?;
+
+        if count > 0
+            && let Some(achievements) = &self.achievements
+        {
+            achievements.check().await;
+        }
+
+        // New recommendation playlists come along with the listens; failing to
+        // get them doesn't fail the sync
+        if config.source == "listenbrainz"
+            && config.token.is_some()
+            && let Err(e) = self.import_playlists(config).await
+        {
+            tracing::warn!(
+                "Failed to pull ListenBrainz playlists for {}: {}",
+                config.username,
+                e
+            );
+        }
+
+        Ok(count)
+    }
+
+    async fn import_playlists(&self, config: &SyncConfig) -> Result<usize> {
+        let importer = ListenBrainzImporter::new(config.username.clone(), config.token.clone())
+            .with_normalizer(self.normalizer.clone());
+        let added = importer.import_playlists(&self.pool).await?;
+        if added > 0 {
+            tracing::info!(
+                "Stored {} new ListenBrainz playlists for {}",
+                added,
+                config.username
+            );
+        }
+        Ok(added)
+    }
+
+    /// Pull a ListenBrainz configuration's generated playlists now
+    pub async fn pull_playlists(&self, config_id: i64) -> Result<usize> {
+        let config = crate::db::get_sync_config(&self.pool, config_id)?
+            .ok_or_else(|| anyhow::anyhow!("Sync config not found"))?;
+
+        if config.source != "listenbrainz" {
+            return Err(anyhow::anyhow!("Only ListenBrainz generates playlists"));
+        }
+        self.import_playlists(&config).await
+    }
+
+    /// Import a Last.fm configuration's weekly charts from before its first
+    /// scrobble, returning the number of chart entries stored
+    pub async fn pull_legacy_charts(&self, config_id: i64) -> Result<usize> {
+        let config = crate::db::get_sync_config(&self.pool, config_id)?
+            .ok_or_else(|| anyhow::anyhow!("Sync config not found"))?;
+
+        if config.source != "lastfm" {
+            return Err(anyhow::anyhow!("Only Last.fm keeps weekly charts"));
+        }
+        let api_key = config
+            .api_key
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("API key required for Last.fm"))?;
+        LastFmImporter::new(api_key, config.username.clone())
+            .import_legacy_charts(&self.pool)
+            .await
+    }
+
+    async fn fetch_since(&self, config: &SyncConfig, since: DateTime<Utc>) -> Result<usize> {
+        let started_at = Utc::now();
+        self.activity.write().await.syncing.push(ActiveSync {
+            config_id: config.id,
+            source: config.source.clone(),
+            username: config.username.clone(),
+            started_at,
+        });
+
+        let result = self.import_since(config, since).await;
+
+        self.activity
+            .write()
+            .await
+            .syncing
+            .retain(|active| !(active.config_id == config.id && active.started_at == started_at));
+        result
+    }
+
+    async fn import_since(&self, config: &SyncConfig, since: DateTime<Utc>) -> Result<usize> {
         match config.source.as_str() {
             "lastfm" => {
                 if let Some(api_key) = &config.api_key {
-                    let importer = LastFmImporter::new(api_key.clone(), config.username.clone());
+                    let importer = LastFmImporter::new(api_key.clone(), config.username.clone())
+                        .with_normalizer(self.normalizer.clone());
                     importer.import_since(&self.pool, since).await
                 } else {
                     Err(anyhow::anyhow!("API key required for Last.fm sync"))
@@ -148,7 +375,8 @@ impl SyncScheduler {
             }
             "listenbrainz" => {
                 let importer =
-                    ListenBrainzImporter::new(config.username.clone(), config.token.clone());
+                    ListenBrainzImporter::new(config.username.clone(), config.token.clone())
+                        .with_normalizer(self.normalizer.clone());
                 importer.import_since(&self.pool, since).await
             }
             _ => Err(anyhow::anyhow!("Unknown source: {}", config.source)),
@@ -171,4 +399,96 @@ impl SyncScheduler {
 
         Ok(count)
     }
+
+    /// Re-fetch everything since `since` for a configuration, regardless of
+    /// when it last synced, to pick up listens the source added late. Already
+    /// stored scrobbles are skipped and the incremental sync position is kept
+    pub async fn rescan(&self, config_id: i64, since: DateTime<Utc>) -> Result<usize> {
+        let config = crate::db::get_sync_config(&self.pool, config_id)?
+            .ok_or_else(|| anyhow::anyhow!("Sync config not found"))?;
+
+        tracing::info!(
+            "Rescanning {} user {} since {}",
+            config.source,
+            config.username,
+            since
+        );
+        self.fetch_since(&config, since).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::setup_pool;
+
+    fn config(id: i64, interval_minutes: i32, last_sync: Option<&str>) -> SyncConfig {
+        let mut config = SyncConfig::new(
+            "listenbrainz".to_string(),
+            "user".to_string(),
+            interval_minutes,
+        );
+        config.id = Some(id);
+        config.last_sync_timestamp = last_sync.map(|ts| ts.parse().unwrap());
+        config
+    }
+
+    #[test]
+    fn test_never_synced_is_due_now() {
+        let now = Utc::now();
+        assert_eq!(next_sync_at(&config(1, 60, None), now), now);
+    }
+
+    #[test]
+    fn test_next_run_aligns_to_interval_boundary() {
+        let now = Utc::now();
+        for id in 1..50 {
+            let config = config(id, 60, Some("2024-05-01T10:20:00Z"));
+            let next = next_sync_at(&config, now);
+            let offset = sync_offset_secs(id, 3600);
+
+            assert!(offset < MAX_SYNC_JITTER_SECS);
+            assert_eq!((next.timestamp() - offset) % 3600, 0);
+            // The next boundary, never one that would fire right after the last sync
+            assert!(next.timestamp() - config.last_sync_timestamp.unwrap().timestamp() >= 1800);
+            assert!(next <= "2024-05-01T11:30:00Z".parse::<DateTime<Utc>>().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_configs_sharing_an_interval_are_spread_out() {
+        let now = Utc::now();
+        let runs: std::collections::HashSet<_> = (1..20)
+            .map(|id| next_sync_at(&config(id, 60, Some("2024-05-01T10:20:00Z")), now))
+            .collect();
+        assert!(runs.len() > 10);
+    }
+
+    #[test]
+    fn test_short_intervals_keep_offset_inside_interval() {
+        for id in 1..50 {
+            assert!(sync_offset_secs(id, 60) < 60);
+        }
+        assert_eq!(sync_offset_secs(7, 0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_due_and_failing_configs() {
+        let (pool, _temp_file) = setup_pool();
+
+        let new_config = SyncConfig::new("listenbrainz".to_string(), "new".to_string(), 60);
+        let new_id = crate::db::insert_sync_config(&pool, &new_config).unwrap();
+        let synced = SyncConfig::new("listenbrainz".to_string(), "synced".to_string(), 60);
+        let synced_id = crate::db::insert_sync_config(&pool, &synced).unwrap();
+        crate::db::update_sync_timestamp(&pool, synced_id, Utc::now()).unwrap();
+        crate::db::record_sync_failure(&pool, synced_id, "503", Utc::now()).unwrap();
+
+        let status = SyncScheduler::new(pool).status().await.unwrap();
+        assert!(!status.running);
+        assert_eq!(status.last_tick, None);
+        assert_eq!(status.configs_due, vec![new_id]);
+        assert!(status.syncing.is_empty());
+        assert_eq!(status.failing.len(), 1);
+        assert_eq!(status.failing[0].last_error.as_deref(), Some("503"));
+    }
 }
@@ -1,6 +1,16 @@
 // Test utilities for creating mock scrobble data
-use crate::models::Scrobble;
+use crate::db::DbPool;
+use crate::models::{MediaType, Scrobble};
 use chrono::{DateTime, Duration, TimeZone, Utc};
+use tempfile::NamedTempFile;
+
+/// A fresh database with the full schema, deleted when the file is dropped
+pub fn setup_pool() -> (DbPool, NamedTempFile) {
+    let temp_file = NamedTempFile::new().unwrap();
+    let pool = crate::db::create_pool(temp_file.path().to_str().unwrap()).unwrap();
+    crate::db::init_database(&pool).unwrap();
+    (pool, temp_file)
+}
 
 /// Create a test scrobble with specified parameters
 pub fn test_scrobble(
@@ -17,6 +27,12 @@ pub fn test_scrobble(
         timestamp,
         source: source.to_string(),
         source_id: None,
+        ms_played: None,
+        skipped: None,
+        raw_metadata: None,
+        media_type: MediaType::Music,
+        source_metadata: None,
+        album_artist: None,
     }
 }
 